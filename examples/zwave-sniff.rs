@@ -0,0 +1,161 @@
+//! # zwave-sniff
+//!
+//! Prints a live trace of the network traffic. The controller is switched
+//! into the promiscuous mode, so the frames between other nodes are shown
+//! as well, when the controller supports it.
+//!
+//! ```bash
+//! cargo run --example zwave-sniff -- /dev/ttyACM0
+//! ```
+//!
+//! Set the `NO_COLOR` environment variable to print without colors.
+
+use rzw::defs::{
+    IncomingFrame, SerialMessage, SerialMessageFunction, SerialMessageHeader, SerialMessageType,
+    TransmitReport, TransmitStatus,
+};
+use rzw::driver::FrameDriver;
+use rzw::error::{ErrorKind, Result};
+
+use std::time::Instant;
+
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+const CYAN: &str = "36";
+const DIM: &str = "2";
+
+fn main() {
+    let device = match std::env::args().nth(1) {
+        Some(device) => device,
+        None => {
+            eprintln!("Usage: zwave-sniff <device>");
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(&device) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Open the controller and print all messages
+fn run(device: &str) -> Result<()> {
+    let mut driver = FrameDriver::open(device)?;
+    driver.set_promiscuous_mode(true)?;
+
+    let style = Style {
+        color: std::env::var_os("NO_COLOR").is_none(),
+    };
+    let start = Instant::now();
+
+    loop {
+        let line = match driver.receive_msg(Some(10)) {
+            Ok(msg) => describe(&style, &msg),
+            Err(ref e) if e.kind() == ErrorKind::Io(std::io::ErrorKind::TimedOut) => continue,
+            Err(e) => style.paint(RED, &e.to_string()),
+        };
+
+        println!("[{:10.3}] {}", start.elapsed().as_secs_f64(), line);
+    }
+}
+
+/// Colors the output, when enabled
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    /// Wrap the text into the ANSI color code
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Return a human readable line for the message
+fn describe(style: &Style, msg: &SerialMessage) -> String {
+    if msg.header != SerialMessageHeader::SOF {
+        return style.paint(DIM, &format!("{:?}", msg.header));
+    }
+
+    let decoded = match (msg.typ, msg.func) {
+        (SerialMessageType::Request, SerialMessageFunction::ApplicationCommandHandler) => {
+            IncomingFrame::parse(&msg.data)
+                .ok()
+                .map(|frame| style.paint(GREEN, &command(&frame, "ctrl".to_string())))
+        }
+        (
+            SerialMessageType::Request,
+            SerialMessageFunction::PromiscuousApplicationCommandHandler,
+        ) => IncomingFrame::parse_promiscuous(&msg.data)
+            .ok()
+            .map(|(frame, destination)| {
+                style.paint(CYAN, &command(&frame, destination.to_string()))
+            }),
+        (SerialMessageType::Request, SerialMessageFunction::SendData) => {
+            TransmitReport::parse(&msg.data).ok().map(|report| {
+                let code = if report.status == TransmitStatus::CompleteOk {
+                    YELLOW
+                } else {
+                    RED
+                };
+                style.paint(
+                    code,
+                    &format!(
+                        "transmit {} {:?} {:?} via {:?}",
+                        report.message_id, report.status, report.time, report.repeaters
+                    ),
+                )
+            })
+        }
+        _ => None,
+    };
+
+    // show the raw message, when it couldn't be decoded
+    decoded.unwrap_or_else(|| {
+        style.paint(
+            DIM,
+            &format!(
+                "{:?} {:?} {}",
+                msg.typ,
+                msg.func,
+                SerialMessage::to_hex(&msg.data)
+            ),
+        )
+    })
+}
+
+/// Return the line of an application command from the source to the destination
+fn command(frame: &IncomingFrame, destination: String) -> String {
+    let mut flags = vec![];
+    if frame.status.broadcast {
+        flags.push("broadcast");
+    }
+    if frame.status.multicast {
+        flags.push("multicast");
+    }
+    if frame.status.explore {
+        flags.push("explore");
+    }
+
+    // the unknown command classes are shown with their byte
+    let command_class = match frame.command_class {
+        Ok(command_class) => format!("{:?}", command_class),
+        Err(id) => format!("{:#04X}", id),
+    };
+
+    format!(
+        "{:>3} -> {:<4} {} {:#04X} {} {}",
+        frame.source,
+        destination,
+        command_class,
+        frame.command,
+        SerialMessage::to_hex(&frame.payload),
+        flags.join(",")
+    )
+}
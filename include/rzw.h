@@ -0,0 +1,52 @@
+/*
+ * C interface of rzw, built with the `ffi` feature as a shared library:
+ *
+ *     cargo build --release --features ffi
+ *
+ * The functions return 0 on success and -1 on failure, the reason of the
+ * last failure on the calling thread is returned by rzw_last_error().
+ * A controller handle must only be used by one thread at a time.
+ */
+
+#ifndef RZW_H
+#define RZW_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RzwController RzwController;
+
+/* Called for each command which was received from a node */
+typedef void (*RzwEventCallback)(void *user_data, uint8_t node, uint8_t cmd_class,
+                                 uint8_t cmd, const uint8_t *payload, size_t len);
+
+/* The reason of the last failure, or NULL. Valid until the next call. */
+const char *rzw_last_error(void);
+
+/* Open the controller at the serial port, NULL on failure */
+RzwController *rzw_open(const char *path);
+void rzw_close(RzwController *controller);
+
+/* Write up to len node ids, returns the amount of nodes or -1 */
+int rzw_nodes(RzwController *controller, uint8_t *nodes, size_t len);
+
+int rzw_basic_set(RzwController *controller, uint8_t node, uint8_t value);
+int rzw_basic_get(RzwController *controller, uint8_t node, uint8_t *value);
+int rzw_switch_binary_set(RzwController *controller, uint8_t node, uint8_t value);
+int rzw_switch_binary_get(RzwController *controller, uint8_t node, uint8_t *value);
+int rzw_switch_multilevel_set(RzwController *controller, uint8_t node, uint8_t value);
+int rzw_switch_multilevel_get(RzwController *controller, uint8_t node, uint8_t *value);
+
+/* The callback is called on a background thread */
+int rzw_set_event_callback(RzwController *controller, RzwEventCallback callback,
+                           void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RZW_H */
@@ -0,0 +1,159 @@
+//! Driver ownership
+//!
+//! The driver is owned by a single thread, which runs the jobs it receives
+//! one after another. The controller and the nodes only hold a handle to
+//! send jobs to this thread, so they are cheap to clone and never wait for
+//! a lock, which is held by a callback.
+
+use crate::error::Error;
+
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+use std::thread;
+
+/// A job, which runs on the thread of the driver
+type Job<D> = Box<dyn FnOnce(&mut D) + Send>;
+
+/// Handle to the thread, which owns the driver.
+pub struct DriverHandle<D> {
+    jobs: Arc<Sender<Job<D>>>,
+}
+
+/// Handle to the thread of the driver, which doesn't keep it running.
+/// Background threads hold it, so they stop with the driver.
+pub struct WeakDriverHandle<D> {
+    jobs: Weak<Sender<Job<D>>>,
+}
+
+impl<D> DriverHandle<D>
+where
+    D: Send + 'static,
+{
+    /// Move the driver to its own thread. The thread stops, when all
+    /// handles are dropped.
+    pub fn spawn(mut driver: D) -> DriverHandle<D> {
+        let (jobs, receiver) = mpsc::channel::<Job<D>>();
+
+        thread::spawn(move || {
+            for job in receiver {
+                job(&mut driver);
+            }
+        });
+
+        DriverHandle {
+            jobs: Arc::new(jobs),
+        }
+    }
+
+    /// Return a handle, which doesn't keep the thread running.
+    pub fn downgrade(&self) -> WeakDriverHandle<D> {
+        WeakDriverHandle {
+            jobs: Arc::downgrade(&self.jobs),
+        }
+    }
+
+    /// Queue the job and return the receiver of its result, without waiting
+    /// for it.
+    pub fn submit<T, F>(&self, f: F) -> Result<Receiver<Result<T, Error>>, Error>
+    where
+        F: FnOnce(&mut D) -> Result<T, Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        self.jobs
+            .send(Box::new(move |driver: &mut D| {
+                let _ = sender.send(f(driver));
+            }))
+            .map_err(|_| stopped())?;
+
+        Ok(receiver)
+    }
+
+    /// Run the job on the thread of the driver and wait for its result.
+    pub fn run<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut D) -> Result<T, Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.submit(f)?.recv().map_err(|_| stopped())?
+    }
+}
+
+impl<D> Clone for DriverHandle<D> {
+    fn clone(&self) -> DriverHandle<D> {
+        DriverHandle {
+            jobs: self.jobs.clone(),
+        }
+    }
+}
+
+impl<D> WeakDriverHandle<D> {
+    /// Return the handle of the driver, when the thread still runs.
+    pub fn upgrade(&self) -> Option<DriverHandle<D>> {
+        self.jobs.upgrade().map(|jobs| DriverHandle { jobs })
+    }
+}
+
+impl<D> Clone for WeakDriverHandle<D> {
+    fn clone(&self) -> WeakDriverHandle<D> {
+        WeakDriverHandle {
+            jobs: self.jobs.clone(),
+        }
+    }
+}
+
+impl<D> fmt::Debug for DriverHandle<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DriverHandle").finish()
+    }
+}
+
+/// The thread of the driver panicked
+fn stopped() -> Error {
+    Error::NoController("The driver thread stopped".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run() {
+        let handle = DriverHandle::spawn(vec![]);
+        let other = handle.clone();
+
+        handle
+            .run(|d: &mut Vec<u8>| {
+                d.push(1);
+                Ok(())
+            })
+            .unwrap();
+        let waiting = other.submit(|d: &mut Vec<u8>| Ok(d.len())).unwrap();
+        handle
+            .run(|d: &mut Vec<u8>| {
+                d.push(2);
+                Ok(())
+            })
+            .unwrap();
+
+        // the jobs run in the order they were queued
+        assert_eq!(waiting.recv().unwrap().unwrap(), 1);
+        assert_eq!(
+            handle.run(|d: &mut Vec<u8>| Ok(d.clone())).unwrap(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn weak() {
+        let handle = DriverHandle::spawn(vec![0u8]);
+        let weak = handle.downgrade();
+        assert!(weak.upgrade().is_some());
+
+        // the weak handle doesn't keep the driver
+        drop(handle);
+        assert!(weak.upgrade().is_none());
+    }
+}
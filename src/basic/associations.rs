@@ -0,0 +1,296 @@
+//! Association management
+//!
+//! Caches the association groups of all nodes, to audit where the nodes
+//! send their reports to and to add the associations which are missing.
+
+use super::Node;
+use crate::cmds::association::Association as AssociationCmd;
+use crate::cmds::association_group_info::AssociationGroupInfo;
+use crate::cmds::multi_channel_association::{
+    MultiChannelAssociation, MultiChannelAssociationReport,
+};
+use crate::cmds::{CommandClass, IncomingFrame};
+use crate::driver::Driver;
+use crate::error::Error;
+
+/// One association group of a node.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AssociationGroup {
+    pub node: u8,
+    pub group: u8,
+    pub name: Option<String>,
+    pub max_nodes: u8,
+    pub nodes: Vec<u8>,
+    pub endpoints: Vec<(u8, u8)>,
+}
+
+/// An association which should exist: the node reports the group to the
+/// target node, or to an end point of the target node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Association {
+    pub node: u8,
+    pub group: u8,
+    pub target: u8,
+    pub endpoint: Option<u8>,
+}
+
+/// Cache of the association groups of all nodes.
+#[derive(Debug, Clone, Default)]
+pub struct Associations {
+    groups: Vec<AssociationGroup>,
+}
+
+impl Associations {
+    /// Create a new empty cache
+    pub fn new() -> Associations {
+        Associations::default()
+    }
+
+    /// Return all cached groups
+    pub fn groups(&self) -> &[AssociationGroup] {
+        &self.groups
+    }
+
+    /// Return the cached group of a node
+    pub fn group(&self, node: u8, group: u8) -> Option<&AssociationGroup> {
+        self.groups
+            .iter()
+            .find(|g| g.node == node && g.group == group)
+    }
+
+    /// Check if the association exists in the cache
+    pub fn contains(&self, association: &Association) -> bool {
+        match self.group(association.node, association.group) {
+            Some(g) => match association.endpoint {
+                Some(e) => g.endpoints.contains(&(association.target, e)),
+                None => g.nodes.contains(&association.target),
+            },
+            None => false,
+        }
+    }
+
+    /// Return the wanted associations which don't exist
+    pub fn missing(&self, wanted: &[Association]) -> Vec<Association> {
+        wanted
+            .iter()
+            .filter(|a| !self.contains(a))
+            .cloned()
+            .collect()
+    }
+
+    /// Add or replace a group in the cache
+    pub fn update(&mut self, group: AssociationGroup) {
+        match self
+            .groups
+            .iter_mut()
+            .find(|g| g.node == group.node && g.group == group.group)
+        {
+            Some(g) => *g = group,
+            None => self.groups.push(group),
+        }
+    }
+
+    /// Replace all groups of a node in the cache
+    pub fn replace_node(&mut self, node: u8, groups: Vec<AssociationGroup>) {
+        self.groups.retain(|g| g.node != node);
+        self.groups.extend(groups);
+    }
+
+    /// Read all association groups from the node.
+    ///
+    /// Nodes without association support have no groups. The Multi Channel
+    /// Association is preferred, because it also delivers the end points.
+    pub fn read<D>(node: &Node<D>) -> Result<Vec<AssociationGroup>, Error>
+    where
+        D: Driver + Send + 'static,
+    {
+        let cmds = node.get_commands();
+        let multi = cmds.contains(&CommandClass::MULTI_INSTANCE_ASSOCIATION);
+        let agi = cmds.contains(&CommandClass::ASSOCIATION_GRP_INFO);
+
+        // check if the node supports associations at all
+        if !multi && !cmds.contains(&CommandClass::ASSOCIATION) {
+            return Ok(vec![]);
+        }
+
+        // get the amount of groups
+        let count = if multi {
+            MultiChannelAssociation::groupings_report(
+                &node.request(MultiChannelAssociation::groupings_get(node.get_id()))?,
+            )?
+        } else {
+            AssociationCmd::groupings_report(
+                &node.request(AssociationCmd::groupings_get(node.get_id()))?,
+            )?
+        };
+
+        // read each group
+        let mut groups = vec![];
+        for group in 1..=count {
+            let mut entry = Associations::read_group(node, group, multi)?;
+
+            // the group name is only available with the group info
+            if agi {
+                entry.name = node
+                    .request(AssociationGroupInfo::name_get(node.get_id(), group))
+                    .and_then(|f| AssociationGroupInfo::name_report(&f))
+                    .map(|(_, name)| name)
+                    .ok();
+            }
+
+            groups.push(entry);
+        }
+
+        Ok(groups)
+    }
+
+    /// Set the association at the node and return the group read back
+    /// from the node.
+    pub fn add<D>(node: &Node<D>, association: &Association) -> Result<AssociationGroup, Error>
+    where
+        D: Driver + Send + 'static,
+    {
+        let multi = node
+            .get_commands()
+            .contains(&CommandClass::MULTI_INSTANCE_ASSOCIATION);
+
+        match association.endpoint {
+            Some(e) => node.send(MultiChannelAssociation::set(
+                node.get_id(),
+                association.group,
+                &[],
+                &[(association.target, e)],
+            )?)?,
+            None => node.send(AssociationCmd::set(
+                node.get_id(),
+                association.group,
+                &[association.target],
+            )?)?,
+        };
+
+        Associations::read_group(node, association.group, multi)
+    }
+
+    /// Read a single group and merge the reports which follow.
+    pub(super) fn read_group<D>(
+        node: &Node<D>,
+        group: u8,
+        multi: bool,
+    ) -> Result<AssociationGroup, Error>
+    where
+        D: Driver + Send + 'static,
+    {
+        // get the first report
+        let mut report = if multi {
+            MultiChannelAssociation::report(
+                &node.request(MultiChannelAssociation::get(node.get_id(), group))?,
+            )?
+        } else {
+            Associations::from_report(&node.request(AssociationCmd::get(node.get_id(), group))?)?
+        };
+
+        // large groups are split up into multiple reports
+        let mut follow = report.reports_to_follow;
+        while follow > 0 {
+            let frame = node.receive()?;
+            let next = if multi {
+                MultiChannelAssociation::report(&frame)?
+            } else {
+                Associations::from_report(&frame)?
+            };
+
+            report.nodes.extend(next.nodes);
+            report.endpoints.extend(next.endpoints);
+            follow = next.reports_to_follow;
+        }
+
+        Ok(AssociationGroup {
+            node: node.get_id(),
+            group,
+            name: None,
+            max_nodes: report.max_nodes,
+            nodes: report.nodes,
+            endpoints: report.endpoints,
+        })
+    }
+
+    /// Convert an association report into a multi channel report without end points
+    fn from_report(frame: &IncomingFrame) -> Result<MultiChannelAssociationReport, Error> {
+        let report = AssociationCmd::report(frame)?;
+
+        Ok(MultiChannelAssociationReport {
+            group: report.group,
+            max_nodes: report.max_nodes,
+            reports_to_follow: report.reports_to_follow,
+            nodes: report.nodes,
+            endpoints: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(node: u8, group: u8, nodes: Vec<u8>, endpoints: Vec<(u8, u8)>) -> AssociationGroup {
+        AssociationGroup {
+            node,
+            group,
+            name: None,
+            max_nodes: 5,
+            nodes,
+            endpoints,
+        }
+    }
+
+    #[test]
+    fn missing_associations() {
+        let mut cache = Associations::new();
+        cache.update(group(2, 1, vec![1], vec![]));
+        cache.update(group(3, 1, vec![], vec![(1, 2)]));
+
+        let wanted = vec![
+            Association {
+                node: 2,
+                group: 1,
+                target: 1,
+                endpoint: None,
+            },
+            Association {
+                node: 2,
+                group: 2,
+                target: 1,
+                endpoint: None,
+            },
+            Association {
+                node: 3,
+                group: 1,
+                target: 1,
+                endpoint: Some(2),
+            },
+            Association {
+                node: 3,
+                group: 1,
+                target: 1,
+                endpoint: Some(1),
+            },
+        ];
+
+        assert_eq!(cache.missing(&wanted), vec![wanted[1], wanted[3]]);
+    }
+
+    #[test]
+    fn replace_groups() {
+        let mut cache = Associations::new();
+        cache.update(group(2, 1, vec![1], vec![]));
+        cache.update(group(2, 1, vec![1, 4], vec![]));
+        assert_eq!(cache.groups().len(), 1);
+        assert_eq!(cache.group(2, 1).unwrap().nodes, vec![1, 4]);
+
+        cache.replace_node(2, vec![group(2, 2, vec![], vec![])]);
+        assert!(cache.group(2, 1).is_none());
+        assert!(cache.group(2, 2).is_some());
+    }
+}
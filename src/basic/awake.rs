@@ -0,0 +1,240 @@
+//! Getters and commands for sleeping nodes
+//!
+//! A sleeping node only listens for a short time after it woke up. Getters
+//! and commands for such a node are stored and run as soon as the node
+//! reports its wake up, instead of failing with a timeout right away.
+//!
+//! The plain commands are stored apart from the getters, so they can be
+//! packed into as few frames as possible at the wake up.
+
+use crate::cmds::Message;
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// A stored getter or command, which sends the request and hands over the
+/// answer
+type Pending = Box<dyn FnOnce() + Send>;
+
+/// Stores the getters and commands until their nodes wake up.
+#[derive(Default)]
+pub struct AwakeQueue {
+    pending: Vec<(u8, Pending)>,
+    commands: Vec<(u8, Message)>,
+    awake: HashSet<u8>,
+}
+
+impl AwakeQueue {
+    /// Create an empty queue
+    pub fn new() -> AwakeQueue {
+        AwakeQueue::default()
+    }
+
+    /// Store the getter until the node wakes up
+    pub fn push<F>(&mut self, node: u8, getter: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.pending.push((node, Box::new(getter)));
+    }
+
+    /// Store the command until the node wakes up
+    pub fn push_command(&mut self, node: u8, message: Message) {
+        self.commands.push((node, message));
+    }
+
+    /// Return the amount of getters and commands, which wait for the node
+    pub fn pending(&self, node: u8) -> usize {
+        self.pending.iter().filter(|p| p.0 == node).count()
+            + self.commands.iter().filter(|c| c.0 == node).count()
+    }
+
+    /// Mark the node as awake, while its stored getters and commands are sent
+    pub fn set_awake(&mut self, node: u8, awake: bool) {
+        if awake {
+            self.awake.insert(node);
+        } else {
+            self.awake.remove(&node);
+        }
+    }
+
+    /// Returns if the node listens right now
+    pub fn is_awake(&self, node: u8) -> bool {
+        self.awake.contains(&node)
+    }
+
+    /// Remove the getters of the node, in the order they were stored
+    pub fn take(&mut self, node: u8) -> Vec<Pending> {
+        let mut taken = vec![];
+        let mut rest = vec![];
+
+        for (n, getter) in self.pending.drain(..) {
+            if n == node {
+                taken.push(getter);
+            } else {
+                rest.push((n, getter));
+            }
+        }
+
+        self.pending = rest;
+        taken
+    }
+
+    /// Remove the commands of the node, in the order they were stored
+    pub fn take_commands(&mut self, node: u8) -> Vec<Message> {
+        let (taken, rest) = self.commands.drain(..).partition(|c| c.0 == node);
+        self.commands = rest;
+        taken.into_iter().map(|c: (u8, Message)| c.1).collect()
+    }
+}
+
+impl fmt::Debug for AwakeQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AwakeQueue")
+            .field("pending", &self.pending.len())
+            .field("commands", &self.commands.len())
+            .field("awake", &self.awake)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::{SimNetwork, SimNode};
+    use crate::basic::Controller;
+    use crate::cmds::basic::Basic;
+    use crate::cmds::CommandClass;
+    use crate::defs::GenericType;
+
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn take_in_order() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut queue = AwakeQueue::new();
+
+        for (node, value) in [(4, 1), (5, 2), (4, 3)] {
+            let calls = calls.clone();
+            queue.push(node, move || calls.lock().unwrap().push(value));
+        }
+        assert_eq!(queue.pending(4), 2);
+
+        for getter in queue.take(4) {
+            getter();
+        }
+        assert_eq!(*calls.lock().unwrap(), vec![1, 3]);
+        assert_eq!(queue.pending(4), 0);
+        assert_eq!(queue.pending(5), 1);
+    }
+
+    #[test]
+    fn take_commands() {
+        let mut queue = AwakeQueue::new();
+        queue.push_command(4, Basic::set(4, 0x10).unwrap());
+        queue.push_command(5, Basic::set(5, 0x20).unwrap());
+        queue.push_command(4, Basic::set(4, 0x30).unwrap());
+        assert_eq!(queue.pending(4), 2);
+
+        let commands = queue.take_commands(4);
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[1].data, vec![0x30]);
+        assert_eq!(queue.pending(4), 0);
+        assert_eq!(queue.pending(5), 1);
+    }
+
+    #[test]
+    fn wake_up_queue() {
+        let sim = SimNetwork::new();
+        sim.add_node(
+            SimNode::new(5, GenericType::BinarySensor)
+                .command_classes(&[CommandClass::WAKE_UP])
+                .sleeping()
+                .value(CommandClass::BASIC, &[0x63]),
+        );
+
+        // the node is awake for its interview
+        sim.wake_up(5);
+        let mut controller = Controller::new(sim.clone()).unwrap();
+        controller.update_node_states().unwrap();
+        sim.sleep(5);
+
+        let node = controller.node(5).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        node.basic_get_when_awake(move |value| sender.send(value).unwrap());
+        assert!(receiver.try_recv().is_err());
+
+        sim.wake_up(5);
+        controller.update_node_states().unwrap();
+        assert_eq!(receiver.try_recv().unwrap().unwrap(), 0x63);
+    }
+
+    #[test]
+    fn wake_up_commands() {
+        let sim = SimNetwork::new();
+        sim.add_node(
+            SimNode::new(5, GenericType::BinarySensor)
+                .command_classes(&[CommandClass::WAKE_UP, CommandClass::CONFIGURATION])
+                .sleeping(),
+        );
+
+        // the node is awake for its interview
+        sim.wake_up(5);
+        let mut controller = Controller::new(sim.clone()).unwrap();
+        sim.sleep(5);
+
+        // the command waits for the next wake up
+        let node = controller.node(5).unwrap();
+        assert_eq!(node.config_set(3, 10, 1).unwrap(), 0);
+        assert_eq!(controller.pending_when_awake(5), 1);
+
+        // the node is sent back to sleep after the command
+        sim.wake_up(5);
+        controller.update_node_states().unwrap();
+        let received = sim.received(5);
+        assert_eq!(
+            received[received.len() - 2..].to_vec(),
+            vec![vec![0x70, 0x04, 0x03, 0x01, 0x0A], vec![0x84, 0x08]]
+        );
+        assert_eq!(controller.pending_when_awake(5), 0);
+    }
+
+    #[test]
+    fn wake_up_batch() {
+        let sim = SimNetwork::new();
+        sim.add_node(
+            SimNode::new(5, GenericType::BinarySensor)
+                .command_classes(&[
+                    CommandClass::WAKE_UP,
+                    CommandClass::CONFIGURATION,
+                    CommandClass::MULTI_CMD,
+                ])
+                .sleeping(),
+        );
+
+        sim.wake_up(5);
+        let mut controller = Controller::new(sim.clone()).unwrap();
+        sim.sleep(5);
+
+        let node = controller.node(5).unwrap();
+        node.config_set(3, 10, 1).unwrap();
+        node.config_set(4, 20, 1).unwrap();
+        assert_eq!(controller.pending_when_awake(5), 2);
+
+        // both commands are sent in a single frame
+        sim.wake_up(5);
+        controller.update_node_states().unwrap();
+        let received = sim.received(5);
+        assert_eq!(
+            received[received.len() - 2..].to_vec(),
+            vec![
+                vec![
+                    0x8F, 0x01, 0x02, 0x05, 0x70, 0x04, 0x03, 0x01, 0x0A, 0x05, 0x70, 0x04, 0x04,
+                    0x01, 0x14
+                ],
+                vec![0x84, 0x08]
+            ]
+        );
+    }
+}
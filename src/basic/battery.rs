@@ -0,0 +1,294 @@
+//! Battery monitoring
+//!
+//! The `BatteryService` reads the battery level of the registered nodes in a
+//! fixed interval, sleeping nodes as soon as they wake up. The levels are kept
+//! as a history and the listeners are informed, when a battery gets low.
+
+use super::Node;
+use crate::cmds::battery::BatteryLevel;
+use crate::cmds::CommandClass;
+use crate::driver::Driver;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// The amount of readings, which are kept for each node
+const HISTORY_LENGTH: usize = 100;
+
+/// A battery reached a threshold
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BatteryEvent {
+    /// The node and the level, which is below the low threshold
+    LowBattery(u8, u8),
+    /// The node and the level, which is below the critical threshold
+    CriticalBattery(u8, u8),
+}
+
+/// A battery level, which was read from a node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatteryReading {
+    pub node: u8,
+    pub level: BatteryLevel,
+    pub time: SystemTime,
+}
+
+/// The state of a battery, the events are only sent when it gets worse
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+enum Charge {
+    Normal,
+    Low,
+    Critical,
+}
+
+/// The history and the state of all batteries.
+#[derive(Debug, Default)]
+struct Monitor {
+    low: u8,
+    critical: u8,
+    history: HashMap<u8, Vec<BatteryReading>>,
+    charges: HashMap<u8, Charge>,
+}
+
+impl Monitor {
+    /// Store the reading, returns the event when the battery got worse
+    fn record(&mut self, reading: BatteryReading) -> Option<BatteryEvent> {
+        let history = self.history.entry(reading.node).or_default();
+        history.push(reading);
+        if history.len() > HISTORY_LENGTH {
+            history.remove(0);
+        }
+
+        let percent = reading.level.percent();
+        let charge = if percent <= self.critical {
+            Charge::Critical
+        } else if percent <= self.low {
+            Charge::Low
+        } else {
+            Charge::Normal
+        };
+
+        // a new battery starts again
+        let last = self.charges.insert(reading.node, charge);
+        if last
+            .map(|l| l >= charge)
+            .unwrap_or(charge == Charge::Normal)
+        {
+            return None;
+        }
+
+        match charge {
+            Charge::Critical => Some(BatteryEvent::CriticalBattery(reading.node, percent)),
+            Charge::Low => Some(BatteryEvent::LowBattery(reading.node, percent)),
+            Charge::Normal => None,
+        }
+    }
+}
+
+type Listeners = Arc<Mutex<Vec<Box<dyn Fn(&BatteryEvent) + Send>>>>;
+
+/// Reads the battery levels of the registered nodes and warns about low
+/// batteries.
+pub struct BatteryService<D>
+where
+    D: Driver,
+{
+    interval: Duration,
+    nodes: Vec<Node<D>>,
+    monitor: Arc<Mutex<Monitor>>,
+    waiting: Arc<Mutex<HashSet<u8>>>,
+    listeners: Listeners,
+}
+
+impl<D> BatteryService<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create a new service, which reads the levels in the interval. The
+    /// thresholds are 20% for a low and 5% for a critical battery.
+    pub fn new(interval: Duration) -> BatteryService<D> {
+        BatteryService {
+            interval,
+            nodes: vec![],
+            monitor: Arc::new(Mutex::new(Monitor {
+                low: 20,
+                critical: 5,
+                ..Monitor::default()
+            })),
+            waiting: Arc::new(Mutex::new(HashSet::new())),
+            listeners: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Set the thresholds in percent, at which the low and the critical
+    /// events are sent.
+    pub fn set_thresholds(&mut self, low: u8, critical: u8) {
+        let mut monitor = self.monitor.lock().unwrap();
+        monitor.low = low;
+        monitor.critical = critical;
+    }
+
+    /// Register the node, when it supports the Battery Command Class.
+    /// Returns if the node was registered.
+    pub fn register(&mut self, node: Node<D>) -> bool {
+        if !node.get_commands().contains(&CommandClass::BATTERY) {
+            return false;
+        }
+
+        self.nodes.push(node);
+        true
+    }
+
+    /// Register a function which gets the low and critical battery events.
+    pub fn on_event<F>(&mut self, listener: F)
+    where
+        F: Fn(&BatteryEvent) + Send + 'static,
+    {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    /// Returns the readings of the node, the oldest first
+    pub fn history(&self, node: u8) -> Vec<BatteryReading> {
+        self.monitor
+            .lock()
+            .unwrap()
+            .history
+            .get(&node)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Read the levels of all nodes once. Returns the readings of the nodes,
+    /// which are awake, the sleeping nodes are read when they wake up.
+    pub fn poll(&self) -> Vec<BatteryReading> {
+        let readings = Arc::new(Mutex::new(vec![]));
+
+        for node in &self.nodes {
+            // a sleeping node is only read once, when it wakes up
+            if !self.waiting.lock().unwrap().insert(node.get_id()) {
+                continue;
+            }
+
+            let id = node.get_id();
+            let monitor = self.monitor.clone();
+            let waiting = self.waiting.clone();
+            let listeners = self.listeners.clone();
+            let readings = readings.clone();
+
+            node.get_when_awake(Node::battery_get, move |level| {
+                waiting.lock().unwrap().remove(&id);
+
+                if let Ok(level) = level {
+                    let reading = BatteryReading {
+                        node: id,
+                        level,
+                        time: SystemTime::now(),
+                    };
+                    readings.lock().unwrap().push(reading);
+
+                    let event = monitor.lock().unwrap().record(reading);
+                    if let Some(event) = event {
+                        for listener in listeners.lock().unwrap().iter() {
+                            listener(&event);
+                        }
+                    }
+                }
+            });
+        }
+
+        let readings = readings.lock().unwrap().clone();
+        readings
+    }
+
+    /// Read the levels in the background, the events are delivered to the
+    /// listeners.
+    pub fn start(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            self.poll();
+            thread::sleep(self.interval);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::{SimNetwork, SimNode};
+    use crate::basic::Controller;
+    use crate::defs::GenericType;
+
+    fn reading(level: BatteryLevel) -> BatteryReading {
+        BatteryReading {
+            node: 5,
+            level,
+            time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn thresholds() {
+        let mut monitor = Monitor {
+            low: 20,
+            critical: 5,
+            ..Monitor::default()
+        };
+
+        assert_eq!(monitor.record(reading(BatteryLevel::Percent(80))), None);
+        assert_eq!(
+            monitor.record(reading(BatteryLevel::Percent(18))),
+            Some(BatteryEvent::LowBattery(5, 18))
+        );
+        // the event is only sent once
+        assert_eq!(monitor.record(reading(BatteryLevel::Percent(15))), None);
+        assert_eq!(
+            monitor.record(reading(BatteryLevel::Low)),
+            Some(BatteryEvent::CriticalBattery(5, 0))
+        );
+
+        // the battery was replaced
+        assert_eq!(monitor.record(reading(BatteryLevel::Percent(100))), None);
+        assert_eq!(
+            monitor.record(reading(BatteryLevel::Percent(3))),
+            Some(BatteryEvent::CriticalBattery(5, 3))
+        );
+        assert_eq!(monitor.history[&5].len(), 6);
+    }
+
+    #[test]
+    fn poll_on_wake_up() {
+        let sim = SimNetwork::new();
+        sim.add_node(
+            SimNode::new(5, GenericType::BinarySensor)
+                .command_classes(&[CommandClass::BATTERY, CommandClass::WAKE_UP])
+                .sleeping()
+                .respond(CommandClass::BATTERY, 0x02, 0x03, &[0x0A]),
+        );
+
+        // the node is awake for its interview
+        sim.wake_up(5);
+        let mut controller = Controller::new(sim.clone()).unwrap();
+        controller.update_node_states().unwrap();
+        sim.sleep(5);
+
+        let mut service = BatteryService::new(Duration::from_secs(3600));
+        assert!(service.register(controller.node(5).unwrap()));
+        let events = Arc::new(Mutex::new(vec![]));
+        let listener = events.clone();
+        service.on_event(move |e| listener.lock().unwrap().push(*e));
+
+        // the node is read only once, when it wakes up
+        assert!(service.poll().is_empty());
+        assert!(service.poll().is_empty());
+
+        sim.wake_up(5);
+        controller.update_node_states().unwrap();
+        assert_eq!(service.history(5).len(), 1);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![BatteryEvent::LowBattery(5, 10)]
+        );
+    }
+}
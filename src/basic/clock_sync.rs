@@ -0,0 +1,258 @@
+//! Clock synchronization
+//!
+//! The `ClockService` compares the clocks of the registered nodes with the
+//! clock of the controller in a fixed interval, and sets them again when they
+//! drifted too far. Sleeping nodes are synchronized when they wake up.
+
+use super::Node;
+use crate::cmds::clock::ClockTime;
+use crate::cmds::time_parameters::DateTime;
+use crate::cmds::CommandClass;
+use crate::driver::Driver;
+
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// The minutes of a week, the clock command class starts again after it
+const MINUTES_OF_WEEK: u32 = 7 * 24 * 60;
+
+/// The result of a synchronization of one node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockSync {
+    pub node: u8,
+    /// The difference to the controller, `None` when the clock couldn't be read
+    pub drift: Option<Duration>,
+    /// The clock of the node was set
+    pub updated: bool,
+    /// Setting the clock of the node failed
+    pub failed: bool,
+}
+
+type Listeners = Arc<Mutex<Vec<Box<dyn Fn(&ClockSync) + Send>>>>;
+
+/// Keeps the clocks of the registered nodes in sync with the controller.
+pub struct ClockService<D>
+where
+    D: Driver,
+{
+    interval: Duration,
+    max_drift: Duration,
+    utc_offset: i64,
+    nodes: Vec<Node<D>>,
+    listeners: Listeners,
+}
+
+impl<D> ClockService<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create a new service, which checks the clocks in the interval and
+    /// sets them when they differ more than the maximal drift.
+    pub fn new(interval: Duration, max_drift: Duration) -> ClockService<D> {
+        ClockService {
+            interval,
+            max_drift,
+            utc_offset: 0,
+            nodes: vec![],
+            listeners: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Set the offset of the local time to UTC in seconds. The Clock Command
+    /// Class uses the local time, the Time Parameters Command Class UTC.
+    pub fn set_utc_offset(&mut self, seconds: i64) {
+        self.utc_offset = seconds;
+    }
+
+    /// Register the node, when it supports the Clock or the Time Parameters
+    /// Command Class. Returns if the node was registered.
+    pub fn register(&mut self, node: Node<D>) -> bool {
+        let cmds = node.get_commands();
+        if !cmds.contains(&CommandClass::CLOCK) && !cmds.contains(&CommandClass::TIME_PARAMETERS) {
+            return false;
+        }
+
+        self.nodes.push(node);
+        true
+    }
+
+    /// Register a function which gets the result of each synchronization,
+    /// also of the sleeping nodes which are synchronized later.
+    pub fn on_sync<F>(&mut self, listener: F)
+    where
+        F: Fn(&ClockSync) + Send + 'static,
+    {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    /// Synchronize all nodes once. Returns the results of the nodes, which
+    /// are awake, the sleeping nodes are synchronized when they wake up.
+    pub fn sync(&self) -> Vec<ClockSync> {
+        let (sender, receiver) = mpsc::channel();
+
+        for node in &self.nodes {
+            let (max_drift, utc_offset) = (self.max_drift, self.utc_offset);
+            let listeners = self.listeners.clone();
+            let sender = sender.clone();
+
+            node.get_when_awake(
+                move |n| Ok(sync_node(n, max_drift, utc_offset)),
+                move |result| {
+                    if let Ok(sync) = result {
+                        for listener in listeners.lock().unwrap().iter() {
+                            listener(&sync);
+                        }
+                        // the receiver is gone, when the node was asleep
+                        let _ = sender.send(sync);
+                    }
+                },
+            );
+        }
+
+        drop(sender);
+        receiver.try_iter().collect()
+    }
+
+    /// Synchronize the clocks in the background, the results are delivered
+    /// to the listeners.
+    pub fn start(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            self.sync();
+            thread::sleep(self.interval);
+        })
+    }
+}
+
+/// Compare the clocks of the node with the controller and set them, when
+/// they drifted too far or couldn't be read.
+fn sync_node<D>(node: &Node<D>, max_drift: Duration, utc_offset: i64) -> ClockSync
+where
+    D: Driver + Send + 'static,
+{
+    let cmds = node.get_commands();
+    let now = SystemTime::now();
+    let mut sync = ClockSync {
+        node: node.get_id(),
+        drift: None,
+        updated: false,
+        failed: false,
+    };
+
+    if cmds.contains(&CommandClass::TIME_PARAMETERS) {
+        let drift = node
+            .time_parameters_get()
+            .ok()
+            .map(|time| difference(time.to_system(), now));
+        sync.drift = drift;
+
+        if drift.map(|d| d > max_drift).unwrap_or(true) {
+            match node.time_parameters_set(DateTime::from_system(now)) {
+                Ok(_) => sync.updated = true,
+                Err(_) => sync.failed = true,
+            }
+        }
+    }
+
+    if cmds.contains(&CommandClass::CLOCK) {
+        let local = local_time(now, utc_offset);
+        let drift = node
+            .clock_get()
+            .ok()
+            .map(|time| clock_difference(time, local));
+        sync.drift = match (sync.drift, drift) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+
+        if drift.map(|d| d > max_drift).unwrap_or(true) {
+            match node.clock_set(local) {
+                Ok(_) => sync.updated = true,
+                Err(_) => sync.failed = true,
+            }
+        }
+    }
+
+    sync
+}
+
+/// Returns the local day of the week and time
+fn local_time(now: SystemTime, utc_offset: i64) -> ClockTime {
+    DateTime::local(now, utc_offset).clock_time()
+}
+
+/// Returns the absolute difference of the two times
+fn difference(a: SystemTime, b: SystemTime) -> Duration {
+    a.duration_since(b)
+        .or_else(|_| b.duration_since(a))
+        .unwrap_or_else(|_| Duration::from_secs(0))
+}
+
+/// Returns the difference of the two clocks, over the end of the week
+fn clock_difference(a: ClockTime, b: ClockTime) -> Duration {
+    let minutes = (a.minute_of_week() as i64 - b.minute_of_week() as i64).unsigned_abs() as u32;
+    let minutes = minutes.min(MINUTES_OF_WEEK - minutes);
+
+    Duration::from_secs(minutes as u64 * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::{SimNetwork, SimNode};
+    use crate::basic::Controller;
+    use crate::defs::GenericType;
+
+    #[test]
+    fn clock_difference_over_week_end() {
+        let sunday = ClockTime {
+            weekday: 7,
+            hour: 23,
+            minute: 58,
+        };
+        let monday = ClockTime {
+            weekday: 1,
+            hour: 0,
+            minute: 3,
+        };
+        assert_eq!(
+            clock_difference(sunday, monday),
+            Duration::from_secs(5 * 60)
+        );
+    }
+
+    #[test]
+    fn sync_clocks() {
+        let sim = SimNetwork::new();
+        sim.add_node(
+            SimNode::new(2, GenericType::Thermostat)
+                .command_classes(&[CommandClass::CLOCK])
+                .respond(CommandClass::CLOCK, 0x05, 0x06, &[0x20, 0x00]),
+        );
+        sim.add_node(
+            SimNode::new(3, GenericType::BinarySwitch)
+                .command_classes(&[CommandClass::SWITCH_BINARY]),
+        );
+
+        let mut controller = Controller::new(sim.clone()).unwrap();
+        let mut service = ClockService::new(Duration::from_secs(3600), Duration::from_secs(60));
+        assert!(service.register(controller.node(2).unwrap()));
+        assert!(!service.register(controller.node(3).unwrap()));
+
+        let synced = Arc::new(Mutex::new(vec![]));
+        let listener = synced.clone();
+        service.on_sync(move |s| listener.lock().unwrap().push(s.node));
+
+        // the clock of the node stays at Monday 0:00
+        let results = service.sync();
+        assert_eq!(results.len(), 1);
+        let drift = results[0].drift.unwrap();
+        assert_eq!(results[0].updated, drift > Duration::from_secs(60));
+        if results[0].updated {
+            assert_eq!(&sim.received(2).last().unwrap()[..2], &[0x81, 0x04]);
+        }
+        assert_eq!(*synced.lock().unwrap(), vec![2]);
+    }
+}
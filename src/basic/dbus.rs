@@ -0,0 +1,380 @@
+//! D-Bus service
+//!
+//! Exposes the controller and its nodes on the D-Bus, built with the `dbus`
+//! feature. The service owns the name `org.rzw` and handles one call after
+//! another, on the thread which calls `serve`.
+//!
+//! | Path                  | Interface             |
+//! |-----------------------|-----------------------|
+//! | `/org/rzw/controller` | `org.rzw.Controller1` |
+//! | `/org/rzw/node/<id>`  | `org.rzw.Node1`       |
+//!
+//! ```text
+//! busctl --user call org.rzw /org/rzw/node/2 org.rzw.Node1 SwitchBinarySet b true
+//! ```
+
+use super::Controller;
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind};
+
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+/// The well-known name of the service
+pub const BUS_NAME: &str = "org.rzw";
+
+/// The bus the service is registered at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bus {
+    /// The bus of the logged in user
+    Session,
+    /// The bus of the whole system, which needs a policy for `org.rzw`
+    System,
+}
+
+/// A call, which is handed from the D-Bus objects to the controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Call {
+    Nodes,
+    BasicSet(u8, u8),
+    BasicGet(u8),
+    SwitchBinarySet(u8, bool),
+    SwitchBinaryGet(u8),
+    SwitchMultilevelSet(u8, u8),
+    SwitchMultilevelGet(u8),
+}
+
+/// The answer of the controller to a call
+#[derive(Debug, Clone, PartialEq)]
+enum Answer {
+    Done,
+    Nodes(Vec<u8>),
+    Level(u8),
+    Switch(bool),
+}
+
+/// A call together with the channel for its answer
+type Pending = (Call, Sender<Result<Answer, Error>>);
+
+/// Hands the calls of the D-Bus objects to the thread of the controller
+struct Bridge {
+    calls: Mutex<Sender<Pending>>,
+}
+
+impl Bridge {
+    /// Send the call to the controller and wait for its answer
+    fn call(&self, call: Call) -> zbus::fdo::Result<Answer> {
+        let (sender, receiver) = mpsc::channel();
+
+        self.calls
+            .lock()
+            .unwrap()
+            .send((call, sender))
+            .map_err(|_| zbus::fdo::Error::Failed("The controller stopped".into()))?;
+
+        receiver
+            .recv()
+            .map_err(|_| zbus::fdo::Error::Failed("The controller stopped".into()))?
+            .map_err(to_fdo)
+    }
+}
+
+/// Convert the error into the matching D-Bus error
+fn to_fdo(error: Error) -> zbus::fdo::Error {
+    match error.kind() {
+        ErrorKind::InvalidInput => zbus::fdo::Error::InvalidArgs(error.to_string()),
+        ErrorKind::NotImplemented => zbus::fdo::Error::NotSupported(error.to_string()),
+        _ => zbus::fdo::Error::Failed(error.to_string()),
+    }
+}
+
+/// The answer didn't match the call
+fn unexpected() -> zbus::fdo::Error {
+    zbus::fdo::Error::Failed("Unexpected answer of the controller".into())
+}
+
+/// The `org.rzw.Controller1` object
+struct Controller1 {
+    bridge: std::sync::Arc<Bridge>,
+    home_id: u32,
+    node_id: u8,
+}
+
+#[zbus::interface(name = "org.rzw.Controller1")]
+impl Controller1 {
+    /// Return the ids of all nodes
+    fn nodes(&self) -> zbus::fdo::Result<Vec<u8>> {
+        match self.bridge.call(Call::Nodes)? {
+            Answer::Nodes(nodes) => Ok(nodes),
+            _ => Err(unexpected()),
+        }
+    }
+
+    #[zbus(property)]
+    fn home_id(&self) -> u32 {
+        self.home_id
+    }
+
+    #[zbus(property)]
+    fn node_id(&self) -> u8 {
+        self.node_id
+    }
+}
+
+/// The `org.rzw.Node1` object
+struct Node1 {
+    bridge: std::sync::Arc<Bridge>,
+    id: u8,
+    name: String,
+    commands: Vec<String>,
+}
+
+#[zbus::interface(name = "org.rzw.Node1")]
+impl Node1 {
+    fn basic_set(&self, value: u8) -> zbus::fdo::Result<()> {
+        self.bridge.call(Call::BasicSet(self.id, value)).map(|_| ())
+    }
+
+    fn basic_get(&self) -> zbus::fdo::Result<u8> {
+        match self.bridge.call(Call::BasicGet(self.id))? {
+            Answer::Level(value) => Ok(value),
+            _ => Err(unexpected()),
+        }
+    }
+
+    fn switch_binary_set(&self, value: bool) -> zbus::fdo::Result<()> {
+        self.bridge
+            .call(Call::SwitchBinarySet(self.id, value))
+            .map(|_| ())
+    }
+
+    fn switch_binary_get(&self) -> zbus::fdo::Result<bool> {
+        match self.bridge.call(Call::SwitchBinaryGet(self.id))? {
+            Answer::Switch(value) => Ok(value),
+            _ => Err(unexpected()),
+        }
+    }
+
+    fn switch_multilevel_set(&self, value: u8) -> zbus::fdo::Result<()> {
+        self.bridge
+            .call(Call::SwitchMultilevelSet(self.id, value))
+            .map(|_| ())
+    }
+
+    fn switch_multilevel_get(&self) -> zbus::fdo::Result<u8> {
+        match self.bridge.call(Call::SwitchMultilevelGet(self.id))? {
+            Answer::Level(value) => Ok(value),
+            _ => Err(unexpected()),
+        }
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// The name of the node, empty when it has none
+    #[zbus(property)]
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The names of the supported command classes
+    #[zbus(property)]
+    fn commands(&self) -> Vec<String> {
+        self.commands.clone()
+    }
+}
+
+/// Service, which exposes the controller on the D-Bus.
+pub struct DbusService<D>
+where
+    D: Driver,
+{
+    controller: Controller<D>,
+}
+
+impl<D> DbusService<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create a service for the controller.
+    pub fn new(controller: Controller<D>) -> DbusService<D> {
+        DbusService { controller }
+    }
+
+    /// Register the objects at the bus and handle the calls. Only returns,
+    /// when the objects couldn't be registered.
+    pub fn serve(&mut self, bus: Bus) -> Result<(), Error> {
+        let (sender, receiver) = mpsc::channel();
+        let bridge = std::sync::Arc::new(Bridge {
+            calls: Mutex::new(sender),
+        });
+
+        let builder = match bus {
+            Bus::Session => zbus::blocking::connection::Builder::session(),
+            Bus::System => zbus::blocking::connection::Builder::system(),
+        };
+
+        let mut builder = builder
+            .map_err(from_zbus)?
+            .name(BUS_NAME)
+            .map_err(from_zbus)?;
+        builder = builder
+            .serve_at(
+                "/org/rzw/controller",
+                Controller1 {
+                    bridge: bridge.clone(),
+                    home_id: self.controller.home_id(),
+                    node_id: self.controller.get_id(),
+                },
+            )
+            .map_err(from_zbus)?;
+
+        for id in self.controller.nodes() {
+            let node = match self.controller.node(id) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            let object = Node1 {
+                bridge: bridge.clone(),
+                id,
+                name: node.name().unwrap_or_default(),
+                commands: node
+                    .get_commands()
+                    .iter()
+                    .map(|c| format!("{:?}", c))
+                    .collect(),
+            };
+            builder = builder
+                .serve_at(format!("/org/rzw/node/{}", id), object)
+                .map_err(from_zbus)?;
+        }
+
+        // the objects are served as long as the connection lives
+        let _connection = builder.build().map_err(from_zbus)?;
+
+        self.handle_calls(receiver);
+        Ok(())
+    }
+
+    /// Run the calls on the controller and send back the answers
+    fn handle_calls(&mut self, calls: Receiver<Pending>) {
+        for (call, answer) in calls {
+            let _ = answer.send(self.execute(call));
+        }
+    }
+
+    /// Run a single call on the controller
+    fn execute(&mut self, call: Call) -> Result<Answer, Error> {
+        let id = match call {
+            Call::Nodes => return Ok(Answer::Nodes(self.controller.nodes())),
+            Call::BasicSet(id, _)
+            | Call::BasicGet(id)
+            | Call::SwitchBinarySet(id, _)
+            | Call::SwitchBinaryGet(id)
+            | Call::SwitchMultilevelSet(id, _)
+            | Call::SwitchMultilevelGet(id) => id,
+        };
+
+        let node = self.controller.node(id).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} is unknown", id),
+            )
+        })?;
+
+        Ok(match call {
+            Call::Nodes => unreachable!(),
+            Call::BasicSet(_, value) => node.basic_set(value).map(|_| Answer::Done)?,
+            Call::BasicGet(_) => Answer::Level(node.basic_get()?),
+            Call::SwitchBinarySet(_, value) => {
+                node.switch_binary_set(value).map(|_| Answer::Done)?
+            }
+            Call::SwitchBinaryGet(_) => Answer::Switch(node.switch_binary_get()?),
+            Call::SwitchMultilevelSet(_, value) => node
+                .switch_multilevel_set(value, None)
+                .map(|_| Answer::Done)?,
+            Call::SwitchMultilevelGet(_) => Answer::Level(node.switch_multilevel_get()?),
+        })
+    }
+}
+
+/// Convert the error of the bus connection
+fn from_zbus(error: zbus::Error) -> Error {
+    Error::new(ErrorKind::Io(io::ErrorKind::Other), error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use zbus::object_server::Interface;
+
+    /// Return a bridge to a controller, which knows the nodes 2 and 5 and
+    /// answers every other call with the switch of the node 2 turned on
+    fn bridge() -> (Arc<Bridge>, thread::JoinHandle<()>) {
+        let (sender, receiver) = mpsc::channel::<Pending>();
+        let bridge = Arc::new(Bridge {
+            calls: Mutex::new(sender),
+        });
+
+        let controller = thread::spawn(move || {
+            for (call, answer) in receiver {
+                let _ = answer.send(match call {
+                    Call::Nodes => Ok(Answer::Nodes(vec![2, 5])),
+                    Call::SwitchBinaryGet(2) => Ok(Answer::Switch(true)),
+                    Call::SwitchBinarySet(2, _) => Ok(Answer::Done),
+                    _ => Err(Error::new(ErrorKind::InvalidInput, "The node is unknown")),
+                });
+            }
+        });
+
+        (bridge, controller)
+    }
+
+    #[test]
+    fn calls() {
+        let (bridge, controller) = bridge();
+
+        assert_eq!(bridge.call(Call::Nodes).unwrap(), Answer::Nodes(vec![2, 5]));
+        assert_eq!(
+            bridge.call(Call::BasicGet(9)).unwrap_err(),
+            zbus::fdo::Error::InvalidArgs("The node is unknown".into())
+        );
+
+        drop(bridge);
+        controller.join().unwrap();
+    }
+
+    #[test]
+    fn objects() {
+        let (bridge, controller) = bridge();
+        assert_eq!(Controller1::name().as_str(), "org.rzw.Controller1");
+        assert_eq!(<Node1 as Interface>::name().as_str(), "org.rzw.Node1");
+
+        let object = Controller1 {
+            bridge: bridge.clone(),
+            home_id: 0xCAFE,
+            node_id: 1,
+        };
+        assert_eq!(object.nodes().unwrap(), vec![2, 5]);
+        assert_eq!(object.home_id(), 0xCAFE);
+
+        let node = Node1 {
+            bridge: bridge.clone(),
+            id: 2,
+            name: "lamp".into(),
+            commands: vec!["SWITCH_BINARY".into()],
+        };
+        node.switch_binary_set(true).unwrap();
+        assert!(node.switch_binary_get().unwrap());
+        assert!(node.basic_get().is_err());
+
+        drop((object, node, bridge));
+        controller.join().unwrap();
+    }
+}
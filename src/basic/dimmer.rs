@@ -0,0 +1,153 @@
+//! Dimmer with smooth transitions
+//!
+//! Multilevel switches of version 2 and newer can fade to a level on their
+//! own. For version 1 devices the fade is done by the driver, with a ramp of
+//! small steps.
+
+use super::Node;
+use crate::cmds::check_level;
+use crate::driver::Driver;
+use crate::error::Error;
+
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+
+/// The time between two steps of a ramp
+const STEP_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The highest level of a multilevel switch
+const MAX_LEVEL: u8 = 99;
+
+/// A multilevel switch, which fades to a level.
+#[derive(Debug, Clone)]
+pub struct Dimmer<D>
+where
+    D: Driver,
+{
+    node: Node<D>,
+    version: u8,
+}
+
+impl<D> Dimmer<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create a dimmer for a node, which is expected to support
+    /// version 1 of the Multilevel Switch Command Class.
+    pub fn new(node: Node<D>) -> Dimmer<D> {
+        Dimmer { node, version: 1 }
+    }
+
+    /// Set the version of the Multilevel Switch Command Class,
+    /// which the node supports.
+    pub fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    /// Return the node of the dimmer
+    pub fn node(&self) -> &Node<D> {
+        &self.node
+    }
+
+    /// Fade to the level (0-99 or 0xFF for the last level) within the duration.
+    ///
+    /// Version 1 devices get a ramp of levels, so this function blocks until
+    /// the level is reached.
+    pub fn fade_to(&self, level: u8, duration: Duration) -> Result<(), Error> {
+        check_level(level)?;
+
+        // the device fades on its own
+        if self.version >= 2 {
+            self.node.switch_multilevel_set(level, Some(duration))?;
+            return Ok(());
+        }
+
+        // ramp from the current level
+        let current = self.node.switch_multilevel_get()?;
+        let steps = ramp(current, level, duration);
+        let interval = if steps.len() > 1 {
+            duration / steps.len() as u32
+        } else {
+            Duration::from_millis(0)
+        };
+
+        for (i, step) in steps.iter().enumerate() {
+            if i > 0 {
+                thread::sleep(interval);
+            }
+            self.node.switch_multilevel_set(*step, None)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Return the levels of a ramp, the last level is always the target level.
+fn ramp(from: u8, to: u8, duration: Duration) -> Vec<u8> {
+    // 0xFF is the last level, which isn't known to ramp to it
+    let from = cmp::min(from, MAX_LEVEL) as i32;
+    let target = if to == 0xFF { MAX_LEVEL } else { to } as i32;
+
+    // one step per interval, but not more steps than levels
+    let steps = (duration.as_millis() / STEP_INTERVAL.as_millis()) as i32;
+    let steps = cmp::max(cmp::min(steps, (target - from).abs()), 1);
+
+    let mut levels: Vec<u8> = (1..steps)
+        .map(|i| (from + (target - from) * i / steps) as u8)
+        .collect();
+    levels.push(to);
+
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::network;
+    use crate::basic::sim::SimNode;
+    use crate::cmds::CommandClass;
+    use crate::defs::GenericType;
+
+    #[test]
+    fn ramp_levels() {
+        // 2 seconds are 8 steps
+        assert_eq!(
+            ramp(0, 80, Duration::from_secs(2)),
+            vec![10, 20, 30, 40, 50, 60, 70, 80]
+        );
+
+        // not more steps than levels
+        assert_eq!(ramp(50, 47, Duration::from_secs(10)), vec![49, 48, 47]);
+
+        // instantly and to the last level
+        assert_eq!(ramp(20, 0xFF, Duration::from_millis(0)), vec![0xFF]);
+    }
+
+    #[test]
+    fn dimmer() {
+        let (sim, mut controller) = network(vec![
+            SimNode::new(2, GenericType::MultiLevelSwitch)
+                .command_classes(&[CommandClass::SWITCH_MULTILEVEL]),
+            SimNode::new(3, GenericType::MultiLevelSwitch)
+                .command_classes(&[CommandClass::SWITCH_MULTILEVEL])
+                .respond(CommandClass::SWITCH_MULTILEVEL, 0x02, 0x03, &[0xFE]),
+        ]);
+        let node = controller.node(2).unwrap();
+
+        node.switch_multilevel_set(0x32, None).unwrap();
+        assert_eq!(node.switch_multilevel_get().unwrap(), 0x32);
+        assert!(node.switch_multilevel_set(100, None).is_err());
+
+        node.switch_multilevel_start_level_change(true, None, None)
+            .unwrap();
+        node.switch_multilevel_stop_level_change().unwrap();
+        assert_eq!(
+            sim.received(2)[sim.received(2).len() - 2..].to_vec(),
+            vec![vec![0x26, 0x04, 0x20, 0x00], vec![0x26, 0x05]]
+        );
+
+        // the unknown level of version 4 nodes is no valid level
+        assert!(controller.node(3).unwrap().switch_multilevel_get().is_err());
+    }
+}
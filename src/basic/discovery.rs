@@ -0,0 +1,132 @@
+//! Node discovery
+//!
+//! A discovery compares the nodes of the network with the ones known by the
+//! controller. The nodes which joined or left the network are reported to
+//! the registered listeners, the other nodes are kept as they are.
+
+use std::fmt;
+
+/// A node joined or left the network.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeEvent {
+    NodeAdded(u8),
+    NodeRemoved(u8),
+}
+
+/// The nodes which joined or left the network since the last discovery.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiscoveryDiff {
+    pub added: Vec<u8>,
+    pub removed: Vec<u8>,
+}
+
+impl DiscoveryDiff {
+    /// Compare the known nodes with the ones of the network.
+    pub fn new(known: &[u8], network: &[u8]) -> DiscoveryDiff {
+        DiscoveryDiff {
+            added: network
+                .iter()
+                .filter(|id| !known.contains(id))
+                .cloned()
+                .collect(),
+            removed: known
+                .iter()
+                .filter(|id| !network.contains(id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns if the nodes didn't change.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Return the changes as events, the removed nodes first.
+    pub fn events(&self) -> Vec<NodeEvent> {
+        self.removed
+            .iter()
+            .map(|id| NodeEvent::NodeRemoved(*id))
+            .chain(self.added.iter().map(|id| NodeEvent::NodeAdded(*id)))
+            .collect()
+    }
+}
+
+type Listener = Box<dyn Fn(&NodeEvent)>;
+
+/// Stores the listeners which get notified, when nodes join or leave
+/// the network.
+#[derive(Default)]
+pub struct NodeEvents {
+    listeners: Vec<Listener>,
+}
+
+impl NodeEvents {
+    /// Create a new store without listeners
+    pub fn new() -> NodeEvents {
+        NodeEvents::default()
+    }
+
+    /// Register a function which is called for each added or removed node.
+    pub fn on_event<F>(&mut self, listener: F)
+    where
+        F: Fn(&NodeEvent) + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Inform all listeners about the event
+    pub fn notify(&self, event: &NodeEvent) {
+        for listener in &self.listeners {
+            listener(event);
+        }
+    }
+}
+
+impl fmt::Debug for NodeEvents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NodeEvents {{listeners: {}}}", self.listeners.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::{network, switch};
+    use crate::cmds::CommandClass;
+
+    #[test]
+    fn diff() {
+        let diff = DiscoveryDiff::new(&[2, 3, 5], &[2, 5, 7, 8]);
+        assert_eq!(diff.added, vec![7, 8]);
+        assert_eq!(diff.removed, vec![3]);
+        assert_eq!(
+            diff.events(),
+            vec![
+                NodeEvent::NodeRemoved(3),
+                NodeEvent::NodeAdded(7),
+                NodeEvent::NodeAdded(8),
+            ]
+        );
+
+        assert!(DiscoveryDiff::new(&[2, 3], &[3, 2]).is_empty());
+    }
+
+    #[test]
+    fn rediscovery() {
+        let (sim, controller) = network(vec![switch(2), switch(3)]);
+        sim.remove_node(2);
+        sim.add_node(switch(7));
+
+        let diff = controller.discover_nodes().unwrap();
+        assert_eq!(diff.added, vec![7]);
+        assert_eq!(diff.removed, vec![2]);
+        assert_eq!(controller.nodes(), vec![3, 7]);
+        assert_eq!(
+            controller.nodes_supporting(CommandClass::SWITCH_BINARY),
+            vec![3, 7]
+        );
+    }
+}
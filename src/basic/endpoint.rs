@@ -0,0 +1,117 @@
+//! End points of Multi Channel nodes
+//!
+//! Devices like power strips consist of multiple end points, which are
+//! controlled independently. An `Endpoint` offers the same functions as a
+//! `Node`, but the commands are sent to one end point of the node.
+//!
+//! ```rust,ignore
+//! // turn on the second socket of the power strip
+//! node.endpoint(2).switch_binary_set(true)?;
+//!
+//! // or find the sockets first
+//! for endpoint in node.endpoints()? {
+//!     println!("{:?}", endpoint.capability());
+//! }
+//! ```
+
+use super::Node;
+use crate::cmds::multi_channel::EndpointCapability;
+use crate::driver::Driver;
+
+use std::ops::Deref;
+
+/// A handle to one end point of a node.
+#[derive(Debug, Clone)]
+pub struct Endpoint<D>
+where
+    D: Driver,
+{
+    node: Node<D>,
+    capability: Option<EndpointCapability>,
+}
+
+impl<D> Endpoint<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create the handle for the node, which addresses the end point.
+    pub(super) fn new(node: Node<D>) -> Endpoint<D> {
+        Endpoint {
+            node,
+            capability: None,
+        }
+    }
+
+    /// Create the handle for an end point, which was discovered.
+    pub(super) fn discovered(node: Node<D>, capability: EndpointCapability) -> Endpoint<D> {
+        Endpoint {
+            node,
+            capability: Some(capability),
+        }
+    }
+
+    /// Return the index of the end point
+    pub fn index(&self) -> u8 {
+        self.node.endpoint
+    }
+
+    /// Return the device class and the command classes of the end point,
+    /// when it was discovered with `Node::endpoints`.
+    pub fn capability(&self) -> Option<&EndpointCapability> {
+        self.capability.as_ref()
+    }
+}
+
+impl<D> Deref for Endpoint<D>
+where
+    D: Driver,
+{
+    type Target = Node<D>;
+
+    /// The functions of the node are sent to the end point
+    fn deref(&self) -> &Node<D> {
+        &self.node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basic::sim::fixture::network;
+    use crate::basic::sim::SimNode;
+    use crate::cmds::CommandClass;
+    use crate::defs::GenericType;
+
+    #[test]
+    fn endpoints() {
+        let (sim, mut controller) = network(vec![SimNode::new(8, GenericType::BinarySwitch)
+            .command_classes(&[CommandClass::MULTI_INSTANCE, CommandClass::SWITCH_BINARY])
+            .respond(CommandClass::MULTI_INSTANCE, 0x07, 0x08, &[0x40, 0x02])
+            .respond(
+                CommandClass::MULTI_INSTANCE,
+                0x09,
+                0x0A,
+                &[0x01, 0x10, 0x01, 0x25],
+            )]);
+        let endpoints = controller.node(8).unwrap().endpoints().unwrap();
+        assert_eq!(endpoints.len(), 2);
+
+        // the identical end points are only requested once
+        let capability = endpoints[1].capability().unwrap();
+        assert_eq!(capability.endpoint, 2);
+        assert_eq!(capability.cmds, vec![CommandClass::SWITCH_BINARY]);
+        assert_eq!(
+            sim.received(8)
+                .iter()
+                .filter(|f| f[..2] == [0x60, 0x09])
+                .count(),
+            1
+        );
+
+        // turn on the second socket
+        endpoints[1].switch_binary_set(true).unwrap();
+        assert_eq!(
+            sim.received(8).last(),
+            Some(&vec![0x60, 0x0D, 0x00, 0x02, 0x25, 0x01, 0xFF])
+        );
+    }
+}
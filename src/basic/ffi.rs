@@ -0,0 +1,322 @@
+//! C interface
+//!
+//! A stable C API around the controller, which is built with the `ffi`
+//! feature. C and C++ programs link against the shared library with the
+//! declarations of `include/rzw.h`, which is built with:
+//!
+//! ```text
+//! cargo build --release --features ffi
+//! ```
+//!
+//! The functions return `0` on success and `-1` on failure, the reason of
+//! the last failure on the calling thread is returned by `rzw_last_error`.
+//! A controller handle must only be used by one thread at a time.
+//!
+//! # Safety
+//!
+//! The pointers handed to the functions must be null or valid. A controller
+//! handle must come from `rzw_open` and must not be used after `rzw_close`,
+//! the output pointers must point to writable memory of the given length.
+
+// the safety requirements are the same for all functions, see above
+#![allow(clippy::missing_safety_doc)]
+
+use super::Controller;
+use crate::cmds::encapsulation::Encapsulation;
+use crate::cmds::transport_service::Reassembly;
+use crate::cmds::IncomingFrame;
+use crate::driver::serial::{SerialDriver, SerialMsgFunction};
+use crate::error::{Error, ErrorKind};
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::Mutex;
+
+/// The controller behind a handle of the C interface
+pub type RzwController = Controller<SerialDriver>;
+
+/// Called for each command which was received from a node, with the user data,
+/// the node id, the command class, the command and the payload.
+pub type RzwEventCallback = extern "C" fn(
+    user_data: *mut c_void,
+    node: u8,
+    cmd_class: u8,
+    cmd: u8,
+    payload: *const u8,
+    len: usize,
+);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The user data is only handed back to the callback
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+/// Run the function, store its error or panic as the last error and
+/// convert the result into a status code.
+fn guard<F>(f: F) -> c_int
+where
+    F: FnOnce() -> Result<(), Error>,
+{
+    let result = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(_) => Err(Error::new(ErrorKind::UnknownZWave, "rzw panicked")),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// Store the error, to return it by `rzw_last_error`
+fn set_last_error(error: Error) {
+    let text = CString::new(error.to_string().replace('\0', ""))
+        .unwrap_or_else(|_| CString::new("unknown error").unwrap());
+
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(text));
+}
+
+/// Return the controller behind the handle, which has to be null or valid
+unsafe fn controller<'a>(handle: *mut RzwController) -> Result<&'a mut RzwController, Error> {
+    handle
+        .as_mut()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The controller handle is null"))
+}
+
+/// Run the function with a node of the controller, the handle has to be
+/// null or valid
+unsafe fn with_node<F>(handle: *mut RzwController, node: u8, f: F) -> c_int
+where
+    F: FnOnce(&super::Node<SerialDriver>) -> Result<(), Error>,
+{
+    guard(|| {
+        let node = controller(handle)?.node(node).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} is unknown", node),
+            )
+        })?;
+        f(&node)
+    })
+}
+
+/// Return the reason of the last failure on this thread, or null when nothing
+/// failed. The text is valid until the next call into rzw on this thread.
+#[no_mangle]
+pub extern "C" fn rzw_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match *e.borrow() {
+        Some(ref text) => text.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Open the Z-Wave controller at the serial port and discover its nodes.
+/// Returns null on failure.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_open(path: *const c_char) -> *mut RzwController {
+    let mut handle = ptr::null_mut();
+
+    guard(|| {
+        if path.is_null() {
+            return Err(Error::new(ErrorKind::InvalidInput, "The path is null"));
+        }
+
+        let path = CStr::from_ptr(path)
+            .to_str()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "The path is not valid UTF-8"))?;
+
+        let controller = Controller::new(SerialDriver::new(path)?)?;
+        handle = Box::into_raw(Box::new(controller));
+        Ok(())
+    });
+
+    handle
+}
+
+/// Close the controller and free the handle.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_close(handle: *mut RzwController) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Write up to `len` node ids into `nodes`. Returns the amount of nodes in the
+/// network, which can be more than `len`, or `-1` on failure.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_nodes(
+    handle: *mut RzwController,
+    nodes: *mut u8,
+    len: usize,
+) -> c_int {
+    let mut count = 0;
+
+    let status = guard(|| {
+        let ids = controller(handle)?.nodes();
+        if !nodes.is_null() {
+            let out = slice::from_raw_parts_mut(nodes, len);
+            for (o, id) in out.iter_mut().zip(ids.iter()) {
+                *o = *id;
+            }
+        }
+
+        count = ids.len() as c_int;
+        Ok(())
+    });
+
+    if status == 0 {
+        count
+    } else {
+        status
+    }
+}
+
+/// Set the basic value of the node.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_basic_set(handle: *mut RzwController, node: u8, value: u8) -> c_int {
+    with_node(handle, node, |n| n.basic_set(value).map(|_| ()))
+}
+
+/// Read the basic value of the node into `value`.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_basic_get(
+    handle: *mut RzwController,
+    node: u8,
+    value: *mut u8,
+) -> c_int {
+    with_node(handle, node, |n| write_out(value, n.basic_get()?))
+}
+
+/// Turn the binary switch of the node on (`value != 0`) or off.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_switch_binary_set(
+    handle: *mut RzwController,
+    node: u8,
+    value: u8,
+) -> c_int {
+    with_node(handle, node, |n| {
+        n.switch_binary_set(value != 0).map(|_| ())
+    })
+}
+
+/// Read the state of the binary switch into `value`, `1` is on and `0` is off.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_switch_binary_get(
+    handle: *mut RzwController,
+    node: u8,
+    value: *mut u8,
+) -> c_int {
+    with_node(handle, node, |n| {
+        write_out(value, n.switch_binary_get()? as u8)
+    })
+}
+
+/// Set the level of the multilevel switch to 0-99 or 0xFF.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_switch_multilevel_set(
+    handle: *mut RzwController,
+    node: u8,
+    value: u8,
+) -> c_int {
+    with_node(handle, node, |n| {
+        n.switch_multilevel_set(value, None).map(|_| ())
+    })
+}
+
+/// Read the level of the multilevel switch into `value`.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_switch_multilevel_get(
+    handle: *mut RzwController,
+    node: u8,
+    value: *mut u8,
+) -> c_int {
+    with_node(handle, node, |n| {
+        write_out(value, n.switch_multilevel_get()?)
+    })
+}
+
+/// Call the callback on a background thread for each command, which is
+/// received from a node. The user data is handed to the callback unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn rzw_set_event_callback(
+    handle: *mut RzwController,
+    callback: Option<RzwEventCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(|| {
+        let callback =
+            callback.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The callback is null"))?;
+        let user_data = UserData(user_data);
+        // the segments of a datagram are collected across the messages
+        let reassembly = Mutex::new(Reassembly::new());
+
+        controller(handle)?.handle_messages(Box::new(move |msg| {
+            if msg.func != SerialMsgFunction::ApplicationCommandHandler {
+                return;
+            }
+
+            // the commands are unwrapped like the answers of the nodes
+            let command = IncomingFrame::parse(&msg.data)
+                .and_then(|frame| Encapsulation::unwrap(frame, &mut reassembly.lock().unwrap()));
+            if let Ok(Some(command)) = command {
+                let frame = command.frame;
+                callback(
+                    user_data.0,
+                    frame.source,
+                    frame.command_class_id(),
+                    frame.command,
+                    frame.payload.as_ptr(),
+                    frame.payload.len(),
+                );
+            }
+        }));
+
+        Ok(())
+    })
+}
+
+/// Write the value to the pointer of the caller, which has to be null or valid
+unsafe fn write_out(out: *mut u8, value: u8) -> Result<(), Error> {
+    match out.as_mut() {
+        Some(out) => {
+            *out = value;
+            Ok(())
+        }
+        None => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The value pointer is null",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        unsafe {
+            assert_eq!(rzw_nodes(ptr::null_mut(), ptr::null_mut(), 0), -1);
+            let error = CStr::from_ptr(rzw_last_error());
+            assert_eq!(error.to_str().unwrap(), "The controller handle is null");
+
+            let path = CString::new("/dev/rzw-does-not-exist").unwrap();
+            assert!(rzw_open(path.as_ptr()).is_null());
+            assert!(!rzw_last_error().is_null());
+
+            let mut value = 0;
+            assert_eq!(rzw_basic_get(ptr::null_mut(), 2, &mut value), -1);
+        }
+    }
+}
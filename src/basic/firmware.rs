@@ -0,0 +1,315 @@
+//! Firmware updates of the nodes
+//!
+//! Transfers a firmware image to a node with the Firmware Update Meta Data
+//! Command Class. The node requests the fragments of the image on its own,
+//! the controller only answers the requests. When no request arrives in
+//! time, the last fragment is sent again, since it may got lost.
+
+use super::{Controller, Node, NodeProgress, Operation, ProgressEvent, ProgressTracker};
+use crate::cmds::crc16::Crc16;
+use crate::cmds::firmware_update::{FirmwareRequestStatus, FirmwareStatus, FirmwareUpdate};
+use crate::cmds::{CommandClass, Message};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind};
+
+use std::cmp;
+use std::time::Duration;
+
+/// The fragment size for the nodes, which don't report their maximum. It
+/// also fits into a frame, when the node supports a bigger one.
+const FRAGMENT_SIZE: usize = 40;
+
+/// How long the controller waits for the next request of the node
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the last fragment is sent again, before the update fails
+const MAX_RETRANSMISSIONS: u8 = 3;
+
+/// Splits a firmware image into the fragments, which the node requests.
+#[derive(Debug, Clone)]
+pub struct FirmwareTransfer {
+    image: Vec<u8>,
+    fragment_size: usize,
+    /// Version 1 nodes don't expect a checksum for the fragments
+    checksum: bool,
+}
+
+impl FirmwareTransfer {
+    /// Prepare the transfer of the image with the given fragment size.
+    pub fn new(image: &[u8], fragment_size: usize, checksum: bool) -> FirmwareTransfer {
+        FirmwareTransfer {
+            image: image.to_vec(),
+            fragment_size: cmp::max(fragment_size, 1),
+            checksum,
+        }
+    }
+
+    /// Return the checksum of the whole image
+    pub fn checksum(&self) -> u16 {
+        Crc16::checksum(&self.image)
+    }
+
+    /// Return the amount of fragments
+    pub fn fragments(&self) -> u16 {
+        self.image.len().div_ceil(self.fragment_size) as u16
+    }
+
+    /// Return the message with the fragment, the fragments are numbered
+    /// from 1. Returns `None` for numbers behind the last fragment and an
+    /// error, when the fragment doesn't fit into a frame.
+    pub fn fragment(&self, node_id: u8, number: u16) -> Result<Option<Message>, Error> {
+        if number == 0 || number > self.fragments() {
+            return Ok(None);
+        }
+
+        let start = (number as usize - 1) * self.fragment_size;
+        let end = cmp::min(start + self.fragment_size, self.image.len());
+
+        FirmwareUpdate::report(
+            node_id,
+            number,
+            number == self.fragments(),
+            &self.image[start..end],
+            self.checksum,
+        )
+        .map(Some)
+    }
+}
+
+impl<D> Controller<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Update the firmware of a node over the air.
+    ///
+    /// The target 0 is the firmware of the Z-Wave chip, the other targets
+    /// are listed in the metadata of the node. The progress of the transfer
+    /// is reported to the listeners. Sleeping nodes need to be woken up
+    /// before, they stay awake until the update is done. Returns the result,
+    /// which the node reported at the end.
+    pub fn update_node_firmware<I>(
+        &self,
+        id: I,
+        image: &[u8],
+        target: u8,
+    ) -> Result<FirmwareStatus, Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+        let node = self
+            .nodes
+            .borrow()
+            .iter()
+            .find(|n| n.get_id() == id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The node {} doesn't exist", id),
+                )
+            })?;
+
+        let metadata = node.firmware_metadata_get()?;
+        if !metadata.upgradable {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The firmware of the node {} isn't upgradable", id),
+            ));
+        }
+
+        // the fragments of version 1 nodes have no checksum
+        let version = node
+            .command_class_version_get(CommandClass::FIRMWARE_UPDATE_MD)
+            .unwrap_or(1);
+        let fragment_size = metadata
+            .max_fragment_size
+            .map(|s| cmp::min(s as usize, FRAGMENT_SIZE))
+            .unwrap_or(FRAGMENT_SIZE);
+        let transfer = FirmwareTransfer::new(image, fragment_size, version >= 2);
+
+        let status =
+            FirmwareUpdate::request_report(&node.request(FirmwareUpdate::request_get(
+                id,
+                &metadata,
+                transfer.checksum(),
+                target,
+                fragment_size as u16,
+            ))?)?;
+        if status != FirmwareRequestStatus::Accepted {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} refused the update: {:?}", id, status),
+            ));
+        }
+
+        let mut tracker = ProgressTracker::new(Operation::FirmwareUpdate, &[id]);
+        let running = tracker.update(id, NodeProgress::Running, "transfer");
+        self.progress.borrow().notify(&running);
+
+        // the node stays awake, until the update is done
+        self.awake.lock().unwrap().set_awake(id, true);
+        let result = self.transfer_firmware(&node, &transfer, &running);
+        self.awake.lock().unwrap().set_awake(id, false);
+
+        let status = match result {
+            Ok(status) if status.is_success() => NodeProgress::Done,
+            _ => NodeProgress::Failed,
+        };
+        self.progress
+            .borrow()
+            .notify(&tracker.update(id, status, "transfer"));
+
+        result
+    }
+
+    /// Answer the requests of the node, until it reports the result of the
+    /// update. The commands of the other nodes are dropped meanwhile.
+    fn transfer_firmware(
+        &self,
+        node: &Node<D>,
+        transfer: &FirmwareTransfer,
+        running: &ProgressEvent,
+    ) -> Result<FirmwareStatus, Error> {
+        let id = node.get_id();
+        let mut last = None;
+        let mut retransmissions = 0;
+
+        loop {
+            let security = node.security.clone();
+            let frame = match self
+                .driver
+                .run(move |d| Node::read_command(d, id, Some(REQUEST_TIMEOUT), &security))
+            {
+                Ok(command) => command.frame,
+                Err(Error::Timeout { .. }) if retransmissions < MAX_RETRANSMISSIONS => {
+                    retransmissions += 1;
+                    if let Some(number) = last {
+                        if let Some(fragment) = transfer.fragment(id, number)? {
+                            node.send(fragment)?;
+                        }
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            if frame.command_class != Ok(CommandClass::FIRMWARE_UPDATE_MD) {
+                continue;
+            }
+
+            match frame.command {
+                // the node requests the next fragments, or the lost ones again
+                0x05 => {
+                    retransmissions = 0;
+                    let (count, first) = FirmwareUpdate::get(&frame)?;
+                    for number in first..first.saturating_add(count as u16) {
+                        match transfer.fragment(id, number)? {
+                            Some(fragment) => node.send(fragment)?,
+                            None => break,
+                        };
+                        last = Some(number);
+                    }
+
+                    let sent = last.unwrap_or(0) as usize;
+                    self.progress.borrow().notify(&ProgressEvent {
+                        percent: (sent * 100 / cmp::max(transfer.fragments() as usize, 1)) as u8,
+                        ..running.clone()
+                    });
+                }
+                0x07 => return Ok(FirmwareUpdate::status_report(&frame)?.0),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::network;
+    use crate::basic::sim::SimNode;
+    use crate::defs::GenericType;
+
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn fragments() {
+        let image: Vec<u8> = (0..100).collect();
+        let transfer = FirmwareTransfer::new(&image, 40, false);
+        assert_eq!(transfer.fragments(), 3);
+        assert!(transfer.fragment(5, 0).unwrap().is_none());
+        assert!(transfer.fragment(5, 4).unwrap().is_none());
+
+        let first = transfer.fragment(5, 1).unwrap().unwrap();
+        assert_eq!(first.data[..3], [0x00, 0x01, 0x00]);
+        assert_eq!(first.data.len(), 42);
+
+        // the last fragment is flagged and shorter
+        let last = transfer.fragment(5, 3).unwrap().unwrap();
+        assert_eq!(last.data[..3], [0x80, 0x03, 80]);
+        assert_eq!(last.data.len(), 22);
+
+        // the checksum is added
+        let transfer = FirmwareTransfer::new(&image, 40, true);
+        assert_eq!(transfer.fragment(5, 3).unwrap().unwrap().data.len(), 24);
+    }
+
+    #[test]
+    fn firmware_update() {
+        let (sim, controller) = network(vec![SimNode::new(7, GenericType::BinarySensor)
+            .command_classes(&[CommandClass::FIRMWARE_UPDATE_MD, CommandClass::VERSION])
+            .respond(
+                CommandClass::FIRMWARE_UPDATE_MD,
+                0x01,
+                0x02,
+                &[0x01, 0x0F, 0x02, 0x01, 0xAB, 0xCD, 0xFF, 0x00, 0x00, 0x20],
+            )
+            .respond(CommandClass::FIRMWARE_UPDATE_MD, 0x03, 0x04, &[0xFF])
+            .respond(CommandClass::VERSION, 0x13, 0x14, &[0x7A, 0x03])]);
+        let (tx, events) = mpsc::channel();
+        controller
+            .progress()
+            .on_progress(move |e| tx.send(e.percent).unwrap());
+
+        // the node requests the fragments, after it accepted the update
+        let node = sim.clone();
+        let fragments = move || {
+            node.received(7)
+                .iter()
+                .filter(|f| f[..2] == [0x7A, 0x06])
+                .count()
+        };
+        let remote = thread::spawn(move || {
+            while !sim.received(7).iter().any(|f| f[..2] == [0x7A, 0x03]) {
+                thread::sleep(Duration::from_millis(10));
+            }
+            sim.report(
+                7,
+                CommandClass::FIRMWARE_UPDATE_MD,
+                0x05,
+                &[0x02, 0x00, 0x01],
+            );
+            while fragments() < 2 {
+                thread::sleep(Duration::from_millis(10));
+            }
+            sim.report(7, CommandClass::FIRMWARE_UPDATE_MD, 0x07, &[0xFF]);
+            sim
+        });
+
+        let image: Vec<u8> = (0..50).collect();
+        assert_eq!(
+            controller.update_node_firmware(7, &image, 0),
+            Ok(FirmwareStatus::Success)
+        );
+
+        // the fragments have 32 bytes and a checksum
+        let sim = remote.join().unwrap();
+        let received = sim.received(7);
+        let last = received.last().unwrap();
+        assert_eq!(last[..4], [0x7A, 0x06, 0x80, 0x02]);
+        assert_eq!(last.len(), 4 + 18 + 2);
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), vec![0, 100, 100]);
+    }
+}
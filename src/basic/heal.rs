@@ -0,0 +1,198 @@
+//! Network heal
+//!
+//! The routes through the mesh get worse, when nodes are moved, added or
+//! removed. A heal lets the nodes search for their neighbors again, so the
+//! controller can calculate new routes, and sends the nodes new return
+//! routes to the controller and to the targets of their associations.
+
+use super::{Controller, NodeProgress, Operation, ProgressTracker};
+use crate::driver::Driver;
+use crate::error::Error;
+
+/// The result of a heal of the network.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HealReport {
+    pub healed: Vec<u8>,
+    /// The nodes which couldn't be healed, like the sleeping ones
+    pub failed: Vec<u8>,
+}
+
+impl<D> Controller<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Heal the routes of the node. The node updates its neighbors and gets
+    /// new return routes, when `interview` is set it's interviewed again
+    /// afterwards. The steps are reported to the progress listeners.
+    ///
+    /// A sleeping node has to be awake for it.
+    pub fn heal_node<I>(&self, id: I, interview: bool) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+        let mut tracker = ProgressTracker::new(Operation::Heal, &[id]);
+
+        let result = self.heal(id, interview, &mut tracker);
+        let status = match result {
+            Ok(()) => NodeProgress::Done,
+            Err(_) => NodeProgress::Failed,
+        };
+        self.progress
+            .borrow()
+            .notify(&tracker.update(id, status, "healed"));
+
+        result
+    }
+
+    /// Heal the routes of all nodes, one after another. The nodes which
+    /// failed don't stop the heal, they are part of the report.
+    ///
+    /// The neighbors of all nodes are updated first, so the return routes
+    /// are calculated from the new neighbors.
+    pub fn heal_network(&self, interview: bool) -> Result<HealReport, Error> {
+        let ids: Vec<u8> = self
+            .nodes()
+            .into_iter()
+            .filter(|id| *id != self.info.node_id)
+            .collect();
+        let mut tracker = ProgressTracker::new(Operation::Heal, &ids);
+        let mut report = HealReport::default();
+
+        for &id in &ids {
+            self.progress.borrow().notify(&tracker.update(
+                id,
+                NodeProgress::Running,
+                "neighbor update",
+            ));
+
+            if self.request_neighbor_update(id).is_err() {
+                report.failed.push(id);
+                self.progress.borrow().notify(&tracker.update(
+                    id,
+                    NodeProgress::Failed,
+                    "neighbor update",
+                ));
+            }
+        }
+
+        let reachable: Vec<u8> = ids
+            .iter()
+            .filter(|id| !report.failed.contains(id))
+            .cloned()
+            .collect();
+        for id in reachable {
+            let status = match self.heal_routes(id, interview, &mut tracker) {
+                Ok(()) => {
+                    report.healed.push(id);
+                    NodeProgress::Done
+                }
+                Err(_) => {
+                    report.failed.push(id);
+                    NodeProgress::Failed
+                }
+            };
+            self.progress
+                .borrow()
+                .notify(&tracker.update(id, status, "healed"));
+        }
+
+        report.failed.sort();
+        Ok(report)
+    }
+
+    /// Update the neighbors and the routes of the node
+    fn heal(&self, id: u8, interview: bool, tracker: &mut ProgressTracker) -> Result<(), Error> {
+        self.progress.borrow().notify(&tracker.update(
+            id,
+            NodeProgress::Running,
+            "neighbor update",
+        ));
+        self.request_neighbor_update(id)?;
+
+        self.heal_routes(id, interview, tracker)
+    }
+
+    /// Replace the return routes of the node and interview it again
+    fn heal_routes(
+        &self,
+        id: u8,
+        interview: bool,
+        tracker: &mut ProgressTracker,
+    ) -> Result<(), Error> {
+        self.progress
+            .borrow()
+            .notify(&tracker.update(id, NodeProgress::Running, "return routes"));
+        self.delete_return_routes(id)?;
+        for destination in self.return_route_destinations(id) {
+            self.assign_return_route(id, destination)?;
+        }
+
+        if interview {
+            self.progress
+                .borrow()
+                .notify(&tracker.update(id, NodeProgress::Running, "interview"));
+            self.interview_node(id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the controller and the targets of the cached associations of
+    /// the node, which need a return route
+    fn return_route_destinations(&self, id: u8) -> Vec<u8> {
+        let mut destinations = vec![self.info.node_id];
+
+        for group in self
+            .associations
+            .borrow()
+            .groups()
+            .iter()
+            .filter(|g| g.node == id)
+        {
+            let targets = group
+                .nodes
+                .iter()
+                .cloned()
+                .chain(group.endpoints.iter().map(|e| e.0));
+            for target in targets {
+                if target != id && !destinations.contains(&target) {
+                    destinations.push(target);
+                }
+            }
+        }
+
+        destinations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basic::sim::fixture::{network, switch};
+    use crate::basic::sim::SIM_CONTROLLER_ID;
+
+    #[test]
+    fn heal() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let (sim, controller) = network(vec![switch(2), switch(3), switch(4).sleeping()]);
+        let events = Rc::new(RefCell::new(vec![]));
+        let e = events.clone();
+        controller
+            .progress()
+            .on_progress(move |event| e.borrow_mut().push(event.percent));
+
+        // the sleeping node can't be reached
+        let report = controller.heal_network(false).unwrap();
+        assert_eq!(report.healed, vec![2, 3]);
+        assert_eq!(report.failed, vec![4]);
+        assert_eq!(sim.return_routes(2), vec![SIM_CONTROLLER_ID]);
+        assert_eq!(events.borrow().last(), Some(&100));
+
+        sim.wake_up(4);
+        controller.heal_node(4, true).unwrap();
+        assert_eq!(sim.return_routes(4), vec![SIM_CONTROLLER_ID]);
+    }
+}
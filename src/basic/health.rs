@@ -0,0 +1,326 @@
+//! Node health check
+//!
+//! Checks how well a node is connected to the controller. The node gets
+//! pinged a few times, tests at which power level it still reaches the
+//! controller and the result is rated with a score between 0 and 10.
+
+use super::Node;
+use crate::cmds::crc16::Crc16;
+use crate::cmds::powerlevel::{PowerLevel, PowerLevelOperationStatus, PowerLevelStatus};
+use crate::cmds::CommandClass;
+use crate::defs::TransmitStatus;
+use crate::driver::Driver;
+use crate::error::{Error, Stage};
+
+use std::time::{Duration, Instant};
+use std::{cmp, thread};
+
+/// Amount of pings which are sent to the node
+const PINGS: u8 = 10;
+
+/// Amount of frames the node sends for each power level test
+const TEST_FRAMES: u16 = 10;
+
+/// The time to listen for the report of a finished power level test,
+/// before the result is requested
+const TEST_LISTEN: Duration = Duration::from_millis(500);
+
+/// The most times the result of a power level test is requested
+const TEST_ROUNDS: u8 = 20;
+
+/// The power levels which get tested, from strong to weak
+const TEST_LEVELS: [PowerLevelStatus; 4] = [
+    PowerLevelStatus::NormalPower,
+    PowerLevelStatus::minus3dBm,
+    PowerLevelStatus::minus6dBm,
+    PowerLevelStatus::minus9dBm,
+];
+
+/// The result of a node health check.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HealthReport {
+    pub pings: u8,
+    pub failed_pings: u8,
+    /// Average latency of the successful pings
+    pub latency: Option<Duration>,
+    /// The weakest power level at which all test frames reached the
+    /// controller, `None` when the node can't test its power level
+    pub min_power_level: Option<PowerLevelStatus>,
+    pub neighbors: Vec<u8>,
+    /// The repeaters of the last successful ping, empty for a direct connection
+    pub route: Vec<u8>,
+    /// The rating from 0 (not reachable) to 10 (perfect)
+    pub score: u8,
+}
+
+impl HealthReport {
+    /// Rate the connection of a node from 0 to 10.
+    pub fn rate(
+        pings: u8,
+        failed_pings: u8,
+        latency: Option<Duration>,
+        min_power_level: Option<PowerLevelStatus>,
+        neighbors: usize,
+    ) -> u8 {
+        // a node which isn't reachable at all
+        if failed_pings >= pings {
+            return 0;
+        }
+
+        let mut score = 10 - cmp::min(2 * failed_pings as i32, 8);
+
+        // slow connections
+        score -= match latency.map(|l| l.as_millis()).unwrap_or(0) {
+            0..=50 => 0,
+            51..=100 => 1,
+            101..=250 => 2,
+            251..=500 => 3,
+            _ => 4,
+        };
+
+        // a node with few neighbors has no alternative routes
+        if neighbors <= 2 {
+            score -= 1;
+        }
+
+        // the node needs a strong signal to reach the controller
+        if let Some(level) = min_power_level {
+            if (level as u8) < PowerLevelStatus::minus6dBm as u8 {
+                score -= 1;
+            }
+        }
+
+        cmp::max(score, 1) as u8
+    }
+}
+
+impl<D> Node<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Check the health of the connection between the node and the controller.
+    ///
+    /// This sends a series of pings, lets the node send test frames at
+    /// different power levels and reads the neighbors of the node. The
+    /// check takes a while, especially for badly connected nodes.
+    pub fn health_check(&self) -> Result<HealthReport, Error> {
+        let mut failed_pings = 0;
+        let mut total = Duration::from_millis(0);
+        let mut route = vec![];
+
+        // ping the node with no operation messages
+        for _ in 0..PINGS {
+            let start = Instant::now();
+            let report = self.ping_report()?;
+
+            if report.status != TransmitStatus::CompleteOk {
+                failed_pings += 1;
+                continue;
+            }
+
+            // prefer the transmit time measured by the controller
+            total += report.time.unwrap_or_else(|| start.elapsed());
+            route = report.repeaters;
+        }
+
+        let latency = match PINGS - failed_pings {
+            0 => None,
+            ok => Some(total / ok as u32),
+        };
+
+        // test the power levels until the frames don't arrive anymore
+        let mut min_power_level = None;
+        if failed_pings < PINGS && self.cmds.contains(&CommandClass::POWER_LEVEL) {
+            let controller = self.driver.run(|d| d.get_controller_id())?.1;
+
+            for level in TEST_LEVELS.iter() {
+                self.powerlevel_test_node_set(controller, *level, TEST_FRAMES)?;
+
+                if self.powerlevel_test_wait()? < TEST_FRAMES {
+                    break;
+                }
+                min_power_level = Some(*level);
+            }
+        }
+
+        let id = self.id;
+        let neighbors = self.driver.run(move |d| d.get_neighbors(id))?;
+
+        Ok(HealthReport {
+            pings: PINGS,
+            failed_pings,
+            latency,
+            min_power_level,
+            score: HealthReport::rate(
+                PINGS,
+                failed_pings,
+                latency,
+                min_power_level,
+                neighbors.len(),
+            ),
+            neighbors,
+            route,
+        })
+    }
+
+    /// Start a power level test and deliver its result to the callback, as
+    /// soon as the test is done. The nodes, which report the end of the test
+    /// on their own, are heard right away. The result of the others is
+    /// requested until the test isn't in progress anymore.
+    ///
+    /// ```rust,ignore
+    /// node.powerlevel_test_node_watch(1, PowerLevelStatus::minus3dBm, 10u16, |result| {
+    ///     println!("{:?}", result);
+    /// })?;
+    /// ```
+    pub fn powerlevel_test_node_watch<T, L, F, C>(
+        &self,
+        test_node_id: T,
+        level: L,
+        test_frames: F,
+        callback: C,
+    ) -> Result<(), Error>
+    where
+        T: Into<u8>,
+        L: Into<PowerLevelStatus>,
+        F: Into<u16>,
+        C: FnOnce(Result<(u8, PowerLevelOperationStatus, u16), Error>) + Send + 'static,
+    {
+        self.powerlevel_test_node_set(test_node_id, level, test_frames)?;
+
+        let node = self.clone();
+        thread::spawn(move || callback(node.powerlevel_test_result()));
+        Ok(())
+    }
+
+    /// Wait until the power level test is done and return the amount
+    /// of acknowledged frames.
+    fn powerlevel_test_wait(&self) -> Result<u16, Error> {
+        match self.powerlevel_test_result() {
+            Ok((_, _, frames)) => Ok(frames),
+            Err(Error::Timeout { .. }) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Wait until the power level test is done and return its result.
+    fn powerlevel_test_result(&self) -> Result<(u8, PowerLevelOperationStatus, u16), Error> {
+        for _ in 0..TEST_ROUNDS {
+            if let Some(result) = self.powerlevel_test_listen()? {
+                if result.1 != PowerLevelOperationStatus::TestInProgress {
+                    return Ok(result);
+                }
+            }
+
+            // the node stayed silent, so ask for the result
+            let result = self.powerlevel_test_node_get()?;
+            if result.1 != PowerLevelOperationStatus::TestInProgress {
+                return Ok(result);
+            }
+        }
+
+        Err(Error::Timeout {
+            stage: Stage::Report,
+        })
+    }
+
+    /// Listen for the test report, which the node sends on its own. The
+    /// commands of the other nodes are dropped meanwhile.
+    fn powerlevel_test_listen(
+        &self,
+    ) -> Result<Option<(u8, PowerLevelOperationStatus, u16)>, Error> {
+        let id = self.id;
+        let security = self.security.clone();
+
+        self.driver.run(move |d| {
+            let deadline = Instant::now() + TEST_LISTEN;
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let frame = match Node::wait_frame(d, remaining) {
+                    Ok(frame) => Crc16::unwrap(frame)?,
+                    Err(Error::Timeout { .. }) => return Ok(None),
+                    Err(e) => return Err(e),
+                };
+
+                if let Some(frame) = Node::decrypt_frame(d, frame, &security)? {
+                    if frame.source == id
+                        && frame.command_class == Ok(CommandClass::POWER_LEVEL)
+                        && frame.command == 0x06
+                    {
+                        return PowerLevel::test_node_report(&frame).map(Some);
+                    }
+                }
+
+                if Instant::now() >= deadline {
+                    return Ok(None);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::network;
+    use crate::basic::sim::SimNode;
+    use crate::defs::GenericType;
+
+    use std::sync::mpsc;
+
+    #[test]
+    fn rate_connections() {
+        let fast = Some(Duration::from_millis(20));
+
+        // perfect and not reachable nodes
+        assert_eq!(
+            HealthReport::rate(10, 0, fast, Some(PowerLevelStatus::minus9dBm), 5),
+            10
+        );
+        assert_eq!(HealthReport::rate(10, 10, None, None, 5), 0);
+
+        // failed pings, a slow connection and few neighbors
+        assert_eq!(
+            HealthReport::rate(10, 2, Some(Duration::from_millis(300)), None, 1),
+            2
+        );
+
+        // a reachable node keeps at least a score of 1
+        assert_eq!(
+            HealthReport::rate(10, 9, Some(Duration::from_secs(2)), None, 0),
+            1
+        );
+    }
+
+    #[cfg(feature = "cc-powerlevel")]
+    #[test]
+    fn powerlevel_test_watch() {
+        use crate::cmds::powerlevel::{PowerLevelOperationStatus, PowerLevelStatus};
+
+        // the node reports the end of the test on its own
+        let (sim, mut controller) = network(vec![SimNode::new(5, GenericType::BinarySwitch)
+            .command_classes(&[CommandClass::POWER_LEVEL])
+            .respond(
+                CommandClass::POWER_LEVEL,
+                0x04,
+                0x06,
+                &[0x01, 0x01, 0x00, 0x0A],
+            )]);
+        let (tx, rx) = mpsc::channel();
+        controller
+            .node(5)
+            .unwrap()
+            .powerlevel_test_node_watch(1, PowerLevelStatus::minus3dBm, 10u16, move |result| {
+                tx.send(result).unwrap();
+            })
+            .unwrap();
+
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            Ok((1, PowerLevelOperationStatus::TestSuccess, 10))
+        );
+        // the result wasn't requested
+        assert!(sim.received(5).iter().all(|f| f.get(1) != Some(&0x05)));
+    }
+}
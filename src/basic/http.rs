@@ -0,0 +1,410 @@
+//! HTTP/JSON control interface
+//!
+//! A small HTTP server, which exposes the controller over a REST interface
+//! with JSON bodies. It's built with the `http` feature and handles one
+//! request after another, on the thread which calls `serve`.
+//!
+//! | Method | Path                               | Body                |
+//! |--------|------------------------------------|---------------------|
+//! | GET    | `/nodes`                           |                     |
+//! | GET    | `/nodes/<id>`                      |                     |
+//! | GET    | `/nodes/<id>/basic`                |                     |
+//! | PUT    | `/nodes/<id>/basic`                | `{"value": 255}`    |
+//! | GET    | `/nodes/<id>/switch_binary`        |                     |
+//! | PUT    | `/nodes/<id>/switch_binary`        | `{"value": true}`   |
+//! | GET    | `/nodes/<id>/switch_multilevel`    |                     |
+//! | PUT    | `/nodes/<id>/switch_multilevel`    | `{"value": 50}`     |
+//! | POST   | `/inclusion`                       |                     |
+
+use super::{Controller, Node, NodeState};
+use crate::defs::{CommandClass, NodeCapabilities, SecurityClass};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind};
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// The largest body which is accepted
+const MAX_BODY: usize = 64 * 1024;
+
+/// The time the inclusion waits for a node
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The information about a node, which is returned by the server.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct NodeSummary {
+    pub id: u8,
+    pub commands: Vec<CommandClass>,
+    pub capabilities: Option<NodeCapabilities>,
+    pub security_class: SecurityClass,
+    pub state: NodeState,
+}
+
+/// A parsed HTTP request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Read the request line, the headers and the body from the stream.
+    pub fn read<R: BufRead>(reader: &mut R) -> Result<Request, Error> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        // `GET /nodes HTTP/1.1`
+        let mut parts = line.split_whitespace();
+        let (method, path) = match (parts.next(), parts.next()) {
+            (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "The request line is invalid",
+                ))
+            }
+        };
+
+        // only the length of the body is of interest
+        let mut length = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+
+            let mut header = line.splitn(2, ':');
+            if let (Some(name), Some(value)) = (header.next(), header.next()) {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().map_err(|_| {
+                        Error::new(ErrorKind::InvalidInput, "The content length is invalid")
+                    })?;
+                }
+            }
+        }
+
+        if length > MAX_BODY {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The request body is too large",
+            ));
+        }
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+
+        Ok(Request { method, path, body })
+    }
+
+    /// Return the value of the JSON body `{"value": ...}`.
+    fn value(&self) -> Result<serde_json::Value, Error> {
+        let json: serde_json::Value = serde_json::from_slice(&self.body)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+
+        json.get("value")
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The body needs to contain a value"))
+    }
+
+    /// Return the value of the body as a level between 0 and 255.
+    fn level(&self) -> Result<u8, Error> {
+        match self.value()? {
+            serde_json::Value::Bool(on) => Ok(if on { 0xFF } else { 0x00 }),
+            value => value
+                .as_u64()
+                .filter(|v| *v <= 0xFF)
+                .map(|v| v as u8)
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        "The value needs to be a number between 0 and 255",
+                    )
+                }),
+        }
+    }
+}
+
+/// Return the HTTP status code for the error.
+pub fn status(err: &Error) -> u16 {
+    match *err {
+        Error::InvalidInput(_) => 400,
+        Error::UnsupportedCommandClass(_) | Error::NotImplemented(_) => 501,
+        Error::NoController(_) => 503,
+        Error::Timeout { .. } => 504,
+        _ => 502,
+    }
+}
+
+/// Serves the controller over HTTP.
+#[derive(Debug)]
+pub struct HttpServer<D>
+where
+    D: Driver,
+{
+    controller: Controller<D>,
+}
+
+impl<D> HttpServer<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create a server for the controller.
+    pub fn new(controller: Controller<D>) -> HttpServer<D> {
+        HttpServer { controller }
+    }
+
+    /// Listen on the address and handle the requests, until the listener fails.
+    pub fn serve<A: ToSocketAddrs>(&mut self, addr: A) -> Result<(), Error> {
+        let listener = TcpListener::bind(addr)?;
+
+        // a failed connection doesn't stop the server
+        for stream in listener.incoming().flatten() {
+            let _ = self.handle_stream(stream);
+        }
+
+        Ok(())
+    }
+
+    /// Read the request from the stream and write the response.
+    fn handle_stream(&mut self, stream: TcpStream) -> Result<(), Error> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let (code, body) = match Request::read(&mut reader) {
+            Ok(request) => self.handle(&request),
+            Err(err) => (status(&err), error_body(&err)),
+        };
+
+        write_response(stream, code, &body)?;
+        Ok(())
+    }
+
+    /// Handle the request and return the status code and the JSON body.
+    pub fn handle(&mut self, request: &Request) -> (u16, String) {
+        let path: Vec<&str> = request.path.split('/').filter(|p| !p.is_empty()).collect();
+
+        let result = match (request.method.as_str(), path.as_slice()) {
+            ("GET", ["nodes"]) => self.nodes().and_then(to_json),
+            ("GET", ["nodes", id]) => self.node(id).and_then(to_json),
+            ("GET", ["nodes", id, value]) => self.get(id, value),
+            ("PUT", ["nodes", id, value]) | ("POST", ["nodes", id, value]) => {
+                self.set(id, value, request)
+            }
+            ("POST", ["inclusion"]) => self.include().and_then(to_json),
+            _ => return (404, r#"{"error":"Not found"}"#.to_string()),
+        };
+
+        match result {
+            Ok(body) => (200, body),
+            Err(err) => (status(&err), error_body(&err)),
+        }
+    }
+
+    /// Return the summaries of all nodes.
+    fn nodes(&mut self) -> Result<Vec<NodeSummary>, Error> {
+        let mut nodes = vec![];
+        for id in self.controller.nodes() {
+            nodes.push(self.summary(id)?);
+        }
+
+        Ok(nodes)
+    }
+
+    /// Return the summary of the node with the id of the path.
+    fn node(&mut self, id: &str) -> Result<NodeSummary, Error> {
+        self.summary(node_id(id)?)
+    }
+
+    /// Add a node to the network and return its summary.
+    fn include(&mut self) -> Result<NodeSummary, Error> {
+        let node = self.controller.add_node(INCLUSION_TIMEOUT)?;
+        self.summary(node.id)
+    }
+
+    /// Return the summary of a node.
+    fn summary(&mut self, id: u8) -> Result<NodeSummary, Error> {
+        let node = self.lookup(id)?;
+
+        Ok(NodeSummary {
+            id,
+            commands: node.get_commands(),
+            capabilities: node.capabilities(),
+            security_class: node.security_class(),
+            state: self.controller.node_state(id),
+        })
+    }
+
+    /// Return the node with the given id.
+    fn lookup(&mut self, id: u8) -> Result<Node<D>, Error> {
+        self.controller.node(id).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} doesn't exist", id),
+            )
+        })
+    }
+
+    /// Read a value of a node.
+    fn get(&mut self, id: &str, value: &str) -> Result<String, Error> {
+        let node = self.lookup(node_id(id)?)?;
+
+        match value {
+            "basic" => value_json(node.basic_get()?),
+            "switch_binary" => value_json(node.switch_binary_get()?),
+            "switch_multilevel" => value_json(node.switch_multilevel_get()?),
+            _ => Err(unknown_value(value)),
+        }
+    }
+
+    /// Set a value of a node.
+    fn set(&mut self, id: &str, value: &str, request: &Request) -> Result<String, Error> {
+        let node = self.lookup(node_id(id)?)?;
+
+        match value {
+            "basic" => node.basic_set(request.level()?)?,
+            "switch_binary" => node.switch_binary_set(request.level()? != 0x00)?,
+            "switch_multilevel" => node.switch_multilevel_set(request.level()?, None)?,
+            _ => return Err(unknown_value(value)),
+        };
+
+        Ok(r#"{"ok":true}"#.to_string())
+    }
+}
+
+/// Parse the node id of the path.
+fn node_id(id: &str) -> Result<u8, Error> {
+    id.parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("'{}' is not a node id", id),
+        )
+    })
+}
+
+/// The error for a value which isn't known.
+fn unknown_value(value: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("The value '{}' is not known", value),
+    )
+}
+
+/// Convert the value into a JSON string.
+fn to_json<T: serde::Serialize>(value: T) -> Result<String, Error> {
+    serde_json::to_string(&value).map_err(|e| Error::Protocol(e.to_string()))
+}
+
+/// Convert the value into the JSON string `{"value": ...}`.
+fn value_json<T: serde::Serialize>(value: T) -> Result<String, Error> {
+    to_json(serde_json::json!({ "value": value }))
+}
+
+/// Convert the error into the JSON string `{"error": ...}`.
+fn error_body(err: &Error) -> String {
+    serde_json::json!({ "error": err.to_string() }).to_string()
+}
+
+/// Write the response with a JSON body and close the connection.
+fn write_response<W: Write>(mut writer: W, code: u16, body: &str) -> io::Result<()> {
+    let reason = match code {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Bad Gateway",
+    };
+
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::{network, switch};
+    use crate::error::Stage;
+
+    /// Create a request without a body
+    fn request(method: &str, path: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn read_request() {
+        let raw = "PUT /nodes/5/basic HTTP/1.1\r\nHost: localhost\r\nContent-Length: 14\r\n\r\n{\"value\": 255}";
+        let request = Request::read(&mut raw.as_bytes()).unwrap();
+
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/nodes/5/basic");
+        assert_eq!(request.level(), Ok(0xFF));
+
+        // a boolean is on or off
+        let raw =
+            "PUT /nodes/5/switch_binary HTTP/1.1\r\nContent-Length: 16\r\n\r\n{\"value\": false}";
+        assert_eq!(
+            Request::read(&mut raw.as_bytes()).unwrap().level(),
+            Ok(0x00)
+        );
+
+        assert!(Request::read(&mut "\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn response() {
+        let mut out = vec![];
+        write_response(&mut out, 404, "{}").unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}"
+        );
+        assert_eq!(
+            status(&Error::Timeout {
+                stage: Stage::Report
+            }),
+            504
+        );
+    }
+
+    #[test]
+    fn inclusion() {
+        let (sim, controller) = network(vec![switch(2)]);
+        let mut server = HttpServer::new(controller);
+
+        sim.join(switch(5));
+        let (code, body) = server.handle(&request("POST", "/inclusion"));
+        assert_eq!(code, 200);
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["id"], 5);
+        assert_eq!(json["commands"], serde_json::json!(["SWITCH_BINARY"]));
+
+        // the new node is part of the network
+        let (code, body) = server.handle(&request("GET", "/nodes"));
+        assert_eq!(code, 200);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let ids: Vec<_> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["id"].clone())
+            .collect();
+        assert_eq!(ids, vec![2, 5]);
+
+        assert_eq!(server.handle(&request("GET", "/inclusion")).0, 404);
+    }
+}
@@ -0,0 +1,484 @@
+//! Node inclusion
+//!
+//! The controller adds a node to the network, while the inclusion button of
+//! the node is pressed. Each step of the inclusion is reported by a callback
+//! of the controller, until the node joined or the inclusion failed.
+//! Afterwards a node with the Security Command Class gets the S0 network key,
+//! the node is interviewed and a Z-Wave Plus node gets its lifeline to the
+//! controller.
+//!
+//! A controller can be included the same way, to hand over the role of the
+//! primary controller or to replace a lost primary controller. The other
+//! way around, the learn mode lets the controller join another network as a
+//! secondary controller.
+
+use super::{Controller, NodeEvent, NodeProgress, Operation, ProgressTracker, SecurityContext};
+#[cfg(feature = "cc-security")]
+use crate::cmds::CommandClass;
+use crate::defs::{
+    GenericType, InclusionReport, InclusionStatus, LearnModeReport, LearnModeStatus,
+    NodeInformation, SecurityClass,
+};
+use crate::driver::serial::SerialMsgFunction;
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind, Stage};
+
+use std::time::{Duration, Instant};
+
+/// Add any kind of node, with high power and network wide
+const ADD_NODE_ANY: u8 = 0xC1;
+
+/// Start the controller change or the new primary, with high power
+const CONTROLLER_START: u8 = 0x82;
+
+/// Stop the inclusion
+const INCLUSION_STOP: u8 = 0x05;
+
+/// Stop the controller change or the new primary, after it failed
+const CONTROLLER_STOP_FAILED: u8 = 0x06;
+
+/// Wait to be included by a controller in direct range
+const LEARN_MODE_CLASSIC: u8 = 0x01;
+
+/// Leave the learn mode
+const LEARN_MODE_DISABLE: u8 = 0x00;
+
+/// A node which joined the network.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IncludedNode {
+    pub id: u8,
+    /// The node information, which the node sent while it joined
+    pub info: NodeInformation,
+    /// The security class, which the node got the network key for
+    pub security_class: SecurityClass,
+}
+
+impl<D> Controller<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Add a node to the network. The controller waits up to the timeout
+    /// for a node, at which the inclusion button is pressed.
+    ///
+    /// The steps are reported to the progress listeners and the new node to
+    /// the node event listeners. A node with the Security Command Class gets
+    /// the S0 network key, when it's set, the node is interviewed and the
+    /// lifeline of a Z-Wave Plus node is set up. A failed step doesn't fail
+    /// the inclusion, the node is known with the command classes of its node
+    /// information then.
+    pub fn add_node(&self, timeout: Duration) -> Result<IncludedNode, Error> {
+        self.include(SerialMsgFunction::AddNodeToNetwork, ADD_NODE_ANY, timeout)
+    }
+
+    /// Stop a running inclusion, like when the user cancels it.
+    pub fn add_node_stop(&self) -> Result<(), Error> {
+        self.start_function(SerialMsgFunction::AddNodeToNetwork, &[INCLUSION_STOP])?;
+        Ok(())
+    }
+
+    /// Hand over the role of the primary controller to another controller,
+    /// like when the network is migrated to a new controller. The controller
+    /// waits up to the timeout for the other controller, which has to be in
+    /// learn mode.
+    ///
+    /// The other controller is added to the network like a new node and
+    /// this one becomes a secondary controller.
+    pub fn controller_change(&mut self, timeout: Duration) -> Result<IncludedNode, Error> {
+        let included = self.include(
+            SerialMsgFunction::ControllerChange,
+            CONTROLLER_START,
+            timeout,
+        )?;
+
+        // the controller isn't the primary anymore
+        self.info = self.driver.run(|d| d.get_controller_info())?;
+        Ok(included)
+    }
+
+    /// Let another controller become the primary controller, when the
+    /// primary controller was lost. Only the SUC/SIS of the network can
+    /// create a new primary. The controller waits up to the timeout for the
+    /// other controller, which has to be in learn mode.
+    pub fn create_new_primary(&self, timeout: Duration) -> Result<IncludedNode, Error> {
+        self.include(
+            SerialMsgFunction::CreateNewPrimary,
+            CONTROLLER_START,
+            timeout,
+        )
+    }
+
+    /// Include a node with the function and return it, when it joined. The
+    /// steps are reported to the listeners.
+    fn include(
+        &self,
+        function: SerialMsgFunction,
+        mode: u8,
+        timeout: Duration,
+    ) -> Result<IncludedNode, Error> {
+        let mut callback_id = self.start_function(function, &[mode])?;
+
+        let mut tracker = ProgressTracker::new(Operation::Inclusion, &[]);
+        let deadline = Instant::now() + timeout;
+        let mut included: Option<IncludedNode> = None;
+
+        loop {
+            if Instant::now() >= deadline {
+                self.start_function(function, &[INCLUSION_STOP])?;
+                return Err(Error::Timeout {
+                    stage: Stage::Callback,
+                });
+            }
+
+            let msg = match self.read_callback(function, callback_id) {
+                Some(msg) => msg,
+                None => continue,
+            };
+
+            let report = InclusionReport::parse(&msg.data)?;
+            match report.status {
+                InclusionStatus::LearnReady => {
+                    self.progress
+                        .borrow()
+                        .notify(&tracker.event("waiting for a node"));
+                }
+                InclusionStatus::NodeFound => {
+                    self.progress.borrow().notify(&tracker.event("node found"));
+                }
+                InclusionStatus::AddingSlave | InclusionStatus::AddingController => {
+                    self.progress.borrow().notify(&tracker.update(
+                        report.node_id,
+                        NodeProgress::Running,
+                        "adding the node",
+                    ));
+
+                    if let Some(info) = report.info {
+                        included = Some(IncludedNode {
+                            id: report.node_id,
+                            info,
+                            security_class: SecurityClass::None,
+                        });
+                    }
+                }
+                // the node joined, the controller has to leave the inclusion
+                InclusionStatus::ProtocolDone => {
+                    callback_id = self.start_function(function, &[INCLUSION_STOP])?;
+                }
+                InclusionStatus::Done => break,
+                InclusionStatus::Failed | InclusionStatus::NotPrimary => {
+                    // only the controller functions have an own mode for it
+                    let stop = match function {
+                        SerialMsgFunction::AddNodeToNetwork => INCLUSION_STOP,
+                        _ => CONTROLLER_STOP_FAILED,
+                    };
+                    self.start_function(function, &[stop])?;
+                    if report.node_id != 0 {
+                        self.progress.borrow().notify(&tracker.update(
+                            report.node_id,
+                            NodeProgress::Failed,
+                            "inclusion failed",
+                        ));
+                    }
+
+                    return Err(Error::new(
+                        ErrorKind::UnknownZWave,
+                        format!("The inclusion failed with {:?}", report.status),
+                    ));
+                }
+            }
+        }
+
+        // only the key exchange changes the security class
+        #[cfg_attr(not(feature = "cc-security"), allow(unused_mut))]
+        let mut included = included.ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnknownZWave,
+                "The node didn't send its node information",
+            )
+        })?;
+
+        // the node is known from its node information, until it's interviewed
+        let id = included.id;
+        let mut node = self.attach(id);
+        node.cmds = included.info.cmds.clone();
+        if included.info.generic != GenericType::Unknown {
+            node.types = vec![included.info.generic];
+        }
+
+        let info = self.driver.run(move |d| d.get_node_protocol_info(id))?;
+        self.protocol_infos.lock().unwrap().insert(id, info);
+        node.protocol_info = Some(info);
+
+        // a node which joined again replaces the old one
+        {
+            let mut nodes = self.nodes.borrow_mut();
+            nodes.retain(|n| n.id != id);
+            nodes.push(node);
+            nodes.sort_by_key(|n| n.id);
+        }
+
+        // the node only accepts the network key right after it joined
+        #[cfg(feature = "cc-security")]
+        if included.info.cmds.contains(&CommandClass::SECURITY)
+            && self.security.lock().unwrap().network_key().is_ok()
+        {
+            self.progress.borrow().notify(&tracker.update(
+                id,
+                NodeProgress::Running,
+                "key exchange",
+            ));
+            if self.s0_key_exchange(id).is_ok() {
+                included.security_class = SecurityClass::S0;
+            }
+        }
+
+        self.progress
+            .borrow()
+            .notify(&tracker.update(id, NodeProgress::Running, "interview"));
+        let interviewed = self.interview_node(id).is_ok();
+        if interviewed && included.security_class == SecurityClass::S0 {
+            #[cfg(feature = "cc-security")]
+            let _ = self.read_secure_commands(id);
+        }
+
+        // the node starts to send its unsolicited reports to the controller
+        self.progress
+            .borrow()
+            .notify(&tracker.update(id, NodeProgress::Running, "lifeline"));
+        let _ = self.lifeline_setup(id);
+
+        self.progress
+            .borrow()
+            .notify(&tracker.update(id, NodeProgress::Done, "node added"));
+        self.node_events.borrow().notify(&NodeEvent::NodeAdded(id));
+
+        Ok(included)
+    }
+
+    /// Let the controller join another network as a secondary controller.
+    /// The controller waits up to the timeout to be included by the primary
+    /// controller of the other network, which has to be in direct range.
+    ///
+    /// Afterwards the controller uses the home id and node id of the new
+    /// network. The nodes of the old network are reported as removed and
+    /// the ones of the new network are discovered and reported as added.
+    pub fn learn_mode(&mut self, timeout: Duration) -> Result<(), Error> {
+        let callback_id =
+            self.start_function(SerialMsgFunction::SetLearnMode, &[LEARN_MODE_CLASSIC])?;
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if Instant::now() >= deadline {
+                self.start_function(SerialMsgFunction::SetLearnMode, &[LEARN_MODE_DISABLE])?;
+                return Err(Error::Timeout {
+                    stage: Stage::Callback,
+                });
+            }
+
+            let msg = match self.read_callback(SerialMsgFunction::SetLearnMode, callback_id) {
+                Some(msg) => msg,
+                None => continue,
+            };
+
+            match LearnModeReport::parse(&msg.data)?.status {
+                LearnModeStatus::Started => {}
+                LearnModeStatus::Done => break,
+                LearnModeStatus::Failed => {
+                    self.start_function(SerialMsgFunction::SetLearnMode, &[LEARN_MODE_DISABLE])?;
+                    return Err(Error::new(
+                        ErrorKind::UnknownZWave,
+                        "The controller couldn't join the network",
+                    ));
+                }
+            }
+        }
+        self.start_function(SerialMsgFunction::SetLearnMode, &[LEARN_MODE_DISABLE])?;
+
+        // the old network key and nodes are of no use anymore
+        self.info = self.driver.run(|d| d.get_controller_info())?;
+        *self.security.lock().unwrap() = SecurityContext::new(self.info.home_id, self.info.node_id);
+
+        let old = self.nodes();
+        self.nodes.borrow_mut().clear();
+        self.protocol_infos.lock().unwrap().clear();
+        for id in old {
+            self.node_events
+                .borrow()
+                .notify(&NodeEvent::NodeRemoved(id));
+        }
+
+        self.discover_nodes()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::{network, switch};
+    use crate::basic::sim::SimNode;
+    use crate::cmds::CommandClass;
+
+    #[test]
+    fn inclusion() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let (sim, controller) = network(vec![switch(2)]);
+        let events = Rc::new(RefCell::new(vec![]));
+        let e = events.clone();
+        controller
+            .node_events()
+            .on_event(move |event| e.borrow_mut().push(*event));
+
+        // no node joins in time
+        assert!(matches!(
+            controller.add_node(Duration::from_millis(200)),
+            Err(Error::Timeout {
+                stage: Stage::Callback
+            })
+        ));
+
+        // a report of another node arrives while the node joins
+        sim.join(switch(5).specific(0x03));
+        sim.report(2, CommandClass::BASIC, 0x03, &[0x63]);
+        let node = controller.add_node(Duration::from_secs(1)).unwrap();
+        assert_eq!(node.id, 5);
+        assert_eq!(node.info.generic, GenericType::BinarySwitch);
+        assert_eq!(node.info.cmds, vec![CommandClass::SWITCH_BINARY]);
+        assert_eq!(node.security_class, SecurityClass::None);
+        assert_eq!(controller.nodes(), vec![2, 5]);
+        assert_eq!(
+            controller.nodes_supporting(CommandClass::SWITCH_BINARY),
+            vec![2, 5]
+        );
+        assert_eq!(*events.borrow(), vec![NodeEvent::NodeAdded(5)]);
+
+        // the node was interviewed after it joined
+        let last = sim.received(5).pop().unwrap();
+        assert_eq!(last, vec![CommandClass::NODE_INFO as u8, 0x02]);
+
+        let report = sim.clone().read_frame().unwrap();
+        assert_eq!(report.source, 2);
+        assert_eq!(report.expect(CommandClass::BASIC, 0x03), Ok(&[0x63][..]));
+    }
+
+    #[cfg(feature = "cc-security")]
+    #[test]
+    fn secure_inclusion() {
+        use crate::keyring::NetworkKey;
+
+        let (sim, controller) = network(vec![switch(2)]);
+        let node = || {
+            SimNode::new(5, GenericType::EntryControl)
+                .command_classes(&[CommandClass::SECURITY])
+                .respond(CommandClass::SECURITY, 0x02, 0x03, &[0x00, 0x62])
+                .secure()
+        };
+
+        // without a key the node is added without security
+        sim.join(node());
+        let included = controller.add_node(Duration::from_secs(1)).unwrap();
+        assert_eq!(included.security_class, SecurityClass::None);
+        assert_eq!(sim.network_key(5), Some([0; 16]));
+
+        let key = NetworkKey([0x42; 16]);
+        controller.set_s0_key(key);
+        sim.join(node());
+        let included = controller.add_node(Duration::from_secs(1)).unwrap();
+        assert_eq!(included.security_class, SecurityClass::S0);
+        assert_eq!(sim.network_key(5), Some(key.0));
+        assert_eq!(
+            controller.nodes_supporting(CommandClass::DOOR_LOCK),
+            vec![5]
+        );
+    }
+
+    #[test]
+    fn lifeline() {
+        let (sim, controller) = network(vec![switch(2)]);
+
+        // the node reports the controller in its lifeline group afterwards
+        sim.join(
+            switch(5)
+                .command_classes(&[
+                    CommandClass::SWITCH_BINARY,
+                    CommandClass::ZWAVEPLUS_INFO,
+                    CommandClass::ASSOCIATION,
+                ])
+                .respond(
+                    CommandClass::ASSOCIATION,
+                    0x02,
+                    0x03,
+                    &[0x01, 0x05, 0x00, 0x01],
+                ),
+        );
+        controller.add_node(Duration::from_secs(1)).unwrap();
+        assert!(sim
+            .received(5)
+            .contains(&vec![CommandClass::ASSOCIATION as u8, 0x01, 0x01, 0x01]));
+
+        // a node, which isn't Z-Wave Plus, doesn't get a lifeline
+        sim.join(
+            switch(6).command_classes(&[CommandClass::SWITCH_BINARY, CommandClass::ASSOCIATION]),
+        );
+        controller.add_node(Duration::from_secs(1)).unwrap();
+        assert!(!sim
+            .received(6)
+            .iter()
+            .any(|f| f[0] == CommandClass::ASSOCIATION as u8));
+    }
+
+    #[test]
+    fn controller_change() {
+        let (sim, mut controller) = network(vec![switch(2)]);
+        assert!(!controller.info().capabilities.secondary);
+
+        // the new primary controller is added and takes over the role
+        sim.join(SimNode::new(5, GenericType::StaticController));
+        let node = controller
+            .controller_change(Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(node.id, 5);
+        assert_eq!(node.info.generic, GenericType::StaticController);
+        assert_eq!(controller.nodes(), vec![2, 5]);
+        assert!(controller.info().capabilities.secondary);
+
+        // the primary controller was lost and gets replaced
+        sim.join(SimNode::new(6, GenericType::StaticController));
+        let node = controller
+            .create_new_primary(Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(node.id, 6);
+        assert_eq!(controller.nodes(), vec![2, 5, 6]);
+    }
+
+    #[test]
+    fn learn_mode() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let (sim, mut controller) = network(vec![switch(2)]);
+        let events = Rc::new(RefCell::new(vec![]));
+        let e = events.clone();
+        controller
+            .node_events()
+            .on_event(move |event| e.borrow_mut().push(*event));
+
+        // the primary controller of the other network includes this one
+        sim.join_network(0xDEAD_BEEF, 0x0C, vec![switch(1), switch(2), switch(3)]);
+        controller.learn_mode(Duration::from_secs(1)).unwrap();
+        assert_eq!(controller.home_id(), 0xDEAD_BEEF);
+        assert_eq!(controller.get_id(), 0x0C);
+        assert_eq!(controller.nodes(), vec![1, 2, 3]);
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                NodeEvent::NodeRemoved(2),
+                NodeEvent::NodeAdded(1),
+                NodeEvent::NodeAdded(2),
+                NodeEvent::NodeAdded(3),
+            ]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Node interview
+//!
+//! All nodes are interviewed at once. The node information is requested
+//! from several nodes, before the first answer is read, so the round trips
+//! through the network overlap instead of adding up. The driver assigns the
+//! transmit reports to the nodes by their callback id, so a node which
+//! can't be reached is given up without waiting for the timeout.
+
+use crate::cmds::info::NodeInfo;
+use crate::cmds::CommandClass;
+use crate::defs::GenericType;
+use crate::defs::{NodeActivity, NodeProtocolInfo, TransmitStatus};
+use crate::driver::serial::SerialMsgFunction;
+use crate::driver::Driver;
+use crate::error::{Error, Stage};
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Amount of node information requests, which are outstanding at once
+pub const MAX_OUTSTANDING: usize = 8;
+
+/// Time a node gets to answer its node information request
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What was learned about a node in the interview
+#[derive(Debug, Clone)]
+pub struct Interview {
+    pub id: u8,
+    pub info: Result<(Vec<GenericType>, Vec<CommandClass>), Error>,
+    pub protocol_info: Option<NodeProtocolInfo>,
+}
+
+/// Interview the nodes, with up to `MAX_OUTSTANDING` requests at once. The
+/// protocol information is only requested for the nodes it isn't known for.
+pub fn interview<D>(
+    driver: &mut D,
+    ids: &[u8],
+    known: &HashMap<u8, NodeProtocolInfo>,
+) -> Vec<Interview>
+where
+    D: Driver,
+{
+    // the protocol information is stored in the controller, which answers directly
+    let mut interviews: Vec<Interview> = ids
+        .iter()
+        .map(|&id| Interview {
+            id,
+            info: Err(Error::Timeout {
+                stage: Stage::Report,
+            }),
+            protocol_info: known
+                .get(&id)
+                .cloned()
+                .or_else(|| driver.get_node_protocol_info(id).ok()),
+        })
+        .collect();
+
+    // the index of the interview and the time its request was sent
+    let mut outstanding: Vec<(usize, Instant)> = vec![];
+    let mut next = 0;
+
+    while next < interviews.len() || !outstanding.is_empty() {
+        // keep the maximum of requests outstanding
+        while next < interviews.len() && outstanding.len() < MAX_OUTSTANDING {
+            match driver.write(NodeInfo::get(interviews[next].id)) {
+                Ok(_) => outstanding.push((next, Instant::now())),
+                Err(e) => interviews[next].info = Err(e),
+            }
+            next += 1;
+        }
+
+        // assign the answers to the nodes, which sent them
+        while let Ok(msg) = driver.read() {
+            if msg.func != SerialMsgFunction::ApplicationUpdate {
+                continue;
+            }
+
+            let node = msg.data.get(1).cloned();
+            if let Some(pos) = outstanding
+                .iter()
+                .position(|&(i, _)| Some(interviews[i].id) == node)
+            {
+                let (i, _) = outstanding.remove(pos);
+                interviews[i].info = NodeInfo::report(msg.data);
+            }
+        }
+
+        // give up the nodes, which the request couldn't be delivered to
+        for activity in driver.take_activity().unwrap_or_default() {
+            if let NodeActivity::Transmit(node, status) = activity {
+                if status == TransmitStatus::CompleteOk {
+                    continue;
+                }
+
+                if let Some(pos) = outstanding
+                    .iter()
+                    .position(|&(i, _)| interviews[i].id == node)
+                {
+                    let (i, _) = outstanding.remove(pos);
+                    interviews[i].info = Err(Error::TransmitFailed(status));
+                }
+            }
+        }
+
+        // the nodes which didn't answer in time keep the timeout error
+        outstanding.retain(|&(_, sent)| sent.elapsed() < ANSWER_TIMEOUT);
+    }
+
+    interviews
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::{network, switch};
+    use crate::basic::sim::{SimFailure, SimNode};
+
+    #[test]
+    fn interview() {
+        let mut nodes: Vec<SimNode> = (2..12).map(switch).collect();
+        nodes.push(
+            SimNode::new(12, GenericType::MultiLevelSwitch)
+                .command_classes(&[CommandClass::SWITCH_MULTILEVEL])
+                .failure(SimFailure::NoAck),
+        );
+
+        let (_, mut controller) = network(nodes);
+        assert_eq!(controller.nodes(), (2..13).collect::<Vec<u8>>());
+        assert_eq!(
+            controller.nodes_supporting(CommandClass::SWITCH_BINARY),
+            (2..12).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            controller.nodes_of_type(GenericType::BinarySwitch).len(),
+            10
+        );
+
+        // the unreachable node is known, but without its command classes
+        assert!(controller.node(12).unwrap().get_commands().is_empty());
+    }
+}
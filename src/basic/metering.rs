@@ -0,0 +1,251 @@
+//! Meter polling and aggregation
+//!
+//! The `MeterService` polls registered meter or sensor values in a fixed
+//! interval and summarizes them for each report interval, with the minimum,
+//! maximum, average and the change of the value.
+
+use super::Node;
+use crate::driver::Driver;
+use crate::error::Error;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The aggregated values of one report interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterAggregate {
+    pub samples: u32,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    /// The change since the end of the previous interval, or since the
+    /// first sample for the first interval
+    pub delta: f64,
+}
+
+/// The summary of a registered value for one report interval.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterSummary {
+    pub node: u8,
+    pub name: String,
+    pub period: Duration,
+    /// Amount of polls which failed
+    pub failures: u32,
+    /// `None` when no poll was successful
+    pub aggregate: Option<MeterAggregate>,
+}
+
+/// Accumulates the samples of one interval.
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    samples: u32,
+    failures: u32,
+    min: f64,
+    max: f64,
+    sum: f64,
+    start: Option<f64>,
+    last: Option<f64>,
+}
+
+impl Accumulator {
+    /// Add a successful sample
+    fn add(&mut self, value: f64) {
+        if self.samples == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+
+        self.samples += 1;
+        self.sum += value;
+        self.start = self.start.or(Some(value));
+        self.last = Some(value);
+    }
+
+    /// Return the aggregate and start the next interval
+    fn finish(&mut self) -> (Option<MeterAggregate>, u32) {
+        let aggregate = match (self.start, self.last) {
+            (Some(start), Some(last)) if self.samples > 0 => Some(MeterAggregate {
+                samples: self.samples,
+                min: self.min,
+                max: self.max,
+                avg: self.sum / self.samples as f64,
+                delta: last - start,
+            }),
+            _ => None,
+        };
+        let failures = self.failures;
+
+        // the next delta starts at the last value
+        *self = Accumulator {
+            start: self.last,
+            last: self.last,
+            ..Accumulator::default()
+        };
+
+        (aggregate, failures)
+    }
+}
+
+type Reader<D> = Box<dyn Fn(&Node<D>) -> Result<f64, Error> + Send>;
+
+/// A registered value and its accumulated samples.
+struct Source<D>
+where
+    D: Driver,
+{
+    node: Node<D>,
+    name: String,
+    read: Reader<D>,
+    accumulator: Accumulator,
+}
+
+type Listener = Box<dyn Fn(&MeterSummary) + Send>;
+
+/// Polls registered values and emits a summary for each report interval.
+pub struct MeterService<D>
+where
+    D: Driver,
+{
+    poll_interval: Duration,
+    report_interval: Duration,
+    sources: Vec<Source<D>>,
+    listeners: Vec<Listener>,
+    started: Instant,
+}
+
+impl<D> MeterService<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create a new service, which polls all values in the poll interval
+    /// and summarizes them in the report interval.
+    pub fn new(poll_interval: Duration, report_interval: Duration) -> MeterService<D> {
+        MeterService {
+            poll_interval,
+            report_interval,
+            sources: vec![],
+            listeners: vec![],
+            started: Instant::now(),
+        }
+    }
+
+    /// Register a value of the node, which is read by the given function.
+    pub fn register<N, F>(&mut self, node: Node<D>, name: N, read: F)
+    where
+        N: Into<String>,
+        F: Fn(&Node<D>) -> Result<f64, Error> + Send + 'static,
+    {
+        self.sources.push(Source {
+            node,
+            name: name.into(),
+            read: Box::new(read),
+            accumulator: Accumulator::default(),
+        });
+    }
+
+    /// Register the meter value of the node.
+    pub fn register_meter(&mut self, node: Node<D>) {
+        self.register(node, "meter", |n| n.meter_get().map(|m| m.get_value()));
+    }
+
+    /// Register a function which gets all summaries, after a report
+    /// interval is finished.
+    pub fn on_report<F>(&mut self, listener: F)
+    where
+        F: Fn(&MeterSummary) + Send + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Poll all values once. When the report interval is finished, the
+    /// summaries are returned and the listeners are informed.
+    pub fn poll(&mut self) -> Vec<MeterSummary> {
+        for source in &mut self.sources {
+            match (source.read)(&source.node) {
+                Ok(value) => source.accumulator.add(value),
+                Err(_) => source.accumulator.failures += 1,
+            }
+        }
+
+        // check if the report interval is over
+        let period = self.started.elapsed();
+        if period < self.report_interval {
+            return vec![];
+        }
+        self.started = Instant::now();
+
+        let mut summaries = vec![];
+        for source in &mut self.sources {
+            let (aggregate, failures) = source.accumulator.finish();
+
+            summaries.push(MeterSummary {
+                node: source.node.get_id(),
+                name: source.name.clone(),
+                period,
+                failures,
+                aggregate,
+            });
+        }
+
+        for summary in &summaries {
+            for listener in &self.listeners {
+                listener(summary);
+            }
+        }
+
+        summaries
+    }
+}
+
+impl<D> MeterService<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Poll the values in the background, the summaries are delivered
+    /// to the listeners.
+    pub fn start(mut self) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            self.poll();
+            thread::sleep(self.poll_interval);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate_intervals() {
+        let mut acc = Accumulator::default();
+        acc.add(10.0);
+        acc.add(14.0);
+        acc.add(12.0);
+        acc.failures += 1;
+
+        let (aggregate, failures) = acc.finish();
+        assert_eq!(failures, 1);
+        assert_eq!(
+            aggregate,
+            Some(MeterAggregate {
+                samples: 3,
+                min: 10.0,
+                max: 14.0,
+                avg: 12.0,
+                delta: 2.0,
+            })
+        );
+
+        // the next delta continues from the last value
+        acc.add(15.0);
+        assert_eq!(acc.finish().0.map(|a| a.delta), Some(3.0));
+
+        // no samples
+        assert_eq!(acc.finish(), (None, 0));
+    }
+}
@@ -8,27 +8,222 @@
 //! The `Controller` provides the functionality to connected
 //! to a Z-Wave network, to send  messages and to receive them.
 
-pub use cmds::powerlevel::PowerLevelOperationStatus;
-pub use cmds::powerlevel::PowerLevelStatus;
-pub use cmds::MeterData;
-
-use cmds::basic::Basic;
-use cmds::info::NodeInfo;
-use cmds::meter::Meter;
-use cmds::powerlevel::PowerLevel;
-use cmds::switch_binary::SwitchBinary;
-use cmds::switch_multilevel::SwitchMultilevel;
-use cmds::CommandClass;
-use driver::serial::SerialMsg;
-use driver::{Driver, GenericType};
-use error::Error;
-
-use std::cell::RefCell;
+mod actor;
+mod associations;
+mod awake;
+mod battery;
+mod clock_sync;
+#[cfg(feature = "dbus")]
+mod dbus;
+mod dimmer;
+mod discovery;
+mod endpoint;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "cc-firmware-update")]
+mod firmware;
+mod heal;
+#[cfg(feature = "cc-powerlevel")]
+mod health;
+#[cfg(feature = "http")]
+mod http;
+mod inclusion;
+mod interview;
+#[cfg(feature = "cc-meter")]
+mod metering;
+mod overrides;
+#[cfg(not(feature = "cc-security"))]
+mod plaintext;
+mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+mod queue;
+mod retry;
+mod routing;
+mod scenes;
+mod scheduler;
+#[cfg(feature = "cc-security")]
+mod security;
+mod self_test;
+#[cfg(any(test, feature = "sim"))]
+pub mod sim;
+#[cfg(feature = "json")]
+mod state;
+mod states;
+mod statistics;
+#[cfg(feature = "cc-user-code")]
+mod user_codes;
+mod values;
+
+pub use self::actor::{DriverHandle, WeakDriverHandle};
+pub use self::associations::{Association, AssociationGroup, Associations};
+pub use self::battery::{BatteryEvent, BatteryReading, BatteryService};
+pub use self::clock_sync::{ClockService, ClockSync};
+#[cfg(feature = "dbus")]
+pub use self::dbus::{Bus, DbusService, BUS_NAME};
+pub use self::dimmer::Dimmer;
+pub use self::discovery::{DiscoveryDiff, NodeEvent, NodeEvents};
+pub use self::endpoint::Endpoint;
+#[cfg(feature = "cc-firmware-update")]
+pub use self::firmware::FirmwareTransfer;
+pub use self::heal::HealReport;
+#[cfg(feature = "cc-powerlevel")]
+pub use self::health::HealthReport;
+#[cfg(feature = "http")]
+pub use self::http::{HttpServer, NodeSummary, Request};
+pub use self::inclusion::IncludedNode;
+#[cfg(feature = "cc-meter")]
+pub use self::metering::{MeterAggregate, MeterService, MeterSummary};
+pub use self::overrides::NodeOverrides;
+pub use self::progress::{NodeProgress, Operation, Progress, ProgressEvent, ProgressTracker};
+pub use self::queue::{QueueEntry, QueueSnapshot};
+pub use self::retry::{Backoff, RetryPolicy};
+pub use self::scenes::{Scene, Scenes};
+pub use self::scheduler::{Action, Job, Priority, Scheduler};
+pub use self::self_test::{SelfTestCheck, SelfTestReport};
+#[cfg(feature = "json")]
+pub use self::state::{NetworkState, NodeExport, STATE_VERSION};
+pub use self::states::{NodeState, NodeStates};
+pub use self::statistics::NodeStatistics;
+#[cfg(feature = "cc-user-code")]
+pub use self::user_codes::{UserCodeManager, UserCodeSlot};
+pub use self::values::{CachedValue, Value};
+#[cfg(feature = "cc-antitheft")]
+pub use crate::cmds::antitheft::{AntiTheftState, AntiTheftStatus};
+pub use crate::cmds::association::AssociationReport;
+pub use crate::cmds::battery::BatteryLevel;
+#[cfg(feature = "cc-central-scene")]
+pub use crate::cmds::central_scene::{
+    CentralSceneNotification, CentralSceneSupported, KeyAttribute,
+};
+pub use crate::cmds::clock::ClockTime;
+#[cfg(feature = "cc-entry-control")]
+pub use crate::cmds::entry_control::{EntryControlNotification, EntryData, EntryEventType};
+#[cfg(feature = "cc-firmware-update")]
+pub use crate::cmds::firmware_update::{FirmwareMetadata, FirmwareRequestStatus, FirmwareStatus};
+#[cfg(feature = "cc-humidity")]
+pub use crate::cmds::humidity_control_setpoint::{HumiditySetpoint, HumiditySetpointType};
+#[cfg(feature = "cc-meter")]
+pub use crate::cmds::meter::MeterSupported;
+#[cfg(feature = "cc-meter")]
+pub use crate::cmds::meter_table_monitor::{
+    MeterTableData, MeterTableEvent, MeterTableStatus, MeterTableValue,
+};
+pub use crate::cmds::multi_channel::{EndpointCapability, MultiChannelEndpoints};
+pub use crate::cmds::notification::NotificationEvent;
+#[cfg(feature = "cc-powerlevel")]
+pub use crate::cmds::powerlevel::PowerLevelOperationStatus;
+#[cfg(feature = "cc-powerlevel")]
+pub use crate::cmds::powerlevel::PowerLevelStatus;
+#[cfg(feature = "cc-protection")]
+pub use crate::cmds::protection::{LocalProtection, ProtectionState, RfProtection};
+#[cfg(feature = "cc-scene-controller")]
+pub use crate::cmds::scene_controller_conf::ButtonScene;
+pub use crate::cmds::sensor_alarm::{AlarmSensorReport, AlarmSensorType};
+#[cfg(feature = "cc-sound-switch")]
+pub use crate::cmds::sound_switch::{ToneInfo, DEFAULT_TONE};
+#[cfg(feature = "cc-thermostat")]
+pub use crate::cmds::thermostat_operating_state::OperatingState;
+#[cfg(feature = "cc-thermostat")]
+pub use crate::cmds::thermostat_setpoint::{TemperatureSetpoint, ThermostatSetpointType};
+pub use crate::cmds::time_parameters::DateTime;
+pub use crate::cmds::version::VersionReport;
+pub use crate::cmds::wake_up::{WakeUpCapabilities, WakeUpInterval};
+#[cfg(feature = "cc-window-covering")]
+pub use crate::cmds::window_covering::{WindowCoveringParameter, WindowCoveringState};
+pub use crate::cmds::{MeterData, MeterKind, MeterUnit, Quantity, RateType, SensorValue, Unit};
+
+use self::awake::AwakeQueue;
+#[cfg(not(feature = "cc-security"))]
+use self::plaintext::SecurityContext;
+use self::queue::{QueueTicket, TransmitQueue};
+#[cfg(feature = "cc-security")]
+use self::security::SecurityContext;
+use self::statistics::Statistics;
+use self::values::Values;
+#[cfg(feature = "cc-antitheft")]
+use crate::cmds::antitheft::AntiTheft;
+use crate::cmds::application_status::ApplicationStatus;
+use crate::cmds::association::Association as AssociationCmd;
+use crate::cmds::basic::Basic;
+use crate::cmds::battery::Battery;
+#[cfg(feature = "cc-central-scene")]
+use crate::cmds::central_scene::CentralScene;
+use crate::cmds::check_level;
+use crate::cmds::clock::Clock;
+use crate::cmds::configuration::Configuration;
+use crate::cmds::encapsulation::{Encapsulation, Unwrapped, Wrapped};
+#[cfg(feature = "cc-entry-control")]
+use crate::cmds::entry_control::EntryControl;
+#[cfg(feature = "cc-firmware-update")]
+use crate::cmds::firmware_update::FirmwareUpdate;
+#[cfg(feature = "cc-humidity")]
+use crate::cmds::humidity_control_setpoint::HumidityControlSetpoint;
+use crate::cmds::info::NodeInfo;
+#[cfg(feature = "cc-meter")]
+use crate::cmds::meter::Meter;
+#[cfg(feature = "cc-meter")]
+use crate::cmds::meter_pulse::MeterPulse;
+#[cfg(feature = "cc-meter")]
+use crate::cmds::meter_table_monitor::MeterTableMonitor;
+use crate::cmds::multi_channel::MultiChannel;
+use crate::cmds::multi_cmd::MultiCmd;
+use crate::cmds::notification::Notification;
+#[cfg(feature = "cc-powerlevel")]
+use crate::cmds::powerlevel::PowerLevel;
+#[cfg(feature = "cc-protection")]
+use crate::cmds::protection::Protection;
+use crate::cmds::report::Report;
+#[cfg(feature = "cc-scene-controller")]
+use crate::cmds::scene_controller_conf::SceneControllerConf;
+#[cfg(feature = "cc-security")]
+use crate::cmds::security::Security;
+use crate::cmds::sensor_alarm::SensorAlarm;
+use crate::cmds::sensor_configuration::SensorConfiguration;
+use crate::cmds::sensor_multilevel::SensorMultilevel;
+#[cfg(feature = "cc-sound-switch")]
+use crate::cmds::sound_switch::SoundSwitch;
+use crate::cmds::supervision::{Supervision, SupervisionStatus};
+use crate::cmds::switch_binary::SwitchBinary;
+use crate::cmds::switch_multilevel::SwitchMultilevel;
+#[cfg(feature = "cc-thermostat")]
+use crate::cmds::thermostat_operating_state::ThermostatOperatingState;
+#[cfg(feature = "cc-thermostat")]
+use crate::cmds::thermostat_setpoint::ThermostatSetpoint;
+use crate::cmds::time::Time;
+use crate::cmds::time_parameters::TimeParameters;
+use crate::cmds::transport_service::{Reassembly, TransportService};
+use crate::cmds::version::Version;
+use crate::cmds::wake_up::WakeUp;
+#[cfg(feature = "cc-window-covering")]
+use crate::cmds::window_covering::WindowCovering;
+use crate::cmds::CommandClass;
+use crate::cmds::IncomingFrame;
+use crate::cmds::Message;
+use crate::defs::{
+    ControllerInfo, GenericType, NodeActivity, NodeCapabilities, NodeProtocolInfo, Route,
+    SecurityClass, SpecificType, TransmitOptions, TransmitReport, TransmitStatus,
+};
+use crate::driver::serial::{SerialMsg, SerialMsgFunction, SerialMsgType};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind, Stage};
+#[cfg(feature = "cc-security")]
+use crate::keyring::NetworkKey;
+
+use std::cell::{Ref, RefCell, RefMut};
 use std::clone::Clone;
+use std::collections::HashMap;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{thread, time};
 
+/// How often a missing segment of a datagram is requested from the node
+const MAX_SEGMENT_REQUESTS: u8 = 2;
+
+/// The time between two reads of the callbacks of a function
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 pub trait Handler: Send {
     fn handle(self, msg: SerialMsg);
 }
@@ -38,8 +233,22 @@ pub struct Controller<D>
 where
     D: Driver,
 {
-    driver: Arc<Mutex<D>>,
+    driver: DriverHandle<D>,
+    info: ControllerInfo,
     nodes: Rc<RefCell<Vec<Node<D>>>>,
+    scenes: Rc<RefCell<Scenes>>,
+    scheduler: Rc<RefCell<Scheduler>>,
+    associations: Rc<RefCell<Associations>>,
+    states: Rc<RefCell<NodeStates>>,
+    retry_policy: Arc<Mutex<RetryPolicy>>,
+    progress: Rc<RefCell<Progress>>,
+    node_events: Rc<RefCell<NodeEvents>>,
+    queue: Arc<Mutex<TransmitQueue>>,
+    statistics: Arc<Mutex<Statistics>>,
+    awake: Arc<Mutex<AwakeQueue>>,
+    values: Arc<Mutex<Values>>,
+    security: Arc<Mutex<SecurityContext>>,
+    protocol_infos: Arc<Mutex<HashMap<u8, NodeProtocolInfo>>>,
 }
 
 impl<D> Controller<D>
@@ -48,53 +257,916 @@ where
 {
     /// Generate a new Controller to interface with the z-wave network.
     pub fn new(driver: D) -> Result<Controller<D>, Error> {
-        let controller = Controller {
-            driver: Arc::new(Mutex::new(driver)),
-            nodes: Rc::new(RefCell::new(vec![])),
-        };
-
+        let controller = Controller::connect(driver)?;
         controller.discover_nodes()?;
 
         Ok(controller)
     }
 
-    /// Discover all nodes which are present in the network
-    pub fn discover_nodes(&self) -> Result<(), Error> {
-        // clear the existing nodes
-        self.nodes.borrow_mut().clear();
+    /// Generate a new Controller, which only reads the ids of the nodes
+    /// without contacting them. A node is interviewed, when it's returned
+    /// by `node()` the first time or by `interview_node()`.
+    ///
+    /// Until then the node is unknown to the lookups by command class or
+    /// device type.
+    pub fn new_lazy(driver: D) -> Result<Controller<D>, Error> {
+        let controller = Controller::connect(driver)?;
+
+        for id in controller.driver.run(|d| d.get_node_ids())? {
+            let node = controller.attach(id);
+            controller.nodes.borrow_mut().push(node);
+        }
+
+        Ok(controller)
+    }
+
+    /// Read the information of the controller and take over the driver
+    fn connect(mut driver: D) -> Result<Controller<D>, Error> {
+        // get the network, the own node id and the controller capabilities
+        let info = driver.get_controller_info()?;
+        let security = SecurityContext::new(info.home_id, info.node_id);
+
+        Ok(Controller {
+            driver: DriverHandle::spawn(driver),
+            info,
+            nodes: Rc::new(RefCell::new(vec![])),
+            scenes: Rc::new(RefCell::new(Scenes::new())),
+            scheduler: Rc::new(RefCell::new(Scheduler::new())),
+            associations: Rc::new(RefCell::new(Associations::new())),
+            states: Rc::new(RefCell::new(NodeStates::new())),
+            retry_policy: Arc::new(Mutex::new(RetryPolicy::new())),
+            progress: Rc::new(RefCell::new(Progress::new())),
+            node_events: Rc::new(RefCell::new(NodeEvents::new())),
+            queue: Arc::new(Mutex::new(TransmitQueue::new())),
+            statistics: Arc::new(Mutex::new(Statistics::new())),
+            awake: Arc::new(Mutex::new(AwakeQueue::new())),
+            values: Arc::new(Mutex::new(Values::new())),
+            security: Arc::new(Mutex::new(security)),
+            protocol_infos: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Create an uninterviewed node, which shares the stores of the controller
+    fn attach(&self, id: u8) -> Node<D> {
+        // the node uses the retry policy of the controller
+        let mut node = Node::uninterviewed(self.driver.clone(), id);
+        node.default_retry_policy = self.retry_policy.clone();
+        node.queue = self.queue.clone();
+        node.statistics = self.statistics.clone();
+        node.awake = self.awake.clone();
+        node.values = self.values.clone();
+        node.security = self.security.clone();
+        node.protocol_infos = self.protocol_infos.clone();
+        node
+    }
+
+    /// Start the function with the data and return the id of its callbacks
+    fn start_function(&self, function: SerialMsgFunction, data: &[u8]) -> Result<u8, Error> {
+        let data = data.to_vec();
+        self.driver.run(move |d| d.start_function(function, &data))
+    }
+
+    /// Return the next callback of the function. The callbacks of an
+    /// earlier call are dropped, other messages like the reports of the
+    /// nodes are handed back to the driver for the usual receive path.
+    /// Waits a moment, when no callback arrived.
+    fn read_callback(&self, function: SerialMsgFunction, callback_id: u8) -> Option<SerialMsg> {
+        let callback = self.driver.run(move |d| {
+            let mut others = vec![];
+            let callback = loop {
+                match d.read() {
+                    Ok(msg) if msg.func == function => {
+                        if msg.data.first() == Some(&callback_id) {
+                            break Some(msg);
+                        }
+                    }
+                    Ok(msg) => others.push(msg),
+                    Err(_) => break None,
+                }
+            };
+
+            d.unread(others);
+            Ok(callback)
+        });
+
+        match callback {
+            Ok(Some(msg)) => Some(msg),
+            _ => {
+                thread::sleep(POLL_INTERVAL);
+                None
+            }
+        }
+    }
 
+    /// Discover all nodes which are present in the network and interview
+    /// the new ones. The progress of the interview is reported to the
+    /// listeners and the added and removed nodes to the node event listeners.
+    ///
+    /// The known nodes keep their information, use `interview_node()` to
+    /// update it. The nodes of a lazy controller are interviewed as well.
+    ///
+    /// The nodes are interviewed at once, with several requests outstanding.
+    /// The nodes which didn't answer are interviewed once more one after
+    /// another, with the retry policy of the controller.
+    ///
+    /// The protocol information doesn't change until a node is included
+    /// again, so it's only requested for the nodes it isn't known for yet.
+    pub fn discover_nodes(&self) -> Result<DiscoveryDiff, Error> {
         // get all node id's which are in the network
-        let ids = self.driver.lock().unwrap().get_node_ids()?;
+        let ids = self.driver.run(|d| d.get_node_ids())?;
+        let diff = DiscoveryDiff::new(&self.nodes(), &ids);
+
+        // remove the nodes which left the network and add the new ones
+        {
+            let mut nodes = self.nodes.borrow_mut();
+            nodes.retain(|n| ids.contains(&n.id));
+            for &id in &diff.added {
+                nodes.push(self.attach(id));
+            }
+            nodes.sort_by_key(|n| n.id);
+        }
+
+        // forget the protocol information of the nodes, which left the network
+        let known = {
+            let mut infos = self.protocol_infos.lock().unwrap();
+            infos.retain(|id, _| ids.contains(id));
+            infos.clone()
+        };
+
+        let pending: Vec<u8> = self
+            .nodes
+            .borrow()
+            .iter()
+            .filter(|n| !n.interviewed)
+            .map(|n| n.id)
+            .collect();
+
+        let mut tracker = ProgressTracker::new(Operation::Interview, &pending);
+        self.progress
+            .borrow()
+            .notify(&tracker.event("discovered nodes"));
+
+        for &i in &pending {
+            self.progress.borrow().notify(&tracker.update(
+                i,
+                NodeProgress::Running,
+                "node information",
+            ));
+        }
+
+        let interviews = self
+            .driver
+            .run(move |d| Ok(interview::interview(d, &pending, &known)))?;
+
+        // store the information at the nodes
+        for interview in interviews {
+            let i = interview.id;
+
+            let status = {
+                let mut nodes = self.nodes.borrow_mut();
+                let node = match nodes.iter_mut().find(|n| n.id == i) {
+                    Some(node) => node,
+                    None => continue,
+                };
+
+                match interview.info {
+                    Ok((types, cmds)) => {
+                        node.types = types;
+                        node.cmds = cmds;
+                        node.protocol_info = interview.protocol_info;
+                        node.interviewed = true;
+                        if let Some(info) = interview.protocol_info {
+                            self.protocol_infos.lock().unwrap().insert(i, info);
+                        }
+                        NodeProgress::Done
+                    }
+                    Err(_) => match node.update_node_info() {
+                        Ok(()) => NodeProgress::Done,
+                        Err(_) => NodeProgress::Failed,
+                    },
+                }
+            };
+            self.progress
+                .borrow()
+                .notify(&tracker.update(i, status, "node information"));
+        }
+
+        // inform the listeners about the changed nodes
+        for event in diff.events() {
+            self.node_events.borrow().notify(&event);
+        }
+
+        Ok(diff)
+    }
+
+    /// This function returns the defined node and a mutable reference
+    /// to the z-wave driver.
+    ///
+    /// A node of a lazy controller is interviewed, when it's returned the
+    /// first time.
+    pub fn node<I>(&mut self, id: I) -> Option<Node<D>>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+
+        // loop over all nodes and check if the id exist
+        for n in self.nodes.borrow_mut().iter_mut() {
+            if id == n.get_id() {
+                if !n.interviewed {
+                    let _ = n.update_node_info();
+                }
+
+                // return the node with the id
+                return Some(n.clone());
+            }
+        }
+
+        // when no id was found return nothing
+        None
+    }
+
+    /// Interview the node with the given id again, to update its device types
+    /// and command classes.
+    pub fn interview_node<I>(&self, id: I) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+
+        // update the stored node, which is handed out by `node()`
+        match self
+            .nodes
+            .borrow_mut()
+            .iter_mut()
+            .find(|n| n.get_id() == id)
+        {
+            Some(node) => node.update_node_info(),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} doesn't exist", id),
+            )),
+        }
+    }
+
+    /// Set the security class the node with the given id was included with.
+    pub fn set_node_security_class<I>(&self, id: I, class: SecurityClass) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+
+        // update the stored node, which is handed out by `node()`
+        match self
+            .nodes
+            .borrow_mut()
+            .iter_mut()
+            .find(|n| n.get_id() == id)
+        {
+            Some(node) => {
+                node.set_security_class(class);
+                Ok(())
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} doesn't exist", id),
+            )),
+        }
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Use the S0 network key, which the nodes were included with. The
+    /// commands to the nodes with the security class S0 are encrypted with
+    /// it.
+    pub fn set_s0_key(&self, key: NetworkKey) {
+        self.security.lock().unwrap().set_key(&key);
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Use the network key of the S2 security class, which the nodes were
+    /// included with. The commands to the nodes with the class are
+    /// encrypted with it.
+    pub fn set_s2_key(&self, class: SecurityClass, key: NetworkKey) -> Result<(), Error> {
+        self.security.lock().unwrap().set_s2_key(class, &key)
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Request the command classes, which the node only supports encrypted,
+    /// and add them to the command classes of the node. The node needs the
+    /// security class S0 for it.
+    pub fn read_secure_commands<I>(&self, id: I) -> Result<Vec<CommandClass>, Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+        let node = self
+            .nodes
+            .borrow()
+            .iter()
+            .find(|n| n.get_id() == id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The node {} doesn't exist", id),
+                )
+            })?;
+        let secure = node.security_commands_get()?;
+
+        if let Some(node) = self
+            .nodes
+            .borrow_mut()
+            .iter_mut()
+            .find(|n| n.get_id() == id)
+        {
+            for cmd in &secure {
+                if !node.cmds.contains(cmd) {
+                    node.cmds.push(*cmd);
+                }
+            }
+        }
+        Ok(secure)
+    }
+
+    /// Set the name of the node with the given id, `None` removes the name.
+    pub fn set_node_name<I>(&self, id: I, name: Option<String>) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+
+        // update the stored node, which is handed out by `node()`
+        match self
+            .nodes
+            .borrow_mut()
+            .iter_mut()
+            .find(|n| n.get_id() == id)
+        {
+            Some(node) => {
+                node.set_name(name);
+                Ok(())
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} doesn't exist", id),
+            )),
+        }
+    }
+
+    /// Set the overrides of the node with the given id, which are applied to
+    /// all messages the node gets.
+    pub fn set_node_overrides<I>(&self, id: I, overrides: NodeOverrides) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+
+        match self
+            .nodes
+            .borrow_mut()
+            .iter_mut()
+            .find(|n| n.get_id() == id)
+        {
+            Some(node) => {
+                node.set_overrides(overrides);
+                Ok(())
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} doesn't exist", id),
+            )),
+        }
+    }
+
+    /// Returns the retry policy, which is used for all nodes without their own.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        *self.retry_policy.lock().unwrap()
+    }
+
+    /// Set the retry policy, which is used for all nodes without their own.
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        *self.retry_policy.lock().unwrap() = policy;
+    }
+
+    /// Set the retry policy of the node with the given id, `None` uses
+    /// the one of the controller.
+    pub fn set_node_retry_policy<I>(&self, id: I, policy: Option<RetryPolicy>) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
 
-        // create a node object for each id
-        for i in ids {
-            // create the node for the given id
+        match self
+            .nodes
+            .borrow_mut()
+            .iter_mut()
+            .find(|n| n.get_id() == id)
+        {
+            Some(node) => {
+                node.set_retry_policy(policy);
+                Ok(())
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} doesn't exist", id),
+            )),
+        }
+    }
+
+    /// Set the basic value of a group of nodes with as few messages as possible.
+    ///
+    /// When all nodes are listening and not included securely, the value is sent
+    /// as one multicast message. Otherwise each node gets its own message.
+    /// Multicast messages are not acknowledged, that's why the value of each node
+    /// is read back afterwards and nodes which missed it get a singlecast message.
+    ///
+    /// Returns the ids of the nodes which didn't confirm the value.
+    pub fn group_set<V>(&self, ids: &[u8], value: V) -> Result<Vec<u8>, Error>
+    where
+        V: Into<u8>,
+    {
+        let value = value.into();
+
+        // get the nodes of the group
+        let mut nodes = vec![];
+        for id in ids {
+            match self.nodes.borrow().iter().find(|n| n.get_id() == *id) {
+                Some(node) => nodes.push(node.clone()),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("The node {} doesn't exist", id),
+                    ))
+                }
+            }
+        }
+
+        // multicast is only possible for listening nodes without encryption
+        let multicast = nodes.len() > 1
+            && nodes.iter().all(|n| {
+                !n.security_class().is_secure()
+                    && n.capabilities().map(|c| c.listening).unwrap_or(false)
+            });
+
+        // send the value to all nodes at once
+        if multicast {
+            let ids = ids.to_vec();
+            let message = Basic::set(0x00, value)?;
+            self.driver.run(move |d| d.write_multi(&ids, message))?;
+        }
+
+        // confirm the value of each node and fall back to singlecast
+        let mut failed = vec![];
+        for node in nodes {
+            if multicast && Controller::<D>::confirm_basic(&node, value) {
+                continue;
+            }
+
+            if node.basic_set(value).is_err() || !Controller::<D>::confirm_basic(&node, value) {
+                failed.push(node.get_id());
+            }
+        }
+
+        Ok(failed)
+    }
+
+    /// Check if the node reports the basic value which was set.
+    ///
+    /// Nodes report the actual level for the value 0xFF, so only on
+    /// and off are compared.
+    fn confirm_basic(node: &Node<D>, value: u8) -> bool {
+        match node.basic_get() {
+            Ok(v) => (v == 0x00) == (value == 0x00),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the messages, which wait for the driver or are transmitted
+    /// right now, to find stuck or backed-up nodes.
+    pub fn queue_snapshot(&self) -> QueueSnapshot {
+        self.queue.lock().unwrap().snapshot()
+    }
+
+    /// Returns the progress listeners, to follow long running operations
+    /// like the interview of the nodes.
+    pub fn progress(&self) -> RefMut<'_, Progress> {
+        self.progress.borrow_mut()
+    }
+
+    /// Returns the node event listeners, which get informed about the nodes
+    /// found or missed by `discover_nodes()`.
+    pub fn node_events(&self) -> RefMut<'_, NodeEvents> {
+        self.node_events.borrow_mut()
+    }
+
+    /// Returns the scenes of the controller, to define, load or save them.
+    pub fn scenes(&self) -> RefMut<'_, Scenes> {
+        self.scenes.borrow_mut()
+    }
+
+    /// Activate the scene with the given name.
+    ///
+    /// All nodes which should get the same value are set together with
+    /// `group_set`, so the values are sent by multicast and confirmed.
+    /// Returns the ids of the nodes which didn't confirm the value.
+    pub fn scene_activate(&self, name: &str) -> Result<Vec<u8>, Error> {
+        // get the targets of the scene
+        let targets = match self.scenes.borrow().get(name) {
+            Some(scene) => scene.targets.clone(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The scene {} doesn't exist", name),
+                ))
+            }
+        };
+
+        // group the nodes by their value
+        let mut groups: Vec<(u8, Vec<u8>)> = vec![];
+        for (node, value) in targets {
+            match groups.iter_mut().find(|g| g.0 == value) {
+                Some(group) => group.1.push(node),
+                None => groups.push((value, vec![node])),
+            }
+        }
+
+        // set the value of each group
+        let mut failed = vec![];
+        for (value, ids) in groups {
+            failed.append(&mut self.group_set(&ids, value)?);
+        }
+
+        // inform the listeners
+        self.scenes.borrow().notify(name, &failed);
+
+        Ok(failed)
+    }
+
+    /// Returns the scheduler of the controller, to add, cancel, load or
+    /// save the scheduled commands.
+    pub fn scheduler(&self) -> RefMut<'_, Scheduler> {
+        self.scheduler.borrow_mut()
+    }
+
+    /// Send the scheduled commands, which are due. This needs to be called
+    /// regularly, like every few seconds. Returns the ids of the jobs and
+    /// the results of their commands.
+    ///
+    /// The values of the sleeping nodes are read when they wake up.
+    pub fn run_scheduled(&self) -> Vec<(u64, Result<(), Error>)> {
+        let busy = {
+            let snapshot = self.queue_snapshot();
+            snapshot.transmitting.is_some() || !snapshot.pending.is_empty()
+        };
+        let due = self
+            .scheduler
+            .borrow_mut()
+            .take_due(time::SystemTime::now(), busy);
+
+        due.into_iter()
+            .map(|job| (job.id, self.run_action(job.action, job.priority)))
+            .collect()
+    }
+
+    /// Send the command of a scheduled job with its priority
+    fn run_action(&self, action: Action, priority: Priority) -> Result<(), Error> {
+        let node = |id: u8| {
             self.nodes
+                .borrow()
+                .iter()
+                .find(|n| n.get_id() == id)
+                .cloned()
+                .map(|mut node| {
+                    node.set_priority(priority);
+                    node
+                })
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("The node {} doesn't exist", id),
+                    )
+                })
+        };
+
+        match action {
+            Action::BasicSet(id, value) => node(id)?.basic_set(value).map(|_| ()),
+            Action::SwitchBinarySet(id, on) => node(id)?.switch_binary_set(on).map(|_| ()),
+            Action::SwitchMultilevelSet(id, level) => {
+                node(id)?.switch_multilevel_set(level, None).map(|_| ())
+            }
+            Action::Refresh(id, cmd_class) => {
+                node(id)?.get_when_awake(move |n| n.refresh(cmd_class), |_| {});
+                Ok(())
+            }
+            Action::SceneActivate(name) => self.scene_activate(&name).map(|_| ()),
+        }
+    }
+
+    /// Returns the cached association groups of all nodes.
+    pub fn associations(&self) -> Ref<'_, Associations> {
+        self.associations.borrow()
+    }
+
+    /// Read all association groups from all nodes and cache them.
+    pub fn association_refresh(&self) -> Result<(), Error> {
+        let nodes = self.nodes.borrow().clone();
+
+        for node in nodes {
+            let groups = Associations::read(&node)?;
+            self.associations
                 .borrow_mut()
-                .push(Node::new(self.driver.clone(), i as u8));
+                .replace_node(node.get_id(), groups);
         }
 
-        // when everything went well, return no error
-        Ok(())
-    }
-    /// This function returns the defined node and a mutable reference
-    /// to the z-wave driver.
-    pub fn node<I>(&mut self, id: I) -> Option<Node<D>>
-    where
-        I: Into<u8>,
-    {
-        let id = id.into();
+        Ok(())
+    }
+
+    /// Make sure the wanted associations exist.
+    ///
+    /// Only the associations which are missing in the cache are set. The
+    /// changed groups are read back afterwards to update the cache.
+    /// Returns the associations which were added.
+    pub fn association_ensure(&self, wanted: &[Association]) -> Result<Vec<Association>, Error> {
+        let missing = self.associations.borrow().missing(wanted);
+
+        for association in &missing {
+            let node = self
+                .nodes
+                .borrow()
+                .iter()
+                .find(|n| n.get_id() == association.node)
+                .cloned()
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("The node {} doesn't exist", association.node),
+                    )
+                })?;
+
+            // set the association and read the group back
+            let group = Associations::add(&node, association)?;
+            self.associations.borrow_mut().update(group);
+        }
+
+        Ok(missing)
+    }
+
+    /// Return the home id of the network.
+    pub fn home_id(&self) -> u32 {
+        self.info.home_id
+    }
+
+    /// Return the node id of the controller itself.
+    pub fn get_id(&self) -> u8 {
+        self.info.node_id
+    }
+
+    /// Return the information about the controller, which was gathered
+    /// when the controller was created or joined another network.
+    pub fn info(&self) -> &ControllerInfo {
+        &self.info
+    }
+
+    /// Restart the Z-Wave chip of the controller, like to recover a controller
+    /// which stopped answering. A stick which re-enumerates on the USB bus is
+    /// opened again by the driver, until the controller announced its start.
+    ///
+    /// The answers and callbacks on their way are lost, so the transmissions
+    /// which wait for them fail.
+    pub fn soft_reset(&self) -> Result<(), Error> {
+        self.driver.run(|d| d.soft_reset())
+    }
+
+    /// Update the firmware of the controller stick with a Gecko bootloader
+    /// image (`.gbl` file).
+    ///
+    /// Only 700 and 800 series controllers can be updated this way. The progress
+    /// of the upload is reported to the listeners. The controller restarts with
+    /// the new firmware, so a new `Controller` should be created afterwards.
+    pub fn update_controller_firmware(&self, image: &[u8]) -> Result<(), Error> {
+        // the version looks like `Z-Wave 7.15`
+        let series = self
+            .info
+            .version
+            .trim_start_matches("Z-Wave ")
+            .split('.')
+            .next()
+            .and_then(|v| v.parse::<u8>().ok());
+        if series.map(|s| s < 7).unwrap_or(true) {
+            return Err(Error::new(
+                ErrorKind::NotImplemented,
+                "The firmware update is only supported for 700 and 800 series controllers",
+            ));
+        }
+
+        let id = self.info.node_id;
+        let mut tracker = ProgressTracker::new(Operation::FirmwareUpdate, &[id]);
+        let running = tracker.update(id, NodeProgress::Running, "upload");
+
+        // the driver reports the progress, while the image is uploaded
+        let (sender, progress) = mpsc::channel();
+        let image = image.to_vec();
+        let answer = self.driver.submit(move |d| {
+            d.update_firmware(&image, |done, total| {
+                let _ = sender.send((done, total));
+            })
+        })?;
+
+        for (done, total) in progress {
+            self.progress.borrow().notify(&ProgressEvent {
+                percent: (done * 100 / total) as u8,
+                ..running.clone()
+            });
+        }
+        let result = answer
+            .recv()
+            .unwrap_or_else(|_| Err(Error::NoController("The driver thread stopped".into())));
+
+        let status = match result {
+            Ok(_) => NodeProgress::Done,
+            Err(_) => NodeProgress::Failed,
+        };
+        self.progress
+            .borrow()
+            .notify(&tracker.update(id, status, "upload"));
+
+        result
+    }
+
+    /// Associate the lifeline group of a Z-Wave Plus node with the controller,
+    /// so the node starts to send its unsolicited reports.
+    ///
+    /// Nodes with end points get a Multi Channel association, so the reports
+    /// of each end point can be distinguished. The inclusion sets it up for
+    /// the new nodes. Returns false for nodes which aren't Z-Wave Plus.
+    pub fn lifeline_setup<I>(&self, id: I) -> Result<bool, Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+
+        // get the command classes of the node
+        let cmds = self
+            .nodes
+            .borrow()
+            .iter()
+            .find(|n| n.get_id() == id)
+            .map(|n| n.get_commands())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The node {} doesn't exist", id),
+                )
+            })?;
+
+        // only Z-Wave Plus nodes have a lifeline in group 1
+        if !cmds.contains(&CommandClass::ZWAVEPLUS_INFO)
+            || !(cmds.contains(&CommandClass::ASSOCIATION)
+                || cmds.contains(&CommandClass::MULTI_INSTANCE_ASSOCIATION))
+        {
+            return Ok(false);
+        }
+
+        // report to the root end point of the controller, when the node has end points
+        let endpoint = if cmds.contains(&CommandClass::MULTI_INSTANCE)
+            && cmds.contains(&CommandClass::MULTI_INSTANCE_ASSOCIATION)
+        {
+            Some(0x00)
+        } else {
+            None
+        };
+
+        self.association_ensure(&[Association {
+            node: id,
+            group: 0x01,
+            target: self.info.node_id,
+            endpoint,
+        }])?;
+
+        Ok(true)
+    }
+
+    /// Returns the states of the nodes, to register listeners for changes.
+    pub fn node_states(&self) -> RefMut<'_, NodeStates> {
+        self.states.borrow_mut()
+    }
+
+    /// Returns the amount of getters and commands, which wait for the
+    /// sleeping node to wake up.
+    pub fn pending_when_awake<I>(&self, id: I) -> usize
+    where
+        I: Into<u8>,
+    {
+        self.awake.lock().unwrap().pending(id.into())
+    }
+
+    /// Return the state of the node with the given id.
+    pub fn node_state<I>(&self, id: I) -> NodeState
+    where
+        I: Into<u8>,
+    {
+        self.states.borrow().state(id.into())
+    }
+
+    /// Returns if the controller marked the node as failed, because it
+    /// didn't acknowledge the last frames. A failed node stays failed until
+    /// it's reachable again or removed from the network.
+    pub fn is_node_failed<I>(&self, id: I) -> Result<bool, Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+        self.driver.run(move |d| d.is_failed_node(id))
+    }
+
+    /// Update the node states with the activity recorded by the driver.
+    ///
+    /// This should be called regularly. When a listening node is considered
+    /// dead and the controller didn't mark it as failed yet, the node gets
+    /// pinged once more, so the controller can mark it as failed. Returns the
+    /// nodes which changed their state.
+    pub fn update_node_states(&self) -> Result<Vec<(u8, NodeState)>, Error> {
+        let activity = self.driver.run(|d| d.take_activity())?;
+
+        let mut changed = vec![];
+        for entry in activity {
+            let id = match entry {
+                NodeActivity::Transmit(id, _) => id,
+                NodeActivity::Received(id) => {
+                    self.statistics.lock().unwrap().node_mut(id).seen();
+                    id
+                }
+                NodeActivity::WakeUp(id) => {
+                    self.statistics.lock().unwrap().node_mut(id).woke_up();
+
+                    let node = self
+                        .nodes
+                        .borrow()
+                        .iter()
+                        .find(|n| n.get_id() == id)
+                        .cloned();
+
+                    // the node listens now, send the commands and run the
+                    // getters which waited for it
+                    self.awake.lock().unwrap().set_awake(id, true);
+                    let commands = self.awake.lock().unwrap().take_commands(id);
+                    if let Some(ref node) = node {
+                        node.send_batch(commands).ok();
+                    }
+                    let pending = self.awake.lock().unwrap().take(id);
+                    for getter in pending {
+                        getter();
+                    }
+
+                    // let the node go back to sleep
+                    if let Some(node) = node {
+                        if node.get_commands().contains(&CommandClass::WAKE_UP) {
+                            node.wake_up_no_more_information().ok();
+                        }
+                    }
+                    self.awake.lock().unwrap().set_awake(id, false);
+                    id
+                }
+            };
+
+            // messages to sleeping nodes fail until they wake up
+            let sleeping = self
+                .nodes
+                .borrow()
+                .iter()
+                .find(|n| n.get_id() == id)
+                .and_then(|n| n.capabilities())
+                .map(|c| c.is_sleeping())
+                .unwrap_or(false);
 
-        // loop over all nodes and check if the id exist
-        for n in self.nodes.borrow().iter() {
-            if id == n.get_id() {
-                // return the node with the id
-                return Some(n.clone());
+            let old = self.states.borrow().state(id);
+            let state = match self.states.borrow_mut().update(&entry, sleeping) {
+                Some(state) => state,
+                None => continue,
+            };
+            // the states are released, so the listeners can read them
+            self.states.borrow().notify(id, old, state);
+
+            // let the controller check the node, the result is part of the next update
+            if state == NodeState::Dead {
+                self.driver.run(move |d| {
+                    if !d.is_failed_node(id)? {
+                        let m_id = d.write(Message::from_array(
+                            id,
+                            CommandClass::NO_OPERATION,
+                            0x00,
+                            [],
+                        ))?;
+                        d.wait_transmit(m_id)?;
+                    }
+                    Ok(())
+                })?;
             }
+
+            changed.push((id, state));
         }
 
-        // when no id was found return nothing
-        None
+        Ok(changed)
     }
 
     /// Return all node ids
@@ -107,21 +1179,109 @@ where
             .collect::<Vec<u8>>()
     }
 
-    pub fn handle_messages(&self, h: Box<dyn Fn(SerialMsg) + Send>) {
-        let driver = self.driver.clone();
-        let duration = time::Duration::from_millis(50);
+    /// Return the ids of all nodes, which support the command class.
+    pub fn nodes_supporting(&self, cmd_class: CommandClass) -> Vec<u8> {
+        self.nodes
+            .borrow()
+            .iter()
+            .filter(|n| n.cmds.contains(&cmd_class))
+            .map(|n| n.id)
+            .collect()
+    }
 
-        thread::spawn(move || loop {
+    /// Return the ids of all nodes with the generic device type, like all
+    /// thermostats. Nodes without protocol information are skipped.
+    pub fn nodes_of_type(&self, generic: GenericType) -> Vec<u8> {
+        self.nodes
+            .borrow()
+            .iter()
+            .filter(|n| n.protocol_info.map(|i| i.generic) == Some(generic))
+            .map(|n| n.id)
+            .collect()
+    }
+
+    /// Return the ids of all nodes with the specific device type.
+    pub fn nodes_of_specific_type(&self, specific: SpecificType) -> Vec<u8> {
+        self.nodes
+            .borrow()
+            .iter()
+            .filter(|n| n.protocol_info.map(|i| i.specific) == Some(specific))
+            .map(|n| n.id)
+            .collect()
+    }
+
+    /// Answer the Time Get and the Date Get of the nodes in the background
+    /// with the clock of the host. The offset of the local time to UTC is
+    /// given in seconds. The messages are read like with `handle_messages`.
+    pub fn answer_time_requests(&self, utc_offset: i64) {
+        let driver = self.driver.downgrade();
+
+        self.handle_messages(Box::new(move |msg| {
+            if msg.func != SerialMsgFunction::ApplicationCommandHandler {
+                return;
+            }
+
+            let now = DateTime::local(time::SystemTime::now(), utc_offset);
+            // the requests may be encapsulated, like with a checksum
+            if let Some(answer) = IncomingFrame::parse(&msg.data)
+                .and_then(|frame| Encapsulation::unwrap(frame, &mut Reassembly::new()))
+                .ok()
+                .flatten()
+                .and_then(|command| Time::answer(&command.frame, &now))
             {
-                let mut m_driver = driver.lock().unwrap();
+                if let Some(driver) = driver.upgrade() {
+                    driver.run(move |d| d.write(answer)).ok();
+                }
+            }
+        }));
+    }
+
+    /// Decode the commands, which the nodes send on their own, like the
+    /// button events of a remote, and pass them with the id of the node to
+    /// the handler. The messages are read like with `handle_messages`.
+    pub fn handle_reports(&self, h: Box<dyn Fn(u8, Report) + Send>) {
+        self.handle_messages(Box::new(move |msg| {
+            if msg.func != SerialMsgFunction::ApplicationCommandHandler {
+                return;
+            }
 
-                loop {
-                    match m_driver.read() {
-                        Ok(msg) => h(msg),
-                        Err(_) => break,
+            if let Ok(frame) = IncomingFrame::parse(&msg.data) {
+                // the reports of a multi command are handed over one by one
+                match Report::parse(&frame) {
+                    Ok(Report::Multi(reports)) => {
+                        for report in reports {
+                            h(frame.source, report);
+                        }
                     }
+                    Ok(report) => h(frame.source, report),
+                    Err(_) => {}
                 }
             }
+        }));
+    }
+
+    pub fn handle_messages(&self, h: Box<dyn Fn(SerialMsg) + Send>) {
+        // the thread stops with the driver, when the controller is dropped
+        let driver = self.driver.downgrade();
+        let duration = time::Duration::from_millis(50);
+
+        thread::spawn(move || loop {
+            // read the pending messages, but handle them outside of the driver thread
+            let messages = match driver.upgrade() {
+                Some(driver) => driver.run(|d| {
+                    let mut messages = vec![];
+                    while let Ok(msg) = d.read() {
+                        messages.push(msg);
+                    }
+                    Ok(messages)
+                }),
+                None => return,
+            };
+
+            match messages {
+                Ok(messages) => messages.into_iter().for_each(&h),
+                Err(_) => return,
+            }
 
             thread::sleep(duration);
         });
@@ -135,39 +1295,89 @@ pub struct Node<D>
 where
     D: Driver,
 {
-    driver: Arc<Mutex<D>>,
+    driver: DriverHandle<D>,
     id: u8,
+    endpoint: u8,
+    name: Option<String>,
     types: Vec<GenericType>,
     cmds: Vec<CommandClass>,
+    protocol_info: Option<NodeProtocolInfo>,
+    security_class: SecurityClass,
+    retry_policy: Option<RetryPolicy>,
+    priority: Priority,
+    overrides: NodeOverrides,
+    default_retry_policy: Arc<Mutex<RetryPolicy>>,
+    queue: Arc<Mutex<TransmitQueue>>,
+    statistics: Arc<Mutex<Statistics>>,
+    awake: Arc<Mutex<AwakeQueue>>,
+    values: Arc<Mutex<Values>>,
+    security: Arc<Mutex<SecurityContext>>,
+    protocol_infos: Arc<Mutex<HashMap<u8, NodeProtocolInfo>>>,
+    interviewed: bool,
 }
 
 impl<D> Node<D>
 where
-    D: Driver,
+    D: Driver + Send + 'static,
 {
     // Create a new node.
-    pub fn new(driver: Arc<Mutex<D>>, id: u8) -> Node<D> {
-        let mut node = Node {
-            driver: driver,
-            id: id,
-            types: vec![],
-            cmds: vec![],
-        };
+    pub fn new(driver: DriverHandle<D>, id: u8) -> Node<D> {
+        let mut node = Node::uninterviewed(driver, id);
 
-        // update the node information
-        node.update_node_info();
+        // update the node information, a node which doesn't answer yet
+        // is interviewed later
+        let _ = node.update_node_info();
 
         node
     }
 
+    /// Create a node, which knows nothing about itself yet.
+    fn uninterviewed(driver: DriverHandle<D>, id: u8) -> Node<D> {
+        Node {
+            driver,
+            id,
+            endpoint: 0,
+            name: None,
+            types: vec![],
+            cmds: vec![],
+            protocol_info: None,
+            security_class: SecurityClass::None,
+            retry_policy: None,
+            priority: Priority::Normal,
+            overrides: NodeOverrides::new(),
+            default_retry_policy: Arc::new(Mutex::new(RetryPolicy::new())),
+            queue: Arc::new(Mutex::new(TransmitQueue::new())),
+            statistics: Arc::new(Mutex::new(Statistics::new())),
+            awake: Arc::new(Mutex::new(AwakeQueue::new())),
+            values: Arc::new(Mutex::new(Values::new())),
+            security: Arc::new(Mutex::new(SecurityContext::new(0, 0))),
+            protocol_infos: Arc::new(Mutex::new(HashMap::new())),
+            interviewed: false,
+        }
+    }
+
     /// Updates the information of the node
     pub fn update_node_info(&mut self) -> Result<(), Error> {
+        // a failed interview isn't repeated, until it's requested
+        self.interviewed = true;
+
         // convert it
         let (types, cmds) = self.node_info_get()?;
 
         self.types = types;
         self.cmds = cmds;
 
+        // get the protocol information from the controller, when it isn't known yet
+        let id = self.id;
+        let cached = self.protocol_infos.lock().unwrap().get(&id).cloned();
+        let info = match cached {
+            Some(info) => info,
+            None => self.driver.run(move |d| d.get_node_protocol_info(id))?,
+        };
+
+        self.protocol_infos.lock().unwrap().insert(id, info);
+        self.protocol_info = Some(info);
+
         Ok(())
     }
 
@@ -180,41 +1390,685 @@ where
         self.cmds.clone()
     }
 
+    /// Returns if the node was interviewed, a node of a lazy controller is
+    /// interviewed when it's used the first time.
+    pub fn is_interviewed(&self) -> bool {
+        self.interviewed
+    }
+
+    /// Returns the name of the node, like the room it's located in.
+    pub fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    /// Set the name of the node.
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    /// Returns a handle to the end point of the node. All commands sent through
+    /// the handle are encapsulated in a Multi Channel Command Encapsulation.
+    ///
+    /// The end point 0 addresses the root device, like the node itself.
+    pub fn endpoint(&self, endpoint: u8) -> Endpoint<D> {
+        let mut node = self.clone();
+        node.endpoint = endpoint;
+
+        Endpoint::new(node)
+    }
+
+    /// Discover the end points of a Multi Channel node and return a handle
+    /// for each of them, with its device class and command classes. The
+    /// aggregated end points are not part of the list.
+    pub fn endpoints(&self) -> Result<Vec<Endpoint<D>>, Error> {
+        let count = self.multi_channel_endpoints_get()?;
+
+        let mut endpoints: Vec<Endpoint<D>> = vec![];
+        for index in 1..=count.individual {
+            // identical end points are only requested once
+            let capability = match endpoints.first().and_then(|e| e.capability()) {
+                Some(first) if count.identical => EndpointCapability {
+                    endpoint: index,
+                    ..first.clone()
+                },
+                _ => self.multi_channel_capability_get(index)?,
+            };
+
+            let mut node = self.clone();
+            node.endpoint = index;
+            endpoints.push(Endpoint::discovered(node, capability));
+        }
+
+        Ok(endpoints)
+    }
+
+    /// The Multi Channel End Point Get Command is used to request the amount
+    /// of end points.
+    pub fn multi_channel_endpoints_get(&self) -> Result<MultiChannelEndpoints, Error> {
+        // Send the command and convert the answer
+        MultiChannel::endpoint_report(&self.request(MultiChannel::endpoint_get(self.id))?)
+    }
+
+    /// The Multi Channel Capability Get Command is used to request the device
+    /// class and the command classes of an end point.
+    pub fn multi_channel_capability_get(&self, endpoint: u8) -> Result<EndpointCapability, Error> {
+        // Send the command and convert the answer
+        MultiChannel::capability_report(
+            &self.request(MultiChannel::capability_get(self.id, endpoint))?,
+        )
+    }
+
+    /// Returns the capabilities of the node, like if the node is always
+    /// listening or needs to be woken up first.
+    pub fn capabilities(&self) -> Option<NodeCapabilities> {
+        self.protocol_info.map(|i| i.capabilities)
+    }
+
+    /// Returns if the node sleeps and only listens after it woke up.
+    fn is_sleeping(&self) -> bool {
+        self.capabilities()
+            .map(|c| c.is_sleeping())
+            .unwrap_or(false)
+    }
+
+    /// Returns the security class the node was included with.
+    pub fn security_class(&self) -> SecurityClass {
+        self.security_class
+    }
+
+    /// Set the security class the node was included with. All following
+    /// commands are encapsulated based on this class.
+    pub fn set_security_class(&mut self, class: SecurityClass) {
+        self.security_class = class;
+    }
+
+    /// Returns the retry policy of the node, which is the one of the
+    /// controller, when the node has none on its own. The overrides of the
+    /// node are applied on top.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let policy = match self.retry_policy {
+            Some(policy) => policy,
+            None => *self.default_retry_policy.lock().unwrap(),
+        };
+
+        self.overrides.apply(policy)
+    }
+
+    /// Set the retry policy of the node, `None` uses the one of the controller.
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry_policy = policy;
+    }
+
+    /// Returns the priority, which the messages of the node are queued with.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Set the priority, which the messages of the node are queued with.
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+
+    /// Returns the route the last message was successfully sent over, or
+    /// `None` when the controller doesn't know a route to the node yet.
+    pub fn last_working_route(&self) -> Result<Option<Route>, Error> {
+        let id = self.id;
+        self.driver.run(move |d| d.get_last_working_route(id))
+    }
+
+    /// Forget the last working route to the node, so the controller searches
+    /// a new one for the next message.
+    pub fn clear_route(&self) -> Result<(), Error> {
+        let id = self.id;
+        self.driver.run(move |d| d.clear_route(id))
+    }
+
+    /// Returns the statistics of the node.
+    pub fn statistics(&self) -> NodeStatistics {
+        self.statistics.lock().unwrap().node(self.id)
+    }
+
+    /// Run the getter as soon as the node is awake and hand its result to
+    /// the callback.
+    ///
+    /// A sleeping node only answers after it woke up, so the getter is stored
+    /// until `Controller::update_node_states` sees the wake up of the node.
+    /// For all other nodes, the getter runs right away.
+    ///
+    /// ```rust,ignore
+    /// node.get_when_awake(Node::meter_get, |value| println!("{:?}", value));
+    /// ```
+    pub fn get_when_awake<T, G, F>(&self, getter: G, callback: F)
+    where
+        D: Send + 'static,
+        G: FnOnce(&Node<D>) -> Result<T, Error> + Send + 'static,
+        F: FnOnce(Result<T, Error>) + Send + 'static,
+    {
+        if !self.is_sleeping() || self.awake.lock().unwrap().is_awake(self.id) {
+            return callback(getter(self));
+        }
+
+        let node = self.clone();
+        self.awake
+            .lock()
+            .unwrap()
+            .push(self.id, move || callback(getter(&node)));
+    }
+
+    /// Read the basic value, as soon as the node is awake.
+    pub fn basic_get_when_awake<F>(&self, callback: F)
+    where
+        D: Send + 'static,
+        F: FnOnce(Result<u8, Error>) + Send + 'static,
+    {
+        self.get_when_awake(Node::basic_get, callback)
+    }
+
+    #[cfg(feature = "cc-meter")]
+    /// Read the meter value, as soon as the node is awake.
+    pub fn meter_get_when_awake<F>(&self, callback: F)
+    where
+        D: Send + 'static,
+        F: FnOnce(Result<MeterData, Error>) + Send + 'static,
+    {
+        self.get_when_awake(Node::meter_get, callback)
+    }
+
+    /// Returns the last value the node reported for the command class,
+    /// without sending anything to the node.
+    pub fn cached_value(&self, cmd_class: CommandClass) -> Option<CachedValue> {
+        self.values
+            .lock()
+            .unwrap()
+            .get(self.id, self.endpoint, cmd_class)
+    }
+
+    /// Returns the last known value of the command class right away. With
+    /// `refresh`, the value is read again in the background, so the next
+    /// call returns the new value. Sleeping nodes are read when they wake up.
+    pub fn try_get(&self, cmd_class: CommandClass, refresh: bool) -> Option<CachedValue>
+    where
+        D: Send + 'static,
+    {
+        if refresh {
+            let node = self.clone();
+            thread::spawn(move || node.get_when_awake(move |n| n.refresh(cmd_class), |_| {}));
+        }
+
+        self.cached_value(cmd_class)
+    }
+
+    /// Read the value of the command class from the node and return it.
+    /// The value is stored in the cache, like with the getters.
+    pub fn refresh(&self, cmd_class: CommandClass) -> Result<Value, Error> {
+        match cmd_class {
+            CommandClass::BASIC => self.basic_get().map(Value::Level),
+            CommandClass::SWITCH_BINARY => self.switch_binary_get().map(Value::Switch),
+            CommandClass::SWITCH_MULTILEVEL => self.switch_multilevel_get().map(Value::Level),
+            #[cfg(feature = "cc-meter")]
+            CommandClass::METER => self.meter_get().map(Value::Meter),
+            CommandClass::BATTERY => self.battery_get().map(|b| Value::Level(b.percent())),
+            CommandClass::SENSOR_MULTILEVEL => self.sensor_multilevel_get().map(Value::Sensor),
+            _ => Err(Error::UnsupportedCommandClass(cmd_class)),
+        }
+    }
+
+    /// Store the reported value in the cache and return it.
+    fn cache<T, F>(
+        &self,
+        cmd_class: CommandClass,
+        value: Result<T, Error>,
+        f: F,
+    ) -> Result<T, Error>
+    where
+        T: Copy,
+        F: FnOnce(T) -> Value,
+    {
+        if let Ok(v) = value {
+            self.values
+                .lock()
+                .unwrap()
+                .update(self.id, self.endpoint, cmd_class, f(v));
+        }
+
+        value
+    }
+
+    /// Returns the overrides, which are applied to all messages of the node.
+    pub fn overrides(&self) -> NodeOverrides {
+        self.overrides
+    }
+
+    /// Set the overrides, which are applied to all messages of the node.
+    pub fn set_overrides(&mut self, overrides: NodeOverrides) {
+        self.overrides = overrides;
+    }
+
+    /// Returns the options the messages are sent to the node with.
+    fn transmit_options(&self) -> TransmitOptions {
+        self.overrides.transmit_options.unwrap_or_default()
+    }
+
+    /// Send a message to the node and return the message id. A supervised
+    /// message is only sent, when the node reported that it executed it.
+    ///
+    /// The messages to a sleeping node are stored, until it wakes up. The
+    /// message id is 0 for such a stored message.
+    fn send(&self, message: Message) -> Result<u8, Error> {
+        if self.is_sleeping() && !self.awake.lock().unwrap().is_awake(self.id) {
+            // the commands to the node itself are sent together
+            if self.endpoint == 0 {
+                self.awake.lock().unwrap().push_command(self.id, message);
+                return Ok(0);
+            }
+
+            let node = self.clone();
+            self.awake.lock().unwrap().push(self.id, move || {
+                node.send(message).ok();
+            });
+            return Ok(0);
+        }
+
+        let wrapped = self.encapsulate(message.clone(), true)?;
+        let options = self.transmit_options();
+        let report_timeout = self.overrides.report_timeout;
+        let cmd_class = message.cmd_class;
+        let security = self.security.clone();
+        let id = self.id;
+
+        self.transmit(&message, move |driver| {
+            let m_id = Node::write_frames(driver, &wrapped, options, &security, report_timeout)?;
+
+            let session = match wrapped.session {
+                Some(session) => session,
+                None => return Ok(m_id),
+            };
+
+            // the other commands of the node are kept for the receive path
+            let mut others = vec![];
+            let status = loop {
+                let command = match Node::read_command(driver, id, report_timeout, &security) {
+                    Ok(command) => command,
+                    Err(e) => break Err(e),
+                };
+                match Supervision::report(&command.frame) {
+                    Ok((s, status)) if s == session => break Ok(status),
+                    _ => others.push(SerialMsg::new(
+                        SerialMsgType::Request,
+                        SerialMsgFunction::ApplicationCommandHandler,
+                        command.frame.to_vec(),
+                    )),
+                }
+            };
+            driver.unread(others);
+
+            match status? {
+                SupervisionStatus::Success | SupervisionStatus::Working => Ok(m_id),
+                SupervisionStatus::NoSupport => Err(Error::UnsupportedCommandClass(cmd_class)),
+                SupervisionStatus::Fail => Err(Error::new(
+                    ErrorKind::UnknownZWave,
+                    "The node couldn't execute the command",
+                )),
+            }
+        })
+    }
+
+    /// Send the commands, which waited for the node to wake up. They are
+    /// packed into as few frames as possible, when the node supports it.
+    fn send_batch(&self, messages: Vec<Message>) -> Result<(), Error> {
+        let frames = if self.cmds.contains(&CommandClass::MULTI_CMD) {
+            let pipeline = Encapsulation::for_node(&self.cmds, self.security_class, self.endpoint);
+            MultiCmd::pack(messages, pipeline.overhead())?
+        } else {
+            messages
+        };
+
+        for frame in frames {
+            self.send(frame)?;
+        }
+        Ok(())
+    }
+
+    /// Send a message to the node and read the answer.
+    fn request(&self, message: Message) -> Result<IncomingFrame, Error> {
+        let wrapped = self.encapsulate(message.clone(), false)?;
+
+        let options = self.transmit_options();
+        let report_timeout = self.overrides.report_timeout;
+        let endpoint = self.endpoint;
+        let security = self.security.clone();
+        let id = self.id;
+
+        let frame = self.transmit(&message, move |driver| {
+            // Send the command
+            Node::write_frames(driver, &wrapped, options, &security, report_timeout)?;
+            // read the answer, far nodes may get more time for it
+            let command = Node::read_command(driver, id, report_timeout, &security)?;
+            if endpoint > 0 && command.endpoint != endpoint {
+                return Err(Error::parse(
+                    CommandClass::MULTI_INSTANCE,
+                    format!("Answer came from the end point {}", command.endpoint),
+                ));
+            }
+            Ok(command.frame)
+        })?;
+
+        self.statistics.lock().unwrap().node_mut(self.id).seen();
+        Ok(frame)
+    }
+
+    /// Write the frames of a wrapped message and return the message id of
+    /// the last one. The frames for S0 and S2 are encrypted right before,
+    /// the ones for S2 are segmented afterwards.
+    fn write_frames(
+        driver: &mut D,
+        wrapped: &Wrapped,
+        options: TransmitOptions,
+        security: &Mutex<SecurityContext>,
+        report_timeout: Option<Duration>,
+    ) -> Result<u8, Error> {
+        let mut m_id = 0;
+        for frame in &wrapped.frames {
+            let frames = match wrapped.security {
+                SecurityClass::None => vec![frame.clone()],
+                SecurityClass::S0 => vec![Node::encrypt_frame(
+                    driver,
+                    frame.clone(),
+                    security,
+                    options,
+                    report_timeout,
+                )?],
+                class => wrapped.segment(Node::encrypt_s2_frame(
+                    driver,
+                    frame.clone(),
+                    class,
+                    security,
+                    options,
+                    report_timeout,
+                )?)?,
+            };
+            for frame in frames {
+                m_id = driver.write_with_options(frame, options)?;
+            }
+        }
+        Ok(m_id)
+    }
+
+    /// Read the next command of the node, decrypt and unwrap it. The
+    /// segments of a datagram are read, until it's complete. A segment,
+    /// which doesn't arrive, is requested again. The commands of other
+    /// nodes, which arrive meanwhile, are put back for the receive path.
+    fn read_command(
+        driver: &mut D,
+        node: u8,
+        report_timeout: Option<Duration>,
+        security: &Mutex<SecurityContext>,
+    ) -> Result<Unwrapped, Error> {
+        let deadline = report_timeout.map(|timeout| Instant::now() + timeout);
+        let mut others = vec![];
+        let command = Node::read_command_of(driver, node, deadline, security, &mut others);
+
+        driver.unread(others);
+        command
+    }
+
+    /// Read the command of the node until the deadline, the commands of
+    /// other nodes are collected.
+    fn read_command_of(
+        driver: &mut D,
+        node: u8,
+        deadline: Option<Instant>,
+        security: &Mutex<SecurityContext>,
+        others: &mut Vec<SerialMsg>,
+    ) -> Result<Unwrapped, Error> {
+        let mut reassembly = Reassembly::new();
+        let mut requests = 0;
+
+        loop {
+            let frame = match deadline {
+                Some(deadline) => {
+                    Node::wait_frame(driver, deadline.saturating_duration_since(Instant::now()))
+                }
+                None => driver.read_frame(),
+            };
+            let frame = match (frame, reassembly.segment_request()) {
+                (Ok(frame), _) => frame,
+                (Err(Error::Timeout { .. }), Some(request)) if requests < MAX_SEGMENT_REQUESTS => {
+                    requests += 1;
+                    driver.write(request)?;
+                    continue;
+                }
+                (Err(e), _) => return Err(e),
+            };
+            if frame.source != node {
+                others.push(SerialMsg::new(
+                    SerialMsgType::Request,
+                    SerialMsgFunction::ApplicationCommandHandler,
+                    frame.to_vec(),
+                ));
+                continue;
+            }
+
+            let command = Encapsulation::unwrap_with(frame, &mut reassembly, |frame| {
+                Node::decrypt_frame(driver, frame, security)
+            });
+            // a corrupt frame doesn't abort the request
+            if let Ok(Some(command)) = command {
+                // the node can't handle the request right now, the queued
+                // ones are answered later
+                if command.frame.command_class == Ok(CommandClass::APPLICATION_STATUS) {
+                    match ApplicationStatus::error(ApplicationStatus::report(&command.frame)?) {
+                        Some(e) => return Err(e),
+                        None => continue,
+                    }
+                }
+
+                // the node waits for the confirmation of its segments
+                if let Some(session) = command.transport_session {
+                    driver.write(TransportService::segment_complete(
+                        command.frame.source,
+                        session,
+                    ))?;
+                }
+                // the node waits for the status of its supervised command
+                if let Some(session) = command.session {
+                    driver.write(Supervision::report_message(
+                        command.frame.source,
+                        session,
+                        SupervisionStatus::Success,
+                    ))?;
+                }
+                return Ok(command);
+            }
+        }
+    }
+
+    /// Queue the message, run the transmission with the retry policy of the
+    /// node and record the result in the statistics.
+    fn transmit<T, F>(&self, message: &Message, f: F) -> Result<T, Error>
+    where
+        F: Fn(&mut D) -> Result<T, Error> + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let ticket = QueueTicket::new(&self.queue, self.id, message, self.priority);
+        let mut attempts = 0;
+        let mut start = Instant::now();
+        let f = Arc::new(f);
+
+        let result = self.retry_policy().run(|| {
+            ticket.start();
+            attempts += 1;
+            start = Instant::now();
+            let f = f.clone();
+            self.driver.run(move |driver| f(driver))
+        });
+
+        self.statistics
+            .lock()
+            .unwrap()
+            .node_mut(self.id)
+            .record(attempts, result.as_ref().ok().map(|_| start.elapsed()));
+
+        result
+    }
+
+    /// Read the next application command from the driver, until the
+    /// timeout elapsed.
+    fn wait_frame(driver: &mut D, timeout: Duration) -> Result<IncomingFrame, Error> {
+        let start = Instant::now();
+
+        loop {
+            match driver.read_frame() {
+                Ok(frame) => return Ok(frame),
+                Err(_) if start.elapsed() < timeout => thread::sleep(Duration::from_millis(50)),
+                Err(_) => {
+                    return Err(Error::Timeout {
+                        stage: Stage::Report,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Read the next application command from the driver, like the
+    /// following reports of a request. It's unwrapped like the answer.
+    fn receive(&self) -> Result<IncomingFrame, Error> {
+        let report_timeout = self.overrides.report_timeout;
+        let security = self.security.clone();
+        let id = self.id;
+        self.driver.run(move |d| {
+            Node::read_command(d, id, report_timeout, &security).map(|command| command.frame)
+        })
+    }
+
+    /// Encapsulate the message for the end point, the command classes and
+    /// the security class of the node.
+    fn encapsulate(&self, message: Message, supervised: bool) -> Result<Wrapped, Error> {
+        // only the commands without an answer are supervised
+        let mut pipeline = Encapsulation::for_node(&self.cmds, self.security_class, self.endpoint);
+        pipeline.supervision &= supervised;
+        pipeline.wrap(message)
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// The Security Commands Supported Get Command is used to request the command classes,
+    /// which the node only supports encrypted. The reports of many command classes are merged.
+    pub fn security_commands_get(&self) -> Result<Vec<CommandClass>, Error> {
+        // Send the command and convert the answer
+        let (mut follow, mut cmds) = Security::commands_supported_report(
+            &self.request(Security::commands_supported_get(self.id))?,
+        )?;
+
+        // many command classes are split up into multiple reports
+        while follow > 0 {
+            let (next, more) = Security::commands_supported_report(&self.receive()?)?;
+            cmds.extend(more);
+            follow = next;
+        }
+
+        Ok(cmds)
+    }
+
     /// This function returns the GenericType for the node and the CommandClass.
     pub fn node_info_get(&self) -> Result<(Vec<GenericType>, Vec<CommandClass>), Error> {
-        let mut driver = self.driver.lock().unwrap();
+        let message = NodeInfo::get(self.id);
 
-        // Send the command
-        driver.write(NodeInfo::get(self.id))?;
+        let request = message.clone();
+        let msg = self.transmit(&message, move |driver| {
+            // Send the command
+            driver.write(request.clone())?;
+
+            // the node information arrives as an update, other messages like
+            // the reports of the nodes are kept for the receive path
+            let mut others = vec![];
+            let msg = loop {
+                match driver.read() {
+                    Ok(msg) if msg.func == SerialMsgFunction::ApplicationUpdate => break Ok(msg),
+                    Ok(msg) => others.push(msg),
+                    Err(e) => break Err(e),
+                }
+            };
 
-        // Receive the result
-        let msg = driver.read()?;
+            driver.unread(others);
+            msg
+        })?;
+        self.statistics.lock().unwrap().node_mut(self.id).seen();
 
         // convert and return it
         NodeInfo::report(msg.data)
     }
 
-    /// This function sets the basic status of the node.
+    /// Send a No Operation frame to check if the node is reachable, before
+    /// real commands are sent. Returns if the node acknowledged the frame.
+    ///
+    /// A sleeping node only acknowledges the frame while it's awake.
+    pub fn ping(&self) -> Result<bool, Error> {
+        Ok(self.ping_report()?.status == TransmitStatus::CompleteOk)
+    }
+
+    /// Send a No Operation frame and return the transmit report of the
+    /// controller, with the route and the time it took.
+    pub fn ping_report(&self) -> Result<TransmitReport, Error> {
+        let id = self.id;
+        let report = self.driver.run(move |d| {
+            let m_id = d.write(Message::from_array(
+                id,
+                CommandClass::NO_OPERATION,
+                0x00,
+                [],
+            ))?;
+            d.wait_transmit(m_id)
+        })?;
+
+        if report.status == TransmitStatus::CompleteOk {
+            self.statistics.lock().unwrap().node_mut(id).seen();
+        }
+        Ok(report)
+    }
+
+    /// This function sets the basic status of the node. The set is sent
+    /// with the primary command class of the node, when it supports one.
     pub fn basic_set<V>(&self, value: V) -> Result<u8, Error>
     where
         V: Into<u8>,
     {
         // Send the command
-        self.driver
-            .lock()
-            .unwrap()
-            .write(Basic::set(self.id, value.into()))
+        match self.basic_mapping() {
+            Some(cmd_class) => self.send(Basic::mapped_set(self.id, cmd_class, value.into())?),
+            None => self.send(Basic::set(self.id, value.into())?),
+        }
     }
 
+    /// This function reads the basic status of the node. The value is
+    /// cached under the primary command class of the node, when it
+    /// supports one.
     pub fn basic_get(&self) -> Result<u8, Error> {
-        let mut driver = self.driver.lock().unwrap();
-        // Send the command
-        driver.write(Basic::get(self.id))?;
-        // read the answer and convert it
-        match driver.read() {
-            Ok(msg) => Basic::report(msg.data),
-            Err(err) => Err(err),
+        // Send the command and convert the answer
+        let value = Basic::report(&self.request(Basic::get(self.id))?);
+
+        match self.basic_mapping() {
+            Some(cmd_class @ CommandClass::SWITCH_MULTILEVEL) => {
+                self.cache(cmd_class, value, Value::Level)
+            }
+            Some(cmd_class) => self.cache(cmd_class, value, |v| Value::Switch(v > 0)),
+            None => self.cache(CommandClass::BASIC, value, Value::Level),
+        }
+    }
+
+    /// Returns the primary command class of the node, which the basic
+    /// commands are mapped to. The end points and the nodes without the
+    /// command class keep the basic commands.
+    fn basic_mapping(&self) -> Option<CommandClass> {
+        if self.endpoint > 0 {
+            return None;
         }
+
+        let generic = self
+            .protocol_info
+            .map(|i| i.generic)
+            .or_else(|| self.types.first().cloned())?;
+
+        Basic::mapped_class(generic).filter(|c| self.cmds.contains(c))
     }
 
     /// The Binary Switch Command Class is used to control devices with On/Off
@@ -226,10 +2080,7 @@ where
         V: Into<bool>,
     {
         // Send the command
-        self.driver
-            .lock()
-            .unwrap()
-            .write(SwitchBinary::set(self.id, value))
+        self.send(SwitchBinary::set(self.id, value))
     }
 
     /// The Binary Switch Command Class is used to control devices with On/Off
@@ -237,30 +2088,27 @@ where
     ///
     /// The Binary Switch Get command, version 1 is used to request the status
     /// of a device with On/Off or Enable/Disable capability.
-    pub fn switch_binary_get(&self) -> Result<bool, Error> {
-        let mut driver = self.driver.lock().unwrap();
-        // Send the command
-        driver.write(SwitchBinary::get(self.id))?;
-        // read the answer and convert it
-        match driver.read() {
-            Ok(msg) => SwitchBinary::report(msg.data),
-            Err(err) => Err(err),
-        }
+    pub fn switch_binary_get(&self) -> Result<bool, Error> {
+        // Send the command and convert the answer
+        let value = SwitchBinary::report(&self.request(SwitchBinary::get(self.id))?);
+        self.cache(CommandClass::SWITCH_BINARY, value, Value::Switch)
     }
 
     /// The Multilevel Switch Command Class is used to control devices with variable levels
     /// such as dimmer switches
     ///
-    /// The Multilevel Switch Set command, version 1 is used to set a u8 value.
-    pub fn switch_multilevel_set<V>(&self, value: V) -> Result<u8, Error>
+    /// The Multilevel Switch Set command is used to set a value between 0-99 or 0xFF,
+    /// optionally with the duration to reach the value.
+    pub fn switch_multilevel_set<V>(
+        &self,
+        value: V,
+        duration: Option<time::Duration>,
+    ) -> Result<u8, Error>
     where
         V: Into<u8>,
     {
         // Send the command
-        self.driver
-            .lock()
-            .unwrap()
-            .write(SwitchMultilevel::set(self.id, value))
+        self.send(SwitchMultilevel::set(self.id, value, duration)?)
     }
 
     /// The Multilevel Switch Command Class is used to control devices with variable levels
@@ -269,16 +2117,49 @@ where
     /// The Multilevel Switch Get command, version 1 is used to request the status
     /// of a device with variable levels capability.
     pub fn switch_multilevel_get(&self) -> Result<u8, Error> {
-        let mut driver = self.driver.lock().unwrap();
+        // Send the command and convert the answer, unknown levels are no valid value
+        let value = SwitchMultilevel::report(&self.request(SwitchMultilevel::get(self.id))?)
+            .and_then(|level| {
+                check_level(level).map_err(|_| {
+                    Error::parse(
+                        CommandClass::SWITCH_MULTILEVEL,
+                        format!("The level {} is not between 0-99 or 0xFF", level),
+                    )
+                })
+            });
+        self.cache(CommandClass::SWITCH_MULTILEVEL, value, Value::Level)
+    }
+
+    /// The Multilevel Switch Command Class is used to control devices with variable levels
+    /// such as dimmer switches
+    ///
+    /// The Multilevel Switch Start Level Change command is used to start dimming up or down,
+    /// optionally from a start level and with the duration from the lowest to the highest level.
+    pub fn switch_multilevel_start_level_change(
+        &self,
+        up: bool,
+        start_level: Option<u8>,
+        duration: Option<time::Duration>,
+    ) -> Result<u8, Error> {
         // Send the command
-        driver.write(SwitchMultilevel::get(self.id))?;
-        // read the answer and convert it
-        match driver.read() {
-            Ok(msg) => SwitchMultilevel::report(msg.data),
-            Err(err) => Err(err),
-        }
+        self.send(SwitchMultilevel::start_level_change(
+            self.id,
+            up,
+            start_level,
+            duration,
+        )?)
+    }
+
+    /// The Multilevel Switch Command Class is used to control devices with variable levels
+    /// such as dimmer switches
+    ///
+    /// The Multilevel Switch Stop Level Change command is used to stop dimming.
+    pub fn switch_multilevel_stop_level_change(&self) -> Result<u8, Error> {
+        // Send the command
+        self.send(SwitchMultilevel::stop_level_change(self.id))
     }
 
+    #[cfg(feature = "cc-powerlevel")]
     /// The Powerlevel Set Command is used to set the power level indicator value,
     /// which should be used by the node when transmitting RF, and the timeout for
     /// this power level indicator value before returning the power level defined
@@ -291,27 +2172,19 @@ where
         T: Into<u8>,
     {
         // Send the command
-        self.driver
-            .lock()
-            .unwrap()
-            .write(PowerLevel::set(self.id, status, seconds))
+        self.send(PowerLevel::set(self.id, status, seconds))
     }
 
+    #[cfg(feature = "cc-powerlevel")]
     /// This command is used to advertise the current power level.
     ///
     /// Return the Powerlevel status and the time left on this power level.
     pub fn powerlevel_get(&self) -> Result<(PowerLevelStatus, u8), Error> {
-        let mut driver = self.driver.lock().unwrap();
-        // Send the command
-        driver.write(PowerLevel::get(self.id))?;
-
-        // read the answer and convert it
-        match driver.read() {
-            Ok(msg) => PowerLevel::report(msg.data),
-            Err(err) => Err(err),
-        }
+        // Send the command and convert the answer
+        PowerLevel::report(&self.request(PowerLevel::get(self.id))?)
     }
 
+    #[cfg(feature = "cc-powerlevel")]
     /// The Powerlevel Test Node Set Command is used to instruct the destination node to transmit
     /// a number of test frames to the specified NodeID with the RF power level specified. After
     /// the test frame transmissions the RF power level is reset to normal and the result (number
@@ -335,7 +2208,7 @@ where
         F: Into<u16>,
     {
         // Send the command
-        self.driver.lock().unwrap().write(PowerLevel::test_node_set(
+        self.send(PowerLevel::test_node_set(
             self.id,
             test_node_id,
             level,
@@ -343,26 +2216,17 @@ where
         ))
     }
 
+    #[cfg(feature = "cc-powerlevel")]
     /// This command is used to report the latest result of a test frame
     /// transmission started by the Powerlevel Test Node Set Command.
     ///
     /// Return the test node id, status of operation and the test frane count.
     pub fn powerlevel_test_node_get(&self) -> Result<(u8, PowerLevelOperationStatus, u16), Error> {
-        let mut driver = self.driver.lock().unwrap();
-
-        // Send the command
-        self.driver
-            .lock()
-            .unwrap()
-            .write(PowerLevel::test_node_get(self.id))?;
-
-        // read the answer and convert it
-        match driver.read() {
-            Ok(msg) => PowerLevel::test_node_report(msg.data),
-            Err(err) => Err(err),
-        }
+        // Send the command and convert the answer
+        PowerLevel::test_node_report(&self.request(PowerLevel::test_node_get(self.id))?)
     }
 
+    #[cfg(feature = "cc-meter")]
     /// A meter is used to monitor a resource. The meter accumulates the resource flow over time.
     /// As an option, the meter may report not only the most recent accumulated reading but also
     /// the previous reading and the time that elapsed since then. A meter may also be able to
@@ -371,17 +2235,12 @@ where
     /// The Meter Get Command is used to request the accumulated consumption in physical units
     /// from a metering device.
     pub fn meter_get(&self) -> Result<MeterData, Error> {
-        let mut driver = self.driver.lock().unwrap();
-        // Send the command
-        driver.write(Meter::get(self.id))?;
-
-        // read the answer and convert it
-        match driver.read() {
-            Ok(msg) => Meter::report(msg.data),
-            Err(err) => Err(err),
-        }
+        // Send the command and convert the answer
+        let value = Meter::report(&self.request(Meter::get(self.id))?);
+        self.cache(CommandClass::METER, value, Value::Meter)
     }
 
+    #[cfg(feature = "cc-meter")]
     /// A meter is used to monitor a resource. The meter accumulates the resource flow over time.
     /// As an option, the meter may report not only the most recent accumulated reading but also
     /// the previous reading and the time that elapsed since then. A meter may also be able to
@@ -389,19 +2248,642 @@ where
     ///
     /// The Meter Get Command is used to request the accumulated consumption in physical units
     /// from a metering device.
-    pub fn meter_get_v2<S>(&self, meter_type: S) -> Result<(MeterData, u16, MeterData), Error>
+    pub fn meter_get_v2<S>(&self, unit: S) -> Result<(MeterData, u16, MeterData), Error>
     where
-        S: Into<MeterData>,
+        S: Into<MeterUnit>,
     {
-        let mut driver = self.driver.lock().unwrap();
+        // Send the command and convert the answer
+        Meter::report_v2(&self.request(Meter::get_v2(self.id, unit.into()))?)
+    }
+
+    #[cfg(feature = "cc-meter")]
+    /// The Meter Supported Get Command is used to request the meter type, the supported scales
+    /// and if the meter can be reset. Check the scales before requesting one with
+    /// `meter_get_v2`.
+    pub fn meter_supported_get(&self) -> Result<MeterSupported, Error> {
+        // Send the command and convert the answer
+        Meter::supported_report(&self.request(Meter::supported_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-meter")]
+    /// A pulse meter only counts the pulses, like the pulse output of a water or gas meter,
+    /// without knowing the physical unit.
+    ///
+    /// The Meter Pulse Get Command is used to request the number of pulses, which the meter
+    /// counted.
+    pub fn meter_pulse_get(&self) -> Result<u32, Error> {
+        // Send the command and convert the answer
+        MeterPulse::report(&self.request(MeterPulse::get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-meter")]
+    /// The Meter Table Point Adm. Number Get Command is used to request the administration
+    /// number of a table meter, like the supply point number of the utility.
+    pub fn meter_table_adm_number_get(&self) -> Result<String, Error> {
+        // Send the command and convert the answer
+        MeterTableMonitor::table_point_adm_no_report(
+            &self.request(MeterTableMonitor::table_point_adm_no_get(self.id))?,
+        )
+    }
+
+    #[cfg(feature = "cc-meter")]
+    /// The Meter Table Status Depth Get Command is used to request the operating status of a
+    /// table meter and the last events of its log. The reports of long logs are merged.
+    pub fn meter_table_status_get(&self, depth: u8) -> Result<MeterTableStatus, Error> {
+        // Send the command and convert the answer
+        let mut status = MeterTableMonitor::status_report(
+            &self.request(MeterTableMonitor::status_depth_get(self.id, depth))?,
+        )?;
+
+        // long logs are split up into multiple reports
+        while status.reports_to_follow > 0 {
+            let next = MeterTableMonitor::status_report(&self.receive()?)?;
+            status.events.extend(next.events);
+            status.reports_to_follow = next.reports_to_follow;
+        }
+
+        Ok(status)
+    }
+
+    #[cfg(feature = "cc-meter")]
+    /// The Meter Table Current Data Get Command is used to request the current values of the
+    /// datasets, which are set in the bit mask. The reports of many datasets are merged.
+    pub fn meter_table_current_data_get(&self, datasets: u32) -> Result<MeterTableData, Error> {
+        // Send the command and convert the answer
+        let mut data = MeterTableMonitor::current_data_report(
+            &self.request(MeterTableMonitor::current_data_get(self.id, datasets)?)?,
+        )?;
+
+        // many datasets are split up into multiple reports
+        while data.reports_to_follow > 0 {
+            let next = MeterTableMonitor::current_data_report(&self.receive()?)?;
+            data.values.extend(next.values);
+            data.reports_to_follow = next.reports_to_follow;
+        }
+
+        Ok(data)
+    }
+
+    /// The Association Set Command is used to add the nodes to the group,
+    /// like the controller to the lifeline group 1.
+    pub fn association_set(&self, group: u8, nodes: &[u8]) -> Result<u8, Error> {
+        // Send the command
+        self.send(AssociationCmd::set(self.id, group, nodes)?)
+    }
+
+    /// The Association Get Command is used to request the nodes of the group.
+    /// The reports of large groups are merged.
+    pub fn association_get(&self, group: u8) -> Result<AssociationReport, Error> {
+        // the group is read like for the cache, without the end points
+        let group = Associations::read_group(self, group, false)?;
+
+        Ok(AssociationReport {
+            group: group.group,
+            max_nodes: group.max_nodes,
+            reports_to_follow: 0,
+            nodes: group.nodes,
+        })
+    }
+
+    /// The Association Remove Command is used to remove the nodes from the
+    /// group. When no nodes are given, all nodes are removed.
+    pub fn association_remove(&self, group: u8, nodes: &[u8]) -> Result<u8, Error> {
+        // Send the command
+        self.send(AssociationCmd::remove(self.id, group, nodes)?)
+    }
+
+    /// The Configuration Set Command is used to set the value of a parameter.
+    /// The size need to be 1, 2 or 4 bytes, like the manufacturer defined it.
+    pub fn config_set(&self, parameter: u8, value: i32, size: u8) -> Result<u8, Error> {
+        // Send the command
+        self.send(Configuration::set(self.id, parameter, value, size)?)
+    }
+
+    /// The Configuration Get Command is used to request the value of a
+    /// parameter.
+    pub fn config_get(&self, parameter: u8) -> Result<i32, Error> {
+        // Send the command and convert the answer
+        let (number, value) =
+            Configuration::report(&self.request(Configuration::get(self.id, parameter))?)?;
+
+        if number != parameter {
+            return Err(Error::parse(
+                CommandClass::CONFIGURATION,
+                format!("Answer contained the parameter {}", number),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    /// The Battery Get Command is used to request the level of a battery.
+    pub fn battery_get(&self) -> Result<BatteryLevel, Error> {
+        // Send the command and convert the answer
+        let value = Battery::report(&self.request(Battery::get(self.id))?);
+        self.cache(CommandClass::BATTERY, value, |b| Value::Level(b.percent()))
+    }
+
+    /// The Multilevel Sensor Get Command is used to request the default value
+    /// of the sensor.
+    pub fn sensor_multilevel_get(&self) -> Result<SensorValue, Error> {
+        // Send the command and convert the answer
+        let value = SensorMultilevel::report(&self.request(SensorMultilevel::get(self.id))?);
+        self.cache(CommandClass::SENSOR_MULTILEVEL, value, Value::Sensor)
+    }
+
+    /// The Multilevel Sensor Get Command is used to request the value in the
+    /// given unit, for sensors which measure more than one value.
+    pub fn sensor_multilevel_get_unit(&self, unit: Unit) -> Result<SensorValue, Error> {
+        // Send the command and convert the answer
+        SensorMultilevel::report(&self.request(SensorMultilevel::get_v5(self.id, unit)?)?)
+    }
+
+    /// The Alarm Sensor Get Command is used to request the state of an
+    /// alarm sensor, which doesn't support the notifications.
+    pub fn sensor_alarm_get(
+        &self,
+        sensor_type: AlarmSensorType,
+    ) -> Result<AlarmSensorReport, Error> {
+        // Send the command and convert the answer
+        SensorAlarm::report(&self.request(SensorAlarm::get(self.id, sensor_type))?)
+    }
+
+    /// The Alarm Sensor Supported Get Command is used to request the sensor
+    /// types of the node.
+    pub fn sensor_alarm_supported(&self) -> Result<Vec<AlarmSensorType>, Error> {
+        // Send the command and convert the answer
+        SensorAlarm::supported_report(&self.request(SensorAlarm::supported_get(self.id))?)
+    }
+
+    /// The Sensor Trigger Level Set Command is used to set the value, at
+    /// which the sensor triggers.
+    pub fn sensor_trigger_level_set(&self, value: SensorValue, precision: u8) -> Result<u8, Error> {
+        // Send the command
+        self.send(SensorConfiguration::set(self.id, value, precision)?)
+    }
+
+    /// The Sensor Trigger Level Set Command is used to reset the trigger
+    /// level of the sensor, which measures in the unit, to its default.
+    pub fn sensor_trigger_level_default(&self, unit: Unit) -> Result<u8, Error> {
+        // Send the command
+        self.send(SensorConfiguration::set_default(self.id, unit)?)
+    }
+
+    /// The Sensor Trigger Level Get Command is used to request the trigger
+    /// level of the sensor.
+    pub fn sensor_trigger_level_get(&self) -> Result<SensorValue, Error> {
+        // Send the command and convert the answer
+        SensorConfiguration::report(&self.request(SensorConfiguration::get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-firmware-update")]
+    /// The Firmware Meta Data Get Command is used to request the ids and the
+    /// checksum of the current firmware, which are needed for an update.
+    pub fn firmware_metadata_get(&self) -> Result<FirmwareMetadata, Error> {
+        // Send the command and convert the answer
+        FirmwareUpdate::metadata_report(&self.request(FirmwareUpdate::metadata_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-sound-switch")]
+    /// Request the name and the duration of all tones of a siren or a
+    /// doorbell.
+    pub fn sound_switch_tones(&self) -> Result<Vec<ToneInfo>, Error> {
+        let count = SoundSwitch::tones_number_report(
+            &self.request(SoundSwitch::tones_number_get(self.id))?,
+        )?;
+
+        (1..=count)
+            .map(|tone| {
+                SoundSwitch::tone_info_report(
+                    &self.request(SoundSwitch::tone_info_get(self.id, tone))?,
+                )
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "cc-sound-switch")]
+    /// The Sound Switch Configuration Set Command is used to set the default
+    /// volume in percent and the default tone. The tone 0 keeps the current
+    /// default tone.
+    pub fn sound_switch_config_set(&self, volume: u8, tone: u8) -> Result<u8, Error> {
+        // Send the command
+        self.send(SoundSwitch::configuration_set(self.id, volume, tone)?)
+    }
+
+    #[cfg(feature = "cc-sound-switch")]
+    /// The Sound Switch Configuration Get Command is used to request the
+    /// default volume and the default tone.
+    pub fn sound_switch_config_get(&self) -> Result<(u8, u8), Error> {
+        // Send the command and convert the answer
+        SoundSwitch::configuration_report(&self.request(SoundSwitch::configuration_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-sound-switch")]
+    /// The Sound Switch Tone Play Set Command is used to play a tone, the
+    /// `DEFAULT_TONE` plays the default tone. Without a volume the default
+    /// volume is used.
+    pub fn sound_switch_play(&self, tone: u8, volume: Option<u8>) -> Result<u8, Error> {
+        // Send the command
+        self.send(SoundSwitch::tone_play_set(self.id, tone, volume)?)
+    }
+
+    #[cfg(feature = "cc-sound-switch")]
+    /// Stop the tone, which is played right now.
+    pub fn sound_switch_stop(&self) -> Result<u8, Error> {
+        // Send the command
+        self.send(SoundSwitch::tone_play_set(self.id, 0x00, None)?)
+    }
+
+    #[cfg(feature = "cc-sound-switch")]
+    /// The Sound Switch Tone Play Get Command is used to request the tone,
+    /// which is played right now. The tone is 0, when nothing plays.
+    pub fn sound_switch_playing(&self) -> Result<u8, Error> {
+        // Send the command and convert the answer
+        SoundSwitch::tone_play_report(&self.request(SoundSwitch::tone_play_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-protection")]
+    /// The Protection Set Command is used to protect the node against the
+    /// control by its buttons and, for version 2 nodes, over the radio.
+    pub fn protection_set(
+        &self,
+        local: LocalProtection,
+        rf: Option<RfProtection>,
+    ) -> Result<u8, Error> {
+        // Send the command
+        self.send(Protection::set(self.id, local, rf))
+    }
+
+    #[cfg(feature = "cc-protection")]
+    /// The Protection Get Command is used to request the protection states.
+    pub fn protection_get(&self) -> Result<ProtectionState, Error> {
+        // Send the command and convert the answer
+        Protection::report(&self.request(Protection::get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-antitheft")]
+    /// The Anti-theft Set Command is used to lock or unlock a portable node
+    /// with the magic code. The hint helps to find the code again.
+    pub fn antitheft_set(
+        &self,
+        enable: bool,
+        magic_code: &[u8],
+        manufacturer: u16,
+        hint: &[u8],
+        locking_entity: Option<u16>,
+    ) -> Result<u8, Error> {
+        // Send the command
+        self.send(AntiTheft::set(
+            self.id,
+            enable,
+            magic_code,
+            manufacturer,
+            hint,
+            locking_entity,
+        )?)
+    }
+
+    #[cfg(feature = "cc-antitheft")]
+    /// The Anti-theft Get Command is used to request the theft protection
+    /// status.
+    pub fn antitheft_get(&self) -> Result<AntiTheftState, Error> {
+        // Send the command and convert the answer
+        AntiTheft::report(&self.request(AntiTheft::get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-humidity")]
+    /// The Humidity Control Setpoint Set Command is used to set the humidity,
+    /// which the humidifier or the dehumidifier keeps.
+    pub fn humidity_setpoint_set(
+        &self,
+        setpoint_type: HumiditySetpointType,
+        value: SensorValue,
+        precision: u8,
+    ) -> Result<u8, Error> {
+        // Send the command
+        self.send(HumidityControlSetpoint::set(
+            self.id,
+            setpoint_type,
+            value,
+            precision,
+        )?)
+    }
+
+    #[cfg(feature = "cc-humidity")]
+    /// The Humidity Control Setpoint Get Command is used to request the
+    /// humidity of the setpoint type.
+    pub fn humidity_setpoint_get(
+        &self,
+        setpoint_type: HumiditySetpointType,
+    ) -> Result<HumiditySetpoint, Error> {
+        // Send the command and convert the answer
+        HumidityControlSetpoint::report(
+            &self.request(HumidityControlSetpoint::get(self.id, setpoint_type))?,
+        )
+    }
+
+    #[cfg(feature = "cc-humidity")]
+    /// The Humidity Control Setpoint Supported Get Command is used to
+    /// request the setpoint types of the node.
+    pub fn humidity_setpoint_supported(&self) -> Result<Vec<HumiditySetpointType>, Error> {
+        // Send the command and convert the answer
+        HumidityControlSetpoint::supported_report(
+            &self.request(HumidityControlSetpoint::supported_get(self.id))?,
+        )
+    }
+
+    #[cfg(feature = "cc-thermostat")]
+    /// The Thermostat Setpoint Set Command is used to set the temperature,
+    /// which the thermostat keeps in the mode of the setpoint type.
+    pub fn thermostat_setpoint_set(
+        &self,
+        setpoint_type: ThermostatSetpointType,
+        value: SensorValue,
+        precision: u8,
+    ) -> Result<u8, Error> {
+        // Send the command
+        self.send(ThermostatSetpoint::set(
+            self.id,
+            setpoint_type,
+            value,
+            precision,
+        )?)
+    }
+
+    #[cfg(feature = "cc-thermostat")]
+    /// The Thermostat Setpoint Get Command is used to request the
+    /// temperature of the setpoint type.
+    pub fn thermostat_setpoint_get(
+        &self,
+        setpoint_type: ThermostatSetpointType,
+    ) -> Result<TemperatureSetpoint, Error> {
+        // Send the command and convert the answer
+        ThermostatSetpoint::report(&self.request(ThermostatSetpoint::get(self.id, setpoint_type))?)
+    }
+
+    #[cfg(feature = "cc-thermostat")]
+    /// The Thermostat Setpoint Supported Get Command is used to request the
+    /// setpoint types of the node.
+    pub fn thermostat_setpoint_supported(&self) -> Result<Vec<ThermostatSetpointType>, Error> {
+        // Send the command and convert the answer
+        ThermostatSetpoint::supported_report(
+            &self.request(ThermostatSetpoint::supported_get(self.id))?,
+        )
+    }
+
+    #[cfg(feature = "cc-central-scene")]
+    /// The Central Scene Supported Get Command is used to request the scenes
+    /// and the key attributes of a remote or a scene keypad.
+    pub fn central_scene_supported_get(&self) -> Result<CentralSceneSupported, Error> {
+        // Send the command and convert the answer
+        CentralScene::supported_report(&self.request(CentralScene::supported_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-central-scene")]
+    /// The Central Scene Configuration Set Command is used to enable the slow
+    /// refresh of the held down notifications.
+    pub fn central_scene_config_set(&self, slow_refresh: bool) -> Result<u8, Error> {
+        // Send the command
+        self.send(CentralScene::configuration_set(self.id, slow_refresh))
+    }
+
+    #[cfg(feature = "cc-central-scene")]
+    /// The Central Scene Configuration Get Command is used to request, if the
+    /// slow refresh is enabled.
+    pub fn central_scene_config_get(&self) -> Result<bool, Error> {
+        // Send the command and convert the answer
+        CentralScene::configuration_report(&self.request(CentralScene::configuration_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-entry-control")]
+    /// The Entry Control Key Supported Get Command is used to request the
+    /// keys of a keypad. The entries are reported as `Report::EntryControl`.
+    pub fn entry_control_keys(&self) -> Result<Vec<char>, Error> {
+        // Send the command and convert the answer
+        EntryControl::key_supported_report(&self.request(EntryControl::key_supported_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-scene-controller")]
+    /// The Scene Controller Configuration Set Command is used to assign a
+    /// scene to a button group of a wall controller. The scene 0 disables the
+    /// group.
+    pub fn scene_controller_set(
+        &self,
+        group: u8,
+        scene: u8,
+        duration: Option<Duration>,
+    ) -> Result<u8, Error> {
+        // Send the command
+        self.send(SceneControllerConf::set(self.id, group, scene, duration)?)
+    }
+
+    #[cfg(feature = "cc-scene-controller")]
+    /// The Scene Controller Configuration Get Command is used to request the
+    /// scene of a button group.
+    pub fn scene_controller_get(&self, group: u8) -> Result<ButtonScene, Error> {
+        // Send the command and convert the answer
+        let button =
+            SceneControllerConf::report(&self.request(SceneControllerConf::get(self.id, group))?)?;
+
+        if button.group != group {
+            return Err(Error::parse(
+                CommandClass::SCENE_CONTROLLER_CONF,
+                format!("Answer contained the group {}", button.group),
+            ));
+        }
+
+        Ok(button)
+    }
+
+    #[cfg(feature = "cc-window-covering")]
+    /// The Window Covering Supported Get Command is used to request the
+    /// movements, which the window covering supports.
+    pub fn window_covering_supported(&self) -> Result<Vec<WindowCoveringParameter>, Error> {
+        // Send the command and convert the answer
+        WindowCovering::supported_report(&self.request(WindowCovering::supported_get(self.id))?)
+    }
+
+    #[cfg(feature = "cc-window-covering")]
+    /// The Window Covering Get Command is used to request the level of a
+    /// movement.
+    pub fn window_covering_get(
+        &self,
+        parameter: WindowCoveringParameter,
+    ) -> Result<WindowCoveringState, Error> {
+        // Send the command and convert the answer
+        let state =
+            WindowCovering::report(&self.request(WindowCovering::get(self.id, parameter))?)?;
+
+        if state.parameter != parameter {
+            return Err(Error::parse(
+                CommandClass::WINDOW_COVERING,
+                format!("Answer contained the parameter {:?}", state.parameter),
+            ));
+        }
+
+        Ok(state)
+    }
+
+    #[cfg(feature = "cc-window-covering")]
+    /// The Window Covering Set Command is used to move one or more movements
+    /// to a level between 0 and 99 at once, like the position and the tilt
+    /// of a venetian blind.
+    pub fn window_covering_set(
+        &self,
+        levels: &[(WindowCoveringParameter, u8)],
+        duration: Option<Duration>,
+    ) -> Result<u8, Error> {
+        // Send the command
+        self.send(WindowCovering::set(self.id, levels, duration)?)
+    }
+
+    #[cfg(feature = "cc-window-covering")]
+    /// The Window Covering Start Level Change Command is used to start moving
+    /// up or down, until it's stopped or the end is reached.
+    pub fn window_covering_start_level_change(
+        &self,
+        parameter: WindowCoveringParameter,
+        up: bool,
+        duration: Option<Duration>,
+    ) -> Result<u8, Error> {
+        // Send the command
+        self.send(WindowCovering::start_level_change(
+            self.id, parameter, up, duration,
+        )?)
+    }
+
+    #[cfg(feature = "cc-window-covering")]
+    /// The Window Covering Stop Level Change Command is used to stop the
+    /// movement.
+    pub fn window_covering_stop_level_change(
+        &self,
+        parameter: WindowCoveringParameter,
+    ) -> Result<u8, Error> {
         // Send the command
-        driver.write(Meter::get_v2(self.id, meter_type.into()))?;
+        self.send(WindowCovering::stop_level_change(self.id, parameter))
+    }
+
+    #[cfg(feature = "cc-thermostat")]
+    /// The Thermostat Operating State Get Command is used to request, if the
+    /// heating or cooling system is running.
+    pub fn thermostat_operating_state_get(&self) -> Result<OperatingState, Error> {
+        // Send the command and convert the answer
+        ThermostatOperatingState::report(&self.request(ThermostatOperatingState::get(self.id))?)
+    }
+
+    /// The Notification Get Command is used to request the last event of the
+    /// notification type. The event 0x00 requests any event of the type.
+    pub fn notification_get(
+        &self,
+        notification_type: u8,
+        event: u8,
+    ) -> Result<NotificationEvent, Error> {
+        // Send the command and convert the answer
+        Notification::report(&self.request(Notification::get(self.id, notification_type, event))?)
+    }
 
-        // read the answer and convert it
-        match driver.read() {
-            Ok(msg) => Meter::report_v2(msg.data),
-            Err(err) => Err(err),
+    /// The Version Get Command is used to request the library type, the
+    /// protocol version and the firmware versions of the node.
+    pub fn version_get(&self) -> Result<VersionReport, Error> {
+        // Send the command and convert the answer
+        Version::report(&self.request(Version::get(self.id))?)
+    }
+
+    /// The Version Command Class Get Command is used to request the version
+    /// of a command class, which the node supports.
+    pub fn command_class_version_get(&self, cmd_class: CommandClass) -> Result<u8, Error> {
+        // Send the command and convert the answer
+        let (answered, version) = Version::command_class_report(
+            &self.request(Version::command_class_get(self.id, cmd_class))?,
+        )?;
+
+        if answered != cmd_class {
+            return Err(Error::parse(
+                CommandClass::VERSION,
+                format!("Answer contained the command class {:?}", answered),
+            ));
         }
+
+        Ok(version)
+    }
+
+    /// Request the version of each command class, which the node supports.
+    pub fn command_class_versions(&self) -> Result<Vec<(CommandClass, u8)>, Error> {
+        self.cmds
+            .iter()
+            .map(|&c| Ok((c, self.command_class_version_get(c)?)))
+            .collect()
+    }
+
+    /// The Wake Up Interval Set Command is used to set the interval of the
+    /// node and the node, which gets the notifications.
+    pub fn wake_up_interval_set(&self, seconds: u32, target: u8) -> Result<u8, Error> {
+        // Send the command
+        self.send(WakeUp::interval_set(self.id, seconds, target)?)
+    }
+
+    /// The Wake Up Interval Get Command is used to request the interval of
+    /// the node.
+    pub fn wake_up_interval_get(&self) -> Result<WakeUpInterval, Error> {
+        // Send the command and convert the answer
+        WakeUp::interval_report(&self.request(WakeUp::interval_get(self.id))?)
+    }
+
+    /// The Wake Up Interval Capabilities Get Command is used to request the
+    /// intervals, which the node supports.
+    pub fn wake_up_capabilities_get(&self) -> Result<WakeUpCapabilities, Error> {
+        // Send the command and convert the answer
+        WakeUp::capabilities_report(&self.request(WakeUp::capabilities_get(self.id))?)
+    }
+
+    /// The Wake Up No More Information Command is used to tell the node,
+    /// that it can go back to sleep.
+    pub fn wake_up_no_more_information(&self) -> Result<u8, Error> {
+        // Send the command
+        self.send(WakeUp::no_more_information(self.id))
+    }
+
+    /// The Time Get and the Date Get Commands are used to request the local
+    /// date and time of the node.
+    pub fn time_get(&self) -> Result<DateTime, Error> {
+        // Send the commands and convert the answers
+        let (hour, minute, second) = Time::report(&self.request(Time::get(self.id))?)?;
+        let (year, month, day) = Time::date_report(&self.request(Time::date_get(self.id))?)?;
+
+        Ok(DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    /// The Clock Set Command is used to set the local time of the node.
+    pub fn clock_set(&self, time: ClockTime) -> Result<u8, Error> {
+        // Send the command
+        self.send(Clock::set(self.id, time)?)
+    }
+
+    /// The Clock Get Command is used to request the local time of the node.
+    pub fn clock_get(&self) -> Result<ClockTime, Error> {
+        // Send the command and convert the answer
+        Clock::report(&self.request(Clock::get(self.id))?)
+    }
+
+    /// The Time Parameters Set Command is used to set the date and the time
+    /// of the node in UTC.
+    pub fn time_parameters_set(&self, time: DateTime) -> Result<u8, Error> {
+        // Send the command
+        self.send(TimeParameters::set(self.id, time))
+    }
+
+    /// The Time Parameters Get Command is used to request the date and the
+    /// time of the node in UTC.
+    pub fn time_parameters_get(&self) -> Result<DateTime, Error> {
+        // Send the command and convert the answer
+        TimeParameters::report(&self.request(TimeParameters::get(self.id))?)
     }
 }
 
@@ -414,8 +2896,244 @@ where
         Node {
             driver: self.driver.clone(),
             id: self.id,
+            endpoint: self.endpoint,
+            name: self.name.clone(),
             types: self.types.clone(),
             cmds: self.cmds.clone(),
+            protocol_info: self.protocol_info,
+            security_class: self.security_class,
+            retry_policy: self.retry_policy,
+            priority: self.priority,
+            overrides: self.overrides,
+            default_retry_policy: self.default_retry_policy.clone(),
+            queue: self.queue.clone(),
+            statistics: self.statistics.clone(),
+            awake: self.awake.clone(),
+            values: self.values.clone(),
+            security: self.security.clone(),
+            protocol_infos: self.protocol_infos.clone(),
+            interviewed: self.interviewed,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::{network, switch};
+    use crate::basic::sim::{SimFailure, SimNode};
+
+    #[test]
+    fn set_and_get() {
+        let (sim, mut controller) = network(vec![
+            switch(2),
+            switch(3).value(CommandClass::SWITCH_BINARY, &[0xFF]),
+        ]);
+        let node = controller.node(2).unwrap();
+
+        node.switch_binary_set(true).unwrap();
+        assert_eq!(sim.value(2, CommandClass::SWITCH_BINARY), Some(vec![0xFF]));
+        assert!(node.switch_binary_get().unwrap());
+
+        assert!(controller.node(3).unwrap().switch_binary_get().unwrap());
+    }
+
+    #[test]
+    fn failures() {
+        let (sim, mut controller) = network(vec![switch(2).respond(
+            CommandClass::SWITCH_BINARY,
+            0x02,
+            0x03,
+            &[0x00],
+        )]);
+        let node = controller.node(2).unwrap();
+        assert!(!node.switch_binary_get().unwrap());
+
+        // a frame with a wrong checksum is skipped
+        sim.report(
+            2,
+            CommandClass::CRC_16_ENCAP,
+            0x01,
+            &[0x25, 0x03, 0xFF, 0x00, 0x00],
+        );
+        assert!(!node.switch_binary_get().unwrap());
+
+        // the node receives the get, but doesn't answer it
+        sim.set_failure(2, Some(SimFailure::NoAnswer));
+        assert!(node.switch_binary_get().is_err());
+        assert_eq!(sim.received(2).last(), Some(&vec![0x25, 0x02]));
+
+        assert!(!controller.is_node_failed(2).unwrap());
+
+        sim.set_failure(2, Some(SimFailure::NoAck));
+        let m_id = node.switch_binary_set(true).unwrap();
+        let mut driver = sim.clone();
+        assert_eq!(
+            driver.wait_transmit(m_id).unwrap().status,
+            TransmitStatus::CompleteNoAck
+        );
+        assert!(controller.is_node_failed(2).unwrap());
+    }
+
+    #[test]
+    fn other_nodes() {
+        let (sim, mut controller) = network(vec![
+            switch(2).respond(CommandClass::SWITCH_BINARY, 0x02, 0x03, &[0x00]),
+            switch(3),
+        ]);
+
+        // the report of another node isn't taken as the answer
+        sim.report(3, CommandClass::SWITCH_BINARY, 0x03, &[0xFF]);
+        assert!(!controller.node(2).unwrap().switch_binary_get().unwrap());
+
+        // it's kept for the receive path
+        let mut driver = sim.clone();
+        let frame = driver.read_frame().unwrap();
+        assert_eq!(frame.source, 3);
+        assert_eq!(frame.payload, vec![0xFF]);
+    }
+
+    #[test]
+    fn node_states() {
+        let (sim, controller) = network(vec![switch(2)]);
+        let controller = Rc::new(controller);
+        let changes = Rc::new(RefCell::new(vec![]));
+
+        // the listener reads the state, which just changed
+        let (c, weak) = (changes.clone(), Rc::downgrade(&controller));
+        controller.node_states().on_change(move |id, _, new| {
+            let stored = weak.upgrade().map(|controller| controller.node_state(id));
+            c.borrow_mut().push((id, new, stored));
+        });
+
+        sim.report(2, CommandClass::BASIC, 0x03, &[0x63]);
+        controller.update_node_states().unwrap();
+        assert_eq!(
+            *changes.borrow(),
+            vec![(2, NodeState::Alive, Some(NodeState::Alive))]
+        );
+    }
+
+    #[test]
+    fn application_status() {
+        let (sim, mut controller) = network(vec![
+            switch(2).respond(CommandClass::SWITCH_BINARY, 0x02, 0x03, &[0xFF]),
+            switch(3),
+        ]);
+
+        // the busy node is asked again
+        let node = controller.node(2).unwrap();
+        sim.report(2, CommandClass::APPLICATION_STATUS, 0x01, &[0x01, 0x00]);
+        assert!(node.switch_binary_get().unwrap());
+        let gets = sim
+            .received(2)
+            .iter()
+            .filter(|f| f[..] == [0x25, 0x02])
+            .count();
+        assert_eq!(gets, 2);
+
+        let node = controller.node(3).unwrap();
+        sim.report(3, CommandClass::APPLICATION_STATUS, 0x02, &[0x00]);
+        assert_eq!(node.switch_binary_get(), Err(Error::Rejected));
+    }
+
+    #[test]
+    fn basic_mapping() {
+        let (sim, mut controller) = network(vec![SimNode::new(2, GenericType::MultiLevelSwitch)
+            .command_classes(&[CommandClass::SWITCH_MULTILEVEL])
+            .value(CommandClass::BASIC, &[0x32])]);
+        let node = controller.node(2).unwrap();
+
+        // the dimmer gets a multilevel set instead of the basic set
+        node.basic_set(0x20).unwrap();
+        assert_eq!(sim.received(2).last(), Some(&vec![0x26, 0x01, 0x20]));
+
+        assert_eq!(node.basic_get().unwrap(), 0x32);
+        assert_eq!(
+            node.cached_value(CommandClass::SWITCH_MULTILEVEL)
+                .map(|c| c.value),
+            Some(Value::Level(0x32))
+        );
+        assert!(node.cached_value(CommandClass::BASIC).is_none());
+    }
+
+    #[test]
+    fn association() {
+        let (sim, mut controller) = network(vec![SimNode::new(4, GenericType::BinarySensor)
+            .command_classes(&[CommandClass::ASSOCIATION])
+            .respond(
+                CommandClass::ASSOCIATION,
+                0x02,
+                0x03,
+                &[0x01, 0x05, 0x00, 0x01],
+            )]);
+        let node = controller.node(4).unwrap();
+
+        // add the controller to the lifeline group
+        node.association_set(1, &[1]).unwrap();
+        assert_eq!(sim.received(4).last(), Some(&vec![0x85, 0x01, 0x01, 0x01]));
+        assert_eq!(node.association_get(1).unwrap().nodes, vec![1]);
+
+        node.association_remove(1, &[]).unwrap();
+        assert_eq!(sim.received(4).last(), Some(&vec![0x85, 0x04, 0x01]));
+    }
+
+    #[cfg(feature = "cc-central-scene")]
+    #[test]
+    fn central_scene() {
+        let (sim, mut controller) = network(vec![SimNode::new(6, GenericType::RemoteSwitch)
+            .command_classes(&[CommandClass::CENTRAL_SCENE])
+            .respond(CommandClass::CENTRAL_SCENE, 0x01, 0x02, &[0x02])]);
+        let supported = controller
+            .node(6)
+            .unwrap()
+            .central_scene_supported_get()
+            .unwrap();
+        assert_eq!(supported.scenes.len(), 2);
+
+        // the remote sends the button events on its own
+        let (tx, rx) = mpsc::channel();
+        controller.handle_reports(Box::new(move |id, report| {
+            tx.send((id, report)).unwrap();
+        }));
+        sim.report(6, CommandClass::CENTRAL_SCENE, 0x03, &[0x01, 0x03, 0x02]);
+
+        let (id, report) = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(id, 6);
+        assert_eq!(
+            report,
+            Report::CentralScene(CentralSceneNotification {
+                sequence: 0x01,
+                scene: 0x02,
+                key: KeyAttribute::Pressed(2),
+                slow_refresh: false,
+            })
+        );
+    }
+
+    #[test]
+    fn ping() {
+        let (sim, mut controller) = network(vec![switch(2)]);
+        let node = controller.node(2).unwrap().clone();
+        assert!(node.ping().unwrap());
+        assert_eq!(sim.received(2).last(), Some(&vec![0x00, 0x00]));
+
+        // the node was unplugged
+        sim.remove_node(2);
+        assert!(!node.ping().unwrap());
+    }
+
+    #[test]
+    fn soft_reset() {
+        let (sim, mut controller) = network(vec![switch(2).latency(Duration::from_millis(200))]);
+        let node = controller.node(2).unwrap().clone();
+
+        // the answer on its way is lost
+        node.send(SwitchBinary::get(2)).unwrap();
+        controller.soft_reset().unwrap();
+        assert!(sim.clone().read().is_err());
+
+        // the controller works after the restart
+        assert!(!node.switch_binary_get().unwrap());
+    }
+}
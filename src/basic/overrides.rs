@@ -0,0 +1,63 @@
+//! Per-node transmit overrides
+//!
+//! Far away nodes need more time to answer, chatty nodes don't need every
+//! message to be retried and some nodes only work with specific transmit
+//! options. The overrides of a node are applied to all messages it gets.
+
+use super::{Backoff, RetryPolicy};
+use crate::defs::TransmitOptions;
+
+use std::time::Duration;
+
+/// The settings which replace the defaults for a single node.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeOverrides {
+    /// The amount of attempts, including the first one
+    pub max_attempts: Option<u32>,
+    /// The time to wait between the attempts
+    pub backoff: Option<Backoff>,
+    /// How long to wait for the report of a getter
+    pub report_timeout: Option<Duration>,
+    /// The options all messages are sent with
+    pub transmit_options: Option<TransmitOptions>,
+}
+
+impl NodeOverrides {
+    /// Create overrides which keep all defaults
+    pub fn new() -> NodeOverrides {
+        NodeOverrides::default()
+    }
+
+    /// Replace the attempts and the backoff of the policy
+    pub fn apply(&self, mut policy: RetryPolicy) -> RetryPolicy {
+        if let Some(attempts) = self.max_attempts {
+            policy = policy.max_attempts(attempts);
+        }
+        if let Some(backoff) = self.backoff {
+            policy = policy.backoff(backoff);
+        }
+
+        policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let overrides = NodeOverrides {
+            max_attempts: Some(1),
+            ..NodeOverrides::new()
+        };
+
+        let policy = overrides.apply(RetryPolicy::new().backoff(Backoff::None));
+        assert_eq!(policy.get_max_attempts(), 1);
+        assert_eq!(policy.get_backoff(), Backoff::None);
+
+        let policy = NodeOverrides::new().apply(RetryPolicy::new());
+        assert_eq!(policy.get_max_attempts(), 3);
+    }
+}
@@ -0,0 +1,103 @@
+//! Nodes without security
+//!
+//! Without the `cc-security` feature the S0 and S2 command classes are left
+//! out. The commands to the securely included nodes fail and the encrypted
+//! commands of the nodes are dropped, nothing is ever decrypted.
+
+use super::Node;
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::defs::{SecurityClass, TransmitOptions};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind};
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Takes the place of the network keys and the nonces, there are none.
+#[derive(Debug)]
+pub struct SecurityContext;
+
+impl SecurityContext {
+    /// Create a context for the controller with the node id
+    pub fn new(_home_id: u32, _controller: u8) -> SecurityContext {
+        SecurityContext
+    }
+}
+
+impl<D> Node<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// The frames to the S0 nodes can't be encrypted
+    pub(super) fn encrypt_frame(
+        _driver: &mut D,
+        _frame: Message,
+        _security: &Mutex<SecurityContext>,
+        _options: TransmitOptions,
+        _report_timeout: Option<Duration>,
+    ) -> Result<Message, Error> {
+        Err(unsupported(SecurityClass::S0))
+    }
+
+    /// The frames to the S2 nodes can't be encrypted
+    pub(super) fn encrypt_s2_frame(
+        _driver: &mut D,
+        _frame: Message,
+        class: SecurityClass,
+        _security: &Mutex<SecurityContext>,
+        _options: TransmitOptions,
+        _report_timeout: Option<Duration>,
+    ) -> Result<Message, Error> {
+        Err(unsupported(class))
+    }
+
+    /// Drop the nonce requests and the encrypted commands of the nodes.
+    /// Returns `None` for them, like for the frames which were handled.
+    pub(super) fn decrypt_frame(
+        _driver: &mut D,
+        frame: IncomingFrame,
+        _security: &Mutex<SecurityContext>,
+    ) -> Result<Option<IncomingFrame>, Error> {
+        match frame.command_class {
+            Ok(CommandClass::SECURITY) | Ok(CommandClass::SECURITY_2) => Ok(None),
+            _ => Ok(Some(frame)),
+        }
+    }
+}
+
+/// The error for the frames, which would need to be encrypted
+fn unsupported(class: SecurityClass) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("{:?} needs the cc-security feature", class),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::{network, switch};
+
+    #[test]
+    fn secure() {
+        let (sim, mut controller) = network(vec![switch(2)]);
+        controller
+            .set_node_security_class(2, SecurityClass::S0)
+            .unwrap();
+        let node = controller.node(2).unwrap();
+
+        // nothing is sent to the node unencrypted
+        let count = sim.received(2).len();
+        assert!(node.switch_binary_set(true).is_err());
+        assert_eq!(sim.received(2).len(), count);
+
+        // the nonce requests of the node are dropped
+        sim.report(2, CommandClass::SECURITY, 0x40, &[]);
+        let frame = sim.clone().read_frame().unwrap();
+        let security = Mutex::new(SecurityContext::new(0, 1));
+        assert_eq!(
+            Node::decrypt_frame(&mut sim.clone(), frame, &security),
+            Ok(None)
+        );
+    }
+}
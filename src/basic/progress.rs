@@ -0,0 +1,160 @@
+//! Progress reporting
+//!
+//! Long running operations, like the interview of all nodes, report their
+//! progress to the registered listeners. Each event contains the current
+//! stage, the overall progress in percent and the status of each node.
+
+use std::fmt;
+
+/// The long running operations which report their progress.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operation {
+    /// Reading the information of the nodes
+    Interview,
+    /// Adding a node to the network
+    Inclusion,
+    /// Updating the neighbors and routes of the nodes
+    Heal,
+    /// Transferring a firmware image
+    FirmwareUpdate,
+}
+
+/// The status of a node within an operation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeProgress {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+/// The progress of an operation at one point in time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProgressEvent {
+    pub operation: Operation,
+    /// Description of the current step
+    pub stage: String,
+    /// The overall progress from 0 to 100
+    pub percent: u8,
+    pub nodes: Vec<(u8, NodeProgress)>,
+}
+
+/// Tracks the progress of one operation over a set of nodes.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    operation: Operation,
+    nodes: Vec<(u8, NodeProgress)>,
+}
+
+impl ProgressTracker {
+    /// Start tracking an operation, all nodes are pending.
+    pub fn new(operation: Operation, nodes: &[u8]) -> ProgressTracker {
+        ProgressTracker {
+            operation,
+            nodes: nodes.iter().map(|n| (*n, NodeProgress::Pending)).collect(),
+        }
+    }
+
+    /// Set the status of a node and return the new progress.
+    pub fn update<S>(&mut self, node: u8, status: NodeProgress, stage: S) -> ProgressEvent
+    where
+        S: Into<String>,
+    {
+        match self.nodes.iter_mut().find(|n| n.0 == node) {
+            Some(entry) => entry.1 = status,
+            None => self.nodes.push((node, status)),
+        }
+
+        self.event(stage)
+    }
+
+    /// Return the current progress with the given stage.
+    pub fn event<S>(&self, stage: S) -> ProgressEvent
+    where
+        S: Into<String>,
+    {
+        ProgressEvent {
+            operation: self.operation,
+            stage: stage.into(),
+            percent: self.percent(),
+            nodes: self.nodes.clone(),
+        }
+    }
+
+    /// Return the share of finished nodes in percent.
+    pub fn percent(&self) -> u8 {
+        if self.nodes.is_empty() {
+            return 100;
+        }
+
+        let finished = self
+            .nodes
+            .iter()
+            .filter(|n| n.1 == NodeProgress::Done || n.1 == NodeProgress::Failed)
+            .count();
+
+        (finished * 100 / self.nodes.len()) as u8
+    }
+}
+
+type Listener = Box<dyn Fn(&ProgressEvent)>;
+
+/// Stores the listeners which get notified about the progress of
+/// long running operations.
+#[derive(Default)]
+pub struct Progress {
+    listeners: Vec<Listener>,
+}
+
+impl Progress {
+    /// Create a new store without listeners
+    pub fn new() -> Progress {
+        Progress::default()
+    }
+
+    /// Register a function which is called for each progress event.
+    pub fn on_progress<F>(&mut self, listener: F)
+    where
+        F: Fn(&ProgressEvent) + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Inform all listeners about the progress
+    pub fn notify(&self, event: &ProgressEvent) {
+        for listener in &self.listeners {
+            listener(event);
+        }
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Progress {{listeners: {}}}", self.listeners.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_nodes() {
+        let mut tracker = ProgressTracker::new(Operation::Interview, &[2, 3, 4, 5]);
+        assert_eq!(tracker.percent(), 0);
+
+        tracker.update(2, NodeProgress::Done, "node information");
+        let event = tracker.update(3, NodeProgress::Failed, "node information");
+        assert_eq!(event.percent, 50);
+        assert_eq!(event.nodes[1], (3, NodeProgress::Failed));
+
+        // running nodes aren't finished
+        assert_eq!(tracker.update(4, NodeProgress::Running, "").percent, 50);
+
+        // no nodes at all
+        assert_eq!(ProgressTracker::new(Operation::Heal, &[]).percent(), 100);
+    }
+}
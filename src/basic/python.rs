@@ -0,0 +1,234 @@
+//! Python bindings
+//!
+//! Exposes the controller, its nodes and the received commands to Python,
+//! built with the `python` feature. The module is built with maturin:
+//!
+//! ```text
+//! maturin build --features python,pyo3/extension-module
+//! ```
+//!
+//! ```python
+//! import rzw
+//!
+//! controller = rzw.Controller("/dev/ttyACM0")
+//! for id in controller.nodes():
+//!     print(id, controller.node(id).commands())
+//!
+//! controller.node(2).switch_binary_set(True)
+//!
+//! for node, cmd_class, cmd, payload in controller.events():
+//!     print(node, cmd_class, cmd, payload)
+//! ```
+
+// the wrappers generated by pyo3 convert the errors of the results again
+#![allow(clippy::useless_conversion)]
+
+use super::{Controller, Node};
+use crate::cmds::crc16::Crc16;
+use crate::cmds::IncomingFrame;
+use crate::driver::serial::{SerialDriver, SerialMsgFunction};
+use crate::error::{Error, ErrorKind};
+
+use pyo3::exceptions::{PyIOError, PyNotImplementedError, PyStopIteration, PyValueError};
+use pyo3::prelude::*;
+
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+
+/// A command received from a node: the node id, the command class, the
+/// command and the payload.
+type Event = (u8, u8, u8, Vec<u8>);
+
+/// Convert the error into the matching Python exception
+fn to_py(error: Error) -> PyErr {
+    match error.kind() {
+        ErrorKind::InvalidInput => PyValueError::new_err(error.to_string()),
+        ErrorKind::NotImplemented => PyNotImplementedError::new_err(error.to_string()),
+        _ => PyIOError::new_err(error.to_string()),
+    }
+}
+
+/// The Z-Wave controller, which is opened at a serial port.
+#[pyclass(name = "Controller", unsendable)]
+pub struct PyController {
+    controller: Controller<SerialDriver>,
+}
+
+#[pymethods]
+impl PyController {
+    /// Open the controller at the serial port and discover its nodes.
+    #[new]
+    fn new(path: &str) -> PyResult<PyController> {
+        let driver = SerialDriver::new(path).map_err(to_py)?;
+        let controller = Controller::new(driver).map_err(to_py)?;
+
+        Ok(PyController { controller })
+    }
+
+    /// Return the ids of all nodes.
+    fn nodes(&self) -> Vec<u8> {
+        self.controller.nodes()
+    }
+
+    /// Return the node with the id.
+    fn node(&mut self, id: u8) -> PyResult<PyNode> {
+        self.controller
+            .node(id)
+            .map(|node| PyNode { node })
+            .ok_or_else(|| PyValueError::new_err(format!("The node {} is unknown", id)))
+    }
+
+    /// Return an iterator over the commands, which are received from the nodes.
+    fn events(&self) -> PyEventStream {
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+
+        self.controller.handle_messages(Box::new(move |msg| {
+            if msg.func != SerialMsgFunction::ApplicationCommandHandler {
+                return;
+            }
+
+            if let Ok(frame) = IncomingFrame::parse(&msg.data).and_then(Crc16::unwrap) {
+                let _ = sender.lock().unwrap().send((
+                    frame.source,
+                    frame.command_class_id(),
+                    frame.command,
+                    frame.payload,
+                ));
+            }
+        }));
+
+        PyEventStream {
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+}
+
+/// A node of the network.
+#[pyclass(name = "Node", unsendable)]
+pub struct PyNode {
+    node: Node<SerialDriver>,
+}
+
+#[pymethods]
+impl PyNode {
+    /// Return the id of the node.
+    fn id(&self) -> u8 {
+        self.node.get_id()
+    }
+
+    /// Return the name of the node.
+    fn name(&self) -> Option<String> {
+        self.node.name()
+    }
+
+    /// Return the names of the supported command classes.
+    fn commands(&self) -> Vec<String> {
+        self.node
+            .get_commands()
+            .iter()
+            .map(|c| format!("{:?}", c))
+            .collect()
+    }
+
+    fn basic_set(&self, value: u8) -> PyResult<()> {
+        self.node.basic_set(value).map(|_| ()).map_err(to_py)
+    }
+
+    fn basic_get(&self) -> PyResult<u8> {
+        self.node.basic_get().map_err(to_py)
+    }
+
+    fn switch_binary_set(&self, value: bool) -> PyResult<()> {
+        self.node
+            .switch_binary_set(value)
+            .map(|_| ())
+            .map_err(to_py)
+    }
+
+    fn switch_binary_get(&self) -> PyResult<bool> {
+        self.node.switch_binary_get().map_err(to_py)
+    }
+
+    fn switch_multilevel_set(&self, value: u8) -> PyResult<()> {
+        self.node
+            .switch_multilevel_set(value, None)
+            .map(|_| ())
+            .map_err(to_py)
+    }
+
+    fn switch_multilevel_get(&self) -> PyResult<u8> {
+        self.node.switch_multilevel_get().map_err(to_py)
+    }
+}
+
+/// The commands received from the nodes, as an iterator of
+/// `(node, cmd_class, cmd, payload)` tuples.
+#[pyclass(name = "EventStream")]
+pub struct PyEventStream {
+    receiver: Arc<Mutex<Receiver<Event>>>,
+}
+
+#[pymethods]
+impl PyEventStream {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Wait for the next command, without blocking other Python threads.
+    fn __next__(&self, py: Python<'_>) -> PyResult<Event> {
+        let receiver = self.receiver.clone();
+
+        py.allow_threads(move || receiver.lock().unwrap().recv())
+            .map_err(|_| PyStopIteration::new_err("The controller stopped"))
+    }
+}
+
+/// The `rzw` Python module
+#[pymodule]
+fn rzw(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyController>()?;
+    m.add_class::<PyNode>()?;
+    m.add_class::<PyEventStream>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let error = to_py(Error::new(ErrorKind::InvalidInput, "wrong level"));
+            assert!(error.is_instance_of::<PyValueError>(py));
+            assert_eq!(error.value_bound(py).to_string(), "wrong level");
+
+            let error = to_py(Error::new(ErrorKind::NoController, "no controller"));
+            assert!(error.is_instance_of::<PyIOError>(py));
+        });
+    }
+
+    #[test]
+    fn module() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let module = pyo3::wrap_pymodule!(rzw)(py);
+            let module = module.bind(py);
+            for class in &["Controller", "Node", "EventStream"] {
+                assert!(module.hasattr(*class).unwrap());
+            }
+
+            // a missing port is raised as IOError
+            let error = module
+                .getattr("Controller")
+                .unwrap()
+                .call1(("/dev/rzw-does-not-exist",))
+                .unwrap_err();
+            assert!(error.is_instance_of::<PyIOError>(py));
+        });
+    }
+}
@@ -0,0 +1,206 @@
+//! Transmit queue introspection
+//!
+//! All nodes share the driver, so a message waits until the messages of the
+//! other nodes are done. The queue records which messages are waiting and
+//! which one is transmitted, to find stuck or backed-up nodes at runtime.
+
+use super::scheduler::Priority;
+use crate::cmds::{CommandClass, Message};
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A message in the transmit queue.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueueEntry {
+    pub id: u64,
+    pub node: u8,
+    pub command_class: CommandClass,
+    pub command: u8,
+    pub priority: Priority,
+    /// The time since the message was queued
+    pub age: Duration,
+}
+
+/// The state of the transmit queue at one point in time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueueSnapshot {
+    /// The message which is transmitted right now, with its retries
+    pub transmitting: Option<QueueEntry>,
+    /// The waiting messages, the oldest first
+    pub pending: Vec<QueueEntry>,
+}
+
+impl QueueSnapshot {
+    /// Return the amount of waiting messages for each node
+    pub fn pending_per_node(&self) -> Vec<(u8, usize)> {
+        let mut nodes: Vec<(u8, usize)> = vec![];
+
+        for entry in &self.pending {
+            match nodes.iter_mut().find(|n| n.0 == entry.node) {
+                Some(n) => n.1 += 1,
+                None => nodes.push((entry.node, 1)),
+            }
+        }
+
+        nodes.sort();
+        nodes
+    }
+}
+
+/// A queued message and the time it was queued.
+#[derive(Debug, Clone)]
+struct Queued {
+    id: u64,
+    node: u8,
+    command_class: CommandClass,
+    command: u8,
+    priority: Priority,
+    since: Instant,
+}
+
+impl Queued {
+    fn entry(&self) -> QueueEntry {
+        QueueEntry {
+            id: self.id,
+            node: self.node,
+            command_class: self.command_class,
+            command: self.command,
+            priority: self.priority,
+            age: self.since.elapsed(),
+        }
+    }
+}
+
+/// Records the messages, which wait for the driver.
+#[derive(Debug, Default)]
+pub struct TransmitQueue {
+    next_id: u64,
+    pending: Vec<Queued>,
+    transmitting: Option<Queued>,
+}
+
+impl TransmitQueue {
+    /// Create an empty queue
+    pub fn new() -> TransmitQueue {
+        TransmitQueue::default()
+    }
+
+    /// Return the current state of the queue
+    pub fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            transmitting: self.transmitting.as_ref().map(|q| q.entry()),
+            pending: self.pending.iter().map(|q| q.entry()).collect(),
+        }
+    }
+
+    /// Add the message of the node and return its id
+    fn push(&mut self, node: u8, message: &Message, priority: Priority) -> u64 {
+        self.next_id += 1;
+        self.pending.push(Queued {
+            id: self.next_id,
+            node,
+            command_class: message.cmd_class,
+            command: message.cmd,
+            priority,
+            since: Instant::now(),
+        });
+
+        self.next_id
+    }
+
+    /// Mark the message as transmitting
+    fn start(&mut self, id: u64) {
+        if let Some(pos) = self.pending.iter().position(|q| q.id == id) {
+            self.transmitting = Some(self.pending.remove(pos));
+        }
+    }
+
+    /// Remove the message from the queue
+    fn finish(&mut self, id: u64) {
+        self.pending.retain(|q| q.id != id);
+
+        if self.transmitting.as_ref().map(|q| q.id) == Some(id) {
+            self.transmitting = None;
+        }
+    }
+}
+
+/// A message in the queue, which is removed when the ticket is dropped.
+pub struct QueueTicket {
+    queue: Arc<Mutex<TransmitQueue>>,
+    id: u64,
+}
+
+impl QueueTicket {
+    /// Add the message of the node with the priority to the queue
+    pub fn new(
+        queue: &Arc<Mutex<TransmitQueue>>,
+        node: u8,
+        message: &Message,
+        priority: Priority,
+    ) -> QueueTicket {
+        let id = queue.lock().unwrap().push(node, message, priority);
+
+        QueueTicket {
+            queue: queue.clone(),
+            id,
+        }
+    }
+
+    /// Mark the message as transmitting, after the driver was acquired
+    pub fn start(&self) {
+        self.queue.lock().unwrap().start(self.id);
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        self.queue.lock().unwrap().finish(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tickets() {
+        let queue = Arc::new(Mutex::new(TransmitQueue::new()));
+
+        let first = QueueTicket::new(
+            &queue,
+            5,
+            &Message::from_array(5, CommandClass::BASIC, 0x02, []),
+            Priority::Normal,
+        );
+        let second = QueueTicket::new(
+            &queue,
+            7,
+            &Message::from_array(7, CommandClass::SWITCH_BINARY, 0x01, [0xFF]),
+            Priority::Normal,
+        );
+        let _third = QueueTicket::new(
+            &queue,
+            5,
+            &Message::from_array(5, CommandClass::BASIC, 0x01, [0x00]),
+            Priority::Low,
+        );
+        first.start();
+
+        let snapshot = queue.lock().unwrap().snapshot();
+        assert_eq!(snapshot.transmitting.as_ref().map(|e| e.node), Some(5));
+        assert_eq!(snapshot.pending.len(), 2);
+        assert_eq!(snapshot.pending_per_node(), vec![(5, 1), (7, 1)]);
+        assert_eq!(snapshot.pending[1].priority, Priority::Low);
+
+        // finished messages are removed
+        drop(first);
+        drop(second);
+        let snapshot = queue.lock().unwrap().snapshot();
+        assert_eq!(snapshot.transmitting, None);
+        assert_eq!(snapshot.pending_per_node(), vec![(5, 1)]);
+    }
+}
@@ -0,0 +1,255 @@
+//! Retry policy
+//!
+//! Messages to nodes fail from time to time, because of interferences or
+//! a busy network. A `RetryPolicy` defines how often a message is sent
+//! again, how long to wait in between and which errors are worth a retry.
+
+use crate::error::{Error, ErrorKind, Stage};
+
+use std::cmp;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// The time to wait before the next attempt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backoff {
+    /// Retry immediately
+    None,
+    /// Wait the same time before each attempt
+    Fixed(Duration),
+    /// Double the time after each attempt, up to the maximum
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// Return the time to wait after the given failed attempt, starting at 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::None => Duration::from_millis(0),
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 1u32 << cmp::min(attempt.saturating_sub(1), 16);
+                cmp::min(initial * factor, max)
+            }
+        }
+    }
+}
+
+/// Defines how often and when a failed message is sent again.
+///
+/// ```rust,ignore
+/// let policy = RetryPolicy::new()
+///     .max_attempts(5)
+///     .backoff(Backoff::Fixed(Duration::from_millis(500)));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    retryable: fn(&Error) -> bool,
+}
+
+impl RetryPolicy {
+    /// Create the default policy, which tries a message 3 times with an
+    /// exponential backoff from 100ms to 1s.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Exponential {
+                initial: Duration::from_millis(100),
+                max: Duration::from_secs(1),
+            },
+            retryable: RetryPolicy::is_transient,
+        }
+    }
+
+    /// Create a policy which tries a message only once.
+    pub fn never() -> RetryPolicy {
+        RetryPolicy::new().max_attempts(1)
+    }
+
+    /// Set the amount of attempts, including the first one.
+    pub fn max_attempts(mut self, attempts: u32) -> RetryPolicy {
+        self.max_attempts = cmp::max(attempts, 1);
+        self
+    }
+
+    /// Set the time to wait between the attempts.
+    pub fn backoff(mut self, backoff: Backoff) -> RetryPolicy {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the function which decides if an error is worth a retry.
+    pub fn retry_if(mut self, retryable: fn(&Error) -> bool) -> RetryPolicy {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Return the amount of attempts, including the first one.
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Return the time to wait between the attempts.
+    pub fn get_backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    /// Check if the error is worth a retry.
+    pub fn is_retryable(&self, err: &Error) -> bool {
+        (self.retryable)(err)
+    }
+
+    /// The default check, which retries the messages that didn't reach the
+    /// node, like failed transmissions or busy nodes.
+    ///
+    /// A missing answer isn't retried, because the node may have already
+    /// executed the command. A set or a toggle would be executed twice.
+    pub fn is_transient(err: &Error) -> bool {
+        match *err {
+            Error::Timeout { stage } => stage != Stage::Report,
+            Error::TransmitFailed(_) | Error::Busy { .. } => true,
+            _ => err.kind() == ErrorKind::Io(io::ErrorKind::TimedOut),
+        }
+    }
+
+    /// Run the function until it succeeds, the error isn't retryable or
+    /// all attempts are used. The last error is returned.
+    ///
+    /// A busy node is asked again after the wait time it requested, when
+    /// it's longer than the backoff.
+    pub fn run<T, F>(&self, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Result<T, Error>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let delay = match f() {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    if attempt >= self.max_attempts || !self.is_retryable(&err) {
+                        return Err(err);
+                    }
+
+                    match err {
+                        Error::Busy { wait: Some(wait) } => {
+                            cmp::max(wait, self.backoff.delay(attempt))
+                        }
+                        _ => self.backoff.delay(attempt),
+                    }
+                }
+            };
+
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    #[test]
+    fn backoff_delay() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+        };
+
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(3), Duration::from_millis(400));
+        assert_eq!(backoff.delay(4), Duration::from_millis(500));
+        assert_eq!(backoff.delay(100), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn run_attempts() {
+        let policy = RetryPolicy::new().max_attempts(3).backoff(Backoff::None);
+
+        // succeeds at the second attempt
+        let mut calls = 0;
+        let result = policy.run(|| {
+            calls += 1;
+            if calls < 2 {
+                Err(Error::Timeout {
+                    stage: Stage::Callback,
+                })
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result, Ok(2));
+
+        // all attempts fail
+        calls = 0;
+        let result: Result<(), Error> = policy.run(|| {
+            calls += 1;
+            Err(Error::Timeout { stage: Stage::Ack })
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        // the node may have executed the command without answering
+        calls = 0;
+        let result: Result<(), Error> = policy.run(|| {
+            calls += 1;
+            Err(Error::Timeout {
+                stage: Stage::Report,
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        // invalid input isn't retried
+        calls = 0;
+        let result: Result<(), Error> = policy.run(|| {
+            calls += 1;
+            Err(Error::new(ErrorKind::InvalidInput, "wrong value"))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn run_busy() {
+        let policy = RetryPolicy::new().max_attempts(2).backoff(Backoff::None);
+
+        // the node is asked again after the time it requested
+        let start = Instant::now();
+        let mut calls = 0;
+        let result = policy.run(|| {
+            calls += 1;
+            if calls < 2 {
+                Err(Error::Busy {
+                    wait: Some(Duration::from_millis(50)),
+                })
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result, Ok(2));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // a rejected request isn't sent again
+        calls = 0;
+        let result: Result<(), Error> = policy.run(|| {
+            calls += 1;
+            Err(Error::Rejected)
+        });
+        assert_eq!(result, Err(Error::Rejected));
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,181 @@
+//! Neighbors and routes
+//!
+//! Each node knows the nodes in its direct range, its neighbors. The
+//! controller calculates the routes through the mesh from the neighbors of
+//! all nodes, so they have to be updated, when a node was moved. The nodes
+//! themselves only know the return routes to the nodes they report to.
+
+use super::Controller;
+use crate::defs::TransmitStatus;
+use crate::driver::serial::SerialMsgFunction;
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind, Stage};
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// The time a node has to find its neighbors
+const NEIGHBOR_UPDATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The node found its neighbors
+const NEIGHBOR_UPDATE_DONE: u8 = 0x22;
+
+/// The node couldn't be reached or didn't find its neighbors
+const NEIGHBOR_UPDATE_FAILED: u8 = 0x23;
+
+/// The time the controller has to transmit the return routes to a node
+const RETURN_ROUTE_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl<D> Controller<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Let the node search for the nodes in its direct range and report
+    /// them to the controller. Returns, when the node is done.
+    ///
+    /// A sleeping node has to be awake for it.
+    pub fn request_neighbor_update<I>(&self, id: I) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+        let function = SerialMsgFunction::RequestNodeNeighborUpdate;
+        let callback_id = self.start_function(function, &[id])?;
+        let deadline = Instant::now() + NEIGHBOR_UPDATE_TIMEOUT;
+
+        while Instant::now() < deadline {
+            let msg = match self.read_callback(function, callback_id) {
+                Some(msg) => msg,
+                None => continue,
+            };
+
+            // `callback-id, status`
+            match msg.data.get(1) {
+                Some(&NEIGHBOR_UPDATE_DONE) => return Ok(()),
+                Some(&NEIGHBOR_UPDATE_FAILED) => {
+                    return Err(Error::new(
+                        ErrorKind::UnknownZWave,
+                        format!("The node {} couldn't update its neighbors", id),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        Err(Error::Timeout {
+            stage: Stage::Callback,
+        })
+    }
+
+    /// Return the neighbors of the node from the routing table of the
+    /// controller, the nodes which don't repeat frames included.
+    pub fn get_neighbors<I>(&self, id: I) -> Result<Vec<u8>, Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+        self.driver.run(move |d| d.get_neighbors(id))
+    }
+
+    /// Let the controller calculate routes from the node to the destination
+    /// and send them to the node. A node needs them to reach the targets of
+    /// its associations, like the controller of the lifeline.
+    ///
+    /// Returns, when the node acknowledged the routes. A sleeping node has
+    /// to be awake for it.
+    pub fn assign_return_route<I, T>(&self, id: I, destination: T) -> Result<(), Error>
+    where
+        I: Into<u8>,
+        T: Into<u8>,
+    {
+        let id = id.into();
+        let function = SerialMsgFunction::AssignReturnRoute;
+        let callback_id = self.start_function(function, &[id, destination.into()])?;
+        self.wait_return_route(function, callback_id, id)
+    }
+
+    /// Delete all return routes of the node, like before the routes to new
+    /// association targets are assigned.
+    ///
+    /// Returns, when the node acknowledged it. A sleeping node has to be
+    /// awake for it.
+    pub fn delete_return_routes<I>(&self, id: I) -> Result<(), Error>
+    where
+        I: Into<u8>,
+    {
+        let id = id.into();
+        let function = SerialMsgFunction::DeleteReturnRoute;
+        let callback_id = self.start_function(function, &[id])?;
+        self.wait_return_route(function, callback_id, id)
+    }
+
+    /// Wait until the return routes were transmitted to the node
+    fn wait_return_route(
+        &self,
+        function: SerialMsgFunction,
+        callback_id: u8,
+        id: u8,
+    ) -> Result<(), Error> {
+        let deadline = Instant::now() + RETURN_ROUTE_TIMEOUT;
+
+        while Instant::now() < deadline {
+            let msg = match self.read_callback(function, callback_id) {
+                Some(msg) => msg,
+                None => continue,
+            };
+
+            // `callback-id, transmit status`
+            let status = msg
+                .data
+                .get(1)
+                .and_then(|s| TransmitStatus::try_from(*s).ok());
+            return match status {
+                Some(TransmitStatus::CompleteOk) => Ok(()),
+                status => Err(Error::new(
+                    ErrorKind::UnknownZWave,
+                    format!(
+                        "The return routes couldn't be sent to the node {}: {:?}",
+                        id, status
+                    ),
+                )),
+            };
+        }
+
+        Err(Error::Timeout {
+            stage: Stage::Callback,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basic::sim::fixture::{network, switch};
+    use crate::basic::sim::SimFailure;
+
+    #[test]
+    fn neighbors() {
+        let (sim, controller) = network(vec![switch(2), switch(3), switch(4).sleeping()]);
+        controller.request_neighbor_update(2).unwrap();
+        assert_eq!(controller.get_neighbors(2).unwrap(), vec![1, 3]);
+
+        // the node is out of range
+        sim.set_failure(3, Some(SimFailure::NoAck));
+        assert!(controller.request_neighbor_update(3).is_err());
+    }
+
+    #[test]
+    fn return_routes() {
+        let (sim, controller) = network(vec![switch(2), switch(3)]);
+        controller.assign_return_route(2, 1).unwrap();
+        controller.assign_return_route(2, 3).unwrap();
+        assert_eq!(sim.return_routes(2), vec![1, 3]);
+
+        controller.delete_return_routes(2).unwrap();
+        assert!(sim.return_routes(2).is_empty());
+
+        // the node doesn't acknowledge the routes
+        sim.set_failure(3, Some(SimFailure::NoAck));
+        assert!(controller.assign_return_route(3, 1).is_err());
+        assert!(controller.delete_return_routes(3).is_err());
+    }
+}
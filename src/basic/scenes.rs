@@ -0,0 +1,210 @@
+//! Scene management
+//!
+//! A scene is a named set of nodes and the basic values they should get.
+//! The scenes can be saved to a file and loaded again on the next start.
+//!
+//! The file format has one scene per line, the name and the targets are
+//! separated by a tab:
+//!
+//! `name<TAB>node:value,node:value`
+
+use crate::error::{Error, ErrorKind};
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A named set of nodes and their values.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    pub name: String,
+    pub targets: Vec<(u8, u8)>,
+}
+
+type Listener = Box<dyn Fn(&str, &[u8])>;
+
+/// Stores all defined scenes and the listeners which get notified
+/// when a scene was activated.
+#[derive(Default)]
+pub struct Scenes {
+    scenes: Vec<Scene>,
+    listeners: Vec<Listener>,
+}
+
+impl Scenes {
+    /// Create a new empty scene store
+    pub fn new() -> Scenes {
+        Scenes::default()
+    }
+
+    /// Define a scene with the node id and value targets. An existing
+    /// scene with the same name gets replaced.
+    pub fn define<N>(&mut self, name: N, targets: Vec<(u8, u8)>) -> Result<(), Error>
+    where
+        N: Into<String>,
+    {
+        let name = name.into();
+        check_name(&name)?;
+
+        // replace the old scene
+        self.remove(name.as_str());
+        self.scenes.push(Scene { name, targets });
+
+        Ok(())
+    }
+
+    /// Remove the scene with the given name and return it
+    pub fn remove(&mut self, name: &str) -> Option<Scene> {
+        let pos = self.scenes.iter().position(|s| s.name == name)?;
+        Some(self.scenes.remove(pos))
+    }
+
+    /// Return the scene with the given name
+    pub fn get(&self, name: &str) -> Option<&Scene> {
+        self.scenes.iter().find(|s| s.name == name)
+    }
+
+    /// Return the names of all scenes
+    pub fn names(&self) -> Vec<String> {
+        self.scenes.iter().map(|s| s.name.clone()).collect()
+    }
+
+    /// Register a function which is called after a scene was activated, with
+    /// the scene name and the ids of the nodes which didn't confirm the value.
+    pub fn on_activate<F>(&mut self, listener: F)
+    where
+        F: Fn(&str, &[u8]) + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Inform all listeners about an activated scene
+    pub fn notify(&self, name: &str, failed: &[u8]) {
+        for listener in &self.listeners {
+            listener(name, failed);
+        }
+    }
+
+    /// Parse scenes from the file format
+    pub fn parse(data: &str) -> Result<Scenes, Error> {
+        let mut scenes = Scenes::new();
+
+        for line in data.lines().filter(|l| !l.trim().is_empty()) {
+            // split the name from the targets
+            let mut parts = line.splitn(2, '\t');
+            let name = parts.next().unwrap_or("");
+            let targets = parts.next().unwrap_or("");
+
+            // parse each node:value pair
+            let mut list = vec![];
+            for target in targets.split(',').filter(|t| !t.is_empty()) {
+                let mut pair = target.splitn(2, ':');
+                let node = pair.next().and_then(|n| n.trim().parse::<u8>().ok());
+                let value = pair.next().and_then(|v| v.trim().parse::<u8>().ok());
+
+                match (node, value) {
+                    (Some(n), Some(v)) => list.push((n, v)),
+                    _ => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("Invalid scene target '{}'", target),
+                        ))
+                    }
+                }
+            }
+
+            scenes.define(name, list)?;
+        }
+
+        Ok(scenes)
+    }
+
+    /// Load the scenes from the given file
+    pub fn load<P>(path: P) -> Result<Scenes, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Scenes::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Save the scenes to the given file
+    pub fn save<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Scenes {
+    /// Write the scenes in the file format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for scene in &self.scenes {
+            let targets = scene
+                .targets
+                .iter()
+                .map(|(n, v)| format!("{}:{}", n, v))
+                .collect::<Vec<String>>()
+                .join(",");
+
+            writeln!(f, "{}\t{}", scene.name, targets)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Scenes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Scenes {{scenes: {:?}}}", self.scenes)
+    }
+}
+
+/// Check that the scene name fits into one line of the file
+pub(super) fn check_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name.contains('\t') || name.contains('\n') {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The scene name needs to be a non empty single line without tabs",
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_write() {
+        let mut scenes = Scenes::new();
+        scenes.define("all off", vec![(2, 0), (3, 0)]).unwrap();
+        scenes.define("movie", vec![(2, 0xFF), (4, 20)]).unwrap();
+
+        let text = scenes.to_string();
+        assert_eq!(text, "all off\t2:0,3:0\nmovie\t2:255,4:20\n");
+
+        let parsed = Scenes::parse(&text).unwrap();
+        assert_eq!(parsed.names(), vec!["all off", "movie"]);
+        assert_eq!(
+            parsed.get("movie").unwrap().targets,
+            vec![(2, 0xFF), (4, 20)]
+        );
+    }
+
+    #[test]
+    fn replace_and_reject() {
+        let mut scenes = Scenes::new();
+        scenes.define("night", vec![(2, 0)]).unwrap();
+        scenes.define("night", vec![(3, 0)]).unwrap();
+
+        assert_eq!(scenes.names(), vec!["night"]);
+        assert_eq!(scenes.get("night").unwrap().targets, vec![(3, 0)]);
+
+        assert!(scenes.define("bad\tname", vec![]).is_err());
+        assert!(Scenes::parse("broken\t2:on").is_err());
+    }
+}
@@ -0,0 +1,448 @@
+//! Scheduled commands
+//!
+//! The `Scheduler` stores commands, which are sent once at a given time or
+//! again and again in an interval. The controller sends the due commands in
+//! `run_scheduled`. Commands with a low priority wait, while other messages
+//! are in the transmit queue.
+//!
+//! The jobs can be saved to a file and loaded again on the next start. The
+//! file format has one job per line, the fields are separated by a tab:
+//!
+//! `id<TAB>due<TAB>interval<TAB>priority<TAB>action`
+//!
+//! The due time is in seconds since 1970, the interval in seconds and `0`
+//! for a job which runs only once.
+
+use super::scenes;
+use crate::cmds::CommandClass;
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A command, which is sent by the scheduler.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    /// Set the basic value of the node
+    BasicSet(u8, u8),
+    /// Switch the binary switch of the node on or off
+    SwitchBinarySet(u8, bool),
+    /// Set the level of the multilevel switch of the node
+    SwitchMultilevelSet(u8, u8),
+    /// Read the value of the command class from the node into the cache
+    Refresh(u8, CommandClass),
+    /// Activate the scene with the name
+    SceneActivate(String),
+}
+
+/// The priority of a scheduled or a queued command.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Priority {
+    Normal,
+    /// The command waits until the transmit queue is empty
+    Low,
+}
+
+/// A scheduled command.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Job {
+    pub id: u64,
+    pub action: Action,
+    /// The next time the command is sent
+    pub due: SystemTime,
+    /// The interval of a recurring command, `None` for a one-shot command
+    pub interval: Option<Duration>,
+    pub priority: Priority,
+}
+
+/// Stores the scheduled commands until they are due.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    next_id: u64,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    /// Create a new scheduler without jobs
+    pub fn new() -> Scheduler {
+        Scheduler::default()
+    }
+
+    /// Send the command once at the given time. The time is rounded up to
+    /// whole seconds, like in the file. Returns the id of the job.
+    pub fn at(&mut self, due: SystemTime, action: Action) -> Result<u64, Error> {
+        self.add(Job {
+            id: 0,
+            action,
+            due,
+            interval: None,
+            priority: Priority::Normal,
+        })
+    }
+
+    /// Send the command once after the delay, like "turn node 5 off in
+    /// 10 minutes". Returns the id of the job.
+    pub fn after(&mut self, delay: Duration, action: Action) -> Result<u64, Error> {
+        self.at(SystemTime::now() + delay, action)
+    }
+
+    /// Send the command in the interval, the first time after one interval.
+    /// The interval needs to be whole seconds, at least one. Returns the id
+    /// of the job.
+    pub fn every(
+        &mut self,
+        interval: Duration,
+        priority: Priority,
+        action: Action,
+    ) -> Result<u64, Error> {
+        if interval.as_secs() == 0 || interval.subsec_nanos() > 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The interval {:?} is no whole number of seconds", interval),
+            ));
+        }
+
+        self.add(Job {
+            id: 0,
+            action,
+            due: SystemTime::now() + interval,
+            interval: Some(interval),
+            priority,
+        })
+    }
+
+    /// Add the job with a new id and return the id. The job needs to fit
+    /// into one line of the file.
+    fn add(&mut self, mut job: Job) -> Result<u64, Error> {
+        if let Action::SceneActivate(ref name) = job.action {
+            scenes::check_name(name)?;
+        }
+
+        // the file only keeps whole seconds
+        let since = job.due.duration_since(UNIX_EPOCH).unwrap_or_default();
+        if since.subsec_nanos() > 0 {
+            job.due = UNIX_EPOCH + Duration::from_secs(since.as_secs() + 1);
+        }
+
+        self.next_id += 1;
+        job.id = self.next_id;
+        self.jobs.push(job);
+
+        Ok(self.next_id)
+    }
+
+    /// Remove the job and return it
+    pub fn cancel(&mut self, id: u64) -> Option<Job> {
+        let pos = self.jobs.iter().position(|j| j.id == id)?;
+        Some(self.jobs.remove(pos))
+    }
+
+    /// Returns all scheduled jobs
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Take the jobs, which are due at the time. The one-shot jobs are
+    /// removed, the recurring ones are scheduled again. When the transmit
+    /// queue is `busy`, the jobs with a low priority stay due.
+    pub fn take_due(&mut self, now: SystemTime, busy: bool) -> Vec<Job> {
+        let mut due = vec![];
+
+        for job in &mut self.jobs {
+            if job.due > now || (busy && job.priority == Priority::Low) {
+                continue;
+            }
+            due.push(job.clone());
+
+            // the missed runs are skipped
+            if let Some(interval) = job.interval.filter(|i| *i > Duration::from_secs(0)) {
+                while job.due <= now {
+                    job.due += interval;
+                }
+            }
+        }
+
+        self.jobs
+            .retain(|j| j.interval.is_some() || !due.iter().any(|d| d.id == j.id));
+        due
+    }
+
+    /// Parse the jobs from the file format
+    pub fn parse(data: &str) -> Result<Scheduler, Error> {
+        let mut scheduler = Scheduler::new();
+
+        for line in data.lines().filter(|l| !l.trim().is_empty()) {
+            let fields: Vec<&str> = line.splitn(5, '\t').collect();
+            if fields.len() != 5 {
+                return Err(invalid(line));
+            }
+
+            let id = fields[0].parse::<u64>().map_err(|_| invalid(line))?;
+            let due = fields[1].parse::<u64>().map_err(|_| invalid(line))?;
+            let interval = fields[2].parse::<u64>().map_err(|_| invalid(line))?;
+            let priority = match fields[3] {
+                "normal" => Priority::Normal,
+                "low" => Priority::Low,
+                _ => return Err(invalid(line)),
+            };
+
+            scheduler.jobs.push(Job {
+                id,
+                action: parse_action(fields[4]).ok_or_else(|| invalid(line))?,
+                due: UNIX_EPOCH + Duration::from_secs(due),
+                interval: if interval > 0 {
+                    Some(Duration::from_secs(interval))
+                } else {
+                    None
+                },
+                priority,
+            });
+            scheduler.next_id = scheduler.next_id.max(id);
+        }
+
+        Ok(scheduler)
+    }
+
+    /// Load the jobs from the given file
+    pub fn load<P>(path: P) -> Result<Scheduler, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Scheduler::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Save the jobs to the given file
+    pub fn save<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Scheduler {
+    /// Write the jobs in the file format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for job in &self.jobs {
+            let due = job
+                .due
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let interval = job.interval.map(|i| i.as_secs()).unwrap_or(0);
+            let priority = match job.priority {
+                Priority::Normal => "normal",
+                Priority::Low => "low",
+            };
+            let action = match job.action {
+                Action::BasicSet(node, value) => format!("basic {} {}", node, value),
+                Action::SwitchBinarySet(node, on) => {
+                    format!("switch {} {}", node, if on { "on" } else { "off" })
+                }
+                Action::SwitchMultilevelSet(node, level) => format!("level {} {}", node, level),
+                Action::Refresh(node, cmd_class) => {
+                    format!("refresh {} {}", node, cmd_class as u8)
+                }
+                Action::SceneActivate(ref name) => format!("scene {}", name),
+            };
+
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}",
+                job.id, due, interval, priority, action
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse an action of the file format
+fn parse_action(action: &str) -> Option<Action> {
+    let (kind, rest) = action.split_once(' ')?;
+
+    if kind == "scene" {
+        scenes::check_name(rest).ok()?;
+        return Some(Action::SceneActivate(rest.to_string()));
+    }
+
+    let mut args = rest.split(' ');
+    let node = args.next()?.parse::<u8>().ok()?;
+    let value = args.next()?;
+
+    match kind {
+        "basic" => value.parse().ok().map(|v| Action::BasicSet(node, v)),
+        "switch" => match value {
+            "on" => Some(Action::SwitchBinarySet(node, true)),
+            "off" => Some(Action::SwitchBinarySet(node, false)),
+            _ => None,
+        },
+        "level" => value
+            .parse()
+            .ok()
+            .map(|v| Action::SwitchMultilevelSet(node, v)),
+        "refresh" => value
+            .parse::<u8>()
+            .ok()
+            .and_then(|c| CommandClass::try_from(c).ok())
+            .map(|c| Action::Refresh(node, c)),
+        _ => None,
+    }
+}
+
+/// The error for a line, which is no job
+fn invalid(line: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("Invalid scheduled job '{}'", line),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::{SimNetwork, SimNode};
+    use crate::basic::Controller;
+    use crate::defs::GenericType;
+
+    #[test]
+    fn take_due() {
+        let start = SystemTime::now();
+        let mut scheduler = Scheduler::new();
+        let off = scheduler
+            .after(Duration::from_secs(600), Action::SwitchBinarySet(5, false))
+            .unwrap();
+        let poll = scheduler
+            .every(
+                Duration::from_secs(900),
+                Priority::Low,
+                Action::Refresh(7, CommandClass::METER),
+            )
+            .unwrap();
+
+        assert!(scheduler.take_due(start, false).is_empty());
+
+        // the low priority job waits for the busy queue
+        let later = start + Duration::from_secs(1000);
+        let due = scheduler.take_due(later, true);
+        assert_eq!(due.iter().map(|j| j.id).collect::<Vec<u64>>(), vec![off]);
+        assert!(scheduler.cancel(off).is_none());
+
+        let due = scheduler.take_due(later, false);
+        assert_eq!(due.iter().map(|j| j.id).collect::<Vec<u64>>(), vec![poll]);
+        assert!(scheduler.jobs()[0].due > later);
+    }
+
+    #[test]
+    fn parse_and_write() {
+        let mut scheduler = Scheduler::new();
+        scheduler
+            .at(
+                UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                Action::SwitchMultilevelSet(4, 30),
+            )
+            .unwrap();
+        scheduler.jobs.push(Job {
+            id: 9,
+            action: Action::SceneActivate("all off".to_string()),
+            due: UNIX_EPOCH + Duration::from_secs(1_700_000_600),
+            interval: Some(Duration::from_secs(86_400)),
+            priority: Priority::Low,
+        });
+
+        let text = scheduler.to_string();
+        assert_eq!(
+            text,
+            "1\t1700000000\t0\tnormal\tlevel 4 30\n9\t1700000600\t86400\tlow\tscene all off\n"
+        );
+
+        let mut parsed = Scheduler::parse(&text).unwrap();
+        assert_eq!(parsed.jobs(), scheduler.jobs());
+        assert_eq!(parsed.at(SystemTime::now(), Action::BasicSet(2, 0)), Ok(10));
+
+        assert!(Scheduler::parse("1\t0\t0\tnormal\tswitch 5 maybe").is_err());
+        assert!(Scheduler::parse("1\t0\t0\tnormal\tscene ").is_err());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut scheduler = Scheduler::new();
+        scheduler
+            .after(Duration::from_millis(1500), Action::BasicSet(2, 0xFF))
+            .unwrap();
+        scheduler
+            .every(
+                Duration::from_secs(60),
+                Priority::Low,
+                Action::SceneActivate("night".to_string()),
+            )
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("rzw-jobs-{}", std::process::id()));
+        scheduler.save(&path).unwrap();
+        let loaded = Scheduler::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().jobs(), scheduler.jobs());
+    }
+
+    #[test]
+    fn reject() {
+        let mut scheduler = Scheduler::new();
+        let action = || Action::BasicSet(2, 0);
+
+        // the interval is written in whole seconds
+        assert!(scheduler
+            .every(Duration::from_secs(0), Priority::Normal, action())
+            .is_err());
+        assert!(scheduler
+            .every(Duration::from_millis(500), Priority::Normal, action())
+            .is_err());
+        assert!(scheduler
+            .every(Duration::from_millis(1500), Priority::Normal, action())
+            .is_err());
+
+        // the scene name needs to fit into one line
+        let now = SystemTime::now();
+        assert!(scheduler
+            .at(now, Action::SceneActivate("a\nb".to_string()))
+            .is_err());
+        assert!(scheduler
+            .at(now, Action::SceneActivate("a\tb".to_string()))
+            .is_err());
+        assert!(scheduler.jobs().is_empty());
+    }
+
+    #[test]
+    fn run_scheduled() {
+        let sim = SimNetwork::new();
+        sim.add_node(
+            SimNode::new(2, GenericType::BinarySwitch)
+                .command_classes(&[CommandClass::SWITCH_BINARY]),
+        );
+
+        let controller = Controller::new(sim.clone()).unwrap();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let on = controller
+            .scheduler()
+            .at(past, Action::SwitchBinarySet(2, true))
+            .unwrap();
+        let missing = controller
+            .scheduler()
+            .at(past, Action::SwitchBinarySet(9, true))
+            .unwrap();
+
+        let results = controller.run_scheduled();
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].0, results[0].1.is_ok()), (on, true));
+        assert_eq!((results[1].0, results[1].1.is_err()), (missing, true));
+        assert_eq!(sim.value(2, CommandClass::SWITCH_BINARY), Some(vec![0xFF]));
+        assert!(controller.scheduler().jobs().is_empty());
+    }
+}
@@ -0,0 +1,667 @@
+//! Encrypted commands of the securely included nodes
+//!
+//! The commands to a node, which was included with S0, are encrypted with a
+//! nonce of the node, which is requested right before every frame. The other
+//! way round the controller hands out its nonces to the nodes. A nonce is
+//! only valid once and only for a few seconds.
+//!
+//! A node, which was included with S2, shares a SPAN with the controller.
+//! It's set up with the entropy of both sides once, afterwards the frames
+//! take their nonces from it without a round trip. The S2 keys are handed
+//! to the nodes by the controller, which included them, this one only uses
+//! them.
+
+use super::{Controller, Node};
+use crate::cmds::aes::BLOCK_SIZE;
+use crate::cmds::security::{Security, SecurityKeys, NONCE_SIZE};
+use crate::cmds::security2::{S2Header, S2Keys, Security2, Span, ENTROPY_SIZE};
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::defs::{SecurityClass, TransmitOptions};
+use crate::driver::serial::{SerialMsg, SerialMsgFunction, SerialMsgType};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind};
+use crate::keyring::{os_random, NetworkKey};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The time a node has to use a nonce of the controller
+const NONCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most nonces, which are handed out at the same time
+const MAX_NONCES: usize = 16;
+
+/// A nonce, which was handed out to a node
+#[derive(Debug)]
+struct IssuedNonce {
+    node: u8,
+    nonce: [u8; NONCE_SIZE],
+    issued: Instant,
+}
+
+/// The state of the SPAN with a node
+#[derive(Debug)]
+enum SpanState {
+    /// The controller handed out its entropy, the node sets up the SPAN
+    /// with its next frame
+    Local([u8; ENTROPY_SIZE]),
+    /// The SPAN was set up with the keys of the security class
+    Established(SecurityClass, Box<Span>),
+}
+
+/// The network keys and the nonces, which were handed out to the nodes.
+#[derive(Debug)]
+pub struct SecurityContext {
+    home_id: u32,
+    controller: u8,
+    key: Option<NetworkKey>,
+    keys: Option<SecurityKeys>,
+    nonces: Vec<IssuedNonce>,
+    s2_keys: Vec<(SecurityClass, S2Keys)>,
+    spans: HashMap<u8, SpanState>,
+    sequences: HashMap<u8, u8>,
+}
+
+impl SecurityContext {
+    /// Create a context without keys for the controller with the node id
+    pub fn new(home_id: u32, controller: u8) -> SecurityContext {
+        SecurityContext {
+            home_id,
+            controller,
+            key: None,
+            keys: None,
+            nonces: vec![],
+            s2_keys: vec![],
+            spans: HashMap::new(),
+            sequences: HashMap::new(),
+        }
+    }
+
+    /// Use the network key, which the nodes were included with
+    pub fn set_key(&mut self, key: &NetworkKey) {
+        self.key = Some(*key);
+        self.keys = Some(SecurityKeys::new(&key.0));
+    }
+
+    /// Returns the network key, which is handed to the new nodes
+    pub fn network_key(&self) -> Result<NetworkKey, Error> {
+        self.key
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The S0 network key is not set"))
+    }
+
+    /// Returns the keys, which are derived from the network key
+    pub fn keys(&self) -> Result<&SecurityKeys, Error> {
+        self.keys
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The S0 network key is not set"))
+    }
+
+    /// Hand out a new nonce to the node. The id of the nonce, its first
+    /// byte, is unique among the nonces, which are still valid.
+    pub fn issue(&mut self, node: u8) -> Result<[u8; NONCE_SIZE], Error> {
+        self.expire();
+        if self.nonces.len() >= MAX_NONCES {
+            self.nonces.remove(0);
+        }
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        loop {
+            os_random(&mut nonce)?;
+            if self.nonces.iter().all(|n| n.nonce[0] != nonce[0]) {
+                break;
+            }
+        }
+
+        self.nonces.push(IssuedNonce {
+            node,
+            nonce,
+            issued: Instant::now(),
+        });
+        Ok(nonce)
+    }
+
+    /// Take the nonce with the id, which was handed out to the node. It
+    /// can't be used again.
+    pub fn take(&mut self, node: u8, id: u8) -> Option<[u8; NONCE_SIZE]> {
+        self.expire();
+
+        let index = self
+            .nonces
+            .iter()
+            .position(|n| n.node == node && n.nonce[0] == id)?;
+        Some(self.nonces.remove(index).nonce)
+    }
+
+    /// Forget the nonces, which weren't used in time
+    fn expire(&mut self) {
+        self.nonces.retain(|n| n.issued.elapsed() < NONCE_TIMEOUT);
+    }
+
+    /// Use the network key of the S2 security class, which the nodes were
+    /// included with. The SPANs of the class are set up again.
+    pub fn set_s2_key(&mut self, class: SecurityClass, key: &NetworkKey) -> Result<(), Error> {
+        if !class.is_secure() || class == SecurityClass::S0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{:?} is no S2 security class", class),
+            ));
+        }
+
+        self.s2_keys.retain(|(c, _)| *c != class);
+        self.s2_keys.push((class, S2Keys::new(&key.0)));
+        self.spans
+            .retain(|_, span| !matches!(span, SpanState::Established(c, _) if *c == class));
+        Ok(())
+    }
+
+    /// Returns the keys, which are derived from the network key of the S2
+    /// security class
+    pub fn s2_keys(&self, class: SecurityClass) -> Result<&S2Keys, Error> {
+        self.s2_keys
+            .iter()
+            .find(|(c, _)| *c == class)
+            .map(|(_, keys)| keys)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The network key of {:?} is not set", class),
+                )
+            })
+    }
+
+    /// Returns the next sequence number of the S2 frames to the node
+    pub fn next_sequence(&mut self, node: u8) -> u8 {
+        let sequence = self.sequences.entry(node).or_insert(0);
+        *sequence = sequence.wrapping_add(1);
+        *sequence
+    }
+
+    /// Returns if a SPAN with the node is set up for the security class
+    pub fn has_span(&self, node: u8, class: SecurityClass) -> bool {
+        matches!(self.spans.get(&node), Some(SpanState::Established(c, _)) if *c == class)
+    }
+
+    /// Hand out new entropy of the controller to the node, the node sets up
+    /// a new SPAN with it.
+    pub fn local_entropy(&mut self, node: u8) -> Result<[u8; ENTROPY_SIZE], Error> {
+        let mut entropy = [0u8; ENTROPY_SIZE];
+        os_random(&mut entropy)?;
+
+        self.spans.insert(node, SpanState::Local(entropy));
+        Ok(entropy)
+    }
+
+    /// Encrypt the frame to the node with S2. With the entropy of the node a
+    /// new SPAN is set up and the frame carries the entropy of the
+    /// controller, otherwise the SPAN needs to be set up already.
+    pub fn encrypt_s2(
+        &mut self,
+        frame: Message,
+        class: SecurityClass,
+        receiver_entropy: Option<[u8; ENTROPY_SIZE]>,
+    ) -> Result<Message, Error> {
+        let node = frame.node_id;
+        let keys = self.s2_keys(class)?.clone();
+
+        let entropy = match receiver_entropy {
+            Some(receiver_entropy) => {
+                let mut entropy = [0u8; ENTROPY_SIZE];
+                os_random(&mut entropy)?;
+                let span = Span::new(&entropy, &receiver_entropy, &keys);
+                self.spans
+                    .insert(node, SpanState::Established(class, Box::new(span)));
+                Some(entropy)
+            }
+            None => None,
+        };
+
+        let nonce = match self.spans.get_mut(&node) {
+            Some(SpanState::Established(c, span)) if *c == class => span.next_nonce(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("No SPAN is set up with the node {}", node),
+                ))
+            }
+        };
+
+        let header = S2Header {
+            source: self.controller,
+            destination: node,
+            home_id: self.home_id,
+            sequence: self.next_sequence(node),
+            entropy,
+        };
+        Security2::encap(frame, &header, &keys, nonce)
+    }
+
+    /// Decrypt the S2 frame of a node. A frame with the entropy of the node
+    /// sets up the SPAN with the entropy, which the controller handed out
+    /// before, and the key of the class, which authenticates it.
+    pub fn decrypt_s2(&mut self, frame: &IncomingFrame) -> Result<IncomingFrame, Error> {
+        let node = frame.source;
+        let header = Security2::header(frame, self.controller, self.home_id)?;
+
+        match (header.entropy, self.spans.get_mut(&node)) {
+            (Some(entropy), Some(SpanState::Local(local))) => {
+                let local = *local;
+                for (class, keys) in &self.s2_keys {
+                    let mut span = Span::new(&entropy, &local, keys);
+                    if let Ok(command) = Security2::decap(frame, &header, keys, span.next_nonce()) {
+                        self.spans
+                            .insert(node, SpanState::Established(*class, Box::new(span)));
+                        return Ok(command);
+                    }
+                }
+
+                Err(Error::parse(
+                    CommandClass::SECURITY_2,
+                    "The frame couldn't be authenticated with any key",
+                ))
+            }
+            (None, Some(SpanState::Established(class, span))) => {
+                let nonce = span.next_nonce();
+                let keys = self
+                    .s2_keys
+                    .iter()
+                    .find(|(c, _)| c == class)
+                    .map(|(_, keys)| keys)
+                    .ok_or_else(|| {
+                        Error::new(ErrorKind::InvalidInput, "The S2 network key is not set")
+                    })?;
+                Security2::decap(frame, &header, keys, nonce)
+            }
+            _ => Err(Error::parse(
+                CommandClass::SECURITY_2,
+                "No SPAN is set up with the node",
+            )),
+        }
+    }
+}
+
+impl<D> Controller<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Hand the S0 network key to the node, which was just included. The
+    /// key is encrypted with a temporary key of zeros and the node confirms
+    /// it with a Network Key Verify, which is encrypted with the new key.
+    ///
+    /// Afterwards the commands to the node are encrypted.
+    pub(super) fn s0_key_exchange(&self, id: u8) -> Result<(), Error> {
+        let key = self.security.lock().unwrap().network_key()?;
+        let node = self.stored_node(id)?;
+
+        // the scheme is requested unencrypted
+        Security::scheme_report(&node.request(Security::scheme_get(id))?)?;
+
+        self.set_node_security_class(id, SecurityClass::S0)?;
+        let result = self.send_network_key(id, key);
+        if result.is_err() {
+            self.set_node_security_class(id, SecurityClass::None)?;
+        }
+
+        result
+    }
+
+    /// Return the stored node, with the current security class
+    fn stored_node(&self, id: u8) -> Result<Node<D>, Error> {
+        self.nodes
+            .borrow()
+            .iter()
+            .find(|n| n.get_id() == id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The node {} doesn't exist", id),
+                )
+            })
+    }
+
+    /// Send the network key to the node and wait for the Network Key Verify
+    fn send_network_key(&self, id: u8, key: NetworkKey) -> Result<(), Error> {
+        let node = self.stored_node(id)?;
+
+        // only the Network Key Set is encrypted with the temporary key
+        self.security
+            .lock()
+            .unwrap()
+            .set_key(&NetworkKey([0; BLOCK_SIZE]));
+        let sent = node.send(Security::network_key_set(id, key.0));
+        self.security.lock().unwrap().set_key(&key);
+        sent?;
+
+        let security = self.security.clone();
+        self.driver.run(move |d| {
+            let deadline = Instant::now() + NONCE_TIMEOUT;
+            let mut others = vec![];
+            let result = loop {
+                let left = deadline.saturating_duration_since(Instant::now());
+                let command = match Node::read_command(d, id, Some(left), &security) {
+                    Ok(command) => command,
+                    Err(e) => break Err(e),
+                };
+                if let Ok(verified) = Security::network_key_verify(&command.frame) {
+                    break Ok(verified);
+                }
+                others.push(SerialMsg::new(
+                    SerialMsgType::Request,
+                    SerialMsgFunction::ApplicationCommandHandler,
+                    command.frame.to_vec(),
+                ));
+            };
+
+            d.unread(others);
+            result
+        })
+    }
+}
+
+impl<D> Node<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Request a nonce of the node and encrypt the frame with it. The
+    /// commands of the other nodes, which arrive meanwhile, are put back for
+    /// the normal receive path. The nonce is only waited for as long as it's
+    /// valid, even when no report timeout is set.
+    pub(super) fn encrypt_frame(
+        driver: &mut D,
+        frame: Message,
+        security: &Mutex<SecurityContext>,
+        options: TransmitOptions,
+        report_timeout: Option<Duration>,
+    ) -> Result<Message, Error> {
+        let node = frame.node_id;
+        // fail before anything is sent, when the key is missing
+        security.lock().unwrap().keys()?;
+
+        driver.write_with_options(Security::nonce_get(node), options)?;
+        let report = Node::wait_nonce(driver, node, CommandClass::SECURITY, 0x80, report_timeout)?;
+        let receiver_nonce = Security::nonce_report(&report)?;
+
+        let mut sender_nonce = [0u8; NONCE_SIZE];
+        os_random(&mut sender_nonce)?;
+
+        let context = security.lock().unwrap();
+        Security::encap(
+            frame,
+            context.keys()?,
+            context.controller,
+            sender_nonce,
+            receiver_nonce,
+        )
+    }
+
+    /// Encrypt the frame with S2. The entropy of the node is requested
+    /// first, when no SPAN is set up with it.
+    pub(super) fn encrypt_s2_frame(
+        driver: &mut D,
+        frame: Message,
+        class: SecurityClass,
+        security: &Mutex<SecurityContext>,
+        options: TransmitOptions,
+        report_timeout: Option<Duration>,
+    ) -> Result<Message, Error> {
+        let node = frame.node_id;
+        // fail before anything is sent, when the key is missing
+        security.lock().unwrap().s2_keys(class)?;
+
+        if security.lock().unwrap().has_span(node, class) {
+            return security.lock().unwrap().encrypt_s2(frame, class, None);
+        }
+
+        let sequence = security.lock().unwrap().next_sequence(node);
+        driver.write_with_options(Security2::nonce_get(node, sequence), options)?;
+        let report =
+            Node::wait_nonce(driver, node, CommandClass::SECURITY_2, 0x02, report_timeout)?;
+        let entropy = Security2::nonce_report(&report)?.ok_or_else(|| {
+            Error::parse(
+                CommandClass::SECURITY_2,
+                "The node didn't hand out its entropy",
+            )
+        })?;
+
+        security
+            .lock()
+            .unwrap()
+            .encrypt_s2(frame, class, Some(entropy))
+    }
+
+    /// Wait for the nonce report of the node. The commands of the other
+    /// nodes, which arrive meanwhile, are put back for the normal receive
+    /// path. The nonce is only waited for as long as it's valid, even when
+    /// no report timeout is set.
+    fn wait_nonce(
+        driver: &mut D,
+        node: u8,
+        command_class: CommandClass,
+        command: u8,
+        report_timeout: Option<Duration>,
+    ) -> Result<IncomingFrame, Error> {
+        let timeout = report_timeout.map_or(NONCE_TIMEOUT, |t| t.min(NONCE_TIMEOUT));
+        let deadline = Instant::now() + timeout;
+        let mut others = vec![];
+        let report = loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let answer = match Node::wait_frame(driver, left) {
+                Ok(answer) => answer,
+                Err(e) => break Err(e),
+            };
+            if answer.source == node
+                && answer.command_class == Ok(command_class)
+                && answer.command == command
+            {
+                break Ok(answer);
+            }
+            others.push(SerialMsg::new(
+                SerialMsgType::Request,
+                SerialMsgFunction::ApplicationCommandHandler,
+                answer.to_vec(),
+            ));
+        };
+
+        driver.unread(others);
+        report
+    }
+
+    /// Answer the nonce requests of the nodes and decrypt their encrypted
+    /// commands. Returns `None` for the frames, which were handled already.
+    pub(super) fn decrypt_frame(
+        driver: &mut D,
+        frame: IncomingFrame,
+        security: &Mutex<SecurityContext>,
+    ) -> Result<Option<IncomingFrame>, Error> {
+        if frame.command_class == Ok(CommandClass::SECURITY_2) {
+            return Node::decrypt_s2_frame(driver, frame, security);
+        }
+        if frame.command_class != Ok(CommandClass::SECURITY) {
+            return Ok(Some(frame));
+        }
+
+        match frame.command {
+            // the node wants to send an encrypted command
+            0x40 => {
+                let nonce = security.lock().unwrap().issue(frame.source)?;
+                driver.write(Security::nonce_report_message(frame.source, nonce))?;
+                Ok(None)
+            }
+            0x81 | 0xC1 => {
+                let (command, nonce) = {
+                    let mut context = security.lock().unwrap();
+                    let receiver_nonce = context
+                        .take(frame.source, Security::nonce_id(&frame)?)
+                        .ok_or_else(|| {
+                            Error::parse(
+                                CommandClass::SECURITY,
+                                "The nonce expired or was never handed out",
+                            )
+                        })?;
+                    let command = Security::decap(
+                        &frame,
+                        context.keys()?,
+                        context.controller,
+                        receiver_nonce,
+                    )?;
+
+                    // the node sends another command right after
+                    let nonce = match frame.command {
+                        0xC1 => Some(context.issue(frame.source)?),
+                        _ => None,
+                    };
+                    (command, nonce)
+                };
+
+                if let Some(nonce) = nonce {
+                    driver.write(Security::nonce_report_message(frame.source, nonce))?;
+                }
+                Ok(Some(command))
+            }
+            _ => Ok(Some(frame)),
+        }
+    }
+
+    /// Answer the entropy requests of the nodes and decrypt their S2
+    /// commands. A frame, which can't be decrypted, is dropped and the node
+    /// is asked to set up a new SPAN.
+    fn decrypt_s2_frame(
+        driver: &mut D,
+        frame: IncomingFrame,
+        security: &Mutex<SecurityContext>,
+    ) -> Result<Option<IncomingFrame>, Error> {
+        let node = frame.source;
+
+        match frame.command {
+            // the node wants to set up a SPAN
+            0x01 => {}
+            // the node couldn't decrypt the last frame of the controller
+            0x02 => {
+                if Security2::nonce_report(&frame)?.is_some() {
+                    security.lock().unwrap().spans.remove(&node);
+                }
+                return Ok(None);
+            }
+            0x03 => match security.lock().unwrap().decrypt_s2(&frame) {
+                Ok(command) => return Ok(Some(command)),
+                Err(Error::ParseError { .. }) => {}
+                Err(e) => return Err(e),
+            },
+            _ => return Ok(Some(frame)),
+        }
+
+        let (sequence, entropy) = {
+            let mut context = security.lock().unwrap();
+            (context.next_sequence(node), context.local_entropy(node)?)
+        };
+        driver.write(Security2::nonce_report_message(node, sequence, entropy))?;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::sim::fixture::network;
+    use crate::basic::sim::{SimNode, SIM_CONTROLLER_ID, SIM_HOME_ID};
+    use crate::defs::{GenericType, SecurityClass};
+
+    #[test]
+    fn nonces() {
+        let mut context = SecurityContext::new(SIM_HOME_ID, 1);
+        assert!(context.keys().is_err());
+        context.set_key(&NetworkKey([0x42; 16]));
+        assert!(context.keys().is_ok());
+
+        let first = context.issue(5).unwrap();
+        let second = context.issue(5).unwrap();
+        assert_ne!(first[0], second[0]);
+
+        // a nonce belongs to its node and is only valid once
+        assert_eq!(context.take(6, first[0]), None);
+        assert_eq!(context.take(5, first[0]), Some(first));
+        assert_eq!(context.take(5, first[0]), None);
+        assert_eq!(context.take(5, second[0]), Some(second));
+    }
+
+    #[test]
+    fn secure() {
+        let nonce = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+        let (sim, mut controller) = network(vec![SimNode::new(2, GenericType::BinarySwitch)
+            .command_classes(&[CommandClass::SWITCH_BINARY, CommandClass::SECURITY])
+            .respond(CommandClass::SECURITY, 0x40, 0x80, &nonce)]);
+        controller
+            .set_node_security_class(2, SecurityClass::S0)
+            .unwrap();
+        let node = controller.node(2).unwrap();
+
+        // the commands can't be encrypted without the key
+        assert!(node.switch_binary_set(true).is_err());
+
+        let key = NetworkKey([0x42; 16]);
+        controller.set_s0_key(key);
+        node.switch_binary_set(true).unwrap();
+
+        // the nonce is requested right before the encrypted command
+        let received = sim.received(2);
+        assert_eq!(received[received.len() - 2], vec![0x98, 0x40]);
+
+        let encrypted = &received[received.len() - 1];
+        let mut data = vec![0x00, SIM_CONTROLLER_ID, encrypted.len() as u8];
+        data.extend_from_slice(encrypted);
+        let frame = IncomingFrame::parse(&data).unwrap();
+        let command = Security::decap(&frame, &SecurityKeys::new(&key.0), 2, nonce).unwrap();
+        assert_eq!(command.command_class, Ok(CommandClass::SWITCH_BINARY));
+        assert_eq!((command.command, command.payload), (0x01, vec![0xFF]));
+
+        // a report of another node, which arrives before the nonce, is kept
+        sim.add_node(SimNode::new(3, GenericType::BinarySwitch));
+        sim.report(3, CommandClass::BASIC, 0x03, &[0x63]);
+        node.switch_binary_set(false).unwrap();
+        let report = sim.clone().read_frame().unwrap();
+        assert_eq!(report.source, 3);
+        assert_eq!(report.expect(CommandClass::BASIC, 0x03), Ok(&[0x63][..]));
+    }
+
+    #[test]
+    fn secure_s2() {
+        let key = NetworkKey([0x24; 16]);
+        let (sim, mut controller) = network(vec![SimNode::new(2, GenericType::BinarySwitch)
+            .command_classes(&[CommandClass::SWITCH_BINARY, CommandClass::SECURITY_2])
+            .secure_s2(key.0)]);
+        controller
+            .set_node_security_class(2, SecurityClass::S2Authenticated)
+            .unwrap();
+        let node = controller.node(2).unwrap();
+
+        // the commands can't be encrypted without the key of the class
+        assert!(node.switch_binary_set(true).is_err());
+        assert!(controller.set_s2_key(SecurityClass::S0, key).is_err());
+        controller
+            .set_s2_key(SecurityClass::S2Unauthenticated, NetworkKey([0x42; 16]))
+            .unwrap();
+        assert!(node.switch_binary_set(true).is_err());
+
+        controller
+            .set_s2_key(SecurityClass::S2Authenticated, key)
+            .unwrap();
+        node.switch_binary_set(true).unwrap();
+        assert_eq!(sim.value(2, CommandClass::SWITCH_BINARY), Some(vec![0xFF]));
+
+        // the entropy of the node is requested once for the SPAN
+        let received = sim.received(2);
+        assert_eq!(
+            received[received.len() - 2],
+            vec![0x9F, 0x01, received[received.len() - 2][2]]
+        );
+        assert_eq!(received[received.len() - 1][..2], [0x9F, 0x03]);
+
+        // afterwards the frames in both directions take the next nonce
+        assert!(node.switch_binary_get().unwrap());
+        let count = sim.received(2).len();
+        node.switch_binary_set(false).unwrap();
+        assert!(!node.switch_binary_get().unwrap());
+        assert_eq!(sim.received(2).len(), count + 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! Controller self test
+//!
+//! Checks if the controller stick itself works, independent of the nodes in
+//! the network. When the self test passes but nodes don't answer, the
+//! problem is located in the network and not in the stick.
+
+use super::Controller;
+use crate::defs::SerialMessageFunction;
+use crate::driver::Driver;
+use crate::error::Error;
+
+use std::time::{Duration, Instant};
+
+/// The functions the controller needs to support, to control the network
+const REQUIRED_FUNCTIONS: [SerialMessageFunction; 5] = [
+    SerialMessageFunction::SendData,
+    SerialMessageFunction::GetVersion,
+    SerialMessageFunction::MemoryGetId,
+    SerialMessageFunction::GetNodeProtocolInfo,
+    SerialMessageFunction::DiscoveryNodes,
+];
+
+/// The amount of bytes which are read from the memory of the controller
+const MEMORY_SAMPLE: u8 = 16;
+
+/// The result of a single check.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub passed: bool,
+    pub duration: Duration,
+    /// What was found, or why the check failed
+    pub detail: String,
+}
+
+impl SelfTestCheck {
+    /// Run the check and measure its duration.
+    pub fn run<N, F>(name: N, check: F) -> SelfTestCheck
+    where
+        N: Into<String>,
+        F: FnOnce() -> Result<String, Error>,
+    {
+        let start = Instant::now();
+        let result = check();
+
+        SelfTestCheck {
+            name: name.into(),
+            passed: result.is_ok(),
+            duration: start.elapsed(),
+            detail: match result {
+                Ok(detail) => detail,
+                Err(err) => err.to_string(),
+            },
+        }
+    }
+}
+
+/// The result of the controller self test.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// Check if all checks passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    /// Return the checks which failed
+    pub fn failures(&self) -> Vec<&SelfTestCheck> {
+        self.checks.iter().filter(|c| !c.passed).collect()
+    }
+}
+
+impl<D> Controller<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Check if the serial link to the controller works.
+    ///
+    /// This queries the version and the capabilities of the controller, resets
+    /// the controller and checks that it answers again and reads a sample of its
+    /// memory. No message is sent to a node.
+    pub fn self_test(&self) -> SelfTestReport {
+        let (expected_home_id, expected_node_id) = (self.info.home_id, self.info.node_id);
+
+        // all checks run at once, so no message gets between them
+        let report = self.driver.run(move |driver| {
+            let mut checks = vec![];
+
+            let mut info = None;
+            checks.push(SelfTestCheck::run("version", || {
+                let i = driver.get_controller_info()?;
+                let detail = format!("{} ({:?})", i.version, i.library);
+                info = Some(i);
+                Ok(detail)
+            }));
+
+            checks.push(SelfTestCheck::run("capabilities", || {
+                let api = match info {
+                    Some(ref i) => &i.api,
+                    None => return Err(Error::NoController("No version information".into())),
+                };
+
+                let missing: Vec<_> = REQUIRED_FUNCTIONS
+                    .iter()
+                    .filter(|f| !api.supports(**f))
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(Error::NotImplemented(format!(
+                        "The controller doesn't support {:?}",
+                        missing
+                    )));
+                }
+
+                Ok(format!("{} functions", api.functions.len()))
+            }));
+
+            checks.push(SelfTestCheck::run("soft reset", || {
+                driver.soft_reset()?;
+
+                // the controller needs to answer with the same network
+                let (home_id, node_id) = driver.get_controller_id()?;
+                if home_id != expected_home_id || node_id != expected_node_id {
+                    return Err(Error::Protocol(format!(
+                        "The controller answered with the home id {:08X} and node id {}",
+                        home_id, node_id
+                    )));
+                }
+
+                Ok(format!("home id {:08X}", home_id))
+            }));
+
+            checks.push(SelfTestCheck::run("memory read", || {
+                let data = driver.read_memory(0x0000, MEMORY_SAMPLE)?;
+                Ok(format!("{} bytes read", data.len()))
+            }));
+
+            Ok(SelfTestReport { checks })
+        });
+
+        report.unwrap_or_else(|e| SelfTestReport {
+            checks: vec![SelfTestCheck::run("driver", || Err(e))],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn report() {
+        let report = SelfTestReport {
+            checks: vec![
+                SelfTestCheck::run("version", || Ok("6.07".into())),
+                SelfTestCheck::run("memory read", || {
+                    Err(Error::new(ErrorKind::NoController, "disconnected"))
+                }),
+            ],
+        };
+
+        assert!(!report.passed());
+        assert_eq!(report.failures().len(), 1);
+        assert_eq!(report.failures()[0].name, "memory read");
+        assert_eq!(report.checks[0].detail, "6.07");
+    }
+}
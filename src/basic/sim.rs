@@ -0,0 +1,1152 @@
+//! Simulated network
+//!
+//! The `SimNetwork` is a driver, which talks to a virtual network instead
+//! of a Z-Wave controller. Its nodes are scripted with their command classes,
+//! values, answers, latency and the way they fail, so the whole controller
+//! can be tested without hardware. It's built for the tests and with the
+//! `sim` feature.
+//!
+//! ```rust,ignore
+//! let sim = SimNetwork::new();
+//! sim.add_node(
+//!     SimNode::new(2, GenericType::BinarySwitch)
+//!         .command_classes(&[CommandClass::SWITCH_BINARY])
+//!         .latency(Duration::from_millis(20)),
+//! );
+//!
+//! let mut controller = Controller::new(sim.clone()).unwrap();
+//! controller.node(2).unwrap().switch_binary_set(true).unwrap();
+//! assert_eq!(sim.value(2, CommandClass::SWITCH_BINARY), Some(vec![0xFF]));
+//! ```
+
+#[cfg(feature = "cc-security")]
+use crate::cmds::aes::BLOCK_SIZE;
+#[cfg(feature = "cc-security")]
+use crate::cmds::security::{Security, SecurityKeys, NONCE_SIZE};
+#[cfg(feature = "cc-security")]
+use crate::cmds::security2::{S2Header, S2Keys, Security2, Span, ENTROPY_SIZE};
+use crate::cmds::CommandClass;
+#[cfg(feature = "cc-security")]
+use crate::cmds::Message;
+use crate::defs::GenericType;
+use crate::defs::{
+    ControllerCapabilities, ControllerInfo, IncomingFrame, LibraryType, NodeActivity,
+    NodeProtocolInfo, Route, SerialApiCapabilities, TransmitOptions, TransmitReport,
+    TransmitStatus,
+};
+use crate::driver::serial::{SerialMsg, SerialMsgFunction, SerialMsgType};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind, Stage};
+
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "cc-security")]
+use std::convert::TryFrom;
+use std::io::ErrorKind as StdErrorKind;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The home id of the simulated network
+pub const SIM_HOME_ID: u32 = 0xC0FF_EE01;
+
+/// The node id of the simulated controller
+pub const SIM_CONTROLLER_ID: u8 = 1;
+
+/// Amount of transmit reports which are kept, when nobody waits for them
+const MAX_REPORTS: usize = 16;
+
+/// The way a simulated node fails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimFailure {
+    /// The node doesn't acknowledge any frame, like a node out of range
+    NoAck,
+    /// The node acknowledges the frames, but never answers them
+    NoAnswer,
+    /// Every n-th frame to the node isn't acknowledged
+    Flaky(u32),
+}
+
+/// A scripted answer of a node
+#[derive(Debug, Clone)]
+struct SimResponse {
+    cmd_class: u8,
+    cmd: u8,
+    answer: u8,
+    payload: Vec<u8>,
+}
+
+#[cfg(feature = "cc-security")]
+/// The S0 state of a secure node
+#[derive(Debug, Clone)]
+struct SimSecurity {
+    // the temporary key of zeros, until the node got the network key
+    key: [u8; BLOCK_SIZE],
+    // the nonce, which the node handed out last
+    nonce: [u8; NONCE_SIZE],
+    // the answer, which waits for a nonce of the controller
+    pending: Option<(u8, u8, Vec<u8>)>,
+}
+
+#[cfg(feature = "cc-security")]
+/// The S2 state of a secure node
+#[derive(Debug, Clone)]
+struct SimS2 {
+    keys: S2Keys,
+    // the entropy, which the node handed out last
+    entropy: [u8; ENTROPY_SIZE],
+    span: Option<Span>,
+    sequence: u8,
+}
+
+/// A virtual node of the simulated network.
+#[derive(Debug, Clone)]
+pub struct SimNode {
+    id: u8,
+    generic: GenericType,
+    specific: u8,
+    cmds: Vec<CommandClass>,
+    listening: bool,
+    awake: bool,
+    latency: Duration,
+    failure: Option<SimFailure>,
+    values: HashMap<u8, Vec<u8>>,
+    responses: Vec<SimResponse>,
+    frames: u32,
+    #[cfg(feature = "cc-security")]
+    security: Option<SimSecurity>,
+    #[cfg(feature = "cc-security")]
+    s2: Option<SimS2>,
+}
+
+impl SimNode {
+    /// Create a listening node, which answers right away.
+    pub fn new(id: u8, generic: GenericType) -> SimNode {
+        SimNode {
+            id,
+            generic,
+            specific: 0x01,
+            cmds: vec![],
+            listening: true,
+            awake: false,
+            latency: Duration::from_millis(0),
+            failure: None,
+            values: HashMap::new(),
+            responses: vec![],
+            frames: 0,
+            #[cfg(feature = "cc-security")]
+            security: None,
+            #[cfg(feature = "cc-security")]
+            s2: None,
+        }
+    }
+
+    /// Set the specific device class.
+    pub fn specific(mut self, specific: u8) -> SimNode {
+        self.specific = specific;
+        self
+    }
+
+    /// Set the command classes, which the node lists in its node information.
+    pub fn command_classes(mut self, cmds: &[CommandClass]) -> SimNode {
+        self.cmds = cmds.to_vec();
+        self
+    }
+
+    /// Let the node sleep, it only receives frames between a wake up and
+    /// the Wake Up No More Information command.
+    pub fn sleeping(mut self) -> SimNode {
+        self.listening = false;
+        self
+    }
+
+    /// Set the time the node needs to answer.
+    pub fn latency(mut self, latency: Duration) -> SimNode {
+        self.latency = latency;
+        self
+    }
+
+    /// Let the node fail.
+    pub fn failure(mut self, failure: SimFailure) -> SimNode {
+        self.failure = Some(failure);
+        self
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Let the node take part in S0. It accepts the network key at the
+    /// inclusion and answers the encrypted commands encrypted.
+    pub fn secure(mut self) -> SimNode {
+        self.security = Some(SimSecurity {
+            key: [0; BLOCK_SIZE],
+            nonce: [0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7],
+            pending: None,
+        });
+        self
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Let the node take part in S2 with the network key of its security
+    /// class. It answers the encrypted commands encrypted.
+    pub fn secure_s2(mut self, key: [u8; BLOCK_SIZE]) -> SimNode {
+        self.s2 = Some(SimS2 {
+            keys: S2Keys::new(&key),
+            entropy: [0xC0; ENTROPY_SIZE],
+            span: None,
+            sequence: 0,
+        });
+        self
+    }
+
+    /// Set the value, which the node reports for the command class.
+    pub fn value(mut self, cmd_class: CommandClass, value: &[u8]) -> SimNode {
+        self.values.insert(cmd_class as u8, value.to_vec());
+        self
+    }
+
+    /// Answer the command with the given command and payload, instead of
+    /// the usual Set, Get and Report handling.
+    pub fn respond(
+        mut self,
+        cmd_class: CommandClass,
+        cmd: u8,
+        answer: u8,
+        payload: &[u8],
+    ) -> SimNode {
+        self.responses.push(SimResponse {
+            cmd_class: cmd_class as u8,
+            cmd,
+            answer,
+            payload: payload.to_vec(),
+        });
+        self
+    }
+
+    /// Returns the id of the node
+    pub fn get_id(&self) -> u8 {
+        self.id
+    }
+
+    /// Check if the node acknowledges the next frame
+    fn acknowledges(&mut self) -> bool {
+        self.frames = self.frames.wrapping_add(1);
+
+        if !self.listening && !self.awake {
+            return false;
+        }
+
+        match self.failure {
+            Some(SimFailure::NoAck) => false,
+            Some(SimFailure::Flaky(n)) => !self.frames.is_multiple_of(n),
+            _ => true,
+        }
+    }
+
+    /// Handle a received frame and return the answer of the node
+    ///
+    /// `command class, command, payload`
+    fn handle(&mut self, frame: &[u8]) -> Option<SerialMsg> {
+        if frame.len() < 2 {
+            return None;
+        }
+        let (cmd_class, cmd, payload) = (frame[0], frame[1], &frame[2..]);
+
+        #[cfg(feature = "cc-security")]
+        if cmd_class == CommandClass::SECURITY as u8 && self.security.is_some() {
+            return self.handle_secure(cmd, payload);
+        }
+        #[cfg(feature = "cc-security")]
+        if cmd_class == CommandClass::SECURITY_2 as u8 && self.s2.is_some() {
+            return self.handle_s2(cmd, payload);
+        }
+
+        // the values are changed, even when the node doesn't answer
+        if cmd == 0x01 && cmd_class != CommandClass::NODE_INFO as u8 {
+            self.values.insert(cmd_class, payload.to_vec());
+        }
+        if cmd_class == CommandClass::WAKE_UP as u8 && cmd == 0x08 {
+            self.awake = false;
+        }
+
+        if self.failure == Some(SimFailure::NoAnswer) {
+            return None;
+        }
+
+        if let Some(response) = self
+            .responses
+            .iter()
+            .find(|r| r.cmd_class == cmd_class && r.cmd == cmd)
+        {
+            return Some(self.command(response.cmd_class, response.answer, &response.payload));
+        }
+
+        // the node information is sent as an update of the controller
+        if cmd_class == CommandClass::NODE_INFO as u8 && cmd == 0x02 {
+            let mut data = vec![
+                0x84,
+                self.id,
+                (self.cmds.len() + 3) as u8,
+                0x04,
+                self.generic as u8,
+                self.specific,
+            ];
+            data.extend(self.cmds.iter().map(|c| *c as u8));
+            return Some(SerialMsg::new(
+                SerialMsgType::Request,
+                SerialMsgFunction::ApplicationUpdate,
+                data,
+            ));
+        }
+
+        // the command classes, which are not supported are ignored
+        let supported = cmd_class == CommandClass::BASIC as u8
+            || self.cmds.iter().any(|c| *c as u8 == cmd_class);
+        if cmd == 0x02 && supported {
+            let value = self
+                .values
+                .get(&cmd_class)
+                .cloned()
+                .unwrap_or_else(|| vec![0]);
+            return Some(self.command(cmd_class, 0x03, &value));
+        }
+
+        None
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Handle a frame of the Security Command Class. The answers to the
+    /// encrypted commands wait for a nonce of the controller.
+    fn handle_secure(&mut self, cmd: u8, payload: &[u8]) -> Option<SerialMsg> {
+        let security = CommandClass::SECURITY as u8;
+
+        match cmd {
+            // the scheme report, only the S0 scheme is supported
+            0x04 => Some(self.command(security, 0x05, &[0x00])),
+            0x40 => {
+                let state = self.security.as_mut()?;
+                state.nonce[0] = state.nonce[0].wrapping_add(1);
+                let nonce = state.nonce;
+                Some(self.command(security, 0x80, &nonce))
+            }
+            // the nonce of the controller for the pending answer
+            0x80 if payload.len() >= NONCE_SIZE => {
+                let state = self.security.as_mut()?;
+                let (cmd_class, cmd, data) = state.pending.take()?;
+                let mut receiver_nonce = [0; NONCE_SIZE];
+                receiver_nonce.copy_from_slice(&payload[..NONCE_SIZE]);
+
+                let message = Message::new(
+                    SIM_CONTROLLER_ID,
+                    CommandClass::try_from(cmd_class).ok()?,
+                    cmd,
+                    &data,
+                )
+                .ok()?;
+                let encrypted = Security::encap(
+                    message,
+                    &SecurityKeys::new(&state.key),
+                    self.id,
+                    [0xB0; NONCE_SIZE],
+                    receiver_nonce,
+                )
+                .ok()?;
+                Some(self.command(security, 0x81, &encrypted.data))
+            }
+            0x81 => {
+                let state = self.security.as_mut()?;
+                let mut data = vec![0x00, SIM_CONTROLLER_ID, (payload.len() + 2) as u8];
+                data.extend_from_slice(&[security, 0x81]);
+                data.extend_from_slice(payload);
+                let frame = IncomingFrame::parse(&data).ok()?;
+                let command =
+                    Security::decap(&frame, &SecurityKeys::new(&state.key), self.id, state.nonce)
+                        .ok()?;
+
+                let answer = if command.command_class == Ok(CommandClass::SECURITY)
+                    && command.command == 0x06
+                    && command.payload.len() >= BLOCK_SIZE
+                {
+                    // the network key is confirmed with the new key
+                    state.key.copy_from_slice(&command.payload[..BLOCK_SIZE]);
+                    (security, 0x07, vec![])
+                } else {
+                    let mut inner = vec![command.command_class_id(), command.command];
+                    inner.extend_from_slice(&command.payload);
+                    let answer = self.handle(&inner)?;
+                    if answer.func != SerialMsgFunction::ApplicationCommandHandler
+                        || answer.data.len() < 5
+                    {
+                        return Some(answer);
+                    }
+                    (answer.data[3], answer.data[4], answer.data[5..].to_vec())
+                };
+
+                self.security.as_mut()?.pending = Some(answer);
+                Some(self.command(security, 0x40, &[]))
+            }
+            _ => {
+                let response = self
+                    .responses
+                    .iter()
+                    .find(|r| r.cmd_class == security && r.cmd == cmd)?;
+                Some(self.command(security, response.answer, &response.payload))
+            }
+        }
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Handle a frame of the Security 2 Command Class. The encrypted
+    /// commands are answered with the next nonce of the SPAN.
+    fn handle_s2(&mut self, cmd: u8, payload: &[u8]) -> Option<SerialMsg> {
+        let security = CommandClass::SECURITY_2 as u8;
+
+        match cmd {
+            // hand out new entropy, the controller sets up the SPAN with it
+            0x01 => {
+                let state = self.s2.as_mut()?;
+                state.entropy[0] = state.entropy[0].wrapping_add(1);
+                state.sequence = state.sequence.wrapping_add(1);
+                state.span = None;
+                let message = Security2::nonce_report_message(
+                    SIM_CONTROLLER_ID,
+                    state.sequence,
+                    state.entropy,
+                );
+                Some(self.command(security, 0x02, &message.data))
+            }
+            0x03 => {
+                let mut data = vec![0x00, SIM_CONTROLLER_ID, (payload.len() + 2) as u8];
+                data.extend_from_slice(&[security, 0x03]);
+                data.extend_from_slice(payload);
+                let frame = IncomingFrame::parse(&data).ok()?;
+                let header = Security2::header(&frame, self.id, SIM_HOME_ID).ok()?;
+
+                let state = self.s2.as_mut()?;
+                if let Some(entropy) = header.entropy {
+                    state.span = Some(Span::new(&entropy, &state.entropy, &state.keys));
+                }
+                let nonce = state.span.as_mut()?.next_nonce();
+                let command = Security2::decap(&frame, &header, &state.keys, nonce).ok()?;
+
+                let mut inner = vec![command.command_class_id(), command.command];
+                inner.extend_from_slice(&command.payload);
+                let answer = self.handle(&inner)?;
+                if answer.func != SerialMsgFunction::ApplicationCommandHandler
+                    || answer.data.len() < 5
+                {
+                    return Some(answer);
+                }
+
+                let state = self.s2.as_mut()?;
+                state.sequence = state.sequence.wrapping_add(1);
+                let header = S2Header {
+                    source: self.id,
+                    destination: SIM_CONTROLLER_ID,
+                    home_id: SIM_HOME_ID,
+                    sequence: state.sequence,
+                    entropy: None,
+                };
+                let message = Message::new(
+                    SIM_CONTROLLER_ID,
+                    CommandClass::try_from(answer.data[3]).ok()?,
+                    answer.data[4],
+                    &answer.data[5..],
+                )
+                .ok()?;
+                let nonce = state.span.as_mut()?.next_nonce();
+                let encrypted = Security2::encap(message, &header, &state.keys, nonce).ok()?;
+                Some(self.command(security, 0x03, &encrypted.data))
+            }
+            _ => None,
+        }
+    }
+
+    /// Create an application command, which was sent by the node
+    fn command(&self, cmd_class: u8, cmd: u8, payload: &[u8]) -> SerialMsg {
+        let mut data = vec![0x00, self.id, (payload.len() + 2) as u8, cmd_class, cmd];
+        data.extend_from_slice(payload);
+
+        SerialMsg::new(
+            SerialMsgType::Request,
+            SerialMsgFunction::ApplicationCommandHandler,
+            data,
+        )
+    }
+
+    /// Return the protocol information, which the controller knows about the node
+    fn protocol_info(&self) -> Result<NodeProtocolInfo, Error> {
+        // routing, 40 kbit/s and version 4, the listening flag on top
+        let capability = if self.listening { 0xD3 } else { 0x53 };
+
+        NodeProtocolInfo::parse(&[
+            capability,
+            0x1C,
+            0x01,
+            0x04,
+            self.generic as u8,
+            self.specific,
+        ])
+    }
+}
+
+/// The state of the simulated network
+#[derive(Debug)]
+struct Network {
+    home_id: u32,
+    node_id: u8,
+    // the flags of the controller capabilities
+    capabilities: u8,
+    nodes: BTreeMap<u8, SimNode>,
+    message_id: u8,
+    // the answers of the nodes and the time they arrive
+    messages: Vec<(Instant, SerialMsg)>,
+    reports: Vec<TransmitReport>,
+    activity: Vec<NodeActivity>,
+    routes: HashMap<u8, Route>,
+    // the destinations of the return routes of each node
+    return_routes: HashMap<u8, Vec<u8>>,
+    // the frames the nodes received
+    received: Vec<(u8, Vec<u8>)>,
+    // the node which joins at the next inclusion and the one which joined
+    // but isn't added until the inclusion is stopped
+    joining: Option<SimNode>,
+    included: Option<SimNode>,
+    // the network, which the controller joins at the next learn mode
+    learning: Option<(u32, u8, Vec<SimNode>)>,
+}
+
+impl Network {
+    /// Return the next message id
+    fn next_message_id(&mut self) -> u8 {
+        self.message_id = match self.message_id.wrapping_add(1) {
+            0 => 1,
+            id => id,
+        };
+        self.message_id
+    }
+
+    /// Check if the node is part of the network and receives frames
+    fn reachable(&self, id: u8) -> bool {
+        self.nodes
+            .get(&id)
+            .map(|n| (n.listening || n.awake) && n.failure != Some(SimFailure::NoAck))
+            .unwrap_or(false)
+    }
+
+    /// Hand the frame to the node and return if the node acknowledged it
+    ///
+    /// `command class, command, payload`
+    fn deliver(&mut self, id: u8, frame: &[u8]) -> Option<Duration> {
+        let node = self.nodes.get_mut(&id)?;
+        if !node.acknowledges() {
+            return None;
+        }
+
+        let latency = node.latency;
+        let answer = node.handle(frame);
+
+        self.received.push((id, frame.to_vec()));
+        if let Some(answer) = answer {
+            if answer.func == SerialMsgFunction::ApplicationCommandHandler {
+                self.activity.push(NodeActivity::Received(id));
+            }
+            self.messages.push((Instant::now() + latency, answer));
+        }
+
+        Some(latency)
+    }
+
+    /// Store the transmit report of a message
+    fn report(&mut self, message_id: u8, status: TransmitStatus, time: Option<Duration>) {
+        if self.reports.len() >= MAX_REPORTS {
+            self.reports.remove(0);
+        }
+
+        self.reports.push(TransmitReport {
+            message_id,
+            status,
+            time,
+            repeaters: vec![],
+            speed: time.map(|_| 40_000),
+        });
+    }
+}
+
+/// A virtual network, which is used as the driver of a controller.
+///
+/// The clones share the network, so it can be changed while the controller
+/// owns one of them.
+#[derive(Debug, Clone)]
+pub struct SimNetwork {
+    network: Arc<Mutex<Network>>,
+}
+
+impl SimNetwork {
+    /// Create a network, which only contains the controller.
+    pub fn new() -> SimNetwork {
+        SimNetwork {
+            network: Arc::new(Mutex::new(Network {
+                home_id: SIM_HOME_ID,
+                node_id: SIM_CONTROLLER_ID,
+                capabilities: 0x1C,
+                nodes: BTreeMap::new(),
+                message_id: 0,
+                messages: vec![],
+                reports: vec![],
+                activity: vec![],
+                routes: HashMap::new(),
+                return_routes: HashMap::new(),
+                received: vec![],
+                joining: None,
+                included: None,
+                learning: None,
+            })),
+        }
+    }
+
+    /// Include the node, a node with the same id is replaced.
+    pub fn add_node(&self, node: SimNode) {
+        self.network.lock().unwrap().nodes.insert(node.id, node);
+    }
+
+    /// Let the node join the network at the next inclusion, like a node at
+    /// which the inclusion button is pressed.
+    pub fn join(&self, node: SimNode) {
+        self.network.lock().unwrap().joining = Some(node);
+    }
+
+    /// Let the controller join another network at the next learn mode. The
+    /// controller gets the node id and the nodes replace the current ones.
+    pub fn join_network(&self, home_id: u32, node_id: u8, nodes: Vec<SimNode>) {
+        self.network.lock().unwrap().learning = Some((home_id, node_id, nodes));
+    }
+
+    /// Exclude the node from the network.
+    pub fn remove_node(&self, id: u8) -> Option<SimNode> {
+        let mut network = self.network.lock().unwrap();
+        network.routes.remove(&id);
+        network.nodes.remove(&id)
+    }
+
+    /// Change the way the node fails, `None` lets it work again.
+    pub fn set_failure(&self, id: u8, failure: Option<SimFailure>) {
+        if let Some(node) = self.network.lock().unwrap().nodes.get_mut(&id) {
+            node.failure = failure;
+        }
+    }
+
+    /// Let the node send a Wake Up Notification. It stays awake, until it
+    /// receives the Wake Up No More Information command or `sleep` is called.
+    pub fn wake_up(&self, id: u8) {
+        let mut network = self.network.lock().unwrap();
+        if let Some(node) = network.nodes.get_mut(&id) {
+            node.awake = true;
+            network.activity.push(NodeActivity::WakeUp(id));
+        }
+    }
+
+    /// Send the node back to sleep.
+    pub fn sleep(&self, id: u8) {
+        if let Some(node) = self.network.lock().unwrap().nodes.get_mut(&id) {
+            node.awake = false;
+        }
+    }
+
+    /// Let the node send a command on its own, like a report of a changed value.
+    pub fn report(&self, id: u8, cmd_class: CommandClass, cmd: u8, payload: &[u8]) {
+        let mut network = self.network.lock().unwrap();
+        let msg = match network.nodes.get(&id) {
+            Some(node) => node.command(cmd_class as u8, cmd, payload),
+            None => return,
+        };
+
+        network.activity.push(NodeActivity::Received(id));
+        network.messages.push((Instant::now(), msg));
+    }
+
+    /// Returns the current value of the node for the command class.
+    pub fn value(&self, id: u8, cmd_class: CommandClass) -> Option<Vec<u8>> {
+        self.network
+            .lock()
+            .unwrap()
+            .nodes
+            .get(&id)
+            .and_then(|n| n.values.get(&(cmd_class as u8)).cloned())
+    }
+
+    /// Returns the destinations of the return routes, which the node got.
+    pub fn return_routes(&self, id: u8) -> Vec<u8> {
+        self.network
+            .lock()
+            .unwrap()
+            .return_routes
+            .get(&id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the frames the node received and acknowledged, oldest first.
+    ///
+    /// `command class, command, payload`
+    pub fn received(&self, id: u8) -> Vec<Vec<u8>> {
+        self.network
+            .lock()
+            .unwrap()
+            .received
+            .iter()
+            .filter(|r| r.0 == id)
+            .map(|r| r.1.clone())
+            .collect()
+    }
+
+    #[cfg(feature = "cc-security")]
+    /// Return the S0 network key, which the node got.
+    pub fn network_key(&self, id: u8) -> Option<[u8; BLOCK_SIZE]> {
+        let network = self.network.lock().unwrap();
+        network.nodes.get(&id)?.security.as_ref().map(|s| s.key)
+    }
+
+    /// Take the first answer, which matches the filter. Waits until the
+    /// answer arrived, when the node didn't send it yet.
+    fn take_message<F>(&self, filter: F) -> Result<SerialMsg, Error>
+    where
+        F: Fn(&SerialMsg) -> bool,
+    {
+        let due = self
+            .network
+            .lock()
+            .unwrap()
+            .messages
+            .iter()
+            .filter(|m| filter(&m.1))
+            .map(|m| m.0)
+            .min();
+
+        let due = due.ok_or_else(|| {
+            Error::new(
+                ErrorKind::Io(StdErrorKind::Other),
+                "No message with the given id received",
+            )
+        })?;
+
+        // the network stays accessible, while the answer is on its way
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+
+        let mut network = self.network.lock().unwrap();
+        let pos = network
+            .messages
+            .iter()
+            .position(|m| m.0 == due && filter(&m.1))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Io(StdErrorKind::Other),
+                    "The message was removed from the network",
+                )
+            })?;
+
+        Ok(network.messages.remove(pos).1)
+    }
+
+    /// Return the node or an error, when it isn't part of the network
+    fn with_node<T, F>(&self, id: u8, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&SimNode) -> Result<T, Error>,
+    {
+        match self.network.lock().unwrap().nodes.get(&id) {
+            Some(node) => f(node),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The node {} is not part of the network", id),
+            )),
+        }
+    }
+}
+
+impl Default for SimNetwork {
+    fn default() -> SimNetwork {
+        SimNetwork::new()
+    }
+}
+
+impl Driver for SimNetwork {
+    fn write<M>(&mut self, message: M) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>,
+    {
+        self.write_with_options(message, TransmitOptions::default())
+    }
+
+    fn write_with_options<M>(&mut self, message: M, _options: TransmitOptions) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>,
+    {
+        let message = message.into();
+        if message.len() < 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The message is too short",
+            ));
+        }
+
+        // `node, length, command class, command, payload`
+        let id = message[0];
+        let end = ::std::cmp::min(message.len(), 2 + message[1] as usize);
+
+        let mut network = self.network.lock().unwrap();
+        let m_id = network.next_message_id();
+
+        let status = match network.deliver(id, &message[2..end]) {
+            Some(latency) => {
+                network.report(m_id, TransmitStatus::CompleteOk, Some(latency));
+                network.routes.insert(
+                    id,
+                    Route {
+                        repeaters: vec![],
+                        speed: 40_000,
+                    },
+                );
+                TransmitStatus::CompleteOk
+            }
+            None => {
+                network.report(m_id, TransmitStatus::CompleteNoAck, None);
+                TransmitStatus::CompleteNoAck
+            }
+        };
+        network.activity.push(NodeActivity::Transmit(id, status));
+
+        Ok(m_id)
+    }
+
+    fn write_multi<M>(&mut self, node_ids: &[u8], message: M) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>,
+    {
+        let message = message.into();
+        if message.len() < 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The message is too short",
+            ));
+        }
+
+        let end = ::std::cmp::min(message.len(), 2 + message[1] as usize);
+
+        let mut network = self.network.lock().unwrap();
+        let m_id = network.next_message_id();
+
+        // the report only tells, if all nodes received the message
+        let mut status = TransmitStatus::CompleteOk;
+        for &id in node_ids {
+            if network.deliver(id, &message[2..end]).is_none() {
+                status = TransmitStatus::CompleteNoAck;
+            }
+        }
+        network.report(m_id, status, None);
+
+        Ok(m_id)
+    }
+
+    fn read(&mut self) -> Result<SerialMsg, Error> {
+        self.take_message(|_| true)
+    }
+
+    fn read_frame(&mut self) -> Result<IncomingFrame, Error> {
+        let msg = self.take_message(|m| m.func == SerialMsgFunction::ApplicationCommandHandler)?;
+        IncomingFrame::parse(&msg.data)
+    }
+
+    fn unread(&mut self, messages: Vec<SerialMsg>) {
+        let mut network = self.network.lock().unwrap();
+
+        // the messages are due before all others
+        let now = Instant::now();
+        let due = network
+            .messages
+            .iter()
+            .map(|m| m.0)
+            .min()
+            .map_or(now, |d| d.min(now));
+        network
+            .messages
+            .splice(0..0, messages.into_iter().map(|m| (due, m)));
+    }
+
+    fn get_controller_id(&mut self) -> Result<(u32, u8), Error> {
+        let network = self.network.lock().unwrap();
+        Ok((network.home_id, network.node_id))
+    }
+
+    fn get_controller_info(&mut self) -> Result<ControllerInfo, Error> {
+        let (home_id, node_id) = self.get_controller_id()?;
+        let capabilities = self.network.lock().unwrap().capabilities;
+
+        Ok(ControllerInfo {
+            home_id,
+            node_id,
+            version: "Z-Wave 6.07".into(),
+            library: LibraryType::StaticController,
+            // the simulated controller supports every function
+            api: SerialApiCapabilities {
+                app_version: 1,
+                app_revision: 0,
+                manufacturer_id: 0,
+                product_type: 0,
+                product_id: 0,
+                functions: (1..=0xFF).collect(),
+            },
+            capabilities: ControllerCapabilities::parse(capabilities),
+        })
+    }
+
+    fn wait_transmit(&mut self, message_id: u8) -> Result<TransmitReport, Error> {
+        let mut network = self.network.lock().unwrap();
+
+        match network
+            .reports
+            .iter()
+            .position(|r| r.message_id == message_id)
+        {
+            Some(pos) => Ok(network.reports.remove(pos)),
+            None => Err(Error::Timeout {
+                stage: Stage::Callback,
+            }),
+        }
+    }
+
+    fn take_activity(&mut self) -> Result<Vec<NodeActivity>, Error> {
+        Ok(self.network.lock().unwrap().activity.drain(..).collect())
+    }
+
+    fn is_failed_node<N>(&mut self, node_id: N) -> Result<bool, Error>
+    where
+        N: Into<u8>,
+    {
+        self.with_node(node_id.into(), |n| Ok(n.failure == Some(SimFailure::NoAck)))
+    }
+
+    fn get_node_ids(&mut self) -> Result<Vec<u8>, Error> {
+        Ok(self.network.lock().unwrap().nodes.keys().cloned().collect())
+    }
+
+    fn get_neighbors<N>(&mut self, node_id: N) -> Result<Vec<u8>, Error>
+    where
+        N: Into<u8>,
+    {
+        let node_id = node_id.into();
+
+        // all listening nodes are in range of each other
+        let mut neighbors = vec![];
+        if node_id != SIM_CONTROLLER_ID {
+            neighbors.push(SIM_CONTROLLER_ID);
+        }
+        neighbors.extend(
+            self.network
+                .lock()
+                .unwrap()
+                .nodes
+                .values()
+                .filter(|n| n.listening && n.id != node_id)
+                .map(|n| n.id),
+        );
+
+        Ok(neighbors)
+    }
+
+    fn get_last_working_route<N>(&mut self, node_id: N) -> Result<Option<Route>, Error>
+    where
+        N: Into<u8>,
+    {
+        Ok(self
+            .network
+            .lock()
+            .unwrap()
+            .routes
+            .get(&node_id.into())
+            .cloned())
+    }
+
+    fn clear_route<N>(&mut self, node_id: N) -> Result<(), Error>
+    where
+        N: Into<u8>,
+    {
+        self.network.lock().unwrap().routes.remove(&node_id.into());
+        Ok(())
+    }
+
+    fn soft_reset(&mut self) -> Result<(), Error> {
+        // the answers on their way are lost
+        self.network.lock().unwrap().messages.clear();
+        Ok(())
+    }
+
+    fn start_function(&mut self, function: SerialMsgFunction, data: &[u8]) -> Result<u8, Error> {
+        let mut network = self.network.lock().unwrap();
+        let callback_id = network.next_message_id();
+
+        // the controller change and the new primary include a controller
+        let adding_slave = function == SerialMsgFunction::AddNodeToNetwork;
+        let inclusion = adding_slave
+            || function == SerialMsgFunction::ControllerChange
+            || function == SerialMsgFunction::CreateNewPrimary;
+
+        // the status, node id and length of each callback
+        let mut callbacks = vec![];
+        match (function, data.first()) {
+            // stopping the inclusion adds the included node
+            (_, Some(0x05)) if inclusion => {
+                let node_id = match network.included.take() {
+                    Some(node) => {
+                        let id = node.id;
+                        network.nodes.insert(id, node);
+                        id
+                    }
+                    None => 0x00,
+                };
+
+                // the controller handed over its primary role
+                if function == SerialMsgFunction::ControllerChange && node_id != 0x00 {
+                    network.capabilities = 0x01;
+                }
+                callbacks.push(vec![0x06, node_id, 0x00]);
+            }
+            // stopping a failed inclusion has no callback
+            (_, Some(0x06)) if inclusion => network.included = None,
+            (_, Some(_)) if inclusion => {
+                callbacks.push(vec![0x01, 0x00, 0x00]);
+
+                if let Some(node) = network.joining.take() {
+                    // a routing slave or a static controller with its node information
+                    let (status, basic) = if adding_slave {
+                        (0x03, 0x04)
+                    } else {
+                        (0x04, 0x02)
+                    };
+                    let mut nif = vec![basic, node.generic as u8, node.specific];
+                    nif.extend(node.cmds.iter().map(|c| *c as u8));
+
+                    let mut adding = vec![status, node.id, nif.len() as u8];
+                    adding.extend(nif);
+
+                    callbacks.push(vec![0x02, 0x00, 0x00]);
+                    callbacks.push(adding);
+                    callbacks.push(vec![0x05, node.id, 0x00]);
+                    network.included = Some(node);
+                }
+            }
+            // only the reachable nodes find their neighbors
+            (SerialMsgFunction::RequestNodeNeighborUpdate, Some(&id)) => {
+                callbacks.push(vec![0x21]);
+                callbacks.push(vec![if network.reachable(id) { 0x22 } else { 0x23 }]);
+            }
+            // the return routes are changed, when the node acknowledges them
+            (SerialMsgFunction::AssignReturnRoute, Some(&id)) if data.len() >= 2 => {
+                if network.reachable(id) {
+                    let routes = network.return_routes.entry(id).or_default();
+                    if !routes.contains(&data[1]) {
+                        routes.push(data[1]);
+                    }
+                    callbacks.push(vec![TransmitStatus::CompleteOk as u8]);
+                } else {
+                    callbacks.push(vec![TransmitStatus::CompleteNoAck as u8]);
+                }
+            }
+            (SerialMsgFunction::DeleteReturnRoute, Some(&id)) => {
+                if network.reachable(id) {
+                    network.return_routes.remove(&id);
+                    callbacks.push(vec![TransmitStatus::CompleteOk as u8]);
+                } else {
+                    callbacks.push(vec![TransmitStatus::CompleteNoAck as u8]);
+                }
+            }
+            // disabling the learn mode has no callback
+            (SerialMsgFunction::SetLearnMode, Some(0x00)) => {}
+            (SerialMsgFunction::SetLearnMode, Some(_)) => {
+                callbacks.push(vec![0x01, 0x00, 0x00]);
+
+                if let Some((home_id, node_id, nodes)) = network.learning.take() {
+                    network.home_id = home_id;
+                    network.node_id = node_id;
+                    network.nodes = nodes.into_iter().map(|n| (n.id, n)).collect();
+                    network.routes.clear();
+                    callbacks.push(vec![0x06, node_id, 0x00]);
+                }
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotImplemented,
+                    format!("The simulated controller doesn't support {:?}", function),
+                ))
+            }
+        }
+
+        let now = Instant::now();
+        for callback in callbacks {
+            let mut data = vec![callback_id];
+            data.extend(callback);
+            network
+                .messages
+                .push((now, SerialMsg::new(SerialMsgType::Request, function, data)));
+        }
+
+        Ok(callback_id)
+    }
+
+    fn read_memory(&mut self, _offset: u16, length: u8) -> Result<Vec<u8>, Error> {
+        Ok(vec![0; length as usize])
+    }
+
+    fn update_firmware<F>(&mut self, _image: &[u8], _progress: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize),
+    {
+        Err(Error::new(
+            ErrorKind::NotImplemented,
+            "The simulated controller has no firmware",
+        ))
+    }
+
+    fn get_node_generic_class<N>(&mut self, node_id: N) -> Result<GenericType, Error>
+    where
+        N: Into<u8>,
+    {
+        self.with_node(node_id.into(), |n| Ok(n.generic))
+    }
+
+    fn get_node_protocol_info<N>(&mut self, node_id: N) -> Result<NodeProtocolInfo, Error>
+    where
+        N: Into<u8>,
+    {
+        self.with_node(node_id.into(), |n| n.protocol_info())
+    }
+}
+
+/// The setup, which the tests of the controller share
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::{SimNetwork, SimNode};
+    use crate::basic::Controller;
+    use crate::cmds::CommandClass;
+    use crate::defs::GenericType;
+
+    use std::time::Duration;
+
+    /// A binary switch, which answers after a short delay
+    pub fn switch(id: u8) -> SimNode {
+        SimNode::new(id, GenericType::BinarySwitch)
+            .command_classes(&[CommandClass::SWITCH_BINARY])
+            .latency(Duration::from_millis(10))
+    }
+
+    /// Create a network with the nodes and a controller, which discovered
+    /// and interviewed them
+    pub fn network(nodes: Vec<SimNode>) -> (SimNetwork, Controller<SimNetwork>) {
+        let sim = SimNetwork::new();
+        for node in nodes {
+            sim.add_node(node);
+        }
+
+        let controller = Controller::new(sim.clone()).unwrap();
+        (sim, controller)
+    }
+}
@@ -0,0 +1,204 @@
+//! Network state export and import
+//!
+//! Writes everything the controller knows about the network into a single
+//! JSON document, to back it up or to hand it to an external UI. The
+//! document carries a version, so older documents can still be read when
+//! the format changes.
+
+use super::{
+    AssociationGroup, Associations, CachedValue, Controller, NodeOverrides, NodeState,
+    NodeStatistics, Scene, Scenes,
+};
+use crate::defs::{CommandClass, GenericType, NodeProtocolInfo, SecurityClass};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind};
+
+/// The version of the document, which is written by `export_state`
+pub const STATE_VERSION: u32 = 1;
+
+/// The state of a single node.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NodeExport {
+    pub id: u8,
+    pub name: Option<String>,
+    pub types: Vec<GenericType>,
+    pub commands: Vec<CommandClass>,
+    pub protocol_info: Option<NodeProtocolInfo>,
+    pub security_class: SecurityClass,
+    pub state: NodeState,
+    pub statistics: NodeStatistics,
+    /// The last known values of the command classes
+    #[serde(default)]
+    pub values: Vec<(CommandClass, CachedValue)>,
+    #[serde(default)]
+    pub overrides: NodeOverrides,
+}
+
+/// The state of the whole network.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NetworkState {
+    pub version: u32,
+    pub home_id: u32,
+    pub controller: u8,
+    pub nodes: Vec<NodeExport>,
+    pub associations: Vec<AssociationGroup>,
+    /// The scenes with the values of their nodes
+    pub scenes: Vec<Scene>,
+}
+
+impl<D> Controller<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Return the state of the network as a JSON document.
+    pub fn export_state(&self) -> Result<String, Error> {
+        let nodes = self
+            .nodes
+            .borrow()
+            .iter()
+            .map(|n| NodeExport {
+                id: n.id,
+                name: n.name.clone(),
+                types: n.types.clone(),
+                commands: n.cmds.clone(),
+                protocol_info: n.protocol_info,
+                security_class: n.security_class,
+                state: self.node_state(n.id),
+                statistics: n.statistics(),
+                values: self.values.lock().unwrap().node(n.id),
+                overrides: n.overrides,
+            })
+            .collect();
+
+        let scenes = self.scenes.borrow();
+        let state = NetworkState {
+            version: STATE_VERSION,
+            home_id: self.info.home_id,
+            controller: self.info.node_id,
+            nodes,
+            associations: self.associations.borrow().groups().to_vec(),
+            scenes: scenes
+                .names()
+                .iter()
+                .filter_map(|name| scenes.get(name).cloned())
+                .collect(),
+        };
+
+        serde_json::to_string_pretty(&state).map_err(|e| Error::Protocol(e.to_string()))
+    }
+
+    /// Restore the names, security classes, overrides, statistics, values,
+    /// associations and scenes of a JSON document, which was written by `export_state`.
+    ///
+    /// The protocol information of the nodes is kept, so it's not requested
+    /// again. Import the document into a lazy controller before the nodes
+    /// are interviewed, to skip these requests at the start.
+    ///
+    /// The document needs to belong to the same network. Nodes which are not
+    /// part of the network anymore are skipped. Nothing is changed, when the
+    /// document is invalid.
+    pub fn import_state(&self, json: &str) -> Result<(), Error> {
+        let state: NetworkState = serde_json::from_str(json)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+
+        if state.version > STATE_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The state version {} is not supported", state.version),
+            ));
+        }
+
+        if state.home_id != self.info.home_id {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The state belongs to the network {:08X}, not to {:08X}",
+                    state.home_id, self.info.home_id
+                ),
+            ));
+        }
+
+        // check all scenes, before anything gets changed
+        let mut checked = Scenes::new();
+        for scene in &state.scenes {
+            checked.define(scene.name.clone(), scene.targets.clone())?;
+        }
+
+        let mut nodes = self.nodes.borrow_mut();
+        for export in state.nodes {
+            let node = match nodes.iter_mut().find(|n| n.id == export.id) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            node.name = export.name;
+            node.security_class = export.security_class;
+            node.overrides = export.overrides;
+            if let Some(info) = export.protocol_info {
+                node.protocol_info = Some(info);
+                self.protocol_infos.lock().unwrap().insert(export.id, info);
+            }
+            self.statistics
+                .lock()
+                .unwrap()
+                .set(export.id, export.statistics);
+
+            let mut values = self.values.lock().unwrap();
+            for (cmd_class, value) in export.values {
+                values.set(export.id, 0, cmd_class, value);
+            }
+        }
+
+        let mut associations = Associations::new();
+        for group in state.associations {
+            associations.update(group);
+        }
+        *self.associations.borrow_mut() = associations;
+
+        // the scene store keeps its listeners
+        let mut scenes = self.scenes.borrow_mut();
+        for name in scenes.names() {
+            scenes.remove(&name);
+        }
+        for scene in state.scenes {
+            scenes.define(scene.name, scene.targets)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document() {
+        let state = NetworkState {
+            version: STATE_VERSION,
+            home_id: 0xC0FFEE00,
+            controller: 1,
+            nodes: vec![NodeExport {
+                id: 5,
+                name: Some("Kitchen".into()),
+                types: vec![GenericType::BinarySwitch],
+                commands: vec![CommandClass::SWITCH_BINARY],
+                protocol_info: None,
+                security_class: SecurityClass::None,
+                state: NodeState::Alive,
+                statistics: NodeStatistics::default(),
+                values: vec![],
+                overrides: NodeOverrides::new(),
+            }],
+            associations: vec![],
+            scenes: vec![Scene {
+                name: "evening".into(),
+                targets: vec![(5, 0xFF)],
+            }],
+        };
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.starts_with(r#"{"version":1,"#));
+        assert_eq!(serde_json::from_str::<NetworkState>(&json).unwrap(), state);
+    }
+}
@@ -0,0 +1,185 @@
+//! Node state tracking
+//!
+//! Tracks if the nodes are reachable, based on the transmit reports of
+//! the sent messages and the messages received from the nodes.
+
+use crate::defs::{NodeActivity, TransmitStatus};
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Amount of failed transmissions in a row, after which a listening
+/// node is considered dead.
+const MAX_FAILURES: u8 = 3;
+
+/// The reachability of a node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeState {
+    /// Nothing was sent to or received from the node yet
+    Unknown,
+    Alive,
+    /// A sleeping node which can't be reached until it wakes up
+    Asleep,
+    /// A listening node which didn't answer multiple times
+    Dead,
+}
+
+type Listener = Box<dyn Fn(u8, NodeState, NodeState)>;
+
+/// Stores the state of each node and the listeners which get notified
+/// when the state of a node changes.
+#[derive(Default)]
+pub struct NodeStates {
+    states: HashMap<u8, (NodeState, u8)>,
+    listeners: Vec<Listener>,
+}
+
+impl NodeStates {
+    /// Create a new state store, where all nodes are unknown
+    pub fn new() -> NodeStates {
+        NodeStates::default()
+    }
+
+    /// Return the state of the node
+    pub fn state(&self, node: u8) -> NodeState {
+        self.states
+            .get(&node)
+            .map(|s| s.0)
+            .unwrap_or(NodeState::Unknown)
+    }
+
+    /// Return the ids of all nodes with the given state
+    pub fn nodes(&self, state: NodeState) -> Vec<u8> {
+        let mut nodes: Vec<u8> = self
+            .states
+            .iter()
+            .filter(|(_, s)| s.0 == state)
+            .map(|(n, _)| *n)
+            .collect();
+        nodes.sort();
+        nodes
+    }
+
+    /// Register a function which is called with the node id, the old
+    /// and the new state, when the state of a node changes.
+    pub fn on_change<F>(&mut self, listener: F)
+    where
+        F: Fn(u8, NodeState, NodeState) + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Inform the listeners about the change of a node. It's called apart
+    /// from the update, so the listeners can read the states.
+    pub fn notify(&self, node: u8, old: NodeState, new: NodeState) {
+        for listener in &self.listeners {
+            listener(node, old, new);
+        }
+    }
+
+    /// Update the state of a node with its activity.
+    ///
+    /// Sleeping nodes which don't answer are asleep, listening nodes
+    /// are dead after multiple failed transmissions. Returns the new
+    /// state, when the state changed. The listeners are not notified.
+    pub fn update(&mut self, activity: &NodeActivity, sleeping: bool) -> Option<NodeState> {
+        let (node, new) = match *activity {
+            NodeActivity::Received(node) | NodeActivity::WakeUp(node) => (node, NodeState::Alive),
+            NodeActivity::Transmit(node, TransmitStatus::CompleteOk) => (node, NodeState::Alive),
+            // the controller was busy, this says nothing about the node
+            NodeActivity::Transmit(_, TransmitStatus::RoutingNotIdle) => return None,
+            NodeActivity::Transmit(node, _) => {
+                let (old, failures) = self
+                    .states
+                    .get(&node)
+                    .cloned()
+                    .unwrap_or((NodeState::Unknown, 0));
+                let failures = failures.saturating_add(1);
+
+                let new = if sleeping {
+                    NodeState::Asleep
+                } else if failures >= MAX_FAILURES {
+                    NodeState::Dead
+                } else {
+                    old
+                };
+
+                return self.set(node, new, failures);
+            }
+        };
+
+        self.set(node, new, 0)
+    }
+
+    /// Store the state and return it, when it changed
+    fn set(&mut self, node: u8, state: NodeState, failures: u8) -> Option<NodeState> {
+        let old = self.state(node);
+        self.states.insert(node, (state, failures));
+
+        if old == state {
+            return None;
+        }
+
+        Some(state)
+    }
+}
+
+impl fmt::Debug for NodeStates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NodeStates {{states: {:?}}}", self.states)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn listening_node_dies() {
+        let mut states = NodeStates::new();
+        let changes = Rc::new(RefCell::new(vec![]));
+        let c = changes.clone();
+        states.on_change(move |n, old, new| c.borrow_mut().push((n, old, new)));
+
+        let failed = NodeActivity::Transmit(2, TransmitStatus::CompleteNoAck);
+        assert_eq!(states.update(&failed, false), None);
+        assert_eq!(states.update(&failed, false), None);
+        assert_eq!(states.update(&failed, false), Some(NodeState::Dead));
+        assert_eq!(states.nodes(NodeState::Dead), vec![2]);
+
+        // a received message revives the node
+        assert_eq!(
+            states.update(&NodeActivity::Received(2), false),
+            Some(NodeState::Alive)
+        );
+
+        // the listeners are notified apart from the update
+        assert!(changes.borrow().is_empty());
+        states.notify(2, NodeState::Dead, NodeState::Alive);
+        assert_eq!(
+            *changes.borrow(),
+            vec![(2, NodeState::Dead, NodeState::Alive)]
+        );
+    }
+
+    #[test]
+    fn sleeping_node_sleeps() {
+        let mut states = NodeStates::new();
+
+        let failed = NodeActivity::Transmit(3, TransmitStatus::CompleteFail);
+        assert_eq!(states.update(&failed, true), Some(NodeState::Asleep));
+        assert_eq!(
+            states.update(&NodeActivity::WakeUp(3), true),
+            Some(NodeState::Alive)
+        );
+
+        // a busy controller doesn't change anything
+        let busy = NodeActivity::Transmit(3, TransmitStatus::RoutingNotIdle);
+        assert_eq!(states.update(&busy, true), None);
+        assert_eq!(states.state(3), NodeState::Alive);
+    }
+}
@@ -0,0 +1,124 @@
+//! Per-node statistics
+//!
+//! Counts the commands sent to each node, how many failed and needed retries,
+//! and how long the node took to answer. This shows which nodes have a bad
+//! connection, long before they are considered dead.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// The statistics of a single node.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeStatistics {
+    /// Amount of commands sent to the node
+    pub sent: u32,
+    /// Amount of commands which failed, after all retries
+    pub failures: u32,
+    /// Amount of additional attempts to send the commands
+    pub retries: u32,
+    /// The average round-trip time of the successful commands
+    pub average_rtt: Option<Duration>,
+    /// The round-trip time of the last successful command
+    pub last_rtt: Option<Duration>,
+    /// The time a message was received from the node for the last time
+    pub last_seen: Option<SystemTime>,
+    /// The time the node woke up for the last time
+    pub last_wake_up: Option<SystemTime>,
+}
+
+impl NodeStatistics {
+    /// Record a sent command, with the amount of attempts and the round-trip
+    /// time of the last attempt, when it succeeded.
+    pub fn record(&mut self, attempts: u32, rtt: Option<Duration>) {
+        self.sent = self.sent.saturating_add(1);
+        self.retries = self.retries.saturating_add(attempts.saturating_sub(1));
+
+        let rtt = match rtt {
+            Some(rtt) => rtt,
+            None => {
+                self.failures = self.failures.saturating_add(1);
+                return;
+            }
+        };
+
+        // the running average over all successful commands
+        let successes = self.sent - self.failures;
+        self.average_rtt = Some(match self.average_rtt {
+            Some(avg) => avg * (successes - 1) / successes + rtt / successes,
+            None => rtt,
+        });
+        self.last_rtt = Some(rtt);
+    }
+
+    /// Record that a message was received from the node
+    pub fn seen(&mut self) {
+        self.last_seen = Some(SystemTime::now());
+    }
+
+    /// Record that the node woke up
+    pub fn woke_up(&mut self) {
+        let now = SystemTime::now();
+        self.last_seen = Some(now);
+        self.last_wake_up = Some(now);
+    }
+}
+
+/// Stores the statistics of all nodes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    nodes: HashMap<u8, NodeStatistics>,
+}
+
+impl Statistics {
+    /// Create a store without any statistics
+    pub fn new() -> Statistics {
+        Statistics::default()
+    }
+
+    /// Return the statistics of the node
+    pub fn node(&self, node: u8) -> NodeStatistics {
+        self.nodes.get(&node).cloned().unwrap_or_default()
+    }
+
+    /// Return the statistics of the node to update them
+    pub fn node_mut(&mut self, node: u8) -> &mut NodeStatistics {
+        self.nodes.entry(node).or_default()
+    }
+
+    /// Replace the statistics of the node
+    pub fn set(&mut self, node: u8, statistics: NodeStatistics) {
+        self.nodes.insert(node, statistics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let mut stats = Statistics::new();
+        assert_eq!(stats.node(4), NodeStatistics::default());
+
+        stats
+            .node_mut(4)
+            .record(1, Some(Duration::from_millis(100)));
+        stats
+            .node_mut(4)
+            .record(3, Some(Duration::from_millis(300)));
+        stats.node_mut(4).record(3, None);
+
+        let node = stats.node(4);
+        assert_eq!(node.sent, 3);
+        assert_eq!(node.failures, 1);
+        assert_eq!(node.retries, 4);
+        assert_eq!(node.average_rtt, Some(Duration::from_millis(200)));
+        assert_eq!(node.last_rtt, Some(Duration::from_millis(300)));
+        assert_eq!(node.last_seen, None);
+
+        stats.node_mut(4).woke_up();
+        assert!(stats.node(4).last_wake_up.is_some());
+        assert_eq!(stats.node(4).last_seen, stats.node(4).last_wake_up);
+    }
+}
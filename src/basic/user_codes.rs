@@ -0,0 +1,236 @@
+//! User code management
+//!
+//! Manages the user code slots of a lock. The codes are only cached as
+//! hashes, to detect changes and to find the slot of a code, without
+//! keeping the codes in memory.
+
+use super::Node;
+use crate::cmds::user_code::{UserCode, UserCodeCapabilities, UserIdStatus};
+use crate::cmds::IncomingFrame;
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind};
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+
+/// One user code slot of a lock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserCodeSlot {
+    pub user_id: u8,
+    pub status: UserIdStatus,
+    hash: Option<u64>,
+}
+
+/// Manages the user codes of a lock and informs the listeners,
+/// when a code was changed at the lock.
+pub struct UserCodeManager<D>
+where
+    D: Driver,
+{
+    node: Node<D>,
+    state: RandomState,
+    capabilities: UserCodeCapabilities,
+    slots: Vec<UserCodeSlot>,
+    listeners: Vec<Box<dyn Fn(u8, UserIdStatus)>>,
+}
+
+impl<D> UserCodeManager<D>
+where
+    D: Driver + Send + 'static,
+{
+    /// Create a new manager for the lock, the slots are read with `refresh`.
+    pub fn new(node: Node<D>) -> UserCodeManager<D> {
+        UserCodeManager {
+            node,
+            state: RandomState::new(),
+            capabilities: UserCodeCapabilities::default(),
+            slots: vec![],
+            listeners: vec![],
+        }
+    }
+
+    /// Read the capabilities and all slots from the lock.
+    pub fn refresh(&mut self) -> Result<(), Error> {
+        // version 1 devices don't know the capabilities
+        self.capabilities = self
+            .node
+            .request(UserCode::capabilities_get(self.node.get_id()))
+            .and_then(|f| UserCode::capabilities_report(&f))
+            .unwrap_or_default();
+
+        let count = UserCode::users_number_report(
+            &self
+                .node
+                .request(UserCode::users_number_get(self.node.get_id()))?,
+        )?;
+
+        self.slots.clear();
+        for user_id in 1..=count {
+            let report = UserCode::report(
+                &self
+                    .node
+                    .request(UserCode::get(self.node.get_id(), user_id))?,
+            )?;
+            let hash = self.hash(report.status, &report.code);
+
+            self.slots.push(UserCodeSlot {
+                user_id,
+                status: report.status,
+                hash,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Return all slots of the lock
+    pub fn slots(&self) -> &[UserCodeSlot] {
+        &self.slots
+    }
+
+    /// Return the keys and the code length the lock supports
+    pub fn capabilities(&self) -> &UserCodeCapabilities {
+        &self.capabilities
+    }
+
+    /// Return the slot which contains the code
+    pub fn find(&self, code: &str) -> Option<u8> {
+        let hash = self.hash(UserIdStatus::Occupied, code)?;
+
+        self.slots
+            .iter()
+            .find(|s| s.hash == Some(hash))
+            .map(|s| s.user_id)
+    }
+
+    /// Return the first available slot
+    pub fn free_slot(&self) -> Option<u8> {
+        self.slots
+            .iter()
+            .find(|s| s.status == UserIdStatus::Available)
+            .map(|s| s.user_id)
+    }
+
+    /// Store the code in the slot
+    pub fn add(&mut self, user_id: u8, code: &str) -> Result<(), Error> {
+        self.capabilities.validate(code)?;
+
+        if self.find(code).is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The user code is already stored",
+            ));
+        }
+
+        self.node.send(UserCode::set(
+            self.node.get_id(),
+            user_id,
+            UserIdStatus::Occupied,
+            code,
+        )?)?;
+
+        let hash = self.hash(UserIdStatus::Occupied, code);
+        self.update(user_id, UserIdStatus::Occupied, hash);
+
+        Ok(())
+    }
+
+    /// Store multiple codes, returns the slots which failed.
+    pub fn add_all(&mut self, codes: &[(u8, &str)]) -> Vec<u8> {
+        codes
+            .iter()
+            .filter(|(user_id, code)| self.add(*user_id, code).is_err())
+            .map(|(user_id, _)| *user_id)
+            .collect()
+    }
+
+    /// Clear the code of the slot
+    pub fn remove(&mut self, user_id: u8) -> Result<(), Error> {
+        self.node
+            .send(UserCode::clear(self.node.get_id(), user_id))?;
+        self.update(user_id, UserIdStatus::Available, None);
+
+        Ok(())
+    }
+
+    /// Clear multiple slots, returns the slots which failed.
+    pub fn remove_all(&mut self, user_ids: &[u8]) -> Vec<u8> {
+        user_ids
+            .iter()
+            .filter(|user_id| self.remove(**user_id).is_err())
+            .cloned()
+            .collect()
+    }
+
+    /// Register a function which is called with the slot and the new status,
+    /// when a code was changed at the lock.
+    pub fn on_change<F>(&mut self, listener: F)
+    where
+        F: Fn(u8, UserIdStatus) + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Handle an unsolicited User Code Report of the lock. When the code
+    /// differs from the cached one, the listeners are informed and the
+    /// slot is returned.
+    pub fn handle_report(&mut self, frame: &IncomingFrame) -> Result<Option<u8>, Error> {
+        let report = UserCode::report(frame)?;
+        let hash = self.hash(report.status, &report.code);
+
+        // check if the code changed
+        let changed = match self.slots.iter().find(|s| s.user_id == report.user_id) {
+            Some(slot) => slot.status != report.status || slot.hash != hash,
+            None => true,
+        };
+        if !changed {
+            return Ok(None);
+        }
+
+        self.update(report.user_id, report.status, hash);
+        for listener in &self.listeners {
+            listener(report.user_id, report.status);
+        }
+
+        Ok(Some(report.user_id))
+    }
+
+    /// Hash the code of an occupied slot
+    fn hash(&self, status: UserIdStatus, code: &str) -> Option<u64> {
+        if status != UserIdStatus::Occupied && status != UserIdStatus::Disabled {
+            return None;
+        }
+
+        Some(self.state.hash_one(code))
+    }
+
+    /// Update the cached slot
+    fn update(&mut self, user_id: u8, status: UserIdStatus, hash: Option<u64>) {
+        match self.slots.iter_mut().find(|s| s.user_id == user_id) {
+            Some(slot) => {
+                slot.status = status;
+                slot.hash = hash;
+            }
+            None => self.slots.push(UserCodeSlot {
+                user_id,
+                status,
+                hash,
+            }),
+        }
+    }
+}
+
+impl<D> fmt::Debug for UserCodeManager<D>
+where
+    D: Driver + Send + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UserCodeManager {{node: {}, slots: {:?}}}",
+            self.node.get_id(),
+            self.slots
+        )
+    }
+}
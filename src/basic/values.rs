@@ -0,0 +1,106 @@
+//! Value cache
+//!
+//! Keeps the last value which was reported by each node for a command
+//! class, so it can be shown right away without waiting for the node.
+
+use crate::cmds::{CommandClass, MeterData, SensorValue};
+
+use std::time::SystemTime;
+
+/// A value reported by a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    /// A level between 0-99 or 0xFF
+    Level(u8),
+    /// A value which is on or off
+    Switch(bool),
+    Meter(MeterData),
+    /// A measured value of a sensor
+    Sensor(SensorValue),
+}
+
+/// The last known value of a command class and when it was reported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CachedValue {
+    pub value: Value,
+    pub updated: SystemTime,
+}
+
+/// Stores the last known values of all nodes and their end points.
+#[derive(Debug, Clone, Default)]
+pub struct Values {
+    values: Vec<(u8, u8, CommandClass, CachedValue)>,
+}
+
+impl Values {
+    /// Create an empty cache
+    pub fn new() -> Values {
+        Values::default()
+    }
+
+    /// Return the last known value of the command class
+    pub fn get(&self, node: u8, endpoint: u8, cmd_class: CommandClass) -> Option<CachedValue> {
+        self.values
+            .iter()
+            .find(|v| v.0 == node && v.1 == endpoint && v.2 == cmd_class)
+            .map(|v| v.3)
+    }
+
+    /// Return all known values of the node itself, without its end points
+    pub fn node(&self, node: u8) -> Vec<(CommandClass, CachedValue)> {
+        self.values
+            .iter()
+            .filter(|v| v.0 == node && v.1 == 0)
+            .map(|v| (v.2, v.3))
+            .collect()
+    }
+
+    /// Store a value, which was just reported
+    pub fn update(&mut self, node: u8, endpoint: u8, cmd_class: CommandClass, value: Value) {
+        self.set(
+            node,
+            endpoint,
+            cmd_class,
+            CachedValue {
+                value,
+                updated: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Store a value with the time it was reported
+    pub fn set(&mut self, node: u8, endpoint: u8, cmd_class: CommandClass, value: CachedValue) {
+        match self
+            .values
+            .iter_mut()
+            .find(|v| v.0 == node && v.1 == endpoint && v.2 == cmd_class)
+        {
+            Some(v) => v.3 = value,
+            None => self.values.push((node, endpoint, cmd_class, value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update() {
+        let mut values = Values::new();
+        assert_eq!(values.get(3, 0, CommandClass::BASIC), None);
+
+        values.update(3, 0, CommandClass::BASIC, Value::Level(20));
+        values.update(3, 0, CommandClass::BASIC, Value::Level(50));
+        values.update(3, 1, CommandClass::SWITCH_BINARY, Value::Switch(true));
+
+        assert_eq!(
+            values.get(3, 0, CommandClass::BASIC).map(|v| v.value),
+            Some(Value::Level(50))
+        );
+        assert_eq!(values.get(3, 0, CommandClass::SWITCH_BINARY), None);
+        assert_eq!(values.node(3).len(), 1);
+    }
+}
@@ -0,0 +1,523 @@
+//! # zwave-cli
+//!
+//! Command line tool to verify a Z-Wave controller and its network, without
+//! writing any code. It's built with the `cli` feature:
+//!
+//! ```bash
+//! cargo run --features cli --bin zwave-cli -- /dev/ttyACM0 nodes
+//! ```
+
+use rzw::defs::{
+    SerialApiCapabilities, SerialMessage, SerialMessageFunction, SerialMessageHeader,
+    SerialMessageType, TransmitReport, TransmitStatus,
+};
+use rzw::driver::FrameDriver;
+use rzw::error::{Error, ErrorKind, Result, Stage};
+
+use std::time::{Duration, Instant};
+
+/// The driver, which sends the raw frames to the controller
+type Driver = FrameDriver<Box<dyn serial::SerialPort>>;
+
+/// The callback id which is used for all requests
+const CALLBACK_ID: u8 = 0x01;
+
+/// Transmit options for messages to the nodes (ACK, auto route and explore)
+const TRANSMIT_OPTIONS: u8 = 0x25;
+
+/// Timeout for the answers of the nodes
+const NODE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeout for the inclusion and exclusion of nodes
+const LEARN_TIMEOUT: Duration = Duration::from_secs(60);
+
+const USAGE: &str = "Usage: zwave-cli <device> <command> [args]
+
+Commands:
+    info                                Print the controller information
+    nodes                               List all nodes of the network
+    include                             Add a node to the network
+    exclude                             Remove a node from the network
+    get <node>                          Read the basic value of a node
+    set <node> <value>                  Set the basic value of a node
+    config get <node> <param>           Read a configuration parameter
+    config set <node> <param> <value>   Set a configuration parameter
+    heal [node]                         Update the neighbors of one or all nodes
+    backup <file>                       Write the NVM of the controller to a file
+    sniff                               Print all messages of the controller";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+
+    if let Err(e) = run(&args[0], &args[1..]) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Open the controller and execute the command
+fn run(device: &str, args: &[String]) -> Result<()> {
+    let mut driver = FrameDriver::open(device)?;
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+
+    match args.as_slice() {
+        ["info"] => info(&mut driver),
+        ["nodes"] => nodes(&mut driver),
+        ["include"] => include(&mut driver),
+        ["exclude"] => exclude(&mut driver),
+        ["get", node] => basic_get(&mut driver, number(node)?),
+        ["set", node, value] => basic_set(&mut driver, number(node)?, number(value)?),
+        ["config", "get", node, param] => config_get(&mut driver, number(node)?, number(param)?),
+        ["config", "set", node, param, value] => config_set(
+            &mut driver,
+            number(node)?,
+            number(param)?,
+            value
+                .parse()
+                .map_err(|_| Error::new(ErrorKind::InvalidInput, "Invalid value"))?,
+        ),
+        ["heal"] => heal_all(&mut driver),
+        ["heal", node] => heal(&mut driver, number(node)?),
+        ["backup", file] => backup(&mut driver, file),
+        ["sniff"] => sniff(&mut driver),
+        _ => Err(Error::new(ErrorKind::InvalidInput, USAGE)),
+    }
+}
+
+/// Print the controller information
+fn info(driver: &mut Driver) -> Result<()> {
+    let version = request(driver, SerialMessageFunction::GetVersion, vec![])?;
+    let id = request(driver, SerialMessageFunction::MemoryGetId, vec![])?;
+    let api = capabilities(driver)?;
+
+    if id.data.len() < 5 {
+        return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
+    }
+
+    println!(
+        "Version:      {}",
+        String::from_utf8_lossy(&version.data).trim_end_matches('\0')
+    );
+    println!(
+        "Home id:      {:#010X}",
+        u32::from_be_bytes([id.data[0], id.data[1], id.data[2], id.data[3]])
+    );
+    println!("Node id:      {}", id.data[4]);
+    println!("Application:  {}.{}", api.app_version, api.app_revision);
+    println!(
+        "Manufacturer: {:#06X}, type {:#06X}, id {:#06X}",
+        api.manufacturer_id, api.product_type, api.product_id
+    );
+
+    Ok(())
+}
+
+/// List all nodes of the network
+fn nodes(driver: &mut Driver) -> Result<()> {
+    for node in node_ids(driver)? {
+        let info = request(
+            driver,
+            SerialMessageFunction::GetNodeProtocolInfo,
+            vec![node],
+        )?;
+        let failed = request(driver, SerialMessageFunction::IsFailedNode, vec![node])?;
+
+        println!(
+            "{:3}  {}  {}",
+            node,
+            SerialMessage::to_hex(&info.data),
+            if failed.data.first() == Some(&0x01) {
+                "failed"
+            } else {
+                ""
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// Add a node to the network
+fn include(driver: &mut Driver) -> Result<()> {
+    learn(driver, SerialMessageFunction::AddNodeToNetwork, "added")
+}
+
+/// Remove a node from the network
+fn exclude(driver: &mut Driver) -> Result<()> {
+    learn(
+        driver,
+        SerialMessageFunction::RemoveNodeFromNetwork,
+        "removed",
+    )
+}
+
+/// Start the inclusion or exclusion and wait until a node was handled
+fn learn(driver: &mut Driver, function: SerialMessageFunction, action: &str) -> Result<()> {
+    // any node with high power
+    driver.write_msg(&SerialMessage::new(
+        SerialMessageType::Request,
+        function,
+        &[0x81, CALLBACK_ID],
+    )?)?;
+    println!("Press the button of the node");
+
+    let mut node = None;
+    let result = loop {
+        let msg = match wait(driver, function, LEARN_TIMEOUT) {
+            Ok(msg) => msg,
+            Err(e) => break Err(e),
+        };
+
+        // callback id, status, node
+        match msg.data.get(1) {
+            Some(0x02) => println!("Node found"),
+            Some(0x03) | Some(0x04) => node = msg.data.get(2).cloned(),
+            Some(0x05) | Some(0x06) => break Ok(()),
+            Some(0x07) => {
+                break Err(Error::new(
+                    ErrorKind::UnknownZWave,
+                    format!("The node couldn't be {}", action),
+                ))
+            }
+            _ => {}
+        }
+    };
+
+    // stop the inclusion or exclusion in any case
+    driver.write_msg(&SerialMessage::new(
+        SerialMessageType::Request,
+        function,
+        &[0x05, 0x00],
+    )?)?;
+
+    result?;
+    match node {
+        Some(node) => println!("Node {} {}", node, action),
+        None => println!("Node {}", action),
+    }
+
+    Ok(())
+}
+
+/// Read the basic value of a node
+fn basic_get(driver: &mut Driver, node: u8) -> Result<()> {
+    // COMMAND_CLASS_BASIC, BASIC_GET
+    send(driver, node, &[0x20, 0x02])?;
+    let report = receive(driver, node, &[0x20, 0x03])?;
+
+    match report.get(5) {
+        Some(value) => println!("{}", value),
+        None => return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short")),
+    }
+
+    Ok(())
+}
+
+/// Set the basic value of a node
+fn basic_set(driver: &mut Driver, node: u8, value: u8) -> Result<()> {
+    // COMMAND_CLASS_BASIC, BASIC_SET
+    send(driver, node, &[0x20, 0x01, value])
+}
+
+/// Read a configuration parameter of a node
+fn config_get(driver: &mut Driver, node: u8, param: u8) -> Result<()> {
+    // COMMAND_CLASS_CONFIGURATION, CONFIGURATION_GET
+    send(driver, node, &[0x70, 0x05, param])?;
+    let report = receive(driver, node, &[0x70, 0x06])?;
+
+    // parameter, size, value
+    let size = report.get(6).map(|s| (s & 0x07) as usize).unwrap_or(0);
+    if size == 0 || report.len() < 7 + size {
+        return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
+    }
+
+    // the value is signed
+    let value = report[7..7 + size]
+        .iter()
+        .fold(0i64, |v, b| (v << 8) | *b as i64);
+    let bits = size as u32 * 8;
+    println!("{}", (value << (64 - bits)) >> (64 - bits));
+
+    Ok(())
+}
+
+/// Set a configuration parameter of a node, with the smallest size for the value
+fn config_set(driver: &mut Driver, node: u8, param: u8, value: i32) -> Result<()> {
+    let size: usize = if value >= i8::MIN as i32 && value <= i8::MAX as i32 {
+        1
+    } else if value >= i16::MIN as i32 && value <= i16::MAX as i32 {
+        2
+    } else {
+        4
+    };
+
+    // COMMAND_CLASS_CONFIGURATION, CONFIGURATION_SET, parameter, size, value
+    let mut data = vec![0x70, 0x04, param, size as u8];
+    data.extend_from_slice(&value.to_be_bytes()[4 - size..]);
+
+    send(driver, node, &data)
+}
+
+/// Update the neighbors of all nodes
+fn heal_all(driver: &mut Driver) -> Result<()> {
+    let id = request(driver, SerialMessageFunction::MemoryGetId, vec![])?;
+    let controller = id.data.get(4).cloned();
+
+    for node in node_ids(driver)? {
+        if Some(node) == controller {
+            continue;
+        }
+
+        if let Err(e) = heal(driver, node) {
+            println!("Node {}: {}", node, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Update the neighbors of a node
+fn heal(driver: &mut Driver, node: u8) -> Result<()> {
+    driver.write_msg(&SerialMessage::new(
+        SerialMessageType::Request,
+        SerialMessageFunction::RequestNodeNeighborUpdate,
+        &[node, CALLBACK_ID],
+    )?)?;
+
+    loop {
+        let msg = wait(
+            driver,
+            SerialMessageFunction::RequestNodeNeighborUpdate,
+            LEARN_TIMEOUT,
+        )?;
+
+        // callback id, status
+        match msg.data.get(1) {
+            Some(0x22) => {
+                println!("Node {}: neighbors updated", node);
+                return Ok(());
+            }
+            Some(0x23) => {
+                return Err(Error::new(
+                    ErrorKind::UnknownZWave,
+                    "The neighbor update failed",
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Write the NVM of the controller to a file
+fn backup(driver: &mut Driver, file: &str) -> Result<()> {
+    if !capabilities(driver)?.supports(SerialMessageFunction::NvmBackupRestore) {
+        return Err(Error::new(
+            ErrorKind::NotImplemented,
+            "The controller doesn't support the NVM backup",
+        ));
+    }
+
+    // open the NVM, the data contains the size
+    let open = request(driver, SerialMessageFunction::NvmBackupRestore, vec![0x00])?;
+    if open.data.len() < 6 || open.data[0] != 0x00 {
+        return Err(Error::new(
+            ErrorKind::UnknownZWave,
+            "The NVM couldn't be opened",
+        ));
+    }
+    let size = u16::from_be_bytes([open.data[4], open.data[5]]) as usize;
+
+    // read the NVM in chunks
+    let mut nvm = Vec::with_capacity(size);
+    let result = loop {
+        if nvm.len() >= size {
+            break Ok(());
+        }
+
+        let offset = (nvm.len() as u16).to_be_bytes();
+        let length = std::cmp::min(size - nvm.len(), 0x30) as u8;
+        let chunk = request(
+            driver,
+            SerialMessageFunction::NvmBackupRestore,
+            vec![0x01, length, offset[0], offset[1]],
+        )?;
+
+        // status, length, offset, data
+        let end = 4 + chunk.data.get(1).cloned().unwrap_or(0) as usize;
+        match chunk.data.first() {
+            Some(0x00) | Some(0xFF) if chunk.data.len() >= end && end > 4 => {
+                nvm.extend_from_slice(&chunk.data[4..end])
+            }
+            _ => {
+                break Err(Error::new(
+                    ErrorKind::UnknownZWave,
+                    "The NVM couldn't be read",
+                ))
+            }
+        }
+    };
+
+    // close the NVM in any case
+    request(driver, SerialMessageFunction::NvmBackupRestore, vec![0x03])?;
+
+    result?;
+    std::fs::write(file, &nvm)?;
+    println!("{} bytes written to {}", nvm.len(), file);
+
+    Ok(())
+}
+
+/// Print all messages of the controller
+fn sniff(driver: &mut Driver) -> Result<()> {
+    loop {
+        match driver.receive_msg(Some(10)) {
+            Ok(msg) => {
+                if msg.header == SerialMessageHeader::SOF {
+                    println!(
+                        "{:?} {:?} {}",
+                        msg.typ,
+                        msg.func,
+                        SerialMessage::to_hex(&msg.data)
+                    );
+                }
+            }
+            Err(ref e) if e.kind() == ErrorKind::Io(std::io::ErrorKind::TimedOut) => {}
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// Return the ids of all nodes in the network
+fn node_ids(driver: &mut Driver) -> Result<Vec<u8>> {
+    let msg = request(driver, SerialMessageFunction::DiscoveryNodes, vec![])?;
+
+    // version, capabilities, bitmask length, node bitmask
+    if msg.data.len() < 3 || msg.data.len() < 3 + msg.data[2] as usize {
+        return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
+    }
+
+    let mut nodes = vec![];
+    for (i, byte) in msg.data[3..3 + msg.data[2] as usize].iter().enumerate() {
+        for bit in 0..8 {
+            if byte & (1 << bit) != 0 {
+                nodes.push((i * 8 + bit + 1) as u8);
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// Return the Serial API capabilities of the controller
+fn capabilities(driver: &mut Driver) -> Result<SerialApiCapabilities> {
+    let msg = request(driver, SerialMessageFunction::SerialGetCapabilities, vec![])?;
+    SerialApiCapabilities::parse(&msg.data)
+}
+
+/// Send a request to the controller and return its response
+fn request(
+    driver: &mut Driver,
+    function: SerialMessageFunction,
+    data: Vec<u8>,
+) -> Result<SerialMessage> {
+    driver.write_msg(&SerialMessage::new(
+        SerialMessageType::Request,
+        function,
+        &data,
+    )?)?;
+
+    next(driver, Stage::Response, NODE_TIMEOUT, |msg| {
+        msg.typ == SerialMessageType::Response && msg.func == function
+    })
+}
+
+/// Wait for a request of the controller with the given function
+fn wait(
+    driver: &mut Driver,
+    function: SerialMessageFunction,
+    timeout: Duration,
+) -> Result<SerialMessage> {
+    next(driver, Stage::Callback, timeout, |msg| {
+        msg.typ == SerialMessageType::Request && msg.func == function
+    })
+}
+
+/// Send a command to a node and wait until the node received it
+fn send(driver: &mut Driver, node: u8, command: &[u8]) -> Result<()> {
+    let mut data = vec![node, command.len() as u8];
+    data.extend_from_slice(command);
+    data.extend_from_slice(&[TRANSMIT_OPTIONS, CALLBACK_ID]);
+
+    let msg = request(driver, SerialMessageFunction::SendData, data)?;
+    if msg.data.first() != Some(&0x01) {
+        return Err(Error::new(
+            ErrorKind::UnknownZWave,
+            "The controller couldn't send the message",
+        ));
+    }
+
+    // callback id, transmit status
+    let msg = wait(driver, SerialMessageFunction::SendData, NODE_TIMEOUT)?;
+    let report = TransmitReport::parse(&msg.data)?;
+    if report.status != TransmitStatus::CompleteOk {
+        return Err(Error::TransmitFailed(report.status));
+    }
+
+    Ok(())
+}
+
+/// Wait for a command from the node, which starts with the given bytes.
+///
+/// Returns the data of the application command `rx status, node, length, command`.
+fn receive(driver: &mut Driver, node: u8, command: &[u8]) -> Result<Vec<u8>> {
+    next(driver, Stage::Report, NODE_TIMEOUT, |msg| {
+        msg.func == SerialMessageFunction::ApplicationCommandHandler
+            && msg.data.get(1) == Some(&node)
+            && msg.data.len() >= 3 + command.len()
+            && &msg.data[3..3 + command.len()] == command
+    })
+    .map(|msg| msg.data.to_vec())
+}
+
+/// Read messages until one matches, or the timeout for the stage is reached
+fn next<F>(
+    driver: &mut Driver,
+    stage: Stage,
+    timeout: Duration,
+    matches: F,
+) -> Result<SerialMessage>
+where
+    F: Fn(&SerialMessage) -> bool,
+{
+    let start = Instant::now();
+
+    while start.elapsed() < timeout {
+        match driver.receive_msg(Some(10)) {
+            Ok(ref msg) if msg.header == SerialMessageHeader::SOF && matches(msg) => {
+                return Ok(msg.clone())
+            }
+            // skip other messages and unknown functions
+            Ok(_) => {}
+            Err(ref e) if e.kind() == ErrorKind::Io(std::io::ErrorKind::TimedOut) => {}
+            Err(ref e) if e.kind() == ErrorKind::UnknownZWave => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(Error::Timeout { stage })
+}
+
+/// Parse a command line number
+fn number(arg: &str) -> Result<u8> {
+    arg.parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("'{}' is not a number between 0 and 255", arg),
+        )
+    })
+}
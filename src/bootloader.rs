@@ -0,0 +1,261 @@
+//! Firmware update of the controller over the serial port
+//!
+//! The 700 and 800 series Z-Wave modules contain the Silabs Gecko bootloader.
+//! Once started, it shows a text menu on the serial port and receives the
+//! firmware image (a `.gbl` file) with the XMODEM-CRC protocol.
+
+use crate::error::{Error, ErrorKind, Result};
+
+/// The magic bytes, which start every Gecko bootloader image
+pub const GBL_MAGIC: [u8; 4] = [0xEB, 0x17, 0xA6, 0x03];
+
+/// The prompt of the bootloader menu
+const PROMPT: &[u8] = b"BL >";
+
+/// The size of the data in each XMODEM block
+const BLOCK_SIZE: usize = 128;
+
+/// Amount of times a block is sent, before the upload is aborted
+const MAX_RETRIES: usize = 10;
+
+/// Amount of read timeouts, which are accepted while waiting for an answer
+const MAX_TIMEOUTS: usize = 100;
+
+const SOH: u8 = 0x01;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+const CRC_MODE: u8 = b'C';
+const PADDING: u8 = 0xFF;
+
+/// Check that the image is a Gecko bootloader image.
+pub fn check_image(image: &[u8]) -> Result<()> {
+    if image.len() < GBL_MAGIC.len() || image[..GBL_MAGIC.len()] != GBL_MAGIC {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The image is not a Gecko bootloader image (.gbl)",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Upload the image to the bootloader and start the new firmware.
+///
+/// The device needs to be in the bootloader already. The progress function
+/// is called with the amount of sent and total bytes after each block.
+pub fn upload<D, F>(device: &mut D, image: &[u8], mut progress: F) -> Result<()>
+where
+    D: std::io::Read + std::io::Write,
+    F: FnMut(usize, usize),
+{
+    check_image(image)?;
+
+    // select "upload gbl" in the menu
+    read_until(device, PROMPT)?;
+    device.write_all(b"1")?;
+    device.flush()?;
+
+    // the receiver starts the transfer
+    loop {
+        match read_byte(device)? {
+            CRC_MODE => break,
+            CAN => return Err(cancelled()),
+            _ => {}
+        }
+    }
+
+    for (i, chunk) in image.chunks(BLOCK_SIZE).enumerate() {
+        send_block(device, (i + 1) as u8, chunk)?;
+        progress(
+            std::cmp::min((i + 1) * BLOCK_SIZE, image.len()),
+            image.len(),
+        );
+    }
+
+    // finish the transfer
+    send_until_ack(device, &[EOT])?;
+
+    // select "run" in the menu, to start the new firmware
+    read_until(device, PROMPT)?;
+    device.write_all(b"2")?;
+    device.flush()?;
+
+    Ok(())
+}
+
+/// Send one XMODEM block, padded to the block size.
+fn send_block<D>(device: &mut D, number: u8, data: &[u8]) -> Result<()>
+where
+    D: std::io::Read + std::io::Write,
+{
+    // SOH, block number, inverted block number, data, CRC
+    let mut block = vec![SOH, number, !number];
+    block.extend_from_slice(data);
+    block.resize(3 + BLOCK_SIZE, PADDING);
+
+    let crc = crc16(&block[3..]);
+    block.extend_from_slice(&crc.to_be_bytes());
+
+    send_until_ack(device, &block)
+}
+
+/// Send the data until the receiver acknowledges it.
+fn send_until_ack<D>(device: &mut D, data: &[u8]) -> Result<()>
+where
+    D: std::io::Read + std::io::Write,
+{
+    for _ in 0..MAX_RETRIES {
+        device.write_all(data)?;
+        device.flush()?;
+
+        match read_byte(device)? {
+            ACK => return Ok(()),
+            CAN => return Err(cancelled()),
+            // NAK or noise, send it again
+            _ => {}
+        }
+    }
+
+    Err(Error::new(
+        ErrorKind::UnknownZWave,
+        "The bootloader didn't accept the firmware image",
+    ))
+}
+
+/// Read from the device, until the text was received.
+fn read_until<D>(device: &mut D, text: &[u8]) -> Result<()>
+where
+    D: std::io::Read,
+{
+    let mut received = vec![];
+
+    while !received.ends_with(text) {
+        received.push(read_byte(device)?);
+    }
+
+    Ok(())
+}
+
+/// Read a single byte and wait a while for it.
+fn read_byte<D>(device: &mut D) -> Result<u8>
+where
+    D: std::io::Read,
+{
+    let mut buffer = [0u8; 1];
+
+    for _ in 0..MAX_TIMEOUTS {
+        match device.read_exact(&mut buffer) {
+            Ok(_) => return Ok(buffer[0]),
+            Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(Error::new(
+        ErrorKind::Io(std::io::ErrorKind::TimedOut),
+        "The bootloader didn't answer",
+    ))
+}
+
+/// The error for a transfer which was cancelled by the bootloader
+fn cancelled() -> Error {
+    Error::new(
+        ErrorKind::UnknownZWave,
+        "The bootloader cancelled the upload",
+    )
+}
+
+/// The CRC-16 of XMODEM (polynomial 0x1021, start value 0)
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bootloader, which answers with the given bytes and records the written ones
+    struct TestBootloader {
+        input: Vec<u8>,
+        output: Vec<u8>,
+    }
+
+    impl std::io::Read for TestBootloader {
+        fn read(&mut self, inp: &mut [u8]) -> std::result::Result<usize, std::io::Error> {
+            if self.input.is_empty() {
+                return Err(std::io::Error::from(std::io::ErrorKind::TimedOut));
+            }
+
+            inp[0] = self.input.remove(0);
+            Ok(1)
+        }
+    }
+
+    impl std::io::Write for TestBootloader {
+        fn write(&mut self, inp: &[u8]) -> std::result::Result<usize, std::io::Error> {
+            self.output.extend_from_slice(inp);
+            Ok(inp.len())
+        }
+
+        fn flush(&mut self) -> std::result::Result<(), std::io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+    }
+
+    #[test]
+    fn test_upload() {
+        let mut image = GBL_MAGIC.to_vec();
+        image.resize(200, 0xAA);
+
+        // the menu, the start of the transfer, a NAK for the first try of
+        // the first block and the ACKs for both blocks and the end
+        let mut input =
+            b"Gecko Bootloader\r\n1. upload gbl\r\n2. run\r\nBL > begin upload\r\nC".to_vec();
+        input.extend_from_slice(&[NAK, ACK, ACK, ACK]);
+        input.extend_from_slice(b"\r\nSerial upload complete\r\nBL > ");
+
+        let mut device = TestBootloader {
+            input,
+            output: vec![],
+        };
+        let mut sent = vec![];
+        upload(&mut device, &image, |done, total| sent.push((done, total))).unwrap();
+
+        assert_eq!(sent, vec![(128, 200), (200, 200)]);
+
+        // menu selection, the first block twice, the second block, EOT and run
+        let block = 3 + BLOCK_SIZE + 2;
+        assert_eq!(device.output.len(), 1 + 3 * block + 1 + 1);
+        assert_eq!(device.output[0], b'1');
+        assert_eq!(&device.output[1..4], &[SOH, 0x01, 0xFE]);
+        assert_eq!(
+            &device.output[1 + 2 * block..4 + 2 * block],
+            &[SOH, 0x02, 0xFD]
+        );
+        // the last block is padded
+        assert_eq!(device.output[3 * block - 2], PADDING);
+        assert_eq!(&device.output[device.output.len() - 2..], &[EOT, b'2']);
+
+        assert!(check_image(&[0x00, 0x01, 0x02, 0x03]).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+//! AES-128 block cipher
+//!
+//! The cipher of the RustCrypto `aes` crate with an interface for single
+//! blocks. The modes of the Security Command Classes are taken from the
+//! `cbc`, `ccm`, `cmac` and `ctr` crates or built on top of it.
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+
+/// The size of a block and of the key in bytes
+pub const BLOCK_SIZE: usize = 16;
+
+/// An AES-128 cipher with the expanded key. The key is never printed, so
+/// it doesn't end up in a log.
+#[derive(Debug, Clone)]
+pub struct Aes128(aes::Aes128);
+
+impl Aes128 {
+    /// Expand the key for the rounds of the cipher
+    pub fn new(key: &[u8; BLOCK_SIZE]) -> Aes128 {
+        Aes128(aes::Aes128::new(key.into()))
+    }
+
+    /// Encrypt a single block
+    pub fn encrypt(&self, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mut block = aes::Block::from(*block);
+        self.0.encrypt_block(&mut block);
+        block.into()
+    }
+
+    /// The cipher of the `aes` crate, which the modes are built on
+    pub(crate) fn cipher(&self) -> aes::Aes128 {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        // the example of FIPS-197, appendix C.1
+        let mut key = [0u8; BLOCK_SIZE];
+        let mut block = [0u8; BLOCK_SIZE];
+        for i in 0..BLOCK_SIZE as u8 {
+            key[i as usize] = i;
+            block[i as usize] = i * 0x11;
+        }
+
+        assert_eq!(
+            Aes128::new(&key).encrypt(&block),
+            [
+                0x69, 0xC4, 0xE0, 0xD8, 0x6A, 0x7B, 0x04, 0x30, 0xD8, 0xCD, 0xB7, 0x80, 0x70, 0xB4,
+                0xC5, 0x5A
+            ]
+        );
+
+        // the key isn't printed
+        assert!(!format!("{:?}", Aes128::new(&key)).contains("0x"));
+    }
+}
@@ -0,0 +1,189 @@
+//! The Anti-theft Command Class is used to lock portable devices, so a
+//! stolen device stops working until it's unlocked with the magic code.
+//!
+//! The magic code isn't reported back. The hint, like a serial number,
+//! helps the owner to find the code of a locked device.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+
+/// The longest magic code and hint in bytes
+const MAX_LENGTH: usize = 10;
+
+/// The protection status of a device
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum AntiTheftStatus {
+    /// The protection is disabled
+    Unprotected = 0x01,
+    /// The protection is enabled and the device works
+    Protected = 0x02,
+    /// The device is locked, until it's unlocked with the magic code
+    Locked = 0x03,
+}
+
+/// The protection status and who protected the device.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AntiTheftState {
+    pub status: AntiTheftStatus,
+    /// The manufacturer, which protected the device
+    pub manufacturer: u16,
+    /// The hint to find the magic code
+    pub hint: Vec<u8>,
+    /// The Z-Wave Alliance id of the locking entity, only sent by version 2
+    pub locking_entity: Option<u16>,
+}
+
+/// Anti-theft Command Class
+#[derive(Debug, Clone)]
+pub struct AntiTheft;
+
+impl AntiTheft {
+    /// The Anti-theft Set Command is used to enable or disable the theft
+    /// protection. The magic code needs 1-10 bytes, the hint up to 10 bytes.
+    pub fn set<N>(
+        node_id: N,
+        enable: bool,
+        magic_code: &[u8],
+        manufacturer: u16,
+        hint: &[u8],
+        locking_entity: Option<u16>,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |         Command Class = COMMAND_CLASS_ANTITHEFT(0x5D)         |
+        // |                 Command = ANTITHEFT_SET(0x01)                 |
+        // |Enable |               Magic Code Length                       |
+        // |                   Magic Code 1 ... Magic Code N               |
+        // |                   Manufacturer ID 1 (MSB)                     |
+        // |                   Manufacturer ID 2 (LSB)                     |
+        // |                 Anti-theft Hint Number Bytes                  |
+        // |               Anti-theft Hint 1 ... Anti-theft Hint M         |
+        // |           Locking Entity ID 1 (MSB, version 2)                |
+        // |           Locking Entity ID 2 (LSB, version 2)                |
+        // -----------------------------------------------------------------
+        if magic_code.is_empty() || magic_code.len() > MAX_LENGTH {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The magic code needs to be between 1-10 bytes long",
+            ));
+        }
+        if hint.len() > MAX_LENGTH {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The hint can't be longer than 10 bytes",
+            ));
+        }
+
+        let mut data = vec![if enable { 0x80 } else { 0x00 } | magic_code.len() as u8];
+        data.extend_from_slice(magic_code);
+        data.extend_from_slice(&manufacturer.to_be_bytes());
+        data.push(hint.len() as u8);
+        data.extend_from_slice(hint);
+        if let Some(entity) = locking_entity {
+            data.extend_from_slice(&entity.to_be_bytes());
+        }
+
+        Message::new(node_id.into(), CommandClass::ANTITHEFT, 0x01, &data)
+    }
+
+    /// The Anti-theft Get Command is used to request the protection status.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::ANTITHEFT, 0x02, [])
+    }
+
+    /// The Anti-theft Report Command is used to advertise the protection
+    /// status.
+    pub fn report(frame: &IncomingFrame) -> Result<AntiTheftState, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |         Command Class = COMMAND_CLASS_ANTITHEFT(0x5D)         |
+        // |                Command = ANTITHEFT_REPORT(0x03)               |
+        // |                       Protection Status                       |
+        // |                   Manufacturer ID 1 (MSB)                     |
+        // |                   Manufacturer ID 2 (LSB)                     |
+        // |                 Anti-theft Hint Number Bytes                  |
+        // |               Anti-theft Hint 1 ... Anti-theft Hint M         |
+        // |           Locking Entity ID 1 (MSB, version 2)                |
+        // |           Locking Entity ID 2 (LSB, version 2)                |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::ANTITHEFT, 0x03)?;
+
+        if payload.len() < 4 || payload.len() < 4 + payload[3] as usize {
+            return Err(Error::parse(
+                CommandClass::ANTITHEFT,
+                "Message is too short",
+            ));
+        }
+
+        let status = AntiTheftStatus::try_from(payload[0]).map_err(|_| {
+            Error::parse(
+                CommandClass::ANTITHEFT,
+                format!("Unknown protection status {:#04x}", payload[0]),
+            )
+        })?;
+
+        let end = 4 + payload[3] as usize;
+        let locking_entity = payload
+            .get(end..end + 2)
+            .map(|e| u16::from_be_bytes([e[0], e[1]]));
+
+        Ok(AntiTheftState {
+            status,
+            manufacturer: u16::from_be_bytes([payload[1], payload[2]]),
+            hint: payload[4..end].to_vec(),
+            locking_entity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = AntiTheft::set(0x05, true, &[0x12, 0x34], 0x0086, &[0x41], Some(0x0001)).unwrap();
+        assert_eq!(
+            msg.data,
+            vec![0x82, 0x12, 0x34, 0x00, 0x86, 0x01, 0x41, 0x00, 0x01]
+        );
+
+        assert!(AntiTheft::set(0x05, true, &[], 0x0086, &[], None).is_err());
+        assert!(AntiTheft::set(0x05, true, &[0x01], 0x0086, &[0x00; 11], None).is_err());
+    }
+
+    #[test]
+    fn report() {
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x08, 0x5D, 0x03, 0x03, 0x00, 0x86, 0x02, 0x41, 0x42,
+        ])
+        .unwrap();
+        assert_eq!(
+            AntiTheft::report(&frame),
+            Ok(AntiTheftState {
+                status: AntiTheftStatus::Locked,
+                manufacturer: 0x0086,
+                hint: vec![0x41, 0x42],
+                locking_entity: None,
+            })
+        );
+
+        // the hint is longer than the message
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x5D, 0x03, 0x02, 0x00, 0x86, 0x02]).unwrap();
+        assert!(AntiTheft::report(&frame).is_err());
+    }
+}
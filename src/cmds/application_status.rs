@@ -0,0 +1,120 @@
+//! The Application Status Command Class is used by a node to tell, that it
+//! can't handle a request right now. It's either busy and asks to try again
+//! later, or it rejects the request.
+
+use crate::cmds::{CommandClass, IncomingFrame};
+use crate::error::Error;
+
+use std::time::Duration;
+
+/// Why the node didn't handle the request
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ApplicationStatusReport {
+    /// The node is busy, the request should be sent again later
+    TryAgainLater,
+    /// The node is busy, the request should be sent again after the time
+    TryAgainIn(Duration),
+    /// The node queued the request and handles it later
+    Queued,
+    /// The node rejected the request
+    Rejected,
+}
+
+/// Application Status Command Class
+#[derive(Debug, Clone)]
+pub struct ApplicationStatus;
+
+impl ApplicationStatus {
+    /// Decode the Application Busy and the Application Rejected Request
+    /// Commands.
+    pub fn report(frame: &IncomingFrame) -> Result<ApplicationStatusReport, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_APPLICATION_STATUS(0x22)    |
+        // |                Command = APPLICATION_BUSY(0x01)               |
+        // |                            Status                             |
+        // |                           Wait Time                           |
+        // -----------------------------------------------------------------
+        // |     Command Class = COMMAND_CLASS_APPLICATION_STATUS(0x22)    |
+        // |          Command = APPLICATION_REJECTED_REQUEST(0x02)         |
+        // |                            Status                             |
+        // -----------------------------------------------------------------
+
+        if frame.command_class != Ok(CommandClass::APPLICATION_STATUS) {
+            return Err(Error::parse(
+                CommandClass::APPLICATION_STATUS,
+                "Answer contained wrong command class",
+            ));
+        }
+
+        let payload = &frame.payload;
+        match frame.command {
+            0x01 if payload.len() < 2 => Err(Error::parse(
+                CommandClass::APPLICATION_STATUS,
+                "Message is too short",
+            )),
+            0x01 => match payload[0] {
+                0x00 => Ok(ApplicationStatusReport::TryAgainLater),
+                0x01 => Ok(ApplicationStatusReport::TryAgainIn(Duration::from_secs(
+                    payload[1] as u64,
+                ))),
+                0x02 => Ok(ApplicationStatusReport::Queued),
+                status => Err(Error::parse(
+                    CommandClass::APPLICATION_STATUS,
+                    format!("Unknown busy status {:#04x}", status),
+                )),
+            },
+            0x02 => Ok(ApplicationStatusReport::Rejected),
+            _ => Err(Error::parse(
+                CommandClass::APPLICATION_STATUS,
+                "Answer contained wrong command",
+            )),
+        }
+    }
+
+    /// Returns the error, which a busy or rejecting node stands for. A
+    /// queued request isn't an error, its answer comes later.
+    pub fn error(report: ApplicationStatusReport) -> Option<Error> {
+        match report {
+            ApplicationStatusReport::TryAgainLater => Some(Error::Busy { wait: None }),
+            ApplicationStatusReport::TryAgainIn(wait) => Some(Error::Busy { wait: Some(wait) }),
+            ApplicationStatusReport::Queued => None,
+            ApplicationStatusReport::Rejected => Some(Error::Rejected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x22, 0x01, 0x01, 0x05]).unwrap();
+        let report = ApplicationStatus::report(&frame).unwrap();
+        assert_eq!(
+            report,
+            ApplicationStatusReport::TryAgainIn(Duration::from_secs(5))
+        );
+        assert_eq!(
+            ApplicationStatus::error(report),
+            Some(Error::Busy {
+                wait: Some(Duration::from_secs(5))
+            })
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x22, 0x01, 0x02, 0x00]).unwrap();
+        let report = ApplicationStatus::report(&frame).unwrap();
+        assert_eq!(ApplicationStatus::error(report), None);
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x22, 0x02, 0x00]).unwrap();
+        assert_eq!(
+            ApplicationStatus::report(&frame),
+            Ok(ApplicationStatusReport::Rejected)
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x02, 0x22, 0x01]).unwrap();
+        assert!(ApplicationStatus::report(&frame).is_err());
+    }
+}
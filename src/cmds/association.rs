@@ -0,0 +1,151 @@
+//! The Association Command Class is used to manage associations to NodeID destinations.
+//!
+//! A node sends its unsolicited reports, like a changed sensor value, to the
+//! nodes which are associated to one of its groups. Group 1 is normally the
+//! lifeline group, which reports to the controller.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// The nodes which are associated to a group of a node.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AssociationReport {
+    pub group: u8,
+    pub max_nodes: u8,
+    pub reports_to_follow: u8,
+    pub nodes: Vec<u8>,
+}
+
+/// Association Command Class
+#[derive(Debug, Clone)]
+pub struct Association;
+
+impl Association {
+    /// The Association Set Command is used to add destinations to a given association group.
+    pub fn set<N>(node_id: N, group: u8, nodes: &[u8]) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |        Command Class = COMMAND_CLASS_ASSOCIATION(0x85)        |
+        // |                Command = ASSOCIATION_SET(0x01)                |
+        // |                      Grouping Identifier                      |
+        // |                    Node ID 1 ... Node ID n                    |
+        // -----------------------------------------------------------------
+        let mut data = vec![group];
+        data.extend_from_slice(nodes);
+
+        Message::new(node_id.into(), CommandClass::ASSOCIATION, 0x01, &data)
+    }
+
+    /// The Association Get Command is used to request the current destinations
+    /// of a given association group.
+    pub fn get<N>(node_id: N, group: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::ASSOCIATION, 0x02, [group])
+    }
+
+    /// The Association Report Command is used to advertise the current destinations
+    /// of a given association group.
+    pub fn report(frame: &IncomingFrame) -> Result<AssociationReport, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |        Command Class = COMMAND_CLASS_ASSOCIATION(0x85)        |
+        // |               Command = ASSOCIATION_REPORT(0x03)              |
+        // |                      Grouping Identifier                      |
+        // |                      Max Nodes Supported                      |
+        // |                       Reports to Follow                       |
+        // |                    Node ID 1 ... Node ID n                    |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::ASSOCIATION, 0x03)?;
+
+        // the payload need to be at least 3 bytes long
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::ASSOCIATION,
+                "Message is too short",
+            ));
+        }
+
+        Ok(AssociationReport {
+            group: payload[0],
+            max_nodes: payload[1],
+            reports_to_follow: payload[2],
+            nodes: payload[3..].to_vec(),
+        })
+    }
+
+    /// The Association Remove Command is used to remove destinations from a given
+    /// association group. When no nodes are given, all destinations are removed.
+    pub fn remove<N>(node_id: N, group: u8, nodes: &[u8]) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        let mut data = vec![group];
+        data.extend_from_slice(nodes);
+
+        Message::new(node_id.into(), CommandClass::ASSOCIATION, 0x04, &data)
+    }
+
+    /// The Association Supported Groupings Get Command is used to request the number
+    /// of association groups that this node supports.
+    pub fn groupings_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::ASSOCIATION, 0x05, [])
+    }
+
+    /// The Association Supported Groupings Report Command is used to advertise the
+    /// number of association groups that this node supports.
+    pub fn groupings_report(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::ASSOCIATION, 0x06)?;
+
+        payload
+            .first()
+            .cloned()
+            .ok_or_else(|| Error::parse(CommandClass::ASSOCIATION, "Message is too short"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_message() {
+        let msg = Association::set(0x05, 0x01, &[0x01, 0x03]).unwrap();
+        assert_eq!(msg.to_vec(), vec![0x05, 0x05, 0x85, 0x01, 0x01, 0x01, 0x03]);
+    }
+
+    #[test]
+    fn report() {
+        let report = Association::report(
+            &IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x85, 0x03, 0x01, 0x05, 0x00, 0x01]).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            report,
+            AssociationReport {
+                group: 0x01,
+                max_nodes: 0x05,
+                reports_to_follow: 0x00,
+                nodes: vec![0x01],
+            }
+        );
+
+        // a wrong command is rejected
+        assert!(Association::report(
+            &IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x85, 0x06, 0x01, 0x05, 0x00]).unwrap()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! The Association Group Information (AGI) Command Class allows a node to advertise
+//! the capabilities of each association group supported by a given application
+//! resource.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// Association Group Information Command Class
+#[derive(Debug, Clone)]
+pub struct AssociationGroupInfo;
+
+impl AssociationGroupInfo {
+    /// The Association Group Name Get Command is used to query the name of an
+    /// association group.
+    pub fn name_get<N>(node_id: N, group: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::ASSOCIATION_GRP_INFO,
+            0x01,
+            [group],
+        )
+    }
+
+    /// The Association Group Name Report Command is used to advertise the
+    /// assigned name of an association group.
+    ///
+    /// Return the group id and the name of the group.
+    pub fn name_report(frame: &IncomingFrame) -> Result<(u8, String), Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |    Command Class = COMMAND_CLASS_ASSOCIATION_GRP_INFO(0x59)   |
+        // |         Command = ASSOCIATION_GROUP_NAME_REPORT(0x02)         |
+        // |                      Grouping Identifier                      |
+        // |                         Length of Name                        |
+        // |                       Name 1 ... Name N                       |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::ASSOCIATION_GRP_INFO, 0x02)?;
+
+        // the payload need to be at least 2 bytes long
+        if payload.len() < 2 {
+            return Err(Error::parse(
+                CommandClass::ASSOCIATION_GRP_INFO,
+                "Message is too short",
+            ));
+        }
+
+        // check if the name fits into the message
+        let len = payload[1] as usize;
+        if payload.len() < 2 + len {
+            return Err(Error::parse(
+                CommandClass::ASSOCIATION_GRP_INFO,
+                "Message has the wrong length",
+            ));
+        }
+
+        // the name is UTF-8 encoded
+        Ok((
+            payload[0],
+            String::from_utf8_lossy(&payload[2..2 + len]).into_owned(),
+        ))
+    }
+}
@@ -1,50 +1,115 @@
-use cmds::{CommandClass, Message};
-use error::{Error, ErrorKind};
+use crate::cmds::switch_binary::SwitchBinary;
+use crate::cmds::switch_multilevel::SwitchMultilevel;
+use crate::cmds::{check_level, CommandClass, IncomingFrame, Message};
+use crate::defs::GenericType;
+use crate::error::Error;
 
 #[derive(Debug, Clone)]
 pub struct Basic;
 
 impl Basic {
     /// Generate the message for the basic Command Class with
-    /// the function to set a value.
-    pub fn set(node_id: u8, value: u8) -> Message {
-        Message::new(node_id, CommandClass::BASIC, 0x01, vec![value])
+    /// the function to set a value. The value need to be 0-99 or 0xFF.
+    pub fn set(node_id: u8, value: u8) -> Result<Message, Error> {
+        Ok(Message::from_array(
+            node_id,
+            CommandClass::BASIC,
+            0x01,
+            [check_level(value)?],
+        ))
     }
 
     /// Generate the message for the basic Command Class with
     /// the function to get a value.
     pub fn get(node_id: u8) -> Message {
-        Message::new(node_id, CommandClass::BASIC, 0x02, vec![])
+        Message::from_array(node_id, CommandClass::BASIC, 0x02, [])
     }
 
     /// Returns the basic node value
-    pub fn report<M>(msg: M) -> Result<u8, Error>
-    where
-        M: Into<Vec<u8>>,
-    {
-        // get the message
-        let msg = msg.into();
-
-        // the message need to be at least 5 bytes long
-        if msg.len() < 5 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
+    pub fn report(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::BASIC, 0x03)?;
+
+        // return the value
+        payload.first().cloned().ok_or_else(|| {
+            Error::parse(
+                CommandClass::BASIC,
                 format!(
                     "Message is too short for a Basic::report message: {:X?}",
-                    msg
+                    frame
                 ),
-            ));
+            )
+        })
+    }
+
+    /// Returns the command class, which the basic commands of a node with
+    /// the generic device type are mapped to. Only the device types with a
+    /// single primary function have one.
+    pub fn mapped_class(generic: GenericType) -> Option<CommandClass> {
+        match generic {
+            GenericType::BinarySwitch => Some(CommandClass::SWITCH_BINARY),
+            GenericType::MultiLevelSwitch | GenericType::WindowCovering => {
+                Some(CommandClass::SWITCH_MULTILEVEL)
+            }
+            GenericType::EntryControl => Some(CommandClass::DOOR_LOCK),
+            GenericType::BinarySensor => Some(CommandClass::SENSOR_BINARY),
+            GenericType::MultilevelSensor => Some(CommandClass::SENSOR_MULTILEVEL),
+            _ => None,
         }
+    }
 
-        // check the CommandClass and command
-        if msg[3] != CommandClass::BASIC as u8 || msg[4] != 0x03 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                "Answer contained wrong command class",
-            ));
+    /// Generate the set message of the command class, which the basic set is
+    /// mapped to. The basic set is returned for the other command classes.
+    pub fn mapped_set(node_id: u8, cmd_class: CommandClass, value: u8) -> Result<Message, Error> {
+        let value = check_level(value)?;
+
+        match cmd_class {
+            CommandClass::SWITCH_BINARY => Ok(SwitchBinary::set(node_id, value > 0)),
+            CommandClass::SWITCH_MULTILEVEL => SwitchMultilevel::set(node_id, value, None),
+            // the door lock operation set secures the lock with 0xFF
+            CommandClass::DOOR_LOCK => Ok(Message::from_array(
+                node_id,
+                CommandClass::DOOR_LOCK,
+                0x01,
+                [if value > 0 { 0xFF } else { 0x00 }],
+            )),
+            _ => Basic::set(node_id, value),
         }
+    }
 
-        // return the value
-        Ok(msg[5])
+    /// Returns the value of a basic set, which was sent by the node. Sensors
+    /// and remotes send it to tell about a change.
+    pub fn set_value(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::BASIC, 0x01)?;
+
+        payload
+            .first()
+            .cloned()
+            .ok_or_else(|| Error::parse(CommandClass::BASIC, "Message is too short"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_set() {
+        let class = Basic::mapped_class(GenericType::MultiLevelSwitch).unwrap();
+        let msg = Basic::mapped_set(0x05, class, 0x32).unwrap();
+        assert_eq!(msg.cmd_class, CommandClass::SWITCH_MULTILEVEL);
+        assert_eq!(msg.data, vec![0x32]);
+
+        let class = Basic::mapped_class(GenericType::EntryControl).unwrap();
+        let msg = Basic::mapped_set(0x05, class, 0x63).unwrap();
+        assert_eq!(msg.cmd_class, CommandClass::DOOR_LOCK);
+        assert_eq!(msg.data, vec![0xFF]);
+
+        // the controllers have no primary command class
+        assert_eq!(Basic::mapped_class(GenericType::StaticController), None);
+        let msg = Basic::mapped_set(0x05, CommandClass::BASIC, 0x00).unwrap();
+        assert_eq!(msg.cmd_class, CommandClass::BASIC);
+        assert!(Basic::mapped_set(0x05, CommandClass::BASIC, 0x64).is_err());
     }
 }
@@ -0,0 +1,77 @@
+//! The Battery Command Class is used to request and report the battery
+//! level of a node.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// The value a node reports instead of the level, to warn about a low battery
+pub const LOW_BATTERY_WARNING: u8 = 0xFF;
+
+/// The battery level of a node
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BatteryLevel {
+    /// The level in percent
+    Percent(u8),
+    /// The battery is almost empty, the node didn't report the level
+    Low,
+}
+
+impl BatteryLevel {
+    /// Returns the level in percent, a low battery warning is 0%
+    pub fn percent(&self) -> u8 {
+        match *self {
+            BatteryLevel::Percent(p) => p,
+            BatteryLevel::Low => 0,
+        }
+    }
+}
+
+/// Battery Command Class
+#[derive(Debug, Clone)]
+pub struct Battery;
+
+impl Battery {
+    /// The Battery Get Command is used to request the level of a battery.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::BATTERY, 0x02, [])
+    }
+
+    /// The Battery Report Command is used to report the battery level of a
+    /// battery operated device.
+    pub fn report(frame: &IncomingFrame) -> Result<BatteryLevel, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::BATTERY, 0x03)?;
+
+        match payload.first() {
+            Some(&LOW_BATTERY_WARNING) => Ok(BatteryLevel::Low),
+            Some(&level) if level <= 100 => Ok(BatteryLevel::Percent(level)),
+            Some(_) => Err(Error::parse(
+                CommandClass::BATTERY,
+                "The battery level is out of range",
+            )),
+            None => Err(Error::parse(CommandClass::BATTERY, "Message is too short")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x80, 0x03, 0x4B]).unwrap();
+        assert_eq!(Battery::report(&frame), Ok(BatteryLevel::Percent(75)));
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x80, 0x03, 0xFF]).unwrap();
+        assert_eq!(Battery::report(&frame), Ok(BatteryLevel::Low));
+        assert_eq!(BatteryLevel::Low.percent(), 0);
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x80, 0x03, 0x65]).unwrap();
+        assert!(Battery::report(&frame).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Message builder
+//!
+//! Builds up a `Message` step by step, instead of assembling the bytes by
+//! hand. Every step is checked right away, the first invalid value is
+//! returned by `build`.
+
+use crate::cmds::multi_channel::{MultiChannel, MAX_ENDPOINT};
+use crate::cmds::{CommandClass, Message};
+use crate::defs::{
+    SecurityClass, SerialMessage, SerialMessageFunction, SerialMessageType, TransmitOptions,
+};
+use crate::error::{Error, ErrorKind};
+
+/// The highest node id of a Z-Wave network
+pub const MAX_NODE_ID: u8 = 232;
+
+/// The maximal length of a command with its command class, after it was
+/// encapsulated. This is what fits into a single frame on every speed.
+pub const MAX_COMMAND_LENGTH: usize = 46;
+
+/// The bytes a Security 0 encapsulation adds to a command
+const S0_OVERHEAD: usize = 20;
+
+/// Builder for a `Message` to a node.
+///
+/// ```rust
+/// use rzw::cmds::{CommandClass, MessageBuilder};
+///
+/// let message = MessageBuilder::new()
+///     .node(0x02)
+///     .cmd_class(CommandClass::BASIC)
+///     .cmd(0x01)
+///     .payload(&[0xFF])
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(message.to_vec(), vec![0x02, 0x03, 0x20, 0x01, 0xFF]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MessageBuilder {
+    node_id: Option<u8>,
+    cmd_class: Option<CommandClass>,
+    cmd: Option<u8>,
+    data: Vec<u8>,
+    options: TransmitOptions,
+    endpoint: u8,
+    security: Option<SecurityClass>,
+    error: Option<Error>,
+}
+
+impl MessageBuilder {
+    /// Create an empty builder
+    pub fn new() -> MessageBuilder {
+        MessageBuilder::default()
+    }
+
+    /// Set the node to send the message to
+    pub fn node(mut self, node_id: u8) -> MessageBuilder {
+        if node_id == 0 || node_id > MAX_NODE_ID {
+            return self.fail(format!("The node id needs to be between 1-{}", MAX_NODE_ID));
+        }
+
+        self.node_id = Some(node_id);
+        self
+    }
+
+    /// Set the command class of the message
+    pub fn cmd_class(mut self, cmd_class: CommandClass) -> MessageBuilder {
+        self.cmd_class = Some(cmd_class);
+        self
+    }
+
+    /// Set the command of the command class
+    pub fn cmd(mut self, cmd: u8) -> MessageBuilder {
+        self.cmd = Some(cmd);
+        self
+    }
+
+    /// Append the bytes to the parameters of the command
+    pub fn payload(mut self, data: &[u8]) -> MessageBuilder {
+        self.data.extend_from_slice(data);
+        self.check_length()
+    }
+
+    /// Append a single byte to the parameters of the command
+    pub fn byte(self, value: u8) -> MessageBuilder {
+        self.payload(&[value])
+    }
+
+    /// Set the options the message is sent with
+    pub fn options(mut self, options: TransmitOptions) -> MessageBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Send the message to an end point of the node, `0` is the node itself
+    pub fn endpoint(mut self, endpoint: u8) -> MessageBuilder {
+        if endpoint > MAX_ENDPOINT {
+            return self.fail(format!(
+                "The end point needs to be between 0-{}",
+                MAX_ENDPOINT
+            ));
+        }
+
+        self.endpoint = endpoint;
+        self.check_length()
+    }
+
+    /// Send the message with the security class
+    pub fn security(mut self, security: SecurityClass) -> MessageBuilder {
+        self.security = Some(security);
+        self.check_length()
+    }
+
+    /// Return the options the message is sent with
+    pub fn transmit_options(&self) -> TransmitOptions {
+        self.options
+    }
+
+    /// Build the message, encapsulated for the end point.
+    pub fn build(self) -> Result<Message, Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        if self.security.map(|s| s.is_secure()).unwrap_or(false) {
+            return Err(Error::new(
+                ErrorKind::NotImplemented,
+                "Secure messages can't be built yet",
+            ));
+        }
+
+        let node_id = self.node_id.ok_or_else(|| missing("node"))?;
+        let cmd_class = self.cmd_class.ok_or_else(|| missing("command class"))?;
+        let cmd = self.cmd.ok_or_else(|| missing("command"))?;
+
+        let message = Message::new(node_id, cmd_class, cmd, &self.data)?;
+        match self.endpoint {
+            0 => Ok(message),
+            endpoint => MultiChannel::encap(message, endpoint),
+        }
+    }
+
+    /// Build the `SendData` request for the controller, with the callback id
+    /// the transmit report is assigned with.
+    pub fn build_serial(self, callback_id: u8) -> Result<SerialMessage, Error> {
+        let options = self.options;
+
+        let mut data = self.build()?.to_vec();
+        data.push(options.bits());
+        data.push(callback_id);
+
+        SerialMessage::new(
+            SerialMessageType::Request,
+            SerialMessageFunction::SendData,
+            &data,
+        )
+    }
+
+    /// Check that the command still fits into a frame
+    fn check_length(self) -> MessageBuilder {
+        let mut length = 2 + self.data.len();
+        if self.endpoint != 0 {
+            length += 4;
+        }
+        if self.security.map(|s| s.is_secure()).unwrap_or(false) {
+            length += S0_OVERHEAD;
+        }
+
+        if length > MAX_COMMAND_LENGTH {
+            return self.fail(format!(
+                "The command is {} bytes long, only {} bytes fit into a frame",
+                length, MAX_COMMAND_LENGTH
+            ));
+        }
+
+        self
+    }
+
+    /// Remember the first error, to return it when the message is built
+    fn fail(mut self, description: String) -> MessageBuilder {
+        if self.error.is_none() {
+            self.error = Some(Error::new(ErrorKind::InvalidInput, description));
+        }
+        self
+    }
+}
+
+/// Error for a part of the message, which was not set
+fn missing(part: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("The {} of the message is missing", part),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint() {
+        let message = MessageBuilder::new()
+            .node(0x05)
+            .cmd_class(CommandClass::SWITCH_BINARY)
+            .cmd(0x01)
+            .byte(0xFF)
+            .endpoint(2)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            message.to_vec(),
+            vec![0x05, 0x07, 0x60, 0x0D, 0x00, 0x02, 0x25, 0x01, 0xFF]
+        );
+    }
+
+    #[test]
+    fn serial() {
+        let options = TransmitOptions {
+            ack: true,
+            ..TransmitOptions::default()
+        };
+        let msg = MessageBuilder::new()
+            .node(0x05)
+            .cmd_class(CommandClass::BASIC)
+            .cmd(0x02)
+            .options(options)
+            .build_serial(0x0A)
+            .unwrap();
+
+        assert_eq!(msg.func, SerialMessageFunction::SendData);
+        assert_eq!(msg.data, vec![0x05, 0x02, 0x20, 0x02, 0x05, 0x0A]);
+    }
+
+    #[test]
+    fn invalid() {
+        // the first error is returned
+        let result = MessageBuilder::new()
+            .node(0)
+            .cmd_class(CommandClass::BASIC)
+            .cmd(0x01)
+            .endpoint(200)
+            .build();
+        assert!(result.unwrap_err().to_string().contains("node id"));
+
+        let result = MessageBuilder::new().node(0x05).cmd(0x01).build();
+        assert!(result.unwrap_err().to_string().contains("command class"));
+
+        let result = MessageBuilder::new()
+            .node(0x05)
+            .cmd_class(CommandClass::BASIC)
+            .cmd(0x01)
+            .payload(&[0x00; 41])
+            .endpoint(1)
+            .build();
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
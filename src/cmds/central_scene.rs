@@ -0,0 +1,276 @@
+//! The Central Scene Command Class is used by remotes and scene keypads to
+//! report which button was pressed, held down or released.
+//!
+//! The notifications are sent unsolicited to the nodes of the lifeline
+//! group. While a button is held down, the node repeats the notification,
+//! the sequence number lets the receiver detect the repeated ones.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// What happened to the button of a scene
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyAttribute {
+    /// The button was pressed the given times in a row, from 1 to 5
+    Pressed(u8),
+    /// The button was released after it was held down
+    Released,
+    /// The button is held down
+    HeldDown,
+}
+
+impl KeyAttribute {
+    /// Decode the key attribute, unknown attributes return `None`
+    pub fn new(value: u8) -> Option<KeyAttribute> {
+        match value {
+            0x00 => Some(KeyAttribute::Pressed(1)),
+            0x01 => Some(KeyAttribute::Released),
+            0x02 => Some(KeyAttribute::HeldDown),
+            0x03..=0x06 => Some(KeyAttribute::Pressed(value - 1)),
+            _ => None,
+        }
+    }
+}
+
+/// A button of a scene was used.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CentralSceneNotification {
+    pub sequence: u8,
+    pub scene: u8,
+    pub key: KeyAttribute,
+    /// The held down notification is repeated every 55 seconds instead of
+    /// every 200 milliseconds
+    pub slow_refresh: bool,
+}
+
+/// The scenes and the key attributes, which the node supports.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CentralSceneSupported {
+    /// The key attributes of each scene, the first entry is the scene 1
+    pub scenes: Vec<Vec<KeyAttribute>>,
+    pub slow_refresh: bool,
+}
+
+/// Central Scene Command Class
+#[derive(Debug, Clone)]
+pub struct CentralScene;
+
+impl CentralScene {
+    /// The Central Scene Supported Get Command is used to request the scenes
+    /// and the key attributes of the node.
+    pub fn supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::CENTRAL_SCENE, 0x01, [])
+    }
+
+    /// The Central Scene Supported Report Command is used to advertise the
+    /// scenes and the key attributes of the node.
+    pub fn supported_report(frame: &IncomingFrame) -> Result<CentralSceneSupported, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_CENTRAL_SCENE(0x5B)       |
+        // |          Command = CENTRAL_SCENE_SUPPORTED_REPORT(0x02)       |
+        // |                       Supported Scenes                        |
+        // | Slow  |        Reserved       | Number of Bytes |  Identical  |
+        // |        Supported Key Attributes for Scene 1 ... Scene n       |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::CENTRAL_SCENE, 0x02)?;
+
+        let count = *payload
+            .first()
+            .ok_or_else(|| Error::parse(CommandClass::CENTRAL_SCENE, "Message is too short"))?
+            as usize;
+
+        // version 1 nodes support the single press, the hold and the release
+        if payload.len() < 2 {
+            let keys = vec![
+                KeyAttribute::Pressed(1),
+                KeyAttribute::Released,
+                KeyAttribute::HeldDown,
+            ];
+            return Ok(CentralSceneSupported {
+                scenes: vec![keys; count],
+                slow_refresh: false,
+            });
+        }
+
+        let identical = payload[1] & 0x01 == 0x01;
+        let size = ((payload[1] >> 1) & 0x03) as usize;
+        let masks = if identical { 1 } else { count };
+
+        let bytes = payload
+            .get(2..2 + masks * size)
+            .ok_or_else(|| Error::parse(CommandClass::CENTRAL_SCENE, "Message is too short"))?;
+
+        let mut scenes: Vec<Vec<KeyAttribute>> = bytes
+            .chunks(size.max(1))
+            .map(|mask| {
+                (0..mask.len() * 8)
+                    .filter(|bit| mask[bit / 8] & (1 << (bit % 8)) != 0)
+                    .filter_map(|bit| KeyAttribute::new(bit as u8))
+                    .collect()
+            })
+            .collect();
+
+        if identical {
+            let keys = scenes.pop().unwrap_or_default();
+            scenes = vec![keys; count];
+        }
+
+        Ok(CentralSceneSupported {
+            scenes,
+            slow_refresh: payload[1] & 0x80 == 0x80,
+        })
+    }
+
+    /// The Central Scene Notification Command is used to advertise, that a
+    /// button of a scene was used.
+    pub fn notification(frame: &IncomingFrame) -> Result<CentralSceneNotification, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_CENTRAL_SCENE(0x5B)       |
+        // |            Command = CENTRAL_SCENE_NOTIFICATION(0x03)         |
+        // |                        Sequence Number                        |
+        // | Slow  |        Reserved       |      Key Attributes           |
+        // |                         Scene Number                          |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::CENTRAL_SCENE, 0x03)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::CENTRAL_SCENE,
+                "Message is too short",
+            ));
+        }
+
+        let key = KeyAttribute::new(payload[1] & 0x07).ok_or_else(|| {
+            Error::parse(
+                CommandClass::CENTRAL_SCENE,
+                format!("Unknown key attribute {:#04x}", payload[1] & 0x07),
+            )
+        })?;
+
+        Ok(CentralSceneNotification {
+            sequence: payload[0],
+            scene: payload[2],
+            key,
+            slow_refresh: payload[1] & 0x80 == 0x80,
+        })
+    }
+
+    /// The Central Scene Configuration Set Command is used to enable the slow
+    /// refresh of the held down notifications.
+    pub fn configuration_set<N>(node_id: N, slow_refresh: bool) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::CENTRAL_SCENE,
+            0x04,
+            [if slow_refresh { 0x80 } else { 0x00 }],
+        )
+    }
+
+    /// The Central Scene Configuration Get Command is used to request, if the
+    /// slow refresh is enabled.
+    pub fn configuration_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::CENTRAL_SCENE, 0x05, [])
+    }
+
+    /// The Central Scene Configuration Report Command is used to advertise,
+    /// if the slow refresh is enabled.
+    pub fn configuration_report(frame: &IncomingFrame) -> Result<bool, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::CENTRAL_SCENE, 0x06)?;
+
+        let value = payload
+            .first()
+            .ok_or_else(|| Error::parse(CommandClass::CENTRAL_SCENE, "Message is too short"))?;
+
+        Ok(value & 0x80 == 0x80)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification() {
+        // the scene 2 was pressed twice
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x5B, 0x03, 0x11, 0x03, 0x02]).unwrap();
+        assert_eq!(
+            CentralScene::notification(&frame),
+            Ok(CentralSceneNotification {
+                sequence: 0x11,
+                scene: 0x02,
+                key: KeyAttribute::Pressed(2),
+                slow_refresh: false,
+            })
+        );
+
+        // the scene 1 is held down
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x5B, 0x03, 0x12, 0x82, 0x01]).unwrap();
+        let notification = CentralScene::notification(&frame).unwrap();
+        assert_eq!(notification.key, KeyAttribute::HeldDown);
+        assert!(notification.slow_refresh);
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x5B, 0x03, 0x13, 0x07, 0x01]).unwrap();
+        assert!(CentralScene::notification(&frame).is_err());
+    }
+
+    #[test]
+    fn supported_report() {
+        // a version 1 node with 2 scenes
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x5B, 0x02, 0x02]).unwrap();
+        let supported = CentralScene::supported_report(&frame).unwrap();
+        assert_eq!(supported.scenes.len(), 2);
+        assert_eq!(supported.scenes[1][2], KeyAttribute::HeldDown);
+
+        // 3 scenes with the same keys, a single and a double press
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x5B, 0x02, 0x03, 0x83, 0x09]).unwrap();
+        assert_eq!(
+            CentralScene::supported_report(&frame),
+            Ok(CentralSceneSupported {
+                scenes: vec![vec![KeyAttribute::Pressed(1), KeyAttribute::Pressed(2)]; 3],
+                slow_refresh: true,
+            })
+        );
+
+        // 2 scenes with different keys
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x5B, 0x02, 0x02, 0x02, 0x01, 0x07]).unwrap();
+        let supported = CentralScene::supported_report(&frame).unwrap();
+        assert_eq!(supported.scenes[0], vec![KeyAttribute::Pressed(1)]);
+        assert_eq!(supported.scenes[1].len(), 3);
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x5B, 0x02, 0x02, 0x02, 0x01]).unwrap();
+        assert!(CentralScene::supported_report(&frame).is_err());
+    }
+
+    #[test]
+    fn configuration() {
+        assert_eq!(CentralScene::configuration_set(0x05, true).data, vec![0x80]);
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x5B, 0x06, 0x80]).unwrap();
+        assert_eq!(CentralScene::configuration_report(&frame), Ok(true));
+    }
+}
@@ -0,0 +1,103 @@
+//! The Clock Command Class is used to set and read the local time of a
+//! node, as the day of the week, the hour and the minute.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// The local time of a node
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockTime {
+    /// The day of the week, 1 is Monday and 7 is Sunday, 0 is unused
+    pub weekday: u8,
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl ClockTime {
+    /// Returns the minutes since the start of the week
+    pub fn minute_of_week(&self) -> u32 {
+        (self.weekday.max(1) as u32 - 1) * 24 * 60 + self.hour as u32 * 60 + self.minute as u32
+    }
+}
+
+/// Clock Command Class
+#[derive(Debug, Clone)]
+pub struct Clock;
+
+impl Clock {
+    /// The Clock Set Command is used to set the local time of the node.
+    pub fn set<N>(node_id: N, time: ClockTime) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |           Command Class = COMMAND_CLASS_CLOCK(0x81)           |
+        // |                  Command = CLOCK_SET(0x04)                    |
+        // |        Weekday        |                 Hour                  |
+        // |                            Minute                             |
+        // -----------------------------------------------------------------
+        if time.weekday > 7 || time.hour > 23 || time.minute > 59 {
+            return Err(Error::parse(
+                CommandClass::CLOCK,
+                "The time is out of range",
+            ));
+        }
+
+        Ok(Message::from_array(
+            node_id.into(),
+            CommandClass::CLOCK,
+            0x04,
+            [(time.weekday << 5) | time.hour, time.minute],
+        ))
+    }
+
+    /// The Clock Get Command is used to request the local time of the node.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::CLOCK, 0x05, [])
+    }
+
+    /// The Clock Report Command is used to advertise the local time of the node.
+    pub fn report(frame: &IncomingFrame) -> Result<ClockTime, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::CLOCK, 0x06)?;
+
+        // the payload need to be at least 2 bytes long
+        if payload.len() < 2 {
+            return Err(Error::parse(CommandClass::CLOCK, "Message is too short"));
+        }
+
+        Ok(ClockTime {
+            weekday: payload[0] >> 5,
+            hour: payload[0] & 0x1F,
+            minute: payload[1],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_report() {
+        let time = ClockTime {
+            weekday: 3,
+            hour: 14,
+            minute: 30,
+        };
+        let msg = Clock::set(0x05, time).unwrap();
+        assert_eq!(msg.data, vec![0x6E, 0x1E]);
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x81, 0x06, 0x6E, 0x1E]).unwrap();
+        assert_eq!(Clock::report(&frame), Ok(time));
+        assert_eq!(time.minute_of_week(), 2 * 24 * 60 + 14 * 60 + 30);
+
+        let time = ClockTime { hour: 24, ..time };
+        assert!(Clock::set(0x05, time).is_err());
+    }
+}
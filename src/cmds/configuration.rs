@@ -0,0 +1,145 @@
+//! The Configuration Command Class is used to read and change the
+//! parameters of a node, which tune its behavior.
+//!
+//! Each parameter has a number and a signed value of 1, 2 or 4 bytes. The
+//! size of a parameter is defined by the manufacturer of the node.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// Configuration Command Class
+#[derive(Debug, Clone)]
+pub struct Configuration;
+
+impl Configuration {
+    /// The Configuration Set Command is used to set the value of a parameter.
+    /// The size need to be 1, 2 or 4 bytes and the value need to fit into it.
+    pub fn set<N>(node_id: N, parameter: u8, value: i32, size: u8) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_CONFIGURATION(0x70)       |
+        // |              Command = CONFIGURATION_SET(0x04)                |
+        // |                      Parameter Number                         |
+        // |Default|       Reserved        |             Size              |
+        // |                   Configuration Value 1 ... n                 |
+        // -----------------------------------------------------------------
+        let (min, max) = match size {
+            1 => (i8::MIN as i32, i8::MAX as i32),
+            2 => (i16::MIN as i32, i16::MAX as i32),
+            4 => (i32::MIN, i32::MAX),
+            _ => {
+                return Err(Error::parse(
+                    CommandClass::CONFIGURATION,
+                    "The size need to be 1, 2 or 4 bytes",
+                ))
+            }
+        };
+        if value < min || value > max {
+            return Err(Error::parse(
+                CommandClass::CONFIGURATION,
+                format!("The value {} doesn't fit into {} bytes", value, size),
+            ));
+        }
+
+        let mut data = vec![parameter, size];
+        data.extend_from_slice(&value.to_be_bytes()[4 - size as usize..]);
+
+        Message::new(node_id.into(), CommandClass::CONFIGURATION, 0x04, &data)
+    }
+
+    /// The Configuration Set Command with the default flag is used to reset
+    /// the parameter to the default value of the manufacturer.
+    pub fn set_default<N>(node_id: N, parameter: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::CONFIGURATION,
+            0x04,
+            [parameter, 0x81, 0x00],
+        )
+    }
+
+    /// The Configuration Get Command is used to request the value of a
+    /// parameter.
+    pub fn get<N>(node_id: N, parameter: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::CONFIGURATION,
+            0x05,
+            [parameter],
+        )
+    }
+
+    /// The Configuration Report Command is used to advertise the value of a
+    /// parameter.
+    ///
+    /// Returns the parameter number and the signed value.
+    pub fn report(frame: &IncomingFrame) -> Result<(u8, i32), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::CONFIGURATION, 0x06)?;
+
+        // the payload need to contain the parameter, the size and the value
+        let size = payload.get(1).map(|s| (s & 0x07) as usize).unwrap_or(0);
+        if payload.len() < 2 + size {
+            return Err(Error::parse(
+                CommandClass::CONFIGURATION,
+                "Message is too short",
+            ));
+        }
+
+        let value = match size {
+            1 => payload[2] as i8 as i32,
+            2 => i16::from_be_bytes([payload[2], payload[3]]) as i32,
+            4 => i32::from_be_bytes([payload[2], payload[3], payload[4], payload[5]]),
+            _ => {
+                return Err(Error::parse(
+                    CommandClass::CONFIGURATION,
+                    format!("The size {} is invalid", size),
+                ))
+            }
+        };
+
+        Ok((payload[0], value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = Configuration::set(0x05, 0x0C, -2, 2).unwrap();
+        assert_eq!(msg.cmd_class, CommandClass::CONFIGURATION);
+        assert_eq!(msg.data, vec![0x0C, 0x02, 0xFF, 0xFE]);
+
+        let msg = Configuration::set(0x05, 0x01, 0x1234_5678, 4).unwrap();
+        assert_eq!(msg.data, vec![0x01, 0x04, 0x12, 0x34, 0x56, 0x78]);
+
+        assert!(Configuration::set(0x05, 0x01, 200, 1).is_err());
+        assert!(Configuration::set(0x05, 0x01, 1, 3).is_err());
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x70, 0x06, 0x0C, 0x01, 0xFB]).unwrap();
+        assert_eq!(Configuration::report(&frame), Ok((0x0C, -5)));
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x70, 0x06, 0x0C, 0x02, 0x01, 0x2C]).unwrap();
+        assert_eq!(Configuration::report(&frame), Ok((0x0C, 300)));
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x70, 0x06, 0x0C, 0x02, 0x01]).unwrap();
+        assert!(Configuration::report(&frame).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! The CRC-16 Encapsulation Command Class is used to protect a command with
+//! a checksum, for the nodes which don't use a security class.
+//!
+//! The checksum is a CRC-CCITT with the initial value `0x1D0F`, calculated
+//! over the whole encapsulation.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+use crate::payload::Payload;
+
+use std::convert::TryFrom;
+
+/// The initial value of the checksum
+const CRC_INIT: u16 = 0x1D0F;
+
+/// The polynomial of the checksum
+const CRC_POLY: u16 = 0x1021;
+
+/// CRC-16 Encapsulation Command Class
+#[derive(Debug, Clone)]
+pub struct Crc16;
+
+impl Crc16 {
+    /// The CRC-16 Encapsulated Command is used to protect the command with
+    /// a checksum.
+    pub fn encap(message: Message) -> Result<Message, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_CRC_16_ENCAP(0x56)        |
+        // |               Command = CRC_16_ENCAP(0x01)                    |
+        // |                         Command Class                         |
+        // |                            Command                            |
+        // |                  Parameter 1 ... Parameter n                  |
+        // |                          Checksum 1                           |
+        // |                          Checksum 2                           |
+        // -----------------------------------------------------------------
+        let mut data = vec![message.cmd_class as u8, message.cmd];
+        data.extend_from_slice(&message.data);
+
+        let mut covered = vec![CommandClass::CRC_16_ENCAP as u8, 0x01];
+        covered.extend_from_slice(&data);
+        let crc = Crc16::checksum(&covered);
+        data.extend_from_slice(&[(crc >> 8) as u8, crc as u8]);
+
+        Ok(Message {
+            node_id: message.node_id,
+            cmd_class: CommandClass::CRC_16_ENCAP,
+            cmd: 0x01,
+            data: Payload::try_from(&data[..])?,
+            raw: Payload::new(),
+        })
+    }
+
+    /// Unwrap the command of a CRC-16 Encapsulated Command, when the
+    /// checksum matches.
+    pub fn decap(frame: &IncomingFrame) -> Result<IncomingFrame, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::CRC_16_ENCAP, 0x01)?;
+
+        // the payload need to be at least 4 bytes long
+        if payload.len() < 4 {
+            return Err(Error::parse(
+                CommandClass::CRC_16_ENCAP,
+                "Message is too short",
+            ));
+        }
+
+        let (command, crc) = payload.split_at(payload.len() - 2);
+        let mut covered = vec![CommandClass::CRC_16_ENCAP as u8, 0x01];
+        covered.extend_from_slice(command);
+        if Crc16::checksum(&covered) != ((crc[0] as u16) << 8) | crc[1] as u16 {
+            return Err(Error::parse(
+                CommandClass::CRC_16_ENCAP,
+                "The checksum doesn't match",
+            ));
+        }
+
+        let command_class = CommandClass::try_from(command[0]).map_err(|_| command[0]);
+
+        Ok(IncomingFrame {
+            status: frame.status,
+            source: frame.source,
+            command_class,
+            command: command[1],
+            payload: command[2..].to_vec(),
+        })
+    }
+
+    /// Decapsulate the frame, when it's protected with a checksum. The other
+    /// frames are returned as they are.
+    pub fn unwrap(frame: IncomingFrame) -> Result<IncomingFrame, Error> {
+        if frame.command_class == Ok(CommandClass::CRC_16_ENCAP) {
+            Crc16::decap(&frame)
+        } else {
+            Ok(frame)
+        }
+    }
+
+    /// Calculate the CRC-CCITT checksum of the data
+    pub fn checksum(data: &[u8]) -> u16 {
+        let mut crc = CRC_INIT;
+
+        for byte in data {
+            crc ^= (*byte as u16) << 8;
+            for _ in 0..8 {
+                crc = if crc & 0x8000 != 0 {
+                    (crc << 1) ^ CRC_POLY
+                } else {
+                    crc << 1
+                };
+            }
+        }
+
+        crc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::basic::Basic;
+
+    #[test]
+    fn encap() {
+        let msg = Crc16::encap(Basic::get(0x05)).unwrap();
+
+        assert_eq!(msg.cmd_class, CommandClass::CRC_16_ENCAP);
+        assert_eq!(msg.data, vec![0x20, 0x02, 0x4D, 0x26]);
+    }
+
+    #[test]
+    fn decap() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x56, 0x01, 0x20, 0x03, 0x63, 0xD0, 0x9D])
+                .unwrap();
+        let frame = Crc16::decap(&frame).unwrap();
+        assert_eq!(Basic::report(&frame), Ok(0x63));
+
+        // a single changed bit is detected
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x56, 0x01, 0x20, 0x03, 0x62, 0xD0, 0x9D])
+                .unwrap();
+        assert!(Crc16::decap(&frame).is_err());
+        assert!(Crc16::unwrap(frame).is_err());
+
+        // the plain frames are passed on
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x20, 0x03, 0x63]).unwrap();
+        assert_eq!(Crc16::unwrap(frame.clone()), Ok(frame));
+    }
+}
@@ -0,0 +1,364 @@
+//! Encapsulation pipeline
+//!
+//! The commands of the command classes don't know how they are sent. The
+//! `Encapsulation` wraps them for the node in a fixed order, from the inside
+//! out:
+//!
+//! `Multi Channel → Supervision → CRC-16 → Security S0/S2 → Transport Service`
+//!
+//! The security class of the node chooses S0 or S2, the commands are
+//! encrypted right before they're sent, because every frame needs a fresh
+//! nonce. The frames of the nodes are unwrapped in the opposite order, so
+//! the command classes only ever see the plain command.
+
+use crate::cmds::crc16::Crc16;
+use crate::cmds::multi_channel::MultiChannel;
+#[cfg(feature = "cc-security")]
+use crate::cmds::security;
+#[cfg(feature = "cc-security")]
+use crate::cmds::security2;
+use crate::cmds::supervision::{self, Supervision};
+use crate::cmds::transport_service::{self, Reassembly, TransportService};
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::defs::SecurityClass;
+use crate::error::{Error, ErrorKind};
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The last session of the supervised commands
+static SUPERVISION_SESSION: AtomicU8 = AtomicU8::new(0);
+
+/// The last session of the segmented commands
+static TRANSPORT_SESSION: AtomicU8 = AtomicU8::new(0);
+
+/// The encapsulations a command is sent with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encapsulation {
+    /// The end point of the node, `0` is the node itself
+    pub endpoint: u8,
+    /// Request the status of the command with a Supervision Get
+    pub supervision: bool,
+    /// Protect the command with a checksum, only used without security
+    pub crc16: bool,
+    pub security: SecurityClass,
+    /// Split the command into segments, when it doesn't fit into a frame
+    pub transport_service: bool,
+}
+
+/// The frames of a wrapped command
+#[derive(Debug, Clone)]
+pub struct Wrapped {
+    /// The frames to send, more than one when the command was segmented
+    pub frames: Vec<Message>,
+    /// The session of the Supervision Get, the node reports the status with
+    pub session: Option<u8>,
+    /// The security class the frames still need to be encrypted with, every
+    /// frame with a fresh nonce
+    pub security: SecurityClass,
+    /// Split the encrypted S2 frame into segments, when it doesn't fit
+    pub transport_service: bool,
+}
+
+/// A command, which was unwrapped from the frame of a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unwrapped {
+    pub frame: IncomingFrame,
+    /// The end point, which sent the command
+    pub endpoint: u8,
+    /// The session of a Supervision Get, the node waits for a report of
+    pub session: Option<u8>,
+    /// The command was protected with a checksum
+    pub crc16: bool,
+    /// The session of the datagram, when the command was segmented
+    pub transport_session: Option<u8>,
+}
+
+impl Encapsulation {
+    /// Create a pipeline, which sends the commands as they are.
+    pub fn new() -> Encapsulation {
+        Encapsulation {
+            endpoint: 0,
+            supervision: false,
+            crc16: false,
+            security: SecurityClass::None,
+            transport_service: false,
+        }
+    }
+
+    /// Choose the encapsulations, which the node supports.
+    pub fn for_node(cmds: &[CommandClass], security: SecurityClass, endpoint: u8) -> Encapsulation {
+        Encapsulation {
+            endpoint,
+            supervision: cmds.contains(&CommandClass::SUPERVISION),
+            crc16: cmds.contains(&CommandClass::CRC_16_ENCAP),
+            security,
+            transport_service: cmds.contains(&CommandClass::TRANSPORT_SERVICE),
+        }
+    }
+
+    /// Returns the bytes, which the encapsulations add to a command. The
+    /// segments of the transport service are not counted.
+    pub fn overhead(&self) -> usize {
+        let mut overhead = 0;
+        if self.endpoint > 0 {
+            overhead += 4;
+        }
+        if self.supervision {
+            overhead += 4;
+        }
+        match self.security {
+            SecurityClass::None if self.crc16 => overhead += 4,
+            SecurityClass::None => {}
+            #[cfg(feature = "cc-security")]
+            SecurityClass::S0 => overhead += security::OVERHEAD + 2,
+            #[cfg(feature = "cc-security")]
+            _ => overhead += security2::OVERHEAD + 2,
+            // the frames to the secure nodes are never sent
+            #[cfg(not(feature = "cc-security"))]
+            _ => {}
+        }
+        overhead
+    }
+
+    /// Wrap the command into the encapsulations of the pipeline.
+    pub fn wrap(&self, message: Message) -> Result<Wrapped, Error> {
+        let mut message = message;
+        let mut session = None;
+
+        if self.endpoint > 0 {
+            message = MultiChannel::encap(message, self.endpoint)?;
+        }
+
+        if self.supervision {
+            let id = next_session(&SUPERVISION_SESSION, supervision::MAX_SESSION_ID);
+            message = Supervision::get(message, id)?;
+            session = Some(id);
+        }
+
+        let mut wrapped = Wrapped {
+            frames: vec![],
+            session,
+            security: self.security,
+            transport_service: self.transport_service,
+        };
+
+        // the security classes protect the command on their own, the nonce
+        // is requested right before the frame is sent
+        match self.security {
+            SecurityClass::None if self.crc16 => message = Crc16::encap(message)?,
+            SecurityClass::None => {}
+            _ => {
+                wrapped.frames.push(message);
+                return Ok(wrapped);
+            }
+        }
+
+        wrapped.frames = wrapped.segment(message)?;
+        Ok(wrapped)
+    }
+
+    /// Unwrap the command of the frame. The segments of a datagram are
+    /// collected by the reassembly, until the datagram is complete.
+    pub fn unwrap(
+        frame: IncomingFrame,
+        reassembly: &mut Reassembly,
+    ) -> Result<Option<Unwrapped>, Error> {
+        Encapsulation::unwrap_with(frame, reassembly, |frame| Ok(Some(frame)))
+    }
+
+    /// Unwrap the command of the frame like `unwrap()`, the encrypted
+    /// frames are handed to `decrypt` after the datagram is complete. It
+    /// returns `None` for the frames, which were handled already.
+    pub fn unwrap_with<F>(
+        frame: IncomingFrame,
+        reassembly: &mut Reassembly,
+        decrypt: F,
+    ) -> Result<Option<Unwrapped>, Error>
+    where
+        F: FnOnce(IncomingFrame) -> Result<Option<IncomingFrame>, Error>,
+    {
+        let mut frame = frame;
+        let mut unwrapped = Unwrapped {
+            frame: frame.clone(),
+            endpoint: 0,
+            session: None,
+            crc16: false,
+            transport_session: None,
+        };
+
+        if frame.command_class == Ok(CommandClass::TRANSPORT_SERVICE) {
+            // the answers to the segments, which were sent to the node
+            if TransportService::is_control(&frame) {
+                return Ok(None);
+            }
+            frame = match reassembly.push(&frame)? {
+                Some(frame) => frame,
+                None => return Ok(None),
+            };
+            unwrapped.transport_session = Some(reassembly.session());
+        }
+
+        frame = match decrypt(frame)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+
+        if frame.command_class == Ok(CommandClass::SECURITY) && frame.command >= 0x81 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The S0 frame needs to be decrypted with the nonce first",
+            ));
+        }
+
+        if frame.command_class == Ok(CommandClass::SECURITY_2) && frame.command == 0x03 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The S2 frame needs to be decrypted with the SPAN first",
+            ));
+        }
+
+        if frame.command_class == Ok(CommandClass::CRC_16_ENCAP) {
+            frame = Crc16::decap(&frame)?;
+            unwrapped.crc16 = true;
+        }
+
+        if frame.command_class == Ok(CommandClass::SUPERVISION) && frame.command == 0x01 {
+            let (session, inner) = Supervision::decap(&frame)?;
+            frame = inner;
+            unwrapped.session = Some(session);
+        }
+
+        if frame.command_class == Ok(CommandClass::MULTI_INSTANCE) && frame.command == 0x0D {
+            let (endpoint, inner) = MultiChannel::decap(&frame)?;
+            frame = inner;
+            unwrapped.endpoint = endpoint;
+        }
+
+        unwrapped.frame = frame;
+        Ok(Some(unwrapped))
+    }
+}
+
+impl Wrapped {
+    /// Split the frame into segments, when it doesn't fit into a frame and
+    /// the node supports the transport service.
+    pub fn segment(&self, message: Message) -> Result<Vec<Message>, Error> {
+        if self.transport_service && TransportService::needs_segments(&message) {
+            let id = next_session(&TRANSPORT_SESSION, transport_service::MAX_SESSION_ID);
+            TransportService::segment(message, id)
+        } else {
+            Ok(vec![message])
+        }
+    }
+}
+
+impl Default for Encapsulation {
+    fn default() -> Encapsulation {
+        Encapsulation::new()
+    }
+}
+
+/// Count the session up, it starts again at 0 after the highest id
+fn next_session(counter: &AtomicU8, max: u8) -> u8 {
+    counter.fetch_add(1, Ordering::Relaxed).wrapping_add(1) & max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::switch_binary::SwitchBinary;
+    use crate::defs::RxStatus;
+
+    #[test]
+    fn wrap() {
+        let cmds = [CommandClass::SUPERVISION, CommandClass::CRC_16_ENCAP];
+        let pipeline = Encapsulation::for_node(&cmds, SecurityClass::None, 2);
+
+        let wrapped = pipeline.wrap(SwitchBinary::set(0x05, true)).unwrap();
+        let session = wrapped.session.unwrap();
+        assert_eq!(wrapped.frames.len(), 1);
+        assert_eq!(
+            wrapped.frames[0].data.len() + 2,
+            SwitchBinary::set(0x05, true).data.len() + 2 + pipeline.overhead()
+        );
+
+        // the command is wrapped from the inside out
+        let crc = &wrapped.frames[0];
+        assert_eq!(crc.cmd_class, CommandClass::CRC_16_ENCAP);
+        assert_eq!(
+            &crc.data[..9],
+            &[0x6C, 0x01, session, 0x07, 0x60, 0x0D, 0x00, 0x02, 0x25]
+        );
+
+        // S0 replaces the checksum and is encrypted, when it's sent
+        let pipeline = Encapsulation::for_node(&cmds, SecurityClass::S0, 0);
+        let wrapped = pipeline.wrap(SwitchBinary::set(0x05, true)).unwrap();
+        assert_eq!(wrapped.security, SecurityClass::S0);
+        assert_eq!(wrapped.frames[0].cmd_class, CommandClass::SUPERVISION);
+
+        // S2 the same, the encrypted frame is segmented when it's sent
+        let cmds = [CommandClass::SUPERVISION, CommandClass::TRANSPORT_SERVICE];
+        let pipeline = Encapsulation::for_node(&cmds, SecurityClass::S2Authenticated, 0);
+        let wrapped = pipeline.wrap(SwitchBinary::set(0x05, true)).unwrap();
+        assert_eq!(wrapped.security, SecurityClass::S2Authenticated);
+        assert_eq!(wrapped.frames.len(), 1);
+        assert_eq!(wrapped.frames[0].cmd_class, CommandClass::SUPERVISION);
+        let data: Vec<u8> = (0..60).collect();
+        let long = Message::new(0x05, CommandClass::SECURITY_2, 0x03, &data).unwrap();
+        assert_eq!(wrapped.segment(long).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn unwrap() {
+        let cmds = [
+            CommandClass::SUPERVISION,
+            CommandClass::CRC_16_ENCAP,
+            CommandClass::TRANSPORT_SERVICE,
+        ];
+        let pipeline = Encapsulation::for_node(&cmds, SecurityClass::None, 0);
+
+        // the report of the third end point, as sent by the node
+        let report = Message::from_array(
+            0x05,
+            CommandClass::MULTI_INSTANCE,
+            0x0D,
+            [0x03, 0x00, 0x25, 0x03, 0xFF],
+        );
+        let wrapped = pipeline.wrap(report).unwrap();
+        let unwrapped = Encapsulation::unwrap(received(&wrapped.frames[0]), &mut Reassembly::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(unwrapped.endpoint, 3);
+        assert_eq!(unwrapped.session, wrapped.session);
+        assert!(unwrapped.crc16);
+        assert_eq!(SwitchBinary::report(&unwrapped.frame), Ok(true));
+
+        // a long command is complete with its last segment
+        let data: Vec<u8> = (0..50).collect();
+        let pipeline = Encapsulation::for_node(&cmds[2..], SecurityClass::None, 0);
+        let wrapped = pipeline
+            .wrap(Message::new(0x05, CommandClass::CONFIGURATION, 0x06, &data).unwrap())
+            .unwrap();
+        assert_eq!(wrapped.frames.len(), 2);
+
+        let mut reassembly = Reassembly::new();
+        let first = Encapsulation::unwrap(received(&wrapped.frames[0]), &mut reassembly);
+        assert_eq!(first, Ok(None));
+        let unwrapped = Encapsulation::unwrap(received(&wrapped.frames[1]), &mut reassembly)
+            .unwrap()
+            .unwrap();
+        assert_eq!(unwrapped.frame.payload, data);
+        assert!(unwrapped.transport_session.is_some());
+    }
+
+    /// Convert the sent frame into a received one
+    fn received(message: &Message) -> IncomingFrame {
+        IncomingFrame {
+            status: RxStatus::default(),
+            source: message.node_id,
+            command_class: Ok(message.cmd_class),
+            command: message.cmd,
+            payload: message.data.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! The Entry Control Command Class is used by keypads to report the entered
+//! PIN codes and the pressed buttons, like arm or disarm, to the controller.
+//!
+//! The keypad sends the notifications unsolicited to the nodes of the
+//! lifeline group, the controller decides what the entry means. The sequence
+//! number lets the receiver detect the repeated notifications.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+use std::convert::TryFrom;
+
+/// What the user did at the keypad
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum EntryEventType {
+    /// The user started to enter the keys
+    Caching = 0x00,
+    /// The keys, which were entered until the cache timed out
+    CachedKeys = 0x01,
+    Enter = 0x02,
+    DisarmAll = 0x03,
+    ArmAll = 0x04,
+    ArmAway = 0x05,
+    ArmHome = 0x06,
+    ExitDelay = 0x07,
+    Arm1 = 0x08,
+    Arm2 = 0x09,
+    Arm3 = 0x0A,
+    Arm4 = 0x0B,
+    Arm5 = 0x0C,
+    Arm6 = 0x0D,
+    Rfid = 0x0E,
+    Bell = 0x0F,
+    Fire = 0x10,
+    Police = 0x11,
+    AlertPanic = 0x12,
+    AlertMedical = 0x13,
+    GateOpen = 0x14,
+    GateClose = 0x15,
+    Lock = 0x16,
+    Unlock = 0x17,
+    Test = 0x18,
+    Cancel = 0x19,
+}
+
+/// The data, which was entered with the event
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryData {
+    None,
+    /// Raw bytes, like the id of a RFID tag
+    Raw(Vec<u8>),
+    /// The keys, like the digits of a PIN code
+    Ascii(String),
+    /// The MD5 hash of the entered keys
+    Md5(Vec<u8>),
+}
+
+/// An entry at the keypad.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryControlNotification {
+    pub sequence: u8,
+    pub event: EntryEventType,
+    pub data: EntryData,
+}
+
+/// Entry Control Command Class
+#[derive(Debug, Clone)]
+pub struct EntryControl;
+
+impl EntryControl {
+    /// The Entry Control Notification Command is used to advertise an entry
+    /// at the keypad.
+    pub fn notification(frame: &IncomingFrame) -> Result<EntryControlNotification, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_ENTRY_CONTROL(0x6F)       |
+        // |            Command = ENTRY_CONTROL_NOTIFICATION(0x01)         |
+        // |                        Sequence Number                        |
+        // |                   Reserved                    |   Data Type   |
+        // |                          Event Type                           |
+        // |                       Event Data Length                       |
+        // |                Event Data 1 ... Event Data N                  |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::ENTRY_CONTROL, 0x01)?;
+
+        if payload.len() < 4 || payload.len() < 4 + payload[3] as usize {
+            return Err(Error::parse(
+                CommandClass::ENTRY_CONTROL,
+                "Message is too short",
+            ));
+        }
+
+        let event = EntryEventType::try_from(payload[2]).map_err(|_| {
+            Error::parse(
+                CommandClass::ENTRY_CONTROL,
+                format!("Unknown event type {:#04x}", payload[2]),
+            )
+        })?;
+
+        let bytes = &payload[4..4 + payload[3] as usize];
+        let data = match payload[1] & 0x03 {
+            _ if bytes.is_empty() => EntryData::None,
+            0x00 => EntryData::None,
+            0x01 => EntryData::Raw(bytes.to_vec()),
+            // the keys are padded to a multiple of 16 bytes
+            0x02 => EntryData::Ascii(
+                bytes
+                    .iter()
+                    .take_while(|b| **b != 0x00 && **b != 0xFF)
+                    .map(|b| *b as char)
+                    .collect(),
+            ),
+            _ => EntryData::Md5(bytes.to_vec()),
+        };
+
+        Ok(EntryControlNotification {
+            sequence: payload[0],
+            event,
+            data,
+        })
+    }
+
+    /// The Entry Control Key Supported Get Command is used to request the
+    /// keys of the keypad.
+    pub fn key_supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::ENTRY_CONTROL, 0x02, [])
+    }
+
+    /// The Entry Control Key Supported Report Command is used to advertise
+    /// the keys of the keypad as ASCII characters.
+    pub fn key_supported_report(frame: &IncomingFrame) -> Result<Vec<char>, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_ENTRY_CONTROL(0x6F)       |
+        // |          Command = ENTRY_CONTROL_KEY_SUPPORTED_REPORT(0x03)   |
+        // |                 Key Supported Bit Mask Length                 |
+        // |         Key Supported Bit Mask 1 ... Bit Mask N               |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::ENTRY_CONTROL, 0x03)?;
+
+        if payload.is_empty() || payload.len() < 1 + payload[0] as usize {
+            return Err(Error::parse(
+                CommandClass::ENTRY_CONTROL,
+                "Message is too short",
+            ));
+        }
+
+        let mut keys = vec![];
+        for (i, mask) in payload[1..1 + payload[0] as usize].iter().enumerate() {
+            for bit in 0..8 {
+                if mask & (1 << bit) != 0 {
+                    keys.push((i * 8 + bit) as u8 as char);
+                }
+            }
+        }
+
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification() {
+        // the PIN 1234 was entered, padded to 16 bytes
+        let mut data = vec![0x00, 0x05, 0x16, 0x6F, 0x01, 0x07, 0x02, 0x02, 0x10];
+        data.extend_from_slice(b"1234");
+        data.extend_from_slice(&[0xFF; 12]);
+        let frame = IncomingFrame::parse(&data).unwrap();
+        assert_eq!(
+            EntryControl::notification(&frame),
+            Ok(EntryControlNotification {
+                sequence: 0x07,
+                event: EntryEventType::Enter,
+                data: EntryData::Ascii("1234".to_string()),
+            })
+        );
+
+        // the arm away button without data
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x6F, 0x01, 0x08, 0x00, 0x05, 0x00]).unwrap();
+        let notification = EntryControl::notification(&frame).unwrap();
+        assert_eq!(notification.event, EntryEventType::ArmAway);
+        assert_eq!(notification.data, EntryData::None);
+
+        // the data is longer than the message
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x6F, 0x01, 0x09, 0x01, 0x0E, 0x04]).unwrap();
+        assert!(EntryControl::notification(&frame).is_err());
+    }
+
+    #[test]
+    fn key_supported_report() {
+        // the digits 0-3
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x0A, 0x6F, 0x03, 0x07, 0, 0, 0, 0, 0, 0, 0x0F])
+                .unwrap();
+        assert_eq!(
+            EntryControl::key_supported_report(&frame),
+            Ok(vec!['0', '1', '2', '3'])
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! The Firmware Update Meta Data Command Class is used to update the firmware
+//! of a node over the air.
+//!
+//! The controller requests the update with the ids and the checksum of the
+//! new image. The node then asks for the fragments of the image one after
+//! another and reports the status, when the image is complete. Since version
+//! 2 each fragment is protected by a CRC-CCITT checksum, like the CRC-16
+//! Encapsulation.
+
+use crate::cmds::crc16::Crc16;
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// The ids and the checksum of the current firmware of a node.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FirmwareMetadata {
+    pub manufacturer_id: u16,
+    pub firmware_id: u16,
+    pub checksum: u16,
+    /// Version 1 and 2 nodes are always upgradable
+    pub upgradable: bool,
+    /// The firmware ids of the other targets, like a second chip
+    pub targets: Vec<u16>,
+    /// The biggest fragment the node accepts, only reported by version 3
+    /// nodes
+    pub max_fragment_size: Option<u16>,
+}
+
+/// The answer of the node to an update request
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum FirmwareRequestStatus {
+    /// The manufacturer or the firmware id doesn't match
+    InvalidCombination = 0x00,
+    /// The update needs to be enabled on the node first
+    RequiresAuthentication = 0x01,
+    InvalidFragmentSize = 0x02,
+    NotUpgradable = 0x03,
+    Accepted = 0xFF,
+}
+
+/// The result of an update, reported by the node
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum FirmwareStatus {
+    /// The checksum of the whole image didn't match
+    ChecksumError = 0x00,
+    /// The node didn't receive the fragments in time
+    DownloadFailed = 0x01,
+    InvalidManufacturerId = 0x02,
+    InvalidFirmwareId = 0x03,
+    InvalidFirmwareTarget = 0x04,
+    InvalidFileHeader = 0x05,
+    InvalidFileHeaderFormat = 0x06,
+    InsufficientMemory = 0x07,
+    InvalidHardwareVersion = 0x08,
+    /// The new firmware is stored, but waits for the activation
+    SuccessWaitingForActivation = 0xFD,
+    /// The node needs to be restarted by the user
+    SuccessRestartManually = 0xFE,
+    /// The node restarts with the new firmware on its own
+    Success = 0xFF,
+}
+
+impl FirmwareStatus {
+    /// Check if the image was stored by the node
+    pub fn is_success(self) -> bool {
+        self as u8 >= 0xFD
+    }
+}
+
+/// Firmware Update Meta Data Command Class
+#[derive(Debug, Clone)]
+pub struct FirmwareUpdate;
+
+impl FirmwareUpdate {
+    /// The Firmware Meta Data Get Command is used to request the ids and the
+    /// checksum of the current firmware.
+    pub fn metadata_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::FIRMWARE_UPDATE_MD, 0x01, [])
+    }
+
+    /// The Firmware Meta Data Report Command is used to advertise the ids and
+    /// the checksum of the current firmware.
+    pub fn metadata_report(frame: &IncomingFrame) -> Result<FirmwareMetadata, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |   Command Class = COMMAND_CLASS_FIRMWARE_UPDATE_MD(0x7A)      |
+        // |          Command = FIRMWARE_MD_REPORT(0x02)                   |
+        // |                  Manufacturer ID 1 ... 2                      |
+        // |                   Firmware 0 ID 1 ... 2                       |
+        // |                 Firmware 0 Checksum 1 ... 2                   |
+        // |                Firmware Upgradable (version 3)                |
+        // |             Number of Firmware Targets (version 3)            |
+        // |              Max Fragment Size 1 ... 2 (version 3)            |
+        // |            Firmware 1 ID 1 ... 2, Firmware n ID ...           |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::FIRMWARE_UPDATE_MD, 0x02)?;
+
+        if payload.len() < 6 {
+            return Err(Error::parse(
+                CommandClass::FIRMWARE_UPDATE_MD,
+                "Message is too short",
+            ));
+        }
+
+        let mut metadata = FirmwareMetadata {
+            manufacturer_id: u16::from_be_bytes([payload[0], payload[1]]),
+            firmware_id: u16::from_be_bytes([payload[2], payload[3]]),
+            checksum: u16::from_be_bytes([payload[4], payload[5]]),
+            upgradable: true,
+            targets: vec![],
+            max_fragment_size: None,
+        };
+
+        // version 3 adds the other targets and the fragment size
+        if payload.len() >= 10 {
+            let targets = payload[7] as usize;
+            if payload.len() < 10 + targets * 2 {
+                return Err(Error::parse(
+                    CommandClass::FIRMWARE_UPDATE_MD,
+                    "Message is too short",
+                ));
+            }
+
+            metadata.upgradable = payload[6] == 0xFF;
+            metadata.max_fragment_size = Some(u16::from_be_bytes([payload[8], payload[9]]));
+            metadata.targets = payload[10..10 + targets * 2]
+                .chunks(2)
+                .map(|id| u16::from_be_bytes([id[0], id[1]]))
+                .collect();
+        }
+
+        Ok(metadata)
+    }
+
+    /// The Firmware Update Meta Data Request Get Command is used to start an
+    /// update. The target 0 is the firmware of the Z-Wave chip.
+    pub fn request_get<N>(
+        node_id: N,
+        metadata: &FirmwareMetadata,
+        checksum: u16,
+        target: u8,
+        fragment_size: u16,
+    ) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |   Command Class = COMMAND_CLASS_FIRMWARE_UPDATE_MD(0x7A)      |
+        // |       Command = FIRMWARE_UPDATE_MD_REQUEST_GET(0x03)          |
+        // |                  Manufacturer ID 1 ... 2                      |
+        // |                     Firmware ID 1 ... 2                       |
+        // |                      Checksum 1 ... 2                         |
+        // |               Firmware Target (version 3)                     |
+        // |              Fragment Size 1 ... 2 (version 3)                |
+        // -----------------------------------------------------------------
+        let firmware_id = match target {
+            0 => metadata.firmware_id,
+            t => metadata
+                .targets
+                .get(t as usize - 1)
+                .cloned()
+                .unwrap_or(metadata.firmware_id),
+        };
+
+        let manufacturer_id = metadata.manufacturer_id.to_be_bytes();
+        let firmware_id = firmware_id.to_be_bytes();
+        let checksum = checksum.to_be_bytes();
+        let fragment_size = fragment_size.to_be_bytes();
+
+        Message::from_array(
+            node_id.into(),
+            CommandClass::FIRMWARE_UPDATE_MD,
+            0x03,
+            [
+                manufacturer_id[0],
+                manufacturer_id[1],
+                firmware_id[0],
+                firmware_id[1],
+                checksum[0],
+                checksum[1],
+                target,
+                fragment_size[0],
+                fragment_size[1],
+            ],
+        )
+    }
+
+    /// The Firmware Update Meta Data Request Report Command is used to
+    /// advertise, if the node accepts the update.
+    pub fn request_report(frame: &IncomingFrame) -> Result<FirmwareRequestStatus, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::FIRMWARE_UPDATE_MD, 0x04)?;
+
+        let status = payload.first().ok_or_else(|| {
+            Error::parse(CommandClass::FIRMWARE_UPDATE_MD, "Message is too short")
+        })?;
+
+        FirmwareRequestStatus::try_from(*status).map_err(|_| {
+            Error::parse(
+                CommandClass::FIRMWARE_UPDATE_MD,
+                format!("Unknown request status {:#04x}", status),
+            )
+        })
+    }
+
+    /// The Firmware Update Meta Data Get Command is used by the node to
+    /// request the fragments of the image.
+    ///
+    /// Returns the amount of fragments and the number of the first one, the
+    /// fragments are numbered from 1.
+    pub fn get(frame: &IncomingFrame) -> Result<(u8, u16), Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |   Command Class = COMMAND_CLASS_FIRMWARE_UPDATE_MD(0x7A)      |
+        // |           Command = FIRMWARE_UPDATE_MD_GET(0x05)              |
+        // |                      Number of Reports                        |
+        // |   0   |                 Report Number 1                       |
+        // |                        Report Number 2                        |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::FIRMWARE_UPDATE_MD, 0x05)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::FIRMWARE_UPDATE_MD,
+                "Message is too short",
+            ));
+        }
+
+        Ok((
+            payload[0],
+            u16::from_be_bytes([payload[1] & 0x7F, payload[2]]),
+        ))
+    }
+
+    /// The Firmware Update Meta Data Report Command is used to send a
+    /// fragment of the image. Version 1 nodes don't expect a checksum.
+    pub fn report<N>(
+        node_id: N,
+        number: u16,
+        last: bool,
+        data: &[u8],
+        checksum: bool,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |   Command Class = COMMAND_CLASS_FIRMWARE_UPDATE_MD(0x7A)      |
+        // |          Command = FIRMWARE_UPDATE_MD_REPORT(0x06)            |
+        // | Last  |                 Report Number 1                       |
+        // |                        Report Number 2                        |
+        // |                     Data 1 ... Data n                         |
+        // |                 Checksum 1 ... 2 (version 2)                  |
+        // -----------------------------------------------------------------
+        let number = number & 0x7FFF;
+        let mut payload = vec![
+            (number >> 8) as u8 | if last { 0x80 } else { 0x00 },
+            number as u8,
+        ];
+        payload.extend_from_slice(data);
+
+        // the checksum covers the whole command
+        if checksum {
+            let mut covered = vec![CommandClass::FIRMWARE_UPDATE_MD as u8, 0x06];
+            covered.extend_from_slice(&payload);
+            payload.extend_from_slice(&Crc16::checksum(&covered).to_be_bytes());
+        }
+
+        Message::new(
+            node_id.into(),
+            CommandClass::FIRMWARE_UPDATE_MD,
+            0x06,
+            &payload,
+        )
+    }
+
+    /// The Firmware Update Meta Data Status Report Command is used by the
+    /// node to advertise the result of the update.
+    ///
+    /// Version 3 nodes also report the time until they are available again.
+    pub fn status_report(
+        frame: &IncomingFrame,
+    ) -> Result<(FirmwareStatus, Option<Duration>), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::FIRMWARE_UPDATE_MD, 0x07)?;
+
+        let status = payload.first().ok_or_else(|| {
+            Error::parse(CommandClass::FIRMWARE_UPDATE_MD, "Message is too short")
+        })?;
+
+        let status = FirmwareStatus::try_from(*status).map_err(|_| {
+            Error::parse(
+                CommandClass::FIRMWARE_UPDATE_MD,
+                format!("Unknown update status {:#04x}", status),
+            )
+        })?;
+
+        let wait = payload
+            .get(1..3)
+            .map(|w| Duration::from_secs(u16::from_be_bytes([w[0], w[1]]) as u64));
+
+        Ok((status, wait))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_report() {
+        // a version 1 report
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x08, 0x7A, 0x02, 0x01, 0x0F, 0x02, 0x01, 0xAB, 0xCD,
+        ])
+        .unwrap();
+        let metadata = FirmwareUpdate::metadata_report(&frame).unwrap();
+        assert_eq!(metadata.manufacturer_id, 0x010F);
+        assert_eq!(metadata.firmware_id, 0x0201);
+        assert_eq!(metadata.checksum, 0xABCD);
+        assert!(metadata.upgradable);
+        assert_eq!(metadata.max_fragment_size, None);
+
+        // a version 3 report with a second target
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0E, 0x7A, 0x02, 0x01, 0x0F, 0x02, 0x01, 0xAB, 0xCD, 0xFF, 0x01, 0x00,
+            0x20, 0x03, 0x01,
+        ])
+        .unwrap();
+        let metadata = FirmwareUpdate::metadata_report(&frame).unwrap();
+        assert_eq!(metadata.max_fragment_size, Some(0x20));
+        assert_eq!(metadata.targets, vec![0x0301]);
+
+        let msg = FirmwareUpdate::request_get(0x05, &metadata, 0x1234, 1, 0x20);
+        assert_eq!(
+            msg.data,
+            vec![0x01, 0x0F, 0x03, 0x01, 0x12, 0x34, 0x01, 0x00, 0x20]
+        );
+    }
+
+    #[test]
+    fn fragments() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x7A, 0x05, 0x02, 0x01, 0x03]).unwrap();
+        assert_eq!(FirmwareUpdate::get(&frame), Ok((2, 0x0103)));
+
+        let msg = FirmwareUpdate::report(0x05, 0x0103, true, &[0xAA, 0xBB], false).unwrap();
+        assert_eq!(msg.data, vec![0x81, 0x03, 0xAA, 0xBB]);
+
+        // the checksum is appended
+        let msg = FirmwareUpdate::report(0x05, 1, false, &[0xAA, 0xBB], true).unwrap();
+        let mut covered = vec![0x7A, 0x06, 0x00, 0x01, 0xAA, 0xBB];
+        assert_eq!(msg.data[..4], covered[2..]);
+        covered.extend_from_slice(&msg.data[4..]);
+        assert_eq!(Crc16::checksum(&covered), 0x0000);
+    }
+
+    #[test]
+    fn status_report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x7A, 0x07, 0xFF, 0x00, 0x1E]).unwrap();
+        assert_eq!(
+            FirmwareUpdate::status_report(&frame),
+            Ok((FirmwareStatus::Success, Some(Duration::from_secs(30))))
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x7A, 0x07, 0x00]).unwrap();
+        let (status, wait) = FirmwareUpdate::status_report(&frame).unwrap();
+        assert!(!status.is_success());
+        assert_eq!(wait, None);
+    }
+}
@@ -0,0 +1,251 @@
+//! The Humidity Control Setpoint Command Class is used to configure the
+//! humidity, which a humidifier or a dehumidifier keeps.
+//!
+//! The setpoints are encoded like the sensor values, with a precision, a
+//! scale and a size in front of the value. The scale is either the relative
+//! humidity in percent or the absolute humidity in g/m³.
+
+use crate::cmds::{
+    decode_value, encode_value, precision_scale_size, CommandClass, IncomingFrame, Message,
+    SensorValue, Unit,
+};
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+
+/// The setpoint types of the humidity control
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum HumiditySetpointType {
+    Humidifier = 0x01,
+    Dehumidifier = 0x02,
+    Auto = 0x03,
+}
+
+/// A setpoint with the precision it was reported with
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HumiditySetpoint {
+    pub setpoint_type: HumiditySetpointType,
+    pub value: SensorValue,
+    /// The amount of decimals of the value
+    pub precision: u8,
+}
+
+/// Humidity Control Setpoint Command Class
+#[derive(Debug, Clone)]
+pub struct HumidityControlSetpoint;
+
+impl HumidityControlSetpoint {
+    /// The Humidity Control Setpoint Set Command is used to set the humidity
+    /// of the setpoint type. The value is sent with the amount of decimals
+    /// of the precision.
+    pub fn set<N>(
+        node_id: N,
+        setpoint_type: HumiditySetpointType,
+        value: SensorValue,
+        precision: u8,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // | Command Class = COMMAND_CLASS_HUMIDITY_CONTROL_SETPOINT(0x64) |
+        // |        Command = HUMIDITY_CONTROL_SETPOINT_SET(0x01)          |
+        // |           Reserved            |        Setpoint Type          |
+        // |       Precision       |     Scale     |         Size          |
+        // |                     Value 1 ... Value n                       |
+        // -----------------------------------------------------------------
+        let scale = match value.unit {
+            Unit::RelativeHumidity | Unit::Percent => 0x00,
+            Unit::AbsoluteHumidity => 0x01,
+            unit => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The unit {:?} is not a humidity", unit),
+                ))
+            }
+        };
+
+        let bytes = encode_value(value.value, precision)?;
+        let mut data = vec![
+            setpoint_type as u8,
+            precision << 5 | scale << 3 | bytes.len() as u8,
+        ];
+        data.extend_from_slice(&bytes);
+
+        Message::new(
+            node_id.into(),
+            CommandClass::HUMIDITY_CONTROL_SETPOINT,
+            0x01,
+            &data,
+        )
+    }
+
+    /// The Humidity Control Setpoint Get Command is used to request the
+    /// humidity of the setpoint type.
+    pub fn get<N>(node_id: N, setpoint_type: HumiditySetpointType) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::HUMIDITY_CONTROL_SETPOINT,
+            0x02,
+            [setpoint_type as u8],
+        )
+    }
+
+    /// The Humidity Control Setpoint Report Command is used to advertise
+    /// the humidity of a setpoint type.
+    pub fn report(frame: &IncomingFrame) -> Result<HumiditySetpoint, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // | Command Class = COMMAND_CLASS_HUMIDITY_CONTROL_SETPOINT(0x64) |
+        // |       Command = HUMIDITY_CONTROL_SETPOINT_REPORT(0x03)        |
+        // |           Reserved            |        Setpoint Type          |
+        // |       Precision       |     Scale     |         Size          |
+        // |                     Value 1 ... Value n                       |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::HUMIDITY_CONTROL_SETPOINT, 0x03)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::HUMIDITY_CONTROL_SETPOINT,
+                "Message is too short",
+            ));
+        }
+
+        let (precision, scale, size) = precision_scale_size(payload[1]);
+        let size = size as usize;
+        if payload.len() < 2 + size {
+            return Err(Error::parse(
+                CommandClass::HUMIDITY_CONTROL_SETPOINT,
+                "Message is too short",
+            ));
+        }
+
+        let setpoint_type = HumiditySetpointType::try_from(payload[0] & 0x0F).map_err(|_| {
+            Error::parse(
+                CommandClass::HUMIDITY_CONTROL_SETPOINT,
+                format!("Unknown setpoint type {:#04x}", payload[0]),
+            )
+        })?;
+        let unit = match scale {
+            0x00 => Unit::RelativeHumidity,
+            0x01 => Unit::AbsoluteHumidity,
+            _ => {
+                return Err(Error::parse(
+                    CommandClass::HUMIDITY_CONTROL_SETPOINT,
+                    format!("The scale {} is not supported", scale),
+                ))
+            }
+        };
+
+        Ok(HumiditySetpoint {
+            setpoint_type,
+            value: SensorValue::new(decode_value(&payload[2..2 + size], precision), unit),
+            precision,
+        })
+    }
+
+    /// The Humidity Control Setpoint Supported Get Command is used to
+    /// request the setpoint types of the node.
+    pub fn supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::HUMIDITY_CONTROL_SETPOINT,
+            0x04,
+            [],
+        )
+    }
+
+    /// The Humidity Control Setpoint Supported Report Command is used to
+    /// advertise the setpoint types of the node. Unknown types are skipped.
+    pub fn supported_report(frame: &IncomingFrame) -> Result<Vec<HumiditySetpointType>, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // | Command Class = COMMAND_CLASS_HUMIDITY_CONTROL_SETPOINT(0x64) |
+        // |   Command = HUMIDITY_CONTROL_SETPOINT_SUPPORTED_REPORT(0x05)  |
+        // |                  Bit Mask 1 ... Bit Mask N                    |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::HUMIDITY_CONTROL_SETPOINT, 0x05)?;
+
+        let mut types = vec![];
+        for (i, mask) in payload.iter().enumerate() {
+            for bit in 0..8 {
+                if mask & (1 << bit) != 0 {
+                    if let Ok(typ) = HumiditySetpointType::try_from((i * 8 + bit) as u8) {
+                        types.push(typ);
+                    }
+                }
+            }
+        }
+
+        Ok(types)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = HumidityControlSetpoint::set(
+            0x05,
+            HumiditySetpointType::Dehumidifier,
+            SensorValue::new(55.5, Unit::RelativeHumidity),
+            1,
+        )
+        .unwrap();
+        assert_eq!(msg.data, vec![0x02, 0x22, 0x02, 0x2B]);
+
+        assert!(HumidityControlSetpoint::set(
+            0x05,
+            HumiditySetpointType::Auto,
+            SensorValue::new(21.0, Unit::Celsius),
+            0,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x64, 0x03, 0x01, 0x09, 0x0C]).unwrap();
+        assert_eq!(
+            HumidityControlSetpoint::report(&frame),
+            Ok(HumiditySetpoint {
+                setpoint_type: HumiditySetpointType::Humidifier,
+                value: SensorValue::new(12.0, Unit::AbsoluteHumidity),
+                precision: 0,
+            })
+        );
+
+        // the value is missing
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x64, 0x03, 0x01, 0x22]).unwrap();
+        assert!(HumidityControlSetpoint::report(&frame).is_err());
+    }
+
+    #[test]
+    fn supported_report() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x64, 0x05, 0x06]).unwrap();
+        assert_eq!(
+            HumidityControlSetpoint::supported_report(&frame),
+            Ok(vec![
+                HumiditySetpointType::Humidifier,
+                HumiditySetpointType::Dehumidifier
+            ])
+        );
+    }
+}
@@ -1,7 +1,7 @@
-use cmds::{CommandClass, Message};
-use driver::GenericType;
-use error::{Error, ErrorKind};
-use num::FromPrimitive;
+use crate::cmds::{CommandClass, Message};
+use crate::defs::GenericType;
+use crate::error::{Error, ErrorKind};
+use std::convert::TryFrom;
 
 #[derive(Debug, Clone)]
 pub struct NodeInfo;
@@ -10,7 +10,7 @@ impl NodeInfo {
     /// Generate the message for the basic Command Class with
     /// the function to get a value.
     pub fn get(node_id: u8) -> Message {
-        Message::new(node_id, CommandClass::NODE_INFO, 0x02, vec![])
+        Message::from_array(node_id, CommandClass::NODE_INFO, 0x02, [])
     }
 
     /// Read a the Node_Information message and parse it to the type and command
@@ -30,7 +30,7 @@ impl NodeInfo {
             let m = msg
                 .get(i as usize)
                 .ok_or(Error::new(ErrorKind::UnknownZWave, "Message is too short"))?;
-            let m = m.clone();
+            let m = *m;
 
             // when the device is unkown continue
             if m == GenericType::Unknown as u8 {
@@ -38,7 +38,7 @@ impl NodeInfo {
             }
 
             // try to convert the type
-            match GenericType::from_u8(m) {
+            match GenericType::try_from(m).ok() {
                 // When the type is known push it to the vec
                 Some(t) => {
                     types.push(t);
@@ -54,12 +54,12 @@ impl NodeInfo {
         for i in 6..msg.len() {
             // get the command for the vector
             let m = msg
-                .get(i as usize)
+                .get(i)
                 .ok_or(Error::new(ErrorKind::UnknownZWave, "Message is too short"))?;
-            let m = m.clone();
+            let m = *m;
 
             // try to convert the command
-            let cmd = CommandClass::from_u8(m.clone()).unwrap_or(CommandClass::NO_OPERATION);
+            let cmd = CommandClass::try_from(m).unwrap_or(CommandClass::NO_OPERATION);
 
             // when the device is unkown continue
             if cmd == CommandClass::NO_OPERATION {
@@ -6,52 +6,38 @@
 //! meter or energy metering devices and transferring that data to a central database for billing
 //! and/or analyzing.
 
-use cmds::{CommandClass, Message, MeterData};
-use enum_primitive::FromPrimitive;
-use error::{Error, ErrorKind};
-use num::PrimInt;
-
-enum_from_primitive! {
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[allow(non_camel_case_types)]
-/// List of the different meter types.
-enum MeterType {
-Electric = 0x01,
-Gas = 0x02,
-Water = 0x03,
-}}
-
-enum_from_primitive! {
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[allow(non_camel_case_types)]
-/// List of the different electric meter values.
-enum ElectricMeter {
-    kWh = 0x00,
-    kVAh = 0x01,
-    W = 0x02,
-    PulseCount = 0x03,
-}}
-
-enum_from_primitive! {
+use crate::cmds::{
+    decode_value, precision_scale_size, CommandClass, IncomingFrame, Message, MeterData, MeterKind,
+    MeterUnit, RateType,
+};
+use crate::error::Error;
+use std::convert::TryFrom;
+
+/// The capabilities of a meter.
 #[derive(Copy, Clone, Debug, PartialEq)]
-#[allow(non_camel_case_types)]
-/// List of the different gas meter values.
-enum GasMeter {
-    CubicMeters = 0x00,
-    CubicFeet = 0x01,
-    PulseCount = 0x03,
-}}
-
-enum_from_primitive! {
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[allow(non_camel_case_types)]
-/// List of the different water meter values.
-enum WaterMeter {
-    CubicMeters = 0x00,
-    CubicFeet = 0x01,
-    USGallons = 0x02,
-    PulseCount = 0x03,
-}}
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterSupported {
+    pub kind: MeterKind,
+    /// The meter can be reset to zero
+    pub reset: bool,
+    /// The bit mask of the supported scales, the extended scales start at bit 7
+    pub scales: u32,
+}
+
+impl MeterSupported {
+    /// Return the units of the supported scales
+    pub fn units(&self) -> Vec<MeterUnit> {
+        (0..32)
+            .filter(|scale| self.scales & (1 << scale) != 0)
+            .filter_map(|scale| MeterUnit::from_scale(self.kind, scale))
+            .collect()
+    }
+
+    /// Check if the meter supports the unit
+    pub fn supports(&self, unit: MeterUnit) -> bool {
+        self.units().contains(&unit)
+    }
+}
 
 #[derive(Debug, Clone)]
 /// Meter Command Class
@@ -69,35 +55,41 @@ impl Meter {
         // |            Command Class = COMMAND_CLASS_METER(0x32)          |
         // |                    Command = METER_GET(0x01)                  |
         // -----------------------------------------------------------------
-        Message::new(node_id.into(), CommandClass::METER, 0x01, vec![])
+        Message::from_array(node_id.into(), CommandClass::METER, 0x01, [])
     }
 
     /// The Meter Get Command is used to request the accumulated consumption in physical units
     /// from a metering device.
-    pub fn get_v2<N, S>(node_id: N, scale: S) -> Message
+    ///
+    /// Extended scales, like kVarh, are requested with the scale 2 byte of version 4.
+    pub fn get_v2<N, S>(node_id: N, unit: S) -> Message
     where
         N: Into<u8>,
-        S: Into<MeterData>,
+        S: Into<MeterUnit>,
     {
         // _________________________________________________________________
         // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
         // |            Command Class = COMMAND_CLASS_METER(0x32)          |
         // |                    Command = METER_GET(0x01)                  |
-        // |        Reserved       |      Scale    |        Reserved       |
+        // |   Rate Type   |         Scale         |        Reserved       |
+        // |                            Scale 2                            |
         // -----------------------------------------------------------------
-        Message::new(
-            node_id.into(),
-            CommandClass::METER,
-            0x01,
-            vec![(scale.into().get_scale() << 3)],
-        )
+        let scale = unit.into().get_scale();
+
+        if scale < 0x07 {
+            Message::from_array(node_id.into(), CommandClass::METER, 0x01, [scale << 3])
+        } else {
+            Message::from_array(
+                node_id.into(),
+                CommandClass::METER,
+                0x01,
+                [0x07 << 3, scale - 0x07],
+            )
+        }
     }
 
     /// The Meter Report Command is used to advertise a meter reading.
-    pub fn report<M>(msg: M) -> Result<MeterData, Error>
-    where
-        M: Into<Vec<u8>>,
-    {
+    pub fn report(frame: &IncomingFrame) -> Result<MeterData, Error> {
         // _________________________________________________________________
         // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
         // |            Command Class = COMMAND_CLASS_METER(0x32)          |
@@ -109,56 +101,43 @@ impl Meter {
         // |                         Meter Value n                         |
         // -----------------------------------------------------------------
 
-        // get the message
-        let msg = msg.into();
-
-        // the message need to be exact 6 digits long
-        if msg.len() < 8 {
-            return Err(Error::new(ErrorKind::UnknownZWave, "Message is to short"));
-        }
-
         // check the CommandClass and command
-        if msg[3] != CommandClass::METER as u8 || msg[4] != 0x02 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                "Answer contained wrong command class",
-            ));
+        let payload = frame.expect(CommandClass::METER, 0x02)?;
+
+        // the payload need to be at least 3 bytes long
+        if payload.len() < 3 {
+            return Err(Error::parse(CommandClass::METER, "Message is to short"));
         }
 
         // get the meter type
-        let typ = MeterType::from_u8(msg[5]).ok_or(Error::new(
-            ErrorKind::UnknownZWave,
-            "Answer contained wrong meter type",
-        ))?;
+        let (rate, typ) = Meter::get_rate_meter_type(payload[0])?;
 
         // get the precission
-        let (precision, scale, size) = Meter::get_precision_scale_size(msg[6]);
+        let (precision, scale, size) = Meter::get_precision_scale_size(payload[1]);
+        let scale = Meter::get_scale_bit(payload[0]) | scale;
 
         // check the message length coorectly
-        if msg.len() != 7 + size as usize {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
+        if payload.len() != 2 + size as usize {
+            return Err(Error::parse(
+                CommandClass::METER,
                 "Message has the wrong length",
             ));
         }
 
         // get the value
-        let value = Meter::calc_value(&msg[7..7 + size as usize], precision);
+        let value = Meter::calc_value(&payload[2..2 + size as usize], precision);
 
         // return the value in MeterData format
-        Meter::to_meter_data(value, typ, scale)
+        Meter::to_meter_data(value, typ, rate, scale)
     }
 
     /// The Meter Report Command is used to advertise a meter reading.
-    pub fn report_v2<M>(msg: M) -> Result<(MeterData, u16, MeterData), Error>
-    where
-        M: Into<Vec<u8>>,
-    {
+    pub fn report_v2(frame: &IncomingFrame) -> Result<(MeterData, u16, MeterData), Error> {
         // _________________________________________________________________
         // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
         // |            Command Class = COMMAND_CLASS_METER(0x32)          |
         // |                    Command = METER_GET(0x01)                  |
-        // |  none |   Rate Type   |              Meter Type               |
+        // | Scale |   Rate Type   |              Meter Type               |
         // |       Precision       |      Scale    |          Size         |
         // |                         Meter Value 1                         |
         // |                              ...                              |
@@ -168,141 +147,155 @@ impl Meter {
         // |                     Previous Meter Value 1                    |
         // |                              ...                              |
         // |                     Previous Meter Value n                    |
+        // |                            Scale 2                            |
         // -----------------------------------------------------------------
 
-        // get the message
-        let msg = msg.into();
-
-        println!("Message {:?}", msg);
-
-        // the message need to be exact 6 digits long
-        if msg.len() < 8 {
-            return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
-        }
-
         // check the CommandClass and command
-        if msg[3] != CommandClass::METER as u8 || msg[4] != 0x02 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                "Answer contained wrong command class",
-            ));
+        let payload = frame.expect(CommandClass::METER, 0x02)?;
+
+        // the payload need to be at least 3 bytes long
+        if payload.len() < 3 {
+            return Err(Error::parse(CommandClass::METER, "Message is too short"));
         }
 
         // get the meter type
-        let (_, typ) = Meter::get_rate_meter_type(msg[5])?;
+        let (rate, typ) = Meter::get_rate_meter_type(payload[0])?;
 
         // get the precission, scale and size
-        let (precision, scale, size) = Meter::get_precision_scale_size(msg[6]);
+        let (precision, scale, size) = Meter::get_precision_scale_size(payload[1]);
+        let size = size as usize;
+        let mut scale = Meter::get_scale_bit(payload[0]) | scale;
 
         // check the message length coorectly
-        if msg.len() < 9 + size as usize {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
+        if payload.len() < 4 + size {
+            return Err(Error::parse(
+                CommandClass::METER,
                 "Message has the wrong length",
             ));
         }
 
         // get the value
-        let value = Meter::calc_value(&msg[7..7 + size as usize], precision);
+        let value = Meter::calc_value(&payload[2..2 + size], precision);
 
         // get the time between this and the last report
-        let time = ((msg[7 + size as usize] as u16) << 8) | msg[8 + size as usize] as u16;
+        let time = ((payload[2 + size] as u16) << 8) | payload[3 + size] as u16;
 
         // get the pre value
-        let pre_value;
-        if time == 0x00 || msg.len() < 10 + (2 * size) as usize {
-            pre_value = 0.0;
+        let pre_value = if time == 0x00 || payload.len() < 4 + 2 * size {
+            0.0
         } else {
-            pre_value = Meter::calc_value(
-                &msg[10 + size as usize..10 + (2 * size) as usize],
-                precision,
-            );
+            Meter::calc_value(&payload[4 + size..4 + 2 * size], precision)
+        };
+
+        // the extended scales are defined by the scale 2 byte at the end
+        if scale == 0x07 && payload.len() > 4 + 2 * size {
+            scale += payload[4 + 2 * size];
         }
 
         // return the value in MeterData format
         Ok((
-            Meter::to_meter_data(pre_value, typ, scale)?,
+            Meter::to_meter_data(pre_value, typ, rate, scale)?,
             time,
-            Meter::to_meter_data(value, typ, scale)?,
+            Meter::to_meter_data(value, typ, rate, scale)?,
         ))
     }
 
-    // extract the precision, scale and size as bit information
-    fn get_precision_scale_size(input: u8) -> (u8, u8, u8) {
-        (
-            (input >> 5),
-            ((input >> 3) & 0b00000011),
-            (input & 0b00000111),
-        )
+    /// The Meter Supported Get Command is used to request the meter type, the supported scales
+    /// and if the meter can be reset.
+    pub fn supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |            Command Class = COMMAND_CLASS_METER(0x32)          |
+        // |               Command = METER_SUPPORTED_GET(0x03)             |
+        // -----------------------------------------------------------------
+        Message::from_array(node_id.into(), CommandClass::METER, 0x03, [])
     }
 
-    /// generate the value out of the scale and byte vector
-    fn calc_value(bytes: &[u8], precision: u8) -> f64 {
-        // pow the prevision and set as f64
-        let precision = (10.pow(precision as u32)) as f64;
+    /// The Meter Supported Report Command is used to advertise the meter type, the supported
+    /// scales and if the meter can be reset.
+    pub fn supported_report(frame: &IncomingFrame) -> Result<MeterSupported, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |            Command Class = COMMAND_CLASS_METER(0x32)          |
+        // |             Command = METER_SUPPORTED_REPORT(0x04)            |
+        // | Reset |   Rate Type   |              Meter Type               |
+        // |  MST  |                  Scale Supported 0                    |
+        // |             Number of Scale Supported Bytes to Follow         |
+        // |          Scale Supported 1 ... Scale Supported n              |
+        // -----------------------------------------------------------------
 
-        // transform for one byte
-        if bytes.len() == 1 {
-            return (bytes[0] as i8) as f64 / precision;
-        }
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::METER, 0x04)?;
 
-        // transform for two bytes
-        if bytes.len() == 2 {
-            return (((bytes[0] as i16) << 8) | bytes[1] as i16) as f64 / precision;
+        // the payload need to be at least 2 bytes long
+        if payload.len() < 2 {
+            return Err(Error::parse(CommandClass::METER, "Message is too short"));
         }
 
-        // transform for four bytes
-        if bytes.len() == 4 {
-            return (((((bytes[0] as i32) << 24) | (bytes[1] as i32) << 16)
-                | (bytes[2] as i32) << 8)
-                | (bytes[3] as i32)) as f64
-                / precision;
+        let (_, kind) = Meter::get_rate_meter_type(payload[0])?;
+        let mut scales = (payload[1] & 0b01111111) as u32;
+
+        // the extended scales of version 4 follow, when MST is set
+        if payload[1] & 0b10000000 != 0 && payload.len() > 2 {
+            let len = payload[2] as usize;
+            let masks = payload.get(3..3 + len).ok_or_else(|| {
+                Error::parse(CommandClass::METER, "The scale bytes are too short")
+            })?;
+
+            for (i, mask) in masks.iter().take(3).enumerate() {
+                scales |= (*mask as u32) << (7 + 8 * i);
+            }
         }
 
-        0.0
+        Ok(MeterSupported {
+            kind,
+            reset: payload[0] & 0b10000000 != 0,
+            scales,
+        })
     }
 
-    /// format the value into the right MeterData format
-    fn to_meter_data(data: f64, typ: MeterType, scale: u8) -> Result<MeterData, Error> {
-        if typ == MeterType::Electric && scale == ElectricMeter::kWh as u8 {
-            return Ok(MeterData::Electric_kWh(data));
-        } else if typ == MeterType::Electric && scale == ElectricMeter::kVAh as u8 {
-            return Ok(MeterData::Electric_kVAh(data));
-        } else if typ == MeterType::Electric && scale == ElectricMeter::W as u8 {
-            return Ok(MeterData::Electric_W(data));
-        } else if typ == MeterType::Electric && scale == ElectricMeter::PulseCount as u8 {
-            return Ok(MeterData::Electric_PulseCount(data));
-        } else if typ == MeterType::Gas && scale == GasMeter::CubicMeters as u8 {
-            return Ok(MeterData::Gas_meter2(data));
-        } else if typ == MeterType::Gas && scale == GasMeter::CubicFeet as u8 {
-            return Ok(MeterData::Gas_feet2(data));
-        } else if typ == MeterType::Gas && scale == GasMeter::PulseCount as u8 {
-            return Ok(MeterData::Gas_PulseCount(data));
-        } else if typ == MeterType::Water && scale == WaterMeter::CubicMeters as u8 {
-            return Ok(MeterData::Water_meter2(data));
-        } else if typ == MeterType::Water && scale == WaterMeter::CubicFeet as u8 {
-            return Ok(MeterData::Water_feet2(data));
-        } else if typ == MeterType::Water && scale == WaterMeter::USGallons as u8 {
-            return Ok(MeterData::Water_Gallons(data));
-        } else if typ == MeterType::Water && scale == WaterMeter::PulseCount as u8 {
-            return Ok(MeterData::Water_PulseCount(data));
-        }
+    // extract the precision, scale and size as bit information
+    fn get_precision_scale_size(input: u8) -> (u8, u8, u8) {
+        precision_scale_size(input)
+    }
 
-        // return error if no right match was found
-        Err(Error::new(
-            ErrorKind::UnknownZWave,
-            "The meter value can't be created",
-        ))
+    /// generate the value out of the scale and byte vector
+    fn calc_value(bytes: &[u8], precision: u8) -> f64 {
+        decode_value(bytes, precision)
+    }
+
+    /// format the value into the right MeterData format
+    fn to_meter_data(
+        data: f64,
+        typ: MeterKind,
+        rate: RateType,
+        scale: u8,
+    ) -> Result<MeterData, Error> {
+        let unit = MeterUnit::from_scale(typ, scale)
+            .ok_or_else(|| Error::parse(CommandClass::METER, "The meter value can't be created"))?;
+
+        Ok(MeterData {
+            value: data,
+            kind: typ,
+            unit,
+            rate_type: rate,
+        })
     }
 
-    fn get_rate_meter_type(input: u8) -> Result<(u8, MeterType), Error> {
-        let typ = MeterType::from_u8(input & 0b00011111).ok_or(Error::new(
-            ErrorKind::UnknownZWave,
-            "Answer contained wrong meter type",
-        ))?;
-        let rate = (input >> 5) & 0b00000011;
+    fn get_rate_meter_type(input: u8) -> Result<(RateType, MeterKind), Error> {
+        let typ = MeterKind::try_from(input & 0b00011111)
+            .map_err(|_| Error::parse(CommandClass::METER, "Answer contained wrong meter type"))?;
+        let rate = RateType::try_from((input >> 5) & 0b00000011).unwrap_or(RateType::Unspecified);
         Ok((rate, typ))
     }
+
+    // the third scale bit is part of the meter type byte since version 3
+    fn get_scale_bit(input: u8) -> u8 {
+        (input & 0b10000000) >> 5
+    }
 }
 
 #[cfg(test)]
@@ -354,4 +347,54 @@ mod tests {
             Meter::calc_value(&[0x80, 0x00, 0x00, 0x00], 2)
         );
     }
+
+    #[test]
+    fn supported_report() {
+        // resettable electric meter with kWh and W
+        let supported = Meter::supported_report(
+            &IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x32, 0x04, 0x81, 0x05]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(supported.kind, MeterKind::Electric);
+        assert!(supported.reset);
+        assert_eq!(supported.units(), vec![MeterUnit::kWh, MeterUnit::W]);
+
+        // kVarh is an extended scale of version 4
+        let supported = Meter::supported_report(
+            &IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x32, 0x04, 0x21, 0x81, 0x01, 0x02]).unwrap(),
+        )
+        .unwrap();
+        assert!(!supported.reset);
+        assert!(supported.supports(MeterUnit::kVarh));
+        assert!(!supported.supports(MeterUnit::kVar));
+    }
+
+    #[test]
+    fn report_extended_scale() {
+        // electric import meter with 230.0V, scale bit 2 in the meter type
+        let (_, _, data) = Meter::report_v2(
+            &IncomingFrame::parse(&[
+                0x00, 0x05, 0x0A, 0x32, 0x02, 0xA1, 0x22, 0x08, 0xFC, 0x00, 0x00,
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(data.unit, MeterUnit::V);
+        assert_eq!(data.kind, MeterKind::Electric);
+        assert_eq!(data.rate_type, RateType::Import);
+        assert_eq!(data.value, 230.0);
+
+        // kVarh, which needs the scale 2 byte
+        let (_, _, data) = Meter::report_v2(
+            &IncomingFrame::parse(&[
+                0x00, 0x05, 0x0C, 0x32, 0x02, 0xA1, 0x39, 0x05, 0x00, 0x00, 0x00, 0x01,
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(data.unit, MeterUnit::kVarh);
+
+        let msg = Meter::get_v2(0x05, MeterUnit::kVarh);
+        assert_eq!(msg.to_vec(), vec![0x05, 0x04, 0x32, 0x01, 0x38, 0x01]);
+    }
 }
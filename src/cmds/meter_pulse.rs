@@ -0,0 +1,72 @@
+//! The Meter Pulse Command Class is used to read the pulse count of meters,
+//! which only count pulses without knowing their physical unit, like the
+//! pulse output of a water or gas meter.
+//!
+//! Converting the pulses into a consumption is up to the application, the
+//! meter doesn't know how much a pulse is worth.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// Meter Pulse Command Class
+#[derive(Debug, Clone)]
+pub struct MeterPulse;
+
+impl MeterPulse {
+    /// The Meter Pulse Get Command is used to request the number of pulses,
+    /// which the meter counted.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |         Command Class = COMMAND_CLASS_METER_PULSE(0x35)       |
+        // |                 Command = METER_PULSE_GET(0x04)               |
+        // -----------------------------------------------------------------
+        Message::from_array(node_id.into(), CommandClass::METER_PULSE, 0x04, [])
+    }
+
+    /// The Meter Pulse Report Command is used to advertise the number of
+    /// pulses, which the meter counted.
+    pub fn report(frame: &IncomingFrame) -> Result<u32, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |         Command Class = COMMAND_CLASS_METER_PULSE(0x35)       |
+        // |               Command = METER_PULSE_REPORT(0x05)              |
+        // |                       Pulse Count 1 (MSB)                     |
+        // |                          Pulse Count 2                        |
+        // |                          Pulse Count 3                        |
+        // |                       Pulse Count 4 (LSB)                     |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::METER_PULSE, 0x05)?;
+
+        if payload.len() < 4 {
+            return Err(Error::parse(
+                CommandClass::METER_PULSE,
+                "Message is too short",
+            ));
+        }
+
+        Ok(u32::from_be_bytes([
+            payload[0], payload[1], payload[2], payload[3],
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x35, 0x05, 0x00, 0x01, 0x86, 0xA0]).unwrap();
+        assert_eq!(MeterPulse::report(&frame), Ok(100_000));
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x35, 0x05, 0x00, 0x01]).unwrap();
+        assert!(MeterPulse::report(&frame).is_err());
+    }
+}
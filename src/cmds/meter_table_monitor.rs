@@ -0,0 +1,361 @@
+//! The Meter Table Monitor Command Class is used to read table based meters,
+//! like the utility meters of electricity, gas and water suppliers.
+//!
+//! A table meter reports several values at once, the datasets. Which value a
+//! dataset holds depends on the meter type, the first dataset of an electric
+//! meter is the imported energy for example. The times are in the local time
+//! of the meter.
+
+use crate::cmds::time_parameters::DateTime;
+use crate::cmds::{decode_value, CommandClass, IncomingFrame, Message, RateType};
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+
+/// An operating status event from the log of the meter.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterTableEvent {
+    /// The bit of the event in the operating status
+    pub event: u8,
+    /// The event started, otherwise it ended
+    pub active: bool,
+    pub time: DateTime,
+}
+
+/// The operating status of the meter and the logged events.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterTableStatus {
+    pub reports_to_follow: u8,
+    /// The bit mask of the events, which are active right now
+    pub current: u32,
+    pub events: Vec<MeterTableEvent>,
+}
+
+/// The value of a dataset.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterTableValue {
+    /// The bit of the dataset in the dataset mask
+    pub dataset: u8,
+    pub value: f64,
+    /// The scale of the value, which depends on the meter type
+    pub scale: u8,
+}
+
+/// The current values of the requested datasets.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterTableData {
+    pub reports_to_follow: u8,
+    pub rate: RateType,
+    /// The time of the reading
+    pub time: DateTime,
+    pub values: Vec<MeterTableValue>,
+}
+
+/// Meter Table Monitor Command Class
+#[derive(Debug, Clone)]
+pub struct MeterTableMonitor;
+
+impl MeterTableMonitor {
+    /// The Meter Table Point Adm. Number Get Command is used to request the
+    /// administration number of the meter, like the supply point number.
+    pub fn table_point_adm_no_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::METER_TBL_MONITOR, 0x01, [])
+    }
+
+    /// The Meter Table Point Adm. Number Report Command is used to advertise
+    /// the administration number of the meter.
+    pub fn table_point_adm_no_report(frame: &IncomingFrame) -> Result<String, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |      Command Class = COMMAND_CLASS_METER_TBL_MONITOR(0x3D)    |
+        // |         Command = METER_TBL_TABLE_POINT_ADM_NO_REPORT(0x02)   |
+        // |       Reserved        |      Number of Characters             |
+        // |          Meter Point Adm Number Character 1 ... n             |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::METER_TBL_MONITOR, 0x02)?;
+
+        let len = payload
+            .first()
+            .map(|l| (l & 0x1F) as usize)
+            .ok_or_else(|| Error::parse(CommandClass::METER_TBL_MONITOR, "Message is too short"))?;
+        let number = payload.get(1..1 + len).ok_or_else(|| {
+            Error::parse(CommandClass::METER_TBL_MONITOR, "The number is too short")
+        })?;
+
+        Ok(String::from_utf8_lossy(number).into_owned())
+    }
+
+    /// The Meter Table Status Supported Get Command is used to request the
+    /// operating status events, which the meter supports.
+    pub fn status_supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::METER_TBL_MONITOR, 0x07, [])
+    }
+
+    /// The Meter Table Status Supported Report Command is used to advertise
+    /// the bit mask of the supported operating status events and how many
+    /// events the log holds.
+    pub fn status_supported_report(frame: &IncomingFrame) -> Result<(u32, u8), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::METER_TBL_MONITOR, 0x08)?;
+
+        if payload.len() < 4 {
+            return Err(Error::parse(
+                CommandClass::METER_TBL_MONITOR,
+                "Message is too short",
+            ));
+        }
+
+        Ok((
+            u32::from_be_bytes([0, payload[0], payload[1], payload[2]]),
+            payload[3],
+        ))
+    }
+
+    /// The Meter Table Status Depth Get Command is used to request the
+    /// operating status and the last events from the log.
+    pub fn status_depth_get<N>(node_id: N, depth: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::METER_TBL_MONITOR,
+            0x09,
+            [depth],
+        )
+    }
+
+    /// The Meter Table Status Report Command is used to advertise the
+    /// operating status and the logged events. Long logs are split up into
+    /// multiple reports.
+    pub fn status_report(frame: &IncomingFrame) -> Result<MeterTableStatus, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |      Command Class = COMMAND_CLASS_METER_TBL_MONITOR(0x3D)    |
+        // |              Command = METER_TBL_STATUS_REPORT(0x0B)          |
+        // |                       Reports to Follow                       |
+        // |             Current Operating Status 1 ... 3                  |
+        // | Type  |   Reserved    |    Operating Status Event ID 1        |
+        // |   Year 1 ... 2, Month, Day, Hour, Minute, Second of Event 1   |
+        // |                             ...                               |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::METER_TBL_MONITOR, 0x0B)?;
+
+        if payload.len() < 4 || (payload.len() - 4) % 8 != 0 {
+            return Err(Error::parse(
+                CommandClass::METER_TBL_MONITOR,
+                "Message has the wrong length",
+            ));
+        }
+
+        let events = payload[4..]
+            .chunks(8)
+            .map(|event| MeterTableEvent {
+                event: event[0] & 0x1F,
+                active: event[0] & 0x80 != 0,
+                time: decode_time(&event[1..]),
+            })
+            .collect();
+
+        Ok(MeterTableStatus {
+            reports_to_follow: payload[0],
+            current: u32::from_be_bytes([0, payload[1], payload[2], payload[3]]),
+            events,
+        })
+    }
+
+    /// The Meter Table Current Data Get Command is used to request the
+    /// current values of the datasets, which are set in the bit mask.
+    pub fn current_data_get<N>(node_id: N, datasets: u32) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        if datasets == 0 || datasets > 0x00FF_FFFF {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The datasets need to be a bit mask of 1-24 bits",
+            ));
+        }
+
+        Message::new(
+            node_id.into(),
+            CommandClass::METER_TBL_MONITOR,
+            0x0C,
+            &datasets.to_be_bytes()[1..],
+        )
+    }
+
+    /// The Meter Table Current Data Report Command is used to advertise the
+    /// current values of the datasets. Each dataset in the bit mask has a
+    /// value, in the order of the bits. Many datasets are split up into
+    /// multiple reports.
+    pub fn current_data_report(frame: &IncomingFrame) -> Result<MeterTableData, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |      Command Class = COMMAND_CLASS_METER_TBL_MONITOR(0x3D)    |
+        // |           Command = METER_TBL_CURRENT_DATA_REPORT(0x0D)       |
+        // |                       Reports to Follow                       |
+        // |                   Reserved                    |   Rate Type   |
+        // |                     Dataset 1 ... 3                           |
+        // |      Year 1 ... 2, Month, Day, Hour, Minute, Second           |
+        // |       Precision       |             Scale 1                   |
+        // |                  Current Value 1 ... 4                        |
+        // |                             ...                               |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::METER_TBL_MONITOR, 0x0D)?;
+
+        if payload.len() < 12 {
+            return Err(Error::parse(
+                CommandClass::METER_TBL_MONITOR,
+                "Message is too short",
+            ));
+        }
+
+        let rate = RateType::try_from(payload[1] & 0x03)
+            .map_err(|_| Error::parse(CommandClass::METER_TBL_MONITOR, "Unknown rate type"))?;
+
+        // every dataset in the mask is followed by its value
+        let datasets = u32::from_be_bytes([0, payload[2], payload[3], payload[4]]);
+        let values = payload[12..].chunks(5);
+        if values.len() != datasets.count_ones() as usize || payload[12..].len() % 5 != 0 {
+            return Err(Error::parse(
+                CommandClass::METER_TBL_MONITOR,
+                "The values don't match the datasets",
+            ));
+        }
+
+        let values = (0..24)
+            .filter(|bit| datasets & (1 << bit) != 0)
+            .zip(values)
+            .map(|(dataset, value)| MeterTableValue {
+                dataset,
+                value: decode_value(&value[1..], value[0] >> 5),
+                scale: value[0] & 0x1F,
+            })
+            .collect();
+
+        Ok(MeterTableData {
+            reports_to_follow: payload[0],
+            rate,
+            time: decode_time(&payload[5..12]),
+            values,
+        })
+    }
+}
+
+/// Decode the year, month, day, hour, minute and second of 7 bytes.
+fn decode_time(bytes: &[u8]) -> DateTime {
+    DateTime {
+        year: u16::from_be_bytes([bytes[0], bytes[1]]),
+        month: bytes[2],
+        day: bytes[3],
+        hour: bytes[4],
+        minute: bytes[5],
+        second: bytes[6],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIME: DateTime = DateTime {
+        year: 2024,
+        month: 3,
+        day: 12,
+        hour: 8,
+        minute: 30,
+        second: 0,
+    };
+
+    #[test]
+    fn current_data() {
+        let msg = MeterTableMonitor::current_data_get(0x05, 0b101).unwrap();
+        assert_eq!(msg.data, vec![0x00, 0x00, 0x05]);
+        assert!(MeterTableMonitor::current_data_get(0x05, 0).is_err());
+
+        // the datasets 0 and 2 with 12345.6 kWh and 12 kW
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x18, 0x3D, 0x0D, 0x00, 0x01, 0x00, 0x00, 0x05, 0x07, 0xE8, 0x03, 0x0C,
+            0x08, 0x1E, 0x00, 0x20, 0x00, 0x01, 0xE2, 0x40, 0x02, 0x00, 0x00, 0x00, 0x0C,
+        ])
+        .unwrap();
+        assert_eq!(
+            MeterTableMonitor::current_data_report(&frame),
+            Ok(MeterTableData {
+                reports_to_follow: 0,
+                rate: RateType::Import,
+                time: TIME,
+                values: vec![
+                    MeterTableValue {
+                        dataset: 0,
+                        value: 12345.6,
+                        scale: 0,
+                    },
+                    MeterTableValue {
+                        dataset: 2,
+                        value: 12.0,
+                        scale: 2,
+                    },
+                ],
+            })
+        );
+
+        // the value of the second dataset is missing
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x13, 0x3D, 0x0D, 0x00, 0x01, 0x00, 0x00, 0x05, 0x07, 0xE8, 0x03, 0x0C,
+            0x08, 0x1E, 0x00, 0x20, 0x00, 0x01, 0xE2, 0x40,
+        ])
+        .unwrap();
+        assert!(MeterTableMonitor::current_data_report(&frame).is_err());
+    }
+
+    #[test]
+    fn status_report() {
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0E, 0x3D, 0x0B, 0x00, 0x00, 0x00, 0x04, 0x82, 0x07, 0xE8, 0x03, 0x0C,
+            0x08, 0x1E, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(
+            MeterTableMonitor::status_report(&frame),
+            Ok(MeterTableStatus {
+                reports_to_follow: 0,
+                current: 0x04,
+                events: vec![MeterTableEvent {
+                    event: 2,
+                    active: true,
+                    time: TIME,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn table_point_adm_no_report() {
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x08, 0x3D, 0x02, 0x05, 0x41, 0x42, 0x31, 0x32, 0x33,
+        ])
+        .unwrap();
+        assert_eq!(
+            MeterTableMonitor::table_point_adm_no_report(&frame),
+            Ok("AB123".into())
+        );
+    }
+}
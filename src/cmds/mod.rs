@@ -8,171 +8,87 @@
 //!
 //! If the full control over the devices and is required, take this layer.
 
+#[cfg(feature = "cc-security")]
+pub mod aes;
+#[cfg(feature = "cc-antitheft")]
+pub mod antitheft;
+pub mod application_status;
+pub mod association;
+pub mod association_group_info;
 pub mod basic;
+pub mod battery;
+pub mod builder;
+#[cfg(feature = "cc-central-scene")]
+pub mod central_scene;
+pub mod clock;
+pub mod configuration;
+pub mod crc16;
+pub mod encapsulation;
+#[cfg(feature = "cc-entry-control")]
+pub mod entry_control;
+#[cfg(feature = "cc-firmware-update")]
+pub mod firmware_update;
+#[cfg(feature = "cc-humidity")]
+pub mod humidity_control_setpoint;
 pub mod info;
+#[cfg(feature = "cc-meter")]
 pub mod meter;
+#[cfg(feature = "cc-meter")]
+pub mod meter_pulse;
+#[cfg(feature = "cc-meter")]
+pub mod meter_table_monitor;
+pub mod multi_channel;
+pub mod multi_channel_association;
+pub mod multi_cmd;
+pub mod notification;
+#[cfg(feature = "cc-powerlevel")]
 pub mod powerlevel;
+#[cfg(feature = "cc-protection")]
+pub mod protection;
+pub mod report;
+#[cfg(feature = "cc-scene-controller")]
+pub mod scene_controller_conf;
+#[cfg(feature = "cc-security")]
+pub mod security;
+#[cfg(feature = "cc-security")]
+pub mod security2;
+pub mod sensor_alarm;
+pub mod sensor_configuration;
+pub mod sensor_multilevel;
+#[cfg(feature = "cc-sound-switch")]
+pub mod sound_switch;
+pub mod supervision;
 pub mod switch_binary;
 pub mod switch_multilevel;
+#[cfg(feature = "cc-thermostat")]
+pub mod thermostat_operating_state;
+#[cfg(feature = "cc-thermostat")]
+pub mod thermostat_setpoint;
+pub mod time;
+pub mod time_parameters;
+pub mod transport_service;
+#[cfg(feature = "cc-user-code")]
+pub mod user_code;
+pub mod version;
+pub mod wake_up;
+#[cfg(feature = "cc-window-covering")]
+pub mod window_covering;
 
-use enum_primitive::FromPrimitive;
-use error::{Error, ErrorKind};
-
-enum_from_primitive! {
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[allow(non_camel_case_types)]
-/// List of the ZWave Command Classes
-pub enum CommandClass {
-    NO_OPERATION = 0x00,
-    NODE_INFO = 0x01,
-    REQUEST_NODE_INFO = 0x02,
-    ASSIGN_IDS = 0x03,
-    FIND_NODES_IN_RANGE = 0x04,
-    GET_NODES_IN_RANGE = 0x05,
-    RANGE_INFO = 0x06,
-    CMD_COMPLETE = 0x07,
-    TRANSFER_PRESENTATION = 0x08,
-    TRANSFER_NODE_INFO = 0x09,
-    TRANSFER_RANGE_INFO = 0x0A,
-    TRANSFER_END = 0x0B,
-    ASSIGN_RETURN_ROUTE = 0x0C,
-    NEW_NODE_REGISTERED = 0x0D,
-    NEW_RANGE_REGISTERED = 0x0E,
-    TRANSFER_NEW_PRIMARY_COMPLETE = 0x0F,
-    AUTOMATIC_CONTROLLER_UPDATE_START = 0x10,
-    SUC_NODE_ID = 0x11,
-    SET_SUC = 0x12,
-    SET_SUC_ACK = 0x13,
-    ASSIGN_SUC_RETURN_ROUTE = 0x14,
-    STATIC_ROUTE_REQUEST = 0x15,
-    LOST = 0x16,
-    ACCEPT_LOST = 0x17,
-    NOP_POWER = 0x18,
-    RESERVE_NODE_IDS = 0x19,
-    RESERVED_IDS = 0x1A,
-    // Unknown
-    BASIC = 0x20,
-    CONTROLLER_REPLICATION = 0x21,
-    APPLICATION_STATUS = 0x22,
-    ZIP_SERVICES = 0x23,
-    ZIP_SERVER = 0x24,
-    SWITCH_BINARY = 0x25,
-    SWITCH_MULTILEVEL = 0x26,
-    SWITCH_ALL = 0x27,
-    SWITCH_TOGGLE_BINARY = 0x28,
-    SWITCH_TOGGLE_MULTILEVEL = 0x29,
-    CHIMNEY_FAN = 0x2A,
-    SCENE_ACTIVATION = 0x2B,
-    SCENE_ACTUATOR_CONF = 0x2C,
-    SCENE_CONTROLLER_CONF = 0x2D,
-    ZIP_CLIENT = 0x2E,
-    ZIP_ADV_SERVICES = 0x2F,
-    SENSOR_BINARY = 0x30,
-    SENSOR_MULTILEVEL = 0x31,
-    METER = 0x32,
-    ZIP_ADV_SERVER = 0x33,
-    ZIP_ADV_CLIENT = 0x34,
-    METER_PULSE = 0x35,
-    METER_TBL_CONFIG = 0x3C,
-    METER_TBL_MONITOR = 0x3D,
-    METER_TBL_PUSH = 0x3E,
-    THERMOSTAT_HEATING = 0x38,
-    THERMOSTAT_MODE = 0x40,
-    THERMOSTAT_OPERATING_STATE = 0x42,
-    THERMOSTAT_SETPOINT = 0x43,
-    THERMOSTAT_FAN_MODE = 0x44,
-    THERMOSTAT_FAN_STATE = 0x45,
-    CLIMATE_CONTROL_SCHEDULE = 0x46,
-    THERMOSTAT_SETBACK = 0x47,
-    TARIF_CONFIG = 0x4A,
-    TARIF_TABLE_MONITOR = 0x4B,
-    COMMAND_CLASS_DOOR_LOCK_LOGGING = 0x4C,
-    SCHEDULE_ENTRY_LOCK = 0x4E,
-    ZIP_6LOWPAN = 0x4F,
-    BASIC_WINDOW_COVERING = 0x50,
-    MTP_WINDOW_COVERING = 0x51,
-    MULTI_INSTANCE = 0x60,
-    DOOR_LOCK = 0x62,
-    USER_CODE = 0x63,
-    CONFIGURATION = 0x70,
-    ALARM = 0x71,
-    MANUFACTURER_SPECIFIC = 0x72,
-    POWER_LEVEL = 0x73,
-    PROTECTION = 0x75,
-    LOCK = 0x76,
-    NODE_NAMING = 0x77,
-    FIRMWARE_UPDATE_MD = 0x7A,
-    GROUPING_NAME = 0x7B,
-    REMOTE_ASSOCIATION_ACTIVATE = 0x7C,
-    REMOTE_ASSOCIATION = 0x7D,
-    BATTERY = 0x80,
-    CLOCK = 0x81,
-    HAIL = 0x82,
-    WAKE_UP = 0x84,
-    ASSOCIATION = 0x85,
-    VERSION = 0x86,
-    INDICATOR = 0x87,
-    PROPRIETARY = 0x88,
-    LANGUAGE = 0x89,
-    TIME = 0x8A,
-    TIME_PARAMETERS = 0x8B,
-    GEOGRAPHIC_LOCATION = 0x8C,
-    COMPOSITE = 0x8D,
-    MULTI_INSTANCE_ASSOCIATION = 0x8E,
-    MULTI_CMD = 0x8F,
-    ENERGY_PRODUCTION = 0x90,
-    MANUFACTURER_PROPRIETARY = 0x91,
-    SCREEN_MD = 0x92,
-    SCREEN_ATTRIBUTES = 0x93,
-    SIMPLE_AV_CONTROL = 0x94,
-    AV_CONTENT_DIRECTORY_MD = 0x95,
-    AV_RENDERER_STATUS = 0x96,
-    AV_CONTENT_SEARCH_MD = 0x97,
-    SECURITY = 0x98,
-    AV_TAGGING_MD = 0x99,
-    IP_CONFIGURATION = 0x9A,
-    ASSOCIATION_COMMAND_CONFIGURATION = 0x9B,
-    SENSOR_ALARM = 0x9C,
-    SILENCE_ALARM = 0x9D,
-    SENSOR_CONFIGURATION = 0x9E,
-    MARK = 0xEF,
-    NON_INTEROPERABLE = 0xF0,
-}
-}
+pub use crate::defs::{
+    CommandClass, IncomingFrame, MeterData, MeterKind, MeterUnit, Quantity, RateType, SensorValue,
+    Unit,
+};
 
-#[derive(Debug)]
-#[allow(non_camel_case_types)]
-pub enum MeterData {
-    Electric_kWh(f64),
-    Electric_kVAh(f64),
-    Electric_W(f64),
-    Electric_PulseCount(f64),
-    Gas_meter2(f64),
-    Gas_feet2(f64),
-    Gas_PulseCount(f64),
-    Water_meter2(f64),
-    Water_feet2(f64),
-    Water_Gallons(f64),
-    Water_PulseCount(f64),
-}
+pub use self::builder::MessageBuilder;
+pub use self::encapsulation::Encapsulation;
+pub use self::report::Report;
 
-impl MeterData {
-    pub fn get_scale(&self) -> u8 {
-        match *self {
-            MeterData::Electric_kWh(_) => 0x00,
-            MeterData::Electric_kVAh(_) => 0x01,
-            MeterData::Electric_W(_) => 0x02,
-            MeterData::Electric_PulseCount(_) => 0x03,
-            MeterData::Gas_meter2(_) => 0x00,
-            MeterData::Gas_feet2(_) => 0x01,
-            MeterData::Gas_PulseCount(_) => 0x03,
-            MeterData::Water_meter2(_) => 0x00,
-            MeterData::Water_feet2(_) => 0x01,
-            MeterData::Water_Gallons(_) => 0x02,
-            MeterData::Water_PulseCount(_) => 0x03,
-        }
-    }
-}
+use crate::defs::SerialMessage;
+use crate::payload::{Payload, MAX_PAYLOAD};
+
+use crate::error::{Error, ErrorKind};
+use std::convert::TryFrom;
+use std::time::Duration;
 
 /// ZWave message to write and read
 ///
@@ -182,29 +98,62 @@ impl MeterData {
 /// ```rust
 /// use rzw::cmds::{Message, CommandClass};
 ///
-/// Message::new(0x02, CommandClass::BASIC, 0x01, vec!(0xFF));
+/// Message::from_array(0x02, CommandClass::BASIC, 0x01, [0xFF]);
 /// ```
 ///
+/// The `MessageBuilder` checks the parts of the message while building it up.
+/// The data is stored inline, so the message doesn't allocate.
+///
 /// The structure of a ZWave message looks like the following:
 ///
 /// `device, data-length, comand class, command, value`
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
     pub node_id: u8,
     pub cmd_class: CommandClass,
     pub cmd: u8,
-    pub data: Vec<u8>,
-    pub raw: Vec<u8>,
+    pub data: Payload,
+    pub raw: Payload,
 }
 
 impl Message {
-    pub fn new(node_id: u8, cmd_class: CommandClass, cmd: u8, data: Vec<u8>) -> Message {
+    /// create a new message, fails when the data is longer than `MAX_PAYLOAD`
+    pub fn new(
+        node_id: u8,
+        cmd_class: CommandClass,
+        cmd: u8,
+        data: &[u8],
+    ) -> Result<Message, Error> {
+        Ok(Message {
+            node_id,
+            cmd_class,
+            cmd,
+            data: Payload::try_from(data)?,
+            raw: Payload::new(),
+        })
+    }
+
+    /// create a new message with data of a fixed size, which is checked
+    /// against `MAX_PAYLOAD` when it's compiled
+    pub fn from_array<const N: usize>(
+        node_id: u8,
+        cmd_class: CommandClass,
+        cmd: u8,
+        data: [u8; N],
+    ) -> Message {
+        const { assert!(N <= MAX_PAYLOAD) };
+
+        let mut payload = Payload::new();
+        // can't fail, the size was checked above
+        let _ = payload.extend_from_slice(&data);
+
         Message {
-            node_id: node_id,
-            cmd_class: cmd_class,
-            cmd: cmd,
-            data: data,
-            raw: Vec::new(),
+            node_id,
+            cmd_class,
+            cmd,
+            data: payload,
+            raw: Payload::new(),
         }
     }
 
@@ -213,78 +162,92 @@ impl Message {
         node_id: u8,
         cmd_class: CommandClass,
         cmd: u8,
-        data: Vec<u8>,
-        raw: Vec<u8>,
+        data: Payload,
+        raw: Payload,
     ) -> Message {
         Message {
-            node_id: node_id,
-            cmd_class: cmd_class,
-            cmd: cmd,
-            data: data,
-            raw: raw,
+            node_id,
+            cmd_class,
+            cmd,
+            data,
+            raw,
         }
     }
 
     /// Parse a `&[u8]` slice and try to convert it to a `Message`
+    ///
+    /// The layout is the one of `to_vec`: `node, length, command class, command, data`
     pub fn parse(data: &[u8]) -> Result<Message, Error> {
-        let raw = data.to_vec();
+        let raw = Payload::try_from(data)?;
         // check if the data is avilable
-        if data.len() < 1 {
+        if data.is_empty() {
             return Err(Error::new(ErrorKind::UnknownZWave, "Message has no data"));
         }
 
         // check if the data has enough entries
-        if data.len() < 3 {
+        if data.len() < 4 {
             return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
         }
 
         // check if the length flag matches
-        //if data.len() - 2 != data[1] as usize {
-        //    return Err(Error::new(ErrorKind::UnknownZWave, "The length of the message delivered didn't match with the actual length"));
-        //}
+        if data.len() - 2 != data[1] as usize {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The length of the message delivered didn't match with the actual length",
+            ));
+        }
 
         // get the node id
-        let node_id = data[1];
+        let node_id = data[0];
 
         // get the commadn class
-        let cmd_class = CommandClass::from_u8(data[3]).unwrap_or(CommandClass::NO_OPERATION);
+        let cmd_class = CommandClass::try_from(data[2]).unwrap_or(CommandClass::NO_OPERATION);
 
         // get the command
         let cmd = data[3];
 
         // create the message data array
-        let msg_data: &[u8];
-
-        // when there is data extract it
-        if data.len() > 4 {
-            msg_data = &data[4..(data.len())];
-        }
-        // if not create a empty array
-        else {
-            msg_data = &[0; 0];
-        }
+        let msg_data: &[u8] = if data.len() > 4 {
+            // when there is data extract it
+            &data[4..(data.len())]
+        } else {
+            // if not create a empty array
+            &[0; 0]
+        };
 
         // create a new Message and return it
         Ok(Message::new_with_raw(
             node_id,
             cmd_class,
             cmd,
-            msg_data.to_vec(),
+            Payload::try_from(msg_data)?,
             raw,
         ))
     }
 
+    /// Parse a message out of a hex string, like `05 03 20 01 FF` from a log
+    pub fn from_hex(text: &str) -> Result<Message, Error> {
+        Message::parse(&SerialMessage::parse_hex(text)?)
+    }
+
     /// Return the message as Vec<u8>
     pub fn to_vec(&self) -> Vec<u8> {
-        // todo check if there a better way
-        let mut v: Vec<u8> = Vec::new();
-        v.push(self.node_id);
-        v.push((self.data.len() + 2) as u8);
-        v.push(self.cmd_class as u8);
-        v.push(self.cmd);
-        v.append(&mut self.data.clone());
+        // the transmit options and the callback id are added by the driver
+        let mut v = Vec::with_capacity(self.data.len() + 6);
+        self.write_to(&mut v);
         v
     }
+
+    /// Append the message to the buffer
+    pub fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&[
+            self.node_id,
+            (self.data.len() + 2) as u8,
+            self.cmd_class as u8,
+            self.cmd,
+        ]);
+        buf.extend_from_slice(&self.data);
+    }
 }
 
 impl From<Message> for Vec<u8> {
@@ -300,10 +263,185 @@ impl From<Message> for String {
         let data = message.to_vec();
         let mut out = String::new();
 
-        for i in 0..data.len() {
-            out.push_str(&*format!("{:#X} ", data[i]));
+        for byte in data {
+            out.push_str(&format!("{:#X} ", byte));
         }
 
         out
     }
 }
+
+/// Check if the value is a valid level for the Basic and the Multilevel Switch
+/// Command Class, which are 0-99 and 0xFF for the last level.
+pub fn check_level(value: u8) -> Result<u8, Error> {
+    if value > 99 && value != 0xFF {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("The level {} is not between 0-99 or 0xFF", value),
+        ));
+    }
+
+    Ok(value)
+}
+
+/// Encode a duration into the Z-Wave format.
+///
+/// `0x00` is instantly, `0x01-0x7F` are 1 to 127 seconds, `0x80-0xFE` are
+/// 1 to 127 minutes and `0xFF` (no duration) is the factory default.
+pub fn encode_duration(duration: Option<Duration>) -> Result<u8, Error> {
+    let duration = match duration {
+        Some(d) => d,
+        None => return Ok(0xFF),
+    };
+
+    // round to full seconds
+    let secs = (duration.as_millis() + 500) / 1000;
+    if secs <= 0x7F {
+        return Ok(secs as u8);
+    }
+
+    // round to full minutes
+    let mins = (secs + 30) / 60;
+    if mins <= 0x7F {
+        return Ok(0x7F + mins as u8);
+    }
+
+    Err(Error::new(
+        ErrorKind::InvalidInput,
+        "The duration can't be longer than 127 minutes",
+    ))
+}
+
+/// Decode a duration from the Z-Wave format, the factory default
+/// and unknown durations are returned as `None`.
+pub fn decode_duration(value: u8) -> Option<Duration> {
+    match value {
+        0x00..=0x7F => Some(Duration::from_secs(value as u64)),
+        0x80..=0xFE => Some(Duration::from_secs((value as u64 - 0x7F) * 60)),
+        _ => None,
+    }
+}
+
+/// Split the byte in front of a measured value into the precision, the
+/// scale and the size of the value.
+pub fn precision_scale_size(input: u8) -> (u8, u8, u8) {
+    (
+        (input >> 5),
+        ((input >> 3) & 0b00000011),
+        (input & 0b00000111),
+    )
+}
+
+/// Decode a signed measured value of 1, 2 or 4 bytes with the precision,
+/// which is the amount of decimals.
+pub fn decode_value(bytes: &[u8], precision: u8) -> f64 {
+    let precision = 10u32.pow(precision as u32) as f64;
+
+    match bytes.len() {
+        1 => (bytes[0] as i8) as f64 / precision,
+        2 => i16::from_be_bytes([bytes[0], bytes[1]]) as f64 / precision,
+        4 => i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64 / precision,
+        _ => 0.0,
+    }
+}
+
+/// Encode a measured value with the precision into the fewest of 1, 2 or 4
+/// bytes, the counterpart of `decode_value`.
+pub fn encode_value(value: f64, precision: u8) -> Result<Vec<u8>, Error> {
+    if precision > 7 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The precision can't be more than 7 decimals",
+        ));
+    }
+
+    let scaled = (value * 10u32.pow(precision as u32) as f64).round();
+    if scaled >= i8::MIN as f64 && scaled <= i8::MAX as f64 {
+        Ok(vec![scaled as i8 as u8])
+    } else if scaled >= i16::MIN as f64 && scaled <= i16::MAX as f64 {
+        Ok((scaled as i16).to_be_bytes().to_vec())
+    } else if scaled >= i32::MIN as f64 && scaled <= i32::MAX as f64 {
+        Ok((scaled as i32).to_be_bytes().to_vec())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The value is too large for the precision",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex() {
+        let message = Message::from_hex("05 03 20 01 FF").unwrap();
+        assert_eq!(message.node_id, 0x05);
+        assert_eq!(message.cmd_class, CommandClass::BASIC);
+        assert_eq!(message.cmd, 0x01);
+        assert_eq!(message.data, vec![0xFF]);
+        assert_eq!(message.to_vec(), message.raw);
+
+        assert!(Message::from_hex("05 04 20 01 FF").is_err());
+    }
+
+    #[test]
+    fn new() {
+        let message = Message::from_array(0x05, CommandClass::BASIC, 0x01, [0xFF]);
+        assert_eq!(message.to_vec(), vec![0x05, 0x03, 0x20, 0x01, 0xFF]);
+
+        // the data doesn't fit into a frame
+        assert!(Message::new(0x05, CommandClass::BASIC, 0x01, &[0; MAX_PAYLOAD]).is_ok());
+        assert!(Message::new(0x05, CommandClass::BASIC, 0x01, &[0; MAX_PAYLOAD + 1]).is_err());
+    }
+
+    #[test]
+    fn levels() {
+        assert!(check_level(0).is_ok());
+        assert!(check_level(99).is_ok());
+        assert!(check_level(0xFF).is_ok());
+        assert!(check_level(100).is_err());
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(encode_value(21.5, 1).unwrap(), vec![0x00, 0xD7]);
+        assert_eq!(encode_value(-1.0, 0).unwrap(), vec![0xFF]);
+        assert_eq!(
+            decode_value(&encode_value(-1234.56, 2).unwrap(), 2),
+            -1234.56
+        );
+        assert!(encode_value(1.0, 8).is_err());
+        assert!(encode_value(1e10, 2).is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(encode_duration(None).unwrap(), 0xFF);
+        assert_eq!(
+            encode_duration(Some(Duration::from_millis(0))).unwrap(),
+            0x00
+        );
+        assert_eq!(
+            encode_duration(Some(Duration::from_secs(10))).unwrap(),
+            0x0A
+        );
+        assert_eq!(
+            encode_duration(Some(Duration::from_secs(127))).unwrap(),
+            0x7F
+        );
+        assert_eq!(
+            encode_duration(Some(Duration::from_secs(180))).unwrap(),
+            0x82
+        );
+        assert_eq!(
+            encode_duration(Some(Duration::from_secs(127 * 60))).unwrap(),
+            0xFE
+        );
+        assert!(encode_duration(Some(Duration::from_secs(128 * 60))).is_err());
+
+        assert_eq!(decode_duration(0x82), Some(Duration::from_secs(180)));
+        assert_eq!(decode_duration(0xFF), None);
+    }
+}
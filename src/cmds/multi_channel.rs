@@ -0,0 +1,247 @@
+//! The Multi Channel Command Class is used to address one or more End Points
+//! in a Multi Channel device, like the sockets of a power strip.
+//!
+//! The commands for an end point are encapsulated into a Multi Channel Command
+//! Encapsulation, which names the source and the destination end point.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::defs::GenericType;
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+
+/// The highest end point, which can be addressed
+pub const MAX_ENDPOINT: u8 = 127;
+
+/// The end points of a node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiChannelEndpoints {
+    /// The amount of end points can change, like for a gateway
+    pub dynamic: bool,
+    /// All end points have the same device class and command classes
+    pub identical: bool,
+    /// The amount of end points, they are numbered from 1
+    pub individual: u8,
+    /// The amount of end points, which combine other end points, like the
+    /// sum of all sockets. They are numbered behind the individual ones.
+    pub aggregated: u8,
+}
+
+/// The device class and the command classes of an end point.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EndpointCapability {
+    pub endpoint: u8,
+    pub dynamic: bool,
+    pub generic: GenericType,
+    pub specific: u8,
+    pub cmds: Vec<CommandClass>,
+}
+
+/// Multi Channel Command Class
+#[derive(Debug, Clone)]
+pub struct MultiChannel;
+
+impl MultiChannel {
+    /// The Multi Channel End Point Get Command is used to request the amount
+    /// of end points.
+    pub fn endpoint_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::MULTI_INSTANCE, 0x07, [])
+    }
+
+    /// The Multi Channel End Point Report Command is used to advertise the
+    /// amount of end points.
+    pub fn endpoint_report(frame: &IncomingFrame) -> Result<MultiChannelEndpoints, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_MULTI_CHANNEL(0x60)       |
+        // |          Command = MULTI_CHANNEL_END_POINT_REPORT(0x08)       |
+        // |Dynamic|Identic|                  Reserved                     |
+        // |  Res  |                 Individual End Points                 |
+        // |  Res  |           Aggregated End Points (version 4)           |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::MULTI_INSTANCE, 0x08)?;
+
+        if payload.len() < 2 {
+            return Err(Error::parse(
+                CommandClass::MULTI_INSTANCE,
+                "Message is too short",
+            ));
+        }
+
+        Ok(MultiChannelEndpoints {
+            dynamic: payload[0] & 0x80 == 0x80,
+            identical: payload[0] & 0x40 == 0x40,
+            individual: payload[1] & 0x7F,
+            aggregated: payload.get(2).map(|a| a & 0x7F).unwrap_or(0),
+        })
+    }
+
+    /// The Multi Channel Capability Get Command is used to request the device
+    /// class and the command classes of an end point.
+    pub fn capability_get<N>(node_id: N, endpoint: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::MULTI_INSTANCE,
+            0x09,
+            [endpoint & 0x7F],
+        )
+    }
+
+    /// The Multi Channel Capability Report Command is used to advertise the
+    /// device class and the command classes of an end point. The unknown
+    /// command classes are skipped.
+    pub fn capability_report(frame: &IncomingFrame) -> Result<EndpointCapability, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_MULTI_CHANNEL(0x60)       |
+        // |        Command = MULTI_CHANNEL_CAPABILITY_REPORT(0x0A)        |
+        // |Dynamic|                      End Point                        |
+        // |                    Generic Device Class                       |
+        // |                    Specific Device Class                      |
+        // |               Command Class 1 ... Command Class n             |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::MULTI_INSTANCE, 0x0A)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::MULTI_INSTANCE,
+                "Message is too short",
+            ));
+        }
+
+        Ok(EndpointCapability {
+            endpoint: payload[0] & 0x7F,
+            dynamic: payload[0] & 0x80 == 0x80,
+            generic: GenericType::try_from(payload[1]).unwrap_or(GenericType::Unknown),
+            specific: payload[2],
+            cmds: payload[3..]
+                .iter()
+                .filter_map(|c| CommandClass::try_from(*c).ok())
+                .collect(),
+        })
+    }
+
+    /// The Multi Channel Command Encapsulation Command is used to send the message
+    /// from the root device of the controller to the end point of the node.
+    pub fn encap(message: Message, endpoint: u8) -> Result<Message, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_MULTI_CHANNEL(0x60)       |
+        // |            Command = MULTI_CHANNEL_CMD_ENCAP(0x0D)            |
+        // |  Res  |                    Source End Point                   |
+        // | BitAdr|                 Destination End Point                 |
+        // |                         Command Class                         |
+        // |                            Command                            |
+        // |                  Parameter 1 ... Parameter n                  |
+        // -----------------------------------------------------------------
+        if endpoint == 0 || endpoint > MAX_ENDPOINT {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The end point needs to be between 1-{}", MAX_ENDPOINT),
+            ));
+        }
+
+        let mut data = vec![0x00, endpoint, message.cmd_class as u8, message.cmd];
+        data.extend_from_slice(&message.data);
+
+        Message::new(message.node_id, CommandClass::MULTI_INSTANCE, 0x0D, &data)
+    }
+
+    /// Unwrap the command of a Multi Channel Command Encapsulation, which was sent
+    /// by an end point. Returns the end point and the encapsulated command.
+    pub fn decap(frame: &IncomingFrame) -> Result<(u8, IncomingFrame), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::MULTI_INSTANCE, 0x0D)?;
+
+        // the payload need to be at least 4 bytes long
+        if payload.len() < 4 {
+            return Err(Error::parse(
+                CommandClass::MULTI_INSTANCE,
+                "Message is too short",
+            ));
+        }
+
+        let command_class = CommandClass::try_from(payload[2]).map_err(|_| payload[2]);
+
+        Ok((
+            payload[0] & 0x7F,
+            IncomingFrame {
+                status: frame.status,
+                source: frame.source,
+                command_class,
+                command: payload[3],
+                payload: payload[4..].to_vec(),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::switch_binary::SwitchBinary;
+
+    #[test]
+    fn encap() {
+        let msg = MultiChannel::encap(SwitchBinary::set(0x05, true), 2).unwrap();
+
+        assert_eq!(msg.node_id, 0x05);
+        assert_eq!(msg.cmd_class, CommandClass::MULTI_INSTANCE);
+        assert_eq!(msg.data, vec![0x00, 0x02, 0x25, 0x01, 0xFF]);
+
+        assert!(MultiChannel::encap(SwitchBinary::get(0x05), 0).is_err());
+    }
+
+    #[test]
+    fn endpoint_report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x60, 0x08, 0x40, 0x02, 0x01]).unwrap();
+        assert_eq!(
+            MultiChannel::endpoint_report(&frame),
+            Ok(MultiChannelEndpoints {
+                dynamic: false,
+                identical: true,
+                individual: 2,
+                aggregated: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn capability_report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x60, 0x0A, 0x02, 0x10, 0x01, 0x25, 0x32])
+                .unwrap();
+        let capability = MultiChannel::capability_report(&frame).unwrap();
+        assert_eq!(capability.endpoint, 2);
+        assert_eq!(capability.generic, GenericType::BinarySwitch);
+        assert_eq!(
+            capability.cmds,
+            vec![CommandClass::SWITCH_BINARY, CommandClass::METER]
+        );
+    }
+
+    #[test]
+    fn decap() {
+        let (endpoint, frame) = MultiChannel::decap(
+            &IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x60, 0x0D, 0x02, 0x00, 0x25, 0x03, 0xFF])
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(endpoint, 2);
+        assert_eq!(SwitchBinary::report(&frame), Ok(true));
+    }
+}
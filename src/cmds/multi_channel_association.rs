@@ -0,0 +1,229 @@
+//! The Multi Channel Association Command Class is used to manage associations to
+//! Multi Channel End Point destinations as well as to NodeID destinations.
+//!
+//! It extends the Association Command Class, so reports can be sent to a
+//! specific end point of the destination node.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// The marker which separates the node ids from the end point destinations.
+const MARKER: u8 = 0x00;
+
+/// The bit which turns the end point into a bit mask of the end points 1 to 7.
+const BIT_ADDRESS: u8 = 0x80;
+
+/// The nodes and end points which are associated to a group of a node.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiChannelAssociationReport {
+    pub group: u8,
+    pub max_nodes: u8,
+    pub reports_to_follow: u8,
+    pub nodes: Vec<u8>,
+    pub endpoints: Vec<(u8, u8)>,
+}
+
+/// Multi Channel Association Command Class
+#[derive(Debug, Clone)]
+pub struct MultiChannelAssociation;
+
+impl MultiChannelAssociation {
+    /// The Multi Channel Association Set Command is used to add destinations to a
+    /// given association group. The end points are given as node id and end point.
+    pub fn set<N>(
+        node_id: N,
+        group: u8,
+        nodes: &[u8],
+        endpoints: &[(u8, u8)],
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // | Command Class = COMMAND_CLASS_MULTI_CHANNEL_ASSOCIATION(0x8E) |
+        // |         Command = MULTI_CHANNEL_ASSOCIATION_SET(0x01)         |
+        // |                      Grouping Identifier                      |
+        // |                    Node ID 1 ... Node ID n                    |
+        // |                         Marker = 0x00                         |
+        // |                    Multi Channel Node ID 1                    |
+        // | BitAdr|                      End Point 1                      |
+        // |                              ...                              |
+        // -----------------------------------------------------------------
+        Message::new(
+            node_id.into(),
+            CommandClass::MULTI_INSTANCE_ASSOCIATION,
+            0x01,
+            &MultiChannelAssociation::destinations(group, nodes, endpoints),
+        )
+    }
+
+    /// The Multi Channel Association Get Command is used to request the current
+    /// destinations of a given association group.
+    pub fn get<N>(node_id: N, group: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::MULTI_INSTANCE_ASSOCIATION,
+            0x02,
+            [group],
+        )
+    }
+
+    /// The Multi Channel Association Report Command is used to advertise the current
+    /// destinations of a given association group.
+    pub fn report(frame: &IncomingFrame) -> Result<MultiChannelAssociationReport, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // | Command Class = COMMAND_CLASS_MULTI_CHANNEL_ASSOCIATION(0x8E) |
+        // |        Command = MULTI_CHANNEL_ASSOCIATION_REPORT(0x03)       |
+        // |                      Grouping Identifier                      |
+        // |                      Max Nodes Supported                      |
+        // |                       Reports to Follow                       |
+        // |                    Node ID 1 ... Node ID n                    |
+        // |                         Marker = 0x00                         |
+        // |                    Multi Channel Node ID 1                    |
+        // | BitAdr|                      End Point 1                      |
+        // |                              ...                              |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::MULTI_INSTANCE_ASSOCIATION, 0x03)?;
+
+        // the payload need to be at least 3 bytes long
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::MULTI_INSTANCE_ASSOCIATION,
+                "Message is too short",
+            ));
+        }
+
+        // split the node ids from the end points
+        let destinations = &payload[3..];
+        let (nodes, endpoints) = match destinations.iter().position(|b| *b == MARKER) {
+            Some(pos) => (&destinations[..pos], &destinations[pos + 1..]),
+            None => (destinations, &[][..]),
+        };
+
+        // a bit addressed destination stands for multiple end points
+        let mut destinations = vec![];
+        for c in endpoints.chunks(2).filter(|c| c.len() == 2) {
+            if c[1] & BIT_ADDRESS == 0 {
+                destinations.push((c[0], c[1]));
+                continue;
+            }
+            for bit in 0..7 {
+                if c[1] & (1 << bit) != 0 {
+                    destinations.push((c[0], bit + 1));
+                }
+            }
+        }
+
+        Ok(MultiChannelAssociationReport {
+            group: payload[0],
+            max_nodes: payload[1],
+            reports_to_follow: payload[2],
+            nodes: nodes.to_vec(),
+            endpoints: destinations,
+        })
+    }
+
+    /// The Multi Channel Association Remove Command is used to remove destinations
+    /// from a given association group. When no destinations are given, all are removed.
+    pub fn remove<N>(
+        node_id: N,
+        group: u8,
+        nodes: &[u8],
+        endpoints: &[(u8, u8)],
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        Message::new(
+            node_id.into(),
+            CommandClass::MULTI_INSTANCE_ASSOCIATION,
+            0x04,
+            &MultiChannelAssociation::destinations(group, nodes, endpoints),
+        )
+    }
+
+    /// The Multi Channel Association Supported Groupings Get Command is used to
+    /// request the number of association groups that this node supports.
+    pub fn groupings_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::MULTI_INSTANCE_ASSOCIATION,
+            0x05,
+            [],
+        )
+    }
+
+    /// The Multi Channel Association Supported Groupings Report Command is used to
+    /// advertise the number of association groups that this node supports.
+    pub fn groupings_report(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::MULTI_INSTANCE_ASSOCIATION, 0x06)?;
+
+        // the payload need to be at least 1 byte long
+        if payload.is_empty() {
+            return Err(Error::parse(
+                CommandClass::MULTI_INSTANCE_ASSOCIATION,
+                "Message is too short",
+            ));
+        }
+
+        Ok(payload[0])
+    }
+
+    /// build the group and destination list of the set and remove command
+    fn destinations(group: u8, nodes: &[u8], endpoints: &[(u8, u8)]) -> Vec<u8> {
+        let mut data = vec![group];
+        data.extend_from_slice(nodes);
+
+        // the end points are only added after the marker
+        if !endpoints.is_empty() {
+            data.push(MARKER);
+            for (node, endpoint) in endpoints {
+                data.push(*node);
+                data.push(*endpoint);
+            }
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        // the nodes 1 and 3, the end point 2 of node 4 and the end points
+        // 1 and 3 of node 5 as bit mask
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x07, 0x0C, 0x8E, 0x03, 0x01, 0x05, 0x00, 0x01, 0x03, 0x00, 0x04, 0x02, 0x05,
+            0x85,
+        ])
+        .unwrap();
+        assert_eq!(
+            MultiChannelAssociation::report(&frame),
+            Ok(MultiChannelAssociationReport {
+                group: 1,
+                max_nodes: 5,
+                reports_to_follow: 0,
+                nodes: vec![1, 3],
+                endpoints: vec![(4, 2), (5, 1), (5, 3)],
+            })
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x07, 0x03, 0x8E, 0x03, 0x01]).unwrap();
+        assert!(MultiChannelAssociation::report(&frame).is_err());
+    }
+}
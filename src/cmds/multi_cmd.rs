@@ -0,0 +1,192 @@
+//! The Multi Command Command Class is used to send several commands to a
+//! node in a single frame. It saves air time, like when the commands, which
+//! waited for a sleeping node, are sent at its wake up.
+//!
+//! Each command is prefixed with its length. The commands are executed in
+//! the order they are packed.
+
+use crate::cmds::builder::MAX_COMMAND_LENGTH;
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+use crate::payload::Payload;
+
+use std::convert::TryFrom;
+
+/// The bytes of the encapsulation without the commands, the command class,
+/// the command and the amount of commands
+const HEADER: usize = 3;
+
+/// Multi Command Command Class
+#[derive(Debug, Clone)]
+pub struct MultiCmd;
+
+impl MultiCmd {
+    /// The Multi Command Encapsulated Command is used to pack several
+    /// commands for the same node into one.
+    pub fn encap(messages: &[Message]) -> Result<Message, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |          Command Class = COMMAND_CLASS_MULTI_CMD(0x8F)        |
+        // |          Command = MULTI_CMD_ENCAPSULATED(0x01)               |
+        // |                      Number of Commands                       |
+        // |                       Command Length 1                        |
+        // |                        Command Class 1                        |
+        // |                          Command 1                            |
+        // |                    Parameter 1 ... Parameter n                |
+        // |                              ...                              |
+        // -----------------------------------------------------------------
+        let node_id = match messages.first() {
+            Some(message) if messages.len() <= 0xFF => message.node_id,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "The amount of commands needs to be between 1-255",
+                ))
+            }
+        };
+
+        let mut data = vec![messages.len() as u8];
+        for message in messages {
+            if message.node_id != node_id {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "The commands need to be for the same node",
+                ));
+            }
+            data.extend_from_slice(&[
+                (message.data.len() + 2) as u8,
+                message.cmd_class as u8,
+                message.cmd,
+            ]);
+            data.extend_from_slice(&message.data);
+        }
+
+        Ok(Message {
+            node_id,
+            cmd_class: CommandClass::MULTI_CMD,
+            cmd: 0x01,
+            data: Payload::try_from(&data[..])?,
+            raw: Payload::new(),
+        })
+    }
+
+    /// Pack the commands into as few frames as possible, keeping their
+    /// order. The overhead is the length, which the encapsulations of the
+    /// node add to each frame. A single command is sent as it is.
+    pub fn pack(messages: Vec<Message>, overhead: usize) -> Result<Vec<Message>, Error> {
+        let room = MAX_COMMAND_LENGTH.saturating_sub(overhead + HEADER);
+        let mut frames = vec![];
+        let mut batch: Vec<Message> = vec![];
+        let mut length = 0;
+
+        for message in messages {
+            let needed = message.data.len() + 3;
+            if !batch.is_empty() && (length + needed > room || batch.len() == 0xFF) {
+                frames.push(MultiCmd::batch(batch)?);
+                batch = vec![];
+                length = 0;
+            }
+            length += needed;
+            batch.push(message);
+        }
+        if !batch.is_empty() {
+            frames.push(MultiCmd::batch(batch)?);
+        }
+
+        Ok(frames)
+    }
+
+    /// Unpack the commands of a Multi Command Encapsulated Command, which
+    /// was sent by a node.
+    pub fn decap(frame: &IncomingFrame) -> Result<Vec<IncomingFrame>, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::MULTI_CMD, 0x01)?;
+
+        let count = *payload
+            .first()
+            .ok_or_else(|| Error::parse(CommandClass::MULTI_CMD, "Message is too short"))?;
+
+        let mut commands = vec![];
+        let mut rest = &payload[1..];
+        for _ in 0..count {
+            let length = rest.first().map(|l| *l as usize).unwrap_or(0);
+            if length < 2 || rest.len() < 1 + length {
+                return Err(Error::parse(
+                    CommandClass::MULTI_CMD,
+                    "Message is too short",
+                ));
+            }
+
+            let command_class = CommandClass::try_from(rest[1]).map_err(|_| rest[1]);
+            commands.push(IncomingFrame {
+                status: frame.status,
+                source: frame.source,
+                command_class,
+                command: rest[2],
+                payload: rest[3..1 + length].to_vec(),
+            });
+            rest = &rest[1 + length..];
+        }
+
+        Ok(commands)
+    }
+
+    /// Encapsulate the batch, when it has more than one command
+    fn batch(mut batch: Vec<Message>) -> Result<Message, Error> {
+        if batch.len() == 1 {
+            Ok(batch.remove(0))
+        } else {
+            MultiCmd::encap(&batch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::basic::Basic;
+    use crate::cmds::switch_binary::SwitchBinary;
+
+    #[test]
+    fn encap() {
+        let msg = MultiCmd::encap(&[SwitchBinary::set(0x05, true), Basic::get(0x05)]).unwrap();
+        assert_eq!(msg.cmd_class, CommandClass::MULTI_CMD);
+        assert_eq!(
+            msg.data,
+            vec![0x02, 0x03, 0x25, 0x01, 0xFF, 0x02, 0x20, 0x02]
+        );
+
+        assert!(MultiCmd::encap(&[]).is_err());
+        assert!(MultiCmd::encap(&[Basic::get(0x05), Basic::get(0x06)]).is_err());
+    }
+
+    #[test]
+    fn pack() {
+        // the 15 commands don't fit into a single frame
+        let messages = vec![SwitchBinary::set(0x05, true); 15];
+        let frames = MultiCmd::pack(messages, 0).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data[0], 10);
+        assert_eq!(frames[1].data[0], 5);
+
+        // a single command isn't encapsulated
+        let frames = MultiCmd::pack(vec![Basic::get(0x05)], 0).unwrap();
+        assert_eq!(frames[0].cmd_class, CommandClass::BASIC);
+    }
+
+    #[test]
+    fn decap() {
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0A, 0x8F, 0x01, 0x02, 0x03, 0x25, 0x03, 0xFF, 0x02, 0x84, 0x08,
+        ])
+        .unwrap();
+        let commands = MultiCmd::decap(&frame).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(SwitchBinary::report(&commands[0]), Ok(true));
+        assert_eq!(commands[1].command_class, Ok(CommandClass::WAKE_UP));
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x8F, 0x01, 0x02, 0x03, 0x25]).unwrap();
+        assert!(MultiCmd::decap(&frame).is_err());
+    }
+}
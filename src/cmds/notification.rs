@@ -0,0 +1,236 @@
+//! The Notification Command Class, formerly the Alarm Command Class, is used
+//! by the nodes to report events, like a detected motion, smoke or an opened
+//! door.
+//!
+//! Version 1 nodes only report a manufacturer specific alarm type and level.
+//! Newer nodes report a standardized notification type and event, which are
+//! decoded into a `NotificationEvent`.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// The notification types, which have typed events
+const SMOKE: u8 = 0x01;
+const CARBON_MONOXIDE: u8 = 0x02;
+const CARBON_DIOXIDE: u8 = 0x03;
+const HEAT: u8 = 0x04;
+const WATER: u8 = 0x05;
+const ACCESS_CONTROL: u8 = 0x06;
+const HOME_SECURITY: u8 = 0x07;
+const POWER_MANAGEMENT: u8 = 0x08;
+
+/// An event reported by a node.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotificationEvent {
+    /// The events of the notification type are over
+    Idle(u8),
+    /// Smoke was detected
+    Smoke,
+    /// Carbon monoxide was detected
+    CarbonMonoxide,
+    /// Carbon dioxide was detected
+    CarbonDioxide,
+    /// An overheat was detected
+    Heat,
+    /// A water leak was detected
+    Water,
+    /// The lock was locked
+    Locked,
+    /// The lock was unlocked
+    Unlocked,
+    /// The door or window was opened
+    DoorOpen,
+    /// The door or window was closed
+    DoorClosed,
+    /// An intrusion was detected
+    Intrusion,
+    /// The cover of the node was removed
+    Tampering,
+    /// A motion was detected
+    Motion,
+    /// The node was powered on
+    PowerApplied,
+    /// The mains power was disconnected
+    MainsDisconnected,
+    /// The mains power was reconnected
+    MainsReconnected,
+    /// The battery needs to be replaced
+    ReplaceBattery,
+    /// An event without a decoder
+    Unknown {
+        notification_type: u8,
+        event: u8,
+        parameters: Vec<u8>,
+    },
+    /// The manufacturer specific alarm of a version 1 node
+    Alarm { alarm_type: u8, level: u8 },
+}
+
+impl NotificationEvent {
+    /// Decode the event of the notification type
+    pub fn new(notification_type: u8, event: u8, parameters: &[u8]) -> NotificationEvent {
+        // the idle event has the same meaning for all types
+        if event == 0x00 {
+            return NotificationEvent::Idle(notification_type);
+        }
+
+        match (notification_type, event) {
+            (SMOKE, 0x01) | (SMOKE, 0x02) => NotificationEvent::Smoke,
+            (CARBON_MONOXIDE, 0x01) | (CARBON_MONOXIDE, 0x02) => NotificationEvent::CarbonMonoxide,
+            (CARBON_DIOXIDE, 0x01) | (CARBON_DIOXIDE, 0x02) => NotificationEvent::CarbonDioxide,
+            (HEAT, 0x01) | (HEAT, 0x02) => NotificationEvent::Heat,
+            (WATER, 0x01) | (WATER, 0x02) => NotificationEvent::Water,
+            (ACCESS_CONTROL, 0x01) | (ACCESS_CONTROL, 0x03) | (ACCESS_CONTROL, 0x05) => {
+                NotificationEvent::Locked
+            }
+            (ACCESS_CONTROL, 0x02) | (ACCESS_CONTROL, 0x04) | (ACCESS_CONTROL, 0x06) => {
+                NotificationEvent::Unlocked
+            }
+            (ACCESS_CONTROL, 0x16) => NotificationEvent::DoorOpen,
+            (ACCESS_CONTROL, 0x17) => NotificationEvent::DoorClosed,
+            (HOME_SECURITY, 0x01) | (HOME_SECURITY, 0x02) => NotificationEvent::Intrusion,
+            (HOME_SECURITY, 0x03) => NotificationEvent::Tampering,
+            (HOME_SECURITY, 0x07) | (HOME_SECURITY, 0x08) => NotificationEvent::Motion,
+            (POWER_MANAGEMENT, 0x01) => NotificationEvent::PowerApplied,
+            (POWER_MANAGEMENT, 0x02) => NotificationEvent::MainsDisconnected,
+            (POWER_MANAGEMENT, 0x03) => NotificationEvent::MainsReconnected,
+            (POWER_MANAGEMENT, 0x0A) | (POWER_MANAGEMENT, 0x0B) => {
+                NotificationEvent::ReplaceBattery
+            }
+            _ => NotificationEvent::Unknown {
+                notification_type,
+                event,
+                parameters: parameters.to_vec(),
+            },
+        }
+    }
+}
+
+/// Notification Command Class
+#[derive(Debug, Clone)]
+pub struct Notification;
+
+impl Notification {
+    /// The Notification Get Command is used to request the last event of the
+    /// notification type. The event 0x00 requests any event of the type.
+    pub fn get<N>(node_id: N, notification_type: u8, event: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::ALARM,
+            0x04,
+            [0x00, notification_type, event],
+        )
+    }
+
+    /// The Notification Set Command is used to enable or disable the
+    /// unsolicited reports of the notification type.
+    pub fn set<N>(node_id: N, notification_type: u8, enabled: bool) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::ALARM,
+            0x06,
+            [notification_type, if enabled { 0xFF } else { 0x00 }],
+        )
+    }
+
+    /// The Notification Report Command is used to advertise an event.
+    pub fn report(frame: &IncomingFrame) -> Result<NotificationEvent, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |           Command Class = COMMAND_CLASS_ALARM(0x71)           |
+        // |              Command = NOTIFICATION_REPORT(0x05)              |
+        // |                       V1 Alarm Type                           |
+        // |                       V1 Alarm Level                          |
+        // |                          Reserved                             |
+        // |                    Notification Status                        |
+        // |                     Notification Type                         |
+        // |                           Event                               |
+        // |Sequence|    Reserved   |  Event Parameters Length             |
+        // |             Event Parameter 1 ... Event Parameter n           |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::ALARM, 0x05)?;
+
+        if payload.len() < 2 {
+            return Err(Error::parse(CommandClass::ALARM, "Message is too short"));
+        }
+
+        // version 1 nodes only report the alarm
+        if payload.len() < 6 || payload[4] == 0x00 {
+            return Ok(NotificationEvent::Alarm {
+                alarm_type: payload[0],
+                level: payload[1],
+            });
+        }
+
+        let length = payload.get(6).map(|p| (p & 0x1F) as usize).unwrap_or(0);
+        let parameters = payload.get(7..7 + length).ok_or_else(|| {
+            Error::parse(CommandClass::ALARM, "The event parameters are too short")
+        })?;
+
+        Ok(NotificationEvent::new(payload[4], payload[5], parameters))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        // a motion sensor
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x09, 0x71, 0x05, 0x00, 0x00, 0x00, 0xFF, 0x07, 0x08, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(Notification::report(&frame), Ok(NotificationEvent::Motion));
+
+        // the door sensor is idle again
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0A, 0x71, 0x05, 0x00, 0x00, 0x00, 0xFF, 0x06, 0x00, 0x01, 0x16,
+        ])
+        .unwrap();
+        assert_eq!(
+            Notification::report(&frame),
+            Ok(NotificationEvent::Idle(0x06))
+        );
+
+        // a version 1 alarm
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x71, 0x05, 0x15, 0x01]).unwrap();
+        assert_eq!(
+            Notification::report(&frame),
+            Ok(NotificationEvent::Alarm {
+                alarm_type: 0x15,
+                level: 0x01,
+            })
+        );
+
+        // the parameters are kept for unknown events
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0A, 0x71, 0x05, 0x00, 0x00, 0x00, 0xFF, 0x09, 0x03, 0x01, 0x2A,
+        ])
+        .unwrap();
+        assert_eq!(
+            Notification::report(&frame),
+            Ok(NotificationEvent::Unknown {
+                notification_type: 0x09,
+                event: 0x03,
+                parameters: vec![0x2A],
+            })
+        );
+
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x09, 0x71, 0x05, 0x00, 0x00, 0x00, 0xFF, 0x09, 0x03, 0x02,
+        ])
+        .unwrap();
+        assert!(Notification::report(&frame).is_err());
+    }
+}
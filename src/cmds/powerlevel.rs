@@ -7,14 +7,15 @@
 //!
 //! NOTE: This Command Class is only used in an installation or test situation.
 
-use cmds::{CommandClass, Message};
-use enum_primitive::FromPrimitive;
-use error::{Error, ErrorKind};
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+use std::convert::TryFrom;
 
-enum_from_primitive! {
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
 #[allow(non_camel_case_types)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// List of the different Power level
+#[repr(u8)]
 pub enum PowerLevelStatus {
     NormalPower = 0x00,
     minus1dBm = 0x01,
@@ -26,17 +27,17 @@ pub enum PowerLevelStatus {
     minus7dBm = 0x07,
     minus8dBm = 0x08,
     minus9dBm = 0x09,
-}}
+}
 
-enum_from_primitive! {
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
 #[allow(non_camel_case_types)]
 /// List of the different Operation Status responses from the device.
+#[repr(u8)]
 pub enum PowerLevelOperationStatus {
-TestFailed = 0x00, //No frame was returned during the test
-TestSuccess = 0x01, // At least 1 frame was returned during the test
-TestInProgress = 0x02, //The test is still ongoing
-}}
+    TestFailed = 0x00,     //No frame was returned during the test
+    TestSuccess = 0x01,    // At least 1 frame was returned during the test
+    TestInProgress = 0x02, //The test is still ongoing
+}
 
 /// Power level command class
 #[derive(Debug, Clone)]
@@ -56,11 +57,11 @@ impl PowerLevel {
         S: Into<u8>,
     {
         // generate the message
-        Message::new(
+        Message::from_array(
             node_id.into(),
             CommandClass::POWER_LEVEL,
             0x01,
-            vec![level.into() as u8, seconds.into()],
+            [level.into() as u8, seconds.into()],
         )
     }
 
@@ -70,40 +71,34 @@ impl PowerLevel {
     where
         N: Into<u8>,
     {
-        Message::new(node_id.into(), CommandClass::POWER_LEVEL, 0x02, vec![])
+        Message::from_array(node_id.into(), CommandClass::POWER_LEVEL, 0x02, [])
     }
 
     /// This command is used to advertise the current power level.
     ///
     /// Return the Powerlevel status and the time left on this power level.
-    pub fn report<M>(msg: M) -> Result<(PowerLevelStatus, u8), Error>
-    where
-        M: Into<Vec<u8>>,
-    {
-        // get the message
-        let msg = msg.into();
-
-        // the message need to be exact 7 digits long
-        if msg.len() != 7 {
-            return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
-        }
-
+    pub fn report(frame: &IncomingFrame) -> Result<(PowerLevelStatus, u8), Error> {
         // check the CommandClass and command
-        if msg[3] != CommandClass::POWER_LEVEL as u8 || msg[4] != 0x03 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                "Answer contained wrong command class",
+        let payload = frame.expect(CommandClass::POWER_LEVEL, 0x03)?;
+
+        // the payload need to be exact 2 bytes long
+        if payload.len() != 2 {
+            return Err(Error::parse(
+                CommandClass::POWER_LEVEL,
+                "Message is too short",
             ));
         }
 
         // get the power level state
-        let level = PowerLevelStatus::from_u8(msg[5]).ok_or(Error::new(
-            ErrorKind::UnknownZWave,
-            "Answer contained wrong power level state",
-        ))?;
+        let level = PowerLevelStatus::try_from(payload[0]).map_err(|_| {
+            Error::parse(
+                CommandClass::POWER_LEVEL,
+                "Answer contained wrong power level state",
+            )
+        })?;
 
         // return the values
-        Ok((level, msg[6]))
+        Ok((level, payload[1]))
     }
 
     /// The Powerlevel Test Node Set Command is used to instruct the destination node to transmit
@@ -133,11 +128,11 @@ impl PowerLevel {
         let frames = PowerLevel::transform_u16_to_array_of_u8(test_frames.into());
 
         // generate the message
-        Message::new(
+        Message::from_array(
             node_id.into(),
             CommandClass::POWER_LEVEL,
             0x04,
-            vec![
+            [
                 test_node_id.into(),
                 level.into() as u8,
                 frames[0],
@@ -152,47 +147,40 @@ impl PowerLevel {
     where
         N: Into<u8>,
     {
-        Message::new(node_id.into(), CommandClass::POWER_LEVEL, 0x05, vec![])
+        Message::from_array(node_id.into(), CommandClass::POWER_LEVEL, 0x05, [])
     }
 
     /// This command is used to report the latest result of a test frame
     /// transmission started by the Powerlevel Test Node Set Command.
     ///
     /// Return the test node id, status of operation and the test frane count.
-    pub fn test_node_report<M>(msg: M) -> Result<(u8, PowerLevelOperationStatus, u16), Error>
-    where
-        M: Into<Vec<u8>>,
-    {
-        // get the message
-        let msg = msg.into();
-
-        // the message need to be exact 9 digits long
-        if msg.len() != 9 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                format!("Message is too short: {:?}", msg),
-            ));
-        }
-
+    pub fn test_node_report(
+        frame: &IncomingFrame,
+    ) -> Result<(u8, PowerLevelOperationStatus, u16), Error> {
         // check the CommandClass and command
-        if msg[3] != CommandClass::POWER_LEVEL as u8 || msg[4] != 0x06 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                "Answer contained wrong command class",
+        let payload = frame.expect(CommandClass::POWER_LEVEL, 0x06)?;
+
+        // the payload need to be exact 4 bytes long
+        if payload.len() != 4 {
+            return Err(Error::parse(
+                CommandClass::POWER_LEVEL,
+                format!("Message is too short: {:?}", frame),
             ));
         }
 
         // get the test node id
-        let n_id = msg[5];
+        let n_id = payload[0];
 
         // get the power level state
-        let level = PowerLevelOperationStatus::from_u8(msg[6]).ok_or(Error::new(
-            ErrorKind::UnknownZWave,
-            "Answer contained wrong operation status",
-        ))?;
+        let level = PowerLevelOperationStatus::try_from(payload[1]).map_err(|_| {
+            Error::parse(
+                CommandClass::POWER_LEVEL,
+                "Answer contained wrong operation status",
+            )
+        })?;
 
         // get the frame count
-        let frame = PowerLevel::transform_array_of_u8_to_u16(msg[7], msg[8]);
+        let frame = PowerLevel::transform_array_of_u8_to_u16(payload[2], payload[3]);
 
         // return the values
         Ok((n_id, level, frame))
@@ -202,7 +190,7 @@ impl PowerLevel {
     fn transform_u16_to_array_of_u8(x: u16) -> [u8; 2] {
         let b1: u8 = ((x >> 8) & 0xff) as u8;
         let b2: u8 = (x & 0xff) as u8;
-        return [b1, b2];
+        [b1, b2]
     }
 
     /// transform two u8 into a u16 value
@@ -210,7 +198,7 @@ impl PowerLevel {
         let msb = msb as u16;
         let lsb = lsb as u16;
 
-        ((msb << 8) | lsb)
+        (msb << 8) | lsb
     }
 }
 
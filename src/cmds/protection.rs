@@ -0,0 +1,150 @@
+//! The Protection Command Class is used to protect a node against unwanted
+//! control, like a child lock for the buttons of a wall dimmer.
+//!
+//! Version 1 nodes only know the local protection of their buttons, version
+//! 2 adds the protection against the control over the radio.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+use std::convert::TryFrom;
+
+/// The protection of the local buttons
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum LocalProtection {
+    Unprotected = 0x00,
+    /// The buttons only work with a special sequence, see the manual
+    ProtectedBySequence = 0x01,
+    /// The buttons don't work at all
+    NoOperationPossible = 0x02,
+}
+
+/// The protection against the control over the radio
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum RfProtection {
+    Unprotected = 0x00,
+    /// The node ignores the commands, but still answers the gets
+    NoRfControl = 0x01,
+    /// The node doesn't answer at all
+    NoRfResponse = 0x02,
+}
+
+/// The protection states of a node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtectionState {
+    pub local: LocalProtection,
+    /// Only reported by version 2 nodes
+    pub rf: Option<RfProtection>,
+}
+
+/// Protection Command Class
+#[derive(Debug, Clone)]
+pub struct Protection;
+
+impl Protection {
+    /// The Protection Set Command is used to set the protection states. The
+    /// RF protection is only sent when it's given, which requires version 2
+    /// of the command class.
+    pub fn set<N>(node_id: N, local: LocalProtection, rf: Option<RfProtection>) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |        Command Class = COMMAND_CLASS_PROTECTION(0x75)         |
+        // |                 Command = PROTECTION_SET(0x01)                |
+        // |           Reserved            |         Local State           |
+        // |           Reserved            |          RF State             |
+        // -----------------------------------------------------------------
+        let node_id = node_id.into();
+
+        match rf {
+            Some(rf) => Message::from_array(
+                node_id,
+                CommandClass::PROTECTION,
+                0x01,
+                [local as u8, rf as u8],
+            ),
+            None => Message::from_array(node_id, CommandClass::PROTECTION, 0x01, [local as u8]),
+        }
+    }
+
+    /// The Protection Get Command is used to request the protection states.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::PROTECTION, 0x02, [])
+    }
+
+    /// The Protection Report Command is used to advertise the protection
+    /// states.
+    pub fn report(frame: &IncomingFrame) -> Result<ProtectionState, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::PROTECTION, 0x03)?;
+
+        let local = payload
+            .first()
+            .ok_or_else(|| Error::parse(CommandClass::PROTECTION, "Message is too short"))?;
+
+        let local = LocalProtection::try_from(local & 0x0F).map_err(|_| {
+            Error::parse(CommandClass::PROTECTION, "Unknown local protection state")
+        })?;
+
+        let rf = match payload.get(1) {
+            Some(rf) => Some(RfProtection::try_from(rf & 0x0F).map_err(|_| {
+                Error::parse(CommandClass::PROTECTION, "Unknown RF protection state")
+            })?),
+            None => None,
+        };
+
+        Ok(ProtectionState { local, rf })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = Protection::set(0x05, LocalProtection::NoOperationPossible, None);
+        assert_eq!(msg.data, vec![0x02]);
+
+        let msg = Protection::set(
+            0x05,
+            LocalProtection::Unprotected,
+            Some(RfProtection::NoRfControl),
+        );
+        assert_eq!(msg.data, vec![0x00, 0x01]);
+    }
+
+    #[test]
+    fn report() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x75, 0x03, 0x01]).unwrap();
+        assert_eq!(
+            Protection::report(&frame),
+            Ok(ProtectionState {
+                local: LocalProtection::ProtectedBySequence,
+                rf: None,
+            })
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x75, 0x03, 0x02, 0x02]).unwrap();
+        assert_eq!(
+            Protection::report(&frame),
+            Ok(ProtectionState {
+                local: LocalProtection::NoOperationPossible,
+                rf: Some(RfProtection::NoRfResponse),
+            })
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x75, 0x03, 0x03]).unwrap();
+        assert!(Protection::report(&frame).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! Typed reports
+//!
+//! Decodes any command received from a node with the decoder of its command
+//! class, so the receiver can match on the report instead of reading the
+//! payload bytes.
+
+#[cfg(feature = "cc-antitheft")]
+use crate::cmds::antitheft::{AntiTheft, AntiTheftState};
+use crate::cmds::association::{Association, AssociationReport};
+use crate::cmds::association_group_info::AssociationGroupInfo;
+use crate::cmds::basic::Basic;
+use crate::cmds::battery::{Battery, BatteryLevel};
+#[cfg(feature = "cc-central-scene")]
+use crate::cmds::central_scene::{CentralScene, CentralSceneNotification};
+use crate::cmds::configuration::Configuration;
+use crate::cmds::crc16::Crc16;
+#[cfg(feature = "cc-entry-control")]
+use crate::cmds::entry_control::{EntryControl, EntryControlNotification};
+#[cfg(feature = "cc-humidity")]
+use crate::cmds::humidity_control_setpoint::{HumidityControlSetpoint, HumiditySetpoint};
+#[cfg(feature = "cc-meter")]
+use crate::cmds::meter::Meter;
+#[cfg(feature = "cc-meter")]
+use crate::cmds::meter_pulse::MeterPulse;
+use crate::cmds::multi_channel::MultiChannel;
+use crate::cmds::multi_channel_association::{
+    MultiChannelAssociation, MultiChannelAssociationReport,
+};
+use crate::cmds::multi_cmd::MultiCmd;
+use crate::cmds::notification::{Notification, NotificationEvent};
+#[cfg(feature = "cc-powerlevel")]
+use crate::cmds::powerlevel::{PowerLevel, PowerLevelOperationStatus, PowerLevelStatus};
+#[cfg(feature = "cc-protection")]
+use crate::cmds::protection::{Protection, ProtectionState};
+#[cfg(feature = "cc-scene-controller")]
+use crate::cmds::scene_controller_conf::{ButtonScene, SceneControllerConf};
+use crate::cmds::sensor_alarm::{AlarmSensorReport, SensorAlarm};
+use crate::cmds::sensor_configuration::SensorConfiguration;
+use crate::cmds::sensor_multilevel::SensorMultilevel;
+use crate::cmds::supervision::Supervision;
+use crate::cmds::switch_binary::SwitchBinary;
+use crate::cmds::switch_multilevel::SwitchMultilevel;
+#[cfg(feature = "cc-thermostat")]
+use crate::cmds::thermostat_operating_state::{OperatingState, ThermostatOperatingState};
+#[cfg(feature = "cc-thermostat")]
+use crate::cmds::thermostat_setpoint::{TemperatureSetpoint, ThermostatSetpoint};
+#[cfg(feature = "cc-user-code")]
+use crate::cmds::user_code::{UserCode, UserCodeCapabilities, UserCodeReport};
+use crate::cmds::version::{Version, VersionReport};
+use crate::cmds::wake_up::{WakeUp, WakeUpCapabilities, WakeUpInterval};
+#[cfg(feature = "cc-window-covering")]
+use crate::cmds::window_covering::{WindowCovering, WindowCoveringState};
+#[cfg(feature = "cc-meter")]
+use crate::cmds::MeterData;
+use crate::cmds::{CommandClass, IncomingFrame, SensorValue};
+use crate::defs::GenericType;
+use crate::error::Error;
+
+/// A report, which was received from a node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Report {
+    /// The level of the Basic Command Class
+    Basic(u8),
+    /// The state of a binary switch
+    SwitchBinary(bool),
+    /// The level of a multilevel switch
+    SwitchMultilevel(u8),
+    /// The state of a binary sensor
+    SensorBinary(bool),
+    /// The lock is secured
+    DoorLock(bool),
+    /// A measured value of a multilevel sensor
+    SensorMultilevel(SensorValue),
+    /// The state of an alarm sensor, which predates the notifications
+    SensorAlarm(AlarmSensorReport),
+    /// The trigger level of a sensor
+    SensorTriggerLevel(SensorValue),
+    /// The level of a movement of a window covering
+    #[cfg(feature = "cc-window-covering")]
+    WindowCovering(WindowCoveringState),
+    /// What the heating or cooling system is doing
+    #[cfg(feature = "cc-thermostat")]
+    ThermostatOperatingState(OperatingState),
+    /// The battery level of the node
+    Battery(BatteryLevel),
+    /// A meter reading
+    #[cfg(feature = "cc-meter")]
+    Meter(MeterData),
+    /// The number of pulses, which a pulse meter counted
+    #[cfg(feature = "cc-meter")]
+    MeterPulse(u32),
+    /// An event like a detected motion or smoke
+    Notification(NotificationEvent),
+    /// A button of a remote or a scene keypad was used
+    #[cfg(feature = "cc-central-scene")]
+    CentralScene(CentralSceneNotification),
+    /// An entry at a keypad, like a PIN code or the arm button
+    #[cfg(feature = "cc-entry-control")]
+    EntryControl(EntryControlNotification),
+    /// The nodes of an association group
+    Association(AssociationReport),
+    /// The amount of association groups
+    AssociationGroupings(u8),
+    /// The group id and the name of an association group
+    AssociationGroupName(u8, String),
+    /// The nodes and end points of an association group
+    MultiChannelAssociation(MultiChannelAssociationReport),
+    /// The amount of multi channel association groups
+    MultiChannelAssociationGroupings(u8),
+    /// The power level and the seconds it is kept
+    #[cfg(feature = "cc-powerlevel")]
+    PowerLevel(PowerLevelStatus, u8),
+    /// The test node, the status and the acknowledged frames of a power level test
+    #[cfg(feature = "cc-powerlevel")]
+    PowerLevelTest(u8, PowerLevelOperationStatus, u16),
+    /// A user code and its status
+    #[cfg(feature = "cc-user-code")]
+    UserCode(UserCodeReport),
+    /// The amount of user codes
+    #[cfg(feature = "cc-user-code")]
+    UsersNumber(u8),
+    /// The supported keys of the user codes
+    #[cfg(feature = "cc-user-code")]
+    UserCodeCapabilities(UserCodeCapabilities),
+    /// The number and the value of a configuration parameter
+    Configuration(u8, i32),
+    /// The library type, the protocol version and the firmware versions
+    Version(VersionReport),
+    /// The version of a command class
+    CommandClassVersion(CommandClass, u8),
+    /// The protection states of the node
+    #[cfg(feature = "cc-protection")]
+    Protection(ProtectionState),
+    /// The theft protection status of the node
+    #[cfg(feature = "cc-antitheft")]
+    AntiTheft(AntiTheftState),
+    /// The humidity of a setpoint of a humidifier or a dehumidifier
+    #[cfg(feature = "cc-humidity")]
+    HumiditySetpoint(HumiditySetpoint),
+    /// The temperature of a setpoint of a thermostat
+    #[cfg(feature = "cc-thermostat")]
+    ThermostatSetpoint(TemperatureSetpoint),
+    /// The scene, which a button group of a wall controller activates
+    #[cfg(feature = "cc-scene-controller")]
+    SceneControllerConf(ButtonScene),
+    /// The wake up interval and the node, which gets the notifications
+    WakeUpInterval(WakeUpInterval),
+    /// The node woke up and listens now
+    WakeUpNotification,
+    /// The wake up intervals, which the node supports
+    WakeUpCapabilities(WakeUpCapabilities),
+    /// A report of an end point of the node
+    Endpoint(u8, Box<Report>),
+    /// Several reports, which the node sent in a single frame
+    Multi(Vec<Report>),
+    /// A command, which has no decoder
+    Unknown(IncomingFrame),
+}
+
+impl Report {
+    /// Decode the frame with the decoder of its command class and command.
+    ///
+    /// Commands without a decoder are returned as `Report::Unknown`, an error
+    /// is only returned when a known report is malformed.
+    pub fn parse(frame: &IncomingFrame) -> Result<Report, Error> {
+        let command_class = match frame.command_class {
+            Ok(command_class) => command_class,
+            Err(_) => return Ok(Report::Unknown(frame.clone())),
+        };
+
+        let report = match (command_class, frame.command) {
+            (CommandClass::BASIC, 0x03) => Report::Basic(Basic::report(frame)?),
+            (CommandClass::SWITCH_BINARY, 0x03) => {
+                Report::SwitchBinary(SwitchBinary::report(frame)?)
+            }
+            (CommandClass::SWITCH_MULTILEVEL, 0x03) => {
+                Report::SwitchMultilevel(SwitchMultilevel::report(frame)?)
+            }
+            // the sensor types without a unit have no decoder yet
+            (CommandClass::SENSOR_MULTILEVEL, 0x05)
+                if frame.payload.len() < 2
+                    || SensorMultilevel::unit(frame.payload[0], (frame.payload[1] >> 3) & 0x03)
+                        .is_some() =>
+            {
+                Report::SensorMultilevel(SensorMultilevel::report(frame)?)
+            }
+            (CommandClass::SENSOR_ALARM, 0x02) => Report::SensorAlarm(SensorAlarm::report(frame)?),
+            (CommandClass::SENSOR_CONFIGURATION, 0x03) => {
+                Report::SensorTriggerLevel(SensorConfiguration::report(frame)?)
+            }
+            #[cfg(feature = "cc-window-covering")]
+            (CommandClass::WINDOW_COVERING, 0x04) => {
+                Report::WindowCovering(WindowCovering::report(frame)?)
+            }
+            #[cfg(feature = "cc-thermostat")]
+            (CommandClass::THERMOSTAT_OPERATING_STATE, 0x03) => {
+                Report::ThermostatOperatingState(ThermostatOperatingState::report(frame)?)
+            }
+            (CommandClass::BATTERY, 0x03) => Report::Battery(Battery::report(frame)?),
+            // version 1 reports have no previous value
+            #[cfg(feature = "cc-meter")]
+            (CommandClass::METER, 0x02) => match Meter::report(frame) {
+                Ok(data) => Report::Meter(data),
+                Err(_) => Report::Meter(Meter::report_v2(frame)?.0),
+            },
+            #[cfg(feature = "cc-meter")]
+            (CommandClass::METER_PULSE, 0x05) => Report::MeterPulse(MeterPulse::report(frame)?),
+            (CommandClass::ALARM, 0x05) => Report::Notification(Notification::report(frame)?),
+            #[cfg(feature = "cc-central-scene")]
+            (CommandClass::CENTRAL_SCENE, 0x03) => {
+                Report::CentralScene(CentralScene::notification(frame)?)
+            }
+            #[cfg(feature = "cc-entry-control")]
+            (CommandClass::ENTRY_CONTROL, 0x01) => {
+                Report::EntryControl(EntryControl::notification(frame)?)
+            }
+            (CommandClass::ASSOCIATION, 0x03) => Report::Association(Association::report(frame)?),
+            (CommandClass::ASSOCIATION, 0x06) => {
+                Report::AssociationGroupings(Association::groupings_report(frame)?)
+            }
+            (CommandClass::ASSOCIATION_GRP_INFO, 0x02) => {
+                let (group, name) = AssociationGroupInfo::name_report(frame)?;
+                Report::AssociationGroupName(group, name)
+            }
+            (CommandClass::MULTI_INSTANCE_ASSOCIATION, 0x03) => {
+                Report::MultiChannelAssociation(MultiChannelAssociation::report(frame)?)
+            }
+            (CommandClass::MULTI_INSTANCE_ASSOCIATION, 0x06) => {
+                Report::MultiChannelAssociationGroupings(MultiChannelAssociation::groupings_report(
+                    frame,
+                )?)
+            }
+            #[cfg(feature = "cc-powerlevel")]
+            (CommandClass::POWER_LEVEL, 0x03) => {
+                let (level, seconds) = PowerLevel::report(frame)?;
+                Report::PowerLevel(level, seconds)
+            }
+            #[cfg(feature = "cc-powerlevel")]
+            (CommandClass::POWER_LEVEL, 0x06) => {
+                let (node, status, frames) = PowerLevel::test_node_report(frame)?;
+                Report::PowerLevelTest(node, status, frames)
+            }
+            #[cfg(feature = "cc-user-code")]
+            (CommandClass::USER_CODE, 0x03) => Report::UserCode(UserCode::report(frame)?),
+            #[cfg(feature = "cc-user-code")]
+            (CommandClass::USER_CODE, 0x05) => {
+                Report::UsersNumber(UserCode::users_number_report(frame)?)
+            }
+            #[cfg(feature = "cc-user-code")]
+            (CommandClass::USER_CODE, 0x07) => {
+                Report::UserCodeCapabilities(UserCode::capabilities_report(frame)?)
+            }
+            (CommandClass::CONFIGURATION, 0x06) => {
+                let (parameter, value) = Configuration::report(frame)?;
+                Report::Configuration(parameter, value)
+            }
+            (CommandClass::VERSION, 0x12) => Report::Version(Version::report(frame)?),
+            (CommandClass::VERSION, 0x14) => {
+                let (cmd_class, version) = Version::command_class_report(frame)?;
+                Report::CommandClassVersion(cmd_class, version)
+            }
+            #[cfg(feature = "cc-protection")]
+            (CommandClass::PROTECTION, 0x03) => Report::Protection(Protection::report(frame)?),
+            #[cfg(feature = "cc-antitheft")]
+            (CommandClass::ANTITHEFT, 0x03) => Report::AntiTheft(AntiTheft::report(frame)?),
+            #[cfg(feature = "cc-humidity")]
+            (CommandClass::HUMIDITY_CONTROL_SETPOINT, 0x03) => {
+                Report::HumiditySetpoint(HumidityControlSetpoint::report(frame)?)
+            }
+            #[cfg(feature = "cc-thermostat")]
+            (CommandClass::THERMOSTAT_SETPOINT, 0x03) => {
+                Report::ThermostatSetpoint(ThermostatSetpoint::report(frame)?)
+            }
+            #[cfg(feature = "cc-scene-controller")]
+            (CommandClass::SCENE_CONTROLLER_CONF, 0x03) => {
+                Report::SceneControllerConf(SceneControllerConf::report(frame)?)
+            }
+            (CommandClass::WAKE_UP, 0x06) => {
+                Report::WakeUpInterval(WakeUp::interval_report(frame)?)
+            }
+            (CommandClass::WAKE_UP, 0x07) => Report::WakeUpNotification,
+            (CommandClass::WAKE_UP, 0x0A) => {
+                Report::WakeUpCapabilities(WakeUp::capabilities_report(frame)?)
+            }
+            (CommandClass::MULTI_INSTANCE, 0x0D) => {
+                let (endpoint, inner) = MultiChannel::decap(frame)?;
+                Report::Endpoint(endpoint, Box::new(Report::parse(&inner)?))
+            }
+            (CommandClass::MULTI_CMD, 0x01) => Report::Multi(
+                MultiCmd::decap(frame)?
+                    .iter()
+                    .map(Report::parse)
+                    .collect::<Result<_, _>>()?,
+            ),
+            // the checksum and the supervision don't change the command
+            (CommandClass::CRC_16_ENCAP, 0x01) => Report::parse(&Crc16::decap(frame)?)?,
+            (CommandClass::SUPERVISION, 0x01) => Report::parse(&Supervision::decap(frame)?.1)?,
+            _ => Report::Unknown(frame.clone()),
+        };
+
+        Ok(report)
+    }
+
+    /// Decode the frame like `Report::parse`, but map the basic report and
+    /// the basic set to the primary command class of the generic device
+    /// type. Many nodes only send basic commands about their state.
+    pub fn parse_mapped(frame: &IncomingFrame, generic: GenericType) -> Result<Report, Error> {
+        Ok(Report::parse(frame)?.map_basic(generic))
+    }
+
+    /// Map a basic level to the report of the primary command class of the
+    /// generic device type.
+    pub fn map_basic(self, generic: GenericType) -> Report {
+        let level = match self {
+            Report::Basic(level) => level,
+            Report::Unknown(ref frame) => match Basic::set_value(frame) {
+                Ok(level) => level,
+                Err(_) => return self,
+            },
+            Report::Endpoint(endpoint, report) => {
+                return Report::Endpoint(endpoint, Box::new(report.map_basic(generic)))
+            }
+            Report::Multi(reports) => {
+                return Report::Multi(reports.into_iter().map(|r| r.map_basic(generic)).collect())
+            }
+            _ => return self,
+        };
+
+        match Basic::mapped_class(generic) {
+            Some(CommandClass::SWITCH_BINARY) => Report::SwitchBinary(level > 0),
+            Some(CommandClass::SWITCH_MULTILEVEL) => Report::SwitchMultilevel(level),
+            Some(CommandClass::DOOR_LOCK) => Report::DoorLock(level == 0xFF),
+            Some(CommandClass::SENSOR_BINARY) => Report::SensorBinary(level > 0),
+            _ => Report::Basic(level),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::Unit;
+
+    #[test]
+    fn parse() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x25, 0x03, 0xFF]).unwrap();
+        assert_eq!(Report::parse(&frame).unwrap(), Report::SwitchBinary(true));
+
+        // a report of the second end point
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x60, 0x0D, 0x02, 0x00, 0x20, 0x03, 0x63])
+                .unwrap();
+        assert_eq!(
+            Report::parse(&frame).unwrap(),
+            Report::Endpoint(2, Box::new(Report::Basic(0x63)))
+        );
+
+        // a report protected by a checksum
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x56, 0x01, 0x20, 0x03, 0x63, 0xD0, 0x9D])
+                .unwrap();
+        assert_eq!(Report::parse(&frame).unwrap(), Report::Basic(0x63));
+
+        // a temperature and a sensor type without a decoder
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x31, 0x05, 0x01, 0x22, 0x00, 0xD7]).unwrap();
+        assert_eq!(
+            Report::parse(&frame).unwrap(),
+            Report::SensorMultilevel(SensorValue::new(21.5, Unit::Celsius))
+        );
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x31, 0x05, 0x40, 0x01, 0x2D]).unwrap();
+        assert_eq!(Report::parse(&frame).unwrap(), Report::Unknown(frame));
+
+        // a low battery warning
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x80, 0x03, 0xFF]).unwrap();
+        assert_eq!(
+            Report::parse(&frame).unwrap(),
+            Report::Battery(BatteryLevel::Low)
+        );
+
+        // a sleeping node woke up
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x02, 0x84, 0x07]).unwrap();
+        assert_eq!(Report::parse(&frame).unwrap(), Report::WakeUpNotification);
+
+        // a battery report and the wake up notification in a single frame
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0A, 0x8F, 0x01, 0x02, 0x03, 0x80, 0x03, 0xFF, 0x02, 0x84, 0x07,
+        ])
+        .unwrap();
+        assert_eq!(
+            Report::parse(&frame).unwrap(),
+            Report::Multi(vec![
+                Report::Battery(BatteryLevel::Low),
+                Report::WakeUpNotification
+            ])
+        );
+
+        // the basic set has no decoder
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x20, 0x01, 0xFF]).unwrap();
+        assert_eq!(Report::parse(&frame).unwrap(), Report::Unknown(frame));
+
+        // a command class, which isn't known at all
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0xF1, 0x01, 0x02]).unwrap();
+        assert_eq!(Report::parse(&frame).unwrap(), Report::Unknown(frame));
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x02, 0x25, 0x03]).unwrap();
+        assert!(Report::parse(&frame).is_err());
+    }
+
+    #[test]
+    fn parse_mapped() {
+        // the basic report of a dimmer
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x20, 0x03, 0x32]).unwrap();
+        assert_eq!(
+            Report::parse_mapped(&frame, GenericType::MultiLevelSwitch).unwrap(),
+            Report::SwitchMultilevel(0x32)
+        );
+
+        // the basic set of a motion sensor
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x20, 0x01, 0xFF]).unwrap();
+        assert_eq!(
+            Report::parse_mapped(&frame, GenericType::BinarySensor).unwrap(),
+            Report::SensorBinary(true)
+        );
+
+        // the basic report of a lock end point
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x60, 0x0D, 0x02, 0x00, 0x20, 0x03, 0xFF])
+                .unwrap();
+        assert_eq!(
+            Report::parse_mapped(&frame, GenericType::EntryControl).unwrap(),
+            Report::Endpoint(2, Box::new(Report::DoorLock(true)))
+        );
+
+        // the controllers keep the basic level
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x20, 0x03, 0x32]).unwrap();
+        assert_eq!(
+            Report::parse_mapped(&frame, GenericType::StaticController).unwrap(),
+            Report::Basic(0x32)
+        );
+    }
+}
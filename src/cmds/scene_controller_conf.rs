@@ -0,0 +1,126 @@
+//! The Scene Controller Configuration Command Class is used to define which
+//! scene a wall controller activates, when a button of a group is pressed.
+//!
+//! The scene 0 disables the group, so the button doesn't activate a scene.
+
+use crate::cmds::{decode_duration, encode_duration, CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+use std::time::Duration;
+
+/// The scene, which a button group of the controller activates.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ButtonScene {
+    pub group: u8,
+    pub scene: u8,
+    /// The duration to reach the scene, `None` is the factory default
+    pub duration: Option<Duration>,
+}
+
+/// Scene Controller Configuration Command Class
+#[derive(Debug, Clone)]
+pub struct SceneControllerConf;
+
+impl SceneControllerConf {
+    /// The Scene Controller Configuration Set Command is used to assign a
+    /// scene to a button group.
+    pub fn set<N>(
+        node_id: N,
+        group: u8,
+        scene: u8,
+        duration: Option<Duration>,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |   Command Class = COMMAND_CLASS_SCENE_CONTROLLER_CONF(0x2D)   |
+        // |           Command = SCENE_CONTROLLER_CONF_SET(0x01)           |
+        // |                            Group ID                           |
+        // |                            Scene ID                           |
+        // |                        Dimming Duration                       |
+        // -----------------------------------------------------------------
+        if group == 0 {
+            return Err(Error::parse(
+                CommandClass::SCENE_CONTROLLER_CONF,
+                "The group id need to be greater than 0",
+            ));
+        }
+
+        Ok(Message::from_array(
+            node_id.into(),
+            CommandClass::SCENE_CONTROLLER_CONF,
+            0x01,
+            [group, scene, encode_duration(duration)?],
+        ))
+    }
+
+    /// The Scene Controller Configuration Get Command is used to request the
+    /// scene of a button group. The group 0 requests the group, which was
+    /// activated last.
+    pub fn get<N>(node_id: N, group: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::SCENE_CONTROLLER_CONF,
+            0x02,
+            [group],
+        )
+    }
+
+    /// The Scene Controller Configuration Report Command is used to advertise
+    /// the scene of a button group.
+    pub fn report(frame: &IncomingFrame) -> Result<ButtonScene, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SCENE_CONTROLLER_CONF, 0x03)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::SCENE_CONTROLLER_CONF,
+                "Message is too short",
+            ));
+        }
+
+        Ok(ButtonScene {
+            group: payload[0],
+            scene: payload[1],
+            duration: decode_duration(payload[2]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = SceneControllerConf::set(0x05, 0x02, 0x0A, Some(Duration::from_secs(3))).unwrap();
+        assert_eq!(msg.data, vec![0x02, 0x0A, 0x03]);
+
+        let msg = SceneControllerConf::set(0x05, 0x02, 0x00, None).unwrap();
+        assert_eq!(msg.data, vec![0x02, 0x00, 0xFF]);
+
+        assert!(SceneControllerConf::set(0x05, 0x00, 0x0A, None).is_err());
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x2D, 0x03, 0x02, 0x0A, 0x82]).unwrap();
+        assert_eq!(
+            SceneControllerConf::report(&frame),
+            Ok(ButtonScene {
+                group: 0x02,
+                scene: 0x0A,
+                duration: Some(Duration::from_secs(180)),
+            })
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x2D, 0x03, 0x02, 0x0A]).unwrap();
+        assert!(SceneControllerConf::report(&frame).is_err());
+    }
+}
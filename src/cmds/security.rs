@@ -0,0 +1,437 @@
+//! The Security Command Class (S0) is used to send the commands encrypted
+//! and authenticated to the nodes, which were included securely. Door locks
+//! for example refuse the commands, which are sent without it.
+//!
+//! Every encrypted command needs a nonce of the receiver, which is requested
+//! with a Nonce Get right before and is only valid once. The command is
+//! encrypted with AES-OFB and authenticated with an AES CBC-MAC, both with
+//! keys derived from the network key.
+
+use crate::cmds::aes::{Aes128, BLOCK_SIZE};
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+use crate::payload::Payload;
+
+use aes::cipher::{BlockEncryptMut, InnerIvInit};
+use subtle::ConstantTimeEq;
+
+use std::convert::TryFrom;
+
+/// The size of a nonce in bytes
+pub const NONCE_SIZE: usize = 8;
+
+/// The size of the authentication code in bytes
+const MAC_SIZE: usize = 8;
+
+/// The bytes an encapsulation adds to a command, the sender nonce, the
+/// sequence byte, the receiver nonce id and the authentication code
+pub const OVERHEAD: usize = NONCE_SIZE + 2 + MAC_SIZE;
+
+/// The keys of the encryption and the authentication, which are derived
+/// from the network key.
+#[derive(Debug, Clone)]
+pub struct SecurityKeys {
+    encryption: Aes128,
+    authentication: Aes128,
+}
+
+impl SecurityKeys {
+    /// Derive the keys from the network key
+    pub fn new(network_key: &[u8; BLOCK_SIZE]) -> SecurityKeys {
+        let cipher = Aes128::new(network_key);
+
+        SecurityKeys {
+            encryption: Aes128::new(&cipher.encrypt(&[0xAA; BLOCK_SIZE])),
+            authentication: Aes128::new(&cipher.encrypt(&[0x55; BLOCK_SIZE])),
+        }
+    }
+
+    /// Encrypt or decrypt the data in the output feedback mode
+    fn apply_keystream(&self, iv: &[u8; BLOCK_SIZE], data: &mut [u8]) {
+        let mut stream = *iv;
+        for chunk in data.chunks_mut(BLOCK_SIZE) {
+            stream = self.encryption.encrypt(&stream);
+            for (byte, key) in chunk.iter_mut().zip(stream.iter()) {
+                *byte ^= key;
+            }
+        }
+    }
+
+    /// Calculate the CBC-MAC over the header and the encrypted command
+    fn authenticate(
+        &self,
+        iv: &[u8; BLOCK_SIZE],
+        command: u8,
+        source: u8,
+        destination: u8,
+        encrypted: &[u8],
+    ) -> [u8; MAC_SIZE] {
+        let mut data = vec![command, source, destination, encrypted.len() as u8];
+        data.extend_from_slice(encrypted);
+
+        // the CBC encryption of the initialization vector and the data,
+        // which is padded with zeros, ends with the authentication code
+        let mut blocks: Vec<aes::Block> = std::iter::once(&iv[..])
+            .chain(data.chunks(BLOCK_SIZE))
+            .map(|chunk| {
+                let mut block = aes::Block::default();
+                block[..chunk.len()].copy_from_slice(chunk);
+                block
+            })
+            .collect();
+        cbc::Encryptor::inner_iv_init(self.authentication.cipher(), &Default::default())
+            .encrypt_blocks_mut(&mut blocks);
+        let mac = blocks[blocks.len() - 1];
+
+        let mut code = [0u8; MAC_SIZE];
+        code.copy_from_slice(&mac[..MAC_SIZE]);
+        code
+    }
+}
+
+/// Security Command Class
+#[derive(Debug, Clone)]
+pub struct Security;
+
+impl Security {
+    /// The Security Commands Supported Get Command is used to request the
+    /// command classes, which the node only supports encrypted.
+    pub fn commands_supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SECURITY, 0x02, [])
+    }
+
+    /// The Security Commands Supported Report Command is used to advertise
+    /// the command classes, which the node only supports encrypted. Returns
+    /// the reports to follow and the supported command classes, the
+    /// controlled ones after the mark are left out.
+    pub fn commands_supported_report(
+        frame: &IncomingFrame,
+    ) -> Result<(u8, Vec<CommandClass>), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SECURITY, 0x03)?;
+
+        let follow = payload
+            .first()
+            .cloned()
+            .ok_or_else(|| Error::parse(CommandClass::SECURITY, "Message is too short"))?;
+
+        // the unknown command classes are skipped
+        let cmds = payload[1..]
+            .iter()
+            .take_while(|c| **c != CommandClass::MARK as u8)
+            .filter_map(|c| CommandClass::try_from(*c).ok())
+            .collect();
+
+        Ok((follow, cmds))
+    }
+
+    /// The Security Scheme Get Command is used to start the S0 key exchange
+    /// with a node, which was just included. The controller supports the
+    /// scheme 0.
+    pub fn scheme_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SECURITY, 0x04, [0x00])
+    }
+
+    /// The Security Scheme Report Command is used to advertise the security
+    /// schemes, which the node supports.
+    pub fn scheme_report(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SECURITY, 0x05)?;
+
+        payload
+            .first()
+            .cloned()
+            .ok_or_else(|| Error::parse(CommandClass::SECURITY, "Message is too short"))
+    }
+
+    /// The Network Key Set Command is used to hand the network key to a node,
+    /// which was just included. It's encrypted with a temporary key of zeros.
+    pub fn network_key_set<N>(node_id: N, key: [u8; BLOCK_SIZE]) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SECURITY, 0x06, key)
+    }
+
+    /// The Network Key Verify Command is used by the node to confirm the
+    /// network key, it's encrypted with the new key.
+    pub fn network_key_verify(frame: &IncomingFrame) -> Result<(), Error> {
+        frame.expect(CommandClass::SECURITY, 0x07)?;
+        Ok(())
+    }
+
+    /// The Security Nonce Get Command is used to request a nonce of the
+    /// node, to encrypt the next command with.
+    pub fn nonce_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SECURITY, 0x40, [])
+    }
+
+    /// The Security Nonce Report Command is used to hand out a nonce, to
+    /// encrypt the next command with.
+    pub fn nonce_report_message<N>(node_id: N, nonce: [u8; NONCE_SIZE]) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SECURITY, 0x80, nonce)
+    }
+
+    /// The Security Nonce Report Command is used to advertise the nonce of
+    /// the node.
+    pub fn nonce_report(frame: &IncomingFrame) -> Result<[u8; NONCE_SIZE], Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SECURITY, 0x80)?;
+
+        if payload.len() < NONCE_SIZE {
+            return Err(Error::parse(CommandClass::SECURITY, "Message is too short"));
+        }
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&payload[..NONCE_SIZE]);
+        Ok(nonce)
+    }
+
+    /// The Security Message Encapsulation Command is used to encrypt and
+    /// authenticate the command with the nonce of the sender and the nonce
+    /// of the receiver. The source is the node id of the controller.
+    pub fn encap(
+        message: Message,
+        keys: &SecurityKeys,
+        source: u8,
+        sender_nonce: [u8; NONCE_SIZE],
+        receiver_nonce: [u8; NONCE_SIZE],
+    ) -> Result<Message, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |          Command Class = COMMAND_CLASS_SECURITY(0x98)         |
+        // |         Command = SECURITY_MESSAGE_ENCAPSULATION(0x81)        |
+        // |               Initialization Vector 1 ... 8                   |
+        // |   Reserved    |Second | Seq.  |       Sequence Counter        |
+        // |            Command Class, Command, Parameter 1 ... n          |
+        // |                   Receiver's Nonce Identifier                 |
+        // |                 Message Authentication Code 1 ... 8           |
+        // -----------------------------------------------------------------
+
+        // the commands are never sequenced
+        let mut encrypted = vec![0x00, message.cmd_class as u8, message.cmd];
+        encrypted.extend_from_slice(&message.data);
+
+        let iv = initialization_vector(&sender_nonce, &receiver_nonce);
+        keys.apply_keystream(&iv, &mut encrypted);
+        let mac = keys.authenticate(&iv, 0x81, source, message.node_id, &encrypted);
+
+        let mut data = sender_nonce.to_vec();
+        data.extend_from_slice(&encrypted);
+        data.push(receiver_nonce[0]);
+        data.extend_from_slice(&mac);
+
+        Ok(Message {
+            node_id: message.node_id,
+            cmd_class: CommandClass::SECURITY,
+            cmd: 0x81,
+            data: Payload::try_from(&data[..])?,
+            raw: Payload::new(),
+        })
+    }
+
+    /// Returns the id of the receiver nonce, which a Security Message
+    /// Encapsulation was encrypted with.
+    pub fn nonce_id(frame: &IncomingFrame) -> Result<u8, Error> {
+        let payload = Security::encapsulated(frame)?;
+
+        Ok(payload[payload.len() - MAC_SIZE - 1])
+    }
+
+    /// Check the authentication code of a Security Message Encapsulation and
+    /// decrypt the command. The destination is the node id of the
+    /// controller and the receiver nonce the one, which was handed out to
+    /// the node. The Encapsulation Nonce Get (0xC1) is decrypted the same.
+    pub fn decap(
+        frame: &IncomingFrame,
+        keys: &SecurityKeys,
+        destination: u8,
+        receiver_nonce: [u8; NONCE_SIZE],
+    ) -> Result<IncomingFrame, Error> {
+        let payload = Security::encapsulated(frame)?;
+
+        let (sender_nonce, rest) = payload.split_at(NONCE_SIZE);
+        let (encrypted, rest) = rest.split_at(rest.len() - MAC_SIZE - 1);
+        let (nonce_id, mac) = (rest[0], &rest[1..]);
+
+        if nonce_id != receiver_nonce[0] {
+            return Err(Error::parse(
+                CommandClass::SECURITY,
+                "The command was encrypted with another nonce",
+            ));
+        }
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(sender_nonce);
+        let iv = initialization_vector(&nonce, &receiver_nonce);
+        let expected = keys.authenticate(&iv, frame.command, frame.source, destination, encrypted);
+        if !bool::from(expected[..].ct_eq(mac)) {
+            return Err(Error::parse(
+                CommandClass::SECURITY,
+                "The authentication code doesn't match",
+            ));
+        }
+
+        let mut plain = encrypted.to_vec();
+        keys.apply_keystream(&iv, &mut plain);
+
+        // the sequenced commands are split up into two frames
+        if plain[0] & 0x10 != 0 {
+            return Err(Error::new(
+                ErrorKind::NotImplemented,
+                "Sequenced S0 commands are not supported",
+            ));
+        }
+
+        let command_class = CommandClass::try_from(plain[1]).map_err(|_| plain[1]);
+
+        Ok(IncomingFrame {
+            status: frame.status,
+            source: frame.source,
+            command_class,
+            command: plain[2],
+            payload: plain[3..].to_vec(),
+        })
+    }
+
+    /// Returns the payload of a Security Message Encapsulation, which holds
+    /// at least the sequence byte, the command class and the command.
+    fn encapsulated(frame: &IncomingFrame) -> Result<&[u8], Error> {
+        if frame.command_class != Ok(CommandClass::SECURITY)
+            || (frame.command != 0x81 && frame.command != 0xC1)
+        {
+            return Err(Error::parse(
+                CommandClass::SECURITY,
+                "The frame is no Security Message Encapsulation",
+            ));
+        }
+
+        if frame.payload.len() < OVERHEAD + 2 {
+            return Err(Error::parse(CommandClass::SECURITY, "Message is too short"));
+        }
+
+        Ok(&frame.payload)
+    }
+}
+
+/// The initialization vector is the nonce of the sender and the receiver
+fn initialization_vector(
+    sender_nonce: &[u8; NONCE_SIZE],
+    receiver_nonce: &[u8; NONCE_SIZE],
+) -> [u8; BLOCK_SIZE] {
+    let mut iv = [0u8; BLOCK_SIZE];
+    iv[..NONCE_SIZE].copy_from_slice(sender_nonce);
+    iv[NONCE_SIZE..].copy_from_slice(receiver_nonce);
+    iv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::switch_binary::SwitchBinary;
+    use crate::defs::RxStatus;
+
+    const KEY: [u8; BLOCK_SIZE] = [
+        0x0F, 0x1E, 0x2D, 0x3C, 0x4B, 0x5A, 0x69, 0x78, 0x87, 0x96, 0xA5, 0xB4, 0xC3, 0xD2, 0xE1,
+        0xF0,
+    ];
+
+    const SENDER: [u8; NONCE_SIZE] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+    const RECEIVER: [u8; NONCE_SIZE] = [0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8];
+
+    #[test]
+    fn encap_and_decap() {
+        let keys = SecurityKeys::new(&KEY);
+        let msg =
+            Security::encap(SwitchBinary::set(0x05, true), &keys, 0x01, SENDER, RECEIVER).unwrap();
+        assert_eq!(msg.cmd_class, CommandClass::SECURITY);
+        assert_eq!(msg.data.len(), 3 + OVERHEAD);
+        assert_eq!(msg.data[..NONCE_SIZE], SENDER);
+        assert_eq!(msg.data[NONCE_SIZE + 4], RECEIVER[0]);
+
+        // the command is encrypted
+        assert_ne!(msg.data[NONCE_SIZE + 1..NONCE_SIZE + 4], [0x25, 0x01, 0xFF]);
+
+        // the node decrypts it the same way, as the controller its answers
+        let mut frame = received(&msg, 0x01);
+        assert_eq!(Security::nonce_id(&frame), Ok(RECEIVER[0]));
+        let plain = Security::decap(&frame, &keys, 0x05, RECEIVER).unwrap();
+        assert_eq!(plain.command_class, Ok(CommandClass::SWITCH_BINARY));
+        assert_eq!((plain.command, plain.payload), (0x01, vec![0xFF]));
+
+        // the sender, the nonce and the encrypted command are authenticated
+        assert!(Security::decap(&frame, &keys, 0x06, RECEIVER).is_err());
+        assert!(Security::decap(&frame, &SecurityKeys::new(&[0; 16]), 0x05, RECEIVER).is_err());
+        frame.payload[NONCE_SIZE + 2] ^= 0x01;
+        assert!(Security::decap(&frame, &keys, 0x05, RECEIVER).is_err());
+    }
+
+    #[test]
+    fn known_answer() {
+        // computed with an independent AES implementation: the keys derived
+        // from the network key, AES-OFB over the sequence byte and the
+        // command, and the CBC-MAC over the header and the encrypted command
+        let keys = SecurityKeys::new(&KEY);
+        let msg =
+            Security::encap(SwitchBinary::set(0x05, true), &keys, 0x01, SENDER, RECEIVER).unwrap();
+        assert_eq!(
+            msg.data,
+            vec![
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x44, 0xBE, 0xF6, 0xB8, 0xA1, 0x6A,
+                0x79, 0x7B, 0x51, 0xBB, 0x6A, 0x5F, 0x59,
+            ]
+        );
+    }
+
+    #[test]
+    fn nonce_report() {
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0A, 0x98, 0x80, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8,
+        ])
+        .unwrap();
+        assert_eq!(Security::nonce_report(&frame), Ok(RECEIVER));
+
+        let msg = Security::nonce_report_message(0x05, RECEIVER);
+        assert_eq!(msg.to_vec()[2..4], [0x98, 0x80]);
+    }
+
+    #[test]
+    fn commands_supported_report() {
+        // the door lock is supported, the basic command class controlled
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x98, 0x03, 0x00, 0x62, 0x72, 0xEF, 0x20])
+                .unwrap();
+        assert_eq!(
+            Security::commands_supported_report(&frame),
+            Ok((
+                0,
+                vec![CommandClass::DOOR_LOCK, CommandClass::MANUFACTURER_SPECIFIC]
+            ))
+        );
+    }
+
+    /// Convert the sent message into a frame, which was received from the
+    /// given node
+    fn received(message: &Message, source: u8) -> IncomingFrame {
+        IncomingFrame {
+            status: RxStatus::default(),
+            source,
+            command_class: Ok(message.cmd_class),
+            command: message.cmd,
+            payload: message.data.to_vec(),
+        }
+    }
+}
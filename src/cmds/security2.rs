@@ -0,0 +1,690 @@
+//! The Security 2 Command Class (S2) is used to send the commands encrypted
+//! and authenticated to the nodes, which were included with one of the S2
+//! security classes. Each class has its own network key.
+//!
+//! Both sides of a connection share a Singlecast Pre-Agreed Nonce (SPAN),
+//! a random generator, which is seeded with the entropy of both of them.
+//! The entropy of the receiver is requested with a Nonce Get once, the one
+//! of the sender is part of the next encapsulation. Afterwards every frame
+//! in either direction takes the next nonce of the generator. The commands
+//! are encrypted and authenticated with AES-CCM.
+
+use crate::cmds::aes::{Aes128, BLOCK_SIZE};
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+use crate::payload::Payload;
+
+use aes::cipher::{InnerIvInit, StreamCipher};
+use ccm::aead::AeadInPlace;
+use ccm::consts::{U13, U8};
+use cmac::digest::{crypto_common::InnerInit, Mac};
+
+use std::convert::TryFrom;
+
+/// The size of the entropy input, which each side adds to the SPAN
+pub const ENTROPY_SIZE: usize = 16;
+
+/// The size of the nonce of AES-CCM
+pub const NONCE_SIZE: usize = 13;
+
+/// The size of the authentication tag
+const TAG_SIZE: usize = 8;
+
+/// AES-CCM with an eight byte tag and a 13 byte nonce, which leaves two
+/// bytes for the length field
+type AesCcm = ccm::Ccm<aes::Aes128, U8, U13>;
+
+/// The bytes an encapsulation adds to a command, the sequence number, the
+/// flags and the authentication tag. The SPAN extension adds 18 bytes more
+/// to the first frame.
+pub const OVERHEAD: usize = 2 + TAG_SIZE;
+
+/// The type of the SPAN extension, with the critical flag
+const SPAN_EXTENSION: u8 = 0x41;
+
+/// The extension follows the sequence number
+const FLAG_EXTENSION: u8 = 0x01;
+
+/// An encrypted extension is part of the command
+const FLAG_ENCRYPTED_EXTENSION: u8 = 0x02;
+
+/// The nonce report holds the entropy of the node, to set up a new SPAN
+const FLAG_SOS: u8 = 0x01;
+
+/// The keys of a security class, which are derived from its network key.
+#[derive(Debug, Clone)]
+pub struct S2Keys {
+    ccm: Aes128,
+    personalization: [u8; 2 * BLOCK_SIZE],
+}
+
+impl S2Keys {
+    /// Derive the key of AES-CCM and the personalization string of the SPAN
+    /// from the network key (CKDF-NetworkKeyExpand)
+    pub fn new(network_key: &[u8; BLOCK_SIZE]) -> S2Keys {
+        let cipher = Aes128::new(network_key);
+        let constant = [0x55; BLOCK_SIZE - 1];
+
+        let mut t1 = constant.to_vec();
+        t1.push(0x01);
+        let t1 = cmac(&cipher, &t1);
+
+        let mut t2 = t1.to_vec();
+        t2.extend_from_slice(&constant);
+        t2.push(0x02);
+        let t2 = cmac(&cipher, &t2);
+
+        let mut t3 = t2.to_vec();
+        t3.extend_from_slice(&constant);
+        t3.push(0x03);
+        let t3 = cmac(&cipher, &t3);
+
+        let mut personalization = [0u8; 2 * BLOCK_SIZE];
+        personalization[..BLOCK_SIZE].copy_from_slice(&t2);
+        personalization[BLOCK_SIZE..].copy_from_slice(&t3);
+
+        S2Keys {
+            ccm: Aes128::new(&t1),
+            personalization,
+        }
+    }
+}
+
+/// The Singlecast Pre-Agreed Nonce, an AES-128 CTR_DRBG without derivation
+/// function, which both sides of a connection run in step.
+#[derive(Debug, Clone)]
+pub struct Span {
+    key: Aes128,
+    v: [u8; BLOCK_SIZE],
+}
+
+impl Span {
+    /// Seed the generator with the entropy of the sender of the SPAN
+    /// extension and the entropy of the receiver, which it sent in its
+    /// nonce report.
+    pub fn new(
+        sender_entropy: &[u8; ENTROPY_SIZE],
+        receiver_entropy: &[u8; ENTROPY_SIZE],
+        keys: &S2Keys,
+    ) -> Span {
+        // CKDF-MEI-Extract and CKDF-MEI-Expand
+        let mut input = sender_entropy.to_vec();
+        input.extend_from_slice(receiver_entropy);
+        let prk = Aes128::new(&cmac(&Aes128::new(&[0x26; BLOCK_SIZE]), &input));
+
+        let constant = [0x88; BLOCK_SIZE - 1];
+        let mut t1 = constant.to_vec();
+        t1.push(0x00);
+        t1.extend_from_slice(&constant);
+        t1.push(0x01);
+        let t1 = cmac(&prk, &t1);
+
+        let mut t2 = t1.to_vec();
+        t2.extend_from_slice(&constant);
+        t2.push(0x02);
+        let t2 = cmac(&prk, &t2);
+
+        // the mixed entropy input and the personalization string seed it
+        let mut seed = keys.personalization;
+        for (byte, entropy) in seed.iter_mut().zip(t1.iter().chain(t2.iter())) {
+            *byte ^= entropy;
+        }
+
+        let mut span = Span {
+            key: Aes128::new(&[0; BLOCK_SIZE]),
+            v: [0; BLOCK_SIZE],
+        };
+        span.update(&seed);
+        span
+    }
+
+    /// Generate the nonce of the next frame
+    pub fn next_nonce(&mut self) -> [u8; NONCE_SIZE] {
+        // the nonce is the first block, the next two update the generator
+        let mut blocks = [0u8; 3 * BLOCK_SIZE];
+        self.keystream(&mut blocks);
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&blocks[..NONCE_SIZE]);
+        self.reseed(&blocks[BLOCK_SIZE..]);
+        nonce
+    }
+
+    /// Mix the data into the key and the counter of the generator
+    fn update(&mut self, data: &[u8; 2 * BLOCK_SIZE]) {
+        let mut temp = *data;
+        self.keystream(&mut temp);
+        self.reseed(&temp);
+    }
+
+    /// Xor the data with the encrypted counter blocks, which follow the
+    /// counter of the generator
+    fn keystream(&self, data: &mut [u8]) {
+        let mut counter = self.v;
+        increment(&mut counter);
+        let core = ctr::CtrCore::inner_iv_init(self.key.cipher(), &counter.into());
+        ctr::Ctr128BE::from_core(core).apply_keystream(data);
+    }
+
+    /// Take the new key and counter from the updated state
+    fn reseed(&mut self, temp: &[u8]) {
+        let mut key = [0u8; BLOCK_SIZE];
+        key.copy_from_slice(&temp[..BLOCK_SIZE]);
+        self.key = Aes128::new(&key);
+        self.v.copy_from_slice(&temp[BLOCK_SIZE..2 * BLOCK_SIZE]);
+    }
+}
+
+/// The fields of an encapsulation, which are authenticated next to the
+/// command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct S2Header {
+    pub source: u8,
+    pub destination: u8,
+    pub home_id: u32,
+    pub sequence: u8,
+    /// The entropy of the sender, when the frame sets up a new SPAN
+    pub entropy: Option<[u8; ENTROPY_SIZE]>,
+}
+
+/// Security 2 Command Class
+#[derive(Debug, Clone)]
+pub struct Security2;
+
+impl Security2 {
+    /// The Security 2 Nonce Get Command is used to request the entropy of
+    /// the node, to set up a SPAN with it.
+    pub fn nonce_get<N>(node_id: N, sequence: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SECURITY_2, 0x01, [sequence])
+    }
+
+    /// The Security 2 Nonce Report Command is used to hand out the entropy
+    /// of the controller, the node sets up a new SPAN with it.
+    pub fn nonce_report_message<N>(node_id: N, sequence: u8, entropy: [u8; ENTROPY_SIZE]) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |                        Sequence Number                        |
+        // |                   Reserved                    |  MOS  |  SOS  |
+        // |                 Receiver's Entropy Input 1 ... 16             |
+        // -----------------------------------------------------------------
+        let mut data = [0u8; 2 + ENTROPY_SIZE];
+        data[0] = sequence;
+        data[1] = FLAG_SOS;
+        data[2..].copy_from_slice(&entropy);
+
+        Message::from_array(node_id.into(), CommandClass::SECURITY_2, 0x02, data)
+    }
+
+    /// The Security 2 Nonce Report Command is used by the node to hand out
+    /// its entropy. Returns the entropy, when the node wants a new SPAN, the
+    /// report without it only asks for a new multicast group.
+    pub fn nonce_report(frame: &IncomingFrame) -> Result<Option<[u8; ENTROPY_SIZE]>, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SECURITY_2, 0x02)?;
+
+        if payload.len() < 2 {
+            return Err(Error::parse(
+                CommandClass::SECURITY_2,
+                "Message is too short",
+            ));
+        }
+        if payload[1] & FLAG_SOS == 0 {
+            return Ok(None);
+        }
+        if payload.len() < 2 + ENTROPY_SIZE {
+            return Err(Error::parse(
+                CommandClass::SECURITY_2,
+                "Message is too short",
+            ));
+        }
+
+        let mut entropy = [0u8; ENTROPY_SIZE];
+        entropy.copy_from_slice(&payload[2..2 + ENTROPY_SIZE]);
+        Ok(Some(entropy))
+    }
+
+    /// The Security 2 Message Encapsulation Command is used to encrypt and
+    /// authenticate the command with the next nonce of the SPAN. The
+    /// entropy of the header is sent along, when the frame sets up the SPAN.
+    pub fn encap(
+        message: Message,
+        header: &S2Header,
+        keys: &S2Keys,
+        nonce: [u8; NONCE_SIZE],
+    ) -> Result<Message, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |                        Sequence Number                        |
+        // |                   Reserved                    |Enc Ext|  Ext  |
+        // |        Extension Length, Type, Data (SPAN: Sender's EI)       |
+        // |            Command Class, Command, Parameter 1 ... n          |
+        // |                 Message Authentication Code 1 ... 8           |
+        // -----------------------------------------------------------------
+        let mut unencrypted = vec![header.sequence, 0x00];
+        if let Some(entropy) = header.entropy {
+            unencrypted[1] |= FLAG_EXTENSION;
+            unencrypted.extend_from_slice(&[2 + ENTROPY_SIZE as u8, SPAN_EXTENSION]);
+            unencrypted.extend_from_slice(&entropy);
+        }
+
+        let mut plain = vec![message.cmd_class as u8, message.cmd];
+        plain.extend_from_slice(&message.data);
+
+        let length = 2 + unencrypted.len() + plain.len() + TAG_SIZE;
+        let aad = additional_data(header, length, &unencrypted);
+        let encrypted = ccm_encrypt(&keys.ccm, &nonce, &aad, &plain)?;
+
+        let mut data = unencrypted;
+        data.extend_from_slice(&encrypted);
+
+        Ok(Message {
+            node_id: message.node_id,
+            cmd_class: CommandClass::SECURITY_2,
+            cmd: 0x03,
+            data: Payload::try_from(&data[..])?,
+            raw: Payload::new(),
+        })
+    }
+
+    /// Returns the header of a Security 2 Message Encapsulation, which was
+    /// sent to the destination. The entropy is set, when the frame sets up
+    /// a new SPAN.
+    pub fn header(frame: &IncomingFrame, destination: u8, home_id: u32) -> Result<S2Header, Error> {
+        let (_, extensions) = Security2::split(frame)?;
+
+        let mut entropy = None;
+        let mut rest = extensions;
+        while rest.len() >= 2 {
+            let (length, kind) = (rest[0] as usize, rest[1]);
+            if length < 2 || length > rest.len() {
+                return Err(Error::parse(
+                    CommandClass::SECURITY_2,
+                    "The extension is too long",
+                ));
+            }
+            if kind & 0x3F == SPAN_EXTENSION & 0x3F && length == 2 + ENTROPY_SIZE {
+                let mut span = [0u8; ENTROPY_SIZE];
+                span.copy_from_slice(&rest[2..length]);
+                entropy = Some(span);
+            }
+            rest = &rest[length..];
+        }
+
+        Ok(S2Header {
+            source: frame.source,
+            destination,
+            home_id,
+            sequence: frame.payload[0],
+            entropy,
+        })
+    }
+
+    /// Check the authentication tag of a Security 2 Message Encapsulation
+    /// and decrypt the command with the next nonce of the SPAN.
+    pub fn decap(
+        frame: &IncomingFrame,
+        header: &S2Header,
+        keys: &S2Keys,
+        nonce: [u8; NONCE_SIZE],
+    ) -> Result<IncomingFrame, Error> {
+        let (unencrypted_len, _) = Security2::split(frame)?;
+        let (unencrypted, encrypted) = frame.payload.split_at(unencrypted_len);
+
+        let aad = additional_data(header, 2 + frame.payload.len(), unencrypted);
+        let plain = ccm_decrypt(&keys.ccm, &nonce, &aad, encrypted).ok_or_else(|| {
+            Error::parse(
+                CommandClass::SECURITY_2,
+                "The authentication code doesn't match",
+            )
+        })?;
+
+        // the encrypted extensions of the multicast groups are skipped
+        let mut plain = &plain[..];
+        if unencrypted[1] & FLAG_ENCRYPTED_EXTENSION != 0 {
+            loop {
+                let (length, kind) = match plain {
+                    [length, kind, ..] if *length >= 2 && *length as usize <= plain.len() => {
+                        (*length as usize, *kind)
+                    }
+                    _ => {
+                        return Err(Error::parse(
+                            CommandClass::SECURITY_2,
+                            "The encrypted extension is too long",
+                        ))
+                    }
+                };
+                plain = &plain[length..];
+                if kind & 0x80 == 0 {
+                    break;
+                }
+            }
+        }
+
+        if plain.len() < 2 {
+            return Err(Error::parse(
+                CommandClass::SECURITY_2,
+                "Message is too short",
+            ));
+        }
+        let command_class = CommandClass::try_from(plain[0]).map_err(|_| plain[0]);
+
+        Ok(IncomingFrame {
+            status: frame.status,
+            source: frame.source,
+            command_class,
+            command: plain[1],
+            payload: plain[2..].to_vec(),
+        })
+    }
+
+    /// Returns the length of the unencrypted part of a Security 2 Message
+    /// Encapsulation and its extensions.
+    fn split(frame: &IncomingFrame) -> Result<(usize, &[u8]), Error> {
+        if frame.command_class != Ok(CommandClass::SECURITY_2) || frame.command != 0x03 {
+            return Err(Error::parse(
+                CommandClass::SECURITY_2,
+                "The frame is no Security 2 Message Encapsulation",
+            ));
+        }
+
+        let payload = &frame.payload;
+        if payload.len() < OVERHEAD {
+            return Err(Error::parse(
+                CommandClass::SECURITY_2,
+                "Message is too short",
+            ));
+        }
+        if payload[1] & FLAG_EXTENSION == 0 {
+            return Ok((2, &[]));
+        }
+
+        // the last extension has the more to follow flag cleared
+        let mut end = 2;
+        loop {
+            let length = payload.get(end).cloned().unwrap_or(0) as usize;
+            let kind = payload.get(end + 1).cloned().unwrap_or(0);
+            if length < 2 || end + length + TAG_SIZE > payload.len() {
+                return Err(Error::parse(
+                    CommandClass::SECURITY_2,
+                    "The extension is too long",
+                ));
+            }
+            end += length;
+            if kind & 0x80 == 0 {
+                break;
+            }
+        }
+
+        Ok((end, &payload[2..end]))
+    }
+}
+
+/// The data, which is authenticated next to the command: the node ids, the
+/// home id, the length of the whole command and its unencrypted part
+fn additional_data(header: &S2Header, length: usize, unencrypted: &[u8]) -> Vec<u8> {
+    let mut aad = vec![header.source, header.destination];
+    aad.extend_from_slice(&header.home_id.to_be_bytes());
+    aad.extend_from_slice(&(length as u16).to_be_bytes());
+    aad.extend_from_slice(unencrypted);
+    aad
+}
+
+/// Count the block up by one, as a big endian number
+fn increment(block: &mut [u8; BLOCK_SIZE]) {
+    for byte in block.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+/// Calculate the AES-CMAC of the data (RFC 4493)
+fn cmac(cipher: &Aes128, data: &[u8]) -> [u8; BLOCK_SIZE] {
+    let mut mac = cmac::Cmac::from_core(cmac::CmacCore::inner_init(cipher.cipher()));
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Encrypt and authenticate the data with AES-CCM. Returns the encrypted
+/// data and the tag.
+fn ccm_encrypt(
+    cipher: &Aes128,
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    plain: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let mut data = plain.to_vec();
+    let tag = AesCcm::from(cipher.cipher())
+        .encrypt_in_place_detached(nonce.into(), aad, &mut data)
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "The command is too long"))?;
+
+    data.extend_from_slice(&tag);
+    Ok(data)
+}
+
+/// Decrypt the data with AES-CCM and check its tag, which follows it. The
+/// tag is compared in constant time.
+fn ccm_decrypt(
+    cipher: &Aes128,
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    encrypted: &[u8],
+) -> Option<Vec<u8>> {
+    if encrypted.len() < TAG_SIZE {
+        return None;
+    }
+    let (encrypted, tag) = encrypted.split_at(encrypted.len() - TAG_SIZE);
+
+    let mut plain = encrypted.to_vec();
+    AesCcm::from(cipher.cipher())
+        .decrypt_in_place_detached(nonce.into(), aad, &mut plain, tag.into())
+        .ok()?;
+
+    Some(plain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::switch_binary::SwitchBinary;
+    use crate::defs::RxStatus;
+
+    const KEY: [u8; BLOCK_SIZE] = [
+        0x0F, 0x1E, 0x2D, 0x3C, 0x4B, 0x5A, 0x69, 0x78, 0x87, 0x96, 0xA5, 0xB4, 0xC3, 0xD2, 0xE1,
+        0xF0,
+    ];
+
+    const SENDER: [u8; ENTROPY_SIZE] = [
+        0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x1D, 0x1E,
+        0x1F,
+    ];
+
+    const RECEIVER: [u8; ENTROPY_SIZE] = [
+        0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2A, 0x2B, 0x2C, 0x2D, 0x2E,
+        0x2F,
+    ];
+
+    const HOME_ID: u32 = 0xC0FF_EE01;
+
+    #[test]
+    fn cmac_rfc4493() {
+        let cipher = Aes128::new(&[
+            0x2B, 0x7E, 0x15, 0x16, 0x28, 0xAE, 0xD2, 0xA6, 0xAB, 0xF7, 0x15, 0x88, 0x09, 0xCF,
+            0x4F, 0x3C,
+        ]);
+        assert_eq!(
+            cmac(&cipher, &[]),
+            [
+                0xBB, 0x1D, 0x69, 0x29, 0xE9, 0x59, 0x37, 0x28, 0x7F, 0xA3, 0x7D, 0x12, 0x9B, 0x75,
+                0x67, 0x46
+            ]
+        );
+
+        let data = [
+            0x6B, 0xC1, 0xBE, 0xE2, 0x2E, 0x40, 0x9F, 0x96, 0xE9, 0x3D, 0x7E, 0x11, 0x73, 0x93,
+            0x17, 0x2A, 0xAE, 0x2D, 0x8A, 0x57, 0x1E, 0x03, 0xAC, 0x9C, 0x9E, 0xB7, 0x6F, 0xAC,
+            0x45, 0xAF, 0x8E, 0x51, 0x30, 0xC8, 0x1C, 0x46, 0xA3, 0x5C, 0xE4, 0x11,
+        ];
+        assert_eq!(
+            cmac(&cipher, &data),
+            [
+                0xDF, 0xA6, 0x67, 0x47, 0xDE, 0x9A, 0xE6, 0x30, 0x30, 0xCA, 0x32, 0x61, 0x14, 0x97,
+                0xC8, 0x27
+            ]
+        );
+    }
+
+    #[test]
+    fn ccm_rfc3610() {
+        // the packet vector #1
+        let mut key = [0u8; BLOCK_SIZE];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = 0xC0 + i as u8;
+        }
+        let cipher = Aes128::new(&key);
+        let nonce = [
+            0x00, 0x00, 0x00, 0x03, 0x02, 0x01, 0x00, 0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5,
+        ];
+        let packet: Vec<u8> = (0..31).collect();
+        let (aad, plain) = packet.split_at(8);
+
+        let encrypted = ccm_encrypt(&cipher, &nonce, aad, plain).unwrap();
+        assert_eq!(
+            encrypted,
+            vec![
+                0x58, 0x8C, 0x97, 0x9A, 0x61, 0xC6, 0x63, 0xD2, 0xF0, 0x66, 0xD0, 0xC2, 0xC0, 0xF9,
+                0x89, 0x80, 0x6D, 0x5F, 0x6B, 0x61, 0xDA, 0xC3, 0x84, 0x17, 0xE8, 0xD1, 0x2C, 0xFD,
+                0xF9, 0x26, 0xE0,
+            ]
+        );
+        assert_eq!(
+            ccm_decrypt(&cipher, &nonce, aad, &encrypted),
+            Some(plain.to_vec())
+        );
+
+        // the additional data is authenticated
+        assert_eq!(ccm_decrypt(&cipher, &nonce, &aad[1..], &encrypted), None);
+    }
+
+    #[test]
+    fn known_answer() {
+        // computed with an independent AES implementation: the keys and the
+        // SPAN derived with CMAC, the nonces of the CTR_DRBG and AES-CCM
+        let keys = S2Keys::new(&KEY);
+        let mut span = Span::new(&SENDER, &RECEIVER, &keys);
+        let header = S2Header {
+            source: 0x01,
+            destination: 0x05,
+            home_id: HOME_ID,
+            sequence: 0x10,
+            entropy: Some(SENDER),
+        };
+        let msg = Security2::encap(
+            SwitchBinary::set(0x05, true),
+            &header,
+            &keys,
+            span.next_nonce(),
+        )
+        .unwrap();
+        assert_eq!(msg.cmd_class, CommandClass::SECURITY_2);
+        assert_eq!(msg.data.len(), 3 + OVERHEAD + 2 + ENTROPY_SIZE);
+        assert_eq!(
+            msg.data[..],
+            [
+                0x10, 0x01, 0x12, 0x41, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19,
+                0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F, 0x15, 0x96, 0x4B, 0xCB, 0x47, 0xAF, 0xF3, 0x6F,
+                0xEB, 0x5D, 0x39,
+            ]
+        );
+
+        // the answer of the node takes the next nonce of the SPAN
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0F, 0x9F, 0x03, 0x11, 0x00, 0xDE, 0x88, 0x13, 0x1E, 0xCF, 0x0E, 0xEA,
+            0xC6, 0xDA, 0xB6, 0x79,
+        ])
+        .unwrap();
+        let header = Security2::header(&frame, 0x01, HOME_ID).unwrap();
+        assert_eq!((header.sequence, header.entropy), (0x11, None));
+        let plain = Security2::decap(&frame, &header, &keys, span.next_nonce()).unwrap();
+        assert_eq!(plain.command_class, Ok(CommandClass::SWITCH_BINARY));
+        assert_eq!((plain.command, plain.payload), (0x03, vec![0x00]));
+    }
+
+    #[test]
+    fn encap_and_decap() {
+        let keys = S2Keys::new(&KEY);
+        let header = S2Header {
+            source: 0x01,
+            destination: 0x05,
+            home_id: HOME_ID,
+            sequence: 0x20,
+            entropy: Some(SENDER),
+        };
+        let mut sender = Span::new(&SENDER, &RECEIVER, &keys);
+        let msg = Security2::encap(
+            SwitchBinary::set(0x05, true),
+            &header,
+            &keys,
+            sender.next_nonce(),
+        )
+        .unwrap();
+
+        // the node sets up the same SPAN with the entropy of the extension
+        let mut frame = received(&msg, 0x01);
+        let parsed = Security2::header(&frame, 0x05, HOME_ID).unwrap();
+        assert_eq!(parsed, header);
+        let mut receiver = Span::new(&parsed.entropy.unwrap(), &RECEIVER, &keys);
+        let nonce = receiver.next_nonce();
+        let plain = Security2::decap(&frame, &parsed, &keys, nonce).unwrap();
+        assert_eq!(plain.command_class, Ok(CommandClass::SWITCH_BINARY));
+        assert_eq!((plain.command, plain.payload), (0x01, vec![0xFF]));
+
+        // the nonce, the key, the header and the command are authenticated
+        assert!(Security2::decap(&frame, &parsed, &keys, receiver.next_nonce()).is_err());
+        assert!(Security2::decap(&frame, &parsed, &S2Keys::new(&[0; 16]), nonce).is_err());
+        let other = S2Header {
+            home_id: 0x0102_0304,
+            ..parsed
+        };
+        assert!(Security2::decap(&frame, &other, &keys, nonce).is_err());
+        let last = frame.payload.len() - 1;
+        frame.payload[last] ^= 0x01;
+        assert!(Security2::decap(&frame, &parsed, &keys, nonce).is_err());
+    }
+
+    #[test]
+    fn nonce_report() {
+        let msg = Security2::nonce_report_message(0x05, 0x07, RECEIVER);
+        let frame = received(&msg, 0x05);
+        assert_eq!(frame.payload[..2], [0x07, 0x01]);
+        assert_eq!(Security2::nonce_report(&frame), Ok(Some(RECEIVER)));
+
+        // the report without SOS doesn't hand out entropy
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x9F, 0x02, 0x08, 0x02]).unwrap();
+        assert_eq!(Security2::nonce_report(&frame), Ok(None));
+
+        assert_eq!(
+            Security2::nonce_get(0x05, 0x09).to_vec()[2..],
+            [0x9F, 0x01, 0x09]
+        );
+    }
+
+    /// Convert the sent message into a frame, which was received from the
+    /// given node
+    fn received(message: &Message, source: u8) -> IncomingFrame {
+        IncomingFrame {
+            status: RxStatus::default(),
+            source,
+            command_class: Ok(message.cmd_class),
+            command: message.cmd,
+            payload: message.data.to_vec(),
+        }
+    }
+}
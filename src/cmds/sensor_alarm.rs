@@ -0,0 +1,198 @@
+//! The Alarm Sensor Command Class is used by older alarm sensors, like smoke
+//! or water sensors, which predate the Notification Command Class.
+//!
+//! A report can be forwarded by another node, so it names the node, which
+//! detected the alarm.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// The kind of alarm, which a sensor detects
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum AlarmSensorType {
+    GeneralPurpose = 0x00,
+    Smoke = 0x01,
+    CarbonMonoxide = 0x02,
+    CarbonDioxide = 0x03,
+    Heat = 0x04,
+    WaterLeak = 0x05,
+}
+
+/// The state of an alarm sensor.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AlarmSensorReport {
+    /// The node, which detected the alarm
+    pub source: u8,
+    pub sensor_type: AlarmSensorType,
+    /// The severity from 1 to 100 %, 0 is no alarm and 0xFF an alarm
+    /// without severity
+    pub level: u8,
+    /// The time the alarm lasts, when the sensor sent it
+    pub duration: Option<Duration>,
+}
+
+impl AlarmSensorReport {
+    /// Returns if the alarm is active
+    pub fn alarm(&self) -> bool {
+        self.level != 0x00
+    }
+}
+
+/// Alarm Sensor Command Class
+#[derive(Debug, Clone)]
+pub struct SensorAlarm;
+
+impl SensorAlarm {
+    /// The Alarm Sensor Get Command is used to request the state of the
+    /// sensor type.
+    pub fn get<N>(node_id: N, sensor_type: AlarmSensorType) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::SENSOR_ALARM,
+            0x01,
+            [sensor_type as u8],
+        )
+    }
+
+    /// The Alarm Sensor Report Command is used to advertise the state of an
+    /// alarm sensor.
+    pub fn report(frame: &IncomingFrame) -> Result<AlarmSensorReport, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |        Command Class = COMMAND_CLASS_SENSOR_ALARM(0x9C)       |
+        // |              Command = SENSOR_ALARM_REPORT(0x02)              |
+        // |                        Source Node ID                         |
+        // |                          Sensor Type                          |
+        // |                          Sensor State                         |
+        // |                       Seconds 1 (MSB)                         |
+        // |                       Seconds 2 (LSB)                         |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SENSOR_ALARM, 0x02)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::SENSOR_ALARM,
+                "Message is too short",
+            ));
+        }
+
+        let sensor_type = AlarmSensorType::try_from(payload[1]).map_err(|_| {
+            Error::parse(
+                CommandClass::SENSOR_ALARM,
+                format!("Unknown sensor type {:#04x}", payload[1]),
+            )
+        })?;
+
+        // the seconds are optional and 0 stands for no duration
+        let duration = match payload.get(3..5) {
+            Some(&[0x00, 0x00]) | None => None,
+            Some(s) => Some(Duration::from_secs(u16::from_be_bytes([s[0], s[1]]) as u64)),
+        };
+
+        Ok(AlarmSensorReport {
+            source: payload[0],
+            sensor_type,
+            level: payload[2],
+            duration,
+        })
+    }
+
+    /// The Alarm Sensor Supported Get Command is used to request the sensor
+    /// types of the node.
+    pub fn supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SENSOR_ALARM, 0x03, [])
+    }
+
+    /// The Alarm Sensor Supported Report Command is used to advertise the
+    /// sensor types of the node. Unknown types are skipped.
+    pub fn supported_report(frame: &IncomingFrame) -> Result<Vec<AlarmSensorType>, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |        Command Class = COMMAND_CLASS_SENSOR_ALARM(0x9C)       |
+        // |         Command = SENSOR_ALARM_SUPPORTED_REPORT(0x04)         |
+        // |                   Number of Bit Masks                         |
+        // |                  Bit Mask 1 ... Bit Mask N                    |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SENSOR_ALARM, 0x04)?;
+
+        if payload.is_empty() || payload.len() < 1 + payload[0] as usize {
+            return Err(Error::parse(
+                CommandClass::SENSOR_ALARM,
+                "Message is too short",
+            ));
+        }
+
+        let mut types = vec![];
+        for (i, mask) in payload[1..1 + payload[0] as usize].iter().enumerate() {
+            for bit in 0..8 {
+                if mask & (1 << bit) != 0 {
+                    if let Ok(typ) = AlarmSensorType::try_from((i * 8 + bit) as u8) {
+                        types.push(typ);
+                    }
+                }
+            }
+        }
+
+        Ok(types)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        // the smoke alarm of the node 5 for 30 seconds
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x9C, 0x02, 0x05, 0x01, 0xFF, 0x00, 0x1E])
+                .unwrap();
+        let report = SensorAlarm::report(&frame).unwrap();
+        assert_eq!(
+            report,
+            AlarmSensorReport {
+                source: 0x05,
+                sensor_type: AlarmSensorType::Smoke,
+                level: 0xFF,
+                duration: Some(Duration::from_secs(30)),
+            }
+        );
+        assert!(report.alarm());
+
+        // a version 1 report without the seconds
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x9C, 0x02, 0x05, 0x05, 0x00]).unwrap();
+        let report = SensorAlarm::report(&frame).unwrap();
+        assert_eq!(report.sensor_type, AlarmSensorType::WaterLeak);
+        assert_eq!(report.duration, None);
+        assert!(!report.alarm());
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x9C, 0x02, 0x05, 0x01]).unwrap();
+        assert!(SensorAlarm::report(&frame).is_err());
+    }
+
+    #[test]
+    fn supported_report() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x9C, 0x04, 0x01, 0x22]).unwrap();
+        assert_eq!(
+            SensorAlarm::supported_report(&frame),
+            Ok(vec![AlarmSensorType::Smoke, AlarmSensorType::WaterLeak])
+        );
+    }
+}
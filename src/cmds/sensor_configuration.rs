@@ -0,0 +1,160 @@
+//! The Sensor Configuration Command Class is used to set the trigger level
+//! of legacy multisensors, the value at which the sensor reports or acts.
+//!
+//! The trigger level is encoded like a multilevel sensor value, with the
+//! sensor type and a precision, a scale and a size in front of the value.
+
+use crate::cmds::sensor_multilevel::SensorMultilevel;
+use crate::cmds::{
+    decode_value, encode_value, precision_scale_size, CommandClass, IncomingFrame, Message,
+    SensorValue, Unit,
+};
+use crate::error::{Error, ErrorKind};
+
+/// Sensor Configuration Command Class
+#[derive(Debug, Clone)]
+pub struct SensorConfiguration;
+
+impl SensorConfiguration {
+    /// The Sensor Trigger Level Set Command is used to set the trigger level
+    /// of the sensor. The value is sent with the amount of decimals of the
+    /// precision.
+    pub fn set<N>(node_id: N, value: SensorValue, precision: u8) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        SensorConfiguration::trigger_level(node_id, 0x00, value, precision)
+    }
+
+    /// The Sensor Trigger Level Set Command is used to reset the trigger
+    /// level of the sensor, which measures in the unit, to its default.
+    pub fn set_default<N>(node_id: N, unit: Unit) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        SensorConfiguration::trigger_level(node_id, 0x80, SensorValue::new(0.0, unit), 0)
+    }
+
+    /// The Sensor Trigger Level Get Command is used to request the trigger
+    /// level of the sensor.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SENSOR_CONFIGURATION, 0x02, [])
+    }
+
+    /// The Sensor Trigger Level Report Command is used to advertise the
+    /// trigger level of the sensor.
+    pub fn report(frame: &IncomingFrame) -> Result<SensorValue, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |    Command Class = COMMAND_CLASS_SENSOR_CONFIGURATION(0x9E)   |
+        // |          Command = SENSOR_TRIGGER_LEVEL_REPORT(0x03)          |
+        // |                         Sensor Type                           |
+        // |       Precision       |     Scale     |         Size          |
+        // |               Trigger Value 1 ... Trigger Value n             |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SENSOR_CONFIGURATION, 0x03)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::SENSOR_CONFIGURATION,
+                "Message is too short",
+            ));
+        }
+
+        let (precision, scale, size) = precision_scale_size(payload[1]);
+        let size = size as usize;
+        if payload.len() < 2 + size {
+            return Err(Error::parse(
+                CommandClass::SENSOR_CONFIGURATION,
+                "Message is too short",
+            ));
+        }
+
+        let unit = SensorMultilevel::unit(payload[0], scale).ok_or_else(|| {
+            Error::parse(
+                CommandClass::SENSOR_CONFIGURATION,
+                format!(
+                    "The sensor type {:#04x} with the scale {} is not supported",
+                    payload[0], scale
+                ),
+            )
+        })?;
+
+        Ok(SensorValue::new(
+            decode_value(&payload[2..2 + size], precision),
+            unit,
+        ))
+    }
+
+    /// Build the set command with the default and current flags
+    fn trigger_level<N>(
+        node_id: N,
+        flags: u8,
+        value: SensorValue,
+        precision: u8,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |    Command Class = COMMAND_CLASS_SENSOR_CONFIGURATION(0x9E)   |
+        // |           Command = SENSOR_TRIGGER_LEVEL_SET(0x01)            |
+        // |Default|Current|                 Reserved                      |
+        // |                         Sensor Type                           |
+        // |       Precision       |     Scale     |         Size          |
+        // |               Trigger Value 1 ... Trigger Value n             |
+        // -----------------------------------------------------------------
+        let (typ, scale) = SensorMultilevel::type_scale(value.unit).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("The unit {:?} is not supported", value.unit),
+            )
+        })?;
+
+        let bytes = encode_value(value.value, precision)?;
+        let mut data = vec![flags, typ, precision << 5 | scale << 3 | bytes.len() as u8];
+        data.extend_from_slice(&bytes);
+
+        Message::new(
+            node_id.into(),
+            CommandClass::SENSOR_CONFIGURATION,
+            0x01,
+            &data,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = SensorConfiguration::set(0x05, SensorValue::new(25.5, Unit::Celsius), 1).unwrap();
+        assert_eq!(msg.data, vec![0x00, 0x01, 0x22, 0x00, 0xFF]);
+
+        let msg = SensorConfiguration::set_default(0x05, Unit::Lux).unwrap();
+        assert_eq!(msg.data, vec![0x80, 0x03, 0x09, 0x00]);
+
+        assert!(SensorConfiguration::set(0x05, SensorValue::new(1.0, Unit::USGallon), 0).is_err());
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x9E, 0x03, 0x03, 0x09, 0x64]).unwrap();
+        assert_eq!(
+            SensorConfiguration::report(&frame),
+            Ok(SensorValue::new(100.0, Unit::Lux))
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x9E, 0x03, 0x03, 0x0A]).unwrap();
+        assert!(SensorConfiguration::report(&frame).is_err());
+    }
+}
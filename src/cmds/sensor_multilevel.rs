@@ -0,0 +1,163 @@
+//! The Multilevel Sensor Command Class is used to read measured values, like
+//! the temperature, the humidity or the luminance of a multisensor.
+//!
+//! The values are encoded like the meter readings, with a precision, a scale
+//! and a size in front of the value. The sensor type and the scale are
+//! decoded into the unit of a `SensorValue`.
+
+use crate::cmds::{
+    decode_value, precision_scale_size, CommandClass, IncomingFrame, Message, SensorValue, Unit,
+};
+use crate::error::Error;
+
+/// The sensor types and scales of the units
+const UNITS: &[(u8, u8, Unit)] = &[
+    (0x01, 0x00, Unit::Celsius),
+    (0x01, 0x01, Unit::Fahrenheit),
+    (0x03, 0x00, Unit::Percent),
+    (0x03, 0x01, Unit::Lux),
+    (0x04, 0x00, Unit::Watt),
+    (0x05, 0x00, Unit::RelativeHumidity),
+    (0x0F, 0x00, Unit::Volt),
+    (0x10, 0x00, Unit::Ampere),
+];
+
+/// Multilevel Sensor Command Class
+#[derive(Debug, Clone)]
+pub struct SensorMultilevel;
+
+impl SensorMultilevel {
+    /// The Multilevel Sensor Get Command is used to request the default
+    /// value of the sensor.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SENSOR_MULTILEVEL, 0x04, [])
+    }
+
+    /// The Multilevel Sensor Get Command is used to request the value in the
+    /// given unit, since version 5 a sensor can measure more than one value.
+    pub fn get_v5<N>(node_id: N, unit: Unit) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_SENSOR_MULTILEVEL(0x31)     |
+        // |           Command = SENSOR_MULTILEVEL_GET(0x04)               |
+        // |                         Sensor Type                           |
+        // |       Reserved        |     Scale     |       Reserved        |
+        // -----------------------------------------------------------------
+        let (typ, scale) = SensorMultilevel::type_scale(unit).ok_or_else(|| {
+            Error::parse(
+                CommandClass::SENSOR_MULTILEVEL,
+                format!("The unit {:?} is not supported", unit),
+            )
+        })?;
+
+        Ok(Message::from_array(
+            node_id.into(),
+            CommandClass::SENSOR_MULTILEVEL,
+            0x04,
+            [typ, scale << 3],
+        ))
+    }
+
+    /// Returns the sensor type and the scale of the unit, when it's known
+    pub fn type_scale(unit: Unit) -> Option<(u8, u8)> {
+        UNITS.iter().find(|u| u.2 == unit).map(|u| (u.0, u.1))
+    }
+
+    /// Returns the unit of the sensor type and scale, when it's known
+    pub fn unit(sensor_type: u8, scale: u8) -> Option<Unit> {
+        UNITS
+            .iter()
+            .find(|u| u.0 == sensor_type && u.1 == scale)
+            .map(|u| u.2)
+    }
+
+    /// The Multilevel Sensor Report Command is used to advertise a measured
+    /// value.
+    pub fn report(frame: &IncomingFrame) -> Result<SensorValue, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_SENSOR_MULTILEVEL(0x31)     |
+        // |          Command = SENSOR_MULTILEVEL_REPORT(0x05)              |
+        // |                         Sensor Type                           |
+        // |       Precision       |     Scale     |         Size          |
+        // |                 Sensor Value 1 ... Sensor Value n             |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SENSOR_MULTILEVEL, 0x05)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::SENSOR_MULTILEVEL,
+                "Message is too short",
+            ));
+        }
+
+        let (precision, scale, size) = precision_scale_size(payload[1]);
+        let size = size as usize;
+        if payload.len() < 2 + size {
+            return Err(Error::parse(
+                CommandClass::SENSOR_MULTILEVEL,
+                "Message is too short",
+            ));
+        }
+
+        let unit = SensorMultilevel::unit(payload[0], scale).ok_or_else(|| {
+            Error::parse(
+                CommandClass::SENSOR_MULTILEVEL,
+                format!(
+                    "The sensor type {:#04x} with the scale {} is not supported",
+                    payload[0], scale
+                ),
+            )
+        })?;
+
+        Ok(SensorValue::new(
+            decode_value(&payload[2..2 + size], precision),
+            unit,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        // 21.5 °C
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x31, 0x05, 0x01, 0x22, 0x00, 0xD7]).unwrap();
+        assert_eq!(
+            SensorMultilevel::report(&frame),
+            Ok(SensorValue::new(21.5, Unit::Celsius))
+        );
+
+        // 45 % humidity
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x31, 0x05, 0x05, 0x01, 0x2D]).unwrap();
+        assert_eq!(
+            SensorMultilevel::report(&frame),
+            Ok(SensorValue::new(45.0, Unit::RelativeHumidity))
+        );
+
+        // the sensor type is unknown
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x31, 0x05, 0x40, 0x01, 0x2D]).unwrap();
+        assert!(SensorMultilevel::report(&frame).is_err());
+    }
+
+    #[test]
+    fn get_v5() {
+        let msg = SensorMultilevel::get_v5(0x05, Unit::Lux).unwrap();
+        assert_eq!(msg.data, vec![0x03, 0x08]);
+
+        assert!(SensorMultilevel::get_v5(0x05, Unit::KilowattHour).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! The Sound Switch Command Class is used to play the tones of sirens and
+//! doorbells, and to choose their default tone and volume.
+//!
+//! The tones are numbered from 1, the tone 0 stops the playback and the tone
+//! 0xFF plays the default tone.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+
+use std::time::Duration;
+
+/// The tone 0xFF plays the default tone
+pub const DEFAULT_TONE: u8 = 0xFF;
+
+/// A tone, which the node can play.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ToneInfo {
+    pub tone: u8,
+    pub duration: Duration,
+    pub name: String,
+}
+
+/// Sound Switch Command Class
+#[derive(Debug, Clone)]
+pub struct SoundSwitch;
+
+impl SoundSwitch {
+    /// The Sound Switch Tones Number Get Command is used to request the
+    /// amount of tones.
+    pub fn tones_number_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SOUND_SWITCH, 0x01, [])
+    }
+
+    /// The Sound Switch Tones Number Report Command is used to advertise the
+    /// amount of tones.
+    pub fn tones_number_report(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SOUND_SWITCH, 0x02)?;
+
+        payload
+            .first()
+            .cloned()
+            .ok_or_else(|| Error::parse(CommandClass::SOUND_SWITCH, "Message is too short"))
+    }
+
+    /// The Sound Switch Tone Info Get Command is used to request the name and
+    /// the duration of a tone.
+    pub fn tone_info_get<N>(node_id: N, tone: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SOUND_SWITCH, 0x03, [tone])
+    }
+
+    /// The Sound Switch Tone Info Report Command is used to advertise the
+    /// name and the duration of a tone.
+    pub fn tone_info_report(frame: &IncomingFrame) -> Result<ToneInfo, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_SOUND_SWITCH(0x79)        |
+        // |          Command = SOUND_SWITCH_TONE_INFO_REPORT(0x04)        |
+        // |                        Tone Identifier                        |
+        // |                 Tone Duration 1 ... 2 (seconds)               |
+        // |                          Name Length                          |
+        // |                      Name 1 ... Name n                        |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SOUND_SWITCH, 0x04)?;
+
+        if payload.len() < 4 {
+            return Err(Error::parse(
+                CommandClass::SOUND_SWITCH,
+                "Message is too short",
+            ));
+        }
+
+        let len = payload[3] as usize;
+        let name = payload
+            .get(4..4 + len)
+            .ok_or_else(|| Error::parse(CommandClass::SOUND_SWITCH, "The name is too short"))?;
+
+        Ok(ToneInfo {
+            tone: payload[0],
+            duration: Duration::from_secs(u16::from_be_bytes([payload[1], payload[2]]) as u64),
+            name: String::from_utf8_lossy(name).into_owned(),
+        })
+    }
+
+    /// The Sound Switch Configuration Set Command is used to set the default
+    /// volume in percent and the default tone. The volume 0xFF restores the
+    /// last volume and the tone 0 keeps the current default tone.
+    pub fn configuration_set<N>(node_id: N, volume: u8, tone: u8) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        Ok(Message::from_array(
+            node_id.into(),
+            CommandClass::SOUND_SWITCH,
+            0x05,
+            [check_volume(volume)?, tone],
+        ))
+    }
+
+    /// The Sound Switch Configuration Get Command is used to request the
+    /// default volume and the default tone.
+    pub fn configuration_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SOUND_SWITCH, 0x06, [])
+    }
+
+    /// The Sound Switch Configuration Report Command is used to advertise the
+    /// default volume and the default tone.
+    pub fn configuration_report(frame: &IncomingFrame) -> Result<(u8, u8), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SOUND_SWITCH, 0x07)?;
+
+        if payload.len() < 2 {
+            return Err(Error::parse(
+                CommandClass::SOUND_SWITCH,
+                "Message is too short",
+            ));
+        }
+
+        Ok((payload[0], payload[1]))
+    }
+
+    /// The Sound Switch Tone Play Set Command is used to play a tone. The
+    /// volume is only sent when it's given, which requires version 2 of the
+    /// command class. Otherwise the default volume is used.
+    pub fn tone_play_set<N>(node_id: N, tone: u8, volume: Option<u8>) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        let mut data = vec![tone];
+
+        if let Some(volume) = volume {
+            data.push(check_volume(volume)?);
+        }
+
+        Message::new(node_id.into(), CommandClass::SOUND_SWITCH, 0x08, &data)
+    }
+
+    /// The Sound Switch Tone Play Get Command is used to request the tone,
+    /// which is played right now.
+    pub fn tone_play_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SOUND_SWITCH, 0x09, [])
+    }
+
+    /// The Sound Switch Tone Play Report Command is used to advertise the
+    /// tone, which is played right now. The tone is 0, when nothing plays.
+    pub fn tone_play_report(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SOUND_SWITCH, 0x0A)?;
+
+        payload
+            .first()
+            .cloned()
+            .ok_or_else(|| Error::parse(CommandClass::SOUND_SWITCH, "Message is too short"))
+    }
+}
+
+/// Check if the volume is between 0-100 or 0xFF for the last volume.
+fn check_volume(volume: u8) -> Result<u8, Error> {
+    if volume > 100 && volume != 0xFF {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("The volume {} is not between 0-100 or 0xFF", volume),
+        ));
+    }
+
+    Ok(volume)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_info_report() {
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0A, 0x79, 0x04, 0x03, 0x00, 0x05, 0x04, 0x44, 0x69, 0x6E, 0x67,
+        ])
+        .unwrap();
+        assert_eq!(
+            SoundSwitch::tone_info_report(&frame),
+            Ok(ToneInfo {
+                tone: 3,
+                duration: Duration::from_secs(5),
+                name: "Ding".into(),
+            })
+        );
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x79, 0x04, 0x03, 0x00, 0x05, 0x04, 0x44])
+                .unwrap();
+        assert!(SoundSwitch::tone_info_report(&frame).is_err());
+    }
+
+    #[test]
+    fn play() {
+        let msg = SoundSwitch::tone_play_set(0x05, 3, None).unwrap();
+        assert_eq!(msg.data, vec![0x03]);
+
+        let msg = SoundSwitch::tone_play_set(0x05, DEFAULT_TONE, Some(40)).unwrap();
+        assert_eq!(msg.data, vec![0xFF, 0x28]);
+
+        assert!(SoundSwitch::tone_play_set(0x05, 3, Some(101)).is_err());
+        assert!(SoundSwitch::configuration_set(0x05, 101, 3).is_err());
+    }
+}
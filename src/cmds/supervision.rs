@@ -0,0 +1,173 @@
+//! The Supervision Command Class is used to get the status of a command
+//! from the node, which tells if the command was executed.
+//!
+//! The command is encapsulated into a Supervision Get, the node answers
+//! with a Supervision Report of the same session.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+use crate::payload::Payload;
+
+use std::convert::TryFrom;
+
+/// The highest session id, the id is counted up to it and starts again at 0
+pub const MAX_SESSION_ID: u8 = 0x3F;
+
+/// The status of a supervised command
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum SupervisionStatus {
+    NoSupport = 0x00,
+    Working = 0x01,
+    Fail = 0x02,
+    Success = 0xFF,
+}
+
+/// Supervision Command Class
+#[derive(Debug, Clone)]
+pub struct Supervision;
+
+impl Supervision {
+    /// The Supervision Get Command is used to execute the command and to
+    /// request its status.
+    pub fn get(message: Message, session: u8) -> Result<Message, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |        Command Class = COMMAND_CLASS_SUPERVISION(0x6C)        |
+        // |                Command = SUPERVISION_GET(0x01)                |
+        // |Updates|  Res  |                  Session ID                   |
+        // |                 Encapsulated Command Length                   |
+        // |                         Command Class                         |
+        // |                            Command                            |
+        // |                  Parameter 1 ... Parameter n                  |
+        // -----------------------------------------------------------------
+        let mut data = vec![
+            session & MAX_SESSION_ID,
+            (message.data.len() + 2) as u8,
+            message.cmd_class as u8,
+            message.cmd,
+        ];
+        data.extend_from_slice(&message.data);
+
+        Ok(Message {
+            node_id: message.node_id,
+            cmd_class: CommandClass::SUPERVISION,
+            cmd: 0x01,
+            data: Payload::try_from(&data[..])?,
+            raw: Payload::new(),
+        })
+    }
+
+    /// Unwrap the command of a Supervision Get, which was sent by the node.
+    /// Returns the session and the encapsulated command.
+    pub fn decap(frame: &IncomingFrame) -> Result<(u8, IncomingFrame), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SUPERVISION, 0x01)?;
+
+        // the payload need to be at least 4 bytes long
+        if payload.len() < 4 {
+            return Err(Error::parse(
+                CommandClass::SUPERVISION,
+                "Message is too short",
+            ));
+        }
+
+        // the encapsulated command has at least the command class and the
+        // command and needs to fit into the payload
+        let length = payload[1] as usize;
+        if length < 2 || payload.len() < 2 + length {
+            return Err(Error::parse(
+                CommandClass::SUPERVISION,
+                format!("Invalid encapsulated command length {}", length),
+            ));
+        }
+
+        let command_class = CommandClass::try_from(payload[2]).map_err(|_| payload[2]);
+
+        Ok((
+            payload[0] & MAX_SESSION_ID,
+            IncomingFrame {
+                status: frame.status,
+                source: frame.source,
+                command_class,
+                command: payload[3],
+                payload: payload[4..2 + length].to_vec(),
+            },
+        ))
+    }
+
+    /// The Supervision Report Command is used to tell the node the status
+    /// of the supervised command it sent.
+    pub fn report_message(node_id: u8, session: u8, status: SupervisionStatus) -> Message {
+        Message::from_array(
+            node_id,
+            CommandClass::SUPERVISION,
+            0x02,
+            [session & MAX_SESSION_ID, status as u8, 0x00],
+        )
+    }
+
+    /// Read the session and the status of a Supervision Report.
+    pub fn report(frame: &IncomingFrame) -> Result<(u8, SupervisionStatus), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::SUPERVISION, 0x02)?;
+
+        // the payload need to be at least 2 bytes long
+        if payload.len() < 2 {
+            return Err(Error::parse(
+                CommandClass::SUPERVISION,
+                "Message is too short",
+            ));
+        }
+
+        let status = SupervisionStatus::try_from(payload[1])
+            .map_err(|_| Error::parse(CommandClass::SUPERVISION, "Unknown status"))?;
+
+        Ok((payload[0] & MAX_SESSION_ID, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::switch_binary::SwitchBinary;
+
+    #[test]
+    fn get() {
+        let msg = Supervision::get(SwitchBinary::set(0x05, true), 0x45).unwrap();
+
+        assert_eq!(msg.cmd_class, CommandClass::SUPERVISION);
+        assert_eq!(msg.data, vec![0x05, 0x03, 0x25, 0x01, 0xFF]);
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x6C, 0x02, 0x05, 0xFF, 0x00]).unwrap();
+        assert_eq!(
+            Supervision::report(&frame),
+            Ok((0x05, SupervisionStatus::Success))
+        );
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x6C, 0x01, 0x81, 0x03, 0x25, 0x03, 0xFF])
+                .unwrap();
+        let (session, frame) = Supervision::decap(&frame).unwrap();
+        assert_eq!(session, 0x01);
+        assert_eq!(SwitchBinary::report(&frame), Ok(true));
+    }
+
+    #[test]
+    fn decap_length() {
+        // the encapsulated command is shorter than its command class and command
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x6C, 0x01, 0x04, 0x01, 0x05, 0x00]).unwrap();
+        assert!(Supervision::decap(&frame).is_err());
+
+        // the encapsulated command is longer than the payload
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x6C, 0x01, 0x04, 0x05, 0x25, 0x03]).unwrap();
+        assert!(Supervision::decap(&frame).is_err());
+    }
+}
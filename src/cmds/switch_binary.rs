@@ -1,5 +1,5 @@
-use cmds::{CommandClass, Message};
-use error::{Error, ErrorKind};
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
 
 /// The Binary Switch Command Class is used to control devices with On/Off
 /// or Enable/Disable capability.
@@ -17,12 +17,7 @@ impl SwitchBinary {
         let value = if value.into() { 0xFF } else { 0x00 };
 
         // generate the message
-        Message::new(
-            node_id.into(),
-            CommandClass::SWITCH_BINARY,
-            0x01,
-            vec![value],
-        )
+        Message::from_array(node_id.into(), CommandClass::SWITCH_BINARY, 0x01, [value])
     }
 
     /// The Binary Switch Get command, version 1 is used to request the status
@@ -31,32 +26,24 @@ impl SwitchBinary {
     where
         N: Into<u8>,
     {
-        Message::new(node_id.into(), CommandClass::SWITCH_BINARY, 0x02, vec![])
+        Message::from_array(node_id.into(), CommandClass::SWITCH_BINARY, 0x02, [])
     }
 
     /// The Binary Switch Report command, version 1 is used to advertise the
     /// status of a device with On/Off or Enable/Disable capability.
-    pub fn report<M>(msg: M) -> Result<bool, Error>
-    where
-        M: Into<Vec<u8>>,
-    {
-        // get the message
-        let msg = msg.into();
-
-        // the message need to be exact 6 digits long
-        if msg.len() != 6 {
-            return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
-        }
-
+    pub fn report(frame: &IncomingFrame) -> Result<bool, Error> {
         // check the CommandClass and command
-        if msg[3] != CommandClass::SWITCH_BINARY as u8 || msg[4] != 0x03 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                "Answer contained wrong command class",
+        let payload = frame.expect(CommandClass::SWITCH_BINARY, 0x03)?;
+
+        // the payload need to be exact 1 byte long
+        if payload.len() != 1 {
+            return Err(Error::parse(
+                CommandClass::SWITCH_BINARY,
+                "Message is too short",
             ));
         }
 
-        let val = if msg[5] < 0xFF { false } else { true };
+        let val = payload[0] == 0xFF;
 
         // return the value
         Ok(val)
@@ -1,5 +1,6 @@
-use cmds::{CommandClass, Message};
-use error::{Error, ErrorKind};
+use crate::cmds::{check_level, encode_duration, CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+use std::time::Duration;
 
 /// The Multilevel Switch Command Class is used to control devices with variable levels
 /// such as dimmer switches
@@ -7,19 +8,86 @@ use error::{Error, ErrorKind};
 pub struct SwitchMultilevel;
 
 impl SwitchMultilevel {
-    /// The Multilevel Switch Set command, version 1 is used to set a u8 value.
-    pub fn set<N, V>(node_id: N, value: V) -> Message
+    /// The Multilevel Switch Set command is used to set a value between 0-99 or 0xFF.
+    ///
+    /// The duration to reach the value is only sent when it's given, which
+    /// requires version 2 of the command class.
+    pub fn set<N, V>(node_id: N, value: V, duration: Option<Duration>) -> Result<Message, Error>
     where
         N: Into<u8>,
         V: Into<u8>,
     {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_SWITCH_MULTILEVEL(0x26)     |
+        // |             Command = SWITCH_MULTILEVEL_SET(0x01)             |
+        // |                             Value                             |
+        // |                        Dimming Duration                       |
+        // -----------------------------------------------------------------
+        let mut data = vec![check_level(value.into())?];
+
+        if duration.is_some() {
+            data.push(encode_duration(duration)?);
+        }
+
         // generate the message
-        Message::new(
-            node_id.into(),
-            CommandClass::SWITCH_MULTILEVEL,
-            0x01,
-            vec![value.into()],
-        )
+        Message::new(node_id.into(), CommandClass::SWITCH_MULTILEVEL, 0x01, &data)
+    }
+
+    /// The Multilevel Switch Start Level Change command is used to start dimming up or down,
+    /// until the device is stopped or the end is reached.
+    ///
+    /// Without a start level the device dims from its current level. The duration from the
+    /// lowest to the highest level is only sent when it's given, which requires version 2 of
+    /// the command class.
+    pub fn start_level_change<N>(
+        node_id: N,
+        up: bool,
+        start_level: Option<u8>,
+        duration: Option<Duration>,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_SWITCH_MULTILEVEL(0x26)     |
+        // |      Command = SWITCH_MULTILEVEL_START_LEVEL_CHANGE(0x04)     |
+        // |  Res  |Up/Down|Ignore |               Reserved                |
+        // |                          Start Level                          |
+        // |                        Dimming Duration                       |
+        // -----------------------------------------------------------------
+        let mut flags = if up { 0x00 } else { 0x40 };
+
+        let start_level = match start_level {
+            Some(level) if level <= 99 => level,
+            Some(level) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The start level {} is not between 0-99", level),
+                ))
+            }
+            None => {
+                flags |= 0x20;
+                0x00
+            }
+        };
+
+        let mut data = vec![flags, start_level];
+
+        if duration.is_some() {
+            data.push(encode_duration(duration)?);
+        }
+
+        Message::new(node_id.into(), CommandClass::SWITCH_MULTILEVEL, 0x04, &data)
+    }
+
+    /// The Multilevel Switch Stop Level Change command is used to stop dimming.
+    pub fn stop_level_change<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::SWITCH_MULTILEVEL, 0x05, [])
     }
 
     /// The Multilevel Switch Get command, version 1 is used to request the status
@@ -28,40 +96,63 @@ impl SwitchMultilevel {
     where
         N: Into<u8>,
     {
-        Message::new(
-            node_id.into(),
-            CommandClass::SWITCH_MULTILEVEL,
-            0x02,
-            vec![],
-        )
+        Message::from_array(node_id.into(), CommandClass::SWITCH_MULTILEVEL, 0x02, [])
     }
 
     /// The Multilevel Switch Report command, version 1 is used to advertise the
     /// status of a device with variable levels capability.
-    pub fn report<M>(msg: M) -> Result<u8, Error>
-    where
-        M: Into<Vec<u8>>,
-    {
-        // get the message
-        let msg = msg.into();
-
-        // the message need to be at least 6 digits long. Version 4 may return
-        // more data, but not currently supported. 
-        if msg.len() < 6 {
-            return Err(Error::new(ErrorKind::UnknownZWave, "Message is too short"));
-        }
-
+    pub fn report(frame: &IncomingFrame) -> Result<u8, Error> {
         // check the CommandClass and command
-        if msg[3] != CommandClass::SWITCH_MULTILEVEL as u8 || msg[4] != 0x03 {
-            return Err(Error::new(
-                ErrorKind::UnknownZWave,
-                "Answer contained wrong command class",
+        let payload = frame.expect(CommandClass::SWITCH_MULTILEVEL, 0x03)?;
+
+        // the payload need to be at least 1 byte long. Version 4 may return
+        // more data, but not currently supported.
+        if payload.is_empty() {
+            return Err(Error::parse(
+                CommandClass::SWITCH_MULTILEVEL,
+                "Message is too short",
             ));
         }
 
-        let val = msg[5];
+        let val = payload[0];
 
         // return the value
         Ok(val)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = SwitchMultilevel::set(0x05, 50, None).unwrap();
+        assert_eq!(msg.data, vec![0x32]);
+
+        let msg = SwitchMultilevel::set(0x05, 0xFF, Some(Duration::from_secs(3))).unwrap();
+        assert_eq!(msg.data, vec![0xFF, 0x03]);
+
+        assert!(SwitchMultilevel::set(0x05, 100, None).is_err());
+    }
+
+    #[test]
+    fn level_change() {
+        // dim down from the current level
+        let msg = SwitchMultilevel::start_level_change(0x05, false, None, None).unwrap();
+        assert_eq!(msg.data, vec![0x60, 0x00]);
+
+        // dim up from 10 within 5 seconds
+        let msg = SwitchMultilevel::start_level_change(
+            0x05,
+            true,
+            Some(10),
+            Some(Duration::from_secs(5)),
+        )
+        .unwrap();
+        assert_eq!(msg.data, vec![0x00, 0x0A, 0x05]);
+
+        assert!(SwitchMultilevel::start_level_change(0x05, true, Some(0xFF), None).is_err());
+        assert!(SwitchMultilevel::stop_level_change(0x05).data.is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+//! The Thermostat Operating State Command Class is used to read what the
+//! heating or cooling system is doing right now, like heating or idling.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+use std::convert::TryFrom;
+
+/// The state of a heating or cooling system
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum OperatingState {
+    Idle = 0x00,
+    Heating = 0x01,
+    Cooling = 0x02,
+    FanOnly = 0x03,
+    PendingHeat = 0x04,
+    PendingCool = 0x05,
+    VentEconomizer = 0x06,
+    AuxHeating = 0x07,
+    SecondStageHeating = 0x08,
+    SecondStageCooling = 0x09,
+    SecondStageAuxHeat = 0x0A,
+    ThirdStageAuxHeat = 0x0B,
+}
+
+/// Thermostat Operating State Command Class
+#[derive(Debug, Clone)]
+pub struct ThermostatOperatingState;
+
+impl ThermostatOperatingState {
+    /// The Thermostat Operating State Get Command is used to request the
+    /// operating state of the thermostat.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::THERMOSTAT_OPERATING_STATE,
+            0x02,
+            [],
+        )
+    }
+
+    /// The Thermostat Operating State Report Command is used to advertise the
+    /// operating state of the thermostat.
+    pub fn report(frame: &IncomingFrame) -> Result<OperatingState, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // | Command Class = COMMAND_CLASS_THERMOSTAT_OPERATING_STATE(0x42)|
+        // |      Command = THERMOSTAT_OPERATING_STATE_REPORT(0x03)        |
+        // |           Reserved            |        Operating State        |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::THERMOSTAT_OPERATING_STATE, 0x03)?;
+
+        let state = payload.first().ok_or_else(|| {
+            Error::parse(
+                CommandClass::THERMOSTAT_OPERATING_STATE,
+                "Message is too short",
+            )
+        })?;
+
+        OperatingState::try_from(state & 0x0F).map_err(|_| {
+            Error::parse(
+                CommandClass::THERMOSTAT_OPERATING_STATE,
+                "Unknown operating state",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x42, 0x03, 0x01]).unwrap();
+        assert_eq!(
+            ThermostatOperatingState::report(&frame),
+            Ok(OperatingState::Heating)
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0x42, 0x03, 0x0E]).unwrap();
+        assert!(ThermostatOperatingState::report(&frame).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+//! The Thermostat Setpoint Command Class is used to configure the
+//! temperature, which a thermostat keeps in a mode, like heating or cooling.
+//!
+//! The setpoints are encoded like the sensor values, with a precision, a
+//! scale and a size in front of the value. The scale is either degrees
+//! Celsius or degrees Fahrenheit.
+
+use crate::cmds::{
+    decode_value, encode_value, precision_scale_size, CommandClass, IncomingFrame, Message,
+    SensorValue, Unit,
+};
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+
+/// The setpoint types of a thermostat
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum ThermostatSetpointType {
+    Heating = 0x01,
+    Cooling = 0x02,
+    Furnace = 0x07,
+    DryAir = 0x08,
+    MoistAir = 0x09,
+    AutoChangeover = 0x0A,
+    EnergySaveHeating = 0x0B,
+    EnergySaveCooling = 0x0C,
+    AwayHeating = 0x0D,
+    AwayCooling = 0x0E,
+    FullPower = 0x0F,
+}
+
+/// A setpoint with the precision it was reported with
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemperatureSetpoint {
+    pub setpoint_type: ThermostatSetpointType,
+    pub value: SensorValue,
+    /// The amount of decimals of the value
+    pub precision: u8,
+}
+
+/// Thermostat Setpoint Command Class
+#[derive(Debug, Clone)]
+pub struct ThermostatSetpoint;
+
+impl ThermostatSetpoint {
+    /// The Thermostat Setpoint Set Command is used to set the temperature
+    /// of the setpoint type. The value is sent with the amount of decimals
+    /// of the precision.
+    pub fn set<N>(
+        node_id: N,
+        setpoint_type: ThermostatSetpointType,
+        value: SensorValue,
+        precision: u8,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_THERMOSTAT_SETPOINT(0x43) |
+        // |             Command = THERMOSTAT_SETPOINT_SET(0x01)           |
+        // |           Reserved            |        Setpoint Type          |
+        // |       Precision       |     Scale     |         Size          |
+        // |                     Value 1 ... Value n                       |
+        // -----------------------------------------------------------------
+        let scale = match value.unit {
+            Unit::Celsius => 0x00,
+            Unit::Fahrenheit => 0x01,
+            unit => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The unit {:?} is not a temperature", unit),
+                ))
+            }
+        };
+
+        let bytes = encode_value(value.value, precision)?;
+        let mut data = vec![
+            setpoint_type as u8,
+            precision << 5 | scale << 3 | bytes.len() as u8,
+        ];
+        data.extend_from_slice(&bytes);
+
+        Message::new(
+            node_id.into(),
+            CommandClass::THERMOSTAT_SETPOINT,
+            0x01,
+            &data,
+        )
+    }
+
+    /// The Thermostat Setpoint Get Command is used to request the
+    /// temperature of the setpoint type.
+    pub fn get<N>(node_id: N, setpoint_type: ThermostatSetpointType) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::THERMOSTAT_SETPOINT,
+            0x02,
+            [setpoint_type as u8],
+        )
+    }
+
+    /// The Thermostat Setpoint Report Command is used to advertise the
+    /// temperature of a setpoint type.
+    pub fn report(frame: &IncomingFrame) -> Result<TemperatureSetpoint, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_THERMOSTAT_SETPOINT(0x43) |
+        // |            Command = THERMOSTAT_SETPOINT_REPORT(0x03)         |
+        // |           Reserved            |        Setpoint Type          |
+        // |       Precision       |     Scale     |         Size          |
+        // |                     Value 1 ... Value n                       |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::THERMOSTAT_SETPOINT, 0x03)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(
+                CommandClass::THERMOSTAT_SETPOINT,
+                "Message is too short",
+            ));
+        }
+
+        let (precision, scale, size) = precision_scale_size(payload[1]);
+        let size = size as usize;
+        if payload.len() < 2 + size {
+            return Err(Error::parse(
+                CommandClass::THERMOSTAT_SETPOINT,
+                "Message is too short",
+            ));
+        }
+
+        let setpoint_type = ThermostatSetpointType::try_from(payload[0] & 0x0F).map_err(|_| {
+            Error::parse(
+                CommandClass::THERMOSTAT_SETPOINT,
+                format!("Unknown setpoint type {:#04x}", payload[0]),
+            )
+        })?;
+        let unit = match scale {
+            0x00 => Unit::Celsius,
+            0x01 => Unit::Fahrenheit,
+            _ => {
+                return Err(Error::parse(
+                    CommandClass::THERMOSTAT_SETPOINT,
+                    format!("The scale {} is not supported", scale),
+                ))
+            }
+        };
+
+        Ok(TemperatureSetpoint {
+            setpoint_type,
+            value: SensorValue::new(decode_value(&payload[2..2 + size], precision), unit),
+            precision,
+        })
+    }
+
+    /// The Thermostat Setpoint Supported Get Command is used to request the
+    /// setpoint types of the node.
+    pub fn supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::THERMOSTAT_SETPOINT, 0x04, [])
+    }
+
+    /// The Thermostat Setpoint Supported Report Command is used to advertise
+    /// the setpoint types of the node. Unknown types are skipped.
+    pub fn supported_report(frame: &IncomingFrame) -> Result<Vec<ThermostatSetpointType>, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |       Command Class = COMMAND_CLASS_THERMOSTAT_SETPOINT(0x43) |
+        // |       Command = THERMOSTAT_SETPOINT_SUPPORTED_REPORT(0x05)    |
+        // |                  Bit Mask 1 ... Bit Mask N                    |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::THERMOSTAT_SETPOINT, 0x05)?;
+
+        let mut types = vec![];
+        for (i, mask) in payload.iter().enumerate() {
+            for bit in 0..8 {
+                if mask & (1 << bit) != 0 {
+                    if let Ok(typ) = ThermostatSetpointType::try_from((i * 8 + bit) as u8) {
+                        types.push(typ);
+                    }
+                }
+            }
+        }
+
+        Ok(types)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let msg = ThermostatSetpoint::set(
+            0x05,
+            ThermostatSetpointType::Heating,
+            SensorValue::new(21.5, Unit::Celsius),
+            1,
+        )
+        .unwrap();
+        assert_eq!(msg.data, vec![0x01, 0x22, 0x00, 0xD7]);
+
+        assert!(ThermostatSetpoint::set(
+            0x05,
+            ThermostatSetpointType::Cooling,
+            SensorValue::new(50.0, Unit::Percent),
+            0,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x05, 0x43, 0x03, 0x02, 0x09, 0x48]).unwrap();
+        let setpoint = ThermostatSetpoint::report(&frame).unwrap();
+        assert_eq!(setpoint.setpoint_type, ThermostatSetpointType::Cooling);
+        assert_eq!(setpoint.value, SensorValue::new(72.0, Unit::Fahrenheit));
+
+        // the value converts like the sensor values
+        let celsius = setpoint.value.convert(Unit::Celsius).unwrap();
+        assert!((celsius.value - 22.222_222).abs() < 1e-6);
+
+        // the value is missing
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x43, 0x03, 0x01, 0x22]).unwrap();
+        assert!(ThermostatSetpoint::report(&frame).is_err());
+    }
+
+    #[test]
+    fn supported_report() {
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x43, 0x05, 0x86, 0x01]).unwrap();
+        assert_eq!(
+            ThermostatSetpoint::supported_report(&frame),
+            Ok(vec![
+                ThermostatSetpointType::Heating,
+                ThermostatSetpointType::Cooling,
+                ThermostatSetpointType::Furnace,
+                ThermostatSetpointType::DryAir
+            ])
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! The Time Command Class is used to read the local time and date of a node.
+//!
+//! Some nodes don't have a clock of their own and ask the controller for the
+//! time and the date instead, the answers are built with `Time::answer`.
+
+use crate::cmds::time_parameters::DateTime;
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+/// Time Command Class
+#[derive(Debug, Clone)]
+pub struct Time;
+
+impl Time {
+    /// The Time Get Command is used to request the local time.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::TIME, 0x01, [])
+    }
+
+    /// Build the Time Report Command, which answers a Time Get of the node.
+    pub fn report_message<N>(node_id: N, time: &DateTime) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |            Command Class = COMMAND_CLASS_TIME(0x8A)           |
+        // |                  Command = TIME_REPORT(0x02)                  |
+        // |  RTC  |    Reserved   |             Hour Local Time           |
+        // |                       Minute Local Time                       |
+        // |                       Second Local Time                       |
+        // -----------------------------------------------------------------
+        Message::from_array(
+            node_id.into(),
+            CommandClass::TIME,
+            0x02,
+            [time.hour & 0x1F, time.minute, time.second],
+        )
+    }
+
+    /// The Time Report Command is used to advertise the local time.
+    ///
+    /// Returns the hour, the minute and the second.
+    pub fn report(frame: &IncomingFrame) -> Result<(u8, u8, u8), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::TIME, 0x02)?;
+
+        if payload.len() < 3 {
+            return Err(Error::parse(CommandClass::TIME, "Message is too short"));
+        }
+
+        Ok((payload[0] & 0x1F, payload[1], payload[2]))
+    }
+
+    /// The Date Get Command is used to request the local date.
+    pub fn date_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::TIME, 0x03, [])
+    }
+
+    /// Build the Date Report Command, which answers a Date Get of the node.
+    pub fn date_report_message<N>(node_id: N, date: &DateTime) -> Message
+    where
+        N: Into<u8>,
+    {
+        let year = date.year.to_be_bytes();
+
+        Message::from_array(
+            node_id.into(),
+            CommandClass::TIME,
+            0x04,
+            [year[0], year[1], date.month, date.day],
+        )
+    }
+
+    /// The Date Report Command is used to advertise the local date.
+    ///
+    /// Returns the year, the month and the day.
+    pub fn date_report(frame: &IncomingFrame) -> Result<(u16, u8, u8), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::TIME, 0x04)?;
+
+        if payload.len() < 4 {
+            return Err(Error::parse(CommandClass::TIME, "Message is too short"));
+        }
+
+        Ok((
+            u16::from_be_bytes([payload[0], payload[1]]),
+            payload[2],
+            payload[3],
+        ))
+    }
+
+    /// Build the answer of a Time Get or a Date Get, which was sent by a
+    /// node. Returns `None` for all other commands.
+    pub fn answer(frame: &IncomingFrame, now: &DateTime) -> Option<Message> {
+        match (frame.command_class.ok()?, frame.command) {
+            (CommandClass::TIME, 0x01) => Some(Time::report_message(frame.source, now)),
+            (CommandClass::TIME, 0x03) => Some(Time::date_report_message(frame.source, now)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime {
+        DateTime {
+            year: 2024,
+            month: 3,
+            day: 9,
+            hour: 14,
+            minute: 5,
+            second: 30,
+        }
+    }
+
+    #[test]
+    fn answer() {
+        let frame = IncomingFrame::parse(&[0x00, 0x07, 0x02, 0x8A, 0x01]).unwrap();
+        let msg = Time::answer(&frame, &now()).unwrap();
+        assert_eq!(msg.node_id, 0x07);
+        assert_eq!(msg.data, vec![0x0E, 0x05, 0x1E]);
+
+        let frame = IncomingFrame::parse(&[0x00, 0x07, 0x02, 0x8A, 0x03]).unwrap();
+        let msg = Time::answer(&frame, &now()).unwrap();
+        assert_eq!(msg.data, vec![0x07, 0xE8, 0x03, 0x09]);
+
+        let frame = IncomingFrame::parse(&[0x00, 0x07, 0x03, 0x20, 0x03, 0x00]).unwrap();
+        assert!(Time::answer(&frame, &now()).is_none());
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x07, 0x05, 0x8A, 0x02, 0x8E, 0x05, 0x1E]).unwrap();
+        assert_eq!(Time::report(&frame), Ok((14, 5, 30)));
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x07, 0x06, 0x8A, 0x04, 0x07, 0xE8, 0x03, 0x09]).unwrap();
+        assert_eq!(Time::date_report(&frame), Ok((2024, 3, 9)));
+    }
+}
@@ -0,0 +1,212 @@
+//! The Time Parameters Command Class is used to set and read the date and
+//! the time of a node in UTC.
+
+use crate::cmds::clock::ClockTime;
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A date and time in UTC
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Convert the system time into the date and time, the seconds
+    /// before 1970 are not supported.
+    pub fn from_system(time: SystemTime) -> DateTime {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::from_secs(0))
+            .as_secs();
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        let secs = secs % 86_400;
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+
+    /// Convert the system time into the local date and time, the offset to
+    /// UTC is given in seconds.
+    pub fn local(time: SystemTime, utc_offset: i64) -> DateTime {
+        let offset = Duration::from_secs(utc_offset.unsigned_abs());
+        let local = if utc_offset < 0 {
+            time - offset
+        } else {
+            time + offset
+        };
+
+        DateTime::from_system(local)
+    }
+
+    /// Convert the date and time into the system time
+    pub fn to_system(&self) -> SystemTime {
+        let days = days_from_civil(self.year, self.month, self.day);
+        let secs =
+            days * 86_400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+
+        UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+    }
+
+    /// Returns the day of the week and the time, like the Clock Command
+    /// Class uses it
+    pub fn clock_time(&self) -> ClockTime {
+        // the 1st of January 1970 was a Thursday
+        let days = days_from_civil(self.year, self.month, self.day);
+
+        ClockTime {
+            weekday: ((days + 3).rem_euclid(7) + 1) as u8,
+            hour: self.hour,
+            minute: self.minute,
+        }
+    }
+}
+
+/// Time Parameters Command Class
+#[derive(Debug, Clone)]
+pub struct TimeParameters;
+
+impl TimeParameters {
+    /// The Time Parameters Set Command is used to set the date and the time
+    /// of the node in UTC.
+    pub fn set<N>(node_id: N, time: DateTime) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_TIME_PARAMETERS(0x8B)       |
+        // |             Command = TIME_PARAMETERS_SET(0x01)               |
+        // |                          Year (MSB)                           |
+        // |                          Year (LSB)                           |
+        // |                             Month                             |
+        // |                              Day                              |
+        // |                           Hour UTC                            |
+        // |                          Minute UTC                           |
+        // |                          Second UTC                           |
+        // -----------------------------------------------------------------
+        Message::from_array(
+            node_id.into(),
+            CommandClass::TIME_PARAMETERS,
+            0x01,
+            [
+                (time.year >> 8) as u8,
+                time.year as u8,
+                time.month,
+                time.day,
+                time.hour,
+                time.minute,
+                time.second,
+            ],
+        )
+    }
+
+    /// The Time Parameters Get Command is used to request the date and the
+    /// time of the node.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::TIME_PARAMETERS, 0x02, [])
+    }
+
+    /// The Time Parameters Report Command is used to advertise the date and
+    /// the time of the node in UTC.
+    pub fn report(frame: &IncomingFrame) -> Result<DateTime, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::TIME_PARAMETERS, 0x03)?;
+
+        // the payload need to be at least 7 bytes long
+        if payload.len() < 7 {
+            return Err(Error::parse(
+                CommandClass::TIME_PARAMETERS,
+                "Message is too short",
+            ));
+        }
+
+        Ok(DateTime {
+            year: ((payload[0] as u16) << 8) | payload[1] as u16,
+            month: payload[2],
+            day: payload[3],
+            hour: payload[4],
+            minute: payload[5],
+            second: payload[6],
+        })
+    }
+}
+
+/// Returns the days since the 1st of January 1970
+fn days_from_civil(year: u16, month: u8, day: u8) -> i64 {
+    let (month, day) = (month as i64, day as i64);
+    let year = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the year, month and day of the days since the 1st of January 1970
+fn civil_from_days(days: i64) -> (u16, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year as u16, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_report() {
+        // Thursday, the 29th of February 2024, 13:45:10 UTC
+        let system = UNIX_EPOCH + Duration::from_secs(1_709_214_310);
+        let time = DateTime::from_system(system);
+        assert_eq!(
+            time,
+            DateTime {
+                year: 2024,
+                month: 2,
+                day: 29,
+                hour: 13,
+                minute: 45,
+                second: 10,
+            }
+        );
+        assert_eq!(time.to_system(), system);
+        assert_eq!(time.clock_time().weekday, 4);
+
+        let msg = TimeParameters::set(0x05, time);
+        assert_eq!(msg.data, vec![0x07, 0xE8, 0x02, 0x1D, 0x0D, 0x2D, 0x0A]);
+
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x09, 0x8B, 0x03, 0x07, 0xE8, 0x02, 0x1D, 0x0D, 0x2D, 0x0A,
+        ])
+        .unwrap();
+        assert_eq!(TimeParameters::report(&frame), Ok(time));
+    }
+}
@@ -0,0 +1,400 @@
+//! The Transport Service Command Class is used to send a command, which
+//! doesn't fit into a single frame, as several segments.
+//!
+//! Each segment carries the size of the whole datagram, the session it
+//! belongs to, its offset and a CRC-16 checksum. The receiver puts the
+//! datagram together again, when all segments arrived.
+//!
+//! The receiver confirms a complete datagram with a Segment Complete and
+//! asks for a missing segment with a Segment Request.
+
+use crate::cmds::builder::MAX_COMMAND_LENGTH;
+use crate::cmds::crc16::Crc16;
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::Error;
+use crate::payload::Payload;
+
+use std::convert::TryFrom;
+
+/// The highest session id, the id is counted up to it and starts again at 0
+pub const MAX_SESSION_ID: u8 = 0x0F;
+
+/// The command of the first segment, the lowest 3 bits are the high bits of the size
+const FIRST_SEGMENT: u8 = 0xC0;
+
+/// The command of the following segments
+const SUBSEQUENT_SEGMENT: u8 = 0xE0;
+
+/// The command, which requests a missing segment
+const SEGMENT_REQUEST: u8 = 0xC8;
+
+/// The command, which confirms a complete datagram
+const SEGMENT_COMPLETE: u8 = 0xE8;
+
+/// The command, which tells the sender to wait
+const SEGMENT_WAIT: u8 = 0xF0;
+
+/// The commands, which the receiver of a datagram answers with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SegmentControl {
+    /// The segment at the offset is missing
+    Request { session: u8, offset: usize },
+    /// All segments of the datagram arrived
+    Complete { session: u8 },
+    /// The receiver is busy, the amount of segments it still waits for
+    Wait { pending: u8 },
+}
+
+/// Transport Service Command Class
+#[derive(Debug, Clone)]
+pub struct TransportService;
+
+impl TransportService {
+    /// Check if the message needs to be split into segments
+    pub fn needs_segments(message: &Message) -> bool {
+        message.data.len() + 2 > MAX_COMMAND_LENGTH
+    }
+
+    /// Split the message into the First Segment and the Subsequent Segment
+    /// Commands, which each fit into a frame.
+    pub fn segment(message: Message, session: u8) -> Result<Vec<Message>, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |    Command Class = COMMAND_CLASS_TRANSPORT_SERVICE(0x55)      |
+        // |   Command = FIRST(0xC0) or SUBSEQUENT(0xE0)   |  Size (MSB)   |
+        // |                          Size (LSB)                           |
+        // |          Session ID           |  Ext  |  Offset (MSB, sub.)   |
+        // |                   Offset (LSB, subsequent)                    |
+        // |                  Payload 1 ... Payload n                      |
+        // |                          Checksum 1                           |
+        // |                          Checksum 2                           |
+        // -----------------------------------------------------------------
+        let mut datagram = vec![message.cmd_class as u8, message.cmd];
+        datagram.extend_from_slice(&message.data);
+
+        let size = datagram.len();
+        let session = (session & MAX_SESSION_ID) << 4;
+        let mut segments = vec![];
+        let mut offset = 0;
+
+        while offset < size {
+            let (cmd, mut data, room) = if offset == 0 {
+                (
+                    FIRST_SEGMENT | (size >> 8) as u8 & 0x07,
+                    vec![size as u8, session],
+                    MAX_COMMAND_LENGTH - 6,
+                )
+            } else {
+                (
+                    SUBSEQUENT_SEGMENT | (size >> 8) as u8 & 0x07,
+                    vec![
+                        size as u8,
+                        session | (offset >> 8) as u8 & 0x07,
+                        offset as u8,
+                    ],
+                    MAX_COMMAND_LENGTH - 7,
+                )
+            };
+
+            let end = ::std::cmp::min(size, offset + room);
+            data.extend_from_slice(&datagram[offset..end]);
+
+            let mut covered = vec![CommandClass::TRANSPORT_SERVICE as u8, cmd];
+            covered.extend_from_slice(&data);
+            let crc = Crc16::checksum(&covered);
+            data.extend_from_slice(&[(crc >> 8) as u8, crc as u8]);
+
+            segments.push(Message {
+                node_id: message.node_id,
+                cmd_class: CommandClass::TRANSPORT_SERVICE,
+                cmd,
+                data: Payload::try_from(&data[..])?,
+                raw: Payload::new(),
+            });
+            offset = end;
+        }
+
+        Ok(segments)
+    }
+
+    /// Returns the offset of the segment in its datagram
+    pub fn offset(segment: &Message) -> usize {
+        match segment.cmd & 0xF8 {
+            SUBSEQUENT_SEGMENT if segment.data.len() > 2 => {
+                ((segment.data[1] as usize & 0x07) << 8) | segment.data[2] as usize
+            }
+            _ => 0,
+        }
+    }
+
+    /// The Segment Request Command is used to request a missing segment of
+    /// a datagram.
+    pub fn segment_request<N>(node_id: N, session: u8, offset: usize) -> Message
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |    Command Class = COMMAND_CLASS_TRANSPORT_SERVICE(0x55)      |
+        // |           Command = COMMAND_SEGMENT_REQUEST(0xC8)             |
+        // |          Session ID           |  Res  | Offset (MSB)          |
+        // |                         Offset (LSB)                          |
+        // -----------------------------------------------------------------
+        Message::from_array(
+            node_id.into(),
+            CommandClass::TRANSPORT_SERVICE,
+            SEGMENT_REQUEST,
+            [
+                (session & MAX_SESSION_ID) << 4 | (offset >> 8) as u8 & 0x07,
+                offset as u8,
+            ],
+        )
+    }
+
+    /// The Segment Complete Command is used to confirm, that all segments of
+    /// a datagram arrived.
+    pub fn segment_complete<N>(node_id: N, session: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::TRANSPORT_SERVICE,
+            SEGMENT_COMPLETE,
+            [(session & MAX_SESSION_ID) << 4],
+        )
+    }
+
+    /// Returns the Segment Request, Complete or Wait Command, which the
+    /// receiver of a datagram answered with.
+    pub fn control(frame: &IncomingFrame) -> Result<SegmentControl, Error> {
+        let payload = &frame.payload;
+        if frame.command_class != Ok(CommandClass::TRANSPORT_SERVICE) {
+            return Err(Error::parse(
+                CommandClass::TRANSPORT_SERVICE,
+                "Answer contained wrong command class",
+            ));
+        }
+
+        let too_short = || Error::parse(CommandClass::TRANSPORT_SERVICE, "Message is too short");
+        match frame.command & 0xF8 {
+            SEGMENT_REQUEST if payload.len() >= 2 => Ok(SegmentControl::Request {
+                session: payload[0] >> 4,
+                offset: ((payload[0] as usize & 0x07) << 8) | payload[1] as usize,
+            }),
+            SEGMENT_COMPLETE => payload
+                .first()
+                .map(|p| SegmentControl::Complete { session: p >> 4 })
+                .ok_or_else(too_short),
+            SEGMENT_WAIT => payload
+                .first()
+                .map(|p| SegmentControl::Wait { pending: *p })
+                .ok_or_else(too_short),
+            SEGMENT_REQUEST => Err(too_short()),
+            _ => Err(Error::parse(
+                CommandClass::TRANSPORT_SERVICE,
+                "The command is no segment control",
+            )),
+        }
+    }
+
+    /// Check if the frame is a Segment Request, Complete or Wait Command
+    pub fn is_control(frame: &IncomingFrame) -> bool {
+        frame.command_class == Ok(CommandClass::TRANSPORT_SERVICE)
+            && [SEGMENT_REQUEST, SEGMENT_COMPLETE, SEGMENT_WAIT].contains(&(frame.command & 0xF8))
+    }
+}
+
+/// Puts the segments of a datagram together, which were sent by a node.
+#[derive(Debug, Clone, Default)]
+pub struct Reassembly {
+    source: u8,
+    session: u8,
+    datagram: Vec<u8>,
+    filled: Vec<bool>,
+}
+
+impl Reassembly {
+    /// Create a reassembly, which waits for the first segment
+    pub fn new() -> Reassembly {
+        Reassembly::default()
+    }
+
+    /// Returns the session of the datagram, which is put together
+    pub fn session(&self) -> u8 {
+        self.session
+    }
+
+    /// Returns the Segment Request for the first missing segment, when a
+    /// datagram is put together.
+    pub fn segment_request(&self) -> Option<Message> {
+        let offset = self.filled.iter().position(|f| !f)?;
+        Some(TransportService::segment_request(
+            self.source,
+            self.session,
+            offset,
+        ))
+    }
+
+    /// Add the segment to the datagram. Returns the command of the datagram,
+    /// when all segments arrived. A datagram, which misses its first segment,
+    /// is started by the next one.
+    pub fn push(&mut self, frame: &IncomingFrame) -> Result<Option<IncomingFrame>, Error> {
+        let payload = &frame.payload;
+        let first = frame.command & 0xF8 == FIRST_SEGMENT;
+        let header = if first { 2 } else { 3 };
+
+        if frame.command_class != Ok(CommandClass::TRANSPORT_SERVICE)
+            || (!first && frame.command & 0xF8 != SUBSEQUENT_SEGMENT)
+        {
+            return Err(Error::parse(
+                CommandClass::TRANSPORT_SERVICE,
+                "Answer contained wrong command class",
+            ));
+        }
+        if payload.len() < header + 2 {
+            return Err(Error::parse(
+                CommandClass::TRANSPORT_SERVICE,
+                "Message is too short",
+            ));
+        }
+
+        let (data, crc) = payload.split_at(payload.len() - 2);
+        let mut covered = vec![CommandClass::TRANSPORT_SERVICE as u8, frame.command];
+        covered.extend_from_slice(data);
+        if Crc16::checksum(&covered) != ((crc[0] as u16) << 8) | crc[1] as u16 {
+            return Err(Error::parse(
+                CommandClass::TRANSPORT_SERVICE,
+                "The checksum doesn't match",
+            ));
+        }
+
+        let size = ((frame.command as usize & 0x07) << 8) | data[0] as usize;
+        let session = data[1] >> 4;
+        let offset = if first {
+            0
+        } else {
+            ((data[1] as usize & 0x07) << 8) | data[2] as usize
+        };
+
+        // the first segment starts a new datagram
+        let other = frame.source != self.source || session != self.session;
+        if self.datagram.is_empty() || (first && other) {
+            self.source = frame.source;
+            self.session = session;
+            self.datagram = vec![0; size];
+            self.filled = vec![false; size];
+        } else if other || size != self.datagram.len() {
+            return Err(Error::parse(
+                CommandClass::TRANSPORT_SERVICE,
+                "The segment belongs to another datagram",
+            ));
+        }
+
+        let chunk = &data[header..];
+        if offset + chunk.len() > size {
+            return Err(Error::parse(
+                CommandClass::TRANSPORT_SERVICE,
+                "The segment is outside of the datagram",
+            ));
+        }
+        self.datagram[offset..offset + chunk.len()].copy_from_slice(chunk);
+        for filled in &mut self.filled[offset..offset + chunk.len()] {
+            *filled = true;
+        }
+
+        if size < 2 || self.filled.iter().any(|f| !f) {
+            return Ok(None);
+        }
+
+        let command_class = CommandClass::try_from(self.datagram[0]).map_err(|_| self.datagram[0]);
+        let command = IncomingFrame {
+            status: frame.status,
+            source: frame.source,
+            command_class,
+            command: self.datagram[1],
+            payload: self.datagram[2..].to_vec(),
+        };
+
+        self.datagram.clear();
+        self.filled.clear();
+        Ok(Some(command))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defs::RxStatus;
+
+    /// Convert the sent segment into a received frame
+    fn received(message: &Message) -> IncomingFrame {
+        IncomingFrame {
+            status: RxStatus::default(),
+            source: message.node_id,
+            command_class: Ok(message.cmd_class),
+            command: message.cmd,
+            payload: message.data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn segments() {
+        let data: Vec<u8> = (0..60).collect();
+        let message = Message::new(0x05, CommandClass::CONFIGURATION, 0x04, &data).unwrap();
+        assert!(TransportService::needs_segments(&message));
+
+        let segments = TransportService::segment(message, 0x03).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].cmd, 0xC0);
+        assert_eq!(&segments[0].data[..4], &[62, 0x30, 0x70, 0x04]);
+        assert_eq!(&segments[1].data[..3], &[62, 0x30, 40]);
+
+        // the datagram is complete with the last segment
+        let mut reassembly = Reassembly::new();
+        assert_eq!(reassembly.push(&received(&segments[0])).unwrap(), None);
+        let frame = reassembly.push(&received(&segments[1])).unwrap().unwrap();
+        assert_eq!(frame.command_class, Ok(CommandClass::CONFIGURATION));
+        assert_eq!(frame.command, 0x04);
+        assert_eq!(frame.payload, data);
+
+        // a corrupt segment is dropped
+        let mut corrupt = received(&segments[0]);
+        corrupt.payload[5] ^= 0x01;
+        assert!(reassembly.push(&corrupt).is_err());
+    }
+
+    #[test]
+    fn missing_segment() {
+        let data: Vec<u8> = (0..60).collect();
+        let message = Message::new(0x05, CommandClass::CONFIGURATION, 0x04, &data).unwrap();
+        let segments = TransportService::segment(message, 0x03).unwrap();
+        assert_eq!(TransportService::offset(&segments[1]), 40);
+
+        // the first segment got lost
+        let mut reassembly = Reassembly::new();
+        assert!(reassembly.segment_request().is_none());
+        assert_eq!(reassembly.push(&received(&segments[1])).unwrap(), None);
+        let request = reassembly.segment_request().unwrap();
+        assert_eq!(
+            (request.cmd, request.data.to_vec()),
+            (0xC8, vec![0x30, 0x00])
+        );
+
+        let frame = reassembly.push(&received(&segments[0])).unwrap().unwrap();
+        assert_eq!(frame.payload, data);
+        assert!(reassembly.segment_request().is_none());
+
+        let complete = TransportService::segment_complete(0x05, reassembly.session());
+        assert_eq!(complete.data.to_vec(), vec![0x30]);
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x55, 0xC8, 0x30, 0x28]).unwrap();
+        assert!(TransportService::is_control(&frame));
+        assert_eq!(
+            TransportService::control(&frame),
+            Ok(SegmentControl::Request {
+                session: 3,
+                offset: 40
+            })
+        );
+    }
+}
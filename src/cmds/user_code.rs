@@ -0,0 +1,288 @@
+//! The User Code Command Class is used to manage User Codes in access control systems,
+//! like the codes which open a door lock.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+use std::convert::TryFrom;
+
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// List of the different states of a user code slot.
+#[repr(u8)]
+pub enum UserIdStatus {
+    Available = 0x00,
+    Occupied = 0x01,
+    Disabled = 0x02,
+    NotAvailable = 0xFE,
+}
+
+/// The code of a user code slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserCodeReport {
+    pub user_id: u8,
+    pub status: UserIdStatus,
+    pub code: String,
+}
+
+/// The keys which the device supports for the user codes.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserCodeCapabilities {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub keys: Vec<char>,
+}
+
+impl Default for UserCodeCapabilities {
+    /// Version 1 devices support 4-10 digits
+    fn default() -> UserCodeCapabilities {
+        UserCodeCapabilities {
+            min_length: 4,
+            max_length: 10,
+            keys: "0123456789".chars().collect(),
+        }
+    }
+}
+
+impl UserCodeCapabilities {
+    /// Check if the code can be stored at the device
+    pub fn validate(&self, code: &str) -> Result<(), Error> {
+        let length = code.chars().count();
+
+        if length < self.min_length || length > self.max_length {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The user code needs {}-{} characters",
+                    self.min_length, self.max_length
+                ),
+            ));
+        }
+
+        if let Some(c) = code.chars().find(|c| !self.keys.contains(c)) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The user code character '{}' is not supported", c),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// User Code Command Class
+#[derive(Debug, Clone)]
+pub struct UserCode;
+
+impl UserCode {
+    /// The User Code Set Command is used to set a User Code in the device.
+    pub fn set<N>(
+        node_id: N,
+        user_id: u8,
+        status: UserIdStatus,
+        code: &str,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |         Command Class = COMMAND_CLASS_USER_CODE(0x63)         |
+        // |                 Command = USER_CODE_SET(0x01)                 |
+        // |                        User Identifier                        |
+        // |                         User ID Status                        |
+        // |                  User Code 1 ... User Code n                  |
+        // -----------------------------------------------------------------
+        let mut data = vec![user_id, status as u8];
+        data.extend_from_slice(code.as_bytes());
+
+        Message::new(node_id.into(), CommandClass::USER_CODE, 0x01, &data)
+    }
+
+    /// Clear the User Code of the slot, so the slot is available again.
+    pub fn clear<N>(node_id: N, user_id: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::USER_CODE,
+            0x01,
+            [
+                user_id,
+                UserIdStatus::Available as u8,
+                0x00,
+                0x00,
+                0x00,
+                0x00,
+            ],
+        )
+    }
+
+    /// The User Code Get Command is used to request the User Code of a specific slot.
+    pub fn get<N>(node_id: N, user_id: u8) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::USER_CODE, 0x02, [user_id])
+    }
+
+    /// The User Code Report Command advertises the User Code of a slot. It's also sent
+    /// by the device, when a code was changed at the device.
+    pub fn report(frame: &IncomingFrame) -> Result<UserCodeReport, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |         Command Class = COMMAND_CLASS_USER_CODE(0x63)         |
+        // |                Command = USER_CODE_REPORT(0x03)               |
+        // |                        User Identifier                        |
+        // |                         User ID Status                        |
+        // |                  User Code 1 ... User Code n                  |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::USER_CODE, 0x03)?;
+
+        // the payload need to be at least 2 bytes long
+        if payload.len() < 2 {
+            return Err(Error::parse(
+                CommandClass::USER_CODE,
+                "Message is too short",
+            ));
+        }
+
+        let status = UserIdStatus::try_from(payload[1]).map_err(|_| {
+            Error::parse(
+                CommandClass::USER_CODE,
+                "Answer contained wrong user id status",
+            )
+        })?;
+
+        // empty slots may report zeros as code
+        let code = payload[2..]
+            .iter()
+            .filter(|b| **b != 0x00)
+            .map(|b| *b as char)
+            .collect();
+
+        Ok(UserCodeReport {
+            user_id: payload[0],
+            status,
+            code,
+        })
+    }
+
+    /// The Users Number Get Command is used to request the number of user codes
+    /// supported by the device.
+    pub fn users_number_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::USER_CODE, 0x04, [])
+    }
+
+    /// The Users Number Report Command advertises the number of user codes.
+    pub fn users_number_report(frame: &IncomingFrame) -> Result<u8, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::USER_CODE, 0x05)?;
+
+        // the payload need to be at least 1 byte long
+        if payload.is_empty() {
+            return Err(Error::parse(
+                CommandClass::USER_CODE,
+                "Message is too short",
+            ));
+        }
+
+        Ok(payload[0])
+    }
+
+    /// The User Code Capabilities Get Command, version 2 is used to request the
+    /// supported keys of the device.
+    pub fn capabilities_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::USER_CODE, 0x06, [])
+    }
+
+    /// The User Code Capabilities Report Command, version 2 advertises the
+    /// supported keys of the device.
+    pub fn capabilities_report(frame: &IncomingFrame) -> Result<UserCodeCapabilities, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::USER_CODE, 0x07)?;
+
+        // skip the user id status and the keypad mode bitmasks
+        let mut pos = 0;
+        for _ in 0..2 {
+            pos += 1 + (payload.get(pos).cloned().unwrap_or(0) & 0b00011111) as usize;
+        }
+
+        // the supported keys bitmask, each bit is an ASCII character
+        let length = (payload.get(pos).cloned().unwrap_or(0) & 0b00011111) as usize;
+        if payload.len() < pos + 1 + length {
+            return Err(Error::parse(
+                CommandClass::USER_CODE,
+                "Message is too short",
+            ));
+        }
+
+        let mut keys = vec![];
+        for (i, byte) in payload[pos + 1..pos + 1 + length].iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (1 << bit) != 0 {
+                    keys.push((i * 8 + bit) as u8 as char);
+                }
+            }
+        }
+
+        Ok(UserCodeCapabilities {
+            keys,
+            ..UserCodeCapabilities::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let report = UserCode::report(
+            &IncomingFrame::parse(&[
+                0x00, 0x05, 0x08, 0x63, 0x03, 0x02, 0x01, 0x31, 0x32, 0x33, 0x34,
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            report,
+            UserCodeReport {
+                user_id: 0x02,
+                status: UserIdStatus::Occupied,
+                code: String::from("1234"),
+            }
+        );
+    }
+
+    #[test]
+    fn capabilities() {
+        // digits and '#' are supported
+        let caps = UserCode::capabilities_report(
+            &IncomingFrame::parse(&[
+                0x00, 0x05, 0x0F, 0x63, 0x07, 0x01, 0x03, 0x01, 0x01, 0x08, 0x00, 0x00, 0x00, 0x00,
+                0x08, 0x00, 0xFF, 0x03,
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(caps.validate("1234#").is_ok());
+        assert!(caps.validate("12a4").is_err());
+        assert!(caps.validate("123").is_err());
+        assert!(UserCodeCapabilities::default()
+            .validate("12345678901")
+            .is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! The Version Command Class is used to read the library type, the protocol
+//! version and the firmware versions of a node, and the version of each
+//! command class it supports.
+//!
+//! The version of a command class decides which encoding the node
+//! understands, like the version 2 meter reports.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::defs::LibraryType;
+use crate::error::Error;
+
+use std::convert::TryFrom;
+
+/// The versions of the Z-Wave library and the firmwares of a node.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VersionReport {
+    pub library: LibraryType,
+    /// The protocol version and sub version
+    pub protocol: (u8, u8),
+    /// The version and sub version of each firmware, the first one is the
+    /// firmware of the Z-Wave chip
+    pub firmwares: Vec<(u8, u8)>,
+    /// The hardware version, only reported by version 2 nodes
+    pub hardware: Option<u8>,
+}
+
+/// Version Command Class
+#[derive(Debug, Clone)]
+pub struct Version;
+
+impl Version {
+    /// The Version Get Command is used to request the library type, the
+    /// protocol version and the firmware versions of the node.
+    pub fn get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::VERSION, 0x11, [])
+    }
+
+    /// The Version Report Command is used to advertise the library type, the
+    /// protocol version and the firmware versions of the node.
+    pub fn report(frame: &IncomingFrame) -> Result<VersionReport, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |          Command Class = COMMAND_CLASS_VERSION(0x86)          |
+        // |                Command = VERSION_REPORT(0x12)                 |
+        // |                   Z-Wave Protocol Library Type                |
+        // |                    Z-Wave Protocol Version                    |
+        // |                  Z-Wave Protocol Sub Version                  |
+        // |                      Firmware 0 Version                       |
+        // |                    Firmware 0 Sub Version                     |
+        // |                 Hardware Version (version 2)                  |
+        // |            Number of firmware targets (version 2)             |
+        // |        Firmware 1 Version, Firmware 1 Sub Version ...         |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::VERSION, 0x12)?;
+
+        if payload.len() < 5 {
+            return Err(Error::parse(CommandClass::VERSION, "Message is too short"));
+        }
+
+        let mut report = VersionReport {
+            library: LibraryType::try_from(payload[0]).unwrap_or(LibraryType::Unknown),
+            protocol: (payload[1], payload[2]),
+            firmwares: vec![(payload[3], payload[4])],
+            hardware: None,
+        };
+
+        // version 2 adds the hardware and the other firmwares
+        if payload.len() >= 7 {
+            let targets = payload[6] as usize;
+            if payload.len() < 7 + targets * 2 {
+                return Err(Error::parse(CommandClass::VERSION, "Message is too short"));
+            }
+
+            report.hardware = Some(payload[5]);
+            report
+                .firmwares
+                .extend(payload[7..7 + targets * 2].chunks(2).map(|v| (v[0], v[1])));
+        }
+
+        Ok(report)
+    }
+
+    /// The Version Command Class Get Command is used to request the version
+    /// of a command class, which the node supports.
+    pub fn command_class_get<N>(node_id: N, cmd_class: CommandClass) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::VERSION,
+            0x13,
+            [cmd_class as u8],
+        )
+    }
+
+    /// The Version Command Class Report Command is used to advertise the
+    /// version of a command class. The version is 0, when the node doesn't
+    /// support the command class.
+    pub fn command_class_report(frame: &IncomingFrame) -> Result<(CommandClass, u8), Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::VERSION, 0x14)?;
+
+        if payload.len() < 2 {
+            return Err(Error::parse(CommandClass::VERSION, "Message is too short"));
+        }
+
+        let cmd_class = CommandClass::try_from(payload[0]).map_err(|_| {
+            Error::parse(
+                CommandClass::VERSION,
+                format!("Unknown command class {:#04x}", payload[0]),
+            )
+        })?;
+
+        Ok((cmd_class, payload[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        // a version 1 report
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x86, 0x12, 0x03, 0x04, 0x05, 0x01, 0x02])
+                .unwrap();
+        assert_eq!(
+            Version::report(&frame),
+            Ok(VersionReport {
+                library: LibraryType::EnhancedSlave,
+                protocol: (0x04, 0x05),
+                firmwares: vec![(0x01, 0x02)],
+                hardware: None,
+            })
+        );
+
+        // a version 2 report with a second firmware
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0B, 0x86, 0x12, 0x03, 0x06, 0x04, 0x01, 0x02, 0x07, 0x01, 0x03, 0x00,
+        ])
+        .unwrap();
+        let report = Version::report(&frame).unwrap();
+        assert_eq!(report.hardware, Some(0x07));
+        assert_eq!(report.firmwares, vec![(0x01, 0x02), (0x03, 0x00)]);
+    }
+
+    #[test]
+    fn command_class_report() {
+        let msg = Version::command_class_get(0x05, CommandClass::METER);
+        assert_eq!(msg.data, vec![0x32]);
+
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x04, 0x86, 0x14, 0x32, 0x03]).unwrap();
+        assert_eq!(
+            Version::command_class_report(&frame),
+            Ok((CommandClass::METER, 0x03))
+        );
+    }
+}
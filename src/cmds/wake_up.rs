@@ -0,0 +1,239 @@
+//! The Wake Up Command Class is used by sleeping nodes, which only listen for
+//! a short time after they woke up.
+//!
+//! The node wakes up in an interval and sends a Wake Up Notification to the
+//! node configured as its target. The target sends its pending commands and
+//! lets the node sleep again with the No More Information command.
+
+use crate::cmds::{CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+
+/// The largest interval, the seconds are sent as 24 bit value
+pub const MAX_WAKE_UP_INTERVAL: u32 = 0x00FF_FFFF;
+
+/// The interval and the node, which gets the notifications.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WakeUpInterval {
+    pub seconds: u32,
+    pub target: u8,
+}
+
+/// The intervals, which the node supports.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WakeUpCapabilities {
+    pub min_seconds: u32,
+    pub max_seconds: u32,
+    pub default_seconds: u32,
+    pub step_seconds: u32,
+}
+
+impl WakeUpCapabilities {
+    /// Check if the node accepts the interval
+    pub fn validate(&self, seconds: u32) -> Result<(), Error> {
+        // some nodes only allow a single interval
+        let step = if self.step_seconds == 0 {
+            1
+        } else {
+            self.step_seconds
+        };
+
+        if seconds < self.min_seconds
+            || seconds > self.max_seconds
+            || !(seconds - self.min_seconds).is_multiple_of(step)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The wake up interval needs {}-{} seconds in steps of {}",
+                    self.min_seconds, self.max_seconds, step
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A node woke up and listens now.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WakeUpNotification {
+    pub node: u8,
+}
+
+/// Wake Up Command Class
+#[derive(Debug, Clone)]
+pub struct WakeUp;
+
+impl WakeUp {
+    /// The Wake Up Interval Set Command is used to set the interval of the
+    /// node and the node, which gets the notifications.
+    pub fn interval_set<N>(node_id: N, seconds: u32, target: u8) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |          Command Class = COMMAND_CLASS_WAKE_UP(0x84)          |
+        // |             Command = WAKE_UP_INTERVAL_SET(0x04)              |
+        // |                        Seconds 1 (MSB)                        |
+        // |                           Seconds 2                           |
+        // |                        Seconds 3 (LSB)                        |
+        // |                            NodeID                             |
+        // -----------------------------------------------------------------
+        if seconds > MAX_WAKE_UP_INTERVAL {
+            return Err(Error::parse(
+                CommandClass::WAKE_UP,
+                "The interval is longer than 24 bits",
+            ));
+        }
+
+        let mut data = seconds.to_be_bytes()[1..].to_vec();
+        data.push(target);
+
+        Message::new(node_id.into(), CommandClass::WAKE_UP, 0x04, &data)
+    }
+
+    /// The Wake Up Interval Get Command is used to request the interval of
+    /// the node.
+    pub fn interval_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::WAKE_UP, 0x05, [])
+    }
+
+    /// The Wake Up Interval Report Command is used to advertise the interval
+    /// of the node.
+    pub fn interval_report(frame: &IncomingFrame) -> Result<WakeUpInterval, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::WAKE_UP, 0x06)?;
+
+        if payload.len() < 4 {
+            return Err(Error::parse(CommandClass::WAKE_UP, "Message is too short"));
+        }
+
+        Ok(WakeUpInterval {
+            seconds: seconds(&payload[0..3]),
+            target: payload[3],
+        })
+    }
+
+    /// The Wake Up Notification Command is sent by the node, when it woke up.
+    pub fn notification(frame: &IncomingFrame) -> Result<WakeUpNotification, Error> {
+        // check the CommandClass and command
+        frame.expect(CommandClass::WAKE_UP, 0x07)?;
+
+        Ok(WakeUpNotification { node: frame.source })
+    }
+
+    /// The Wake Up No More Information Command is used to tell the node,
+    /// that it can go back to sleep.
+    pub fn no_more_information<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::WAKE_UP, 0x08, [])
+    }
+
+    /// The Wake Up Interval Capabilities Get Command is used to request the
+    /// intervals, which the node supports.
+    pub fn capabilities_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::WAKE_UP, 0x09, [])
+    }
+
+    /// The Wake Up Interval Capabilities Report Command is used to advertise
+    /// the intervals, which the node supports.
+    pub fn capabilities_report(frame: &IncomingFrame) -> Result<WakeUpCapabilities, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |          Command Class = COMMAND_CLASS_WAKE_UP(0x84)          |
+        // |     Command = WAKE_UP_INTERVAL_CAPABILITIES_REPORT(0x0A)      |
+        // |          Minimum Wake Up Interval Seconds (3 bytes)           |
+        // |          Maximum Wake Up Interval Seconds (3 bytes)           |
+        // |          Default Wake Up Interval Seconds (3 bytes)           |
+        // |           Wake Up Interval Step Seconds (3 bytes)             |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::WAKE_UP, 0x0A)?;
+
+        if payload.len() < 12 {
+            return Err(Error::parse(CommandClass::WAKE_UP, "Message is too short"));
+        }
+
+        Ok(WakeUpCapabilities {
+            min_seconds: seconds(&payload[0..3]),
+            max_seconds: seconds(&payload[3..6]),
+            default_seconds: seconds(&payload[6..9]),
+            step_seconds: seconds(&payload[9..12]),
+        })
+    }
+}
+
+/// Read the seconds of a 24 bit value
+fn seconds(data: &[u8]) -> u32 {
+    u32::from_be_bytes([0x00, data[0], data[1], data[2]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval() {
+        let msg = WakeUp::interval_set(0x05, 3600, 0x01).unwrap();
+        assert_eq!(msg.data, vec![0x00, 0x0E, 0x10, 0x01]);
+        assert!(WakeUp::interval_set(0x05, 0x0100_0000, 0x01).is_err());
+
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x84, 0x06, 0x00, 0x0E, 0x10, 0x01]).unwrap();
+        assert_eq!(
+            WakeUp::interval_report(&frame),
+            Ok(WakeUpInterval {
+                seconds: 3600,
+                target: 0x01,
+            })
+        );
+    }
+
+    #[test]
+    fn notification() {
+        let frame = IncomingFrame::parse(&[0x00, 0x07, 0x02, 0x84, 0x07]).unwrap();
+        assert_eq!(
+            WakeUp::notification(&frame),
+            Ok(WakeUpNotification { node: 0x07 })
+        );
+
+        let frame = IncomingFrame::parse(&[0x00, 0x07, 0x02, 0x84, 0x08]).unwrap();
+        assert!(WakeUp::notification(&frame).is_err());
+    }
+
+    #[test]
+    fn capabilities() {
+        let frame = IncomingFrame::parse(&[
+            0x00, 0x05, 0x0E, 0x84, 0x0A, 0x00, 0x01, 0x2C, 0x01, 0x51, 0x80, 0x00, 0x0E, 0x10,
+            0x00, 0x00, 0x3C,
+        ])
+        .unwrap();
+        let capabilities = WakeUp::capabilities_report(&frame).unwrap();
+        assert_eq!(
+            capabilities,
+            WakeUpCapabilities {
+                min_seconds: 300,
+                max_seconds: 86400,
+                default_seconds: 3600,
+                step_seconds: 60,
+            }
+        );
+
+        assert!(capabilities.validate(3600).is_ok());
+        assert!(capabilities.validate(3630).is_err());
+        assert!(capabilities.validate(60).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! The Window Covering Command Class is used to control blinds, shutters and
+//! curtains, which have more than one movement, like the position and the
+//! tilt of the slats of a venetian blind.
+//!
+//! Each movement is a parameter with its own level from 0 (closed) to 99
+//! (open). The parameters without a position can only be moved with a level
+//! change.
+
+use crate::cmds::{decode_duration, encode_duration, CommandClass, IncomingFrame, Message};
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// The movements of a window covering
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum WindowCoveringParameter {
+    OutboundLeftNoPosition = 0x00,
+    OutboundLeft = 0x01,
+    OutboundRightNoPosition = 0x02,
+    OutboundRight = 0x03,
+    OutboundRightLeftNoPosition = 0x04,
+    OutboundRightLeft = 0x05,
+    InboundLeftNoPosition = 0x06,
+    InboundLeft = 0x07,
+    InboundRightNoPosition = 0x08,
+    InboundRight = 0x09,
+    InboundRightLeftNoPosition = 0x0A,
+    InboundRightLeft = 0x0B,
+    VerticalSlatsAngleNoPosition = 0x0C,
+    VerticalSlatsAngle = 0x0D,
+    OutboundBottomNoPosition = 0x0E,
+    OutboundBottom = 0x0F,
+    OutboundTopNoPosition = 0x10,
+    OutboundTop = 0x11,
+    InboundBottomNoPosition = 0x12,
+    InboundBottom = 0x13,
+    InboundTopNoPosition = 0x14,
+    InboundTop = 0x15,
+    InboundTopBottomNoPosition = 0x16,
+    InboundTopBottom = 0x17,
+    HorizontalSlatsAngleNoPosition = 0x18,
+    HorizontalSlatsAngle = 0x19,
+}
+
+impl WindowCoveringParameter {
+    /// Check if the parameter can be set to a level
+    pub fn has_position(self) -> bool {
+        self as u8 % 2 == 1
+    }
+}
+
+/// The level of a parameter and the level it moves to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowCoveringState {
+    pub parameter: WindowCoveringParameter,
+    pub current: u8,
+    pub target: u8,
+    /// The time until the target is reached, `None` when it's unknown
+    pub duration: Option<Duration>,
+}
+
+/// Window Covering Command Class
+#[derive(Debug, Clone)]
+pub struct WindowCovering;
+
+impl WindowCovering {
+    /// The Window Covering Supported Get Command is used to request the
+    /// parameters, which the node supports.
+    pub fn supported_get<N>(node_id: N) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(node_id.into(), CommandClass::WINDOW_COVERING, 0x01, [])
+    }
+
+    /// The Window Covering Supported Report Command is used to advertise the
+    /// parameters, which the node supports.
+    pub fn supported_report(frame: &IncomingFrame) -> Result<Vec<WindowCoveringParameter>, Error> {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_WINDOW_COVERING(0x6A)       |
+        // |        Command = WINDOW_COVERING_SUPPORTED_REPORT(0x02)       |
+        // |           Reserved            |   Number of Mask Bytes        |
+        // |              Parameter Mask 1 ... Parameter Mask n            |
+        // -----------------------------------------------------------------
+
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::WINDOW_COVERING, 0x02)?;
+
+        let len = payload
+            .first()
+            .map(|l| (l & 0x0F) as usize)
+            .ok_or_else(|| Error::parse(CommandClass::WINDOW_COVERING, "Message is too short"))?;
+        let masks = payload
+            .get(1..1 + len)
+            .ok_or_else(|| Error::parse(CommandClass::WINDOW_COVERING, "Message is too short"))?;
+
+        Ok((0..len * 8)
+            .filter(|bit| masks[bit / 8] & (1 << (bit % 8)) != 0)
+            .filter_map(|bit| WindowCoveringParameter::try_from(bit as u8).ok())
+            .collect())
+    }
+
+    /// The Window Covering Get Command is used to request the level of a
+    /// parameter.
+    pub fn get<N>(node_id: N, parameter: WindowCoveringParameter) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::WINDOW_COVERING,
+            0x03,
+            [parameter as u8],
+        )
+    }
+
+    /// The Window Covering Report Command is used to advertise the level of a
+    /// parameter.
+    pub fn report(frame: &IncomingFrame) -> Result<WindowCoveringState, Error> {
+        // check the CommandClass and command
+        let payload = frame.expect(CommandClass::WINDOW_COVERING, 0x04)?;
+
+        if payload.len() < 4 {
+            return Err(Error::parse(
+                CommandClass::WINDOW_COVERING,
+                "Message is too short",
+            ));
+        }
+
+        let parameter = WindowCoveringParameter::try_from(payload[0]).map_err(|_| {
+            Error::parse(
+                CommandClass::WINDOW_COVERING,
+                format!("Unknown parameter {:#04x}", payload[0]),
+            )
+        })?;
+
+        Ok(WindowCoveringState {
+            parameter,
+            current: payload[1],
+            target: payload[2],
+            duration: decode_duration(payload[3]),
+        })
+    }
+
+    /// The Window Covering Set Command is used to move one or more parameters
+    /// to a level between 0 and 99 at once, like the position and the tilt.
+    pub fn set<N>(
+        node_id: N,
+        levels: &[(WindowCoveringParameter, u8)],
+        duration: Option<Duration>,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_WINDOW_COVERING(0x6A)       |
+        // |              Command = WINDOW_COVERING_SET(0x05)              |
+        // |       Reserved        |          Parameter Count              |
+        // |                        Parameter ID 1                         |
+        // |                            Value 1                            |
+        // |                             ...                               |
+        // |                       Dimming Duration                        |
+        // -----------------------------------------------------------------
+        if levels.is_empty() || levels.len() > 0x1F {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The amount of parameters needs to be between 1-31",
+            ));
+        }
+
+        let mut data = vec![levels.len() as u8];
+        for &(parameter, value) in levels {
+            if !parameter.has_position() || value > 99 {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The level {} can't be set for {:?}", value, parameter),
+                ));
+            }
+            data.extend_from_slice(&[parameter as u8, value]);
+        }
+        data.push(encode_duration(duration)?);
+
+        Message::new(node_id.into(), CommandClass::WINDOW_COVERING, 0x05, &data)
+    }
+
+    /// The Window Covering Start Level Change Command is used to start
+    /// moving a parameter up or down, until it's stopped or the end is
+    /// reached.
+    pub fn start_level_change<N>(
+        node_id: N,
+        parameter: WindowCoveringParameter,
+        up: bool,
+        duration: Option<Duration>,
+    ) -> Result<Message, Error>
+    where
+        N: Into<u8>,
+    {
+        // _________________________________________________________________
+        // |   7   |   6   |   5   |   4   |   3   |   2   |   1   |   0   |
+        // |     Command Class = COMMAND_CLASS_WINDOW_COVERING(0x6A)       |
+        // |     Command = WINDOW_COVERING_START_LEVEL_CHANGE(0x06)        |
+        // |  Res  |Up/Down|                 Reserved                      |
+        // |                         Parameter ID                          |
+        // |                           Duration                            |
+        // -----------------------------------------------------------------
+        Ok(Message::from_array(
+            node_id.into(),
+            CommandClass::WINDOW_COVERING,
+            0x06,
+            [
+                if up { 0x00 } else { 0x40 },
+                parameter as u8,
+                encode_duration(duration)?,
+            ],
+        ))
+    }
+
+    /// The Window Covering Stop Level Change Command is used to stop the
+    /// movement of a parameter.
+    pub fn stop_level_change<N>(node_id: N, parameter: WindowCoveringParameter) -> Message
+    where
+        N: Into<u8>,
+    {
+        Message::from_array(
+            node_id.into(),
+            CommandClass::WINDOW_COVERING,
+            0x07,
+            [parameter as u8],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        // open the blind and tilt the slats
+        let msg = WindowCovering::set(
+            0x05,
+            &[
+                (WindowCoveringParameter::OutboundBottom, 99),
+                (WindowCoveringParameter::HorizontalSlatsAngle, 50),
+            ],
+            None,
+        )
+        .unwrap();
+        assert_eq!(msg.data, vec![0x02, 0x0F, 0x63, 0x19, 0x32, 0xFF]);
+
+        assert!(WindowCovering::set(0x05, &[], None).is_err());
+        assert!(WindowCovering::set(
+            0x05,
+            &[(WindowCoveringParameter::OutboundBottomNoPosition, 99)],
+            None
+        )
+        .is_err());
+
+        let msg = WindowCovering::start_level_change(
+            0x05,
+            WindowCoveringParameter::HorizontalSlatsAngle,
+            false,
+            Some(Duration::from_secs(5)),
+        )
+        .unwrap();
+        assert_eq!(msg.data, vec![0x40, 0x19, 0x05]);
+    }
+
+    #[test]
+    fn report() {
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x06, 0x6A, 0x04, 0x0F, 0x10, 0x63, 0x05]).unwrap();
+        assert_eq!(
+            WindowCovering::report(&frame),
+            Ok(WindowCoveringState {
+                parameter: WindowCoveringParameter::OutboundBottom,
+                current: 0x10,
+                target: 0x63,
+                duration: Some(Duration::from_secs(5)),
+            })
+        );
+
+        // the position of the blind and the angle of the slats
+        let frame =
+            IncomingFrame::parse(&[0x00, 0x05, 0x07, 0x6A, 0x02, 0x04, 0x00, 0x80, 0x00, 0x02])
+                .unwrap();
+        assert_eq!(
+            WindowCovering::supported_report(&frame),
+            Ok(vec![
+                WindowCoveringParameter::OutboundBottom,
+                WindowCoveringParameter::HorizontalSlatsAngle,
+            ])
+        );
+    }
+}
@@ -1,30 +1,37 @@
 /// A SerialMessage which can be sent and received over a Driver
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerialMessage {
     pub header: SerialMessageHeader,
     pub typ: SerialMessageType,
     pub func: SerialMessageFunction,
-    pub data: Vec<u8>,
+    pub data: crate::payload::Payload,
 }
 
 impl SerialMessage {
-    /// create a new message
-    pub fn new(typ: SerialMessageType, func: SerialMessageFunction, data: Vec<u8>) -> Self {
-        SerialMessage {
+    /// create a new message, fails when the data is longer than `MAX_PAYLOAD`
+    pub fn new(
+        typ: SerialMessageType,
+        func: SerialMessageFunction,
+        data: &[u8],
+    ) -> Result<Self, crate::error::Error> {
+        use std::convert::TryFrom;
+
+        Ok(SerialMessage {
             header: SerialMessageHeader::SOF,
-            typ: typ,
-            func: func,
-            data: data,
-        }
+            typ,
+            func,
+            data: crate::payload::Payload::try_from(data)?,
+        })
     }
 
     // create a new message with only the header
     pub fn new_header(header: SerialMessageHeader) -> Self {
         SerialMessage {
-            header: header,
+            header,
             typ: SerialMessageType::Response,
             func: SerialMessageFunction::None,
-            data: vec![],
+            data: crate::payload::Payload::new(),
         }
     }
 
@@ -33,7 +40,7 @@ impl SerialMessage {
         use std::convert::TryFrom;
 
         // check if the data has a header
-        if data.len() < 1 {
+        if data.is_empty() {
             return Err(crate::error::Error::new(
                 crate::error::ErrorKind::UnknownZWave,
                 "No message delivered, at least a head is needed",
@@ -65,11 +72,12 @@ impl SerialMessage {
 
         
         // check if the checksum is right for the message
-        if SerialMessage::checksum(&data[0..(data.len() - 1)]) != data[data.len() - 1] {
-            return Err(crate::error::Error::new(
-                crate::error::ErrorKind::UnknownZWave,
-                "The checksum didn't match to the message",
-            ));
+        let expected = SerialMessage::checksum(&data[0..(data.len() - 1)]);
+        if expected != data[data.len() - 1] {
+            return Err(crate::error::Error::FrameCorrupt {
+                expected,
+                got: data[data.len() - 1],
+            });
         }
 
         
@@ -83,51 +91,109 @@ impl SerialMessage {
         ))?;
 
         // create the message data array
-        let msg_data: &[u8];
-        // when there is data extract it
-        if data.len() > 5 {
-            msg_data = &data[4..(data.len() - 1)];
-        }
-        // if not create a empty array
-        else {
-            msg_data = &[0; 0];
-        }
+        let msg_data: &[u8] = if data.len() > 5 {
+            // when there is data extract it
+            &data[4..(data.len() - 1)]
+        } else {
+            // if not create a empty array
+            &[0; 0]
+        };
 
         // create a new Message and return it
-        Ok(SerialMessage::new(typ, function, msg_data.to_vec()))
+        Ok(SerialMessage {
+            header,
+            typ,
+            func: function,
+            data: crate::payload::Payload::try_from(msg_data)?,
+        })
     }
 
     /// return the command as Vec<u8>
     pub fn get_command(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.data.len() + 5);
+        self.write_command(&mut buf);
+        buf
+    }
+
+    /// Write the command into the buffer, which is cleared first. A buffer
+    /// which is reused for every message only allocates once.
+    pub fn write_command(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+
         // only create a full command if the header defines it
         if self.header != SerialMessageHeader::SOF {
-            return vec![self.header as u8];
+            buf.push(self.header as u8);
+            return;
         }
 
         // create the header, length, typ and ZWave function
-        let mut buf: Vec<u8> = vec![
+        buf.extend_from_slice(&[
             self.header as u8,
             (self.data.len() + 3) as u8,
             self.typ as u8,
             self.func as u8,
-        ];
+        ]);
 
         // append the data
-        buf.append(&mut self.data.clone());
+        buf.extend_from_slice(&self.data);
 
         // calc checksum
-        let cs = SerialMessage::checksum(&buf);
+        let cs = SerialMessage::checksum(buf);
         buf.push(cs);
+    }
 
-        buf
+    /// Parse a message out of a hex string, like `01 03 00 15 E9` from a log
+    pub fn from_hex(text: &str) -> Result<SerialMessage, crate::error::Error> {
+        SerialMessage::parse(&SerialMessage::parse_hex(text)?)
+    }
+
+    /// Convert a hex string into the bytes.
+    ///
+    /// The bytes can be separated by spaces, commas or colons and may have a
+    /// `0x` prefix, which also accepts the output of `to_hex`. Bytes without a
+    /// separator are read in pairs, like `0103001`.
+    pub fn parse_hex(text: &str) -> Result<Vec<u8>, crate::error::Error> {
+        let invalid = |token: &str| {
+            crate::error::Error::new(
+                crate::error::ErrorKind::InvalidInput,
+                format!("'{}' is not a hex byte", token),
+            )
+        };
+
+        let mut data = vec![];
+        for token in text.split(|c: char| c.is_whitespace() || c == ',' || c == ':') {
+            if token.is_empty() {
+                continue;
+            }
+
+            // a prefixed byte may have a single digit
+            let digits = token.trim_start_matches("0x").trim_start_matches("0X");
+            if digits.len() != token.len() || digits.len() == 1 {
+                if digits.len() > 2 {
+                    return Err(invalid(token));
+                }
+                data.push(u8::from_str_radix(digits, 16).map_err(|_| invalid(token))?);
+                continue;
+            }
+
+            if digits.len() % 2 != 0 {
+                return Err(invalid(token));
+            }
+            for i in (0..digits.len()).step_by(2) {
+                let pair = digits.get(i..i + 2).ok_or_else(|| invalid(token))?;
+                data.push(u8::from_str_radix(pair, 16).map_err(|_| invalid(token))?);
+            }
+        }
+
+        Ok(data)
     }
 
     /// Return a Vec<u8> into a String in a hex format.
-    pub fn to_hex(data: &Vec<u8>) -> String {
+    pub fn to_hex(data: &[u8]) -> String {
         let mut out = String::new();
 
-        for i in 0..data.len() {
-            out.push_str(&*format!("{:#X} ", data[i]));
+        for byte in data {
+            out.push_str(&format!("{:#X} ", byte));
         }
 
         out
@@ -137,8 +203,8 @@ impl SerialMessage {
     pub fn checksum(data: &[u8]) -> u8 {
         let mut ret: u8 = 0xFF;
 
-        for i in 1..data.len() {
-            ret ^= data[i];
+        for byte in data.iter().skip(1) {
+            ret ^= byte;
         }
 
         ret
@@ -147,6 +213,7 @@ impl SerialMessage {
 
 /// List of the ZWave start header
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum SerialMessageHeader {
     SOF = 0x01, // Start of Frame
@@ -174,6 +241,7 @@ impl std::convert::TryFrom<u8> for SerialMessageHeader {
 
 /// List of different ZWave command types (rx/tx)
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum SerialMessageType {
     Request = 0x00,
@@ -206,8 +274,448 @@ pub enum SerialTransmissionType {
     Direct = 0x25,
 }
 
+/// The options a message is sent to a node with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransmitOptions {
+    /// The node needs to acknowledge the message
+    pub ack: bool,
+    /// Send with a reduced power, for nodes right next to the controller
+    pub low_power: bool,
+    /// Try the known routes, when the direct one fails
+    pub auto_route: bool,
+    /// Only send the message directly, without any repeater
+    pub no_route: bool,
+    /// Search a new route with explorer frames, when the known ones fail
+    pub explore: bool,
+}
+
+impl TransmitOptions {
+    /// Return the options as the bitmask of the `SendData` function
+    pub fn bits(&self) -> u8 {
+        let mut bits = 0x00;
+        if self.ack {
+            bits |= SerialTransmissionType::ACK as u8;
+        }
+        if self.low_power {
+            bits |= SerialTransmissionType::LowPower as u8;
+        }
+        if self.auto_route {
+            bits |= SerialTransmissionType::AutoRoute as u8;
+        }
+        if self.no_route {
+            bits |= 0x10;
+        }
+        if self.explore {
+            bits |= SerialTransmissionType::Explore as u8;
+        }
+        bits
+    }
+}
+
+impl Default for TransmitOptions {
+    /// Send the message over the known routes
+    fn default() -> TransmitOptions {
+        TransmitOptions {
+            ack: false,
+            low_power: false,
+            auto_route: true,
+            no_route: false,
+            explore: false,
+        }
+    }
+}
+
+/// List of the transmit status of a sent message
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum TransmitStatus {
+    CompleteOk = 0x00,
+    CompleteNoAck = 0x01,
+    CompleteFail = 0x02,
+    RoutingNotIdle = 0x03,
+    CompleteNoRoute = 0x04,
+}
+
+/// The transmit report of a sent message, as delivered by the
+/// callback of the `SendData` function.
+///
+/// Newer controllers add the transmit time and the used route
+/// to the report.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransmitReport {
+    pub message_id: u8,
+    pub status: TransmitStatus,
+    pub time: Option<std::time::Duration>,
+    pub repeaters: Vec<u8>,
+    pub speed: Option<u32>,
+}
+
+impl TransmitReport {
+    /// Parse the data of a `SendData` callback
+    ///
+    /// `message-id, status, [time, time, repeater-count, rssi, ..., repeaters, speed]`
+    pub fn parse(data: &[u8]) -> Result<TransmitReport, crate::error::Error> {
+        use std::convert::TryFrom;
+
+        // the message id and the status are always delivered
+        if data.len() < 2 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The transmit report is too short",
+            ));
+        }
+
+        let status = TransmitStatus::try_from(data[1]).map_err(|_| {
+            crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "Unknown transmit status detected",
+            )
+        })?;
+
+        // the transmit time is counted in 10ms ticks
+        let time = if data.len() >= 4 {
+            let ticks = ((data[2] as u64) << 8) | data[3] as u64;
+            Some(std::time::Duration::from_millis(ticks * 10))
+        } else {
+            None
+        };
+
+        // the route is only part of the extended report
+        let (repeaters, speed) = if data.len() >= 18 {
+            let count = std::cmp::min(data[4] as usize, 4);
+            (data[13..13 + count].to_vec(), Route::speed(data[17]))
+        } else {
+            (vec![], None)
+        };
+
+        Ok(TransmitReport {
+            message_id: data[0],
+            status,
+            time,
+            repeaters,
+            speed,
+        })
+    }
+
+    /// Return the route of the message, when the report contains it
+    pub fn route(&self) -> Option<Route> {
+        self.speed.map(|speed| Route {
+            repeaters: self.repeaters.clone(),
+            speed,
+        })
+    }
+}
+
+/// The route to a node, over up to four repeaters.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Route {
+    /// The repeaters in the order they forward the message, empty for a direct route
+    pub repeaters: Vec<u8>,
+    /// The speed in bit/s
+    pub speed: u32,
+}
+
+impl Route {
+    /// Parse the response of the `GetLastWorkingRoute` function, which is
+    /// empty when the controller doesn't know a route to the node
+    ///
+    /// `node-id, repeater, repeater, repeater, repeater, speed`
+    pub fn parse(data: &[u8]) -> Result<Option<Route>, crate::error::Error> {
+        if data.len() < 6 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The route is too short",
+            ));
+        }
+
+        let speed = match Route::speed(data[5]) {
+            Some(speed) => speed,
+            None => return Ok(None),
+        };
+
+        // unused repeater slots are zero
+        Ok(Some(Route {
+            repeaters: data[1..5].iter().cloned().take_while(|r| *r != 0).collect(),
+            speed,
+        }))
+    }
+
+    /// Convert the speed code of the controller into bit/s
+    fn speed(code: u8) -> Option<u32> {
+        match code {
+            0x01 => Some(9_600),
+            0x02 => Some(40_000),
+            0x03 => Some(100_000),
+            _ => None,
+        }
+    }
+}
+
+/// Activity of a node, recorded by the driver to track if the node is reachable.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeActivity {
+    /// A message to the node was transmitted with the given status
+    Transmit(u8, TransmitStatus),
+    /// A message from the node was received
+    Received(u8),
+    /// The node sent a wake up notification
+    WakeUp(u8),
+}
+
+/// The receive status of an application command
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RxStatus {
+    pub routed_busy: bool,
+    pub low_power: bool,
+    pub broadcast: bool,
+    pub multicast: bool,
+    pub explore: bool,
+}
+
+impl RxStatus {
+    /// Parse the rx status flags of `ApplicationCommandHandler`
+    pub fn parse(flags: u8) -> RxStatus {
+        RxStatus {
+            routed_busy: flags & 0x01 != 0,
+            low_power: flags & 0x02 != 0,
+            broadcast: flags & 0x0C == 0x04,
+            multicast: flags & 0x0C == 0x08,
+            explore: flags & 0x10 != 0,
+        }
+    }
+
+    /// Return the rx status flags, the opposite of `parse`
+    pub fn bits(&self) -> u8 {
+        let mut flags = 0x00;
+        if self.routed_busy {
+            flags |= 0x01;
+        }
+        if self.low_power {
+            flags |= 0x02;
+        }
+        if self.broadcast {
+            flags |= 0x04;
+        }
+        if self.multicast {
+            flags |= 0x08;
+        }
+        if self.explore {
+            flags |= 0x10;
+        }
+        flags
+    }
+}
+
+/// An application command which was received from a node
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IncomingFrame {
+    pub status: RxStatus,
+    pub source: u8,
+    /// The command class, or its byte when it's unknown
+    pub command_class: Result<CommandClass, u8>,
+    pub command: u8,
+    pub payload: Vec<u8>,
+}
+
+impl IncomingFrame {
+    /// Parse the data of `ApplicationCommandHandler`
+    ///
+    /// `rx status, source node, length, command class, command, payload`
+    pub fn parse(data: &[u8]) -> Result<IncomingFrame, crate::error::Error> {
+        use std::convert::TryFrom;
+
+        if data.len() < 4 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The application command is too short",
+            ));
+        }
+
+        // the unknown command classes are kept, so they can be reported
+        let command_class = CommandClass::try_from(data[3]).map_err(|_| data[3]);
+
+        // newer controllers append the signal strength after the command
+        let end = std::cmp::min(data.len(), 3 + data[2] as usize);
+
+        Ok(IncomingFrame {
+            status: RxStatus::parse(data[0]),
+            source: data[1],
+            command_class,
+            command: data.get(4).cloned().unwrap_or(0),
+            payload: data.get(5..end).unwrap_or(&[]).to_vec(),
+        })
+    }
+
+    /// Parse the data of `PromiscuousApplicationCommandHandler`, which also contains
+    /// the frames between other nodes. Returns the frame and its destination node.
+    ///
+    /// `rx status, source node, length, command class, command, payload, destination node`
+    pub fn parse_promiscuous(data: &[u8]) -> Result<(IncomingFrame, u8), crate::error::Error> {
+        let frame = IncomingFrame::parse(data)?;
+
+        // the destination follows the command
+        let destination = data.get(3 + data[2] as usize).cloned().ok_or_else(|| {
+            crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The application command has no destination",
+            )
+        })?;
+
+        Ok((frame, destination))
+    }
+
+    /// Return the payload, when the frame contains the command of the command class
+    pub fn expect(
+        &self,
+        command_class: CommandClass,
+        command: u8,
+    ) -> Result<&[u8], crate::error::Error> {
+        if self.command_class != Ok(command_class) || self.command != command {
+            return Err(crate::error::Error::parse(
+                command_class,
+                "Answer contained wrong command class",
+            ));
+        }
+
+        Ok(&self.payload)
+    }
+
+    /// Return the byte of the command class, also of an unknown one
+    pub fn command_class_id(&self) -> u8 {
+        match self.command_class {
+            Ok(command_class) => command_class as u8,
+            Err(id) => id,
+        }
+    }
+
+    /// Return the data of `ApplicationCommandHandler`, the opposite of `parse`
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data = vec![
+            self.status.bits(),
+            self.source,
+            (self.payload.len() + 2) as u8,
+            self.command_class_id(),
+            self.command,
+        ];
+        data.extend_from_slice(&self.payload);
+        data
+    }
+}
+
+/// List of the Z-Wave library types of a controller
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum LibraryType {
+    Unknown = 0x00,
+    StaticController = 0x01,
+    Controller = 0x02,
+    EnhancedSlave = 0x03,
+    Slave = 0x04,
+    Installer = 0x05,
+    RoutingSlave = 0x06,
+    BridgeController = 0x07,
+    DeviceUnderTest = 0x08,
+    AvRemote = 0x0A,
+    AvDevice = 0x0B,
+}
+
+/// The role of the controller in the network
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControllerCapabilities {
+    pub secondary: bool,
+    pub other_network: bool,
+    pub sis_present: bool,
+    pub real_primary: bool,
+    pub suc: bool,
+}
+
+impl ControllerCapabilities {
+    /// Parse the flags of `GetControllerCapabilities`
+    pub fn parse(flags: u8) -> ControllerCapabilities {
+        ControllerCapabilities {
+            secondary: flags & 0x01 != 0,
+            other_network: flags & 0x02 != 0,
+            sis_present: flags & 0x04 != 0,
+            real_primary: flags & 0x08 != 0,
+            suc: flags & 0x10 != 0,
+        }
+    }
+}
+
+/// The application of the controller and the Serial API functions it supports
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerialApiCapabilities {
+    pub app_version: u8,
+    pub app_revision: u8,
+    pub manufacturer_id: u16,
+    pub product_type: u16,
+    pub product_id: u16,
+    pub functions: Vec<u8>,
+}
+
+impl SerialApiCapabilities {
+    /// Parse the data of `SerialGetCapabilities`
+    ///
+    /// `version, revision, manufacturer, manufacturer, type, type, id, id, function bitmask`
+    pub fn parse(data: &[u8]) -> Result<SerialApiCapabilities, crate::error::Error> {
+        if data.len() < 8 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The Serial API capabilities are too short",
+            ));
+        }
+
+        // each bit of the bitmask is a supported function
+        let mut functions = vec![];
+        for (i, byte) in data[8..].iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (1 << bit) != 0 {
+                    functions.push((i * 8 + bit + 1) as u8);
+                }
+            }
+        }
+
+        Ok(SerialApiCapabilities {
+            app_version: data[0],
+            app_revision: data[1],
+            manufacturer_id: ((data[2] as u16) << 8) | data[3] as u16,
+            product_type: ((data[4] as u16) << 8) | data[5] as u16,
+            product_id: ((data[6] as u16) << 8) | data[7] as u16,
+            functions,
+        })
+    }
+
+    /// Check if the controller supports the function
+    pub fn supports(&self, function: SerialMessageFunction) -> bool {
+        self.functions.contains(&(function as u8))
+    }
+}
+
+/// The information about the controller, which is gathered at the start
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControllerInfo {
+    pub home_id: u32,
+    pub node_id: u8,
+    pub version: String,
+    pub library: LibraryType,
+    pub api: SerialApiCapabilities,
+    pub capabilities: ControllerCapabilities,
+}
+
 /// List of all available ZWave functions
 #[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum SerialMessageFunction {
     None = 0x00,
@@ -232,7 +740,9 @@ pub enum SerialMessageFunction {
     MemoryPutByte = 0x22,
     MemoryGetBuffer = 0x23, // todo recheck
     MemoryPutBuffer = 0x24,
+    EnterBootloader = 0x27,
     // ReadMemory = 0x23, todo recheck
+    NvmBackupRestore = 0x2e,
     ClockSet = 0x30,
     ClockGet = 0x31,
     ClockCompare = 0x32,
@@ -276,6 +786,8 @@ pub enum SerialMessageFunction {
     StoreHomeId = 0x84,
     LockRouteResponse = 0x90,
     SendDataRouteDemo = 0x91,
+    GetLastWorkingRoute = 0x92,
+    SetLastWorkingRoute = 0x93,
     SerialApiTest = 0x95,
     SerialApiSlaveNodeInfo = 0xa0,
     ApplicationSlaveCommandHandler = 0xa1,
@@ -285,10 +797,12 @@ pub enum SerialMessageFunction {
     GetVirtualNodes = 0xa5,
     IsVirtualNode = 0xa6,
     SetPromiscuousMode = 0xd0,
+    PromiscuousApplicationCommandHandler = 0xd1,
 }
 
 /// List of the ZWave Command Classes
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(non_camel_case_types)]
 #[repr(u8)]
 pub enum CommandClass {
@@ -360,9 +874,19 @@ pub enum CommandClass {
     ZIP_6LOWPAN = 0x4F,
     BASIC_WINDOW_COVERING = 0x50,
     MTP_WINDOW_COVERING = 0x51,
+    TRANSPORT_SERVICE = 0x55,
+    CRC_16_ENCAP = 0x56,
+    ASSOCIATION_GRP_INFO = 0x59,
+    CENTRAL_SCENE = 0x5B,
+    ANTITHEFT = 0x5D,
+    ZWAVEPLUS_INFO = 0x5E,
     MULTI_INSTANCE = 0x60,
     DOOR_LOCK = 0x62,
     USER_CODE = 0x63,
+    HUMIDITY_CONTROL_SETPOINT = 0x64,
+    WINDOW_COVERING = 0x6A,
+    SUPERVISION = 0x6C,
+    ENTRY_CONTROL = 0x6F,
     CONFIGURATION = 0x70,
     ALARM = 0x71,
     MANUFACTURER_SPECIFIC = 0x72,
@@ -370,6 +894,7 @@ pub enum CommandClass {
     PROTECTION = 0x75,
     LOCK = 0x76,
     NODE_NAMING = 0x77,
+    SOUND_SWITCH = 0x79,
     FIRMWARE_UPDATE_MD = 0x7A,
     GROUPING_NAME = 0x7B,
     REMOTE_ASSOCIATION_ACTIVATE = 0x7C,
@@ -404,12 +929,14 @@ pub enum CommandClass {
     SENSOR_ALARM = 0x9C,
     SILENCE_ALARM = 0x9D,
     SENSOR_CONFIGURATION = 0x9E,
+    SECURITY_2 = 0x9F,
     MARK = 0xEF,
     NON_INTEROPERABLE = 0xF0,
 }
 
 /// List of the generic node types
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum GenericType {
     Unknown = 0x00,
@@ -442,36 +969,1099 @@ pub enum GenericType {
     NonInteroperable = 0xFF,
 }
 
-#[derive(Debug)]
+/// List of the specific node types
+///
+/// The specific type refines the generic type of a node. The same specific
+/// byte has a different meaning for each generic type, that's why it can
+/// only be decoded together with the generic type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpecificType {
+    NotUsed,
+    Unknown,
+    // RemoteController
+    PortableRemoteController,
+    PortableSceneController,
+    PortableInstallerTool,
+    RemoteControlAv,
+    RemoteControlSimple,
+    // StaticController
+    PcController,
+    SceneController,
+    StaticInstallerTool,
+    SetTopBox,
+    SubSystemController,
+    Tv,
+    Gateway,
+    // AvControlPoint
+    Doorbell,
+    SatelliteReceiver,
+    SatelliteReceiverV2,
+    // Display
+    SimpleDisplay,
+    // GarageDoor
+    SimpleGarageDoor,
+    // WindowCovering
+    SimpleWindowCovering,
+    // Thermostat
+    ThermostatHeating,
+    ThermostatGeneral,
+    SetbackScheduleThermostat,
+    SetpointThermostat,
+    SetbackThermostat,
+    ThermostatGeneralV2,
+    // RepeaterSlave
+    RepeaterSlave,
+    VirtualNode,
+    // BinarySwitch
+    PowerSwitchBinary,
+    ColorTunableBinary,
+    SceneSwitchBinary,
+    PowerStrip,
+    Siren,
+    ValveOpenClose,
+    IrrigationController,
+    // MultiLevelSwitch
+    PowerSwitchMultilevel,
+    ColorTunableMultilevel,
+    MotorMultiposition,
+    SceneSwitchMultilevel,
+    MotorControlClassA,
+    MotorControlClassB,
+    MotorControlClassC,
+    FanSwitch,
+    // RemoteSwitch
+    SwitchRemoteBinary,
+    SwitchRemoteMultilevel,
+    SwitchRemoteToggleBinary,
+    SwitchRemoteToggleMultilevel,
+    // ToggleSwitch
+    SwitchToggleBinary,
+    SwitchToggleMultilevel,
+    // ZIpGateway
+    ZIpTunnelingGateway,
+    ZIpAdvancedGateway,
+    // ZIpNode
+    ZIpTunnelingNode,
+    ZIpAdvancedNode,
+    // Ventilation
+    ResidentialHeatRecoveryVentilation,
+    // GenericSecurityPanel
+    ZonedSecurityPanel,
+    // RemoteSwitch2
+    BasicWallController,
+    // BinarySensor
+    RoutingSensorBinary,
+    // MultilevelSensor
+    RoutingSensorMultilevel,
+    ChimneyFan,
+    // Meter
+    SimpleMeter,
+    AdvancedEnergyControl,
+    WholeHomeMeterSimple,
+    // EntryControl
+    DoorLock,
+    AdvancedDoorLock,
+    SecureKeypadDoorLock,
+    SecureKeypadDoorLockDeadbolt,
+    SecureDoor,
+    SecureGate,
+    SecureBarrierAddOn,
+    SecureBarrierOpenOnly,
+    SecureBarrierCloseOnly,
+    SecureLockbox,
+    SecureKeypad,
+    // SemiInteroperable
+    EnergyProduction,
+    // AlarmSensor
+    BasicRoutingAlarmSensor,
+    RoutingAlarmSensor,
+    BasicZensorNetAlarmSensor,
+    ZensorNetAlarmSensor,
+    AdvancedZensorNetAlarmSensor,
+    BasicRoutingSmokeSensor,
+    RoutingSmokeSensor,
+    BasicZensorNetSmokeSensor,
+    ZensorNetSmokeSensor,
+    AdvancedZensorNetSmokeSensor,
+    AlarmSensor,
+}
+
+impl SpecificType {
+    /// Decode the specific type byte for the given generic type
+    pub fn new(generic: GenericType, specific: u8) -> Self {
+        // the specific type 0x00 is never used
+        if specific == 0x00 {
+            return SpecificType::NotUsed;
+        }
+
+        match (generic, specific) {
+            (GenericType::RemoteController, 0x01) => SpecificType::PortableRemoteController,
+            (GenericType::RemoteController, 0x02) => SpecificType::PortableSceneController,
+            (GenericType::RemoteController, 0x03) => SpecificType::PortableInstallerTool,
+            (GenericType::RemoteController, 0x04) => SpecificType::RemoteControlAv,
+            (GenericType::RemoteController, 0x06) => SpecificType::RemoteControlSimple,
+            (GenericType::StaticController, 0x01) => SpecificType::PcController,
+            (GenericType::StaticController, 0x02) => SpecificType::SceneController,
+            (GenericType::StaticController, 0x03) => SpecificType::StaticInstallerTool,
+            (GenericType::StaticController, 0x04) => SpecificType::SetTopBox,
+            (GenericType::StaticController, 0x05) => SpecificType::SubSystemController,
+            (GenericType::StaticController, 0x06) => SpecificType::Tv,
+            (GenericType::StaticController, 0x07) => SpecificType::Gateway,
+            (GenericType::AvControlPoint, 0x04) => SpecificType::Doorbell,
+            (GenericType::AvControlPoint, 0x05) => SpecificType::SatelliteReceiver,
+            (GenericType::AvControlPoint, 0x11) => SpecificType::SatelliteReceiverV2,
+            (GenericType::Display, 0x01) => SpecificType::SimpleDisplay,
+            (GenericType::GarageDoor, 0x01) => SpecificType::SimpleGarageDoor,
+            (GenericType::WindowCovering, 0x01) => SpecificType::SimpleWindowCovering,
+            (GenericType::Thermostat, 0x01) => SpecificType::ThermostatHeating,
+            (GenericType::Thermostat, 0x02) => SpecificType::ThermostatGeneral,
+            (GenericType::Thermostat, 0x03) => SpecificType::SetbackScheduleThermostat,
+            (GenericType::Thermostat, 0x04) => SpecificType::SetpointThermostat,
+            (GenericType::Thermostat, 0x05) => SpecificType::SetbackThermostat,
+            (GenericType::Thermostat, 0x06) => SpecificType::ThermostatGeneralV2,
+            (GenericType::RepeaterSlave, 0x01) => SpecificType::RepeaterSlave,
+            (GenericType::RepeaterSlave, 0x02) => SpecificType::VirtualNode,
+            (GenericType::BinarySwitch, 0x01) => SpecificType::PowerSwitchBinary,
+            (GenericType::BinarySwitch, 0x02) => SpecificType::ColorTunableBinary,
+            (GenericType::BinarySwitch, 0x03) => SpecificType::SceneSwitchBinary,
+            (GenericType::BinarySwitch, 0x04) => SpecificType::PowerStrip,
+            (GenericType::BinarySwitch, 0x05) => SpecificType::Siren,
+            (GenericType::BinarySwitch, 0x06) => SpecificType::ValveOpenClose,
+            (GenericType::BinarySwitch, 0x07) => SpecificType::IrrigationController,
+            (GenericType::MultiLevelSwitch, 0x01) => SpecificType::PowerSwitchMultilevel,
+            (GenericType::MultiLevelSwitch, 0x02) => SpecificType::ColorTunableMultilevel,
+            (GenericType::MultiLevelSwitch, 0x03) => SpecificType::MotorMultiposition,
+            (GenericType::MultiLevelSwitch, 0x04) => SpecificType::SceneSwitchMultilevel,
+            (GenericType::MultiLevelSwitch, 0x05) => SpecificType::MotorControlClassA,
+            (GenericType::MultiLevelSwitch, 0x06) => SpecificType::MotorControlClassB,
+            (GenericType::MultiLevelSwitch, 0x07) => SpecificType::MotorControlClassC,
+            (GenericType::MultiLevelSwitch, 0x08) => SpecificType::FanSwitch,
+            (GenericType::RemoteSwitch, 0x01) => SpecificType::SwitchRemoteBinary,
+            (GenericType::RemoteSwitch, 0x02) => SpecificType::SwitchRemoteMultilevel,
+            (GenericType::RemoteSwitch, 0x03) => SpecificType::SwitchRemoteToggleBinary,
+            (GenericType::RemoteSwitch, 0x04) => SpecificType::SwitchRemoteToggleMultilevel,
+            (GenericType::ToggleSwitch, 0x01) => SpecificType::SwitchToggleBinary,
+            (GenericType::ToggleSwitch, 0x02) => SpecificType::SwitchToggleMultilevel,
+            (GenericType::ZIpGateway, 0x01) => SpecificType::ZIpTunnelingGateway,
+            (GenericType::ZIpGateway, 0x02) => SpecificType::ZIpAdvancedGateway,
+            (GenericType::ZIpNode, 0x01) => SpecificType::ZIpTunnelingNode,
+            (GenericType::ZIpNode, 0x02) => SpecificType::ZIpAdvancedNode,
+            (GenericType::Ventilation, 0x01) => SpecificType::ResidentialHeatRecoveryVentilation,
+            (GenericType::GenericSecurityPanel, 0x01) => SpecificType::ZonedSecurityPanel,
+            (GenericType::RemoteSwitch2, 0x01) => SpecificType::BasicWallController,
+            (GenericType::BinarySensor, 0x01) => SpecificType::RoutingSensorBinary,
+            (GenericType::MultilevelSensor, 0x01) => SpecificType::RoutingSensorMultilevel,
+            (GenericType::MultilevelSensor, 0x02) => SpecificType::ChimneyFan,
+            (GenericType::Meter, 0x01) => SpecificType::SimpleMeter,
+            (GenericType::Meter, 0x02) => SpecificType::AdvancedEnergyControl,
+            (GenericType::Meter, 0x03) => SpecificType::WholeHomeMeterSimple,
+            (GenericType::EntryControl, 0x01) => SpecificType::DoorLock,
+            (GenericType::EntryControl, 0x02) => SpecificType::AdvancedDoorLock,
+            (GenericType::EntryControl, 0x03) => SpecificType::SecureKeypadDoorLock,
+            (GenericType::EntryControl, 0x04) => SpecificType::SecureKeypadDoorLockDeadbolt,
+            (GenericType::EntryControl, 0x05) => SpecificType::SecureDoor,
+            (GenericType::EntryControl, 0x06) => SpecificType::SecureGate,
+            (GenericType::EntryControl, 0x07) => SpecificType::SecureBarrierAddOn,
+            (GenericType::EntryControl, 0x08) => SpecificType::SecureBarrierOpenOnly,
+            (GenericType::EntryControl, 0x09) => SpecificType::SecureBarrierCloseOnly,
+            (GenericType::EntryControl, 0x0A) => SpecificType::SecureLockbox,
+            (GenericType::EntryControl, 0x0B) => SpecificType::SecureKeypad,
+            (GenericType::SemiInteroperable, 0x01) => SpecificType::EnergyProduction,
+            (GenericType::AlarmSensor, 0x01) => SpecificType::BasicRoutingAlarmSensor,
+            (GenericType::AlarmSensor, 0x02) => SpecificType::RoutingAlarmSensor,
+            (GenericType::AlarmSensor, 0x03) => SpecificType::BasicZensorNetAlarmSensor,
+            (GenericType::AlarmSensor, 0x04) => SpecificType::ZensorNetAlarmSensor,
+            (GenericType::AlarmSensor, 0x05) => SpecificType::AdvancedZensorNetAlarmSensor,
+            (GenericType::AlarmSensor, 0x06) => SpecificType::BasicRoutingSmokeSensor,
+            (GenericType::AlarmSensor, 0x07) => SpecificType::RoutingSmokeSensor,
+            (GenericType::AlarmSensor, 0x08) => SpecificType::BasicZensorNetSmokeSensor,
+            (GenericType::AlarmSensor, 0x09) => SpecificType::ZensorNetSmokeSensor,
+            (GenericType::AlarmSensor, 0x0A) => SpecificType::AdvancedZensorNetSmokeSensor,
+            (GenericType::AlarmSensor, 0x0B) => SpecificType::AlarmSensor,
+            _ => SpecificType::Unknown,
+        }
+    }
+}
+
+/// The wake up interval of a frequently listening routing slave (FLiRS)
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BeamWakeUp {
+    Sensor250ms,
+    Sensor1000ms,
+}
+
+/// The capabilities of a node, as delivered by the
+/// `GetNodeProtocolInfo` function of the Z-Wave controller.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeCapabilities {
+    pub listening: bool,
+    pub routing: bool,
+    pub max_baud_rate: u32,
+    pub protocol_version: u8,
+    pub optional_functionality: bool,
+    pub flirs: Option<BeamWakeUp>,
+    pub beaming: bool,
+    pub routing_slave: bool,
+    pub specific_device: bool,
+    pub controller: bool,
+    pub security: bool,
+}
+
+impl NodeCapabilities {
+    /// Parse the capability, security and reserved byte of the protocol info
+    pub fn parse(capability: u8, security: u8, reserved: u8) -> NodeCapabilities {
+        // the reserved byte signals the higher speeds of newer nodes
+        let max_baud_rate = if reserved & 0x02 != 0 {
+            200_000
+        } else if reserved & 0x01 != 0 {
+            100_000
+        } else if capability & 0x38 == 0x10 {
+            40_000
+        } else {
+            9_600
+        };
+
+        // a node can only have one of the beam wake up intervals
+        let flirs = if security & 0x40 != 0 {
+            Some(BeamWakeUp::Sensor1000ms)
+        } else if security & 0x20 != 0 {
+            Some(BeamWakeUp::Sensor250ms)
+        } else {
+            None
+        };
+
+        NodeCapabilities {
+            listening: capability & 0x80 != 0,
+            routing: capability & 0x40 != 0,
+            max_baud_rate,
+            protocol_version: (capability & 0x07) + 1,
+            optional_functionality: security & 0x80 != 0,
+            flirs,
+            beaming: security & 0x10 != 0,
+            routing_slave: security & 0x08 != 0,
+            specific_device: security & 0x04 != 0,
+            controller: security & 0x02 != 0,
+            security: security & 0x01 != 0,
+        }
+    }
+
+    /// Returns true when the node can't receive messages at any time, so
+    /// the messages need to wait until the node wakes up.
+    pub fn is_sleeping(&self) -> bool {
+        !self.listening && self.flirs.is_none()
+    }
+}
+
+/// The security class a node was included with
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SecurityClass {
+    None,
+    S0,
+    S2Unauthenticated,
+    S2Authenticated,
+    S2AccessControl,
+}
+
+impl SecurityClass {
+    /// Returns true when the commands need to be sent encrypted
+    pub fn is_secure(&self) -> bool {
+        *self != SecurityClass::None
+    }
+}
+
+/// The device information of a node, as delivered by the
+/// `GetNodeProtocolInfo` function of the Z-Wave controller.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeProtocolInfo {
+    pub capabilities: NodeCapabilities,
+    pub basic: u8,
+    pub generic: GenericType,
+    pub specific: SpecificType,
+}
+
+impl NodeProtocolInfo {
+    /// Parse the data of a `GetNodeProtocolInfo` response
+    ///
+    /// `capability, security, reserved, basic, generic, specific`
+    pub fn parse(data: &[u8]) -> Result<NodeProtocolInfo, crate::error::Error> {
+        use std::convert::TryFrom;
+
+        // check if the answer has the right length
+        if data.len() != 6 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The node protocol info has a wrong format",
+            ));
+        }
+
+        // unknown generic types are handled as unknown
+        let generic = GenericType::try_from(data[4]).unwrap_or(GenericType::Unknown);
+
+        Ok(NodeProtocolInfo {
+            capabilities: NodeCapabilities::parse(data[0], data[1], data[2]),
+            basic: data[3],
+            generic,
+            specific: SpecificType::new(generic, data[5]),
+        })
+    }
+}
+
+/// The steps of an inclusion, as reported by the callbacks of the
+/// `AddNodeToNetwork` function.
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum InclusionStatus {
+    /// The controller waits for a node to send its node information
+    LearnReady = 0x01,
+    NodeFound = 0x02,
+    AddingSlave = 0x03,
+    AddingController = 0x04,
+    /// The node is part of the network, the inclusion needs to be stopped
+    ProtocolDone = 0x05,
+    Done = 0x06,
+    Failed = 0x07,
+    /// Only the primary controller can include nodes
+    NotPrimary = 0x23,
+}
+
+/// The node information frame (NIF), which a node sends when it joins a
+/// network or when it's requested.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeInformation {
+    pub basic: u8,
+    pub generic: GenericType,
+    pub specific: SpecificType,
+    /// The supported command classes, the controlled ones are left out
+    pub cmds: Vec<CommandClass>,
+}
+
+impl NodeInformation {
+    /// Parse the node information
+    ///
+    /// `basic, generic, specific, command classes`
+    pub fn parse(data: &[u8]) -> Result<NodeInformation, crate::error::Error> {
+        use std::convert::TryFrom;
+
+        if data.len() < 3 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The node information is too short",
+            ));
+        }
+
+        // unknown generic types and command classes are skipped
+        let generic = GenericType::try_from(data[1]).unwrap_or(GenericType::Unknown);
+        let cmds = data[3..]
+            .iter()
+            .take_while(|c| **c != CommandClass::MARK as u8)
+            .filter_map(|c| CommandClass::try_from(*c).ok())
+            .collect();
+
+        Ok(NodeInformation {
+            basic: data[0],
+            generic,
+            specific: SpecificType::new(generic, data[2]),
+            cmds,
+        })
+    }
+}
+
+/// A callback of the `AddNodeToNetwork` function.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InclusionReport {
+    pub status: InclusionStatus,
+    /// The id of the new node, 0 until the node is added
+    pub node_id: u8,
+    /// The node information, which is part of the adding callbacks
+    pub info: Option<NodeInformation>,
+}
+
+impl InclusionReport {
+    /// Parse the data of an `AddNodeToNetwork` callback
+    ///
+    /// `callback-id, status, node, length, [basic, generic, specific, command classes]`
+    pub fn parse(data: &[u8]) -> Result<InclusionReport, crate::error::Error> {
+        use std::convert::TryFrom;
+
+        if data.len() < 2 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The inclusion callback is too short",
+            ));
+        }
+
+        let status = InclusionStatus::try_from(data[1]).map_err(|_| {
+            crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                format!("Unknown inclusion status {:#04x}", data[1]),
+            )
+        })?;
+
+        // the node information has a length in front of it
+        let info = match data.get(3) {
+            Some(&length) if length >= 3 && data.len() >= 4 + length as usize => {
+                Some(NodeInformation::parse(&data[4..4 + length as usize])?)
+            }
+            _ => None,
+        };
+
+        Ok(InclusionReport {
+            status,
+            node_id: data.get(2).cloned().unwrap_or(0),
+            info,
+        })
+    }
+}
+
+/// The steps of joining another network, as reported by the callbacks of
+/// the `SetLearnMode` function.
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum LearnModeStatus {
+    /// The controller waits to be included by the primary controller
+    Started = 0x01,
+    /// The controller is part of the new network
+    Done = 0x06,
+    Failed = 0x07,
+}
+
+/// A callback of the `SetLearnMode` function.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LearnModeReport {
+    pub status: LearnModeStatus,
+    /// The node id of the controller in the new network, 0 until it's done
+    pub node_id: u8,
+}
+
+impl LearnModeReport {
+    /// Parse the data of a `SetLearnMode` callback
+    ///
+    /// `callback-id, status, node, length, [node information]`
+    pub fn parse(data: &[u8]) -> Result<LearnModeReport, crate::error::Error> {
+        use std::convert::TryFrom;
+
+        if data.len() < 2 {
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The learn mode callback is too short",
+            ));
+        }
+
+        let status = LearnModeStatus::try_from(data[1]).map_err(|_| {
+            crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                format!("Unknown learn mode status {:#04x}", data[1]),
+            )
+        })?;
+
+        Ok(LearnModeReport {
+            status,
+            node_id: data.get(2).cloned().unwrap_or(0),
+        })
+    }
+}
+
+/// List of the different meter types
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum MeterKind {
+    Electric = 0x01,
+    Gas = 0x02,
+    Water = 0x03,
+    Heating = 0x04,
+    Cooling = 0x05,
+}
+
+/// List of the different rate types, if the value was consumed or produced
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum RateType {
+    Unspecified = 0x00,
+    Import = 0x01,
+    Export = 0x02,
+}
+
+/// List of the different meter units
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(non_camel_case_types)]
-pub enum MeterData {
-    Electric_kWh(f64),
-    Electric_kVAh(f64),
-    Electric_W(f64),
-    Electric_PulseCount(f64),
-    Gas_meter2(f64),
-    Gas_feet2(f64),
-    Gas_PulseCount(f64),
-    Water_meter2(f64),
-    Water_feet2(f64),
-    Water_Gallons(f64),
-    Water_PulseCount(f64),
+pub enum MeterUnit {
+    kWh,
+    kVAh,
+    W,
+    PulseCount,
+    V,
+    A,
+    PowerFactor,
+    kVar,
+    kVarh,
+    CubicMeters,
+    CubicFeet,
+    USGallons,
+}
+
+impl MeterUnit {
+    /// Return the unit of the scale for the meter type.
+    ///
+    /// Scales from 7 on are the extended scales, which are
+    /// transmitted as `7 + scale 2`.
+    pub fn from_scale(kind: MeterKind, scale: u8) -> Option<MeterUnit> {
+        match (kind, scale) {
+            (MeterKind::Electric, 0x00) => Some(MeterUnit::kWh),
+            (MeterKind::Electric, 0x01) => Some(MeterUnit::kVAh),
+            (MeterKind::Electric, 0x02) => Some(MeterUnit::W),
+            (MeterKind::Electric, 0x03) => Some(MeterUnit::PulseCount),
+            (MeterKind::Electric, 0x04) => Some(MeterUnit::V),
+            (MeterKind::Electric, 0x05) => Some(MeterUnit::A),
+            (MeterKind::Electric, 0x06) => Some(MeterUnit::PowerFactor),
+            (MeterKind::Electric, 0x07) => Some(MeterUnit::kVar),
+            (MeterKind::Electric, 0x08) => Some(MeterUnit::kVarh),
+            (MeterKind::Gas, 0x00) | (MeterKind::Water, 0x00) => Some(MeterUnit::CubicMeters),
+            (MeterKind::Gas, 0x01) | (MeterKind::Water, 0x01) => Some(MeterUnit::CubicFeet),
+            (MeterKind::Water, 0x02) => Some(MeterUnit::USGallons),
+            (MeterKind::Gas, 0x03) | (MeterKind::Water, 0x03) => Some(MeterUnit::PulseCount),
+            (MeterKind::Heating, 0x00) | (MeterKind::Cooling, 0x00) => Some(MeterUnit::kWh),
+            _ => None,
+        }
+    }
+
+    /// Return the scale of the unit, extended scales are `7 + scale 2`
+    pub fn get_scale(&self) -> u8 {
+        match *self {
+            MeterUnit::kWh => 0x00,
+            MeterUnit::kVAh => 0x01,
+            MeterUnit::W => 0x02,
+            MeterUnit::PulseCount => 0x03,
+            MeterUnit::V => 0x04,
+            MeterUnit::A => 0x05,
+            MeterUnit::PowerFactor => 0x06,
+            MeterUnit::kVar => 0x07,
+            MeterUnit::kVarh => 0x08,
+            MeterUnit::CubicMeters => 0x00,
+            MeterUnit::CubicFeet => 0x01,
+            MeterUnit::USGallons => 0x02,
+        }
+    }
+
+    /// Return the symbol of the unit
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            MeterUnit::kWh => "kWh",
+            MeterUnit::kVAh => "kVAh",
+            MeterUnit::W => "W",
+            MeterUnit::PulseCount => "pulses",
+            MeterUnit::V => "V",
+            MeterUnit::A => "A",
+            MeterUnit::PowerFactor => "",
+            MeterUnit::kVar => "kVar",
+            MeterUnit::kVarh => "kVarh",
+            MeterUnit::CubicMeters => "m³",
+            MeterUnit::CubicFeet => "ft³",
+            MeterUnit::USGallons => "gal",
+        }
+    }
+}
+
+/// A meter reading with its unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterData {
+    pub value: f64,
+    pub kind: MeterKind,
+    pub unit: MeterUnit,
+    pub rate_type: RateType,
 }
 
 impl MeterData {
+    /// Create a new meter reading without a rate type
+    pub fn new(value: f64, kind: MeterKind, unit: MeterUnit) -> MeterData {
+        MeterData {
+            value,
+            kind,
+            unit,
+            rate_type: RateType::Unspecified,
+        }
+    }
+
     pub fn get_scale(&self) -> u8 {
+        self.unit.get_scale()
+    }
+
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl std::fmt::Display for MeterData {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", self.value, self.unit.symbol())
+    }
+}
+
+/// List of the physical quantities of sensor values
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Quantity {
+    Temperature,
+    Illuminance,
+    /// A share of the range of the sensor, like a luminance in percent
+    Percentage,
+    Power,
+    Energy,
+    Volume,
+    Voltage,
+    Current,
+    Humidity,
+}
+
+/// List of the units of sensor values
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Unit {
+    Celsius,
+    Fahrenheit,
+    Lux,
+    Percent,
+    Watt,
+    Kilowatt,
+    WattHour,
+    KilowattHour,
+    CubicMeter,
+    CubicFeet,
+    USGallon,
+    Volt,
+    Ampere,
+    RelativeHumidity,
+    AbsoluteHumidity,
+}
+
+impl Unit {
+    /// Return the quantity which is measured in this unit
+    pub fn quantity(&self) -> Quantity {
+        match *self {
+            Unit::Celsius | Unit::Fahrenheit => Quantity::Temperature,
+            Unit::Lux => Quantity::Illuminance,
+            Unit::Percent => Quantity::Percentage,
+            Unit::Watt | Unit::Kilowatt => Quantity::Power,
+            Unit::WattHour | Unit::KilowattHour => Quantity::Energy,
+            Unit::CubicMeter | Unit::CubicFeet | Unit::USGallon => Quantity::Volume,
+            Unit::Volt => Quantity::Voltage,
+            Unit::Ampere => Quantity::Current,
+            Unit::RelativeHumidity | Unit::AbsoluteHumidity => Quantity::Humidity,
+        }
+    }
+
+    /// Return the symbol of the unit
+    pub fn symbol(&self) -> &'static str {
         match *self {
-            MeterData::Electric_kWh(_) => 0x00,
-            MeterData::Electric_kVAh(_) => 0x01,
-            MeterData::Electric_W(_) => 0x02,
-            MeterData::Electric_PulseCount(_) => 0x03,
-            MeterData::Gas_meter2(_) => 0x00,
-            MeterData::Gas_feet2(_) => 0x01,
-            MeterData::Gas_PulseCount(_) => 0x03,
-            MeterData::Water_meter2(_) => 0x00,
-            MeterData::Water_feet2(_) => 0x01,
-            MeterData::Water_Gallons(_) => 0x02,
-            MeterData::Water_PulseCount(_) => 0x03,
+            Unit::Celsius => "°C",
+            Unit::Fahrenheit => "°F",
+            Unit::Lux => "lx",
+            Unit::Percent => "%",
+            Unit::Watt => "W",
+            Unit::Kilowatt => "kW",
+            Unit::WattHour => "Wh",
+            Unit::KilowattHour => "kWh",
+            Unit::CubicMeter => "m³",
+            Unit::CubicFeet => "ft³",
+            Unit::USGallon => "gal",
+            Unit::Volt => "V",
+            Unit::Ampere => "A",
+            Unit::RelativeHumidity => "%",
+            Unit::AbsoluteHumidity => "g/m³",
+        }
+    }
+
+    /// Convert a value of this unit into the base unit of the quantity
+    fn base_value(self, value: f64) -> f64 {
+        match self {
+            Unit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+            Unit::Kilowatt | Unit::KilowattHour => value * 1000.0,
+            Unit::CubicFeet => value * 0.028_316_846_592,
+            Unit::USGallon => value * 0.003_785_411_784,
+            _ => value,
+        }
+    }
+
+    /// Convert a value of the base unit of the quantity into this unit
+    fn unit_value(self, value: f64) -> f64 {
+        match self {
+            Unit::Fahrenheit => value * 9.0 / 5.0 + 32.0,
+            Unit::Kilowatt | Unit::KilowattHour => value / 1000.0,
+            Unit::CubicFeet => value / 0.028_316_846_592,
+            Unit::USGallon => value / 0.003_785_411_784,
+            _ => value,
+        }
+    }
+}
+
+/// A measured value with its unit, shared by all value reporting command classes
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SensorValue {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl SensorValue {
+    pub fn new(value: f64, unit: Unit) -> SensorValue {
+        SensorValue { value, unit }
+    }
+
+    /// Return the quantity of the value
+    pub fn quantity(&self) -> Quantity {
+        self.unit.quantity()
+    }
+
+    /// Convert the value into another unit of the same quantity.
+    ///
+    /// Percent can't be converted into lux, because the percent depend
+    /// on the sensor. The same goes for the relative and the absolute
+    /// humidity, which depend on the temperature.
+    pub fn convert(&self, unit: Unit) -> Option<SensorValue> {
+        if self.unit == unit {
+            return Some(*self);
         }
+
+        if self.quantity() != unit.quantity() || self.quantity() == Quantity::Humidity {
+            return None;
+        }
+
+        Some(SensorValue {
+            value: unit.unit_value(self.unit.base_value(self.value)),
+            unit,
+        })
+    }
+}
+
+impl std::fmt::Display for SensorValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", self.value, self.unit.symbol())
+    }
+}
+
+impl MeterData {
+    /// Return the reading as a sensor value, when the unit is a physical unit
+    pub fn to_sensor_value(&self) -> Option<SensorValue> {
+        let unit = match self.unit {
+            MeterUnit::kWh => Unit::KilowattHour,
+            MeterUnit::W => Unit::Watt,
+            MeterUnit::V => Unit::Volt,
+            MeterUnit::A => Unit::Ampere,
+            MeterUnit::CubicMeters => Unit::CubicMeter,
+            MeterUnit::CubicFeet => Unit::CubicFeet,
+            MeterUnit::USGallons => Unit::USGallon,
+            _ => return None,
+        };
+
+        Some(SensorValue::new(self.value, unit))
+    }
+}
+
+impl From<MeterData> for MeterUnit {
+    fn from(data: MeterData) -> MeterUnit {
+        data.unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_specific_type() {
+        // the same specific byte differs for each generic type
+        assert_eq!(
+            SpecificType::new(GenericType::MultiLevelSwitch, 0x01),
+            SpecificType::PowerSwitchMultilevel
+        );
+        assert_eq!(
+            SpecificType::new(GenericType::MultiLevelSwitch, 0x05),
+            SpecificType::MotorControlClassA
+        );
+        assert_eq!(
+            SpecificType::new(GenericType::EntryControl, 0x01),
+            SpecificType::DoorLock
+        );
+        assert_eq!(
+            SpecificType::new(GenericType::BinarySwitch, 0x00),
+            SpecificType::NotUsed
+        );
+        assert_eq!(
+            SpecificType::new(GenericType::Display, 0x42),
+            SpecificType::Unknown
+        );
+    }
+
+    #[test]
+    fn test_parse_node_protocol_info() {
+        let info = NodeProtocolInfo::parse(&[0xD3, 0x9C, 0x01, 0x04, 0x11, 0x01]).unwrap();
+
+        assert_eq!(info.basic, 0x04);
+        assert_eq!(info.generic, GenericType::MultiLevelSwitch);
+        assert_eq!(info.specific, SpecificType::PowerSwitchMultilevel);
+
+        assert!(info.capabilities.listening);
+        assert!(info.capabilities.routing);
+        assert_eq!(info.capabilities.max_baud_rate, 100_000);
+        assert_eq!(info.capabilities.protocol_version, 4);
+        assert_eq!(info.capabilities.flirs, None);
+        assert!(info.capabilities.beaming);
+        assert!(!info.capabilities.security);
+        assert!(!info.capabilities.is_sleeping());
+
+        // a frequently listening node needs no wake up
+        let info = NodeProtocolInfo::parse(&[0x53, 0x5C, 0x00, 0x04, 0x40, 0x03]).unwrap();
+        assert!(!info.capabilities.listening);
+        assert_eq!(info.capabilities.flirs, Some(BeamWakeUp::Sensor1000ms));
+        assert!(!info.capabilities.is_sleeping());
+
+        // a battery device without beaming sleeps
+        let info = NodeProtocolInfo::parse(&[0x53, 0x1C, 0x00, 0x04, 0x20, 0x01]).unwrap();
+        assert!(info.capabilities.is_sleeping());
+
+        // a wrong length is not accepted
+        assert!(NodeProtocolInfo::parse(&[0xD3, 0x9C, 0x01, 0x04, 0x11]).is_err());
+    }
+
+    #[test]
+    fn test_parse_inclusion_report() {
+        let report = InclusionReport::parse(&[0x01, 0x01, 0x00, 0x00]).unwrap();
+        assert_eq!(report.status, InclusionStatus::LearnReady);
+        assert_eq!(report.info, None);
+
+        // a binary switch with the switch and the version command class
+        let report = InclusionReport::parse(&[
+            0x01, 0x03, 0x07, 0x06, 0x04, 0x10, 0x01, 0x25, 0x86, 0xEF, 0x20,
+        ])
+        .unwrap();
+        assert_eq!(report.status, InclusionStatus::AddingSlave);
+        assert_eq!(report.node_id, 0x07);
+        let info = report.info.unwrap();
+        assert_eq!(info.generic, GenericType::BinarySwitch);
+        assert_eq!(
+            info.cmds,
+            vec![CommandClass::SWITCH_BINARY, CommandClass::VERSION]
+        );
+
+        assert!(InclusionReport::parse(&[0x01, 0x42]).is_err());
+    }
+
+    #[test]
+    fn test_parse_learn_mode_report() {
+        let report = LearnModeReport::parse(&[0x01, 0x06, 0x0C, 0x00]).unwrap();
+        assert_eq!(report.status, LearnModeStatus::Done);
+        assert_eq!(report.node_id, 0x0C);
+
+        assert!(LearnModeReport::parse(&[0x01, 0x03]).is_err());
+    }
+
+    #[test]
+    fn test_parse_transmit_report() {
+        // the short report of older controllers
+        let report = TransmitReport::parse(&[0x05, 0x01]).unwrap();
+        assert_eq!(report.message_id, 0x05);
+        assert_eq!(report.status, TransmitStatus::CompleteNoAck);
+        assert_eq!(report.time, None);
+
+        // the extended report with one repeater
+        let report = TransmitReport::parse(&[
+            0x06, 0x00, 0x00, 0x03, 0x01, 0xC8, 0x7F, 0x7F, 0x7F, 0x7F, 0x00, 0x00, 0x03, 0x0A,
+            0x00, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(report.status, TransmitStatus::CompleteOk);
+        assert_eq!(report.time, Some(std::time::Duration::from_millis(30)));
+        assert_eq!(report.repeaters, vec![0x0A]);
+        assert_eq!(report.speed, Some(100_000));
+        assert_eq!(report.route().unwrap().repeaters, vec![0x0A]);
+
+        assert!(TransmitReport::parse(&[0x05, 0x09]).is_err());
+    }
+
+    #[test]
+    fn test_write_command() {
+        let mut buf = Vec::with_capacity(64);
+        let msg = SerialMessage::new(
+            SerialMessageType::Request,
+            SerialMessageFunction::GetVersion,
+            &[],
+        )
+        .unwrap();
+
+        msg.write_command(&mut buf);
+        assert_eq!(buf, vec![0x01, 0x03, 0x00, 0x15, 0xE9]);
+        assert_eq!(buf, msg.get_command());
+
+        // the buffer is cleared and keeps its memory
+        SerialMessage::new_header(SerialMessageHeader::ACK).write_command(&mut buf);
+        assert_eq!(buf, vec![0x06]);
+        assert!(buf.capacity() >= 64);
+
+        // the data doesn't fit into a frame
+        assert!(SerialMessage::new(
+            SerialMessageType::Request,
+            SerialMessageFunction::SendData,
+            &[0; crate::payload::MAX_PAYLOAD + 1],
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_hex() {
+        let msg = SerialMessage::from_hex("01 09 00 04 00 05 03 20 03 FF 28").unwrap();
+        assert_eq!(msg.typ, SerialMessageType::Request);
+        assert_eq!(msg.func, SerialMessageFunction::ApplicationCommandHandler);
+        assert_eq!(msg.data, vec![0x00, 0x05, 0x03, 0x20, 0x03, 0xFF]);
+
+        // the output of to_hex and bytes without separators
+        let data = vec![0x01, 0x0A, 0xFF];
+        assert_eq!(
+            SerialMessage::parse_hex(&SerialMessage::to_hex(&data)).unwrap(),
+            data
+        );
+        assert_eq!(SerialMessage::parse_hex("010AFF").unwrap(), data);
+
+        assert!(SerialMessage::parse_hex("01 0G").is_err());
+        assert!(SerialMessage::parse_hex("010").is_err());
+        assert!(SerialMessage::from_hex("01 09 00 04 00 05 03 20 03 FF 00").is_err());
+    }
+
+    #[test]
+    fn test_transmit_options() {
+        assert_eq!(
+            TransmitOptions::default().bits(),
+            SerialTransmissionType::AutoRoute as u8
+        );
+
+        let options = TransmitOptions {
+            ack: true,
+            explore: true,
+            ..TransmitOptions::default()
+        };
+        assert_eq!(options.bits(), SerialTransmissionType::Direct as u8);
+    }
+
+    #[test]
+    fn test_parse_route() {
+        // over two repeaters with 40 kbit/s
+        let route = Route::parse(&[0x05, 0x0A, 0x0C, 0x00, 0x00, 0x02])
+            .unwrap()
+            .unwrap();
+        assert_eq!(route.repeaters, vec![0x0A, 0x0C]);
+        assert_eq!(route.speed, 40_000);
+
+        // a direct route and no known route
+        let route = Route::parse(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x03]).unwrap();
+        assert_eq!(route.unwrap().repeaters, Vec::<u8>::new());
+        assert_eq!(
+            Route::parse(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x00]),
+            Ok(None)
+        );
+
+        assert!(Route::parse(&[0x05, 0x0A]).is_err());
+    }
+
+    #[test]
+    fn test_meter_unit_scale() {
+        assert_eq!(
+            MeterUnit::from_scale(MeterKind::Electric, 0x08),
+            Some(MeterUnit::kVarh)
+        );
+        assert_eq!(
+            MeterUnit::from_scale(MeterKind::Water, 0x02),
+            Some(MeterUnit::USGallons)
+        );
+        assert_eq!(MeterUnit::from_scale(MeterKind::Gas, 0x02), None);
+        assert_eq!(MeterUnit::kVarh.get_scale(), 0x08);
+
+        let data = MeterData::new(12.5, MeterKind::Electric, MeterUnit::kWh);
+        assert_eq!(data.to_string(), "12.5 kWh");
+    }
+
+    #[test]
+    fn test_sensor_value_convert() {
+        let celsius = SensorValue::new(20.0, Unit::Celsius);
+        let fahrenheit = celsius.convert(Unit::Fahrenheit).unwrap();
+        assert!((fahrenheit.value - 68.0).abs() < 1e-9);
+        assert!((fahrenheit.convert(Unit::Celsius).unwrap().value - 20.0).abs() < 1e-9);
+
+        let power = SensorValue::new(1500.0, Unit::Watt);
+        assert_eq!(power.convert(Unit::Kilowatt).unwrap().value, 1.5);
+
+        let volume = SensorValue::new(1.0, Unit::CubicMeter);
+        let feet = volume.convert(Unit::CubicFeet).unwrap();
+        assert!((feet.value - 35.314_666_7).abs() < 1e-6);
+
+        // different quantities can't be converted
+        assert_eq!(power.convert(Unit::Celsius), None);
+        let percent = SensorValue::new(50.0, Unit::Percent);
+        assert_eq!(percent.quantity(), Quantity::Percentage);
+        assert_eq!(percent.convert(Unit::Lux), None);
+
+        let meter = MeterData::new(2.5, MeterKind::Gas, MeterUnit::CubicMeters);
+        assert_eq!(
+            meter.to_sensor_value(),
+            Some(SensorValue::new(2.5, Unit::CubicMeter))
+        );
+    }
+
+    #[test]
+    fn test_parse_incoming_frame() {
+        // a broadcast basic report with the signal strength at the end
+        let frame =
+            IncomingFrame::parse(&[0x04, 0x05, 0x03, 0x20, 0x03, 0x63, 0xB0]).unwrap();
+
+        assert!(frame.status.broadcast);
+        assert!(!frame.status.multicast);
+        assert_eq!(frame.source, 0x05);
+        assert_eq!(frame.expect(CommandClass::BASIC, 0x03), Ok(&[0x63][..]));
+        assert!(frame.expect(CommandClass::BASIC, 0x02).is_err());
+        assert!(IncomingFrame::parse(&[0x00, 0x05, 0x01]).is_err());
+        assert_eq!(frame.to_vec(), vec![0x04, 0x05, 0x03, 0x20, 0x03, 0x63]);
+
+        // an unknown command class is kept
+        let frame = IncomingFrame::parse(&[0x00, 0x05, 0x03, 0xF1, 0x01, 0x02]).unwrap();
+        assert_eq!(frame.command_class, Err(0xF1));
+        assert_eq!(frame.command_class_id(), 0xF1);
+        assert_eq!(frame.to_vec(), vec![0x00, 0x05, 0x03, 0xF1, 0x01, 0x02]);
+
+        // a binary switch set from node 2 to node 7
+        let (frame, destination) =
+            IncomingFrame::parse_promiscuous(&[0x00, 0x02, 0x03, 0x25, 0x01, 0xFF, 0x07]).unwrap();
+        assert_eq!(frame.source, 0x02);
+        assert_eq!(frame.expect(CommandClass::SWITCH_BINARY, 0x01), Ok(&[0xFF][..]));
+        assert_eq!(destination, 0x07);
+        assert!(IncomingFrame::parse_promiscuous(&[0x00, 0x02, 0x03, 0x25, 0x01, 0xFF]).is_err());
+    }
+
+    #[test]
+    fn test_parse_serial_api_capabilities() {
+        let caps = SerialApiCapabilities::parse(&[
+            0x01, 0x02, 0x00, 0x86, 0x00, 0x01, 0x00, 0x5A, 0xFE, 0x00, 0x04,
+        ])
+        .unwrap();
+
+        assert_eq!(caps.manufacturer_id, 0x0086);
+        assert_eq!(caps.product_id, 0x005A);
+        assert!(caps.supports(SerialMessageFunction::DiscoveryNodes));
+        assert!(caps.supports(SerialMessageFunction::SendData));
+        assert!(!caps.supports(SerialMessageFunction::SendDataMulti));
+
+        let flags = ControllerCapabilities::parse(0x1C);
+        assert!(flags.sis_present && flags.real_primary && flags.suc);
+        assert!(!flags.secondary);
     }
 }
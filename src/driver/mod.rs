@@ -0,0 +1,419 @@
+//! Drivers to communicate with the Z-Wave controller
+//!
+//! The `Driver` trait is the interface the `Controller` of the basic module
+//! uses to talk to the Z-Wave network. The `serial` module implements it for
+//! a Z-Wave controller, which is connected over a serial port.
+//!
+//! The `FrameDriver` only sends and receives the raw frames of the Serial
+//! API, for tools like a sniffer, which handle the frames on their own.
+
+pub mod serial;
+
+use self::serial::{SerialMsg, SerialMsgFunction};
+use crate::defs::{
+    ControllerInfo, GenericType, IncomingFrame, NodeActivity, NodeProtocolInfo, Route,
+    TransmitOptions, TransmitReport,
+};
+use crate::error::Error;
+
+/// The interface to a Z-Wave controller.
+pub trait Driver {
+    /// Send the message with the default transmit options and return its message id.
+    fn write<M>(&mut self, message: M) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>;
+
+    /// Send the message with the transmit options and return its message id.
+    fn write_with_options<M>(&mut self, message: M, options: TransmitOptions) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>;
+
+    /// Send the message to multiple nodes at once and return its message id.
+    fn write_multi<M>(&mut self, node_ids: &[u8], message: M) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>;
+
+    /// Read the next message from the controller.
+    fn read(&mut self) -> Result<SerialMsg, Error>;
+
+    /// Read the next command, which a node sent to the controller.
+    fn read_frame(&mut self) -> Result<IncomingFrame, Error>;
+
+    /// Put the messages back in front of the received ones, in the same
+    /// order, so `read` and `read_frame` return them again. Used for the
+    /// messages, which arrived while waiting for another one.
+    fn unread(&mut self, messages: Vec<SerialMsg>);
+
+    /// Wait until the controller reports the transmit status of the message.
+    fn wait_transmit(&mut self, message_id: u8) -> Result<TransmitReport, Error>;
+
+    /// Take the activities of the nodes since the last call.
+    fn take_activity(&mut self) -> Result<Vec<NodeActivity>, Error>;
+
+    /// Check if the controller has the node marked as failed.
+    fn is_failed_node<N>(&mut self, node_id: N) -> Result<bool, Error>
+    where
+        N: Into<u8>;
+
+    /// Return the ids of all nodes in the network.
+    fn get_node_ids(&mut self) -> Result<Vec<u8>, Error>;
+
+    /// Return the neighbors of the node from the routing table.
+    fn get_neighbors<N>(&mut self, node_id: N) -> Result<Vec<u8>, Error>
+    where
+        N: Into<u8>;
+
+    /// Return the route, which was used to reach the node the last time.
+    fn get_last_working_route<N>(&mut self, node_id: N) -> Result<Option<Route>, Error>
+    where
+        N: Into<u8>;
+
+    /// Forget the last working route to the node.
+    fn clear_route<N>(&mut self, node_id: N) -> Result<(), Error>
+    where
+        N: Into<u8>;
+
+    /// Return the home id and the node id of the controller.
+    fn get_controller_id(&mut self) -> Result<(u32, u8), Error>;
+
+    /// Return the ids, the role and the capabilities of the controller.
+    fn get_controller_info(&mut self) -> Result<ControllerInfo, Error>;
+
+    /// Restart the controller, without losing the network.
+    fn soft_reset(&mut self) -> Result<(), Error>;
+
+    /// Start a function of the controller, which reports its progress by
+    /// callbacks, and return the callback id.
+    fn start_function(&mut self, function: SerialMsgFunction, data: &[u8]) -> Result<u8, Error>;
+
+    /// Read the bytes at the offset from the memory of the controller.
+    fn read_memory(&mut self, offset: u16, length: u8) -> Result<Vec<u8>, Error>;
+
+    /// Write the firmware image to the controller.
+    fn update_firmware<F>(&mut self, image: &[u8], progress: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize);
+
+    /// Return the generic device class of the node.
+    fn get_node_generic_class<N>(&mut self, node_id: N) -> Result<GenericType, Error>
+    where
+        N: Into<u8>;
+
+    /// Return the protocol information, which the controller has about the node.
+    fn get_node_protocol_info<N>(&mut self, node_id: N) -> Result<NodeProtocolInfo, Error>
+    where
+        N: Into<u8>;
+}
+
+/// The memory, which is reserved for the frames to send and receive: the
+/// largest payload, the header, the length, the type, the function and the
+/// checksum
+const FRAME_CAPACITY: usize = crate::payload::MAX_PAYLOAD + 5;
+
+/// Sends and receives the raw frames of the Serial API over a stream. It
+/// doesn't implement `Driver`, the `Controller` uses `serial::SerialDriver`.
+pub struct FrameDriver<D>
+where
+    D: std::io::Read + std::io::Write,
+{
+    device: D,
+    // buffers, which are reused for every frame
+    tx: Vec<u8>,
+    rx: Vec<u8>,
+}
+
+impl FrameDriver<Box<dyn ::serial::SerialPort>> {
+    /// Open the serial port of the controller
+    pub fn open<P>(path: P) -> crate::error::Result<Self>
+    where
+        P: Into<String>,
+    {
+        use ::serial::prelude::*;
+
+        // open the serial port
+        let mut port = ::serial::open(&path.into())?;
+
+        // set the settings
+        port.reconfigure(&|settings| {
+            settings.set_baud_rate(::serial::Baud115200)?;
+            settings.set_char_size(::serial::Bits8);
+            settings.set_parity(::serial::ParityNone);
+            settings.set_stop_bits(::serial::Stop1);
+            settings.set_flow_control(::serial::FlowHardware);
+            Ok(())
+        })?;
+
+        // set the timeout
+        port.set_timeout(std::time::Duration::from_millis(100))?;
+
+        Ok(FrameDriver::new(Box::new(port)))
+    }
+}
+
+impl<D> FrameDriver<D>
+where
+    D: std::io::Read + std::io::Write,
+{
+    /// Create a new frame driver based on the given stream
+    pub fn new(device: D) -> Self {
+        FrameDriver {
+            device,
+            tx: Vec::with_capacity(FRAME_CAPACITY),
+            rx: Vec::with_capacity(FRAME_CAPACITY),
+        }
+    }
+
+    /// Read a single byte from the stream and retries the amount of times as specified
+    fn read_byte(&mut self, timeout: Option<usize>) -> crate::error::Result<u8> {
+        // buffer to read the byte in
+        let mut buffer = [0u8; 1];
+
+        // request the byte read
+        match self.device.read_exact(&mut buffer) {
+            // on success return the byte
+            Ok(_) => Ok(buffer[0]),
+            // on error
+            Err(e) => {
+                // we check if there was a timeout
+                if e.kind() == std::io::ErrorKind::TimedOut {
+                    // when yes, we calculate the new timeout
+                    if let Some(t) = timeout {
+                        let new_timeout = t - 1;
+
+                        // when there are still timeout time left, retry
+                        if new_timeout > 0 {
+                            return self.read_byte(Some(new_timeout));
+                        }
+                    }
+                }
+
+                // if an error occoured or no timeouts are left, stop trying
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Write the message to the controller and wait until the controller accepted it
+    pub fn write_msg(&mut self, msg: &crate::defs::SerialMessage) -> crate::error::Result<()> {
+        use crate::defs::SerialMessageHeader;
+        use std::convert::TryFrom;
+
+        // send the message
+        msg.write_command(&mut self.tx);
+        self.device.write_all(&self.tx)?;
+        self.device.flush()?;
+
+        // only frames get acknowledged
+        if msg.header != SerialMessageHeader::SOF {
+            return Ok(());
+        }
+
+        // wait for the answer of the controller
+        let answer = self.read_byte(Some(16)).map_err(|e| match e.kind() {
+            crate::error::ErrorKind::Io(std::io::ErrorKind::TimedOut) => {
+                crate::error::Error::Timeout {
+                    stage: crate::error::Stage::Ack,
+                }
+            }
+            _ => e,
+        })?;
+
+        match SerialMessageHeader::try_from(answer)? {
+            SerialMessageHeader::ACK => Ok(()),
+            _ => Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "The controller didn't accept the message",
+            )),
+        }
+    }
+
+    /// Enable or disable the promiscuous mode, in which the controller also
+    /// delivers the frames between other nodes.
+    pub fn set_promiscuous_mode(&mut self, enabled: bool) -> crate::error::Result<()> {
+        use crate::defs::{SerialMessage, SerialMessageFunction, SerialMessageType};
+
+        self.write_msg(&SerialMessage::new(
+            SerialMessageType::Request,
+            SerialMessageFunction::SetPromiscuousMode,
+            &[enabled as u8],
+        )?)
+    }
+
+    /// Read the next message from the controller and acknowledge it
+    pub fn receive_msg(
+        &mut self,
+        timeout: Option<usize>,
+    ) -> crate::error::Result<crate::defs::SerialMessage> {
+        use crate::defs::{SerialMessage, SerialMessageHeader};
+
+        // wait for the start of the message
+        let header = self.read_byte(timeout)?;
+        if header != SerialMessageHeader::SOF as u8 {
+            return SerialMessage::parse(&[header]);
+        }
+
+        // read the length and the rest of the frame
+        let len = self.read_byte(Some(16))?;
+        self.rx.clear();
+        self.rx.extend_from_slice(&[header, len]);
+        for _ in 0..len {
+            let byte = self.read_byte(Some(16))?;
+            self.rx.push(byte);
+        }
+
+        // a frame without type, function and checksum can't be parsed
+        if len < 3 {
+            self.write_msg(&SerialMessage::new_header(SerialMessageHeader::NAK))?;
+            return Err(crate::error::Error::new(
+                crate::error::ErrorKind::UnknownZWave,
+                "Data is too short for a ZWave message with SOF header",
+            ));
+        }
+
+        // acknowledge the frame, when it arrived complete. Every length fits
+        // into the payload, so an acknowledged frame isn't lost.
+        let expected = SerialMessage::checksum(&self.rx[..self.rx.len() - 1]);
+        let got = self.rx[self.rx.len() - 1];
+        if expected != got {
+            self.write_msg(&SerialMessage::new_header(SerialMessageHeader::NAK))?;
+            return Err(crate::error::Error::FrameCorrupt { expected, got });
+        }
+        self.write_msg(&SerialMessage::new_header(SerialMessageHeader::ACK))?;
+
+        SerialMessage::parse(&self.rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum TestDeviceMode {
+        Timeout(isize),
+        Data(Vec<u8>),
+    }
+
+    struct TestDevice {
+        mode: TestDeviceMode,
+    }
+
+    impl TestDevice {
+        fn new(mode: TestDeviceMode) -> Self {
+            TestDevice { mode }
+        }
+    }
+
+    impl std::io::Read for TestDevice {
+        fn read(&mut self, inp: &mut [u8]) -> std::result::Result<usize, std::io::Error> {
+            match self.mode {
+                // on timeout we always timeout and count one down
+                // when we reach zero, we sent the value 0xFF
+                TestDeviceMode::Timeout(t) => {
+                    let t = t - 1;
+                    self.mode = TestDeviceMode::Timeout(t);
+
+                    if t == 0 {
+                        inp[0] = 0xFF;
+                        Ok(1)
+                    } else {
+                        Err(std::io::Error::from(std::io::ErrorKind::TimedOut))
+                    }
+                }
+                // return the data byte by byte
+                TestDeviceMode::Data(ref mut data) => {
+                    if data.is_empty() {
+                        return Err(std::io::Error::from(std::io::ErrorKind::TimedOut));
+                    }
+
+                    inp[0] = data.remove(0);
+                    Ok(1)
+                }
+            }
+        }
+    }
+
+    impl std::io::Write for TestDevice {
+        fn write(&mut self, inp: &[u8]) -> std::result::Result<usize, std::io::Error> {
+            Ok(inp.len())
+        }
+
+        fn flush(&mut self) -> std::result::Result<(), std::io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let device = std::io::Cursor::new(Vec::new());
+        FrameDriver::new(device);
+    }
+
+    #[test]
+    fn test_timeout_read_byte() {
+        // timeout error to compare against
+        let timeout_error = crate::error::Error::new(
+            crate::error::ErrorKind::Io(std::io::ErrorKind::TimedOut),
+            "timed out",
+        );
+
+        // Test Device to generate data for the test
+        let device = TestDevice::new(TestDeviceMode::Timeout(0));
+        // generate a driver for the test device
+        let mut driver = FrameDriver::new(device);
+
+        // check if we can timeout
+        assert_eq!(driver.read_byte(Some(1600)), Err(timeout_error));
+    }
+
+    #[test]
+    fn test_delayed_read_byte() {
+        // Test Device to generate data for the test
+        let device = TestDevice::new(TestDeviceMode::Timeout(5));
+        // generate a driver for the test device
+        let mut driver = FrameDriver::new(device);
+
+        // check if we can timeout
+        assert_eq!(driver.read_byte(Some(16)), Ok(0xFF));
+    }
+
+    #[test]
+    fn test_receive_msg() {
+        use crate::defs::{SerialMessageFunction, SerialMessageHeader, SerialMessageType};
+
+        // an ACK followed by a GetVersion response
+        let device = TestDevice::new(TestDeviceMode::Data(vec![
+            0x06, 0x01, 0x05, 0x01, 0x15, 0x5A, 0x06, 0xB2,
+        ]));
+        let mut driver = FrameDriver::new(device);
+
+        let ack = driver.receive_msg(Some(16)).unwrap();
+        assert_eq!(ack.header, SerialMessageHeader::ACK);
+
+        let msg = driver.receive_msg(Some(16)).unwrap();
+        assert_eq!(msg.typ, SerialMessageType::Response);
+        assert_eq!(msg.func, SerialMessageFunction::GetVersion);
+        assert_eq!(msg.data, vec![0x5A, 0x06]);
+    }
+
+    #[test]
+    fn test_receive_large_msg() {
+        use crate::defs::{SerialMessage, SerialMessageFunction, SerialMessageType};
+        use crate::payload::MAX_PAYLOAD;
+
+        // the largest frame the Serial API allows is received completely
+        let sent = SerialMessage::new(
+            SerialMessageType::Request,
+            SerialMessageFunction::ApplicationCommandHandler,
+            &[0x42; MAX_PAYLOAD],
+        )
+        .unwrap();
+        let device = TestDevice::new(TestDeviceMode::Data(sent.get_command()));
+        let mut driver = FrameDriver::new(device);
+        assert_eq!(driver.receive_msg(Some(16)).unwrap().data, sent.data);
+
+        // a frame without a function is rejected
+        let device = TestDevice::new(TestDeviceMode::Data(vec![0x01, 0x01, 0xFE]));
+        let mut driver = FrameDriver::new(device);
+        assert!(driver.receive_msg(Some(16)).is_err());
+    }
+}
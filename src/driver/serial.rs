@@ -0,0 +1,1228 @@
+//! Serial Driver implementation to communicate with the serial Z-Wave devices.
+// `header, length, type(rx|tx), zw-function, data, transmit-type, message-id, checksum`
+//
+// ZWave data structure for basic
+// `device, data-length, comand class, command, value`
+
+use crate::bootloader;
+use crate::defs::{
+    ControllerCapabilities, ControllerInfo, GenericType, IncomingFrame, LibraryType, NodeActivity,
+    NodeProtocolInfo, Route, SerialApiCapabilities, TransmitOptions, TransmitReport,
+    TransmitStatus,
+};
+use crate::driver::Driver;
+use crate::error::{Error, ErrorKind, Stage};
+use serial::{self, SerialPort, SystemPort};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::ErrorKind as StdErrorKind;
+use std::io::{Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Amount of transmit reports which are kept, when nobody waits for them
+const MAX_REPORTS: usize = 16;
+
+/// Amount of node activities which are kept, until they are taken
+const MAX_ACTIVITY: usize = 64;
+
+/// The memory, which is reserved for the frames to send and receive
+const FRAME_CAPACITY: usize = 64;
+
+/// The time the controller needs to start again after a soft reset
+const RESET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The time between two tries to open a port, which re-enumerates
+const REOPEN_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct SerialDriver {
+    // serial port
+    port: SystemPort,
+    // message id counter
+    message_id: u8,
+    // message store
+    messages: Vec<SerialMsg>,
+    // transmit report store
+    reports: Vec<TransmitReport>,
+    // message ids and the nodes they were sent to
+    sent: Vec<(u8, u8)>,
+    // node activity store
+    activity: Vec<NodeActivity>,
+    // last working route of each node
+    routes: HashMap<u8, Route>,
+    // buffers, which are reused for every frame
+    tx: Vec<u8>,
+    rx: Vec<u8>,
+    // serial driver path
+    path: String,
+}
+
+impl SerialDriver {
+    /// Creates a new SerialDriver which is a connection point to
+    /// ZWave device & network.
+    pub fn new<P>(path: P) -> Result<SerialDriver, Error>
+    where
+        P: Into<String>,
+    {
+        // get the path
+        let path = path.into();
+
+        // try to open the serial port
+        let port = SerialDriver::open_port(&path)?;
+
+        // create the new struct
+        let driver = SerialDriver {
+            port,
+            message_id: 0x00,
+            messages: vec![],
+            reports: vec![],
+            sent: vec![],
+            activity: vec![],
+            routes: HashMap::new(),
+            tx: Vec::with_capacity(FRAME_CAPACITY),
+            rx: Vec::with_capacity(FRAME_CAPACITY),
+            path,
+        };
+
+        // return it
+        Ok(driver)
+    }
+
+    /// Open and configure the serial port
+    fn open_port(path: &str) -> Result<SystemPort, Error> {
+        let mut port = serial::open(path)?;
+
+        // set the settings
+        port.reconfigure(&|settings| {
+            settings.set_baud_rate(serial::Baud115200)?;
+            settings.set_char_size(serial::Bits8);
+            settings.set_parity(serial::ParityNone);
+            settings.set_stop_bits(serial::Stop1);
+            settings.set_flow_control(serial::FlowHardware);
+            Ok(())
+        })?;
+
+        // set the timeout
+        port.set_timeout(Duration::from_millis(200))?;
+
+        Ok(port)
+    }
+
+    /// Open the port again, like after the stick re-enumerated on the USB
+    /// bus. The messages of the old connection are dropped.
+    fn reopen(&mut self) -> Result<(), Error> {
+        self.port = SerialDriver::open_port(&self.path)?;
+        self.messages.clear();
+        self.sent.clear();
+        Ok(())
+    }
+
+    /// Wait until the controller announces its start with the Serial API
+    /// Started frame. The port is opened again, when it got lost while the
+    /// controller restarted. Older controllers don't announce the start, so
+    /// they have to answer a request instead.
+    fn wait_started(&mut self) -> Result<(), Error> {
+        let deadline = Instant::now() + RESET_TIMEOUT;
+
+        while Instant::now() < deadline {
+            match self.read_single_msg() {
+                Ok(ref m)
+                    if m.header == SerialMsgHeader::SOF
+                        && m.func == SerialMsgFunction::SerialApiStarted =>
+                {
+                    return Ok(());
+                }
+                // the messages of the start are dropped
+                Ok(_) => {}
+                Err(ref e) if e.kind() == ErrorKind::Io(StdErrorKind::TimedOut) => {}
+                // the stick left the USB bus, it comes back with the same path
+                Err(_) => {
+                    thread::sleep(REOPEN_INTERVAL);
+                    self.reopen().ok();
+                }
+            }
+        }
+
+        self.get_controller_id()?;
+        Ok(())
+    }
+
+    /// Write the message to the port, through the reused send buffer
+    fn send_msg(&mut self, msg: &SerialMsg) -> Result<(), Error> {
+        msg.write_command(&mut self.tx);
+        self.port.write_all(&self.tx)?;
+        Ok(())
+    }
+
+    // Count the message_id up and return the new
+    // message_id
+    fn get_next_msg_id(&mut self) -> u8 {
+        // count the message_id up
+        self.message_id += 1;
+
+        // jump over 0x00 it's reserved
+        if self.message_id == 0x00 {
+            self.message_id += 1;
+        }
+
+        // return the message id
+        self.message_id
+    }
+
+    /// This function reads a single message from the ZWave device/driver
+    fn read_single_msg(&mut self) -> Result<SerialMsg, Error> {
+        // buffer to read each byte in
+        let mut buf = [0u8; 1];
+        // the frame is read into the reused receive buffer
+        self.rx.clear();
+
+        // try to read the first byte
+        self.port.read_exact(&mut buf)?;
+
+        // when the first byte is the start of a frame
+        if buf[0] == SerialMsgHeader::SOF as u8 {
+            // add the header byte to the result
+            self.rx.push(buf[0]);
+
+            // read the next byte which includes the length
+            self.port.read_exact(&mut buf)?;
+
+            // add the length to the result
+            self.rx.push(buf[0]);
+
+            // read the full length of the message
+            let len = buf[0];
+            for _ in 0..len {
+                // read a byte
+                self.port.read_exact(&mut buf)?;
+                // add the byte to the result
+                self.rx.push(buf[0]);
+            }
+
+            // create the message
+            let m = SerialMsg::parse(self.rx.as_slice());
+
+            // if it was successfull return ACK
+            if m.is_ok() {
+                self.port.write_all(&[SerialMsgHeader::ACK as u8])?;
+            }
+            // if there occoured an error send back a NAK
+            else {
+                self.port.write_all(&[SerialMsgHeader::NAK as u8])?;
+            }
+
+            //return the message
+            return m;
+        }
+        // on message ackonwledge
+        else if buf[0] == SerialMsgHeader::ACK as u8 {
+            return Ok(SerialMsg::new_header(SerialMsgHeader::ACK));
+        }
+        // on message not ackonwledge
+        else if buf[0] == SerialMsgHeader::NAK as u8 {
+            return Ok(SerialMsg::new_header(SerialMsgHeader::NAK));
+        }
+        // on resent
+        else if buf[0] == SerialMsgHeader::CAN as u8 {
+            return Ok(SerialMsg::new_header(SerialMsgHeader::CAN));
+        }
+
+        // if the header is unknown return a error
+        Err(Error::new(
+            ErrorKind::UnknownZWave,
+            "Unknown ZWave header detected",
+        ))
+    }
+
+    /// Reads a single message from the zwave driver. It retries to read after a timeout as defined.
+    fn read_single_msg_rty(&mut self, tries: &i32) -> Result<SerialMsg, Error> {
+        // set the variable to count
+        let mut counter: i32 = *tries;
+        loop {
+            // throw an error when we tried to read too much
+            if counter <= 0 {
+                return Err(Error::new(ErrorKind::Io(StdErrorKind::TimedOut), "Timeout"));
+            }
+
+            // count down
+            counter -= 1;
+
+            match self.read_single_msg() {
+                // on timeout error try to read again
+                Err(e) => {
+                    if e.kind() == ErrorKind::Io(StdErrorKind::TimedOut) {
+                        continue;
+                    } else {
+                        return Err(e);
+                    }
+                }
+                // on message recveive check if it's
+                Ok(m) => {
+                    return Ok(m);
+                }
+            }
+        }
+    }
+
+    /// Reads all messages and stores them to the table.
+    fn read_all_msg(&mut self) -> Result<bool, Error> {
+        // read all messages
+        loop {
+            // try to read a message 3 times
+            match self.read_single_msg_rty(&3) {
+                // when there is a timout quit
+                Err(e) => {
+                    if e.kind() != ErrorKind::Io(StdErrorKind::TimedOut) {
+                        return Err(e);
+                    } else {
+                        return Ok(true);
+                    }
+                }
+                // store the message to the table
+                Ok(m) => {
+                    // the transmit reports of sent messages are stored separately
+                    if m.header == SerialMsgHeader::SOF
+                        && m.typ == SerialMsgType::Request
+                        && (m.func == SerialMsgFunction::SendData
+                            || m.func == SerialMsgFunction::SendDataMulti)
+                    {
+                        if let Ok(report) = TransmitReport::parse(&m.data) {
+                            // record the status for the node the message was sent to
+                            if let Some(pos) =
+                                self.sent.iter().position(|s| s.0 == report.message_id)
+                            {
+                                let (_, node) = self.sent.remove(pos);
+                                self.record(NodeActivity::Transmit(node, report.status));
+
+                                // remember the route of a successful transmission
+                                if report.status == TransmitStatus::CompleteOk {
+                                    if let Some(route) = report.route() {
+                                        self.routes.insert(node, route);
+                                    }
+                                }
+                            }
+
+                            // only keep the latest reports
+                            if self.reports.len() >= MAX_REPORTS {
+                                self.reports.remove(0);
+                            }
+                            self.reports.push(report);
+                        }
+                        continue;
+                    }
+                    // record from which node the message was received
+                    if m.header == SerialMsgHeader::SOF
+                        && m.typ == SerialMsgType::Request
+                        && m.func == SerialMsgFunction::ApplicationCommandHandler
+                        && m.data.len() >= 2
+                    {
+                        // Wake Up Notification
+                        if m.data.len() >= 5 && m.data[3] == 0x84 && m.data[4] == 0x07 {
+                            self.record(NodeActivity::WakeUp(m.data[1]));
+                        } else {
+                            self.record(NodeActivity::Received(m.data[1]));
+                        }
+                    }
+
+                    // save incoming messages sorted for the device the message is sent to
+                    if m.header == SerialMsgHeader::SOF && !m.data.is_empty() {
+                        // push the message to the stack
+                        self.messages.push(m.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Call a function of the Z-Wave controller and return its response.
+    fn request(&mut self, function: SerialMsgFunction, data: Vec<u8>) -> Result<SerialMsg, Error> {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // send the request
+        let msg = SerialMsg::new(SerialMsgType::Request, function, data);
+        self.send_msg(&msg)?;
+
+        // check if the first message has the ACK answer
+        let m = self.read_single_msg_rty(&5)?;
+        if m.header != SerialMsgHeader::ACK {
+            return Err(Error::new(
+                ErrorKind::Io(StdErrorKind::InvalidData),
+                "The driver refused the data - No ACK package",
+            ));
+        }
+
+        // read the response of the function
+        let m = self.read_single_msg_rty(&10)?;
+        if m.typ != SerialMsgType::Response || m.func != function {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The ZWave message has a wrong format",
+            ));
+        }
+
+        Ok(m)
+    }
+
+    /// Store a node activity and drop the oldest, when too many are stored.
+    fn record(&mut self, activity: NodeActivity) {
+        if self.activity.len() >= MAX_ACTIVITY {
+            self.activity.remove(0);
+        }
+        self.activity.push(activity);
+    }
+
+    /// Checks if the bit at the requested position is set
+    fn get_bit_at(&self, input: u8, n: u8) -> bool {
+        if n < 8 {
+            input & (1 << n) != 0
+        } else {
+            false
+        }
+    }
+
+    /// Return a copy the message stack
+    pub fn get_messages(&self) -> Vec<SerialMsg> {
+        self.messages.clone()
+    }
+}
+
+impl Driver for SerialDriver {
+    fn write<M>(&mut self, message: M) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>,
+    {
+        self.write_with_options(message, TransmitOptions::default())
+    }
+
+    fn write_with_options<M>(&mut self, message: M, options: TransmitOptions) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>,
+    {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // get the message from into
+        let mut message = message.into();
+
+        // Add the sent type to the message
+        message.push(options.bits());
+
+        // get the next message id
+        let m_id = self.get_next_msg_id();
+
+        // add it to the message
+        message.push(m_id);
+
+        // remember the node, to assign the transmit report to it
+        if self.sent.len() >= MAX_REPORTS {
+            self.sent.remove(0);
+        }
+        self.sent.push((m_id, message[0]));
+
+        // generate the message
+        let msg = SerialMsg::new(SerialMsgType::Request, SerialMsgFunction::SendData, message);
+
+        // send the value
+        self.send_msg(&msg)?;
+
+        // read the ACK accept package
+        match self.read_single_msg_rty(&10) {
+            // on error return it
+            Err(e) => {
+                return Err(e);
+            }
+            // check the message
+            Ok(m) => {
+                // when wrong header is received
+                if m.header != SerialMsgHeader::ACK {
+                    return Err(Error::new(
+                        ErrorKind::Io(StdErrorKind::InvalidData),
+                        "The driver refused the data - No ACK package",
+                    ));
+                }
+            }
+        }
+
+        // read the driver accept
+        match self.read_single_msg_rty(&10) {
+            // on error return it
+            Err(e) => {
+                return Err(e);
+            }
+            // check the message
+            Ok(m) => {
+                // when wrong message is received
+                if m.header != SerialMsgHeader::SOF
+                    || m.typ != SerialMsgType::Response
+                    || m.func != SerialMsgFunction::SendData
+                    || m.data != vec![0x01u8]
+                {
+                    return Err(Error::new(
+                        ErrorKind::Io(StdErrorKind::InvalidData),
+                        "The driver refused the data - Negative response message",
+                    ));
+                }
+            }
+        }
+
+        // return the message id
+        Ok(m_id)
+    }
+
+    fn write_multi<M>(&mut self, node_ids: &[u8], message: M) -> Result<u8, Error>
+    where
+        M: Into<Vec<u8>>,
+    {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // get the message from into
+        let mut message = message.into();
+
+        // the node id of the message is replaced by the node list
+        if !message.is_empty() {
+            message.remove(0);
+        }
+
+        // add the amount of nodes and the node ids in front
+        let mut data = vec![node_ids.len() as u8];
+        data.extend_from_slice(node_ids);
+        data.append(&mut message);
+
+        // Add the sent type to the message
+        data.push(SerialTransmissionType::AutoRoute as u8);
+
+        // get the next message id
+        let m_id = self.get_next_msg_id();
+
+        // add it to the message
+        data.push(m_id);
+
+        // generate the message
+        let msg = SerialMsg::new(
+            SerialMsgType::Request,
+            SerialMsgFunction::SendDataMulti,
+            data,
+        );
+
+        // send the value
+        self.send_msg(&msg)?;
+
+        // read the ACK accept package
+        let m = self.read_single_msg_rty(&10)?;
+        if m.header != SerialMsgHeader::ACK {
+            return Err(Error::new(
+                ErrorKind::Io(StdErrorKind::InvalidData),
+                "The driver refused the data - No ACK package",
+            ));
+        }
+
+        // read the driver accept
+        let m = self.read_single_msg_rty(&10)?;
+        if m.header != SerialMsgHeader::SOF
+            || m.typ != SerialMsgType::Response
+            || m.func != SerialMsgFunction::SendDataMulti
+            || m.data != vec![0x01u8]
+        {
+            return Err(Error::new(
+                ErrorKind::Io(StdErrorKind::InvalidData),
+                "The driver refused the data - Negative response message",
+            ));
+        }
+
+        // return the message id
+        Ok(m_id)
+    }
+
+    fn read(&mut self) -> Result<SerialMsg, Error> {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // check if a message is available
+        if self.messages.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Io(StdErrorKind::Other),
+                "No message with the given id received",
+            ));
+        }
+
+        // return the first message
+        Ok(self.messages.remove(0))
+    }
+
+    fn read_frame(&mut self) -> Result<IncomingFrame, Error> {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // the first application command, other messages stay for `read`
+        let pos = self
+            .messages
+            .iter()
+            .position(|m| m.func == SerialMsgFunction::ApplicationCommandHandler)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Io(StdErrorKind::Other),
+                    "No application command received",
+                )
+            })?;
+
+        IncomingFrame::parse(&self.messages.remove(pos).data)
+    }
+
+    fn unread(&mut self, messages: Vec<SerialMsg>) {
+        self.messages.splice(0..0, messages);
+    }
+
+    fn wait_transmit(&mut self, message_id: u8) -> Result<TransmitReport, Error> {
+        // the report can take some time, when the message gets routed
+        for _ in 0..10 {
+            // check if the report is already received
+            if let Some(pos) = self.reports.iter().position(|r| r.message_id == message_id) {
+                return Ok(self.reports.remove(pos));
+            }
+
+            self.read_all_msg()?;
+        }
+
+        Err(Error::Timeout {
+            stage: Stage::Callback,
+        })
+    }
+
+    fn take_activity(&mut self) -> Result<Vec<NodeActivity>, Error> {
+        // read all messages to get the latest activity
+        self.read_all_msg()?;
+
+        Ok(self.activity.drain(..).collect())
+    }
+
+    fn is_failed_node<N>(&mut self, node_id: N) -> Result<bool, Error>
+    where
+        N: Into<u8>,
+    {
+        let msg = self.request(SerialMsgFunction::IsFailedNode, vec![node_id.into()])?;
+
+        if msg.data.len() != 1 {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The ZWave message has a wrong format",
+            ));
+        }
+
+        Ok(msg.data[0] == 0x01)
+    }
+
+    fn get_node_ids(&mut self) -> Result<Vec<u8>, Error> {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // create the serial message
+        let msg = SerialMsg::new(
+            SerialMsgType::Request,
+            SerialMsgFunction::DiscoveryNodes,
+            vec![],
+        );
+
+        // send the value
+        self.send_msg(&msg)?;
+
+        // check if the first message has the ACK answer
+        match self.read_single_msg_rty(&5) {
+            Err(e) => {
+                return Err(e);
+            }
+            Ok(m) => {
+                if m.header != SerialMsgHeader::ACK {
+                    return Err(Error::new(
+                        ErrorKind::Io(StdErrorKind::InvalidData),
+                        "The driver refused the data - No ACK package",
+                    ));
+                }
+            }
+        }
+
+        // read the second message and get the data
+        let msg = self.read_single_msg_rty(&10)?;
+
+        // grab the data
+        let data = msg.data;
+
+        // check if the data is long enough and if the right bit is set
+        if data.len() != 34 || data[2] != 0x1D {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The ZWave message has a wrong format",
+            ));
+        }
+
+        // create the return variable
+        let mut nodes = Vec::new();
+
+        // loop over each bitmask byte
+        for (i, byte) in data.iter().enumerate().take(31).skip(3) {
+            // loop over each bit of the byte
+            for j in 0..7 {
+                // check if the bit is set
+                if self.get_bit_at(*byte, j) {
+                    // calc the number out of the bitmask
+                    let n = ((i - 3) * 8) + (j as usize + 1);
+                    // add the node to the vector
+                    nodes.push(n as u8);
+                }
+            }
+        }
+
+        //return the node ids
+        Ok(nodes)
+    }
+
+    fn get_neighbors<N>(&mut self, node_id: N) -> Result<Vec<u8>, Error>
+    where
+        N: Into<u8>,
+    {
+        // keep bad and non repeating nodes
+        let msg = self.request(
+            SerialMsgFunction::GetRoutingTableLine,
+            vec![node_id.into(), 0x00, 0x00, 0x03],
+        )?;
+
+        // the bitmask has a bit for each of the 232 nodes
+        if msg.data.len() != 29 {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The ZWave message has a wrong format",
+            ));
+        }
+
+        // create the return variable
+        let mut nodes = Vec::new();
+
+        // loop over each bit of the bitmask
+        for (i, byte) in msg.data.iter().enumerate() {
+            for j in 0..8 {
+                if self.get_bit_at(*byte, j) {
+                    nodes.push((i * 8 + j as usize + 1) as u8);
+                }
+            }
+        }
+
+        Ok(nodes)
+    }
+
+    fn get_last_working_route<N>(&mut self, node_id: N) -> Result<Option<Route>, Error>
+    where
+        N: Into<u8>,
+    {
+        let node_id = node_id.into();
+
+        // read all messages to get the latest transmit reports
+        self.read_all_msg()?;
+        if let Some(route) = self.routes.get(&node_id) {
+            return Ok(Some(route.clone()));
+        }
+
+        // older controllers don't report the route, ask for it
+        Route::parse(
+            &self
+                .request(SerialMsgFunction::GetLastWorkingRoute, vec![node_id])?
+                .data,
+        )
+    }
+
+    fn clear_route<N>(&mut self, node_id: N) -> Result<(), Error>
+    where
+        N: Into<u8>,
+    {
+        let node_id = node_id.into();
+
+        // without a route, the controller searches a new one for the next
+        // message: `node id, 4 repeaters, speed`, all of them empty
+        let data = self
+            .request(
+                SerialMsgFunction::SetLastWorkingRoute,
+                vec![node_id, 0x00, 0x00, 0x00, 0x00, 0x00],
+            )?
+            .data;
+        if data.last().cloned().unwrap_or(0x00) == 0x00 {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The controller didn't clear the route",
+            ));
+        }
+
+        self.routes.remove(&node_id);
+        Ok(())
+    }
+
+    fn get_controller_id(&mut self) -> Result<(u32, u8), Error> {
+        let data = self.request(SerialMsgFunction::MemoryGetId, vec![])?.data;
+
+        // the answer contains the home id and the node id
+        if data.len() != 5 {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The ZWave message has a wrong format",
+            ));
+        }
+
+        let home_id = ((data[0] as u32) << 24)
+            | ((data[1] as u32) << 16)
+            | ((data[2] as u32) << 8)
+            | (data[3] as u32);
+
+        Ok((home_id, data[4]))
+    }
+
+    fn get_controller_info(&mut self) -> Result<ControllerInfo, Error> {
+        let (home_id, node_id) = self.get_controller_id()?;
+
+        // the version is a null terminated string, followed by the library type
+        let data = self.request(SerialMsgFunction::GetVersion, vec![])?.data;
+        if data.len() < 2 {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The ZWave message has a wrong format",
+            ));
+        }
+        let version = String::from_utf8_lossy(&data[..data.len() - 1])
+            .trim_end_matches('\0')
+            .to_string();
+        let library = LibraryType::try_from(data[data.len() - 1]).unwrap_or(LibraryType::Unknown);
+
+        let api = SerialApiCapabilities::parse(
+            &self
+                .request(SerialMsgFunction::SerialGetCapabilities, vec![])?
+                .data,
+        )?;
+
+        let flags = self
+            .request(SerialMsgFunction::GetControllerCapabilities, vec![])?
+            .data;
+        let capabilities = ControllerCapabilities::parse(flags.first().cloned().unwrap_or(0));
+
+        Ok(ControllerInfo {
+            home_id,
+            node_id,
+            version,
+            library,
+            api,
+            capabilities,
+        })
+    }
+
+    fn soft_reset(&mut self) -> Result<(), Error> {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // send the request
+        let msg = SerialMsg::new(
+            SerialMsgType::Request,
+            SerialMsgFunction::SerialApiSoftReset,
+            vec![],
+        );
+        self.send_msg(&msg)?;
+
+        // the reset has no response, only the ACK answer, which is lost
+        // when the stick leaves the USB bus right away
+        match self.read_single_msg_rty(&5) {
+            Ok(ref m) if m.header != SerialMsgHeader::ACK => {
+                return Err(Error::new(
+                    ErrorKind::Io(StdErrorKind::InvalidData),
+                    "The driver refused the data - No ACK package",
+                ));
+            }
+            _ => {}
+        }
+
+        self.wait_started()
+    }
+
+    fn start_function(&mut self, function: SerialMsgFunction, data: &[u8]) -> Result<u8, Error> {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // the callback id is the last byte of the request
+        let callback_id = self.get_next_msg_id();
+        let mut data = data.to_vec();
+        data.push(callback_id);
+        self.send_msg(&SerialMsg::new(SerialMsgType::Request, function, data))?;
+
+        // the function has no response, the results are sent as callbacks
+        let m = self.read_single_msg_rty(&5)?;
+        if m.header != SerialMsgHeader::ACK {
+            return Err(Error::new(
+                ErrorKind::Io(StdErrorKind::InvalidData),
+                "The driver refused the data - No ACK package",
+            ));
+        }
+
+        Ok(callback_id)
+    }
+
+    fn read_memory(&mut self, offset: u16, length: u8) -> Result<Vec<u8>, Error> {
+        let data = self
+            .request(
+                SerialMsgFunction::MemoryGetBuffer,
+                vec![(offset >> 8) as u8, offset as u8, length],
+            )?
+            .data;
+
+        if data.len() != length as usize {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "The ZWave message has a wrong format",
+            ));
+        }
+
+        Ok(data)
+    }
+
+    fn update_firmware<F>(&mut self, image: &[u8], progress: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, usize),
+    {
+        // check the image, before the controller leaves the serial api
+        bootloader::check_image(image)?;
+
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // restart the controller into the bootloader, the ACK is
+        // skipped together with the bootloader menu
+        let msg = SerialMsg::new(
+            SerialMsgType::Request,
+            SerialMsgFunction::EnterBootloader,
+            vec![],
+        );
+        self.send_msg(&msg)?;
+
+        bootloader::upload(&mut self.port, image, progress)?;
+
+        // wait for the start of the new firmware and drop its messages
+        thread::sleep(Duration::from_millis(1500));
+        self.read_all_msg()?;
+
+        Ok(())
+    }
+
+    fn get_node_generic_class<N>(&mut self, node_id: N) -> Result<GenericType, Error>
+    where
+        N: Into<u8>,
+    {
+        // extract the delivered type and return it
+        Ok(self.get_node_protocol_info(node_id)?.generic)
+    }
+
+    fn get_node_protocol_info<N>(&mut self, node_id: N) -> Result<NodeProtocolInfo, Error>
+    where
+        N: Into<u8>,
+    {
+        // read all messages to clean the driver pipe
+        self.read_all_msg()?;
+
+        // create the serial message
+        let msg = SerialMsg::new(
+            SerialMsgType::Request,
+            SerialMsgFunction::GetNodeProtocolInfo,
+            vec![node_id.into()],
+        );
+
+        // send the value
+        self.send_msg(&msg)?;
+
+        // check if the first message has the ACK answer
+        match self.read_single_msg_rty(&5) {
+            Err(e) => {
+                return Err(e);
+            }
+            Ok(m) => {
+                if m.header != SerialMsgHeader::ACK {
+                    return Err(Error::new(
+                        ErrorKind::Io(StdErrorKind::InvalidData),
+                        "The driver refused the data - No ACK package",
+                    ));
+                }
+            }
+        }
+
+        // read the second message and get the data
+        let msg = self.read_single_msg_rty(&10)?;
+
+        // parse the generic and specific type out of the answer
+        NodeProtocolInfo::parse(&msg.data)
+    }
+}
+
+impl fmt::Debug for SerialDriver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Z-Wave Driver {{path: {}}}", self.path)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SerialMsg {
+    pub header: SerialMsgHeader,
+    pub typ: SerialMsgType,
+    pub func: SerialMsgFunction,
+    pub data: Vec<u8>,
+}
+
+impl SerialMsg {
+    /// create a new message
+    pub fn new(typ: SerialMsgType, func: SerialMsgFunction, data: Vec<u8>) -> SerialMsg {
+        SerialMsg {
+            header: SerialMsgHeader::SOF,
+            typ,
+            func,
+            data,
+        }
+    }
+
+    // create a new message with only the header
+    pub fn new_header(header: SerialMsgHeader) -> SerialMsg {
+        SerialMsg {
+            header,
+            typ: SerialMsgType::Response,
+            func: SerialMsgFunction::None,
+            data: vec![],
+        }
+    }
+
+    /// Parse a `&[u8]` slice and try to convert it to a `Message`
+    pub fn parse(data: &[u8]) -> Result<SerialMsg, Error> {
+        // check if the data has a header
+        if data.is_empty() {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "No message delivered, at least a head is needed",
+            ));
+        }
+
+        // try to parse the header
+        let header = SerialMsgHeader::try_from(data[0])
+            .map_err(|_| Error::new(ErrorKind::UnknownZWave, "Unknown ZWave header detected"))?;
+
+        // return message if there is no start of frame header
+        if header != SerialMsgHeader::SOF {
+            return Ok(SerialMsg::new_header(header));
+        }
+
+        // check if the message is long enough for a SOF message
+        if data.len() < 5 {
+            return Err(Error::new(
+                ErrorKind::UnknownZWave,
+                "Data is too short for a ZWave message with SOF header",
+            ));
+        }
+
+        // check if the data is as long as the given length
+        if data[1] != (data.len() - 2) as u8 {
+            return Err(Error::new(ErrorKind::UnknownZWave, "The length of the message defined in the ZWave message didn't match with the actual length"));
+        }
+
+        // check if the checksum is right for the message
+        let expected = SerialMsg::checksum(&data[0..(data.len() - 1)]);
+        if expected != data[data.len() - 1] {
+            return Err(Error::FrameCorrupt {
+                expected,
+                got: data[data.len() - 1],
+            });
+        }
+
+        // try to parse the type
+        let typ = SerialMsgType::try_from(data[2])
+            .map_err(|_| Error::new(ErrorKind::UnknownZWave, "Unknown message type"))?;
+
+        // try to parse the function
+        let function = SerialMsgFunction::try_from(data[3])
+            .map_err(|_| Error::new(ErrorKind::UnknownZWave, "Unknown ZWave function detected"))?;
+
+        // create the message data array
+        let msg_data: &[u8] = if data.len() > 5 {
+            // when there is data extract it
+            &data[4..(data.len() - 1)]
+        } else {
+            // if not create a empty array
+            &[0; 0]
+        };
+
+        // create a new Message and return it
+        Ok(SerialMsg::new(typ, function, msg_data.to_vec()))
+    }
+
+    /// return the command as Vec<u8>
+    pub fn get_command(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.data.len() + 5);
+        self.write_command(&mut buf);
+        buf
+    }
+
+    /// Write the command into the buffer, which is cleared first.
+    pub fn write_command(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+
+        // only create a full command if the header defines it
+        if self.header != SerialMsgHeader::SOF {
+            buf.push(self.header as u8);
+            return;
+        }
+
+        // create the header, length, typ and ZWave function
+        buf.extend_from_slice(&[
+            self.header as u8,
+            (self.data.len() + 3) as u8,
+            self.typ as u8,
+            self.func as u8,
+        ]);
+
+        // append the data
+        buf.extend_from_slice(&self.data);
+
+        // calc checksum
+        let cs = SerialMsg::checksum(buf);
+        buf.push(cs);
+    }
+
+    /// Return a Vec<u8> into a String in a hex format.
+    pub fn to_hex(data: &[u8]) -> String {
+        let mut out = String::new();
+
+        for byte in data {
+            out.push_str(&format!("{:#X} ", byte));
+        }
+
+        out
+    }
+
+    /// return the message as string in hex format
+    pub fn get_hex(&self) -> String {
+        SerialMsg::to_hex(&self.get_command())
+    }
+
+    /// Returns the checksum for the given vector
+    pub fn checksum(data: &[u8]) -> u8 {
+        let mut ret: u8 = 0xFF;
+
+        for byte in data.iter().skip(1) {
+            ret ^= byte;
+        }
+
+        ret
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+/// List of the ZWave start header
+#[repr(u8)]
+pub enum SerialMsgHeader {
+    SOF = 0x01, // Start of Frame
+    ACK = 0x06, // Message Accepted
+    NAK = 0x15, // Message not Accepted
+    CAN = 0x18, // Channel - Resend Request
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+/// List of different ZWave command types (rx/tx)
+#[repr(u8)]
+pub enum SerialMsgType {
+    Request = 0x00,
+    Response = 0x01,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+/// List of different ZWave transmission types
+#[repr(u8)]
+pub enum SerialTransmissionType {
+    ACK = 0x01,
+    LowPower = 0x02,
+    AutoRoute = 0x04,
+    Explore = 0x20,
+    Direct = 0x25,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, num_enum::TryFromPrimitive)]
+/// List of all available ZWave functions
+#[repr(u8)]
+pub enum SerialMsgFunction {
+    None = 0x00,
+    DiscoveryNodes = 0x02,
+    SerialApiApplNodeInformation = 0x03,
+    ApplicationCommandHandler = 0x04,
+    GetControllerCapabilities = 0x05,
+    SerialApiSetTimeouts = 0x06,
+    SerialGetCapabilities = 0x07,
+    SerialApiSoftReset = 0x08,
+    SerialApiStarted = 0x0a,
+    SetRFReceiveMode = 0x10,
+    SetSleepMode = 0x11,
+    SendNodeInformation = 0x12,
+    SendData = 0x13,
+    SendDataMulti = 0x14,
+    GetVersion = 0x15,
+    SendDataAbort = 0x16,
+    RFPowerLevelSet = 0x17,
+    SendDataMeta = 0x18,
+    MemoryGetId = 0x20,
+    MemoryGetByte = 0x21,
+    MemoryPutByte = 0x22,
+    MemoryGetBuffer = 0x23, // todo recheck
+    MemoryPutBuffer = 0x24,
+    EnterBootloader = 0x27,
+    // ReadMemory = 0x23, todo recheck
+    ClockSet = 0x30,
+    ClockGet = 0x31,
+    ClockCompare = 0x32,
+    RtcTimerCreate = 0x33,
+    RtcTimerRead = 0x34,
+    RtcTimerDelete = 0x35,
+    RtcTimerCall = 0x36,
+    GetNodeProtocolInfo = 0x41,
+    SetDefault = 0x42,
+    ReplicationCommandComplete = 0x44,
+    ReplicationSendData = 0x45,
+    AssignReturnRoute = 0x46,
+    DeleteReturnRoute = 0x47,
+    RequestNodeNeighborUpdate = 0x48,
+    ApplicationUpdate = 0x49,
+    AddNodeToNetwork = 0x4a,
+    RemoveNodeFromNetwork = 0x4b,
+    CreateNewPrimary = 0x4c,
+    ControllerChange = 0x4d,
+    SetLearnMode = 0x50,
+    AssignSucReturnRoute = 0x51,
+    EnableSuc = 0x52,
+    RequestNetworkUpdate = 0x53,
+    SetSucNodeId = 0x54,
+    DeleteSucReturnRoute = 0x55,
+    GetSucNodeId = 0x56,
+    SendSucId = 0x57,
+    RediscoveryNeeded = 0x59,
+    RequestNodeInfo = 0x60,
+    RemoveFailedNodeId = 0x61,
+    IsFailedNode = 0x62,
+    ReplaceFailedNode = 0x63,
+    TimerStart = 0x70,
+    TimerRestart = 0x71,
+    TimerCancel = 0x72,
+    TimerCall = 0x73,
+    GetRoutingTableLine = 0x80,
+    GetTXCounter = 0x81,
+    ResetTXCounter = 0x82,
+    StoreNodeInfo = 0x83,
+    StoreHomeId = 0x84,
+    LockRouteResponse = 0x90,
+    SendDataRouteDemo = 0x91,
+    GetLastWorkingRoute = 0x92,
+    SetLastWorkingRoute = 0x93,
+    SerialApiTest = 0x95,
+    SerialApiSlaveNodeInfo = 0xa0,
+    ApplicationSlaveCommandHandler = 0xa1,
+    SendSlaveNodeInfo = 0xa2,
+    SendSlaveData = 0xa3,
+    SetSlaveLearnMode = 0xa4,
+    GetVirtualNodes = 0xa5,
+    IsVirtualNode = 0xa6,
+    SetPromiscuousMode = 0xd0,
+}
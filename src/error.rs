@@ -2,6 +2,8 @@
 //!
 //! These error type is compatible with the rust standard io `ErrorKind`.
 
+use crate::defs::{CommandClass, TransmitStatus};
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Categories of errors that can occur when interacting with z-Wave.
@@ -30,53 +32,185 @@ pub enum ErrorKind {
     Io(std::io::ErrorKind),
 }
 
+/// The stage of a transmission at which no answer arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// The controller didn't acknowledge the frame
+    Ack,
+    /// The controller didn't respond to the function
+    Response,
+    /// The controller didn't report the transmission to the node
+    Callback,
+    /// The node didn't answer
+    Report,
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str(match *self {
+            Stage::Ack => "acknowledge",
+            Stage::Response => "response",
+            Stage::Callback => "callback",
+            Stage::Report => "report",
+        })
+    }
+}
+
 /// An error type for Z-Wave operations.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Error {
-    kind: ErrorKind,
-    description: String,
+///
+/// The variants describe the cause of the failure, the `ErrorKind` of
+/// each variant can be read with `kind()`.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// No answer arrived at the given stage
+    Timeout { stage: Stage },
+
+    /// The checksum of a received frame didn't match
+    FrameCorrupt { expected: u8, got: u8 },
+
+    /// The controller couldn't deliver the message to the node
+    TransmitFailed(TransmitStatus),
+
+    /// The node doesn't support the command class
+    UnsupportedCommandClass(CommandClass),
+
+    /// The node is busy and asked to send the request again, after the wait
+    /// time when it's given
+    Busy { wait: Option<std::time::Duration> },
+
+    /// The node rejected the request
+    Rejected,
+
+    /// A message of the command class couldn't be parsed
+    ParseError { cc: CommandClass, reason: String },
+
+    /// The controller answered with an unknown or unexpected message
+    Protocol(String),
+
+    /// The controller is not available
+    NoController(String),
+
+    /// A parameter was incorrect
+    InvalidInput(String),
+
+    /// This functionallity is not implemented
+    NotImplemented(String),
+
+    /// An I/O error occured
+    Io(std::sync::Arc<std::io::Error>),
 }
 
 impl Error {
     /// Create a new error with a given type and description
     pub fn new<T: Into<String>>(kind: ErrorKind, description: T) -> Self {
-        Error {
-            kind: kind,
-            description: description.into(),
+        let description = description.into();
+
+        match kind {
+            ErrorKind::NoController => Error::NoController(description),
+            ErrorKind::InvalidInput => Error::InvalidInput(description),
+            ErrorKind::UnknownZWave => Error::Protocol(description),
+            ErrorKind::NotImplemented => Error::NotImplemented(description),
+            ErrorKind::Io(kind) => std::io::Error::new(kind, description).into(),
+        }
+    }
+
+    /// Create a new error for a message of the command class, which couldn't be parsed
+    pub fn parse<C, T>(cc: C, reason: T) -> Self
+    where
+        C: Into<CommandClass>,
+        T: Into<String>,
+    {
+        Error::ParseError {
+            cc: cc.into(),
+            reason: reason.into(),
         }
     }
 
     /// Returns the corresponding `ErrorKind` for this error.
     pub fn kind(&self) -> ErrorKind {
-        self.kind
+        match *self {
+            Error::Timeout { .. } => ErrorKind::Io(std::io::ErrorKind::TimedOut),
+            Error::FrameCorrupt { .. } => ErrorKind::UnknownZWave,
+            Error::TransmitFailed(_) => ErrorKind::UnknownZWave,
+            Error::UnsupportedCommandClass(_) => ErrorKind::NotImplemented,
+            Error::Busy { .. } | Error::Rejected => ErrorKind::UnknownZWave,
+            Error::ParseError { .. } => ErrorKind::UnknownZWave,
+            Error::Protocol(_) => ErrorKind::UnknownZWave,
+            Error::NoController(_) => ErrorKind::NoController,
+            Error::InvalidInput(_) => ErrorKind::InvalidInput,
+            Error::NotImplemented(_) => ErrorKind::NotImplemented,
+            Error::Io(ref e) => ErrorKind::Io(e.kind()),
+        }
+    }
+}
+
+impl PartialEq for Error {
+    /// Errors are equal, when they have the same cause and description
+    fn eq(&self, other: &Error) -> bool {
+        match (self, other) {
+            (Error::Io(a), Error::Io(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            (Error::Io(_), _) | (_, Error::Io(_)) => false,
+            _ => self.kind() == other.kind() && self.to_string() == other.to_string(),
+        }
     }
 }
 
+impl Eq for Error {}
+
 impl std::fmt::Display for Error {
     /// How to print the error
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::result::Result<(), std::fmt::Error> {
-        fmt.write_str(&self.description)
+        match *self {
+            Error::Timeout { stage } => write!(fmt, "No {} received in time", stage),
+            Error::FrameCorrupt { expected, got } => write!(
+                fmt,
+                "The checksum didn't match to the message, expected {:#04X} got {:#04X}",
+                expected, got
+            ),
+            Error::TransmitFailed(status) => {
+                write!(fmt, "The message couldn't be transmitted: {:?}", status)
+            }
+            Error::UnsupportedCommandClass(cc) => {
+                write!(fmt, "The node doesn't support the command class {:?}", cc)
+            }
+            Error::Busy { wait: Some(wait) } => write!(
+                fmt,
+                "The node is busy, try again in {} seconds",
+                wait.as_secs()
+            ),
+            Error::Busy { wait: None } => fmt.write_str("The node is busy, try again later"),
+            Error::Rejected => fmt.write_str("The node rejected the request"),
+            Error::ParseError { cc, ref reason } => write!(fmt, "{:?}: {}", cc, reason),
+            Error::Protocol(ref description)
+            | Error::NoController(ref description)
+            | Error::InvalidInput(ref description)
+            | Error::NotImplemented(ref description) => fmt.write_str(description),
+            Error::Io(ref e) => write!(fmt, "{}", e),
+        }
     }
 }
 
 impl std::error::Error for Error {
-    /// Get the error description
-    fn description(&self) -> &str {
-        &self.description
+    /// The I/O error which caused this error
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(&**e),
+            _ => None,
+        }
     }
 }
 
 impl From<std::io::Error> for Error {
     /// Transform std io errors to this crate error
     fn from(io_error: std::io::Error) -> Error {
-        Error::new(ErrorKind::Io(io_error.kind()), format!("{}", io_error))
+        Error::Io(std::sync::Arc::new(io_error))
     }
 }
 
 impl From<Error> for std::io::Error {
     /// Transform this error to a std io error
     fn from(error: Error) -> std::io::Error {
-        let kind = match error.kind {
+        let kind = match error.kind() {
             ErrorKind::NoController => std::io::ErrorKind::NotFound,
             ErrorKind::InvalidInput => std::io::ErrorKind::InvalidInput,
             ErrorKind::UnknownZWave => std::io::ErrorKind::InvalidData,
@@ -84,21 +218,47 @@ impl From<Error> for std::io::Error {
             ErrorKind::Io(kind) => kind,
         };
 
-        std::io::Error::new(kind, error.description)
+        std::io::Error::new(kind, error)
     }
 }
 
 impl From<serial::Error> for Error {
     /// Transform from a serial error
     fn from(ser_error: serial::Error) -> Error {
-        use std::error::Error;
-
         let kind = match ser_error.kind() {
             serial::ErrorKind::NoDevice => ErrorKind::NoController,
             serial::ErrorKind::InvalidInput => ErrorKind::InvalidInput,
             serial::ErrorKind::Io(kind) => ErrorKind::Io(kind),
         };
 
-        crate::error::Error::new(kind, ser_error.description())
+        crate::error::Error::new(kind, ser_error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        // the kinds are mapped to the variants
+        let error = Error::new(ErrorKind::UnknownZWave, "Unknown ZWave header detected");
+        assert_eq!(
+            error,
+            Error::Protocol(String::from("Unknown ZWave header detected"))
+        );
+        assert_eq!(error.kind(), ErrorKind::UnknownZWave);
+
+        // timeouts are I/O timeouts
+        let error = Error::Timeout {
+            stage: Stage::Callback,
+        };
+        assert_eq!(error.kind(), ErrorKind::Io(std::io::ErrorKind::TimedOut));
+        assert_eq!(error.to_string(), "No callback received in time");
+
+        // I/O errors keep their source
+        let error = Error::new(ErrorKind::Io(std::io::ErrorKind::BrokenPipe), "closed");
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(std::error::Error::source(&Error::parse(CommandClass::METER, "short")).is_none());
     }
 }
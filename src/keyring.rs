@@ -0,0 +1,654 @@
+//! Network keys
+//!
+//! The `Keyring` holds the S0 network key and the three S2 keys of the
+//! network. The keys are generated once and need to survive a restart,
+//! otherwise the secure nodes can't be reached anymore. They're kept by a
+//! `KeyStore`, either an `EncryptedFile` or callbacks of the application.
+//! The `EncryptedFile` is built with the `keyfile` feature and encrypts the
+//! keys with AES-256-GCM and a key derived with PBKDF2-HMAC-SHA256.
+//!
+//! The export uses the names of the other controllers, so the keys can be
+//! moved to them:
+//!
+//! `{"S0_Legacy": "hex", "S2_Unauthenticated": "hex", ...}`
+
+use crate::defs::SecurityClass;
+use crate::error::{Error, ErrorKind, Result};
+
+#[cfg(feature = "keyfile")]
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+#[cfg(feature = "keyfile")]
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use std::fmt;
+#[cfg(feature = "keyfile")]
+use std::fs;
+use std::io;
+#[cfg(feature = "keyfile")]
+use std::io::Write;
+#[cfg(feature = "keyfile")]
+use std::path::{Path, PathBuf};
+
+/// The first bytes of an encrypted keyring file
+#[cfg(feature = "keyfile")]
+const MAGIC: &[u8; 4] = b"RZWK";
+
+/// The version of the encrypted keyring file
+#[cfg(feature = "keyfile")]
+const FILE_VERSION: u8 = 2;
+
+/// The length of the random salt of the encrypted file
+#[cfg(feature = "keyfile")]
+const SALT_LENGTH: usize = 16;
+
+/// The length of the random nonce of the encrypted file
+#[cfg(feature = "keyfile")]
+const NONCE_LENGTH: usize = 12;
+
+/// The length of the authentication tag of the encrypted file
+#[cfg(feature = "keyfile")]
+const TAG_LENGTH: usize = 16;
+
+/// The rounds of the key derivation from the passphrase
+#[cfg(feature = "keyfile")]
+const ITERATIONS: u32 = 600_000;
+
+/// The security classes with a key and their names in the export
+const KEY_NAMES: [(SecurityClass, &str); 4] = [
+    (SecurityClass::S0, "S0_Legacy"),
+    (SecurityClass::S2Unauthenticated, "S2_Unauthenticated"),
+    (SecurityClass::S2Authenticated, "S2_Authenticated"),
+    (SecurityClass::S2AccessControl, "S2_AccessControl"),
+];
+
+/// A 128 bit network key
+#[derive(Copy, Clone, PartialEq)]
+pub struct NetworkKey(pub [u8; 16]);
+
+impl NetworkKey {
+    /// Parse the key out of 32 hex digits
+    pub fn parse(hex: &str) -> Result<NetworkKey> {
+        let hex = hex.trim();
+        if hex.len() != 32 || !hex.is_ascii() {
+            return Err(invalid("A network key needs to have 32 hex digits"));
+        }
+
+        let mut key = [0u8; 16];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| invalid(format!("'{}' is not a hex number", hex)))?;
+        }
+
+        Ok(NetworkKey(key))
+    }
+
+    /// Returns the key as 32 hex digits
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+}
+
+/// The key is never printed, so it doesn't end up in a log
+impl fmt::Debug for NetworkKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("NetworkKey(..)")
+    }
+}
+
+/// The network keys of all security classes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Keyring {
+    keys: Vec<(SecurityClass, NetworkKey)>,
+    /// The replaced keys, the nodes included with them need them until
+    /// they are included again
+    retired: Vec<(SecurityClass, NetworkKey)>,
+}
+
+impl Keyring {
+    /// Create a keyring without keys
+    pub fn new() -> Keyring {
+        Keyring::default()
+    }
+
+    /// Generate the keys of all security classes with the random source of
+    /// the operating system.
+    pub fn generate() -> Result<Keyring> {
+        Keyring::generate_with(os_random)
+    }
+
+    /// Generate the keys of all security classes with the given random
+    /// source, which fills the buffer.
+    pub fn generate_with<F>(mut fill: F) -> Result<Keyring>
+    where
+        F: FnMut(&mut [u8]) -> Result<()>,
+    {
+        let mut keyring = Keyring::new();
+        for (class, _) in KEY_NAMES.iter() {
+            let mut key = [0u8; 16];
+            fill(&mut key)?;
+            keyring.set_key(*class, NetworkKey(key))?;
+        }
+
+        Ok(keyring)
+    }
+
+    /// Returns the key of the security class
+    pub fn key(&self, class: SecurityClass) -> Option<NetworkKey> {
+        self.keys.iter().find(|k| k.0 == class).map(|k| k.1)
+    }
+
+    /// Set the key of the security class, a set key is replaced
+    pub fn set_key(&mut self, class: SecurityClass, key: NetworkKey) -> Result<()> {
+        if !class.is_secure() {
+            return Err(invalid("The security class None has no key"));
+        }
+
+        self.keys.retain(|k| k.0 != class);
+        self.keys.push((class, key));
+        Ok(())
+    }
+
+    /// Replace the key of the security class with a new one, generated by
+    /// the random source of the operating system. The old key is retired.
+    pub fn rotate(&mut self, class: SecurityClass) -> Result<NetworkKey> {
+        self.rotate_with(class, os_random)
+    }
+
+    /// Replace the key of the security class with a new one, generated by
+    /// the given random source. The old key is retired.
+    pub fn rotate_with<F>(&mut self, class: SecurityClass, mut fill: F) -> Result<NetworkKey>
+    where
+        F: FnMut(&mut [u8]) -> Result<()>,
+    {
+        let mut key = [0u8; 16];
+        fill(&mut key)?;
+
+        if let Some(old) = self.key(class) {
+            self.retired.push((class, old));
+        }
+        self.set_key(class, NetworkKey(key))?;
+
+        Ok(NetworkKey(key))
+    }
+
+    /// Returns the retired keys of the security class, the oldest first
+    pub fn retired(&self, class: SecurityClass) -> Vec<NetworkKey> {
+        self.retired
+            .iter()
+            .filter(|k| k.0 == class)
+            .map(|k| k.1)
+            .collect()
+    }
+
+    /// Forget the retired keys of the security class, when all nodes use
+    /// the new key
+    pub fn forget_retired(&mut self, class: SecurityClass) {
+        self.retired.retain(|k| k.0 != class);
+    }
+
+    /// Export the keys as JSON object with the names of the other controllers
+    pub fn export(&self) -> String {
+        let keys: Vec<String> = KEY_NAMES
+            .iter()
+            .filter_map(|(class, name)| {
+                self.key(*class)
+                    .map(|k| format!("  \"{}\": \"{}\"", name, k.to_hex()))
+            })
+            .collect();
+
+        format!("{{\n{}\n}}", keys.join(",\n"))
+    }
+
+    /// Import the keys of an export
+    pub fn import(export: &str) -> Result<Keyring> {
+        let mut keyring = Keyring::new();
+
+        for (class, name) in KEY_NAMES.iter() {
+            if let Some(hex) = json_value(export, name) {
+                keyring.set_key(*class, NetworkKey::parse(hex)?)?;
+            }
+        }
+
+        Ok(keyring)
+    }
+
+    /// Encrypt the keyring with the passphrase
+    #[cfg(feature = "keyfile")]
+    pub fn encrypt(&self, passphrase: &str) -> Result<Vec<u8>> {
+        self.encrypt_rounds(passphrase, ITERATIONS)
+    }
+
+    /// Decrypt a keyring, which was encrypted with the passphrase
+    #[cfg(feature = "keyfile")]
+    pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Keyring> {
+        // _________________________________________________________________
+        // | Magic (4) | Version (1) | Iterations (4) | Salt (16)           |
+        // | Nonce (12) | Keys ... | Tag (16)                              |
+        // -----------------------------------------------------------------
+        let header = MAGIC.len() + 5 + SALT_LENGTH + NONCE_LENGTH;
+        if data.len() < header + TAG_LENGTH || &data[..4] != MAGIC {
+            return Err(invalid("The data is no encrypted keyring"));
+        }
+        if data[4] != FILE_VERSION {
+            return Err(Error::new(
+                ErrorKind::NotImplemented,
+                format!("The keyring version {} is unknown", data[4]),
+            ));
+        }
+
+        let iterations = u32::from_be_bytes([data[5], data[6], data[7], data[8]]);
+        if iterations == 0 {
+            return Err(invalid("The keyring is corrupt"));
+        }
+        let salt = &data[9..9 + SALT_LENGTH];
+        let nonce = &data[9 + SALT_LENGTH..header];
+
+        // the header is authenticated with the keys
+        let plain = cipher(passphrase, salt, iterations)
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: &data[header..],
+                    aad: &data[..header],
+                },
+            )
+            .map_err(|_| invalid("The passphrase is wrong or the keyring was changed"))?;
+        let plain = String::from_utf8(plain).map_err(|_| invalid("The keyring is corrupt"))?;
+
+        Keyring::from_lines(&plain)
+    }
+
+    /// Encrypt the keyring with the given rounds of the key derivation
+    #[cfg(feature = "keyfile")]
+    fn encrypt_rounds(&self, passphrase: &str, iterations: u32) -> Result<Vec<u8>> {
+        let mut salt = [0u8; SALT_LENGTH];
+        os_random(&mut salt)?;
+        let mut nonce = [0u8; NONCE_LENGTH];
+        os_random(&mut nonce)?;
+
+        self.encrypt_with(passphrase, iterations, salt, nonce)
+    }
+
+    /// Encrypt the keyring with the given rounds, salt and nonce
+    #[cfg(feature = "keyfile")]
+    fn encrypt_with(
+        &self,
+        passphrase: &str,
+        iterations: u32,
+        salt: [u8; SALT_LENGTH],
+        nonce: [u8; NONCE_LENGTH],
+    ) -> Result<Vec<u8>> {
+        let mut data = MAGIC.to_vec();
+        data.push(FILE_VERSION);
+        data.extend_from_slice(&iterations.to_be_bytes());
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+
+        let content = self.to_lines();
+        let encrypted = cipher(passphrase, &salt, iterations)
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: content.as_bytes(),
+                    aad: &data,
+                },
+            )
+            .map_err(|_| Error::new(ErrorKind::UnknownZWave, "The keyring can't be encrypted"))?;
+        data.extend_from_slice(&encrypted);
+
+        Ok(data)
+    }
+
+    /// Write the active and the retired keys line by line, like
+    /// `S0_Legacy=hex` and `retired.S0_Legacy=hex`
+    fn to_lines(&self) -> String {
+        let name = |class: SecurityClass| {
+            KEY_NAMES
+                .iter()
+                .find(|n| n.0 == class)
+                .map(|n| n.1)
+                .unwrap_or_default()
+        };
+
+        let active = self
+            .keys
+            .iter()
+            .map(|(c, k)| format!("{}={}\n", name(*c), k.to_hex()));
+        let retired = self
+            .retired
+            .iter()
+            .map(|(c, k)| format!("retired.{}={}\n", name(*c), k.to_hex()));
+
+        active.chain(retired).collect()
+    }
+
+    /// Read the keys, which were written by `to_lines`
+    fn from_lines(lines: &str) -> Result<Keyring> {
+        let mut keyring = Keyring::new();
+
+        for line in lines.lines().filter(|l| !l.trim().is_empty()) {
+            let mut parts = line.splitn(2, '=');
+            let (name, hex) = match (parts.next(), parts.next()) {
+                (Some(name), Some(hex)) => (name, hex),
+                _ => return Err(invalid("The keyring is corrupt")),
+            };
+
+            let (retired, name) = match name.strip_prefix("retired.") {
+                Some(name) => (true, name),
+                None => (false, name),
+            };
+            let class = KEY_NAMES
+                .iter()
+                .find(|n| n.1 == name)
+                .map(|n| n.0)
+                .ok_or_else(|| invalid(format!("The key '{}' is unknown", name)))?;
+            let key = NetworkKey::parse(hex)?;
+
+            if retired {
+                keyring.retired.push((class, key));
+            } else {
+                keyring.set_key(class, key)?;
+            }
+        }
+
+        Ok(keyring)
+    }
+
+    /// Load the keyring of the store. When the store is empty, new keys
+    /// are generated and stored.
+    pub fn load_or_generate<S>(store: &mut S) -> Result<Keyring>
+    where
+        S: KeyStore + ?Sized,
+    {
+        if let Some(keyring) = store.load()? {
+            return Ok(keyring);
+        }
+
+        let keyring = Keyring::generate()?;
+        store.store(&keyring)?;
+        Ok(keyring)
+    }
+}
+
+/// Keeps the keyring between the starts of the application.
+pub trait KeyStore {
+    /// Load the keyring, `None` when no keyring was stored yet
+    fn load(&mut self) -> Result<Option<Keyring>>;
+
+    /// Store the keyring and replace the stored one
+    fn store(&mut self, keyring: &Keyring) -> Result<()>;
+}
+
+/// Stores the keyring in a file, encrypted with a passphrase.
+#[cfg(feature = "keyfile")]
+#[derive(Debug, Clone)]
+pub struct EncryptedFile {
+    path: PathBuf,
+    passphrase: String,
+    iterations: u32,
+}
+
+#[cfg(feature = "keyfile")]
+impl EncryptedFile {
+    /// Create a store for the file, which is encrypted with the passphrase
+    pub fn new<P, S>(path: P, passphrase: S) -> EncryptedFile
+    where
+        P: Into<PathBuf>,
+        S: Into<String>,
+    {
+        EncryptedFile {
+            path: path.into(),
+            passphrase: passphrase.into(),
+            iterations: ITERATIONS,
+        }
+    }
+}
+
+#[cfg(feature = "keyfile")]
+impl KeyStore for EncryptedFile {
+    fn load(&mut self) -> Result<Option<Keyring>> {
+        match fs::read(&self.path) {
+            Ok(data) => Keyring::decrypt(&data, &self.passphrase).map(Some),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The keyring is written to a temporary file, which only the owner can
+    /// read, and replaces the stored one afterwards. A crash never leaves a
+    /// half written keyring behind.
+    fn store(&mut self, keyring: &Keyring) -> Result<()> {
+        let data = keyring.encrypt_rounds(&self.passphrase, self.iterations)?;
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+
+        let mut file = create_private(&temp)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Create the file, which only the owner can read and write
+#[cfg(all(feature = "keyfile", unix))]
+fn create_private(path: &Path) -> Result<fs::File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // the mode only applies to new files
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+/// Create the file, which only the owner can read and write
+#[cfg(all(feature = "keyfile", not(unix)))]
+fn create_private(path: &Path) -> Result<fs::File> {
+    Ok(fs::File::create(path)?)
+}
+
+type LoadKeys = dyn FnMut() -> Result<Option<String>> + Send;
+type StoreKeys = dyn FnMut(&str) -> Result<()> + Send;
+
+/// Hands the keys of the keyring as text to the application, which keeps
+/// them in its own secure storage.
+pub struct CallbackStore {
+    load: Box<LoadKeys>,
+    store: Box<StoreKeys>,
+}
+
+impl CallbackStore {
+    /// Create a store, which loads and stores the keys with the callbacks
+    pub fn new<L, S>(load: L, store: S) -> CallbackStore
+    where
+        L: FnMut() -> Result<Option<String>> + Send + 'static,
+        S: FnMut(&str) -> Result<()> + Send + 'static,
+    {
+        CallbackStore {
+            load: Box::new(load),
+            store: Box::new(store),
+        }
+    }
+}
+
+impl KeyStore for CallbackStore {
+    fn load(&mut self) -> Result<Option<Keyring>> {
+        match (self.load)()? {
+            Some(lines) => Keyring::from_lines(&lines).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn store(&mut self, keyring: &Keyring) -> Result<()> {
+        (self.store)(&keyring.to_lines())
+    }
+}
+
+impl fmt::Debug for CallbackStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CallbackStore")
+    }
+}
+
+/// Fill the buffer with the random source of the operating system
+pub(crate) fn os_random(buf: &mut [u8]) -> Result<()> {
+    getrandom::getrandom(buf).map_err(io::Error::from)?;
+    Ok(())
+}
+
+/// Returns the string value of the key in a flat JSON object
+fn json_value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let start = json.find(&format!("\"{}\"", key))? + key.len() + 2;
+    let rest = json[start..].trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+
+    rest.find('"').map(|end| &rest[..end])
+}
+
+/// Derive the key from the passphrase and create the cipher with it
+#[cfg(feature = "keyfile")]
+fn cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+
+    Aes256Gcm::new(&key.into())
+}
+
+/// The error for an invalid key or keyring
+fn invalid<S: Into<String>>(reason: S) -> Error {
+    Error::new(ErrorKind::InvalidInput, reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A random source, which counts up
+    fn counter() -> impl FnMut(&mut [u8]) -> Result<()> {
+        let mut next = 0u8;
+        move |buf| {
+            for byte in buf.iter_mut() {
+                *byte = next;
+                next = next.wrapping_add(1);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        let keyring = Keyring::generate_with(counter()).unwrap();
+        assert_eq!(
+            keyring.key(SecurityClass::S0).map(|k| k.to_hex()),
+            Some("000102030405060708090A0B0C0D0E0F".to_string())
+        );
+
+        let export = keyring.export();
+        assert!(export.contains("\"S2_AccessControl\": \"303132333435363738393A3B3C3D3E3F\""));
+        assert_eq!(Keyring::import(&export).unwrap(), keyring);
+
+        assert!(Keyring::import("{\"S0_Legacy\": \"0011\"}").is_err());
+        assert!(NetworkKey::parse("XX0102030405060708090A0B0C0D0E0F").is_err());
+    }
+
+    #[test]
+    fn test_generate() {
+        // the random source of the operating system is available everywhere
+        let keyring = Keyring::generate().unwrap();
+        let s0 = keyring.key(SecurityClass::S0).unwrap();
+        let s2 = keyring.key(SecurityClass::S2Unauthenticated).unwrap();
+        assert_ne!(s0, s2);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut keyring = Keyring::generate_with(counter()).unwrap();
+        let old = keyring.key(SecurityClass::S2Authenticated).unwrap();
+
+        let new = keyring
+            .rotate_with(SecurityClass::S2Authenticated, |buf| {
+                buf.copy_from_slice(&[0xAA; 16]);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(keyring.key(SecurityClass::S2Authenticated), Some(new));
+        assert_eq!(keyring.retired(SecurityClass::S2Authenticated), vec![old]);
+
+        keyring.forget_retired(SecurityClass::S2Authenticated);
+        assert!(keyring.retired(SecurityClass::S2Authenticated).is_empty());
+        assert!(keyring
+            .set_key(SecurityClass::None, NetworkKey([0; 16]))
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "keyfile")]
+    fn test_encryption() {
+        let mut keyring = Keyring::generate_with(counter()).unwrap();
+        keyring.rotate_with(SecurityClass::S0, counter()).unwrap();
+
+        // a few rounds keep the test fast
+        let data = keyring
+            .encrypt_with("secret", 1_000, [0x42; SALT_LENGTH], [0x24; NONCE_LENGTH])
+            .unwrap();
+        assert_eq!(&data[..9], b"RZWK\x02\x00\x00\x03\xE8");
+        assert_eq!(Keyring::decrypt(&data, "secret").unwrap(), keyring);
+
+        // the wrong passphrase and changed bytes are detected
+        assert!(Keyring::decrypt(&data, "wrong").is_err());
+        let mut changed = data.clone();
+        changed[60] ^= 0x01;
+        assert!(Keyring::decrypt(&changed, "secret").is_err());
+        let mut changed = data.clone();
+        changed[10] ^= 0x01;
+        assert!(Keyring::decrypt(&changed, "secret").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "keyfile", unix))]
+    fn test_encrypted_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("rzw-keyring-{}", std::process::id()));
+        let mut file = EncryptedFile::new(&path, "secret");
+        file.iterations = 1_000;
+        assert_eq!(file.load().unwrap(), None);
+
+        let keyring = Keyring::generate_with(counter()).unwrap();
+        file.store(&keyring).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(file.load().unwrap(), Some(keyring));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_callback_store() {
+        use std::sync::{Arc, Mutex};
+
+        let stored = Arc::new(Mutex::new(None));
+        let (load, store) = (stored.clone(), stored.clone());
+        let mut callbacks = CallbackStore::new(
+            move || Ok(load.lock().unwrap().clone()),
+            move |lines| {
+                *store.lock().unwrap() = Some(lines.to_string());
+                Ok(())
+            },
+        );
+
+        // the keys are generated once and loaded afterwards
+        let first = Keyring::load_or_generate(&mut callbacks).unwrap();
+        assert!(stored.lock().unwrap().is_some());
+        assert_eq!(Keyring::load_or_generate(&mut callbacks).unwrap(), first);
+    }
+}
@@ -11,10 +11,43 @@
 //! * FreeBSD (amd64)
 //! * OpenBSD (amd64)
 //! * Windows (x86_64)
+//!
 //! Compiling the `rzw` crate requires Rust 1.9 or later.
 //!
 //! ---
 //!
+//! ## Features
+//! * `serde` - Implements `Serialize` and `Deserialize` for the protocol types, to persist
+//!   them or exchange them as JSON.
+//! * `cli` - Builds the `zwave-cli` binary, to include and exclude nodes, read and set
+//!   values, heal the network, backup the controller or sniff the messages.
+//! * `json` - Adds `export_state` and `import_state` to the controller, to back up the
+//!   state of the network or hand it to other tools as a JSON document.
+//! * `http` - Adds the `HttpServer`, which exposes the controller over a REST interface
+//!   with JSON bodies, to build web dashboards on top of it.
+//! * `ffi` - Exports a C interface to open the controller, list the nodes, set and get
+//!   their values and receive their commands. The declarations are in `include/rzw.h`,
+//!   the shared library is built with `cargo build --release --features ffi`.
+//! * `python` - Builds the `rzw` Python module with the `Controller`, its nodes and the
+//!   received commands, to script the network from Python.
+//! * `dbus` - Adds the `DbusService`, which exposes the controller and its nodes as
+//!   `org.rzw.Controller1` and `org.rzw.Node1` objects on the D-Bus.
+//! * `cc-antitheft`, `cc-central-scene`, `cc-entry-control`, `cc-firmware-update`,
+//!   `cc-humidity`, `cc-meter`, `cc-powerlevel`, `cc-protection`, `cc-scene-controller`,
+//!   `cc-sound-switch`, `cc-thermostat`, `cc-user-code`, `cc-window-covering` - The
+//!   command classes, which can be left out to keep the binary small. All of them are
+//!   enabled by default, the health check needs `cc-powerlevel`.
+//! * `cc-security` - The S0 and S2 command classes with their crypto dependencies.
+//!   Enabled by default. Without it the securely included nodes can't be controlled and
+//!   their encrypted commands are dropped.
+//!
+//! The command classes of the interview, the switches, sensors and notifications,
+//! and the encapsulations are always built in.
+//! * `sim` - Adds the `SimNetwork`, a virtual network of scriptable nodes, which replaces
+//!   the driver to test applications on top of the controller without hardware.
+//!
+//! ---
+//!
 //! ## Usage
 //! Add `rzw` as a dependency in `Cargo.toml`:
 //!
@@ -30,7 +63,8 @@
 //!
 //! fn main() {
 //!     // Access the zwave network
-//!     let mut zwave = rzw::open("/dev/tty.usbmodem1411").unwrap();
+//!     let driver = rzw::open("/dev/tty.usbmodem1411").unwrap();
+//!     let mut zwave = rzw::basic::Controller::new(driver).unwrap();
 //!
 //!     // get all node ids
 //!     let nodes = zwave.nodes();
@@ -51,34 +85,21 @@
 #![allow(dead_code)]
 
 // load all internal dependencies, which are used
+pub mod basic;
+pub mod bootloader;
+pub mod cmds;
 pub mod defs;
 pub mod driver;
 pub mod error;
+pub mod keyring;
+pub mod payload;
+pub mod smartstart;
 
-pub fn open<P>(
-    path: P,
-) -> crate::error::Result<crate::driver::SerialDriver<Box<dyn serial::SerialPort>>>
+/// Open the serial port of the controller. The driver is handed to the
+/// `Controller`, the raw frames are sent with the `driver::FrameDriver`.
+pub fn open<P>(path: P) -> crate::error::Result<crate::driver::serial::SerialDriver>
 where
     P: Into<String>,
 {
-    // imports needed
-    use serial::prelude::*;
-
-    // open the serial port
-    let mut port = serial::open(&path.into())?;
-
-    // set the settings
-    port.reconfigure(&|settings| {
-        settings.set_baud_rate(serial::Baud115200)?;
-        settings.set_char_size(serial::Bits8);
-        settings.set_parity(serial::ParityNone);
-        settings.set_stop_bits(serial::Stop1);
-        settings.set_flow_control(serial::FlowHardware);
-        Ok(())
-    })?;
-
-    // set the timeout
-    port.set_timeout(std::time::Duration::from_millis(100))?;
-
-    Ok(crate::driver::SerialDriver::new(Box::new(port)))
+    crate::driver::serial::SerialDriver::new(path)
 }
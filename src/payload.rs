@@ -0,0 +1,169 @@
+//! Payload of a frame
+//!
+//! Z-Wave frames are short, so their payload is stored inline in a fixed
+//! buffer instead of a heap allocated vector. Creating, parsing and cloning
+//! a message doesn't allocate.
+
+use crate::error::{Error, ErrorKind};
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+
+/// The maximum amount of bytes a payload can hold, the data of the largest
+/// frame of the Serial API: the length byte counts up to 255 bytes, of which
+/// the type, the function and the checksum take three
+pub const MAX_PAYLOAD: usize = 252;
+
+/// Bytes of a frame, which are stored without a heap allocation.
+#[derive(Clone, Copy)]
+pub struct Payload {
+    len: u8,
+    buf: [u8; MAX_PAYLOAD],
+}
+
+impl Payload {
+    /// Create an empty payload.
+    pub fn new() -> Payload {
+        Payload {
+            len: 0,
+            buf: [0; MAX_PAYLOAD],
+        }
+    }
+
+    /// Append the byte.
+    pub fn push(&mut self, byte: u8) -> Result<(), Error> {
+        self.extend_from_slice(&[byte])
+    }
+
+    /// Append the data, nothing is appended when it doesn't fit.
+    pub fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), Error> {
+        let len = self.len as usize;
+        if len + data.len() > MAX_PAYLOAD {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The payload can't be longer than {} bytes, got {}",
+                    MAX_PAYLOAD,
+                    len + data.len()
+                ),
+            ));
+        }
+
+        self.buf[len..len + data.len()].copy_from_slice(data);
+        self.len += data.len() as u8;
+        Ok(())
+    }
+
+    /// Return the bytes of the payload.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len as usize]
+    }
+}
+
+impl Default for Payload {
+    fn default() -> Payload {
+        Payload::new()
+    }
+}
+
+impl Deref for Payload {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl AsRef<[u8]> for Payload {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Payload {
+    type Error = Error;
+
+    fn try_from(data: &'a [u8]) -> Result<Payload, Error> {
+        let mut payload = Payload::new();
+        payload.extend_from_slice(data)?;
+        Ok(payload)
+    }
+}
+
+impl From<Payload> for Vec<u8> {
+    fn from(payload: Payload) -> Vec<u8> {
+        payload.to_vec()
+    }
+}
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl PartialEq for Payload {
+    fn eq(&self, other: &Payload) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Payload {}
+
+impl PartialEq<[u8]> for Payload {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl PartialEq<Vec<u8>> for Payload {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl PartialEq<Payload> for Vec<u8> {
+    fn eq(&self, other: &Payload) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Payload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Payload, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = Vec::<u8>::deserialize(deserializer)?;
+        Payload::try_from(data.as_slice()).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity() {
+        let mut payload = Payload::try_from(&[0x20, 0x01][..]).unwrap();
+        payload.push(0xFF).unwrap();
+        assert_eq!(payload, vec![0x20, 0x01, 0xFF]);
+
+        assert!(payload.extend_from_slice(&[0; MAX_PAYLOAD]).is_err());
+        assert_eq!(payload.len(), 3);
+
+        assert!(Payload::try_from(&[0; MAX_PAYLOAD][..]).is_ok());
+        assert!(Payload::try_from(&[0; MAX_PAYLOAD + 1][..]).is_err());
+    }
+}
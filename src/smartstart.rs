@@ -0,0 +1,372 @@
+//! SmartStart provisioning
+//!
+//! Parses the QR codes and the DSK strings which are printed on the
+//! devices, into the provisioning entries of the SmartStart list. A device
+//! on this list is included automatically, as soon as it's powered on.
+//!
+//! The QR code contains only digits:
+//!
+//! `90, version, checksum, requested keys, DSK, [type, length, value]...`
+
+use crate::defs::SecurityClass;
+use crate::error::{Error, ErrorKind, Result};
+
+/// The lead-in of every Z-Wave QR code
+const LEAD_IN: &str = "90";
+
+/// The length of the fixed part of the QR code, up to the end of the DSK
+const HEADER_LENGTH: usize = 52;
+
+/// The Device Specific Key, which identifies a device and authenticates it
+/// during the S2 inclusion.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dsk(pub [u8; 16]);
+
+impl Dsk {
+    /// Parse the DSK string of eight blocks with five digits each, like
+    /// `51525-35455-41424-34445-31323-33435-21222-32425`
+    pub fn parse(dsk: &str) -> Result<Dsk> {
+        let blocks: Vec<&str> = dsk.trim().split('-').collect();
+        if blocks.len() != 8 {
+            return Err(invalid("The DSK needs to have eight blocks"));
+        }
+
+        Dsk::from_digits(&blocks.concat())
+    }
+
+    /// Parse the 40 digits of the DSK without separators
+    fn from_digits(digits: &str) -> Result<Dsk> {
+        if digits.len() != 40 {
+            return Err(invalid("The DSK needs to have 40 digits"));
+        }
+
+        let mut dsk = [0u8; 16];
+        for i in 0..8 {
+            let block = number(&digits[i * 5..i * 5 + 5])?;
+            if block > 0xFFFF {
+                return Err(invalid("A block of the DSK is larger than 65535"));
+            }
+
+            dsk[i * 2] = (block >> 8) as u8;
+            dsk[i * 2 + 1] = block as u8;
+        }
+
+        Ok(Dsk(dsk))
+    }
+
+    /// Return the PIN, the first block which needs to be entered by the
+    /// user to include the device with S2 Authenticated
+    pub fn pin(&self) -> u16 {
+        ((self.0[0] as u16) << 8) | self.0[1] as u16
+    }
+}
+
+impl std::fmt::Display for Dsk {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let blocks: Vec<String> = self
+            .0
+            .chunks(2)
+            .map(|b| format!("{:05}", ((b[0] as u16) << 8) | b[1] as u16))
+            .collect();
+
+        f.write_str(&blocks.join("-"))
+    }
+}
+
+/// The version of the QR code.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QrVersion {
+    /// The device is included with S2, after it was started manually
+    S2,
+    /// The device is included with SmartStart, as soon as it's powered on
+    SmartStart,
+}
+
+/// The device class and the icon of the device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProductType {
+    pub generic: u8,
+    pub specific: u8,
+    pub icon: u16,
+}
+
+/// The identifiers of the product.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProductId {
+    pub manufacturer: u16,
+    pub product_type: u16,
+    pub product_id: u16,
+    /// The major and minor version of the application
+    pub version: (u8, u8),
+}
+
+/// An entry of the SmartStart provisioning list.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProvisioningEntry {
+    pub version: QrVersion,
+    pub dsk: Dsk,
+    /// The security classes the device asks to be included with
+    pub requested_keys: Vec<SecurityClass>,
+    pub product_type: Option<ProductType>,
+    pub product_id: Option<ProductId>,
+    /// The interval between the inclusion requests of the device, in seconds
+    pub inclusion_interval: Option<u32>,
+}
+
+impl ProvisioningEntry {
+    /// Create an entry for a device, which was only given by its DSK.
+    pub fn new(dsk: Dsk, requested_keys: Vec<SecurityClass>) -> ProvisioningEntry {
+        ProvisioningEntry {
+            version: QrVersion::SmartStart,
+            dsk,
+            requested_keys,
+            product_type: None,
+            product_id: None,
+            inclusion_interval: None,
+        }
+    }
+
+    /// Parse the text of a Z-Wave QR code and check its checksum.
+    ///
+    /// Unknown information blocks are skipped, unless they are marked as
+    /// critical.
+    pub fn parse_qr(code: &str) -> Result<ProvisioningEntry> {
+        let code = code.trim();
+
+        if !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid("The QR code may only contain digits"));
+        }
+        if code.len() < HEADER_LENGTH || !code.starts_with(LEAD_IN) {
+            return Err(invalid("The text is not a Z-Wave QR code"));
+        }
+
+        let version = match number(&code[2..4])? {
+            0 => QrVersion::S2,
+            1 => QrVersion::SmartStart,
+            v => return Err(invalid(format!("The QR code version {} is unknown", v))),
+        };
+
+        // the first two bytes of the SHA-1 hash of everything after the checksum
+        let hash = sha1(&code.as_bytes()[9..]);
+        if number(&code[4..9])? != ((hash[0] as u32) << 8) | hash[1] as u32 {
+            return Err(invalid("The checksum of the QR code is wrong"));
+        }
+
+        let mut entry = ProvisioningEntry::new(
+            Dsk::from_digits(&code[12..52])?,
+            requested_keys(number(&code[9..12])?),
+        );
+        entry.version = version;
+
+        // the information blocks
+        let mut rest = &code[HEADER_LENGTH..];
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return Err(invalid("An information block of the QR code is too short"));
+            }
+
+            let typ = number(&rest[0..2])?;
+            let length = number(&rest[2..4])? as usize;
+            if rest.len() < 4 + length {
+                return Err(invalid("An information block of the QR code is too short"));
+            }
+            let value = &rest[4..4 + length];
+            rest = &rest[4 + length..];
+
+            // the lowest bit marks blocks which need to be understood
+            match (typ >> 1, value.len()) {
+                (0x00, 10) => {
+                    let class = number(&value[0..5])?;
+                    entry.product_type = Some(ProductType {
+                        generic: (class >> 8) as u8,
+                        specific: class as u8,
+                        icon: number(&value[5..10])? as u16,
+                    });
+                }
+                (0x01, 20) => {
+                    let version = number(&value[15..20])?;
+                    entry.product_id = Some(ProductId {
+                        manufacturer: number(&value[0..5])? as u16,
+                        product_type: number(&value[5..10])? as u16,
+                        product_id: number(&value[10..15])? as u16,
+                        version: ((version >> 8) as u8, version as u8),
+                    });
+                }
+                // counted in steps of 128 seconds
+                (0x02, 3) => entry.inclusion_interval = Some(number(value)? * 128),
+                _ if typ & 0x01 == 0x01 => {
+                    return Err(Error::new(
+                        ErrorKind::NotImplemented,
+                        format!("The critical information block {} is unknown", typ >> 1),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(entry)
+    }
+}
+
+/// Convert the bitmask of the requested keys into the security classes
+fn requested_keys(mask: u32) -> Vec<SecurityClass> {
+    let mut keys = vec![];
+
+    if mask & 0x01 != 0 {
+        keys.push(SecurityClass::S2Unauthenticated);
+    }
+    if mask & 0x02 != 0 {
+        keys.push(SecurityClass::S2Authenticated);
+    }
+    if mask & 0x04 != 0 {
+        keys.push(SecurityClass::S2AccessControl);
+    }
+    if mask & 0x80 != 0 {
+        keys.push(SecurityClass::S0);
+    }
+
+    keys
+}
+
+/// Parse a decimal number out of the digits
+fn number(digits: &str) -> Result<u32> {
+    digits
+        .parse()
+        .map_err(|_| invalid(format!("'{}' is not a number", digits)))
+}
+
+/// The error for an invalid code
+fn invalid<S: Into<String>>(reason: S) -> Error {
+    Error::new(ErrorKind::InvalidInput, reason.into())
+}
+
+/// The SHA-1 hash of the data
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // pad the data with a one bit, zeros and the length in bits
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0x00);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                chunk[i * 4],
+                chunk[i * 4 + 1],
+                chunk[i * 4 + 2],
+                chunk[i * 4 + 3],
+            ]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    }
+
+    let mut hash = [0u8; 20];
+    for (i, word) in h.iter().enumerate() {
+        hash[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QR: &str = "9001090290035152535455414243444531323334352122232425001004097017920220001340000300096002580403010960242";
+
+    #[test]
+    fn test_sha1() {
+        assert_eq!(sha1(b"abc")[..4], [0xA9, 0x99, 0x3E, 0x36]);
+        assert_eq!(sha1(&[0x61; 100])[..2], [0x7F, 0x90]);
+    }
+
+    #[test]
+    fn test_parse_dsk() {
+        let dsk = Dsk::parse("51525-35455-41424-34445-31323-33435-21222-32425").unwrap();
+        assert_eq!(dsk.pin(), 51525);
+        assert_eq!(
+            dsk.to_string(),
+            "51525-35455-41424-34445-31323-33435-21222-32425"
+        );
+
+        assert!(Dsk::parse("51525-35455").is_err());
+        assert!(Dsk::parse("71525-35455-41424-34445-31323-33435-21222-32425").is_err());
+    }
+
+    #[test]
+    fn test_parse_qr() {
+        let entry = ProvisioningEntry::parse_qr(QR).unwrap();
+
+        assert_eq!(entry.version, QrVersion::SmartStart);
+        assert_eq!(entry.dsk.pin(), 51525);
+        assert_eq!(
+            entry.requested_keys,
+            vec![
+                SecurityClass::S2Unauthenticated,
+                SecurityClass::S2Authenticated
+            ]
+        );
+        assert_eq!(
+            entry.product_type,
+            Some(ProductType {
+                generic: 0x10,
+                specific: 0x01,
+                icon: 0x0700,
+            })
+        );
+        assert_eq!(
+            entry.product_id,
+            Some(ProductId {
+                manufacturer: 0x0086,
+                product_type: 0x0003,
+                product_id: 0x0060,
+                version: (1, 2),
+            })
+        );
+        assert_eq!(entry.inclusion_interval, Some(1280));
+
+        // a changed digit breaks the checksum
+        let broken = QR.replacen("51525", "51526", 1);
+        assert!(ProvisioningEntry::parse_qr(&broken).is_err());
+        assert!(ProvisioningEntry::parse_qr("9001").is_err());
+    }
+}